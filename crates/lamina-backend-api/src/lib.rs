@@ -0,0 +1,125 @@
+// Interface between lx and the compiler backends
+//
+// A backend compiles the program in one source file to files: the EVM
+// backend to Huff source, an ABI and a Foundry artifact. `lx build --target
+// <name>` finds the backend by name in a `Registry`, compiles each source file
+// with it and writes the files it returns under `--out-dir`; `lx lint
+// --target <name>` prints its diagnostics. Backends don't touch the file
+// system themselves, so they can be driven by other tools too.
+//
+// A backend lives in its own crate, depending only on `lamina` and this
+// crate, and implements `Backend`. lx registers each backend it is built with
+// behind a Cargo feature named after the crate.
+
+use lamina::diagnostics::Diagnostic;
+use lamina::error::Error;
+use lamina::value::Value;
+
+/// Version of this interface, raised whenever `Backend` changes incompatibly
+pub const API_VERSION: u32 = 1;
+
+/// The program in one source file
+#[derive(Clone, Debug)]
+pub struct Program {
+    /// Named after the file
+    pub name: String,
+    /// The top-level forms, in a single `begin`
+    pub expr: Value,
+    /// The text of the file, for backends that point into it
+    pub source: Option<String>,
+}
+
+/// How a program is compiled
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Fail compilation when any warning is raised
+    pub deny_warnings: bool,
+    /// Leave out `assert` and the checks of `define-with-contract`
+    pub strip_assertions: bool,
+    /// Most bytes of code the program may compile to, the backend's
+    /// `size_limit` when unset
+    pub size_budget: Option<usize>,
+    /// The hardfork EVM backends target, by name, the latest when unset
+    pub hardfork: Option<String>,
+}
+
+/// A file a backend produced, to be written under the output directory
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputFile {
+    /// File name, relative to the output directory
+    pub name: String,
+    pub contents: Vec<u8>,
+}
+
+/// Bytes of code one function accounts for
+#[derive(Clone, Debug, PartialEq)]
+pub struct FunctionSize {
+    pub name: String,
+    pub bytes: usize,
+}
+
+/// What compiling a program produced
+#[derive(Clone, Debug, Default)]
+pub struct Artifacts {
+    pub files: Vec<OutputFile>,
+    /// Warnings raised while compiling, minus the ones the program allows
+    pub warnings: Vec<Diagnostic>,
+    /// Functions left out because nothing reaches them
+    pub removed: Vec<String>,
+    /// Size of the code, and the bytes of each function, largest first, for
+    /// targets where size is limited
+    pub code_size: Option<usize>,
+    pub sizes: Vec<FunctionSize>,
+}
+
+/// A compiler from Lamina programs to a target
+pub trait Backend: Send + Sync {
+    /// The name `--target` selects the backend by
+    fn name(&self) -> &str;
+
+    /// Other names the backend answers to
+    fn aliases(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Extension of the main file compiling `<Name>` produces, `<Name>.<extension>`
+    fn extension(&self) -> &str;
+
+    /// Largest code the target accepts, in bytes, if it has a limit
+    fn size_limit(&self) -> Option<usize> {
+        None
+    }
+
+    fn compile(&self, program: &Program, options: &Options) -> Result<Artifacts, Error>;
+
+    /// The warnings compiling `program` raises, without producing any files
+    fn diagnostics(&self, program: &Program) -> Result<Vec<Diagnostic>, Error>;
+}
+
+/// The backends a tool can compile with
+#[derive(Default)]
+pub struct Registry {
+    backends: Vec<Box<dyn Backend>>,
+}
+
+impl Registry {
+    /// The registry with `backend` added; a backend registered earlier keeps
+    /// the names it shares with it
+    pub fn with(mut self, backend: Box<dyn Backend>) -> Self {
+        self.backends.push(backend);
+        self
+    }
+
+    /// The backend called `name`, by its name or an alias
+    pub fn find(&self, name: &str) -> Option<&dyn Backend> {
+        self.backends
+            .iter()
+            .find(|backend| backend.name() == name || backend.aliases().contains(&name))
+            .map(|backend| backend.as_ref())
+    }
+
+    /// The names of the backends, in the order they were registered
+    pub fn names(&self) -> Vec<&str> {
+        self.backends.iter().map(|backend| backend.name()).collect()
+    }
+}
@@ -0,0 +1,79 @@
+// The Huff compiler as an lx backend
+//
+// Compiling `<Name>` produces the files `compile_and_save` writes: the Huff
+// source `<Name>.huff`, the ABI `<Name>.abi.json`, the symbol table
+// `<Name>.symbols.json` and the Foundry artifact `<Name>.json`, the main one.
+
+use lamina::diagnostics::Diagnostic;
+use lamina::error::Error;
+use lamina::evm::Hardfork;
+use lamina_backend_api::{Artifacts, Backend, FunctionSize, Options, OutputFile, Program};
+
+use crate::huff::{self, CompileOptions, MAX_CODE_SIZE};
+
+/// The `evm` target, also called `huff`
+pub struct HuffBackend;
+
+impl Backend for HuffBackend {
+    fn name(&self) -> &str {
+        "evm"
+    }
+
+    fn aliases(&self) -> &[&str] {
+        &["huff"]
+    }
+
+    fn extension(&self) -> &str {
+        "json"
+    }
+
+    fn size_limit(&self) -> Option<usize> {
+        Some(MAX_CODE_SIZE)
+    }
+
+    fn compile(&self, program: &Program, options: &Options) -> Result<Artifacts, Error> {
+        let hardfork = match &options.hardfork {
+            Some(name) => Hardfork::from_name(name)
+                .ok_or_else(|| Error::Compilation(format!("unknown hardfork {}", name)))?,
+            None => Hardfork::default(),
+        };
+        let compile_options = CompileOptions {
+            deny_warnings: options.deny_warnings,
+            strip_assertions: options.strip_assertions,
+            size_budget: options.size_budget,
+            hardfork,
+            source: program.source.clone(),
+        };
+        let artifact =
+            huff::compile_artifact_with_options(&program.expr, &program.name, &compile_options)?;
+        let huff_code = huff::compile_with_options(&program.expr, &program.name, &compile_options)?;
+
+        let file = |extension: &str, contents: String| OutputFile {
+            name: format!("{}.{}", program.name, extension),
+            contents: contents.into_bytes(),
+        };
+        Ok(Artifacts {
+            files: vec![
+                file("huff", huff_code),
+                file("abi.json", artifact.abi_json()),
+                file("symbols.json", artifact.symbols_json()),
+                file("json", artifact.to_json()),
+            ],
+            code_size: Some(artifact.deployed_bytecode.len()),
+            sizes: artifact
+                .sizes
+                .into_iter()
+                .map(|size| FunctionSize {
+                    name: size.name,
+                    bytes: size.bytes,
+                })
+                .collect(),
+            warnings: artifact.warnings,
+            removed: artifact.removed,
+        })
+    }
+
+    fn diagnostics(&self, program: &Program) -> Result<Vec<Diagnostic>, Error> {
+        huff::warnings(&program.expr, &program.name)
+    }
+}
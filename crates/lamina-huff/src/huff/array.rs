@@ -0,0 +1,235 @@
+// Lowering of memory and storage arrays
+//
+// Both kinds of array are length-prefixed, following the Solidity layout:
+//
+//   memory:  ptr -> length, ptr + 32 * (i + 1) -> element i
+//   storage: slot -> length, keccak256(slot) + i -> element i
+//
+// A memory array is allocated once by `make-array` with a size known at compile
+// time, and is zeroed since the allocator only hands out fresh memory. A
+// storage array is named by the slot holding its length and grows with
+// `storage-array-push!`. Every index is bounds checked against the length, and
+// an out-of-bounds access reverts.
+//
+// `array-set!` leaves the array on the stack, so updates can be chained inside
+// a single expression, as in `(array-ref (array-set! (make-array 2) 0 x) 0)`.
+
+use lamina::value::{NumberKind, Value};
+
+use super::bytecode::Instruction;
+use super::expression::{emit, minimal_bytes, push_bytes, Scope};
+use super::memory;
+use super::opcodes::Opcode;
+
+/// Primitives compiled by [`emit_array_op`]
+pub const ARRAY_PRIMITIVES: [&str; 8] = [
+    "make-array",
+    "array-length",
+    "array-ref",
+    "array-set!",
+    "storage-array-length",
+    "storage-array-ref",
+    "storage-array-set!",
+    "storage-array-push!",
+];
+
+/// Compile a call to one of the [`ARRAY_PRIMITIVES`]
+pub(crate) fn emit_array_op(
+    op: &str,
+    args: &[&Value],
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    match (op, args) {
+        ("make-array", [size]) => emit_make_array(size, out),
+        ("array-length", [array]) => {
+            emit(array, scope, out)?;
+            out.push(Instruction::Simple(Opcode::MLOAD));
+            Some(())
+        }
+        ("array-ref", [array, index]) => emit_array_ref(array, index, scope, out),
+        ("array-set!", [array, index, value]) => emit_array_set(array, index, value, scope, out),
+        ("storage-array-length", [slot]) => {
+            emit(slot, scope, out)?;
+            out.push(Instruction::Simple(Opcode::SLOAD));
+            Some(())
+        }
+        ("storage-array-ref", [slot, index]) => emit_storage_ref(slot, index, scope, out),
+        ("storage-array-set!", [slot, index, value]) => {
+            emit_storage_set(slot, index, value, scope, out)
+        }
+        ("storage-array-push!", [slot, value]) => emit_storage_push(slot, value, scope, out),
+        _ => None,
+    }
+}
+
+// Only literal sizes compile, since the allocation is fixed
+fn emit_make_array(size: &Value, out: &mut Vec<Instruction>) -> Option<()> {
+    let size = match size {
+        Value::Number(NumberKind::Integer(size)) if *size >= 0 => *size as u64,
+        _ => return None,
+    };
+
+    out.extend(memory::alloc(32 * (size + 1)));
+    // [ptr] -> mstore(ptr, size), leaving ptr
+    out.push(push_bytes(minimal_bytes(size)));
+    out.push(Instruction::Simple(Opcode::DUP2));
+    out.push(Instruction::Simple(Opcode::MSTORE));
+    Some(())
+}
+
+fn emit_array_ref(
+    array: &Value,
+    index: &Value,
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    emit(index, scope, out)?;
+    emit(array, scope, out)?;
+
+    // [ptr, index]
+    out.push(Instruction::Simple(Opcode::DUP1));
+    out.push(Instruction::Simple(Opcode::MLOAD));
+    emit_bounds_check(scope, out);
+    element_address(out);
+    out.push(Instruction::Simple(Opcode::MLOAD));
+    Some(())
+}
+
+fn emit_array_set(
+    array: &Value,
+    index: &Value,
+    value: &Value,
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    emit(value, scope, out)?;
+    emit(index, scope, out)?;
+    emit(array, scope, out)?;
+
+    // [ptr, index, value]
+    out.push(Instruction::Simple(Opcode::DUP1));
+    out.push(Instruction::Simple(Opcode::MLOAD));
+    emit_bounds_check(scope, out);
+    out.push(Instruction::Simple(Opcode::DUP1));
+    out.push(Instruction::Simple(Opcode::SWAP2));
+    out.push(Instruction::Simple(Opcode::SWAP1));
+    // [ptr, index, ptr, value] -> [ptr]
+    element_address(out);
+    out.push(Instruction::Simple(Opcode::SWAP2));
+    out.push(Instruction::Simple(Opcode::SWAP1));
+    out.push(Instruction::Simple(Opcode::SWAP2));
+    out.push(Instruction::Simple(Opcode::MSTORE));
+    Some(())
+}
+
+// [ptr, index] -> [ptr + 32 * (index + 1)]
+fn element_address(out: &mut Vec<Instruction>) {
+    out.push(Instruction::Simple(Opcode::SWAP1));
+    out.push(push_bytes(vec![1]));
+    out.push(Instruction::Simple(Opcode::ADD));
+    out.push(push_bytes(vec![5]));
+    out.push(Instruction::Simple(Opcode::SHL));
+    out.push(Instruction::Simple(Opcode::ADD));
+}
+
+fn emit_storage_ref(
+    slot: &Value,
+    index: &Value,
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    emit(index, scope, out)?;
+    emit(slot, scope, out)?;
+
+    // [slot, index]
+    out.push(Instruction::Simple(Opcode::DUP1));
+    out.push(Instruction::Simple(Opcode::SLOAD));
+    emit_bounds_check(scope, out);
+    emit_data_slot(out);
+    out.push(Instruction::Simple(Opcode::ADD));
+    out.push(Instruction::Simple(Opcode::SLOAD));
+    Some(())
+}
+
+// Leaves the value written
+fn emit_storage_set(
+    slot: &Value,
+    index: &Value,
+    value: &Value,
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    emit(value, scope, out)?;
+    emit(index, scope, out)?;
+    emit(slot, scope, out)?;
+
+    // [slot, index, value]
+    out.push(Instruction::Simple(Opcode::DUP1));
+    out.push(Instruction::Simple(Opcode::SLOAD));
+    emit_bounds_check(scope, out);
+    emit_data_slot(out);
+    out.push(Instruction::Simple(Opcode::ADD));
+    // [element, value] -> sstore(element, value), leaving value
+    out.push(Instruction::Simple(Opcode::DUP2));
+    out.push(Instruction::Simple(Opcode::SWAP1));
+    out.push(Instruction::Simple(Opcode::SSTORE));
+    Some(())
+}
+
+// Leaves the new length
+fn emit_storage_push(
+    slot: &Value,
+    value: &Value,
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    emit(value, scope, out)?;
+    emit(slot, scope, out)?;
+
+    // [slot, value] -> [element, length, slot, value]
+    out.push(Instruction::Simple(Opcode::DUP1));
+    out.push(Instruction::Simple(Opcode::SLOAD));
+    out.push(Instruction::Simple(Opcode::DUP2));
+    emit_data_slot(out);
+    out.push(Instruction::Simple(Opcode::DUP2));
+    out.push(Instruction::Simple(Opcode::ADD));
+
+    // sstore(element, value), leaving [slot, length]
+    out.push(Instruction::Simple(Opcode::SWAP3));
+    out.push(Instruction::Simple(Opcode::SWAP1));
+    out.push(Instruction::Simple(Opcode::SWAP3));
+    out.push(Instruction::Simple(Opcode::SSTORE));
+
+    // sstore(slot, length + 1), leaving length + 1
+    out.push(Instruction::Simple(Opcode::SWAP1));
+    out.push(push_bytes(vec![1]));
+    out.push(Instruction::Simple(Opcode::ADD));
+    out.push(Instruction::Simple(Opcode::DUP1));
+    out.push(Instruction::Simple(Opcode::SWAP2));
+    out.push(Instruction::Simple(Opcode::SSTORE));
+    Some(())
+}
+
+// [slot, ...] -> [keccak256(slot), ...], hashing in a fresh allocation
+pub(super) fn emit_data_slot(out: &mut Vec<Instruction>) {
+    out.extend(memory::alloc(32));
+    out.push(Instruction::Simple(Opcode::SWAP1));
+    out.push(Instruction::Simple(Opcode::DUP2));
+    out.push(Instruction::Simple(Opcode::MSTORE));
+    out.push(push_bytes(vec![0x20]));
+    out.push(Instruction::Simple(Opcode::SWAP1));
+    out.push(Instruction::Simple(Opcode::SHA3));
+}
+
+// [length, array, index]: reverts unless index < length, leaving [array, index]
+fn emit_bounds_check(scope: &Scope, out: &mut Vec<Instruction>) {
+    let ok = scope.label("index_ok");
+    out.push(Instruction::Simple(Opcode::DUP3));
+    out.push(Instruction::Simple(Opcode::LT));
+    out.push(Instruction::JumpToIf(ok.clone()));
+    out.push(push_bytes(vec![0]));
+    out.push(Instruction::Simple(Opcode::DUP1));
+    out.push(Instruction::Simple(Opcode::REVERT));
+    out.push(Instruction::Label(ok));
+}
@@ -0,0 +1,251 @@
+use std::fmt::Write;
+
+use lamina::diagnostics::Diagnostic;
+use lamina::encoding::encode_hex;
+
+use super::assembler::MacroSize;
+use super::bytecode::{huff_macro_name, macro_to_function_name, FunctionSignature, HuffContract};
+
+/// ABI description of a single contract function
+#[derive(Debug, Clone, PartialEq)]
+pub struct AbiFunction {
+    /// Solidity-style (camelCase) function name
+    pub name: String,
+    /// Parameter names; every parameter is a uint256 for now
+    pub inputs: Vec<String>,
+    /// Return types, `uint256` or `string`
+    pub outputs: Vec<String>,
+    /// `pure`, `view`, `nonpayable` or `payable`
+    pub state_mutability: &'static str,
+    /// Canonical signature, e.g. `setValue(uint256)`
+    pub signature: String,
+    pub selector: u32,
+    /// The docstring of the definition, kept as its NatSpec notice
+    pub notice: Option<String>,
+}
+
+/// A function in the compiled code and the Lamina definition it was compiled
+/// from, for tools that show generated names to people
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    /// Name the Lamina source defines, e.g. `set-value`
+    pub lamina: String,
+    /// Huff macro holding the function's code, e.g. `SET_VALUE_MACRO`
+    pub huff_macro: String,
+    /// ABI function name, e.g. `setValue`
+    pub function: String,
+    pub selector: u32,
+}
+
+/// A compiled contract in the layout of a Foundry `out/<Name>.sol/<Name>.json` artifact
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    pub contract_name: String,
+    pub abi: Vec<AbiFunction>,
+    /// Creation bytecode, including the constructor
+    pub bytecode: Vec<u8>,
+    /// Runtime bytecode
+    pub deployed_bytecode: Vec<u8>,
+    /// Warnings raised while compiling the contract
+    pub warnings: Vec<Diagnostic>,
+    /// Functions left out of the bytecode because no public function reaches them
+    pub removed: Vec<String>,
+    /// Runtime bytes of the dispatcher and of each function, largest first
+    pub sizes: Vec<MacroSize>,
+    /// The functions in the runtime bytecode, in definition order
+    pub symbols: Vec<Symbol>,
+}
+
+impl Artifact {
+    /// Build the artifact for an assembled contract
+    pub fn new(contract: &HuffContract, bytecode: Vec<u8>, deployed_bytecode: Vec<u8>) -> Self {
+        let mut abi: Vec<AbiFunction> = Vec::new();
+        let mut symbols: Vec<Symbol> = Vec::new();
+
+        for function in &contract.functions {
+            let name = macro_to_function_name(&function.name);
+            if name.to_lowercase() == "main" || abi.iter().any(|f| f.name == name) {
+                continue;
+            }
+
+            abi.push(AbiFunction {
+                signature: signature(&name, function),
+                name,
+                inputs: function.params.clone(),
+                outputs: function.returns.clone(),
+                state_mutability: function.state_mutability.as_str(),
+                selector: function.selector,
+                notice: function.doc.clone(),
+            });
+
+            // Functions inlined or left out have no macro of their own
+            let huff_macro = huff_macro_name(&function.name);
+            if contract
+                .macros
+                .iter()
+                .any(|m| huff_macro_name(&m.name) == huff_macro)
+            {
+                symbols.push(Symbol {
+                    lamina: function.name.clone(),
+                    huff_macro,
+                    function: macro_to_function_name(&function.name),
+                    selector: function.selector,
+                });
+            }
+        }
+
+        Artifact {
+            contract_name: contract.name.clone(),
+            abi,
+            bytecode,
+            deployed_bytecode,
+            warnings: Vec::new(),
+            removed: Vec::new(),
+            sizes: Vec::new(),
+            symbols,
+        }
+    }
+
+    /// Selector table: canonical signature to selector hex, sorted by signature
+    pub fn method_identifiers(&self) -> Vec<(String, String)> {
+        let mut identifiers: Vec<(String, String)> = self
+            .abi
+            .iter()
+            .map(|f| (f.signature.clone(), format!("{:08x}", f.selector)))
+            .collect();
+        identifiers.sort();
+        identifiers
+    }
+
+    /// Render just the ABI as a JSON array
+    pub fn abi_json(&self) -> String {
+        let entries: Vec<String> = self.abi.iter().map(abi_entry_json).collect();
+        format!("[{}]", entries.join(","))
+    }
+
+    /// Render the symbol table as a JSON array, each function with its Lamina
+    /// name, Huff macro, ABI name and selector
+    pub fn symbols_json(&self) -> String {
+        let entries: Vec<String> = self
+            .symbols
+            .iter()
+            .map(|symbol| {
+                format!(
+                    "{{\"lamina\":{},\"macro\":{},\"function\":{},\"selector\":{}}}",
+                    json_string(&symbol.lamina),
+                    json_string(&symbol.huff_macro),
+                    json_string(&symbol.function),
+                    json_string(&format!("{:08x}", symbol.selector))
+                )
+            })
+            .collect();
+        format!("[{}]", entries.join(","))
+    }
+
+    /// Render the full artifact as JSON
+    pub fn to_json(&self) -> String {
+        let method_identifiers: Vec<String> = self
+            .method_identifiers()
+            .iter()
+            .map(|(signature, selector)| {
+                format!("{}:{}", json_string(signature), json_string(selector))
+            })
+            .collect();
+
+        format!(
+            "{{\"abi\":{},\"bytecode\":{},\"deployedBytecode\":{},\"methodIdentifiers\":{{{}}},\"userdoc\":{}}}",
+            self.abi_json(),
+            bytecode_json(&self.bytecode),
+            bytecode_json(&self.deployed_bytecode),
+            method_identifiers.join(","),
+            self.userdoc_json()
+        )
+    }
+
+    /// Render the docstrings as solc's NatSpec `userdoc`: the notice of each
+    /// documented function, by canonical signature
+    pub fn userdoc_json(&self) -> String {
+        let methods: Vec<String> = self
+            .abi
+            .iter()
+            .filter_map(|f| {
+                let notice = f.notice.as_ref()?;
+                Some(format!(
+                    "{}:{{\"notice\":{}}}",
+                    json_string(&f.signature),
+                    json_string(notice)
+                ))
+            })
+            .collect();
+        format!(
+            "{{\"kind\":\"user\",\"methods\":{{{}}},\"version\":1}}",
+            methods.join(",")
+        )
+    }
+}
+
+fn abi_entry_json(function: &AbiFunction) -> String {
+    let inputs: Vec<String> = function
+        .inputs
+        .iter()
+        .map(|name| param_json(name, "uint256"))
+        .collect();
+    let outputs: Vec<String> = function
+        .outputs
+        .iter()
+        .map(|ty| param_json("", ty))
+        .collect();
+
+    format!(
+        "{{\"type\":\"function\",\"name\":{},\"inputs\":[{}],\"outputs\":[{}],\"stateMutability\":{}}}",
+        json_string(&function.name),
+        inputs.join(","),
+        outputs.join(","),
+        json_string(function.state_mutability)
+    )
+}
+
+fn param_json(name: &str, ty: &str) -> String {
+    format!(
+        "{{\"name\":{},\"type\":{},\"internalType\":{}}}",
+        json_string(name),
+        json_string(ty),
+        json_string(ty)
+    )
+}
+
+fn bytecode_json(code: &[u8]) -> String {
+    format!(
+        "{{\"object\":\"{}\",\"sourceMap\":\"\",\"linkReferences\":{{}}}}",
+        encode_hex(code)
+    )
+}
+
+/// Quote and escape a string for JSON
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn signature(name: &str, function: &FunctionSignature) -> String {
+    format!(
+        "{}({})",
+        name,
+        vec!["uint256"; function.params.len()].join(",")
+    )
+}
@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+
+use lamina::error::Error;
+
+use super::bytecode::{HuffContract, HuffMacro, Instruction};
+use super::opcodes::Opcode;
+
+/// Maximum macro nesting depth before assembly gives up on a recursive macro
+const MAX_MACRO_DEPTH: usize = 32;
+
+/// Largest runtime code a contract can deploy, from EIP-170
+pub const MAX_CODE_SIZE: usize = 24576;
+
+/// Runtime bytes one macro accounts for, with everything it expands
+#[derive(Debug, Clone, PartialEq)]
+pub struct MacroSize {
+    pub name: String,
+    pub bytes: usize,
+}
+
+/// An assembled item; its size is fixed before label offsets are resolved
+enum Item {
+    Bytes(Vec<u8>),
+    /// JUMPDEST for a label
+    Label(String),
+    /// PUSH2 of a label offset
+    LabelRef(String),
+}
+
+/// Assembles the runtime bytecode of a contract, the same code `huffc -r` would emit.
+///
+/// # Arguments
+///
+/// * `contract` - The contract to assemble
+/// * `constants` - Values of the constants referenced by the macros, e.g. `COUNTER_SLOT`
+///
+/// # Returns
+///
+/// The runtime (deployed) bytecode
+pub fn assemble_runtime(
+    contract: &HuffContract,
+    constants: &HashMap<String, Vec<u8>>,
+) -> Result<Vec<u8>, Error> {
+    let mut items = selector_prologue()?;
+    expand_macro(&contract.main, contract, constants, &mut items, 0)?;
+
+    // First pass: every item has a fixed size, so label offsets are known up front
+    let mut labels = HashMap::new();
+    let mut offset = 0usize;
+    for item in &items {
+        if let Item::Label(name) = item {
+            if labels.insert(name.clone(), offset).is_some() {
+                return Err(Error::Compilation(format!("Duplicate label: {}", name)));
+            }
+        }
+        offset += item.size();
+    }
+
+    if offset > u16::MAX as usize {
+        return Err(Error::Compilation(
+            "Contract too large for 2-byte jump offsets".to_string(),
+        ));
+    }
+
+    // Second pass: emit bytes with resolved label offsets
+    let mut code = Vec::with_capacity(offset);
+    for item in items {
+        match item {
+            Item::Bytes(bytes) => code.extend(bytes),
+            Item::Label(_) => code.push(byte(&Opcode::JUMPDEST)?),
+            Item::LabelRef(name) => {
+                let target = labels
+                    .get(&name)
+                    .ok_or_else(|| Error::Compilation(format!("Undefined label: {}", name)))?;
+                code.push(byte(&Opcode::PUSH2)?);
+                code.extend((*target as u16).to_be_bytes());
+            }
+        }
+    }
+
+    Ok(code)
+}
+
+/// How the runtime code of a contract divides between the dispatcher, as
+/// `MAIN`, and the macros it expands, largest first.
+///
+/// # Arguments
+///
+/// * `contract` - The contract to measure
+/// * `constants` - Values of the constants referenced by the macros
+///
+/// # Returns
+///
+/// The size of each macro, adding up to the size of the runtime code
+pub fn macro_sizes(
+    contract: &HuffContract,
+    constants: &HashMap<String, Vec<u8>>,
+) -> Result<Vec<MacroSize>, Error> {
+    let mut sizes: Vec<MacroSize> = Vec::new();
+    let mut main = items_size(&selector_prologue()?);
+
+    for instruction in &contract.main.instructions {
+        let name = match instruction {
+            Instruction::MacroCall(name) if !name.ends_with("_SLOT") => name,
+            other => {
+                let mut items = Vec::new();
+                expand_instruction(other, contract, constants, &mut items, 0)?;
+                main += items_size(&items);
+                continue;
+            }
+        };
+        let callee = find_macro(contract, name)
+            .ok_or_else(|| Error::Compilation(format!("Undefined macro: {}", name)))?;
+        let mut items = Vec::new();
+        expand_macro(callee, contract, constants, &mut items, 1)?;
+        let bytes = items_size(&items);
+        match sizes.iter_mut().find(|size| size.name == callee.name) {
+            Some(size) => size.bytes += bytes,
+            None => sizes.push(MacroSize {
+                name: callee.name.clone(),
+                bytes,
+            }),
+        }
+    }
+
+    sizes.push(MacroSize {
+        name: "MAIN".to_string(),
+        bytes: main,
+    });
+    sizes.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+    Ok(sizes)
+}
+
+/// MAIN(): 0x00 calldataload 0xe0 shr, loading the function selector
+fn selector_prologue() -> Result<Vec<Item>, Error> {
+    Ok(vec![
+        Item::Bytes(push_bytes(&[0x00])),
+        Item::Bytes(vec![byte(&Opcode::CALLDATALOAD)?]),
+        Item::Bytes(push_bytes(&[0xe0])),
+        Item::Bytes(vec![byte(&Opcode::SHR)?]),
+    ])
+}
+
+impl Item {
+    fn size(&self) -> usize {
+        match self {
+            Item::Bytes(bytes) => bytes.len(),
+            Item::Label(_) => 1,
+            Item::LabelRef(_) => 3,
+        }
+    }
+}
+
+fn items_size(items: &[Item]) -> usize {
+    items.iter().map(Item::size).sum()
+}
+
+/// Runtime bytes one instruction assembles to, with any macro it expands
+pub(super) fn instruction_size(
+    instruction: &Instruction,
+    contract: &HuffContract,
+    constants: &HashMap<String, Vec<u8>>,
+) -> Result<usize, Error> {
+    let mut items = Vec::new();
+    expand_instruction(instruction, contract, constants, &mut items, 0)?;
+    Ok(items_size(&items))
+}
+
+/// Wraps runtime bytecode in the standard constructor that copies it into place and returns it
+pub fn creation_code(runtime: &[u8]) -> Vec<u8> {
+    let size = (runtime.len() as u16).to_be_bytes();
+
+    // PUSH2 size DUP1 PUSH2 offset PUSH0 CODECOPY PUSH0 RETURN
+    let mut code = vec![
+        0x61, size[0], size[1], 0x80, 0x61, 0x00, 0x00, 0x5f, 0x39, 0x5f, 0xf3,
+    ];
+    let offset = (code.len() as u16).to_be_bytes();
+    code[5] = offset[0];
+    code[6] = offset[1];
+
+    code.extend_from_slice(runtime);
+    code
+}
+
+/// Inline a macro's instructions into `items`
+fn expand_macro(
+    mac: &HuffMacro,
+    contract: &HuffContract,
+    constants: &HashMap<String, Vec<u8>>,
+    items: &mut Vec<Item>,
+    depth: usize,
+) -> Result<(), Error> {
+    if depth > MAX_MACRO_DEPTH {
+        return Err(Error::Compilation(format!(
+            "Macro expansion too deep in {}",
+            mac.name
+        )));
+    }
+
+    for instruction in &mac.instructions {
+        expand_instruction(instruction, contract, constants, items, depth)?;
+    }
+
+    Ok(())
+}
+
+fn expand_instruction(
+    instruction: &Instruction,
+    contract: &HuffContract,
+    constants: &HashMap<String, Vec<u8>>,
+    items: &mut Vec<Item>,
+    depth: usize,
+) -> Result<(), Error> {
+    match instruction {
+        Instruction::Simple(Opcode::CONSTANT(name)) => {
+            items.push(Item::Bytes(push_constant(name, constants)?))
+        }
+        Instruction::Simple(op) => items.push(Item::Bytes(vec![byte(op)?])),
+        Instruction::Push(_size, bytes) => items.push(Item::Bytes(push_bytes(bytes))),
+        Instruction::Label(label) => items.push(Item::Label(label.clone())),
+        Instruction::JumpTo(label) => {
+            items.push(Item::LabelRef(label.clone()));
+            items.push(Item::Bytes(vec![byte(&Opcode::JUMP)?]));
+        }
+        Instruction::JumpToIf(label) => {
+            items.push(Item::LabelRef(label.clone()));
+            items.push(Item::Bytes(vec![byte(&Opcode::JUMPI)?]));
+        }
+        Instruction::JumpLabel(label) => items.push(Item::LabelRef(label.clone())),
+        Instruction::MacroCall(name) if name.ends_with("_SLOT") => {
+            items.push(Item::Bytes(push_constant(name, constants)?))
+        }
+        Instruction::MacroCall(name) => {
+            let callee = find_macro(contract, name)
+                .ok_or_else(|| Error::Compilation(format!("Undefined macro: {}", name)))?;
+            expand_macro(callee, contract, constants, items, depth + 1)?;
+        }
+        Instruction::Comment(_) => {}
+    }
+
+    Ok(())
+}
+
+/// Look up a macro by the name it is written under in Huff
+fn find_macro<'a>(contract: &'a HuffContract, name: &str) -> Option<&'a HuffMacro> {
+    let huff_name = huff_macro_name(name);
+    contract
+        .macros
+        .iter()
+        .chain(contract.constructor.iter())
+        .find(|mac| huff_macro_name(&mac.name) == huff_name)
+}
+
+fn huff_macro_name(name: &str) -> String {
+    name.to_uppercase().replace('-', "_")
+}
+
+fn push_constant(name: &str, constants: &HashMap<String, Vec<u8>>) -> Result<Vec<u8>, Error> {
+    let value = constants
+        .get(name)
+        .ok_or_else(|| Error::Compilation(format!("Undefined constant: {}", name)))?;
+    Ok(push_bytes(value))
+}
+
+/// Encode a literal push with the smallest PUSHn that fits it, as huffc does
+fn push_bytes(bytes: &[u8]) -> Vec<u8> {
+    let first = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    let mut value = bytes[first..].to_vec();
+    if value.is_empty() {
+        value.push(0);
+    }
+
+    let mut code = vec![0x5f + value.len() as u8];
+    code.extend(value);
+    code
+}
+
+fn byte(op: &Opcode) -> Result<u8, Error> {
+    op.to_byte()
+        .ok_or_else(|| Error::Compilation(format!("Opcode has no encoding: {:?}", op)))
+}
@@ -0,0 +1,148 @@
+// Lowering of assertions and function contracts
+//
+// A check evaluates its test and jumps over a revert when it holds. A check
+// with a message reverts with it ABI-encoded as `Error(string)`, as
+// Solidity's `require(test, message)` does, so callers and tools decode it
+// the same way:
+//
+//   selector 0x08c379a0, offset 0x20, length, message padded to whole words
+//
+// A function defined with `define-with-contract` runs its `#:requires` checks,
+// then its body, then its `#:ensures` checks in a frame holding its arguments
+// and the body's value as `result`, like an inlined internal function. The
+// body is compiled knowing the `#:requires` tests hold, which can drop its
+// overflow checks.
+// `(assert ...)` evaluates to 0. With `CompileOptions::strip_assertions` no
+// check is compiled at all.
+
+use lamina::contracts::{Condition, Contract, RESULT};
+use lamina::value::Value;
+
+use super::bytecode::Instruction;
+use super::expression::{emit, emit_inlined, list_items, minimal_bytes, push_bytes, Scope};
+use super::memory;
+use super::opcodes::Opcode;
+use super::ranges::assume;
+use super::strings::trim_leading_zeros;
+
+/// Selector of `Error(string)`, the revert data of a failed `require`
+pub const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// `(assert test ["message"])`, leaving 0
+pub(crate) fn emit_assert(
+    args: &[&Value],
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    let (test, message) = match args {
+        [test] => (*test, None),
+        [test, Value::String(message)] => (*test, Some(message.as_str())),
+        _ => return None,
+    };
+    if !scope.strip_assertions {
+        emit_check(test, message, scope, out)?;
+    }
+    out.push(push_bytes(vec![0]));
+    Some(())
+}
+
+/// The body of a function defined with `define-with-contract`, leaving its value
+pub(crate) fn emit_contract(
+    contract: &Contract,
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    let body = match list_items(&contract.body)?.as_slice() {
+        [body] => *body,
+        _ => return None,
+    };
+    if scope.strip_assertions {
+        return emit(body, scope, out);
+    }
+
+    // Past its check, each precondition holds in the body
+    for condition in &contract.requires {
+        emit_condition(condition, scope, out)?;
+        assume(&condition.test, &mut scope.ranges.borrow_mut());
+    }
+    emit(body, scope, out)?;
+    if contract.ensures.is_empty() {
+        return Some(());
+    }
+
+    // The arguments go on top of the result, so the frame holds them in order
+    // followed by the result
+    let mut params = scope.params.to_vec();
+    for param in params.iter().rev() {
+        emit(&Value::Symbol(param.clone()), scope, out)?;
+    }
+    params.push(RESULT.to_string());
+    emit_inlined("ensures", &params, scope, out, |callee, out| {
+        for condition in &contract.ensures {
+            emit_condition(condition, callee, out)?;
+        }
+        emit(&Value::Symbol(RESULT.to_string()), callee, out)
+    })
+}
+
+fn emit_condition(condition: &Condition, scope: &Scope, out: &mut Vec<Instruction>) -> Option<()> {
+    emit_check(&condition.test, Some(&condition.message), scope, out)
+}
+
+// Revert unless `test` holds, leaving nothing on the stack
+fn emit_check(
+    test: &Value,
+    message: Option<&str>,
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    let ok = scope.label("assert_ok");
+    emit(test, scope, out)?;
+    out.push(Instruction::JumpToIf(ok.clone()));
+    match message {
+        Some(message) => emit_error_revert(message, out),
+        None => {
+            out.push(push_bytes(vec![0]));
+            out.push(Instruction::Simple(Opcode::DUP1));
+            out.push(Instruction::Simple(Opcode::REVERT));
+        }
+    }
+    out.push(Instruction::Label(ok));
+    Some(())
+}
+
+// Revert with `Error(message)`, built in a fresh allocation
+fn emit_error_revert(message: &str, out: &mut Vec<Instruction>) {
+    let bytes = message.as_bytes();
+    let size = 4 + 32 + 32 + bytes.len().div_ceil(32) as u64 * 32;
+    out.extend(memory::alloc(size));
+
+    let mut selector = ERROR_SELECTOR.to_vec();
+    selector.resize(32, 0);
+    let mut words = vec![
+        (0, selector),
+        (4, vec![0x20]),
+        (36, minimal_bytes(bytes.len() as u64)),
+    ];
+    for (index, chunk) in bytes.chunks(32).enumerate() {
+        let mut word = chunk.to_vec();
+        word.resize(32, 0);
+        words.push((68 + 32 * index as u64, trim_leading_zeros(word)));
+    }
+
+    // [ptr] -> mstore(ptr + offset, word), leaving ptr
+    for (offset, word) in words {
+        out.push(push_bytes(word));
+        out.push(Instruction::Simple(Opcode::DUP2));
+        if offset > 0 {
+            out.push(push_bytes(minimal_bytes(offset)));
+            out.push(Instruction::Simple(Opcode::ADD));
+        }
+        out.push(Instruction::Simple(Opcode::MSTORE));
+    }
+
+    // [ptr] -> revert(ptr, size)
+    out.push(push_bytes(minimal_bytes(size)));
+    out.push(Instruction::Simple(Opcode::SWAP1));
+    out.push(Instruction::Simple(Opcode::REVERT));
+}
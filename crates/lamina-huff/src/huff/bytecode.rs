@@ -1,10 +1,12 @@
 use std::fmt;
 use tiny_keccak::{Hasher, Keccak};
 
+use super::docs;
+use super::mutability::Mutability;
 use super::opcodes::Opcode;
 
 /// Represents an EVM instruction with its arguments
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Instruction {
     /// Simple opcode without arguments (e.g., ADD, MUL)
     Simple(Opcode),
@@ -45,8 +47,8 @@ impl fmt::Display for HuffMacro {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(
             f,
-            "#define macro {}_MACRO() = takes({}) returns({}) {{",
-            self.name.to_uppercase().replace('-', "_"),
+            "#define macro {}() = takes({}) returns({}) {{",
+            huff_macro_name(&self.name),
             self.takes,
             self.returns
         )?;
@@ -103,6 +105,12 @@ pub struct FunctionSignature {
     pub params: Vec<String>,
     pub returns: Vec<String>,
     pub selector: u32,
+    /// Until the function is compiled, assume it may write state
+    pub state_mutability: Mutability,
+    /// The docstring of the definition
+    pub doc: Option<String>,
+    /// The first and last lines of the definition, when the source is known
+    pub lines: Option<(usize, usize)>,
 }
 
 impl FunctionSignature {
@@ -117,6 +125,9 @@ impl FunctionSignature {
             params,
             returns,
             selector,
+            state_mutability: Mutability::NonPayable,
+            doc: None,
+            lines: None,
         }
     }
 
@@ -137,26 +148,15 @@ impl FunctionSignature {
                 .join(",")
         };
 
-        // Format return types - for now assume all are uint256
         let return_types = if self.returns.is_empty() {
             "".to_string()
         } else {
-            format!(
-                "returns ({})",
-                "uint256"
-                    .repeat(self.returns.len())
-                    .chars()
-                    .collect::<Vec<_>>()
-                    .chunks(7) // Length of "uint256"
-                    .map(|c| c.iter().collect::<String>())
-                    .collect::<Vec<_>>()
-                    .join(",")
-            )
+            format!("returns ({})", self.returns.join(","))
         };
 
         format!(
-            "#define function {}({}) view {}",
-            function_name, param_types, return_types
+            "#define function {}({}) {} {}",
+            function_name, param_types, self.state_mutability, return_types
         )
     }
 }
@@ -184,13 +184,16 @@ impl fmt::Display for HuffContract {
             writeln!(f, "{}", self.storage_constants)?;
         }
 
-        // Define the function interfaces with proper signatures
+        // Define the function interfaces with proper signatures, by selector
+        // so the order doesn't depend on the source
         writeln!(f, "/* Function Signatures */")?;
 
         // Use a HashSet to track function signatures we've already written
         let mut seen_functions = std::collections::HashSet::new();
 
-        for function in &self.functions {
+        let mut functions: Vec<&FunctionSignature> = self.functions.iter().collect();
+        functions.sort_by_key(|function| function.selector);
+        for function in &functions {
             let func_name = &function.name;
 
             // Skip duplicates and skip the main function
@@ -206,11 +209,26 @@ impl fmt::Display for HuffContract {
         // Write all the macros with proper Huff syntax
         writeln!(f, "\n/* Function Implementations */")?;
 
-        // Write user-defined functions first (excluding main)
-        for mac in &self.macros {
-            if mac.name.to_lowercase() != "main" {
-                writeln!(f, "{}\n", mac)?;
+        // Write user-defined functions first (excluding main), by selector,
+        // each under a header describing it
+        let function_of = |mac: &HuffMacro| {
+            functions
+                .iter()
+                .find(|function| huff_macro_name(&function.name) == huff_macro_name(&mac.name))
+                .copied()
+        };
+        let mut macros: Vec<&HuffMacro> = self.macros.iter().collect();
+        macros.sort_by_key(|mac| (function_of(mac).map_or(u32::MAX, |f| f.selector), &mac.name));
+        for mac in macros {
+            if mac.name.to_lowercase() == "main" {
+                continue;
             }
+            if let Some(function) = function_of(mac) {
+                for line in docs::header(function, mac) {
+                    writeln!(f, "/// {}", line)?;
+                }
+            }
+            writeln!(f, "{}\n", mac)?;
         }
 
         // Constructor if any
@@ -255,8 +273,13 @@ impl fmt::Display for HuffContract {
     }
 }
 
+/// The name a macro is defined under in Huff source, e.g. `SET_VALUE_MACRO`
+pub(crate) fn huff_macro_name(macro_name: &str) -> String {
+    format!("{}_MACRO", macro_name.to_uppercase().replace('-', "_"))
+}
+
 /// Convert a macro name to a function name in camelCase
-fn macro_to_function_name(macro_name: &str) -> String {
+pub(crate) fn macro_to_function_name(macro_name: &str) -> String {
     // Convert snake_case or kebab-case to camelCase
     let parts: Vec<&str> = macro_name.split(['_', '-']).collect();
     if parts.is_empty() {
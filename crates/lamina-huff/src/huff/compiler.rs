@@ -1,10 +1,37 @@
-use std::collections::HashMap;
-
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use lamina::contracts::Contract;
+use lamina::diagnostics::{
+    annotated_forms, deny_warnings, AnnotatedForm, Diagnostic, Warnings, MUTABILITY,
+    STORAGE_SLOT_REUSE, UNREACHABLE_CLAUSE, UNSUPPORTED, UNUSED_FUNCTION,
+};
 use lamina::error::Error;
-use lamina::value::{Value, NumberKind};
-
-use super::bytecode::{FunctionSignature, HuffContract, HuffMacro, Instruction};
+use lamina::evaluator::special_forms::parse_define_enum;
+use lamina::evm::abi::wrapper_name;
+use lamina::evm::{load_abi, AbiFunction, Hardfork};
+use lamina::expand::expand_for;
+use lamina::targets::EVM;
+use lamina::value::{EnumType, NumberKind, Value};
+
+use super::artifact::Artifact;
+use super::assembler::{assemble_runtime, creation_code, macro_sizes, MacroSize, MAX_CODE_SIZE};
+use super::assertions::emit_contract;
+use super::bytecode::{
+    macro_to_function_name, FunctionSignature, HuffContract, HuffMacro, Instruction,
+};
+use super::docs;
+use super::enums::check_enum_cases;
+use super::expression::{compile_expression, list_items, InternalFunction, Scope};
+use super::lint;
+use super::memory;
+use super::mutability::{self, Mutability};
 use super::opcodes::Opcode;
+use super::ranges::{assume, check_overflow, Ranges};
+use super::strings::{emit_string_return, string_load_slot};
+use super::switch::constant_test;
+use super::tails::share_tails;
 
 /// Compiler context to track state during compilation
 struct CompilerContext {
@@ -17,81 +44,165 @@ struct CompilerContext {
     /// Track storage slots
     storage_slots: HashMap<String, u64>,
 
-    /// Track label counter
-    label_counter: usize,
+    /// Track address constants
+    addresses: HashMap<String, [u8; 20]>,
 
     /// Track function signatures
     function_signatures: Vec<FunctionSignature>,
+
+    /// Track external contract functions by wrapper name
+    interfaces: HashMap<String, AbiFunction>,
+
+    /// Track internal functions, which are inlined rather than dispatched
+    internal_functions: HashMap<String, InternalFunction>,
+
+    /// Track enums by variant name
+    enums: HashMap<String, Rc<EnumType>>,
+
+    /// Track functions defined with `define-with-contract`, by name
+    contracts: HashMap<String, Contract>,
+
+    /// Leave out assertions and contract checks
+    strip_assertions: bool,
+
+    /// Warnings raised so far
+    warnings: Warnings,
+
+    /// Functions left out because no public function reaches them
+    removed: Vec<String>,
 }
 
 /// Information about a function
 struct FunctionInfo {
-    name: String,
     params: Vec<String>,
-    return_count: usize,
 }
 
 impl CompilerContext {
-    fn new(_contract_name: &str) -> Self {
+    fn new(_contract_name: &str, options: &CompileOptions) -> Self {
         CompilerContext {
             macros: Vec::new(),
             functions: HashMap::new(),
             storage_slots: HashMap::new(),
-            label_counter: 0,
+            addresses: HashMap::new(),
             function_signatures: Vec::new(),
+            interfaces: HashMap::new(),
+            internal_functions: HashMap::new(),
+            enums: HashMap::new(),
+            contracts: HashMap::new(),
+            strip_assertions: options.strip_assertions,
+            warnings: Warnings::default(),
+            removed: Vec::new(),
         }
     }
 
-    /// Generate a new unique label
-    fn new_label(&mut self, prefix: &str) -> String {
-        let label = format!("{}_{}", prefix, self.label_counter);
-        self.label_counter += 1;
-        label
-    }
-
     /// Add a macro to the context
     fn add_macro(&mut self, macro_def: HuffMacro) {
         self.macros.push(macro_def);
     }
 
     /// Register a function definition
-    fn register_function(&mut self, name: &str, params: Vec<String>, return_count: usize) {
+    fn register_function(&mut self, name: &str, params: Vec<String>, returns: Vec<String>) {
+        if self.is_defined(name) {
+            self.warnings.warn(
+                UNUSED_FUNCTION,
+                format!(
+                    "{} is defined more than once; only the first definition is compiled",
+                    name
+                ),
+            );
+            return;
+        }
+        if name.to_lowercase() == "main" {
+            self.warnings.warn(
+                UNUSED_FUNCTION,
+                format!(
+                    "{} is never compiled; the dispatcher is generated from the other functions",
+                    name
+                ),
+            );
+        }
+
         self.functions.insert(
             name.to_string(),
             FunctionInfo {
-                name: name.to_string(),
                 params: params.clone(),
-                return_count,
             },
         );
 
         // Register function signature if it's not the main function
         if name.to_lowercase() != "main" {
-            // Assuming all returns are uint256 for now
-            let returns = vec!["uint256".to_string(); return_count];
             self.function_signatures
                 .push(FunctionSignature::new(name, params, returns));
         }
     }
 
+    /// Register an internal function, which gets no selector and no ABI entry
+    fn register_internal_function(&mut self, name: &str, params: Vec<String>, body: Value) {
+        if self.is_defined(name) {
+            self.warnings.warn(
+                UNUSED_FUNCTION,
+                format!(
+                    "{} is defined more than once; only the first definition is compiled",
+                    name
+                ),
+            );
+            return;
+        }
+
+        self.internal_functions
+            .insert(name.to_string(), InternalFunction { params, body });
+    }
+
+    fn is_defined(&self, name: &str) -> bool {
+        self.functions.contains_key(name) || self.internal_functions.contains_key(name)
+    }
+
     /// Register a storage slot
     fn register_storage_slot(&mut self, name: &str, slot: u64) {
+        let mut others: Vec<&String> = self
+            .storage_slots
+            .iter()
+            .filter(|(other, &other_slot)| other_slot == slot && other.as_str() != name)
+            .map(|(other, _)| other)
+            .collect();
+        if !others.is_empty() {
+            others.sort();
+            let message = format!(
+                "storage slot {} is used by both {} and {}",
+                slot, others[0], name
+            );
+            self.warnings.warn(STORAGE_SLOT_REUSE, message);
+        }
+
         self.storage_slots.insert(name.to_string(), slot);
     }
 
+    /// Register an address constant
+    fn register_address(&mut self, name: &str, address: [u8; 20]) {
+        self.addresses.insert(name.to_string(), address);
+    }
+
+    /// Register the functions of an external contract interface
+    fn register_interface(&mut self, name: &str, functions: Vec<AbiFunction>) {
+        for function in functions {
+            self.interfaces
+                .insert(wrapper_name(name, &function.name), function);
+        }
+    }
+
+    /// Register an enum, whose variants compile to their indices
+    fn register_enum(&mut self, enum_type: EnumType) {
+        let enum_type = Rc::new(enum_type);
+        for variant in &enum_type.variants {
+            self.enums.insert(variant.clone(), enum_type.clone());
+        }
+    }
+
     /// Get a storage slot by name
     fn get_storage_slot(&self, name: &str) -> Option<u64> {
         self.storage_slots.get(name).copied()
     }
 
-    /// Get all storage slots with their names
-    fn get_all_storage_slots(&self) -> Vec<(String, u64)> {
-        self.storage_slots
-            .iter()
-            .map(|(name, &slot)| (name.clone(), slot))
-            .collect()
-    }
-
     /// Generate Huff constant definitions for storage slots
     fn generate_storage_constants(&self) -> String {
         let mut result = String::new();
@@ -113,9 +224,36 @@ impl CompilerContext {
             ));
         }
 
+        // Address constants, sorted by name
+        let mut addresses: Vec<(&String, &[u8; 20])> = self.addresses.iter().collect();
+        addresses.sort();
+
+        for (name, address) in addresses {
+            let hex: String = address.iter().map(|b| format!("{:02x}", b)).collect();
+            result.push_str(&format!(
+                "#define constant {} = 0x{}\n",
+                address_constant_name(name),
+                hex
+            ));
+        }
+
         result
     }
 
+    /// Values of every constant the generated macros can reference
+    fn constant_values(&self) -> HashMap<String, Vec<u8>> {
+        let slots = self.storage_slots.iter().map(|(name, slot)| {
+            let constant_name = format!("{}_SLOT", name.replace('-', "_").to_uppercase());
+            (constant_name, slot.to_be_bytes().to_vec())
+        });
+        let addresses = self
+            .addresses
+            .iter()
+            .map(|(name, address)| (address_constant_name(name), address.to_vec()));
+
+        slots.chain(addresses).collect()
+    }
+
     /// Get a storage slot name by its value
     fn get_storage_slot_name_by_value(&self, value: u64) -> Option<String> {
         self.storage_slots.iter().find_map(|(name, &slot)| {
@@ -127,31 +265,173 @@ impl CompilerContext {
         })
     }
 
-    /// Get the function info by name
-    fn get_function_info(&self, name: &str) -> Option<&FunctionInfo> {
-        self.functions.get(name)
-    }
-
-    /// Get a function's selector by name
-    fn get_function_selector(&self, name: &str) -> Option<u32> {
-        self.function_signatures
-            .iter()
-            .find(|sig| sig.name == name)
-            .map(|sig| sig.selector)
-    }
-
     /// Get all function signatures
     fn get_function_signatures(&self) -> &[FunctionSignature] {
         &self.function_signatures
     }
 }
 
+/// Options controlling how a contract is compiled
+#[derive(Clone, Debug, Default)]
+pub struct CompileOptions {
+    /// Fail compilation when any warning is raised
+    pub deny_warnings: bool,
+    /// Leave out `assert` and the checks of `define-with-contract`
+    pub strip_assertions: bool,
+    /// Most bytes of runtime code the contract may have, `MAX_CODE_SIZE`
+    /// when unset
+    pub size_budget: Option<usize>,
+    /// The hardfork the code has to run on; opcodes added after it are
+    /// refused
+    pub hardfork: Hardfork,
+    /// The text the program was read from, to point the generated Huff at
+    /// the lines of each function
+    pub source: Option<String>,
+}
+
+/// A contract lowered to Huff, before it is assembled
+struct BuiltContract {
+    contract: HuffContract,
+    /// Values of the constants the macros reference
+    constants: HashMap<String, Vec<u8>>,
+    warnings: Vec<Diagnostic>,
+    removed: Vec<String>,
+}
+
 /// Compile a Lamina expression to Huff code
 pub fn compile(expr: &Value, contract_name: &str) -> Result<String, Error> {
-    let mut context = CompilerContext::new(contract_name);
+    compile_with_options(expr, contract_name, &CompileOptions::default())
+}
+
+/// Compile a Lamina expression to Huff code with the given options
+pub fn compile_with_options(
+    expr: &Value,
+    contract_name: &str,
+    options: &CompileOptions,
+) -> Result<String, Error> {
+    let built = build_contract(expr, contract_name, options)?;
+
+    // Convert the contract to Huff code
+    Ok(built.contract.to_string())
+}
+
+/// Lower a Lamina expression to Huff macros, without assembling them
+pub fn compile_contract(expr: &Value, contract_name: &str) -> Result<HuffContract, Error> {
+    Ok(build_contract(expr, contract_name, &CompileOptions::default())?.contract)
+}
+
+/// Compile a Lamina expression to a Foundry-style artifact with assembled bytecode
+pub fn compile_artifact(expr: &Value, contract_name: &str) -> Result<Artifact, Error> {
+    compile_artifact_with_options(expr, contract_name, &CompileOptions::default())
+}
+
+/// Compile a Lamina expression to an artifact, keeping the warnings raised on the way
+pub fn compile_artifact_with_options(
+    expr: &Value,
+    contract_name: &str,
+    options: &CompileOptions,
+) -> Result<Artifact, Error> {
+    let built = build_contract(expr, contract_name, options)?;
+    if options.deny_warnings {
+        deny_warnings(&built.warnings)?;
+    }
+
+    let deployed_bytecode = assemble_runtime(&built.contract, &built.constants)?;
+    let sizes = macro_sizes(&built.contract, &built.constants)?;
+    check_size_budget(
+        deployed_bytecode.len(),
+        options.size_budget.unwrap_or(MAX_CODE_SIZE),
+        &sizes,
+    )?;
+    let bytecode = creation_code(&deployed_bytecode);
+
+    let mut artifact = Artifact::new(&built.contract, bytecode, deployed_bytecode);
+    artifact.warnings = built.warnings;
+    artifact.removed = built.removed;
+    artifact.sizes = sizes;
+    Ok(artifact)
+}
+
+/// Fail when the runtime code is over `budget`, naming the macros that take
+/// the most of it
+fn check_size_budget(size: usize, budget: usize, sizes: &[MacroSize]) -> Result<(), Error> {
+    if size <= budget {
+        return Ok(());
+    }
+    let largest: Vec<String> = sizes
+        .iter()
+        .filter(|size| size.name != "MAIN")
+        .take(3)
+        .map(|size| format!("{} ({} bytes)", size.name, size.bytes))
+        .collect();
+    let mut message = format!(
+        "Runtime code is {} bytes, {} over the budget of {}",
+        size,
+        size - budget,
+        budget
+    );
+    if !largest.is_empty() {
+        message += &format!(
+            ". The largest functions are {}; consider splitting them into \
+             another contract or moving shared code into a library",
+            largest.join(", ")
+        );
+    }
+    Err(Error::Compilation(message))
+}
+
+/// Fail when a macro uses an opcode `hardfork` doesn't have yet, naming the
+/// first one found
+fn check_hardfork(contract: &HuffContract, hardfork: Hardfork) -> Result<(), Error> {
+    let macros = std::iter::once(&contract.main).chain(&contract.macros);
+    for huff_macro in macros {
+        for instruction in &huff_macro.instructions {
+            let Instruction::Simple(op) = instruction else {
+                continue;
+            };
+            if let Some(introduced) = op.introduced_in().filter(|fork| *fork > hardfork) {
+                return Err(Error::Compilation(format!(
+                    "{} uses {}, which needs the {} hardfork, but the target is {}",
+                    huff_macro.name,
+                    op.as_huff_str(),
+                    introduced,
+                    hardfork
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Warnings compiling a Lamina expression raises
+pub fn warnings(expr: &Value, contract_name: &str) -> Result<Vec<Diagnostic>, Error> {
+    Ok(build_contract(expr, contract_name, &CompileOptions::default())?.warnings)
+}
+
+/// Build the contract along with the values of the storage slot constants it references
+fn build_contract(
+    expr: &Value,
+    contract_name: &str,
+    options: &CompileOptions,
+) -> Result<BuiltContract, Error> {
+    let mut context = CompilerContext::new(contract_name, options);
+
+    // Run the compile-time phase so only its results are lowered, leaving
+    // docstrings to the generated comments
+    let (expr, docs) = docs::take_docstrings(&expand_for(expr, &EVM)?);
+    let expr = &expr;
 
     // First pass: analyze the program to discover functions and storage slots
     analyze_program(expr, &mut context)?;
+    let lines = options
+        .source
+        .as_deref()
+        .map(docs::definition_lines)
+        .unwrap_or_default();
+    for function in &mut context.function_signatures {
+        function.doc = docs.get(&function.name).cloned();
+        function.lines = lines.get(&function.name).copied();
+    }
 
     // Second pass: compile functions to macros
     compile_functions(expr, &mut context)?;
@@ -159,21 +439,61 @@ pub fn compile(expr: &Value, contract_name: &str) -> Result<String, Error> {
     // Create a main dispatcher macro that uses the auto-generated function selectors
     let main_macro = create_auto_dispatcher_macro(&context)?;
 
+    // Leave out the macros the dispatcher never expands
+    let macros = std::mem::take(&mut context.macros);
+    let macros = reachable_macros(&main_macro, macros, &mut context.removed);
+
     // Generate storage constants
     let storage_constants = context.generate_storage_constants();
+    let constants = context.constant_values();
 
     // Build the contract
-    let contract = HuffContract {
+    let mut contract = HuffContract {
         name: contract_name.to_string(),
         constructor: None, // Default constructor for now
         main: main_macro,
-        macros: context.macros,
+        macros,
         storage_constants,
         functions: context.function_signatures.clone(),
     };
 
-    // Convert the contract to Huff code
-    Ok(contract.to_string())
+    // Jump to one copy of each repeated epilogue instead of repeating it
+    share_tails(&mut contract, &constants)?;
+    check_hardfork(&contract, options.hardfork)?;
+
+    Ok(BuiltContract {
+        contract,
+        constants,
+        warnings: context.warnings.into_diagnostics(),
+        removed: context.removed,
+    })
+}
+
+/// Keep the macros `main` expands, directly or through other macros, adding
+/// the functions of the others to `removed`
+fn reachable_macros(
+    main: &HuffMacro,
+    macros: Vec<HuffMacro>,
+    removed: &mut Vec<String>,
+) -> Vec<HuffMacro> {
+    let mut reached = HashSet::new();
+    let mut pending = vec![main];
+    while let Some(mac) = pending.pop() {
+        for instruction in &mac.instructions {
+            if let Instruction::MacroCall(name) = instruction {
+                if reached.insert(name.as_str()) {
+                    pending.extend(macros.iter().filter(|callee| callee.name == *name));
+                }
+            }
+        }
+    }
+
+    let reached: HashSet<String> = reached.into_iter().map(str::to_string).collect();
+    let (kept, dropped): (Vec<_>, Vec<_>) = macros
+        .into_iter()
+        .partition(|mac| reached.contains(&mac.name));
+    removed.extend(dropped.iter().map(|mac| macro_to_function_name(&mac.name)));
+    kept
 }
 
 /// Create an automatic dispatcher macro based on function signatures
@@ -187,10 +507,13 @@ fn create_auto_dispatcher_macro(context: &CompilerContext) -> Result<HuffMacro,
         "Compare function selector and route to appropriate function".to_string(),
     ));
 
+    // Every function allocates memory through the free memory pointer
+    instructions.extend(memory::init());
+
     // Get function signatures
     let function_signatures = context.get_function_signatures();
 
-    // For each function, create a selector comparison and jump
+    // Compare the selector with each function's, jumping to the first match
     for (i, function) in function_signatures.iter().enumerate() {
         let function_name = normalize_function_name(&function.name);
         let selector = function.selector;
@@ -200,7 +523,7 @@ fn create_auto_dispatcher_macro(context: &CompilerContext) -> Result<HuffMacro,
 
         // Add a label for this comparison branch
         let comparison_label = format!("compare_selector_{}", i);
-        instructions.push(Instruction::Label(comparison_label.clone()));
+        instructions.push(Instruction::Label(comparison_label));
 
         // Push the function selector constant
         instructions.push(Instruction::Push(4, selector_bytes));
@@ -212,12 +535,24 @@ fn create_auto_dispatcher_macro(context: &CompilerContext) -> Result<HuffMacro,
         instructions.push(Instruction::Simple(Opcode::EQ));
 
         // Jump to function if selectors match
-        let jump_label = format!("jump_to_{}", function_name);
-        instructions.push(Instruction::JumpLabel(jump_label.clone()));
-        instructions.push(Instruction::JumpToIf(jump_label.clone()));
+        instructions.push(Instruction::JumpToIf(format!("jump_to_{}", function_name)));
+    }
+
+    // No function has the selector, so revert
+    instructions.push(Instruction::Label("unknown_selector".to_string()));
+    instructions.push(Instruction::Comment(
+        "Unknown function selector, revert".to_string(),
+    ));
+    instructions.push(Instruction::Push(1, vec![0]));
+    instructions.push(Instruction::Push(1, vec![0]));
+    instructions.push(Instruction::Simple(Opcode::REVERT));
+
+    // The body of each function, reached only by its jump
+    for function in function_signatures {
+        let function_name = normalize_function_name(&function.name);
 
         // Add function jump destination
-        instructions.push(Instruction::Label(jump_label));
+        instructions.push(Instruction::Label(format!("jump_to_{}", function_name)));
 
         // Pop the selector before calling the function
         instructions.push(Instruction::Simple(Opcode::POP));
@@ -225,31 +560,13 @@ fn create_auto_dispatcher_macro(context: &CompilerContext) -> Result<HuffMacro,
         // Call the function
         instructions.push(Instruction::MacroCall(function_name));
 
-        // Store result in memory for return
-        instructions.push(Instruction::Comment(
-            "Store return value in memory".to_string(),
-        ));
-        instructions.push(Instruction::Push(1, vec![0]));
-        instructions.push(Instruction::Simple(Opcode::MSTORE));
-
-        // Return 32 bytes from memory
+        // Return the result from freshly allocated memory
         instructions.push(Instruction::Comment(
             "Return 32 bytes from memory".to_string(),
         ));
-        instructions.push(Instruction::Push(1, vec![32]));
-        instructions.push(Instruction::Push(1, vec![0]));
-        instructions.push(Instruction::Simple(Opcode::RETURN));
+        instructions.extend(memory::return_word());
     }
 
-    // Add fallback for unknown selectors
-    instructions.push(Instruction::Label("unknown_selector".to_string()));
-    instructions.push(Instruction::Comment(
-        "Unknown function selector, revert".to_string(),
-    ));
-    instructions.push(Instruction::Push(1, vec![0]));
-    instructions.push(Instruction::Push(1, vec![0]));
-    instructions.push(Instruction::Simple(Opcode::REVERT));
-
     // Create the main macro
     Ok(HuffMacro {
         name: "main".to_string(),
@@ -272,13 +589,15 @@ fn process_define(define_expr: &Value, context: &mut CompilerContext) -> Result<
                     Value::Number(NumberKind::Integer(slot)) => {
                         context.register_storage_slot(name, *slot as u64);
                     }
-                    Value::Pair(inner_pair) => {
-                        if let Value::Number(NumberKind::Integer(slot)) =
-                            &inner_pair.0
-                        {
+                    Value::Pair(inner_pair) => match &inner_pair.0 {
+                        Value::Number(NumberKind::Integer(slot)) => {
                             context.register_storage_slot(name, *slot as u64);
                         }
-                    }
+                        Value::Address(address) => {
+                            context.register_address(name, *address);
+                        }
+                        _ => {}
+                    },
                     _ => {}
                 }
 
@@ -299,12 +618,14 @@ fn process_define(define_expr: &Value, context: &mut CompilerContext) -> Result<
                         param_list = &param_pair.1;
                     }
 
-                    // Analyze the body to determine return count
-                    // For now, assume all functions return 1 value (typical for getters/setters)
-                    let return_count = 1;
+                    // Functions return a single word, except string getters
+                    let returns = match &pair.1 {
+                        Value::Pair(body) if string_load_slot(&body.0).is_some() => "string",
+                        _ => "uint256",
+                    };
 
-                    // Register the function with its parameters and return count
-                    context.register_function(func_name, params, return_count);
+                    // Register the function with its parameters and return type
+                    context.register_function(func_name, params, vec![returns.to_string()]);
                 }
                 Ok(())
             }
@@ -317,32 +638,56 @@ fn process_define(define_expr: &Value, context: &mut CompilerContext) -> Result<
     }
 }
 
+/// Process a define-interface form during analysis: (define-interface Name "abi.json")
+fn process_define_interface(args: &Value, context: &mut CompilerContext) -> Result<(), Error> {
+    if let Value::Pair(pair) = args {
+        if let (Value::Symbol(name), Value::Pair(path)) = (&pair.0, &pair.1) {
+            if let Value::String(path) = &path.0 {
+                let functions = load_abi(path).map_err(Error::Compilation)?;
+                context.register_interface(name, functions);
+                return Ok(());
+            }
+        }
+    }
+    Err(Error::Compilation(
+        "Invalid define-interface form, expected (define-interface Name \"abi.json\")".to_string(),
+    ))
+}
+
 /// Analyze the program to discover functions and storage slots
 fn analyze_program(expr: &Value, context: &mut CompilerContext) -> Result<(), Error> {
     // Extract the top-level begin form
     if let Value::Pair(pair) = expr {
         if let Value::Symbol(sym) = &pair.0 {
             if sym == "begin" {
-                // Process the body of the begin form
-                let mut body = &pair.1;
-
                 // Process each expression in the body
-                while let Value::Pair(pair) = body {
-                    let expr = &pair.0;
+                for form in annotated_forms(&pair.1)? {
+                    context.warnings.set_allowed(&form.allowed);
 
                     // Look for define forms
-                    if let Value::Pair(def_pair) = expr {
+                    if let Value::Pair(def_pair) = &form.form {
                         if let Value::Symbol(def_sym) = &def_pair.0 {
                             if def_sym == "define" {
                                 process_define(&def_pair.1, context)?;
+                            } else if def_sym == "define-internal" {
+                                process_define_internal(&def_pair.1, context)?;
+                            } else if def_sym == "define-interface" {
+                                process_define_interface(&def_pair.1, context)?;
+                            } else if def_sym == "define-enum" {
+                                context.register_enum(parse_define_enum(&def_pair.1)?);
+                            } else if def_sym == "define-with-contract" {
+                                let contract = Contract::parse(&def_pair.1)?;
+                                if let Value::Pair(define) = contract_function(&contract) {
+                                    process_define(&define.1, context)?;
+                                }
+                                context.contracts.insert(contract.name.clone(), contract);
                             }
                         }
                     }
-
-                    // Move to the next expression
-                    body = &pair.1;
                 }
 
+                check_internal_calls(&pair.1, context)?;
+                check_enum_cases(&pair.1, &context.enums)?;
                 return Ok(());
             }
         }
@@ -353,24 +698,152 @@ fn analyze_program(expr: &Value, context: &mut CompilerContext) -> Result<(), Er
     ))
 }
 
+/// Process a define-internal form during analysis: (define-internal (name params...) body)
+fn process_define_internal(args: &Value, context: &mut CompilerContext) -> Result<(), Error> {
+    let (signature, body) = match args {
+        Value::Pair(pair) => (&pair.0, &pair.1),
+        _ => return Err(Error::Runtime("Invalid define-internal form".to_string())),
+    };
+    let (name, mut param_list) = match signature {
+        Value::Pair(pair) => match &pair.0 {
+            Value::Symbol(name) => (name, &pair.1),
+            _ => return Err(Error::Runtime("Invalid define-internal form".to_string())),
+        },
+        _ => {
+            return Err(Error::Compilation(
+                "define-internal only defines functions".to_string(),
+            ))
+        }
+    };
+
+    let mut params = Vec::new();
+    while let Value::Pair(param_pair) = param_list {
+        if let Value::Symbol(param_name) = &param_pair.0 {
+            params.push(param_name.clone());
+        }
+        param_list = &param_pair.1;
+    }
+
+    context.register_internal_function(name, params, body.clone());
+    Ok(())
+}
+
+/// Warn about internal functions no public function reaches, directly or
+/// through other internal functions, since they compile to nothing
+fn check_internal_calls(body: &Value, context: &mut CompilerContext) -> Result<(), Error> {
+    let forms = annotated_forms(body)?;
+    let internals: Vec<(&str, &AnnotatedForm)> = forms
+        .iter()
+        .filter_map(|annotated| Some((internal_function_name(&annotated.form)?, annotated)))
+        .collect();
+
+    // Walk the call graph from every form that isn't an internal function
+    let mut reached = HashSet::new();
+    let mut pending: Vec<&Value> = forms
+        .iter()
+        .filter(|annotated| internal_function_name(&annotated.form).is_none())
+        .map(|annotated| &annotated.form)
+        .collect();
+    while let Some(caller) = pending.pop() {
+        for (name, annotated) in &internals {
+            if !reached.contains(name) && calls(caller, name) {
+                reached.insert(*name);
+                pending.push(&annotated.form);
+            }
+        }
+    }
+
+    for (name, annotated) in &internals {
+        if reached.contains(name) {
+            continue;
+        }
+        let called = internals
+            .iter()
+            .any(|(other, caller)| other != name && calls(&caller.form, name));
+        context.warnings.set_allowed(&annotated.allowed);
+        context.warnings.warn(
+            UNUSED_FUNCTION,
+            if called {
+                format!(
+                    "{} is internal and only called from functions that are never called, so it is not compiled",
+                    name
+                )
+            } else {
+                format!(
+                    "{} is internal and never called, so it is not compiled",
+                    name
+                )
+            },
+        );
+        context.removed.push(name.to_string());
+    }
+    Ok(())
+}
+
+// The name defined by a `(define-internal (name ...) ...)` form
+fn internal_function_name(form: &Value) -> Option<&str> {
+    let Value::Pair(pair) = form else {
+        return None;
+    };
+    let Value::Pair(args) = &pair.1 else {
+        return None;
+    };
+    match (&pair.0, &args.0) {
+        (Value::Symbol(op), Value::Pair(signature)) if op == "define-internal" => {
+            match &signature.0 {
+                Value::Symbol(name) => Some(name),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+// Whether `expr` calls `name`, leaving out function signatures
+fn calls(expr: &Value, name: &str) -> bool {
+    let Value::Pair(pair) = expr else {
+        return false;
+    };
+    match &pair.0 {
+        Value::Symbol(op) if op == name => true,
+        Value::Symbol(op) if op == "quote" => false,
+        Value::Symbol(op)
+            if op == "define" || op == "define-internal" || op == "define-with-contract" =>
+        {
+            match &pair.1 {
+                Value::Pair(args) => calls(&args.1, name),
+                _ => false,
+            }
+        }
+        head => calls(head, name) || calls(&pair.1, name),
+    }
+}
+
 /// Compile functions to Huff macros
 fn compile_functions(expr: &Value, context: &mut CompilerContext) -> Result<(), Error> {
     // Extract the top-level begin form
     if let Value::Pair(pair) = expr {
         if let Value::Symbol(sym) = &pair.0 {
             if sym == "begin" {
-                // Process the body of the begin form
-                let mut body = &pair.1;
-
                 // Track visited function names to avoid duplicates
                 let mut visited_functions = std::collections::HashSet::new();
 
                 // Process each expression in the body
-                while let Value::Pair(pair) = body {
-                    let expr = &pair.0;
+                for form in annotated_forms(&pair.1)? {
+                    context.warnings.set_allowed(&form.allowed);
+                    let mut declared = form.mutability.as_deref();
+
+                    // A contract compiles like the function it defines, with
+                    // its checks added from `context.contracts`
+                    let definition = match &form.form {
+                        Value::Pair(def_pair) if is_contract(&def_pair.0) => {
+                            contract_function(&Contract::parse(&def_pair.1)?)
+                        }
+                        other => other.clone(),
+                    };
 
                     // Look for define forms
-                    if let Value::Pair(def_pair) = expr {
+                    if let Value::Pair(def_pair) = &definition {
                         if let Value::Symbol(def_sym) = &def_pair.0 {
                             if def_sym == "define" {
                                 if let Value::Pair(define_pair) = &def_pair.1 {
@@ -378,7 +851,6 @@ fn compile_functions(expr: &Value, context: &mut CompilerContext) -> Result<(),
                                         if let Value::Symbol(func_name) = &func_def.0 {
                                             // Skip the main function as it's handled separately
                                             if func_name == "main" {
-                                                body = &pair.1;
                                                 continue;
                                             }
 
@@ -388,13 +860,25 @@ fn compile_functions(expr: &Value, context: &mut CompilerContext) -> Result<(),
 
                                             // Skip if we've already compiled this function
                                             if visited_functions.contains(&normalized_name) {
-                                                body = &pair.1;
                                                 continue;
                                             }
                                             visited_functions.insert(normalized_name);
 
+                                            check_cond_clauses(
+                                                func_name,
+                                                &define_pair.1,
+                                                &mut context.warnings,
+                                            );
+                                            check_ranges(func_name, &define_pair.1, context);
+
                                             // Compile the function
                                             compile_function(func_name, &define_pair.1, context)?;
+                                            resolve_mutability(
+                                                func_name,
+                                                declared.take(),
+                                                context,
+                                            )?;
+                                            lint_function(func_name, context);
                                         }
                                     }
                                 }
@@ -402,8 +886,12 @@ fn compile_functions(expr: &Value, context: &mut CompilerContext) -> Result<(),
                         }
                     }
 
-                    // Move to the next expression
-                    body = &pair.1;
+                    if let Some(declared) = declared {
+                        return Err(Error::Compilation(format!(
+                            "{} {} only applies to public function definitions",
+                            MUTABILITY, declared
+                        )));
+                    }
                 }
 
                 return Ok(());
@@ -416,6 +904,130 @@ fn compile_functions(expr: &Value, context: &mut CompilerContext) -> Result<(),
     ))
 }
 
+fn is_contract(head: &Value) -> bool {
+    matches!(head, Value::Symbol(op) if op == "define-with-contract")
+}
+
+/// `(define (name param ...) body ...)` for a function defined with a contract
+fn contract_function(contract: &Contract) -> Value {
+    let signature = Value::cons(
+        Value::Symbol(contract.name.clone()),
+        contract.params.clone(),
+    );
+    Value::cons(
+        Value::Symbol("define".to_string()),
+        Value::cons(signature, contract.body.clone()),
+    )
+}
+
+/// Run the security lints over a compiled function
+fn lint_function(func_name: &str, context: &mut CompilerContext) {
+    let macro_name = normalize_function_name(func_name);
+    if let Some(mac) = context.macros.iter().find(|mac| mac.name == macro_name) {
+        lint::lint(func_name, &mac.instructions, &mut context.warnings);
+    }
+}
+
+/// Record the mutability of a compiled function in its signature, checking it
+/// against the level it was declared with
+fn resolve_mutability(
+    func_name: &str,
+    declared: Option<&str>,
+    context: &mut CompilerContext,
+) -> Result<(), Error> {
+    let macro_name = normalize_function_name(func_name);
+    let (inferred, reason) = context
+        .macros
+        .iter()
+        .find(|mac| mac.name == macro_name)
+        .map_or((Mutability::NonPayable, None), |mac| {
+            mutability::infer(&mac.instructions)
+        });
+
+    let mutability = match declared {
+        None => inferred,
+        Some(name) => {
+            let declared = Mutability::parse(name).ok_or_else(|| {
+                Error::Compilation(format!("Unknown mutability for {}: {}", func_name, name))
+            })?;
+            if declared < inferred {
+                return Err(Error::Compilation(format!(
+                    "{} is declared {} but {}",
+                    func_name,
+                    declared,
+                    reason.unwrap_or("needs more")
+                )));
+            }
+            declared
+        }
+    };
+
+    if let Some(signature) = context
+        .function_signatures
+        .iter_mut()
+        .find(|signature| signature.name == func_name)
+    {
+        signature.state_mutability = mutability;
+    }
+    Ok(())
+}
+
+/// Warn about arithmetic in a function body that the ranges of its operands
+/// show can overflow, taking a contract's preconditions as given
+fn check_ranges(func_name: &str, body: &Value, context: &mut CompilerContext) {
+    let mut known = Ranges::new();
+    if let (Some(contract), false) = (context.contracts.get(func_name), context.strip_assertions) {
+        for condition in &contract.requires {
+            assume(&condition.test, &mut known);
+        }
+    }
+    for expr in list_items(body).unwrap_or_default() {
+        check_overflow(func_name, expr, &known, &mut context.warnings);
+    }
+}
+
+/// Warn about `cond` clauses that follow a clause which always matches
+fn check_cond_clauses(func_name: &str, expr: &Value, warnings: &mut Warnings) {
+    let pair = match expr {
+        Value::Pair(pair) => pair,
+        _ => return,
+    };
+
+    match &pair.0 {
+        Value::Symbol(op) if op == "quote" => return,
+        Value::Symbol(op) if op == "cond" => {
+            let mut clauses = &pair.1;
+            let mut position = 1;
+            while let Value::Pair(clause) = clauses {
+                if always_matches(&clause.0) && !matches!(clause.1, Value::Nil) {
+                    warnings.warn(
+                        UNREACHABLE_CLAUSE,
+                        format!(
+                            "cond clauses after clause {} in {} are unreachable",
+                            position, func_name
+                        ),
+                    );
+                    break;
+                }
+                clauses = &clause.1;
+                position += 1;
+            }
+        }
+        _ => {}
+    }
+
+    check_cond_clauses(func_name, &pair.0, warnings);
+    check_cond_clauses(func_name, &pair.1, warnings);
+}
+
+/// Whether a cond clause matches regardless of the state it runs in
+fn always_matches(clause: &Value) -> bool {
+    match clause {
+        Value::Pair(clause) => constant_test(&clause.0) == Some(true),
+        _ => false,
+    }
+}
+
 /// Compile a function to a Huff macro
 fn compile_function(
     func_name: &str,
@@ -506,10 +1118,7 @@ fn compile_function(
             )));
             instructions.push(Instruction::Simple(Opcode::CONSTANT(slot_constant.clone())));
 
-            // Swap the value and slot
-            instructions.push(Instruction::Simple(Opcode::SWAP1));
-
-            // Store the value
+            // Store the value, SSTORE taking the slot from the top of the stack
             instructions.push(Instruction::Simple(Opcode::SSTORE));
 
             // Load the value again to return it
@@ -565,7 +1174,6 @@ fn compile_function(
 
             // Store updated value
             instructions.push(Instruction::Simple(Opcode::CONSTANT(slot_constant.clone())));
-            instructions.push(Instruction::Simple(Opcode::SWAP1));
             instructions.push(Instruction::Simple(Opcode::SSTORE));
 
             // Create the macro and add it to the context
@@ -580,9 +1188,38 @@ fn compile_function(
             context.add_macro(macro_def);
         }
 
+        FunctionType::Expression(body) => {
+            let mut instructions = vec![Instruction::Comment(
+                "Evaluate expression over calldata arguments".to_string(),
+            )];
+            instructions.extend(body);
+
+            let macro_def = HuffMacro {
+                name: normalized_name.clone(),
+                takes: 0,
+                returns: 1,
+                instructions,
+                params: context
+                    .functions
+                    .get(func_name)
+                    .map(|info| info.params.clone())
+                    .unwrap_or_default(),
+            };
+
+            context.add_macro(macro_def);
+        }
+
         // Default case for unknown function types
         FunctionType::Unknown => {
-            // For now, create a basic macro that just reverts
+            context.warnings.warn(
+                UNSUPPORTED,
+                format!(
+                    "{} uses forms the EVM backend can't compile, so it always reverts",
+                    func_name
+                ),
+            );
+
+            // Create a basic macro that just reverts
             let instructions = vec![
                 Instruction::Comment("Function not yet implemented, reverting".to_string()),
                 Instruction::Push(1, vec![0]), // Size: 0
@@ -612,6 +1249,7 @@ enum FunctionType {
     StorageGetter(u64),
     StorageSetter(u64),
     StorageIncrementer(u64),
+    Expression(Vec<Instruction>),
     Unknown,
 }
 
@@ -636,55 +1274,51 @@ fn extract_direct_storage_slot(
     body: &Value,
     context: &CompilerContext,
 ) -> Result<Option<u64>, Error> {
-    match body {
-        // Direct storage-load: (storage-load slot-name)
-        Value::Pair(pair) => {
-            if let Value::Symbol(op) = &pair.0 {
-                if op == "storage-load" {
-                    if let Value::Symbol(slot_name) = &pair.1 {
+    // Direct storage-load: (storage-load slot-name)
+    if let Value::Pair(pair) = body {
+        if let Value::Symbol(op) = &pair.0 {
+            if op == "storage-load" {
+                if let Value::Symbol(slot_name) = &pair.1 {
+                    if let Some(slot) = context.get_storage_slot(slot_name) {
+                        return Ok(Some(slot));
+                    }
+                }
+            } else if op == "storage-store" {
+                if let Value::Pair(args) = &pair.1 {
+                    if let Value::Symbol(slot_name) = &args.0 {
                         if let Some(slot) = context.get_storage_slot(slot_name) {
                             return Ok(Some(slot));
                         }
                     }
-                } else if op == "storage-store" {
-                    if let Value::Pair(args) = &pair.1 {
-                        if let Value::Symbol(slot_name) = &args.0 {
-                            if let Some(slot) = context.get_storage_slot(slot_name) {
-                                return Ok(Some(slot));
-                            }
-                        }
-                    }
-                } else if op == "begin" {
-                    let mut body_iter = &pair.1;
-
-                    // Look for storage operations within the begin block
-                    while let Value::Pair(inner_pair) = body_iter {
-                        if let Value::Pair(inner_op_pair) = &inner_pair.0 {
-                            if let Value::Symbol(inner_op) = &inner_op_pair.0 {
-                                if inner_op == "storage-load" || inner_op == "storage-store" {
-                                    // For simplicity, check the first storage operation we find
-                                    if let Value::Symbol(slot_name) = &inner_op_pair.1 {
+                }
+            } else if op == "begin" {
+                let mut body_iter = &pair.1;
+
+                // Look for storage operations within the begin block
+                while let Value::Pair(inner_pair) = body_iter {
+                    if let Value::Pair(inner_op_pair) = &inner_pair.0 {
+                        if let Value::Symbol(inner_op) = &inner_op_pair.0 {
+                            if inner_op == "storage-load" || inner_op == "storage-store" {
+                                // For simplicity, check the first storage operation we find
+                                if let Value::Symbol(slot_name) = &inner_op_pair.1 {
+                                    if let Some(slot) = context.get_storage_slot(slot_name) {
+                                        return Ok(Some(slot));
+                                    }
+                                } else if let Value::Pair(args) = &inner_op_pair.1 {
+                                    if let Value::Symbol(slot_name) = &args.0 {
                                         if let Some(slot) = context.get_storage_slot(slot_name) {
                                             return Ok(Some(slot));
                                         }
-                                    } else if let Value::Pair(args) = &inner_op_pair.1 {
-                                        if let Value::Symbol(slot_name) = &args.0 {
-                                            if let Some(slot) = context.get_storage_slot(slot_name)
-                                            {
-                                                return Ok(Some(slot));
-                                            }
-                                        }
                                     }
                                 }
                             }
                         }
-
-                        body_iter = &inner_pair.1;
                     }
+
+                    body_iter = &inner_pair.1;
                 }
             }
         }
-        _ => {}
     }
 
     Ok(None)
@@ -724,6 +1358,12 @@ fn extract_storage_from_function_call(
 
 /// Analyze a function body to determine its type
 fn analyze_function_body(body: &Value, context: &CompilerContext) -> Result<FunctionType, Error> {
+    // The checks of a contract have to run whatever its body looks like
+    if get_current_function_name().is_some_and(|name| context.contracts.contains_key(&name)) {
+        return Ok(compile_body_expression(body, context)
+            .map_or(FunctionType::Unknown, FunctionType::Expression));
+    }
+
     // First look at function name patterns as a hint
 
     // Check for known storage slots
@@ -754,10 +1394,60 @@ fn analyze_function_body(body: &Value, context: &CompilerContext) -> Result<Func
         }
     }
 
+    // Pure expressions over parameters, literals and storage compile directly
+    if let Some(instructions) = compile_body_expression(body, context) {
+        return Ok(FunctionType::Expression(instructions));
+    }
+
     // Default to unknown function type
     Ok(FunctionType::Unknown)
 }
 
+/// Compile a single-expression function body, if it only uses supported primitives
+fn compile_body_expression(body: &Value, context: &CompilerContext) -> Option<Vec<Instruction>> {
+    let function_name = get_current_function_name()?;
+    let params = &context.functions.get(&function_name)?.params;
+
+    let slots = context.storage_slots.keys().map(|name| {
+        let constant_name = format!("{}_SLOT", name.replace('-', "_").to_uppercase());
+        (name.clone(), constant_name)
+    });
+    let addresses = context
+        .addresses
+        .keys()
+        .map(|name| (name.clone(), address_constant_name(name)));
+
+    let scope = Scope {
+        params,
+        constants: slots.chain(addresses).collect(),
+        interfaces: &context.interfaces,
+        internals: &context.internal_functions,
+        enums: &context.enums,
+        strip_assertions: context.strip_assertions,
+        inlined: Vec::new(),
+        label_prefix: normalize_function_name(&function_name),
+        labels: Cell::new(0),
+        ranges: RefCell::default(),
+    };
+    if let Some(contract) = context.contracts.get(&function_name) {
+        let mut instructions = Vec::new();
+        emit_contract(contract, &scope, &mut instructions)?;
+        return Some(instructions);
+    }
+
+    let expr = match body {
+        Value::Pair(pair) if matches!(pair.1, Value::Nil) => &pair.0,
+        _ => return None,
+    };
+    if let Some(slot) = string_load_slot(expr) {
+        // Returns from inside the macro, with the string ABI-encoded
+        let mut instructions = Vec::new();
+        emit_string_return(slot, &scope, &mut instructions)?;
+        return Some(instructions);
+    }
+    compile_expression(expr, &scope)
+}
+
 /// Check if a function body is mainly doing a storage load
 fn is_storage_getter(body: &Value) -> bool {
     if let Value::Pair(pair) = body {
@@ -849,8 +1539,8 @@ fn is_storage_setter(body: &Value) -> bool {
     false
 }
 
-/// Get the current function name being compiled
-/// This is a thread_local variable that will be set during compile_function
+// Get the current function name being compiled
+// This is a thread_local variable that will be set during compile_function
 thread_local! {
     static CURRENT_FUNCTION: std::cell::RefCell<Option<String>> = const { std::cell::RefCell::new(None) };
 }
@@ -867,6 +1557,11 @@ fn get_current_function_name() -> Option<String> {
     CURRENT_FUNCTION.with(|current| current.borrow().clone())
 }
 
+/// Huff constant name for an address definition
+fn address_constant_name(name: &str) -> String {
+    name.replace('-', "_").to_uppercase()
+}
+
 /// Helper function to normalize function names
 fn normalize_function_name(name: &str) -> String {
     name.replace('-', "_")
@@ -0,0 +1,61 @@
+// Lowering of CREATE2 deployments
+//
+// `(deploy-create2 salt init-code)` deploys a contract at an address fixed by
+// the contract's own address, the salt and the hash of the init code, so
+// factories can compute where their children live before creating them, with
+// `(compile-time (create2-address ...))` or off-chain. The init code must be a
+// bytevector literal, usually produced by `compile-time`; it is copied into a
+// fresh allocation before CREATE2 runs. A deployment that fails, such as a
+// second one with the same salt and init code, reverts.
+
+use lamina::value::Value;
+
+use super::bytecode::Instruction;
+use super::expression::{emit, minimal_bytes, push_bytes, Scope};
+use super::memory;
+use super::opcodes::Opcode;
+use super::strings::trim_leading_zeros;
+
+/// `(deploy-create2 salt init-code)`, leaving the address of the new contract
+pub(crate) fn emit_deploy_create2(
+    args: &[&Value],
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    let (salt, init_code) = match args {
+        [salt, Value::Bytevector(code)] => (*salt, code.borrow().clone()),
+        _ => return None,
+    };
+
+    emit(salt, scope, out)?;
+    out.extend(memory::alloc(init_code.len() as u64));
+
+    // [ptr, salt] -> mstore(ptr + 32 * i, chunk) for each 32-byte chunk
+    for (index, chunk) in init_code.chunks(32).enumerate() {
+        let mut word = chunk.to_vec();
+        word.resize(32, 0);
+        out.push(push_bytes(trim_leading_zeros(word)));
+        out.push(Instruction::Simple(Opcode::DUP2));
+        if index > 0 {
+            out.push(push_bytes(minimal_bytes(32 * index as u64)));
+            out.push(Instruction::Simple(Opcode::ADD));
+        }
+        out.push(Instruction::Simple(Opcode::MSTORE));
+    }
+
+    // value, offset, size, salt
+    out.push(push_bytes(minimal_bytes(init_code.len() as u64)));
+    out.push(Instruction::Simple(Opcode::SWAP1));
+    out.push(push_bytes(vec![0]));
+    out.push(Instruction::Simple(Opcode::CREATE2));
+
+    // CREATE2 leaves zero when the deployment fails
+    let ok = scope.label("create2_ok");
+    out.push(Instruction::Simple(Opcode::DUP1));
+    out.push(Instruction::JumpToIf(ok.clone()));
+    out.push(push_bytes(vec![0]));
+    out.push(Instruction::Simple(Opcode::DUP1));
+    out.push(Instruction::Simple(Opcode::REVERT));
+    out.push(Instruction::Label(ok));
+    Some(())
+}
@@ -0,0 +1,178 @@
+// Doc comments of the generated Huff
+//
+// A function's docstring is a string at the start of its body, followed by
+// the expressions it evaluates:
+//
+//   (define (set-value v)
+//     "Store v as the new value"
+//     (storage-store value v))
+//
+// The compiler takes docstrings off before lowering the bodies. Each function
+// macro in the Huff source then starts with a header in NatSpec tags: the
+// docstring as `@notice`, and as `@dev` the Lamina name with the lines of its
+// definition, the signature and selector, and the storage slots the macro
+// references:
+//
+//   /// @notice Store v as the new value
+//   /// @dev set-value, lines 3-5
+//   /// @dev setValue(uint256) nonpayable returns (uint256), selector 0x55241077
+//   /// @dev storage: VALUE_SLOT
+//
+// The lines are only known when the compiler is given the source. The
+// docstrings also go into the artifact's `userdoc`, where solc puts notices.
+
+use std::collections::{BTreeSet, HashMap};
+
+use lamina::lexer::{self, Token};
+use lamina::value::Value;
+
+use super::bytecode::{macro_to_function_name, FunctionSignature, HuffMacro, Instruction};
+use super::opcodes::Opcode;
+
+/// `expr` with the docstrings taken off its top-level function definitions,
+/// and the docstrings by function name
+pub(crate) fn take_docstrings(expr: &Value) -> (Value, HashMap<String, String>) {
+    let mut docs = HashMap::new();
+    let Value::Pair(program) = expr else {
+        return (expr.clone(), docs);
+    };
+    if !matches!(&program.0, Value::Symbol(head) if head == "begin") {
+        return (expr.clone(), docs);
+    }
+
+    let mut forms = vec![program.0.clone()];
+    let mut rest = &program.1;
+    while let Value::Pair(item) = rest {
+        forms.push(match docstring(&item.0) {
+            Some((name, doc, definition)) => {
+                let lines: Vec<&str> = doc.lines().map(str::trim).collect();
+                docs.insert(name, lines.join("\n").trim().to_string());
+                definition
+            }
+            None => item.0.clone(),
+        });
+        rest = &item.1;
+    }
+    (Value::list(forms), docs)
+}
+
+// The name, docstring and docstring-less form of a `(define (name ...) "doc"
+// body ...)`
+fn docstring(form: &Value) -> Option<(String, String, Value)> {
+    let items = list_items(form)?;
+    let [Value::Symbol(head), Value::Pair(signature), Value::String(doc), body @ ..] =
+        items.as_slice()
+    else {
+        return None;
+    };
+    let Value::Symbol(name) = &signature.0 else {
+        return None;
+    };
+    if head != "define" || body.is_empty() {
+        return None;
+    }
+    let definition = [items[0].clone(), items[1].clone()]
+        .into_iter()
+        .chain(body.iter().cloned());
+    Some((name.clone(), doc.clone(), Value::list(definition)))
+}
+
+fn list_items(value: &Value) -> Option<Vec<Value>> {
+    let mut items = Vec::new();
+    let mut rest = value;
+    while let Value::Pair(item) = rest {
+        items.push(item.0.clone());
+        rest = &item.1;
+    }
+    matches!(rest, Value::Nil).then_some(items)
+}
+
+/// The first and last lines of each top-level function definition in
+/// `source`, by name; definitions inside a top-level `begin` count too
+pub(crate) fn definition_lines(source: &str) -> HashMap<String, (usize, usize)> {
+    let mut lines = HashMap::new();
+    let Ok(tokens) = lexer::lex_with_spans(source) else {
+        return lines;
+    };
+
+    // The head of each list the scan is in, and the index of its opening token
+    let mut open: Vec<(Option<&str>, usize)> = Vec::new();
+    for (index, (token, span)) in tokens.iter().enumerate() {
+        match token {
+            Token::LeftParen | Token::VectorOpen | Token::BytevectorOpen => {
+                let head = match tokens.get(index + 1) {
+                    Some((Token::Symbol(head), _)) if *token == Token::LeftParen => {
+                        Some(head.as_str())
+                    }
+                    _ => None,
+                };
+                open.push((head, index));
+            }
+            Token::RightParen => {
+                let Some((head, start)) = open.pop() else {
+                    continue;
+                };
+                let top_level = open.iter().all(|(head, _)| *head == Some("begin"));
+                if head != Some("define") || !top_level {
+                    continue;
+                }
+                if let [_, _, (Token::LeftParen, _), (Token::Symbol(name), _), ..] =
+                    &tokens[start..]
+                {
+                    let first = lexer::line_and_column(source, tokens[start].1.start).0;
+                    let last = lexer::line_and_column(source, span.start).0;
+                    lines.entry(name.clone()).or_insert((first, last));
+                }
+            }
+            _ => {}
+        }
+    }
+    lines
+}
+
+/// The header comment lines of `function`'s macro, without the `///`
+pub(crate) fn header(function: &FunctionSignature, mac: &HuffMacro) -> Vec<String> {
+    // A tag runs on until the next one, so only the first line has it
+    let mut header: Vec<String> = function
+        .doc
+        .iter()
+        .flat_map(|doc| doc.lines())
+        .enumerate()
+        .map(|(index, line)| match index {
+            0 => format!("@notice {}", line),
+            _ => line.to_string(),
+        })
+        .collect();
+
+    header.push(match function.lines {
+        Some((first, last)) if first == last => format!("@dev {}, line {}", function.name, first),
+        Some((first, last)) => format!("@dev {}, lines {}-{}", function.name, first, last),
+        None => format!("@dev {}", function.name),
+    });
+    header.push(format!(
+        "@dev {}({}) {} returns ({}), selector 0x{:08x}",
+        macro_to_function_name(&function.name),
+        vec!["uint256"; function.params.len()].join(","),
+        function.state_mutability,
+        function.returns.join(","),
+        function.selector
+    ));
+
+    let slots: BTreeSet<&str> = mac
+        .instructions
+        .iter()
+        .filter_map(|instruction| match instruction {
+            Instruction::MacroCall(name) | Instruction::Simple(Opcode::CONSTANT(name)) => {
+                Some(name.as_str()).filter(|name| name.ends_with("_SLOT"))
+            }
+            _ => None,
+        })
+        .collect();
+    if !slots.is_empty() {
+        header.push(format!(
+            "@dev storage: {}",
+            slots.into_iter().collect::<Vec<_>>().join(", ")
+        ));
+    }
+    header
+}
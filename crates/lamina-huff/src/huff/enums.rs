@@ -0,0 +1,95 @@
+// Lowering of `define-enum`
+//
+// A variant compiles to its index, so an enum value is a small integer in
+// storage and on the stack, and a `case` over variants compiles like a `case`
+// over integers, jump table included:
+//
+//   Variant:          PUSH index
+//   Name? x:          x < count
+//   Name->integer x:  x
+//   integer->Name n:  revert unless n < count, then n
+//
+// A `case` without an `else` clause has to name every variant of the enum its
+// datums are from, as in the interpreter.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use lamina::error::Error;
+use lamina::evaluator::special_forms::check_case_exhaustive;
+use lamina::value::{EnumType, Value};
+
+use super::bytecode::Instruction;
+use super::expression::{emit, push_integer, Scope};
+use super::opcodes::Opcode;
+use super::safemath::emit_check;
+
+/// The index of a variant, if `name` is one
+pub(crate) fn variant_index(name: &str, scope: &Scope) -> Option<i64> {
+    let index = scope.enums.get(name)?.index(name)?;
+    Some(index as i64)
+}
+
+/// The enum `op` is the predicate or a conversion of, if any
+pub(crate) fn enum_operation<'a>(op: &str, scope: &Scope<'a>) -> Option<&'a EnumType> {
+    let enum_type = scope.enums.values().find(|enum_type| {
+        op == format!("{}?", enum_type.name)
+            || op == format!("{}->integer", enum_type.name)
+            || op == format!("integer->{}", enum_type.name)
+    })?;
+    Some(enum_type)
+}
+
+/// Compile `Name?`, `Name->integer` or `integer->Name`
+pub(crate) fn emit_enum_op(
+    op: &str,
+    enum_type: &EnumType,
+    args: &[&Value],
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    let value = match args {
+        [value] => *value,
+        _ => return None,
+    };
+    let count = push_integer(enum_type.variants.len() as i64);
+
+    emit(value, scope, out)?;
+    if op.ends_with('?') {
+        // [x] -> [count > x]
+        out.push(count);
+        out.push(Instruction::Simple(Opcode::GT));
+    } else if op.starts_with("integer->") {
+        // [n] -> [count > n, n], then the check leaves [n]
+        out.push(Instruction::Simple(Opcode::DUP1));
+        out.push(count);
+        out.push(Instruction::Simple(Opcode::GT));
+        emit_check(scope, out);
+    }
+    Some(())
+}
+
+/// Check every `case` in `expr` against the enums, by variant name
+pub(crate) fn check_enum_cases(
+    expr: &Value,
+    enums: &HashMap<String, Rc<EnumType>>,
+) -> Result<(), Error> {
+    let pair = match expr {
+        Value::Pair(pair) => pair,
+        _ => return Ok(()),
+    };
+
+    match &pair.0 {
+        Value::Symbol(op) if op == "quote" => return Ok(()),
+        Value::Symbol(op) if op == "case" => {
+            if let Value::Pair(case) = &pair.1 {
+                check_case_exhaustive(&case.1, |datum| enums.get(datum).cloned())
+                    .map_err(Error::Compilation)?;
+            }
+        }
+        _ => {}
+    }
+
+    check_enum_cases(&pair.0, enums)?;
+    check_enum_cases(&pair.1, enums)
+}
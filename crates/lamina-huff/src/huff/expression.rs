@@ -0,0 +1,443 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use lamina::evm::{AbiFunction, SAFEMATH_PRIMITIVES};
+use lamina::value::{EnumType, NumberKind, Value};
+
+use super::array::{emit_array_op, ARRAY_PRIMITIVES};
+use super::assertions::emit_assert;
+use super::bytecode::Instruction;
+use super::create2::emit_deploy_create2;
+use super::enums::{emit_enum_op, enum_operation, variant_index};
+use super::memory;
+use super::opcodes::Opcode;
+use super::ranges::{assume, Ranges};
+use super::safemath::emit_safemath_op;
+use super::specialize::{code_size, emit_specialized};
+use super::strings::emit_string_store;
+use super::switch::{emit_case, emit_cond, emit_logic};
+
+/// A function defined with `define-internal`, inlined wherever it is called
+pub(crate) struct InternalFunction {
+    pub params: Vec<String>,
+    pub body: Value,
+}
+
+/// Names visible to an expression being compiled
+pub(crate) struct Scope<'a> {
+    /// Function parameters, read from calldata in order, or from the frame
+    /// of the call when inside an internal function
+    pub params: &'a [String],
+    /// Lamina names of top-level constants mapped to their Huff constant names
+    pub constants: HashMap<String, String>,
+    /// External functions by wrapper name, e.g. `IERC20/balanceOf`
+    pub interfaces: &'a HashMap<String, AbiFunction>,
+    /// Internal functions by name
+    pub internals: &'a HashMap<String, InternalFunction>,
+    /// Enums by variant name
+    pub enums: &'a HashMap<String, Rc<EnumType>>,
+    /// Leave out `assert` and contract checks
+    pub strip_assertions: bool,
+    /// Internal functions being inlined around the expression, innermost last
+    pub inlined: Vec<String>,
+    /// Prefix for the labels an expression defines, unique per macro
+    pub label_prefix: String,
+    /// Labels defined so far
+    pub labels: Cell<usize>,
+    /// Ranges of the parameters known from the guards around the expression
+    pub ranges: RefCell<Ranges>,
+}
+
+impl Scope<'_> {
+    /// A label no other part of the macro uses
+    pub fn label(&self, name: &str) -> String {
+        let index = self.labels.get();
+        self.labels.set(index + 1);
+        format!("{}_{}_{}", self.label_prefix, name, index)
+    }
+
+    /// The scope of the body of `name`, inlined here, which reads `params`
+    /// from its frame
+    pub fn callee<'b>(&'b self, name: &str, params: &'b [String]) -> Scope<'b> {
+        let mut inlined = self.inlined.clone();
+        inlined.push(name.to_string());
+        Scope {
+            params,
+            constants: self.constants.clone(),
+            interfaces: self.interfaces,
+            internals: self.internals,
+            enums: self.enums,
+            strip_assertions: self.strip_assertions,
+            inlined,
+            label_prefix: self.label(name),
+            labels: Cell::new(0),
+            ranges: RefCell::default(),
+        }
+    }
+
+    /// Run `f` knowing that `test` holds
+    pub fn assuming<T>(&self, test: &Value, f: impl FnOnce(&Self) -> T) -> T {
+        let known = self.ranges.borrow().clone();
+        assume(test, &mut self.ranges.borrow_mut());
+        let result = f(self);
+        *self.ranges.borrow_mut() = known;
+        result
+    }
+}
+
+/// The opcode a binary primitive compiles to
+pub(crate) fn binary_opcode(name: &str) -> Option<Opcode> {
+    let opcode = match name {
+        "+" => Opcode::ADD,
+        "-" => Opcode::SUB,
+        "*" => Opcode::MUL,
+        "/" => Opcode::DIV,
+        "<" => Opcode::LT,
+        ">" => Opcode::GT,
+        "=" => Opcode::EQ,
+        "s<" => Opcode::SLT,
+        "s/" => Opcode::SDIV,
+        "smod" => Opcode::SMOD,
+        "sign-extend" => Opcode::SIGNEXTEND,
+        "bitwise-and" => Opcode::AND,
+        "bitwise-or" => Opcode::OR,
+        "bitwise-xor" => Opcode::XOR,
+        _ => return None,
+    };
+    Some(opcode)
+}
+
+/// The opcode an environment primitive taking no arguments compiles to
+pub(crate) fn nullary_opcode(name: &str) -> Option<Opcode> {
+    match name {
+        "caller" => Some(Opcode::CALLER),
+        "callvalue" => Some(Opcode::CALLVALUE),
+        "tx-origin" => Some(Opcode::ORIGIN),
+        _ => None,
+    }
+}
+
+/// The opcode a unary primitive compiles to
+pub(crate) fn unary_opcode(name: &str) -> Option<Opcode> {
+    match name {
+        "bitwise-not" => Some(Opcode::NOT),
+        "transient-load" => Some(Opcode::TLOAD),
+        _ => None,
+    }
+}
+
+/// Compile a pure expression to instructions that leave its value on the stack.
+///
+/// Returns `None` when the expression uses anything other than integer and address
+/// literals, parameters, constants, enum variants, `storage-load`, transient storage, interface and internal
+/// calls, `cond`, `case`, `and`, `or`, arrays, string stores, CREATE2 deployments, checked arithmetic and known primitives, so the caller can fall back to the pattern-based compilation.
+pub(crate) fn compile_expression(expr: &Value, scope: &Scope) -> Option<Vec<Instruction>> {
+    let mut instructions = Vec::new();
+    emit(expr, scope, &mut instructions)?;
+    Some(instructions)
+}
+
+pub(super) fn emit(expr: &Value, scope: &Scope, out: &mut Vec<Instruction>) -> Option<()> {
+    match expr {
+        Value::Number(NumberKind::Integer(n)) => out.push(push_integer(*n)),
+        Value::Boolean(b) => out.push(push_integer(*b as i64)),
+        Value::Address(address) => out.push(push_bytes(address.to_vec())),
+        Value::Symbol(name) => {
+            if let Some(index) = scope.params.iter().position(|param| param == name) {
+                if scope.inlined.is_empty() {
+                    // Arguments follow the 4-byte selector, one 32-byte word each
+                    let offset = 4 + 32 * index as u64;
+                    out.push(push_bytes(minimal_bytes(offset)));
+                    out.push(Instruction::Simple(Opcode::CALLDATALOAD));
+                } else {
+                    // Arguments follow the saved frame pointer
+                    out.push(push_bytes(vec![memory::FRAME_POINTER]));
+                    out.push(Instruction::Simple(Opcode::MLOAD));
+                    out.push(push_bytes(minimal_bytes(32 * (index as u64 + 1))));
+                    out.push(Instruction::Simple(Opcode::ADD));
+                    out.push(Instruction::Simple(Opcode::MLOAD));
+                }
+            } else if let Some(index) = variant_index(name, scope) {
+                out.push(push_integer(index));
+            } else {
+                let constant = scope.constants.get(name)?;
+                out.push(Instruction::Simple(Opcode::CONSTANT(constant.clone())));
+            }
+        }
+        Value::Pair(pair) => {
+            let op = match &pair.0 {
+                Value::Symbol(op) => op.as_str(),
+                _ => return None,
+            };
+            let args = list_items(&pair.1)?;
+
+            if let Some(function) = scope.interfaces.get(op) {
+                emit_external_call(function, &args, scope, out)?;
+            } else if let Some(function) = scope.internals.get(op) {
+                emit_internal_call(op, function, &args, scope, out)?;
+            } else if op == "cond" {
+                emit_cond(&args, scope, out)?;
+            } else if op == "and" || op == "or" {
+                emit_logic(op == "and", &args, scope, out)?;
+            } else if op == "assert" {
+                emit_assert(&args, scope, out)?;
+            } else if op == "case" {
+                emit_case(&args, scope, out)?;
+            } else if ARRAY_PRIMITIVES.contains(&op) {
+                emit_array_op(op, &args, scope, out)?;
+            } else if op == "storage-string-store!" {
+                emit_string_store(&args, scope, out)?;
+            } else if op == "deploy-create2" {
+                emit_deploy_create2(&args, scope, out)?;
+            } else if SAFEMATH_PRIMITIVES.contains(&op) {
+                emit_safemath_op(op, &args, scope, out)?;
+            } else if let Some(enum_type) = enum_operation(op, scope) {
+                emit_enum_op(op, enum_type, &args, scope, out)?;
+            } else if op == "storage-load" && args.len() == 1 {
+                emit(args[0], scope, out)?;
+                out.push(Instruction::Simple(Opcode::SLOAD));
+            } else if op == "transient-store!" && args.len() == 2 {
+                // Leaves the value stored, as the interpreter returns it
+                emit(args[1], scope, out)?;
+                out.push(Instruction::Simple(Opcode::DUP1));
+                emit(args[0], scope, out)?;
+                out.push(Instruction::Simple(Opcode::TSTORE));
+            } else if op == "arithmetic-shift" && args.len() == 2 {
+                // The direction must be known at compile time, so only literal counts compile
+                let count = match args[1] {
+                    Value::Number(NumberKind::Integer(count)) => *count,
+                    _ => return None,
+                };
+                let (opcode, bits) = if count >= 0 {
+                    (Opcode::SHL, count as u64)
+                } else {
+                    (Opcode::SAR, count.unsigned_abs())
+                };
+
+                // SHL and SAR take the shift amount on top of the value
+                emit(args[0], scope, out)?;
+                out.push(push_bytes(minimal_bytes(bits)));
+                out.push(Instruction::Simple(opcode));
+            } else if let (Some(opcode), 0) = (nullary_opcode(op), args.len()) {
+                out.push(Instruction::Simple(opcode));
+            } else if let (Some(opcode), 1) = (unary_opcode(op), args.len()) {
+                emit(args[0], scope, out)?;
+                out.push(Instruction::Simple(opcode));
+            } else if let (Some(opcode), 2) = (binary_opcode(op), args.len()) {
+                // The first operand has to end up on top of the stack
+                emit(args[1], scope, out)?;
+                emit(args[0], scope, out)?;
+                out.push(Instruction::Simple(opcode));
+            } else {
+                return None;
+            }
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+/// Call an external contract: `(Iface/fn target args...)`.
+///
+/// The calldata is built in a fresh allocation, which the first word of the
+/// return data is read back from; a failed call reverts with its return data.
+fn emit_external_call(
+    function: &AbiFunction,
+    args: &[&Value],
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    let (target, args) = args.split_first()?;
+    if args.len() != function.inputs.len() {
+        return None;
+    }
+
+    // Evaluate the arguments first, first argument on top, then the buffer
+    for arg in args.iter().rev() {
+        emit(arg, scope, out)?;
+    }
+    let args_size = 4 + 32 * args.len() as u64;
+    out.extend(memory::alloc(args_size.max(32)));
+
+    // Selector in the top 4 bytes of the first word, written before the
+    // arguments since the word overlaps the first of them
+    out.push(push_bytes(function.selector().to_vec()));
+    out.push(push_bytes(vec![0xe0]));
+    out.push(Instruction::Simple(Opcode::SHL));
+    out.push(Instruction::Simple(Opcode::DUP2));
+    out.push(Instruction::Simple(Opcode::MSTORE));
+
+    // [arg, ptr] -> mstore(ptr + offset, arg), leaving ptr
+    for index in 0..args.len() {
+        out.push(Instruction::Simple(Opcode::SWAP1));
+        out.push(Instruction::Simple(Opcode::DUP2));
+        out.push(push_bytes(minimal_bytes(4 + 32 * index as u64)));
+        out.push(Instruction::Simple(Opcode::ADD));
+        out.push(Instruction::Simple(Opcode::MSTORE));
+    }
+
+    // retSize, retOffset, argsSize, argsOffset, [value], address, gas
+    out.push(push_bytes(vec![0x20]));
+    out.push(Instruction::Simple(Opcode::DUP2));
+    out.push(push_bytes(minimal_bytes(args_size)));
+    out.push(Instruction::Simple(Opcode::DUP4));
+    if !function.is_view() {
+        out.push(push_bytes(vec![0]));
+    }
+    emit(target, scope, out)?;
+    out.push(Instruction::Simple(Opcode::GAS));
+    out.push(Instruction::Simple(if function.is_view() {
+        Opcode::STATICCALL
+    } else {
+        Opcode::CALL
+    }));
+
+    let ok = scope.label("call_ok");
+    out.push(Instruction::JumpToIf(ok.clone()));
+    out.push(Instruction::Simple(Opcode::RETURNDATASIZE));
+    out.push(push_bytes(vec![0]));
+    out.push(Instruction::Simple(Opcode::DUP3));
+    out.push(Instruction::Simple(Opcode::RETURNDATACOPY));
+    out.push(Instruction::Simple(Opcode::RETURNDATASIZE));
+    out.push(Instruction::Simple(Opcode::DUP2));
+    out.push(Instruction::Simple(Opcode::REVERT));
+
+    out.push(Instruction::Label(ok));
+    out.push(Instruction::Simple(Opcode::MLOAD));
+    Some(())
+}
+
+/// Call an internal function: `(helper args...)`.
+///
+/// The arguments are written to a fresh frame whose first word saves the
+/// caller's frame pointer, and the function body is inlined with its own
+/// labels. A call with literal arguments can be specialized on them instead,
+/// which is the only way a recursive call compiles.
+fn emit_internal_call(
+    name: &str,
+    function: &InternalFunction,
+    args: &[&Value],
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    if args.len() != function.params.len() {
+        return None;
+    }
+    let body = match &function.body {
+        Value::Pair(pair) if matches!(pair.1, Value::Nil) => &pair.0,
+        _ => return None,
+    };
+
+    let specialized = emit_specialized(name, function, body, args, scope);
+    let generic = emit_generic(name, function, body, args, scope);
+    let code = match (specialized, generic) {
+        (Some(specialized), Some(generic)) if code_size(&specialized) > code_size(&generic) => {
+            generic
+        }
+        (Some(code), _) | (None, Some(code)) => code,
+        (None, None) => return None,
+    };
+    out.extend(code);
+    Some(())
+}
+
+/// Inline the body of an internal function in a frame holding all its
+/// arguments. Recursive calls can't be inlined this way.
+fn emit_generic(
+    name: &str,
+    function: &InternalFunction,
+    body: &Value,
+    args: &[&Value],
+    scope: &Scope,
+) -> Option<Vec<Instruction>> {
+    if scope.inlined.iter().any(|n| n == name) {
+        return None;
+    }
+
+    // Evaluate the arguments in the caller's frame, first argument on top
+    let mut out = Vec::new();
+    for arg in args.iter().rev() {
+        emit(arg, scope, &mut out)?;
+    }
+    out.push(Instruction::Comment(format!(
+        "Inlined internal function {}",
+        name
+    )));
+    emit_inlined(name, &function.params, scope, &mut out, |callee, out| {
+        emit(body, callee, out)
+    })?;
+    Some(out)
+}
+
+/// Inline code in a new frame holding the values of `params`, which are on
+/// the stack with the first on top. `body` emits the code with the scope
+/// that reads `params` from the frame, and has to leave one value.
+pub(super) fn emit_inlined(
+    name: &str,
+    params: &[String],
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+    body: impl FnOnce(&Scope, &mut Vec<Instruction>) -> Option<()>,
+) -> Option<()> {
+    out.extend(memory::alloc(32 * (params.len() as u64 + 1)));
+
+    // [arg, frame] -> mstore(frame + 32 * (index + 1), arg), leaving frame
+    for index in 0..params.len() {
+        out.push(Instruction::Simple(Opcode::SWAP1));
+        out.push(Instruction::Simple(Opcode::DUP2));
+        out.push(push_bytes(minimal_bytes(32 * (index as u64 + 1))));
+        out.push(Instruction::Simple(Opcode::ADD));
+        out.push(Instruction::Simple(Opcode::MSTORE));
+    }
+
+    // Save the caller's frame pointer in the new frame and switch to it
+    out.push(push_bytes(vec![memory::FRAME_POINTER]));
+    out.push(Instruction::Simple(Opcode::MLOAD));
+    out.push(Instruction::Simple(Opcode::DUP2));
+    out.push(Instruction::Simple(Opcode::MSTORE));
+    out.push(push_bytes(vec![memory::FRAME_POINTER]));
+    out.push(Instruction::Simple(Opcode::MSTORE));
+
+    let callee = scope.callee(name, params);
+    body(&callee, out)?;
+
+    // Restore the caller's frame pointer, keeping the result
+    out.push(push_bytes(vec![memory::FRAME_POINTER]));
+    out.push(Instruction::Simple(Opcode::MLOAD));
+    out.push(Instruction::Simple(Opcode::MLOAD));
+    out.push(push_bytes(vec![memory::FRAME_POINTER]));
+    out.push(Instruction::Simple(Opcode::MSTORE));
+    Some(())
+}
+
+/// Push an integer literal; negative values are pushed as their 256-bit two's complement
+pub(super) fn push_integer(n: i64) -> Instruction {
+    if n >= 0 {
+        push_bytes(minimal_bytes(n as u64))
+    } else {
+        let mut bytes = vec![0xff; 24];
+        bytes.extend_from_slice(&n.to_be_bytes());
+        push_bytes(bytes)
+    }
+}
+
+pub(super) fn push_bytes(bytes: Vec<u8>) -> Instruction {
+    Instruction::Push(bytes.len() as u8, bytes)
+}
+
+pub(super) fn minimal_bytes(value: u64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let first = bytes.iter().position(|b| *b != 0).unwrap_or(7);
+    bytes[first..].to_vec()
+}
+
+pub(super) fn list_items(list: &Value) -> Option<Vec<&Value>> {
+    let mut items = Vec::new();
+    let mut current = list;
+    while let Value::Pair(pair) = current {
+        items.push(&pair.0);
+        current = &pair.1;
+    }
+    matches!(current, Value::Nil).then_some(items)
+}
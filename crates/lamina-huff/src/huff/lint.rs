@@ -0,0 +1,98 @@
+// Contract security lints
+//
+// These run over the instructions a public function compiles to, internal
+// functions included since they are inlined, and raise warnings for shapes
+// that are usually bugs in a contract:
+//
+// - `reentrancy`: storage written after an external call that may change state
+//   (anything but STATICCALL). The callee can call back into the contract
+//   before the write, while storage is still stale; write storage before
+//   calling out instead.
+// - `unchecked-call`: an external call whose success flag isn't branched on,
+//   so a failed call goes unnoticed.
+// - `tx-origin`: reading the transaction origin, which lets any contract the
+//   origin calls act on its behalf if it is used for authentication.
+//
+// The instructions are scanned in order without following jumps, so a write
+// on a branch that can't run after the call is still reported.
+
+use lamina::diagnostics::{Warnings, REENTRANCY, TX_ORIGIN, UNCHECKED_CALL};
+
+use super::bytecode::Instruction;
+use super::opcodes::Opcode;
+
+/// Raise the security warnings for a function's compiled instructions
+pub fn lint(function: &str, instructions: &[Instruction], warnings: &mut Warnings) {
+    let code: Vec<&Instruction> = instructions
+        .iter()
+        .filter(|instruction| !matches!(instruction, Instruction::Comment(_)))
+        .collect();
+
+    let first_call = code.iter().position(|instruction| {
+        matches!(
+            instruction,
+            Instruction::Simple(Opcode::CALL | Opcode::CALLCODE | Opcode::DELEGATECALL)
+        )
+    });
+    if let Some(call) = first_call {
+        if code[call..]
+            .iter()
+            .any(|instruction| matches!(instruction, Instruction::Simple(Opcode::SSTORE)))
+        {
+            warnings.warn(
+                REENTRANCY,
+                format!(
+                    "{} writes storage after an external call, which can reenter it first",
+                    function
+                ),
+            );
+        }
+    }
+
+    let unchecked = code
+        .iter()
+        .enumerate()
+        .any(|(index, instruction)| is_call(instruction) && !is_checked(&code[index + 1..]));
+    if unchecked {
+        warnings.warn(
+            UNCHECKED_CALL,
+            format!("{} ignores whether an external call succeeded", function),
+        );
+    }
+
+    if code
+        .iter()
+        .any(|instruction| matches!(instruction, Instruction::Simple(Opcode::ORIGIN)))
+    {
+        warnings.warn(
+            TX_ORIGIN,
+            format!(
+                "{} reads tx-origin, use caller to authenticate the sender",
+                function
+            ),
+        );
+    }
+}
+
+/// Whether an instruction calls another contract
+fn is_call(instruction: &Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::Simple(
+            Opcode::CALL | Opcode::CALLCODE | Opcode::DELEGATECALL | Opcode::STATICCALL
+        )
+    )
+}
+
+/// Whether the success flag a call leaves on the stack is branched on
+fn is_checked(rest: &[&Instruction]) -> bool {
+    matches!(
+        rest,
+        [Instruction::JumpToIf(_), ..]
+            | [
+                Instruction::Simple(Opcode::ISZERO),
+                Instruction::JumpToIf(_),
+                ..
+            ]
+    )
+}
@@ -0,0 +1,80 @@
+// Memory layout of generated code
+//
+// Generated code follows the Solidity convention: 0x00-0x3f is scratch space,
+// 0x40 holds the free memory pointer and allocations start at 0x80. The first
+// scratch word points at the arguments of the internal call being run. Memory is
+// never freed. Anything built in memory, such as calldata for external calls,
+// return data, event data or hash inputs, takes a fresh region from `alloc`,
+// so nested expressions can't overwrite each other's buffers.
+
+use super::bytecode::Instruction;
+use super::expression::{minimal_bytes, push_bytes};
+use super::opcodes::Opcode;
+
+/// Memory offset holding the frame of the innermost internal function call
+pub const FRAME_POINTER: u8 = 0x00;
+
+/// Memory offset of the free memory pointer
+pub const FREE_MEMORY_POINTER: u8 = 0x40;
+
+/// Where the first allocation starts
+pub const HEAP_START: u8 = 0x80;
+
+/// Point the free memory pointer at the start of the heap; runs before dispatch
+pub fn init() -> Vec<Instruction> {
+    vec![
+        push_bytes(vec![HEAP_START]),
+        push_bytes(vec![FREE_MEMORY_POINTER]),
+        Instruction::Simple(Opcode::MSTORE),
+    ]
+}
+
+/// Allocate `size` bytes, rounded up to whole words, leaving the start of the
+/// region on the stack
+pub fn alloc(size: u64) -> Vec<Instruction> {
+    let size = size.div_ceil(32) * 32;
+    vec![
+        push_bytes(vec![FREE_MEMORY_POINTER]),
+        Instruction::Simple(Opcode::MLOAD),
+        Instruction::Simple(Opcode::DUP1),
+        push_bytes(minimal_bytes(size)),
+        Instruction::Simple(Opcode::ADD),
+        push_bytes(vec![FREE_MEMORY_POINTER]),
+        Instruction::Simple(Opcode::MSTORE),
+    ]
+}
+
+/// Allocate as many bytes as the size on top of the stack, rounded up to whole
+/// words, replacing the size with the start of the region
+pub fn alloc_dynamic() -> Vec<Instruction> {
+    vec![
+        push_bytes(vec![0x1f]),
+        Instruction::Simple(Opcode::ADD),
+        push_bytes(vec![0x1f]),
+        Instruction::Simple(Opcode::NOT),
+        Instruction::Simple(Opcode::AND),
+        // [size] -> mstore(0x40, ptr + size), leaving ptr
+        push_bytes(vec![FREE_MEMORY_POINTER]),
+        Instruction::Simple(Opcode::MLOAD),
+        Instruction::Simple(Opcode::DUP1),
+        Instruction::Simple(Opcode::SWAP2),
+        Instruction::Simple(Opcode::ADD),
+        push_bytes(vec![FREE_MEMORY_POINTER]),
+        Instruction::Simple(Opcode::MSTORE),
+    ]
+}
+
+/// Return the word on top of the stack to the caller
+pub fn return_word() -> Vec<Instruction> {
+    let mut instructions = alloc(32);
+    // [value, ptr] -> mstore(ptr, value), leaving ptr
+    instructions.extend([
+        Instruction::Simple(Opcode::SWAP1),
+        Instruction::Simple(Opcode::DUP2),
+        Instruction::Simple(Opcode::MSTORE),
+        push_bytes(vec![32]),
+        Instruction::Simple(Opcode::SWAP1),
+        Instruction::Simple(Opcode::RETURN),
+    ]);
+    instructions
+}
@@ -1,10 +1,33 @@
+pub mod array;
+pub mod artifact;
+pub mod assembler;
+mod assertions;
 pub mod bytecode;
 mod compiler;
-mod opcodes;
-mod types;
+pub mod create2;
+mod docs;
+mod enums;
+mod expression;
+pub mod lint;
+pub mod memory;
+pub mod mutability;
+pub mod opcodes;
+mod ranges;
+pub mod safemath;
+mod specialize;
+pub mod strings;
+pub mod switch;
+mod tails;
 
+use lamina::diagnostics::Diagnostic;
 use lamina::error::Error;
 use lamina::value::Value;
+use std::path::Path;
+
+pub use artifact::{Artifact, Symbol};
+pub use assembler::{MacroSize, MAX_CODE_SIZE};
+pub use bytecode::HuffContract;
+pub use compiler::CompileOptions;
 
 /// Compiles a Lamina expression to Huff code.
 ///
@@ -20,6 +43,32 @@ pub fn compile(expr: &Value, contract_name: &str) -> Result<String, Error> {
     compiler::compile(expr, contract_name)
 }
 
+/// Compiles a Lamina expression to Huff code with the given options, which
+/// give the generated comments the source lines of each function when
+/// `options.source` is set.
+pub fn compile_with_options(
+    expr: &Value,
+    contract_name: &str,
+    options: &CompileOptions,
+) -> Result<String, Error> {
+    compiler::compile_with_options(expr, contract_name, options)
+}
+
+/// Lowers a Lamina expression to Huff macros, before they are printed or
+/// assembled.
+///
+/// # Arguments
+///
+/// * `expr` - The Lamina expression to compile
+/// * `contract_name` - The name of the contract to generate
+///
+/// # Returns
+///
+/// The contract, with its dispatcher and the macros it reaches
+pub fn compile_contract(expr: &Value, contract_name: &str) -> Result<HuffContract, Error> {
+    compiler::compile_contract(expr, contract_name)
+}
+
 /// Compiles and outputs Huff code to a file.
 ///
 /// # Arguments
@@ -37,5 +86,114 @@ pub fn compile_to_file(expr: &Value, contract_name: &str, output_path: &str) ->
     Ok(())
 }
 
+/// Compiles a Lamina expression to a Foundry-compatible artifact.
+///
+/// # Arguments
+///
+/// * `expr` - The Lamina expression to compile
+/// * `contract_name` - The name of the contract to generate
+///
+/// # Returns
+///
+/// The artifact holding the ABI, creation and runtime bytecode, and selector table
+pub fn compile_artifact(expr: &Value, contract_name: &str) -> Result<Artifact, Error> {
+    compiler::compile_artifact(expr, contract_name)
+}
+
+/// Compiles a Lamina expression to an artifact with the given options.
+///
+/// # Arguments
+///
+/// * `expr` - The Lamina expression to compile
+/// * `contract_name` - The name of the contract to generate
+/// * `options` - Compilation options, e.g. whether warnings are errors
+///
+/// # Returns
+///
+/// The artifact, including the warnings raised while compiling
+pub fn compile_artifact_with_options(
+    expr: &Value,
+    contract_name: &str,
+    options: &CompileOptions,
+) -> Result<Artifact, Error> {
+    compiler::compile_artifact_with_options(expr, contract_name, options)
+}
+
+/// Collects the warnings compiling a Lamina expression raises.
+///
+/// # Arguments
+///
+/// * `expr` - The Lamina expression to check
+/// * `contract_name` - The name of the contract to generate
+///
+/// # Returns
+///
+/// The warnings, minus the ones the program allows with `#:allow`
+pub fn warnings(expr: &Value, contract_name: &str) -> Result<Vec<Diagnostic>, Error> {
+    compiler::warnings(expr, contract_name)
+}
+
+/// Compiles a contract and saves its Huff source and build artifacts.
+///
+/// Writes `<Name>.huff`, `<Name>.abi.json`, the symbol table
+/// `<Name>.symbols.json` and the Foundry artifact `<Name>.json` (abi,
+/// bytecode, deployedBytecode, methodIdentifiers) into `output_dir`, creating
+/// it if needed.
+///
+/// # Arguments
+///
+/// * `expr` - The Lamina expression to compile
+/// * `contract_name` - The name of the contract to generate
+/// * `output_dir` - Directory where the files should be saved
+///
+/// # Returns
+///
+/// The compiled artifact
+pub fn compile_and_save(
+    expr: &Value,
+    contract_name: &str,
+    output_dir: &Path,
+) -> Result<Artifact, Error> {
+    compile_and_save_with_options(expr, contract_name, output_dir, &CompileOptions::default())
+}
+
+/// Compiles a contract with the given options and saves its Huff source and
+/// build artifacts, as `compile_and_save` does.
+///
+/// # Arguments
+///
+/// * `expr` - The Lamina expression to compile
+/// * `contract_name` - The name of the contract to generate
+/// * `output_dir` - Directory where the files should be saved
+/// * `options` - Compilation options, e.g. whether warnings are errors
+///
+/// # Returns
+///
+/// The compiled artifact, including the warnings raised while compiling
+pub fn compile_and_save_with_options(
+    expr: &Value,
+    contract_name: &str,
+    output_dir: &Path,
+    options: &CompileOptions,
+) -> Result<Artifact, Error> {
+    let artifact = compile_artifact_with_options(expr, contract_name, options)?;
+    let huff_code = compile_with_options(expr, contract_name, options)?;
+
+    let write = |file: String, contents: String| {
+        std::fs::write(output_dir.join(file), contents).map_err(|e| Error::IO(e.to_string()))
+    };
+
+    std::fs::create_dir_all(output_dir).map_err(|e| Error::IO(e.to_string()))?;
+    write(format!("{}.huff", contract_name), huff_code)?;
+    write(format!("{}.abi.json", contract_name), artifact.abi_json())?;
+    write(
+        format!("{}.symbols.json", contract_name),
+        artifact.symbols_json(),
+    )?;
+    write(format!("{}.json", contract_name), artifact.to_json())?;
+
+    Ok(artifact)
+}
+
 // Re-export the function selector calculation
 pub use bytecode::calculate_function_selector;
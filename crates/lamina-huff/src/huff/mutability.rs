@@ -0,0 +1,104 @@
+// State mutability of compiled functions
+//
+// A function's mutability is inferred from the opcodes its macro compiles to,
+// internal calls included since they are inlined: reading storage or the
+// environment makes it `view`, writing storage, logging or making a call that
+// may write makes it `nonpayable`, and reading the value sent makes it
+// `payable`. A `#:mutability` annotation may declare a less restrictive level
+// than inferred, but not a more restrictive one. A `view` function that wrote
+// storage would revert when called through STATICCALL.
+
+use std::fmt;
+
+use super::bytecode::Instruction;
+use super::opcodes::Opcode;
+
+/// Solidity state mutability, from most to least restrictive
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Mutability {
+    Pure,
+    View,
+    NonPayable,
+    Payable,
+}
+
+impl Mutability {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "pure" => Some(Mutability::Pure),
+            "view" => Some(Mutability::View),
+            "nonpayable" => Some(Mutability::NonPayable),
+            "payable" => Some(Mutability::Payable),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Mutability::Pure => "pure",
+            Mutability::View => "view",
+            Mutability::NonPayable => "nonpayable",
+            Mutability::Payable => "payable",
+        }
+    }
+}
+
+impl fmt::Display for Mutability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The least restrictive level `instructions` need, with the reason for it
+pub fn infer(instructions: &[Instruction]) -> (Mutability, Option<&'static str>) {
+    instructions
+        .iter()
+        .filter_map(|instruction| match instruction {
+            Instruction::Simple(op) => requirement(op),
+            _ => None,
+        })
+        .max_by_key(|(mutability, _)| *mutability)
+        .map_or((Mutability::Pure, None), |(mutability, reason)| {
+            (mutability, Some(reason))
+        })
+}
+
+fn requirement(op: &Opcode) -> Option<(Mutability, &'static str)> {
+    let requirement = match op {
+        Opcode::CALLVALUE => (Mutability::Payable, "reads the value sent"),
+        Opcode::SSTORE | Opcode::TSTORE => (Mutability::NonPayable, "writes storage"),
+        Opcode::LOG0 | Opcode::LOG1 | Opcode::LOG2 | Opcode::LOG3 | Opcode::LOG4 => {
+            (Mutability::NonPayable, "emits events")
+        }
+        Opcode::CALL | Opcode::CALLCODE | Opcode::DELEGATECALL => (
+            Mutability::NonPayable,
+            "calls a function that may write state",
+        ),
+        Opcode::CREATE | Opcode::CREATE2 | Opcode::SELFDESTRUCT => {
+            (Mutability::NonPayable, "creates or destroys contracts")
+        }
+        Opcode::SLOAD | Opcode::TLOAD => (Mutability::View, "reads storage"),
+        Opcode::STATICCALL => (Mutability::View, "calls a view function"),
+        Opcode::ADDRESS
+        | Opcode::BALANCE
+        | Opcode::SELFBALANCE
+        | Opcode::ORIGIN
+        | Opcode::CALLER
+        | Opcode::GASPRICE
+        | Opcode::EXTCODESIZE
+        | Opcode::EXTCODECOPY
+        | Opcode::EXTCODEHASH
+        | Opcode::BLOCKHASH
+        | Opcode::COINBASE
+        | Opcode::TIMESTAMP
+        | Opcode::NUMBER
+        | Opcode::DIFFICULTY
+        | Opcode::GASLIMIT
+        | Opcode::CHAINID
+        | Opcode::BASEFEE
+        | Opcode::BLOBHASH
+        | Opcode::BLOBBASEFEE => (Mutability::View, "reads the environment"),
+        _ => return None,
+    };
+    Some(requirement)
+}
@@ -1,5 +1,8 @@
+use lamina::evm::Hardfork;
+
 /// EVM Opcodes used in Huff
 #[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Opcode {
     // Stack operations
     PUSH0,
@@ -9,9 +12,14 @@ pub enum Opcode {
     POP,
     DUP1,
     DUP2,
+    DUP3,
+    DUP4,
+    DUP5,
+    DUP6,
     DUP16,
     SWAP1,
     SWAP2,
+    SWAP3,
     SWAP16,
 
     // Arithmetic operations
@@ -25,6 +33,7 @@ pub enum Opcode {
     ADDMOD,
     MULMOD,
     EXP,
+    SIGNEXTEND,
 
     // Comparison operations
     LT,
@@ -48,15 +57,19 @@ pub enum Opcode {
     MSTORE,
     MSTORE8,
     MSIZE,
+    MCOPY,
 
     // Storage operations
     SLOAD,
     SSTORE,
+    TLOAD,
+    TSTORE,
 
     // Program counter operations
     JUMP,
     JUMPI,
     PC,
+    GAS,
     JUMPDEST,
 
     // Environment operations
@@ -87,6 +100,8 @@ pub enum Opcode {
     CHAINID,
     SELFBALANCE,
     BASEFEE,
+    BLOBHASH,
+    BLOBBASEFEE,
 
     // Control flow operations
     STOP,
@@ -135,9 +150,14 @@ impl Opcode {
                     Opcode::POP => "pop",
                     Opcode::DUP1 => "dup1",
                     Opcode::DUP2 => "dup2",
+                    Opcode::DUP3 => "dup3",
+                    Opcode::DUP4 => "dup4",
+                    Opcode::DUP5 => "dup5",
+                    Opcode::DUP6 => "dup6",
                     Opcode::DUP16 => "dup16",
                     Opcode::SWAP1 => "swap1",
                     Opcode::SWAP2 => "swap2",
+                    Opcode::SWAP3 => "swap3",
                     Opcode::SWAP16 => "swap16",
 
                     // Arithmetic operations
@@ -151,6 +171,7 @@ impl Opcode {
                     Opcode::ADDMOD => "addmod",
                     Opcode::MULMOD => "mulmod",
                     Opcode::EXP => "exp",
+                    Opcode::SIGNEXTEND => "signextend",
 
                     // Comparison operations
                     Opcode::LT => "lt",
@@ -174,15 +195,19 @@ impl Opcode {
                     Opcode::MSTORE => "mstore",
                     Opcode::MSTORE8 => "mstore8",
                     Opcode::MSIZE => "msize",
+                    Opcode::MCOPY => "mcopy",
 
                     // Storage operations
                     Opcode::SLOAD => "sload",
                     Opcode::SSTORE => "sstore",
+                    Opcode::TLOAD => "tload",
+                    Opcode::TSTORE => "tstore",
 
                     // Program counter operations
                     Opcode::JUMP => "jump",
                     Opcode::JUMPI => "jumpi",
                     Opcode::PC => "pc",
+                    Opcode::GAS => "gas",
                     Opcode::JUMPDEST => "jumpdest",
 
                     // Environment operations
@@ -213,6 +238,8 @@ impl Opcode {
                     Opcode::CHAINID => "chainid",
                     Opcode::SELFBALANCE => "selfbalance",
                     Opcode::BASEFEE => "basefee",
+                    Opcode::BLOBHASH => "blobhash",
+                    Opcode::BLOBBASEFEE => "blobbasefee",
 
                     // Control flow operations
                     Opcode::STOP => "stop",
@@ -246,6 +273,125 @@ impl Opcode {
             }
         }
     }
+
+    /// Returns the byte value of the opcode, or `None` for Huff constants
+    pub fn to_byte(&self) -> Option<u8> {
+        let byte = match self {
+            Opcode::PUSH0 => 0x5f,
+            Opcode::PUSH1 => 0x60,
+            Opcode::PUSH2 => 0x61,
+            Opcode::PUSH32 => 0x7f,
+            Opcode::POP => 0x50,
+            Opcode::DUP1 => 0x80,
+            Opcode::DUP2 => 0x81,
+            Opcode::DUP3 => 0x82,
+            Opcode::DUP4 => 0x83,
+            Opcode::DUP5 => 0x84,
+            Opcode::DUP6 => 0x85,
+            Opcode::DUP16 => 0x8f,
+            Opcode::SWAP1 => 0x90,
+            Opcode::SWAP2 => 0x91,
+            Opcode::SWAP3 => 0x92,
+            Opcode::SWAP16 => 0x9f,
+            Opcode::ADD => 0x01,
+            Opcode::SUB => 0x03,
+            Opcode::MUL => 0x02,
+            Opcode::DIV => 0x04,
+            Opcode::SDIV => 0x05,
+            Opcode::MOD => 0x06,
+            Opcode::SMOD => 0x07,
+            Opcode::ADDMOD => 0x08,
+            Opcode::MULMOD => 0x09,
+            Opcode::EXP => 0x0a,
+            Opcode::SIGNEXTEND => 0x0b,
+            Opcode::LT => 0x10,
+            Opcode::GT => 0x11,
+            Opcode::SLT => 0x12,
+            Opcode::SGT => 0x13,
+            Opcode::EQ => 0x14,
+            Opcode::ISZERO => 0x15,
+            Opcode::AND => 0x16,
+            Opcode::OR => 0x17,
+            Opcode::XOR => 0x18,
+            Opcode::NOT => 0x19,
+            Opcode::SHL => 0x1b,
+            Opcode::SHR => 0x1c,
+            Opcode::SAR => 0x1d,
+            Opcode::MLOAD => 0x51,
+            Opcode::MSTORE => 0x52,
+            Opcode::MSTORE8 => 0x53,
+            Opcode::MSIZE => 0x59,
+            Opcode::MCOPY => 0x5e,
+            Opcode::SLOAD => 0x54,
+            Opcode::SSTORE => 0x55,
+            Opcode::TLOAD => 0x5c,
+            Opcode::TSTORE => 0x5d,
+            Opcode::JUMP => 0x56,
+            Opcode::JUMPI => 0x57,
+            Opcode::PC => 0x58,
+            Opcode::GAS => 0x5a,
+            Opcode::JUMPDEST => 0x5b,
+            Opcode::ADDRESS => 0x30,
+            Opcode::BALANCE => 0x31,
+            Opcode::ORIGIN => 0x32,
+            Opcode::CALLER => 0x33,
+            Opcode::CALLVALUE => 0x34,
+            Opcode::CALLDATALOAD => 0x35,
+            Opcode::CALLDATASIZE => 0x36,
+            Opcode::CALLDATACOPY => 0x37,
+            Opcode::CODESIZE => 0x38,
+            Opcode::CODECOPY => 0x39,
+            Opcode::GASPRICE => 0x3a,
+            Opcode::EXTCODESIZE => 0x3b,
+            Opcode::EXTCODECOPY => 0x3c,
+            Opcode::RETURNDATASIZE => 0x3d,
+            Opcode::RETURNDATACOPY => 0x3e,
+            Opcode::EXTCODEHASH => 0x3f,
+            Opcode::BLOCKHASH => 0x40,
+            Opcode::COINBASE => 0x41,
+            Opcode::TIMESTAMP => 0x42,
+            Opcode::NUMBER => 0x43,
+            Opcode::DIFFICULTY => 0x44,
+            Opcode::GASLIMIT => 0x45,
+            Opcode::CHAINID => 0x46,
+            Opcode::SELFBALANCE => 0x47,
+            Opcode::BASEFEE => 0x48,
+            Opcode::BLOBHASH => 0x49,
+            Opcode::BLOBBASEFEE => 0x4a,
+            Opcode::STOP => 0x00,
+            Opcode::RETURN => 0xf3,
+            Opcode::REVERT => 0xfd,
+            Opcode::INVALID => 0xfe,
+            Opcode::SELFDESTRUCT => 0xff,
+            Opcode::CALL => 0xf1,
+            Opcode::CALLCODE => 0xf2,
+            Opcode::DELEGATECALL => 0xf4,
+            Opcode::STATICCALL => 0xfa,
+            Opcode::CREATE => 0xf0,
+            Opcode::CREATE2 => 0xf5,
+            Opcode::LOG0 => 0xa0,
+            Opcode::LOG1 => 0xa1,
+            Opcode::LOG2 => 0xa2,
+            Opcode::LOG3 => 0xa3,
+            Opcode::LOG4 => 0xa4,
+            Opcode::SHA3 => 0x20,
+            Opcode::CONSTANT(_) => return None,
+        };
+        Some(byte)
+    }
+
+    /// The hardfork that added the opcode, for the ones newer than the
+    /// oldest hardfork there is a preset for
+    pub fn introduced_in(&self) -> Option<Hardfork> {
+        match self {
+            Opcode::TLOAD
+            | Opcode::TSTORE
+            | Opcode::MCOPY
+            | Opcode::BLOBHASH
+            | Opcode::BLOBBASEFEE => Some(Hardfork::Cancun),
+            _ => None,
+        }
+    }
 }
 
 /// Helper function to convert Opcode to Huff representation
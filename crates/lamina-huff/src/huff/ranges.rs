@@ -0,0 +1,346 @@
+// Value ranges of expressions
+//
+// An interval analysis over a function's expressions: each expression gets
+// the unsigned range of 256-bit words it can evaluate to. Ranges come from
+//
+// - integer literals, comparisons (0 or 1) and addresses (160 bits),
+// - masks, `(bitwise-and x 0xff)`, and right shifts,
+// - guards: the `#:requires` clauses of `define-with-contract` hold in the
+//   body, and a `cond` clause's test holds in its body. A guard narrows a
+//   parameter it compares with a literal, as in `(< amount 1000)`.
+//
+// Everything else, parameters included, can be any word. The compiler drops
+// the check of `add-checked`, `sub-checked` and `mul-checked` when the ranges
+// of the operands show it can't fail, and warns where the ranges show that
+// wrapping `+`, `-` or `*` can overflow, or that a checked operation always
+// reverts. Guards only count while assertions are compiled.
+
+use std::collections::HashMap;
+
+use lamina::diagnostics::{Warnings, OVERFLOW};
+use lamina::encoding::encode_hex;
+use lamina::evm::Word;
+use lamina::value::{NumberKind, Value};
+
+use super::expression::list_items;
+
+/// The words from `lo` to `hi`, inclusive
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Interval {
+    pub lo: Word,
+    pub hi: Word,
+}
+
+impl Interval {
+    pub const FULL: Interval = Interval {
+        lo: Word::ZERO,
+        hi: Word::MAX,
+    };
+
+    pub fn exactly(word: Word) -> Self {
+        Interval { lo: word, hi: word }
+    }
+
+    pub fn up_to(hi: Word) -> Self {
+        Interval { lo: Word::ZERO, hi }
+    }
+
+    /// Whether something is known about the value
+    pub fn is_bounded(&self) -> bool {
+        *self != Interval::FULL
+    }
+
+    fn intersect(self, other: Interval) -> Option<Interval> {
+        let lo = self.lo.max(other.lo);
+        let hi = self.hi.min(other.hi);
+        (lo <= hi).then_some(Interval { lo, hi })
+    }
+}
+
+/// What the guards in force say about parameters
+pub(crate) type Ranges = HashMap<String, Interval>;
+
+/// The range of `expr` given the ranges of the parameters
+pub(crate) fn range(expr: &Value, ranges: &Ranges) -> Interval {
+    match expr {
+        Value::Number(NumberKind::Integer(n)) if *n >= 0 => Interval::exactly(Word::from_i64(*n)),
+        Value::Address(_) => Interval::up_to(address_max()),
+        Value::Symbol(name) => ranges.get(name).copied().unwrap_or(Interval::FULL),
+        Value::Pair(pair) => {
+            let (Value::Symbol(op), Some(args)) = (&pair.0, list_items(&pair.1)) else {
+                return Interval::FULL;
+            };
+            call_range(op, &args, ranges)
+        }
+        _ => Interval::FULL,
+    }
+}
+
+fn call_range(op: &str, args: &[&Value], ranges: &Ranges) -> Interval {
+    let operand = |index: usize| range(args[index], ranges);
+    match (op, args.len()) {
+        ("<" | ">" | "=" | "s<", 2) => Interval::up_to(Word::ONE),
+        ("caller" | "tx-origin", 0) => Interval::up_to(address_max()),
+        ("bitwise-and", 2) => Interval::up_to(operand(0).hi.min(operand(1).hi)),
+        ("bitwise-or" | "bitwise-xor", 2) => {
+            Interval::up_to(fill_below(operand(0).hi.max(operand(1).hi)))
+        }
+        ("arithmetic-shift", 2) => match args[1] {
+            Value::Number(NumberKind::Integer(count)) if *count < 0 => {
+                let bits = count.unsigned_abs().min(256) as u32;
+                let x = operand(0);
+                Interval {
+                    lo: x.lo.shift_right(bits),
+                    hi: x.hi.shift_right(bits),
+                }
+            }
+            _ => Interval::FULL,
+        },
+        // Wrapping operations stay in range when the extremes don't wrap
+        ("+", 2) => add(operand(0), operand(1)).unwrap_or(Interval::FULL),
+        ("-", 2) => sub(operand(0), operand(1)).unwrap_or(Interval::FULL),
+        ("*", 2) => mul(operand(0), operand(1)).unwrap_or(Interval::FULL),
+        ("/" | "div-floor", 2) => div(operand(0), operand(1)),
+        // A checked operation that returns didn't overflow
+        ("add-checked", 2) => {
+            let (x, y) = (operand(0), operand(1));
+            Interval {
+                lo: x.lo.checked_add(y.lo).unwrap_or(Word::MAX),
+                hi: x.hi.checked_add(y.hi).unwrap_or(Word::MAX),
+            }
+        }
+        ("sub-checked", 2) => {
+            let (x, y) = (operand(0), operand(1));
+            Interval {
+                lo: x.lo.checked_sub(y.hi).unwrap_or(Word::ZERO),
+                hi: x.hi.checked_sub(y.lo).unwrap_or(Word::ZERO),
+            }
+        }
+        ("mul-checked", 2) => {
+            let (x, y) = (operand(0), operand(1));
+            Interval {
+                lo: x.lo.checked_mul(y.lo).unwrap_or(Word::MAX),
+                hi: x.hi.checked_mul(y.hi).unwrap_or(Word::MAX),
+            }
+        }
+        ("addmod" | "mulmod", 3) => {
+            let modulus = operand(2).hi;
+            Interval::up_to(modulus.checked_sub(Word::ONE).unwrap_or(Word::ZERO))
+        }
+        _ => Interval::FULL,
+    }
+}
+
+fn add(x: Interval, y: Interval) -> Option<Interval> {
+    Some(Interval {
+        lo: x.lo.checked_add(y.lo)?,
+        hi: x.hi.checked_add(y.hi)?,
+    })
+}
+
+fn sub(x: Interval, y: Interval) -> Option<Interval> {
+    Some(Interval {
+        lo: x.lo.checked_sub(y.hi)?,
+        hi: x.hi.checked_sub(y.lo)?,
+    })
+}
+
+fn mul(x: Interval, y: Interval) -> Option<Interval> {
+    Some(Interval {
+        lo: x.lo.checked_mul(y.lo)?,
+        hi: x.hi.checked_mul(y.hi)?,
+    })
+}
+
+/// DIV, which gives 0 for a zero divisor
+fn div(x: Interval, y: Interval) -> Interval {
+    let lo = if y.lo.is_zero() {
+        Word::ZERO
+    } else {
+        x.lo.div_rem(y.hi).0
+    };
+    let hi = x.hi.div_rem(y.lo.max(Word::ONE)).0;
+    Interval { lo, hi }
+}
+
+fn address_max() -> Word {
+    Word::MAX.shift_right(96)
+}
+
+/// `word` with every bit below its highest set bit set as well
+fn fill_below(word: Word) -> Word {
+    let mut filled = word;
+    for bits in [1, 2, 4, 8, 16, 32, 64, 128] {
+        filled = filled | filled.shift_right(bits);
+    }
+    filled
+}
+
+/// Narrow `ranges` by what holds when `test` is true
+pub(crate) fn assume(test: &Value, ranges: &mut Ranges) {
+    let Some(items) = list_items(test) else {
+        return;
+    };
+    let (op, left, right) = match items.as_slice() {
+        [Value::Symbol(op), left, right] => (op.as_str(), *left, *right),
+        _ => return,
+    };
+    let literal = |value: &Value| match value {
+        Value::Number(NumberKind::Integer(n)) if *n >= 0 => Some(Word::from_i64(*n)),
+        _ => None,
+    };
+
+    // Put the parameter on the left: `(< 10 x)` is `(> x 10)`
+    let (name, op, bound) = match (left, right) {
+        (Value::Symbol(name), other) => match literal(other) {
+            Some(bound) => (name, op, bound),
+            None => return,
+        },
+        (other, Value::Symbol(name)) => match (literal(other), op) {
+            (Some(bound), "<") => (name, ">", bound),
+            (Some(bound), ">") => (name, "<", bound),
+            (Some(bound), "=") => (name, "=", bound),
+            _ => return,
+        },
+        _ => return,
+    };
+
+    let known = match op {
+        "<" => match bound.checked_sub(Word::ONE) {
+            Some(hi) => Interval::up_to(hi),
+            None => return,
+        },
+        ">" => match bound.checked_add(Word::ONE) {
+            Some(lo) => Interval { lo, hi: Word::MAX },
+            None => return,
+        },
+        "=" => Interval::exactly(bound),
+        _ => return,
+    };
+    let current = ranges.get(name).copied().unwrap_or(Interval::FULL);
+    // A guard that contradicts what is known leaves the body unreachable;
+    // keep the old range rather than reason about dead code
+    if let Some(narrowed) = current.intersect(known) {
+        ranges.insert(name.clone(), narrowed);
+    }
+}
+
+/// Whether the checked operation `op` on `args` can never fail
+pub(crate) fn cannot_fail(op: &str, args: &[&Value], ranges: &Ranges) -> bool {
+    let operands: Vec<Interval> = args.iter().map(|arg| range(arg, ranges)).collect();
+    match (op, operands.as_slice()) {
+        ("add-checked", [x, y]) => add(*x, *y).is_some(),
+        ("sub-checked", [x, y]) => sub(*x, *y).is_some(),
+        ("mul-checked", [x, y]) => mul(*x, *y).is_some(),
+        ("div-floor", [_, y]) => !y.lo.is_zero(),
+        _ => false,
+    }
+}
+
+/// Warn about arithmetic in `expr`, part of `function`, that the ranges show
+/// can overflow or always reverts
+pub(crate) fn check_overflow(
+    function: &str,
+    expr: &Value,
+    ranges: &Ranges,
+    warnings: &mut Warnings,
+) {
+    let Value::Pair(pair) = expr else {
+        return;
+    };
+    let op = match &pair.0 {
+        Value::Symbol(op) => op.as_str(),
+        head => {
+            check_overflow(function, head, ranges, warnings);
+            ""
+        }
+    };
+    let args = list_items(&pair.1).unwrap_or_default();
+
+    match op {
+        "quote" => return,
+        "cond" => {
+            // Each clause body runs knowing its test held
+            for clause in &args {
+                let Some(items) = list_items(clause) else {
+                    continue;
+                };
+                let mut clause_ranges = ranges.clone();
+                if let Some(test) = items.first() {
+                    check_overflow(function, test, ranges, warnings);
+                    assume(test, &mut clause_ranges);
+                }
+                for body in items.iter().skip(1) {
+                    check_overflow(function, body, &clause_ranges, warnings);
+                }
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    let operands: Vec<Interval> = args.iter().map(|arg| range(arg, ranges)).collect();
+    if let ("+" | "-" | "*", [x, y]) = (op, operands.as_slice()) {
+        // Unbounded operands could be anything, which says nothing about
+        // this program; only ranges the program sets are reported
+        if x.is_bounded() && y.is_bounded() {
+            let wraps = match op {
+                "+" => add(*x, *y).is_none(),
+                "-" => sub(*x, *y).is_none(),
+                _ => mul(*x, *y).is_none(),
+            };
+            if wraps {
+                let (left, right) = if op == "-" {
+                    (show(x.lo), show(y.hi))
+                } else {
+                    (show(x.hi), show(y.hi))
+                };
+                warnings.warn(
+                    OVERFLOW,
+                    format!(
+                        "{} in {} can {}: the operands reach {} and {}; use {} to revert instead",
+                        expr,
+                        function,
+                        if op == "-" { "underflow" } else { "overflow" },
+                        left,
+                        right,
+                        match op {
+                            "+" => "add-checked",
+                            "-" => "sub-checked",
+                            _ => "mul-checked",
+                        }
+                    ),
+                );
+            }
+        }
+    }
+    if let ("add-checked" | "sub-checked" | "mul-checked", [x, y]) = (op, operands.as_slice()) {
+        let always = match op {
+            "add-checked" => x.lo.checked_add(y.lo).is_none(),
+            "sub-checked" => x.hi < y.lo,
+            _ => x.lo.checked_mul(y.lo).is_none(),
+        };
+        if always {
+            warnings.warn(
+                OVERFLOW,
+                format!("{} in {} always overflows and reverts", expr, function),
+            );
+        }
+    }
+
+    for arg in &args {
+        check_overflow(function, arg, ranges, warnings);
+    }
+}
+
+/// A bound in decimal when small, otherwise in hex
+fn show(word: Word) -> String {
+    match word.to_i64() {
+        Some(n) if n >= 0 => n.to_string(),
+        _ => {
+            let bytes = word.to_be_bytes();
+            let start = bytes.iter().position(|byte| *byte != 0).unwrap_or(31);
+            encode_hex(&bytes[start..])
+        }
+    }
+}
@@ -0,0 +1,104 @@
+// Lowering of `(lamina evm safemath)`
+//
+// Each operation compiles to the overflow check Solidity's checked arithmetic
+// uses, rather than to a generic comparison of the wrapped result:
+//
+//   add-checked x y:  revert if x > not(y), then ADD
+//   sub-checked x y:  revert if x < y, then SUB
+//   mul-checked x y:  p := MUL; revert unless x == 0 or p / x == y
+//   div-floor x y:    revert if y == 0, then DIV
+//
+// `addmod` and `mulmod` are single opcodes, which can't overflow. A failed
+// check reverts with no data, like an out-of-bounds array index. A check the
+// ranges of the operands show can't fail is left out.
+
+use lamina::value::Value;
+
+use super::bytecode::Instruction;
+use super::expression::{emit, push_bytes, Scope};
+use super::opcodes::Opcode;
+use super::ranges::cannot_fail;
+
+/// Compile a call to one of the `SAFEMATH_PRIMITIVES`
+pub(crate) fn emit_safemath_op(
+    op: &str,
+    args: &[&Value],
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    // Operands are pushed last first, so the first ends up on top
+    for arg in args.iter().rev() {
+        emit(arg, scope, out)?;
+    }
+
+    if cannot_fail(op, args, &scope.ranges.borrow()) {
+        let opcode = match op {
+            "add-checked" => Opcode::ADD,
+            "sub-checked" => Opcode::SUB,
+            "mul-checked" => Opcode::MUL,
+            _ => Opcode::DIV,
+        };
+        out.push(Instruction::Simple(opcode));
+        return Some(());
+    }
+
+    match (op, args.len()) {
+        ("add-checked", 2) => {
+            // [x, y]: ok unless x > not(y)
+            out.push(Instruction::Simple(Opcode::DUP2));
+            out.push(Instruction::Simple(Opcode::NOT));
+            out.push(Instruction::Simple(Opcode::DUP2));
+            out.push(Instruction::Simple(Opcode::GT));
+            out.push(Instruction::Simple(Opcode::ISZERO));
+            emit_check(scope, out);
+            out.push(Instruction::Simple(Opcode::ADD));
+        }
+        ("sub-checked", 2) => {
+            // [x, y]: ok unless x < y
+            out.push(Instruction::Simple(Opcode::DUP2));
+            out.push(Instruction::Simple(Opcode::DUP2));
+            out.push(Instruction::Simple(Opcode::LT));
+            out.push(Instruction::Simple(Opcode::ISZERO));
+            emit_check(scope, out);
+            out.push(Instruction::Simple(Opcode::SUB));
+        }
+        ("mul-checked", 2) => {
+            // [x, y] -> [p, x, y]: ok if x == 0 or p / x == y
+            out.push(Instruction::Simple(Opcode::DUP2));
+            out.push(Instruction::Simple(Opcode::DUP2));
+            out.push(Instruction::Simple(Opcode::MUL));
+            out.push(Instruction::Simple(Opcode::DUP2));
+            out.push(Instruction::Simple(Opcode::DUP2));
+            out.push(Instruction::Simple(Opcode::DIV));
+            out.push(Instruction::Simple(Opcode::DUP4));
+            out.push(Instruction::Simple(Opcode::EQ));
+            out.push(Instruction::Simple(Opcode::DUP3));
+            out.push(Instruction::Simple(Opcode::ISZERO));
+            out.push(Instruction::Simple(Opcode::OR));
+            emit_check(scope, out);
+            out.push(Instruction::Simple(Opcode::SWAP2));
+            out.push(Instruction::Simple(Opcode::POP));
+            out.push(Instruction::Simple(Opcode::POP));
+        }
+        ("div-floor", 2) => {
+            // [x, y]: ok if y is nonzero
+            out.push(Instruction::Simple(Opcode::DUP2));
+            emit_check(scope, out);
+            out.push(Instruction::Simple(Opcode::DIV));
+        }
+        ("addmod", 3) => out.push(Instruction::Simple(Opcode::ADDMOD)),
+        ("mulmod", 3) => out.push(Instruction::Simple(Opcode::MULMOD)),
+        _ => return None,
+    }
+    Some(())
+}
+
+// [ok, ...]: reverts unless ok is nonzero, leaving [...]
+pub(super) fn emit_check(scope: &Scope, out: &mut Vec<Instruction>) {
+    let ok = scope.label("checked");
+    out.push(Instruction::JumpToIf(ok.clone()));
+    out.push(push_bytes(vec![0]));
+    out.push(Instruction::Simple(Opcode::DUP1));
+    out.push(Instruction::Simple(Opcode::REVERT));
+    out.push(Instruction::Label(ok));
+}
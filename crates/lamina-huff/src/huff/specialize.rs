@@ -0,0 +1,314 @@
+// Specialization of internal functions on constant arguments
+//
+// An internal function called with integer literals for some of its
+// arguments gets a copy compiled for that call: the literals replace their
+// parameters in the body, which is then simplified by folding operations on
+// literals, the way the EVM would compute them, by picking the `cond` clause
+// whose test folds to true, and by evaluating calls of internal functions on
+// literals. Given
+//
+//   (define-internal (pow b n)
+//     (cond ((= n 0) 1)
+//           (else (* b (pow b (- n 1))))))
+//
+// `(pow base 2)` compiles as `(* b (pow b 1))` in a frame holding only `b`,
+// its own call of `pow` specialized in turn until `n` reaches 0, and a call
+// whose arguments are all literals, like `(pow 3 4)`, folds to a literal.
+//
+// A specialized call may reenter the function it specializes, as long as its
+// own call passes literals as well, at most `MAX_UNROLL` deep; this is the only
+// way a recursive internal function compiles. A function that could be inlined
+// either way keeps whichever code is smaller, so specializing never grows the
+// contract.
+
+use std::collections::HashMap;
+
+use lamina::evm::Word;
+use lamina::value::{NumberKind, Value};
+
+use super::bytecode::Instruction;
+use super::expression::{emit, emit_inlined, list_items, InternalFunction, Scope};
+use super::switch::constant_test;
+
+/// Most copies of one function a specialized call may be nested in
+pub(crate) const MAX_UNROLL: usize = 16;
+
+/// Compile a call of the internal function `name` specialized on the
+/// arguments that simplify to literals, or `None` when none do
+pub(super) fn emit_specialized(
+    name: &str,
+    function: &InternalFunction,
+    body: &Value,
+    args: &[&Value],
+    scope: &Scope,
+) -> Option<Vec<Instruction>> {
+    let args: Vec<Value> = args
+        .iter()
+        .map(|arg| simplify(arg, scope.internals, 0))
+        .collect();
+    if !args.iter().any(is_literal) {
+        return None;
+    }
+    if scope.inlined.iter().filter(|n| *n == name).count() >= MAX_UNROLL {
+        return None;
+    }
+
+    let mut bindings = Vec::new();
+    let mut params = Vec::new();
+    let mut passed = Vec::new();
+    for (param, arg) in function.params.iter().zip(&args) {
+        if is_literal(arg) {
+            bindings.push((param.as_str(), arg.clone()));
+        } else {
+            params.push(param.clone());
+            passed.push(arg);
+        }
+    }
+    let body = simplify(&substitute(body, &bindings), scope.internals, 0);
+
+    let mut out = Vec::new();
+    out.push(Instruction::Comment(format!(
+        "Specialized internal function {}",
+        name
+    )));
+    if params.is_empty() {
+        // Nothing to read from a frame
+        let callee = scope.callee(name, &params);
+        emit(&body, &callee, &mut out)?;
+        return Some(out);
+    }
+    for arg in passed.iter().rev() {
+        emit(arg, scope, &mut out)?;
+    }
+    emit_inlined(name, &params, scope, &mut out, |callee, out| {
+        emit(&body, callee, out)
+    })?;
+    Some(out)
+}
+
+fn is_literal(value: &Value) -> bool {
+    matches!(value, Value::Number(NumberKind::Integer(_)))
+}
+
+fn list(items: Vec<Value>) -> Value {
+    items
+        .into_iter()
+        .rev()
+        .fold(Value::Nil, |list, item| Value::cons(item, list))
+}
+
+/// `expr` with the parameters in `bindings` replaced by their values
+fn substitute(expr: &Value, bindings: &[(&str, Value)]) -> Value {
+    match expr {
+        Value::Symbol(name) => bindings
+            .iter()
+            .find(|(param, _)| param == name)
+            .map_or_else(|| expr.clone(), |(_, value)| value.clone()),
+        Value::Pair(pair) => {
+            let Some(items) = list_items(expr) else {
+                return expr.clone();
+            };
+            match &pair.0 {
+                Value::Symbol(op) if op == "quote" => expr.clone(),
+                // The datums of a `case` clause are not expressions
+                Value::Symbol(op) if op == "case" => {
+                    let mut rewritten = vec![pair.0.clone()];
+                    for (index, item) in items.iter().enumerate().skip(1) {
+                        rewritten.push(match (index, list_items(item).as_deref()) {
+                            (1, _) => substitute(item, bindings),
+                            (_, Some([datums, body])) => {
+                                list(vec![(*datums).clone(), substitute(body, bindings)])
+                            }
+                            _ => (*item).clone(),
+                        });
+                    }
+                    list(rewritten)
+                }
+                _ => list(
+                    items
+                        .iter()
+                        .map(|item| substitute(item, bindings))
+                        .collect(),
+                ),
+            }
+        }
+        _ => expr.clone(),
+    }
+}
+
+/// Fold the operations of `expr` whose operands are literals, including calls
+/// of internal functions, evaluated at most `MAX_UNROLL` calls deep
+fn simplify(expr: &Value, internals: &HashMap<String, InternalFunction>, depth: usize) -> Value {
+    let Value::Pair(pair) = expr else {
+        return expr.clone();
+    };
+    let (Value::Symbol(op), Some(items)) = (&pair.0, list_items(expr)) else {
+        return expr.clone();
+    };
+    match op.as_str() {
+        "quote" | "case" => return expr.clone(),
+        "cond" => {
+            return simplify_cond(&items[1..], internals, depth).unwrap_or_else(|| expr.clone())
+        }
+        _ => {}
+    }
+
+    let args: Vec<Value> = items[1..]
+        .iter()
+        .map(|arg| simplify(arg, internals, depth))
+        .collect();
+    if let Some(function) = internals.get(op) {
+        return call(function, &args, internals, depth).unwrap_or_else(|| {
+            let mut items = vec![pair.0.clone()];
+            items.extend(args);
+            list(items)
+        });
+    }
+    let words: Option<Vec<Word>> = args
+        .iter()
+        .map(|arg| match arg {
+            Value::Number(NumberKind::Integer(n)) => Some(Word::from_i64(*n)),
+            _ => None,
+        })
+        .collect();
+    let folded = match words.as_deref() {
+        Some(words) => fold(op, words, &args),
+        None => identity(op, &args),
+    };
+    folded.unwrap_or_else(|| {
+        let mut items = vec![pair.0.clone()];
+        items.extend(args);
+        list(items)
+    })
+}
+
+/// A `cond` without the clauses whose tests fold to false, and without the
+/// clauses after one whose test folds to true
+fn simplify_cond(
+    clauses: &[&Value],
+    internals: &HashMap<String, InternalFunction>,
+    depth: usize,
+) -> Option<Value> {
+    let mut kept = Vec::new();
+    for clause in clauses {
+        let (test, body) = match list_items(clause)?.as_slice() {
+            [test, body] => (*test, simplify(body, internals, depth)),
+            _ => return None,
+        };
+        let test = match constant_test(test) {
+            Some(holds) => Value::Number(NumberKind::Integer(holds as i64)),
+            None => simplify(test, internals, depth),
+        };
+        match test {
+            Value::Number(NumberKind::Integer(0)) => {}
+            Value::Number(NumberKind::Integer(_)) if kept.is_empty() => return Some(body),
+            Value::Number(NumberKind::Integer(_)) => {
+                kept.push(list(vec![Value::Symbol("else".into()), body]));
+                break;
+            }
+            test => kept.push(list(vec![test, body])),
+        }
+    }
+    // A cond where no clause matches is 0
+    if kept.is_empty() {
+        return Some(Value::Number(NumberKind::Integer(0)));
+    }
+    let mut items = vec![Value::Symbol("cond".into())];
+    items.extend(kept);
+    Some(list(items))
+}
+
+/// The value of a call of an internal function, when its arguments are
+/// literals and its body folds to a literal
+fn call(
+    function: &InternalFunction,
+    args: &[Value],
+    internals: &HashMap<String, InternalFunction>,
+    depth: usize,
+) -> Option<Value> {
+    if depth >= MAX_UNROLL || args.len() != function.params.len() || !args.iter().all(is_literal) {
+        return None;
+    }
+    let body = match list_items(&function.body)?.as_slice() {
+        [body] => (*body).clone(),
+        _ => return None,
+    };
+    let bindings: Vec<(&str, Value)> = function
+        .params
+        .iter()
+        .map(String::as_str)
+        .zip(args.iter().cloned())
+        .collect();
+    let value = simplify(&substitute(&body, &bindings), internals, depth + 1);
+    is_literal(&value).then_some(value)
+}
+
+/// `op` applied to literal operands, when it is known and the result is an
+/// integer literal
+fn fold(op: &str, words: &[Word], args: &[Value]) -> Option<Value> {
+    let bool_word = |b: bool| if b { Word::ONE } else { Word::ZERO };
+    let result = match (op, words) {
+        ("+", [x, y]) => x.wrapping_add(*y),
+        ("-", [x, y]) => x.wrapping_sub(*y),
+        ("*", [x, y]) => x.wrapping_mul(*y),
+        ("/", [x, y]) => x.div_rem(*y).0,
+        ("<", [x, y]) => bool_word(x < y),
+        (">", [x, y]) => bool_word(x > y),
+        ("=", [x, y]) => bool_word(x == y),
+        ("s<", [x, y]) => bool_word(x.signed_lt(*y)),
+        ("bitwise-and", [x, y]) => *x & *y,
+        ("bitwise-or", [x, y]) => *x | *y,
+        ("bitwise-xor", [x, y]) => *x ^ *y,
+        ("bitwise-not", [x]) => !*x,
+        ("arithmetic-shift", [x, _]) => match &args[1] {
+            Value::Number(NumberKind::Integer(count)) if *count >= 0 => {
+                x.shift_left((*count).min(256) as u32)
+            }
+            Value::Number(NumberKind::Integer(count)) => {
+                x.shift_right_arithmetic(count.unsigned_abs().min(256) as u32)
+            }
+            _ => return None,
+        },
+        // A checked operation that fails has to revert at run time
+        ("add-checked", [x, y]) => x.checked_add(*y)?,
+        ("sub-checked", [x, y]) => x.checked_sub(*y)?,
+        ("mul-checked", [x, y]) => x.checked_mul(*y)?,
+        ("div-floor", [x, y]) if !y.is_zero() => x.div_rem(*y).0,
+        ("addmod", [x, y, m]) => x.add_mod(*y, *m),
+        ("mulmod", [x, y, m]) => x.mul_mod(*y, *m),
+        _ => return None,
+    };
+    result
+        .to_i64()
+        .map(|n| Value::Number(NumberKind::Integer(n)))
+}
+
+/// `op` applied to an operand and its identity element, as in `(* x 1)`
+fn identity(op: &str, args: &[Value]) -> Option<Value> {
+    let literal =
+        |value: &Value, n: i64| matches!(value, Value::Number(NumberKind::Integer(m)) if *m == n);
+    match (op, args) {
+        ("+", [x, zero]) | ("+", [zero, x]) | ("-", [x, zero]) if literal(zero, 0) => {
+            Some(x.clone())
+        }
+        ("*", [x, one]) | ("*", [one, x]) if literal(one, 1) => Some(x.clone()),
+        _ => None,
+    }
+}
+
+/// Bytes of runtime code `instructions` assemble to, for comparing two ways
+/// of compiling the same call
+pub(super) fn code_size(instructions: &[Instruction]) -> usize {
+    instructions
+        .iter()
+        .map(|instruction| match instruction {
+            Instruction::Simple(_) | Instruction::Label(_) | Instruction::JumpLabel(_) => 1,
+            Instruction::Push(size, _) => 1 + *size as usize,
+            // PUSH2 of the label and JUMP or JUMPI
+            Instruction::JumpTo(_) | Instruction::JumpToIf(_) => 4,
+            // Constants, pushed as a full word at most
+            Instruction::MacroCall(_) => 33,
+            Instruction::Comment(_) => 0,
+        })
+        .sum()
+}
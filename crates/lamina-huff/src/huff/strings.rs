@@ -0,0 +1,197 @@
+// Lowering of string storage variables
+//
+// Strings use the Solidity storage layout. A string of up to 31 bytes shares
+// its slot with its length, the data left-aligned and twice the length in the
+// low byte. A longer string keeps `2 * length + 1` in its slot, so the low bit
+// tells the two apart, and its data in consecutive slots from
+// `keccak256(slot)`.
+//
+// Only string literals can be stored, since values on the stack are single
+// words. `storage-string-load` is only compiled as the whole body of a
+// function, which returns the string ABI-encoded instead of a word.
+
+use lamina::value::Value;
+
+use super::array::emit_data_slot;
+use super::bytecode::Instruction;
+use super::expression::{emit, list_items, minimal_bytes, push_bytes, Scope};
+use super::memory;
+use super::opcodes::Opcode;
+
+/// Longest string stored in a single slot alongside its length
+pub const SHORT_STRING_MAX: usize = 31;
+
+/// The slot read by a function body of the form `(storage-string-load slot)`
+pub(crate) fn string_load_slot(expr: &Value) -> Option<&Value> {
+    let Value::Pair(pair) = expr else {
+        return None;
+    };
+    match (&pair.0, list_items(&pair.1)?.as_slice()) {
+        (Value::Symbol(op), [slot]) if op == "storage-string-load" => Some(*slot),
+        _ => None,
+    }
+}
+
+/// `(storage-string-store! slot "literal")`, leaving the length of the string
+pub(crate) fn emit_string_store(
+    args: &[&Value],
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    let (slot, bytes) = match args {
+        [slot, Value::String(s)] => (*slot, s.as_bytes()),
+        _ => return None,
+    };
+
+    emit(slot, scope, out)?;
+    if bytes.len() <= SHORT_STRING_MAX {
+        let mut word = bytes.to_vec();
+        word.resize(32, 0);
+        word[31] = 2 * bytes.len() as u8;
+        // [slot] -> sstore(slot, word)
+        out.push(push_bytes(trim_leading_zeros(word)));
+        out.push(Instruction::Simple(Opcode::SWAP1));
+        out.push(Instruction::Simple(Opcode::SSTORE));
+    } else {
+        // [slot] -> sstore(slot, 2 * length + 1), leaving slot
+        out.push(push_bytes(minimal_bytes(2 * bytes.len() as u64 + 1)));
+        out.push(Instruction::Simple(Opcode::DUP2));
+        out.push(Instruction::Simple(Opcode::SSTORE));
+
+        // [base] -> sstore(base + i, chunk) for each 32-byte chunk
+        emit_data_slot(out);
+        for (index, chunk) in bytes.chunks(32).enumerate() {
+            let mut word = chunk.to_vec();
+            word.resize(32, 0);
+            out.push(push_bytes(trim_leading_zeros(word)));
+            out.push(Instruction::Simple(Opcode::DUP2));
+            if index > 0 {
+                out.push(push_bytes(minimal_bytes(index as u64)));
+                out.push(Instruction::Simple(Opcode::ADD));
+            }
+            out.push(Instruction::Simple(Opcode::SSTORE));
+        }
+        out.push(Instruction::Simple(Opcode::POP));
+    }
+
+    out.push(push_bytes(minimal_bytes(bytes.len() as u64)));
+    Some(())
+}
+
+/// Return the string stored at `slot` ABI-encoded: offset, length, then the
+/// data padded to whole words
+pub(crate) fn emit_string_return(
+    slot: &Value,
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    let long = scope.label("string_long");
+    let copy = scope.label("string_copy");
+    let copied = scope.label("string_copied");
+    let encode = scope.label("string_encode");
+
+    emit(slot, scope, out)?;
+    out.push(Instruction::Simple(Opcode::DUP1));
+    out.push(Instruction::Simple(Opcode::SLOAD));
+    out.push(Instruction::Simple(Opcode::DUP1));
+    out.push(push_bytes(vec![1]));
+    out.push(Instruction::Simple(Opcode::AND));
+    out.push(Instruction::JumpToIf(long.clone()));
+
+    // Short string, [word, slot] -> [ptr, length]
+    out.push(Instruction::Simple(Opcode::SWAP1));
+    out.push(Instruction::Simple(Opcode::POP));
+    out.push(Instruction::Simple(Opcode::DUP1));
+    out.push(push_bytes(vec![0xff]));
+    out.push(Instruction::Simple(Opcode::AND));
+    out.push(push_bytes(vec![1]));
+    out.push(Instruction::Simple(Opcode::SHR));
+    emit_alloc_encoding(out);
+    // [ptr, length, word] -> mstore(ptr + 0x40, data)
+    out.push(Instruction::Simple(Opcode::SWAP2));
+    out.push(push_bytes(vec![0xff]));
+    out.push(Instruction::Simple(Opcode::NOT));
+    out.push(Instruction::Simple(Opcode::AND));
+    out.push(Instruction::Simple(Opcode::DUP3));
+    out.push(push_bytes(vec![0x40]));
+    out.push(Instruction::Simple(Opcode::ADD));
+    out.push(Instruction::Simple(Opcode::MSTORE));
+    out.push(Instruction::Simple(Opcode::SWAP1));
+    out.push(Instruction::JumpTo(encode.clone()));
+
+    // Long string, [word, slot] -> [index, base, length, ptr]
+    out.push(Instruction::Label(long));
+    out.push(push_bytes(vec![1]));
+    out.push(Instruction::Simple(Opcode::SHR));
+    emit_alloc_encoding(out);
+    out.push(Instruction::Simple(Opcode::SWAP2));
+    emit_data_slot(out);
+    out.push(push_bytes(vec![0]));
+
+    // Copy a word at a time while 32 * index < length
+    out.push(Instruction::Label(copy.clone()));
+    out.push(Instruction::Simple(Opcode::DUP1));
+    out.push(push_bytes(vec![5]));
+    out.push(Instruction::Simple(Opcode::SHL));
+    out.push(Instruction::Simple(Opcode::DUP4));
+    out.push(Instruction::Simple(Opcode::GT));
+    out.push(Instruction::Simple(Opcode::ISZERO));
+    out.push(Instruction::JumpToIf(copied.clone()));
+    // mstore(ptr + 0x40 + 32 * index, sload(base + index))
+    out.push(Instruction::Simple(Opcode::DUP2));
+    out.push(Instruction::Simple(Opcode::DUP2));
+    out.push(Instruction::Simple(Opcode::ADD));
+    out.push(Instruction::Simple(Opcode::SLOAD));
+    out.push(Instruction::Simple(Opcode::DUP2));
+    out.push(push_bytes(vec![5]));
+    out.push(Instruction::Simple(Opcode::SHL));
+    out.push(Instruction::Simple(Opcode::DUP6));
+    out.push(Instruction::Simple(Opcode::ADD));
+    out.push(push_bytes(vec![0x40]));
+    out.push(Instruction::Simple(Opcode::ADD));
+    out.push(Instruction::Simple(Opcode::MSTORE));
+    out.push(push_bytes(vec![1]));
+    out.push(Instruction::Simple(Opcode::ADD));
+    out.push(Instruction::JumpTo(copy));
+
+    out.push(Instruction::Label(copied));
+    out.push(Instruction::Simple(Opcode::POP));
+    out.push(Instruction::Simple(Opcode::POP));
+    out.push(Instruction::Simple(Opcode::SWAP1));
+
+    // [ptr, length] -> return(ptr, 0x40 + length rounded up to whole words)
+    out.push(Instruction::Label(encode));
+    out.push(push_bytes(vec![0x20]));
+    out.push(Instruction::Simple(Opcode::DUP2));
+    out.push(Instruction::Simple(Opcode::MSTORE));
+    out.push(Instruction::Simple(Opcode::DUP2));
+    out.push(Instruction::Simple(Opcode::DUP2));
+    out.push(push_bytes(vec![0x20]));
+    out.push(Instruction::Simple(Opcode::ADD));
+    out.push(Instruction::Simple(Opcode::MSTORE));
+    out.push(Instruction::Simple(Opcode::SWAP1));
+    out.push(push_bytes(vec![0x1f]));
+    out.push(Instruction::Simple(Opcode::ADD));
+    out.push(push_bytes(vec![0x1f]));
+    out.push(Instruction::Simple(Opcode::NOT));
+    out.push(Instruction::Simple(Opcode::AND));
+    out.push(push_bytes(vec![0x40]));
+    out.push(Instruction::Simple(Opcode::ADD));
+    out.push(Instruction::Simple(Opcode::SWAP1));
+    out.push(Instruction::Simple(Opcode::RETURN));
+    Some(())
+}
+
+// [length, ...] -> [ptr, length, ...], allocating the offset and length words
+// and the data
+fn emit_alloc_encoding(out: &mut Vec<Instruction>) {
+    out.push(Instruction::Simple(Opcode::DUP1));
+    out.push(push_bytes(vec![0x40]));
+    out.push(Instruction::Simple(Opcode::ADD));
+    out.extend(memory::alloc_dynamic());
+}
+
+pub(super) fn trim_leading_zeros(word: Vec<u8>) -> Vec<u8> {
+    let first = word.iter().position(|b| *b != 0).unwrap_or(word.len() - 1);
+    word[first..].to_vec()
+}
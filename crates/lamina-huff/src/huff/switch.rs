@@ -0,0 +1,300 @@
+// Lowering of cond, case, and and or
+//
+// `cond` and sparse `case` dispatch test one clause after another. A `case`
+// over enough dense integer datums instead jumps through a table of fixed-size
+// stubs, one per value between the smallest and largest datum:
+//
+//   key - min, bounds check, * STUB_SIZE + table start, JUMP
+//   table: JUMPDEST PUSH2 <clause> JUMP   (one stub per value)
+//
+// A sequential test costs 22 gas per datum checked (DUP1, PUSH, EQ, PUSH2,
+// JUMPI) and 8 bytes of code; the table costs about 68 gas whatever the key,
+// plus 5 bytes of code per value in the range. The table is used from
+// JUMP_TABLE_MIN_DATUMS datums on, where the average sequential search costs
+// more, and only while at least half of the table is filled so the extra code
+// doesn't outweigh the gas saved.
+//
+// A test computed at run time holds when its word isn't zero, which is how
+// the interpreter tests the #t and #f of a comparison, compiled as 1 and 0. A
+// literal test is decided when compiling, the way the interpreter decides it,
+// so `(cond (0 x))` is `x` on both. `and` and `or` keep the value that decides
+// them on the stack and jump past the rest, so `(and 0 x)` is 0 and `(or 0 x)`
+// is `x`.
+
+use lamina::value::{NumberKind, Value};
+
+use super::bytecode::Instruction;
+use super::enums::variant_index;
+use super::expression::{emit, list_items, minimal_bytes, push_bytes, push_integer, Scope};
+use super::opcodes::Opcode;
+
+/// Smallest number of datums a `case` needs to compile to a jump table
+pub const JUMP_TABLE_MIN_DATUMS: usize = 6;
+
+/// Largest number of stubs in a jump table
+pub const JUMP_TABLE_MAX_SIZE: u64 = 256;
+
+/// Bytes per table stub: JUMPDEST, PUSH2 <label>, JUMP
+const STUB_SIZE: u8 = 5;
+
+/// Whether a `cond` test written as a literal, or `else`, always holds or
+/// never does; None for a test computed at run time
+pub(crate) fn constant_test(test: &Value) -> Option<bool> {
+    match test {
+        Value::Symbol(s) => (s == "else").then_some(true),
+        Value::Boolean(_) | Value::Number(_) | Value::String(_) | Value::Character(_) => {
+            Some(test.is_truthy())
+        }
+        _ => None,
+    }
+}
+
+/// `(cond (test expr) ... (else expr))`; evaluates to 0 when no clause matches
+pub(crate) fn emit_cond(
+    clauses: &[&Value],
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    let end = scope.label("cond_end");
+    let mut bodies = Vec::new();
+    let mut fallback = None;
+
+    for clause in clauses {
+        let items = list_items(clause)?;
+        let (test, body) = match items.as_slice() {
+            [test, body] => (*test, *body),
+            _ => return None,
+        };
+        match constant_test(test) {
+            Some(true) => {
+                fallback = Some(body);
+                break;
+            }
+            Some(false) => continue,
+            None => {}
+        }
+
+        let label = scope.label("cond_clause");
+        emit(test, scope, out)?;
+        out.push(Instruction::JumpToIf(label.clone()));
+        bodies.push((label, test, body));
+    }
+
+    emit_fallback(fallback, scope, out)?;
+    out.push(Instruction::JumpTo(end.clone()));
+    // A clause's body only runs when its test holds
+    for (label, test, body) in bodies {
+        out.push(Instruction::Label(label));
+        scope.assuming(test, |scope| emit(body, scope, out))?;
+        out.push(Instruction::JumpTo(end.clone()));
+    }
+    out.push(Instruction::Label(end));
+    Some(())
+}
+
+/// `(and expr ...)`, or `(or expr ...)` when `and` is false, evaluating the
+/// expressions until one decides it
+pub(crate) fn emit_logic(
+    and: bool,
+    exprs: &[&Value],
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    let Some((last, init)) = exprs.split_last() else {
+        out.push(push_integer(and as i64));
+        return Some(());
+    };
+    if init.is_empty() {
+        return emit(last, scope, out);
+    }
+    let end = scope.label(if and { "and_end" } else { "or_end" });
+    for expr in init {
+        // [value] -> [value], jumping to the end with it when it decides
+        emit(expr, scope, out)?;
+        out.push(Instruction::Simple(Opcode::DUP1));
+        if and {
+            out.push(Instruction::Simple(Opcode::ISZERO));
+        }
+        out.push(Instruction::JumpToIf(end.clone()));
+        out.push(Instruction::Simple(Opcode::POP));
+    }
+    emit(last, scope, out)?;
+    out.push(Instruction::Label(end));
+    Some(())
+}
+
+/// `(case key ((datum ...) expr) ... (else expr))` over integer datums or
+/// enum variants
+pub(crate) fn emit_case(args: &[&Value], scope: &Scope, out: &mut Vec<Instruction>) -> Option<()> {
+    let (key, clauses) = args.split_first()?;
+
+    let mut arms = Vec::new();
+    let mut fallback = None;
+    for clause in clauses {
+        let items = list_items(clause)?;
+        let (datums, body) = match items.as_slice() {
+            [datums, body] => (*datums, *body),
+            _ => return None,
+        };
+        if matches!(datums, Value::Symbol(s) if s == "else") {
+            fallback = Some(body);
+            break;
+        }
+
+        let datums = list_items(datums)?
+            .into_iter()
+            .map(|datum| match datum {
+                Value::Number(NumberKind::Integer(n)) => Some(*n),
+                Value::Symbol(variant) => variant_index(variant, scope),
+                _ => None,
+            })
+            .collect::<Option<Vec<i64>>>()?;
+        arms.push((datums, body));
+    }
+
+    emit(key, scope, out)?;
+    match jump_table_range(&arms) {
+        Some((min, size)) => emit_jump_table(&arms, fallback, min, size, scope, out),
+        None => emit_comparisons(&arms, fallback, scope, out),
+    }
+}
+
+/// The smallest datum and the table size, when `arms` are worth a jump table
+fn jump_table_range(arms: &[(Vec<i64>, &Value)]) -> Option<(u64, u64)> {
+    let datums: Vec<i64> = arms.iter().flat_map(|(datums, _)| datums.clone()).collect();
+    if datums.len() < JUMP_TABLE_MIN_DATUMS || datums.iter().any(|d| *d < 0) {
+        return None;
+    }
+
+    let min = *datums.iter().min()? as u64;
+    let size = *datums.iter().max()? as u64 - min + 1;
+    (size <= JUMP_TABLE_MAX_SIZE && size <= 2 * datums.len() as u64).then_some((min, size))
+}
+
+// The key is on the stack and stays there until a clause is chosen
+fn emit_comparisons(
+    arms: &[(Vec<i64>, &Value)],
+    fallback: Option<&Value>,
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    let end = scope.label("case_end");
+    let mut bodies = Vec::new();
+
+    for (datums, body) in arms {
+        let label = scope.label("case_clause");
+        for datum in datums {
+            out.push(Instruction::Simple(Opcode::DUP1));
+            out.push(push_integer(*datum));
+            out.push(Instruction::Simple(Opcode::EQ));
+            out.push(Instruction::JumpToIf(label.clone()));
+        }
+        bodies.push((label, *body));
+    }
+
+    out.push(Instruction::Simple(Opcode::POP));
+    emit_fallback(fallback, scope, out)?;
+    out.push(Instruction::JumpTo(end.clone()));
+    emit_bodies(&bodies, &end, true, scope, out)?;
+    out.push(Instruction::Label(end));
+    Some(())
+}
+
+// The key is on the stack and is used up by the computed jump
+fn emit_jump_table(
+    arms: &[(Vec<i64>, &Value)],
+    fallback: Option<&Value>,
+    min: u64,
+    size: u64,
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    let end = scope.label("case_end");
+    let out_of_range = scope.label("case_out_of_range");
+    let default = scope.label("case_default");
+    let table = scope.label("case_table");
+
+    // Index into the table; keys below `min` wrap around and fail the bounds check
+    if min > 0 {
+        out.push(push_bytes(minimal_bytes(min)));
+        out.push(Instruction::Simple(Opcode::SWAP1));
+        out.push(Instruction::Simple(Opcode::SUB));
+    }
+    out.push(Instruction::Simple(Opcode::DUP1));
+    out.push(push_bytes(minimal_bytes(size)));
+    out.push(Instruction::Simple(Opcode::GT));
+    out.push(Instruction::Simple(Opcode::ISZERO));
+    out.push(Instruction::JumpToIf(out_of_range.clone()));
+
+    out.push(push_bytes(vec![STUB_SIZE]));
+    out.push(Instruction::Simple(Opcode::MUL));
+    out.push(Instruction::JumpLabel(table.clone()));
+    out.push(Instruction::Simple(Opcode::ADD));
+    out.push(Instruction::Simple(Opcode::JUMP));
+
+    // One stub per value; the first stub's JUMPDEST is the table label itself
+    let mut bodies = Vec::new();
+    let mut targets = vec![default.clone(); size as usize];
+    for (datums, body) in arms {
+        let label = scope.label("case_clause");
+        for datum in datums {
+            let slot = &mut targets[(*datum as u64 - min) as usize];
+            // An earlier clause wins for a repeated datum
+            if *slot == default {
+                *slot = label.clone();
+            }
+        }
+        bodies.push((label, *body));
+    }
+    for (index, target) in targets.into_iter().enumerate() {
+        let stub = if index == 0 {
+            table.clone()
+        } else {
+            scope.label("case_stub")
+        };
+        out.push(Instruction::Label(stub));
+        out.push(Instruction::JumpTo(target));
+    }
+
+    out.push(Instruction::Label(out_of_range));
+    out.push(Instruction::Simple(Opcode::POP));
+    out.push(Instruction::Label(default));
+    emit_fallback(fallback, scope, out)?;
+    out.push(Instruction::JumpTo(end.clone()));
+    emit_bodies(&bodies, &end, false, scope, out)?;
+    out.push(Instruction::Label(end));
+    Some(())
+}
+
+fn emit_fallback(
+    fallback: Option<&Value>,
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    match fallback {
+        Some(body) => emit(body, scope, out),
+        None => {
+            out.push(push_bytes(vec![0]));
+            Some(())
+        }
+    }
+}
+
+// Each clause body leaves its value and jumps to the end
+fn emit_bodies(
+    bodies: &[(String, &Value)],
+    end: &str,
+    pop_key: bool,
+    scope: &Scope,
+    out: &mut Vec<Instruction>,
+) -> Option<()> {
+    for (label, body) in bodies {
+        out.push(Instruction::Label(label.clone()));
+        if pop_key {
+            out.push(Instruction::Simple(Opcode::POP));
+        }
+        emit(body, scope, out)?;
+        out.push(Instruction::JumpTo(end.to_string()));
+    }
+    Some(())
+}
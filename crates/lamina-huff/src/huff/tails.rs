@@ -0,0 +1,235 @@
+// Sharing of repeated code that leaves the code it is in
+//
+// Every dispatcher branch ends by returning its function's result, the same
+// instructions each time, and a function that reverts or returns in several
+// places repeats its epilogue too. A run of instructions that ends with RETURN,
+// REVERT, STOP, INVALID, SELFDESTRUCT or an unconditional jump never falls
+// through to what follows it, so all but one copy of it can be replaced by a
+// jump to the one kept:
+//
+//   ... <tail>                ... shared_tail_0: <tail>
+//   ... <tail>         =>     ... [shared_tail_0] jump
+//
+// Each copy replaced saves the size of the tail less the 4 bytes of the jump,
+// and the copy kept grows by the JUMPDEST of its label. The sharing that saves
+// the most goes first, over and over until no tail of at least
+// MIN_SHARED_BYTES is repeated; sharing shorter ones would save a byte or two
+// at the price of 12 more gas each time one runs.
+//
+// A tail never includes a label, which something else may jump to, or a call
+// of another macro. Tails are only shared within a macro expanded once, as the
+// dispatcher and the functions it calls are, since a label is defined once and
+// Huff resolves it in the macro that defines it.
+
+use std::collections::{HashMap, HashSet};
+
+use lamina::error::Error;
+
+use super::assembler::instruction_size;
+use super::bytecode::{HuffContract, HuffMacro, Instruction};
+use super::opcodes::Opcode;
+
+/// Fewest bytes a repeated tail needs to be shared
+pub const MIN_SHARED_BYTES: usize = 8;
+
+/// Bytes of the jump replacing a copy: PUSH2 <label> JUMP
+const JUMP_SIZE: usize = 4;
+
+/// Copies of one tail to share
+struct Sharing {
+    /// The tail whose copy is kept
+    keep: usize,
+    /// The tails ending in a copy of it
+    copies: Vec<usize>,
+    /// Instructions shared, from the end of each tail
+    length: usize,
+    /// Bytes of code saved
+    saved: usize,
+}
+
+/// Replace the repeated tails of the macros of `contract` expanded once by
+/// jumps to a single copy
+pub(super) fn share_tails(
+    contract: &mut HuffContract,
+    constants: &HashMap<String, Vec<u8>>,
+) -> Result<(), Error> {
+    let once = expanded_once(contract);
+    let mut labels = 0;
+
+    let mut shared = Vec::new();
+    for mac in std::iter::once(&contract.main).chain(&contract.macros) {
+        if once.contains(&mac.name) {
+            let mut mac = mac.clone();
+            share_in_macro(&mut mac, contract, constants, &mut labels)?;
+            shared.push(mac);
+        }
+    }
+
+    for mac in shared {
+        if mac.name == contract.main.name {
+            contract.main = mac;
+        } else if let Some(slot) = contract.macros.iter_mut().find(|m| m.name == mac.name) {
+            *slot = mac;
+        }
+    }
+    Ok(())
+}
+
+fn share_in_macro(
+    mac: &mut HuffMacro,
+    contract: &HuffContract,
+    constants: &HashMap<String, Vec<u8>>,
+    labels: &mut usize,
+) -> Result<(), Error> {
+    loop {
+        let sizes = mac
+            .instructions
+            .iter()
+            .map(|instruction| instruction_size(instruction, contract, constants))
+            .collect::<Result<Vec<_>, _>>()?;
+        let tails = tails(&mac.instructions);
+        let Some(sharing) = best_sharing(&mac.instructions, &tails, &sizes) else {
+            return Ok(());
+        };
+
+        let label = format!("shared_tail_{}", labels);
+        *labels += 1;
+
+        // (start, end, replacement), applied from the last so indices hold
+        let start = |tail: &[usize]| tail[tail.len() - sharing.length];
+        let kept = start(&tails[sharing.keep]);
+        let mut edits = vec![(kept, kept, Instruction::Label(label.clone()))];
+        for &copy in &sharing.copies {
+            let tail = &tails[copy];
+            edits.push((
+                start(tail),
+                tail[tail.len() - 1] + 1,
+                Instruction::JumpTo(label.clone()),
+            ));
+        }
+        edits.sort_by_key(|edit| std::cmp::Reverse(edit.0));
+        for (start, end, replacement) in edits {
+            mac.instructions.splice(start..end, [replacement]);
+        }
+    }
+}
+
+/// The macros expanded exactly once: the dispatcher, and those called from
+/// one place in a macro expanded once
+fn expanded_once(contract: &HuffContract) -> HashSet<String> {
+    let mut callers: HashMap<&str, Vec<&str>> = HashMap::new();
+    for mac in std::iter::once(&contract.main).chain(&contract.macros) {
+        for instruction in &mac.instructions {
+            if let Instruction::MacroCall(name) = instruction {
+                callers.entry(name).or_default().push(&mac.name);
+            }
+        }
+    }
+
+    let mut once = HashSet::from([contract.main.name.clone()]);
+    loop {
+        let called_once: Vec<String> = contract
+            .macros
+            .iter()
+            .filter(|mac| !once.contains(&mac.name))
+            .filter(
+                |mac| match callers.get(mac.name.as_str()).map(Vec::as_slice) {
+                    Some([caller]) => once.contains(*caller),
+                    _ => false,
+                },
+            )
+            .map(|mac| mac.name.clone())
+            .collect();
+        if called_once.is_empty() {
+            return once;
+        }
+        once.extend(called_once);
+    }
+}
+
+/// The runs of instructions that end by leaving the code they are in, as the
+/// indices of their instructions, leaving out comments
+fn tails(instructions: &[Instruction]) -> Vec<Vec<usize>> {
+    let mut tails = Vec::new();
+    let mut run = Vec::new();
+    for (index, instruction) in instructions.iter().enumerate() {
+        match instruction {
+            Instruction::Comment(_) => {}
+            Instruction::Label(_) | Instruction::Simple(Opcode::JUMPDEST) => run.clear(),
+            Instruction::MacroCall(name) if !name.ends_with("_SLOT") => run.clear(),
+            _ => {
+                run.push(index);
+                if leaves(instruction) {
+                    tails.push(std::mem::take(&mut run));
+                }
+            }
+        }
+    }
+    tails
+}
+
+fn leaves(instruction: &Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::JumpTo(_)
+            | Instruction::Simple(
+                Opcode::JUMP
+                    | Opcode::STOP
+                    | Opcode::RETURN
+                    | Opcode::REVERT
+                    | Opcode::INVALID
+                    | Opcode::SELFDESTRUCT
+            )
+    )
+}
+
+/// The sharing of the tails in `tails` that saves the most bytes, if any tail
+/// of at least MIN_SHARED_BYTES is repeated
+fn best_sharing(
+    instructions: &[Instruction],
+    tails: &[Vec<usize>],
+    sizes: &[usize],
+) -> Option<Sharing> {
+    let mut best: Option<Sharing> = None;
+    for (keep, tail) in tails.iter().enumerate() {
+        // The other tails by how much of this one they end with, most first
+        let mut common: Vec<(usize, usize)> = tails
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| *other != keep)
+            .map(|(other, other_tail)| (common_suffix(instructions, tail, other_tail), other))
+            .filter(|(length, _)| *length > 0)
+            .collect();
+        common.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        // Sharing with one more tail may mean sharing fewer instructions
+        for (count, &(length, _)) in common.iter().enumerate() {
+            let bytes: usize = tail[tail.len() - length..]
+                .iter()
+                .map(|&index| sizes[index])
+                .sum();
+            if bytes < MIN_SHARED_BYTES {
+                break;
+            }
+            let saved = (count + 1) * (bytes - JUMP_SIZE) - 1;
+            if best.as_ref().is_none_or(|best| saved > best.saved) {
+                best = Some(Sharing {
+                    keep,
+                    copies: common[..=count].iter().map(|&(_, other)| other).collect(),
+                    length,
+                    saved,
+                });
+            }
+        }
+    }
+    best
+}
+
+/// How many instructions `a` and `b` end with in common
+fn common_suffix(instructions: &[Instruction], a: &[usize], b: &[usize]) -> usize {
+    a.iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(&x, &y)| instructions[x] == instructions[y])
+        .count()
+}
@@ -1,6 +1,10 @@
+pub mod backend;
 pub mod huff;
+#[cfg(feature = "primitives")]
+pub mod primitives;
 
+pub use backend::HuffBackend;
 pub use huff::*;
 
 // Re-export core lamina types used in this crate
-pub use lamina; 
\ No newline at end of file
+pub use lamina;
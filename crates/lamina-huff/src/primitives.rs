@@ -0,0 +1,115 @@
+// Compiling from inside the language
+//
+// `with_primitives` binds two procedures in an interpreter, so a REPL session
+// or a script can compile a program it holds as a quoted datum:
+//
+//   (compile-to-huff '(begin (define (get) 42)) "Counter")  ; => "/* Generated ..."
+//   (compile-to-ir '(begin (define (get) 42)))
+//
+// `compile-to-huff` returns the Huff source as a string. `compile-to-ir`
+// returns the macros the program lowers to, before they are printed or
+// assembled, as a datum the REPL prints and a script can walk:
+//
+//   (contract "Counter"
+//     (macro "GET" (takes 0) (returns 1) (push "0x2a") ...)
+//     (macro "MAIN" ...))
+//
+// An instruction is an opcode symbol such as `mstore`, `(push "0x..")`,
+// `(label name)`, `(jump name)`, `(jumpi name)`, `(label-ref name)` or
+// `(call MACRO)`. The contract name is optional for both and defaults to
+// "Contract". A program that fails to compile raises the compiler's error.
+
+use lamina::embed::InterpreterBuilder;
+use lamina::ffi::value_to_string;
+use lamina::value::{NumberKind, Value};
+
+use crate::huff::bytecode::{HuffMacro, Instruction};
+use crate::huff::{self, HuffContract};
+
+const DEFAULT_NAME: &str = "Contract";
+
+/// `builder` with `compile-to-huff` and `compile-to-ir` bound
+pub fn with_primitives(builder: InterpreterBuilder) -> InterpreterBuilder {
+    builder
+        .with_function("compile-to-huff", |args| {
+            let (expr, name) = program("compile-to-huff", &args)?;
+            huff::compile(expr, &name)
+                .map(Value::String)
+                .map_err(|e| e.to_string())
+        })
+        .with_function("compile-to-ir", |args| {
+            let (expr, name) = program("compile-to-ir", &args)?;
+            huff::compile_contract(expr, &name)
+                .map(|contract| contract_to_value(&contract))
+                .map_err(|e| e.to_string())
+        })
+}
+
+/// The program and contract name a primitive was called with
+fn program<'a>(primitive: &str, args: &'a [Value]) -> Result<(&'a Value, String), String> {
+    match args {
+        [expr] => Ok((expr, DEFAULT_NAME.to_string())),
+        [expr, name] => Ok((expr, value_to_string(name)?)),
+        _ => Err(format!(
+            "{}: expected a program and an optional contract name, got {} arguments",
+            primitive,
+            args.len()
+        )),
+    }
+}
+
+fn list(items: Vec<Value>) -> Value {
+    items
+        .into_iter()
+        .rev()
+        .fold(Value::Nil, |list, item| Value::cons(item, list))
+}
+
+fn symbol(name: &str) -> Value {
+    Value::Symbol(name.to_string())
+}
+
+fn tagged(tag: &str, value: Value) -> Value {
+    list(vec![symbol(tag), value])
+}
+
+fn contract_to_value(contract: &HuffContract) -> Value {
+    let mut items = vec![symbol("contract"), Value::String(contract.name.clone())];
+    items.extend(contract.macros.iter().map(macro_to_value));
+    items.push(macro_to_value(&contract.main));
+    list(items)
+}
+
+fn macro_to_value(huff_macro: &HuffMacro) -> Value {
+    let count = |n: usize| Value::Number(NumberKind::Integer(n as i64));
+    let mut items = vec![
+        symbol("macro"),
+        Value::String(huff_macro.name.to_uppercase().replace('-', "_")),
+        tagged("takes", count(huff_macro.takes)),
+        tagged("returns", count(huff_macro.returns)),
+    ];
+    items.extend(
+        huff_macro
+            .instructions
+            .iter()
+            .filter_map(instruction_to_value),
+    );
+    list(items)
+}
+
+/// The datum for `instruction`; comments have none
+fn instruction_to_value(instruction: &Instruction) -> Option<Value> {
+    Some(match instruction {
+        Instruction::Simple(opcode) => symbol(&opcode.as_huff_str()),
+        Instruction::Push(_, bytes) => {
+            let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            tagged("push", Value::String(format!("0x{}", hex)))
+        }
+        Instruction::Label(label) => tagged("label", symbol(label)),
+        Instruction::JumpTo(label) => tagged("jump", symbol(label)),
+        Instruction::JumpToIf(label) => tagged("jumpi", symbol(label)),
+        Instruction::JumpLabel(label) => tagged("label-ref", symbol(label)),
+        Instruction::MacroCall(name) => tagged("call", symbol(name)),
+        Instruction::Comment(_) => return None,
+    })
+}
@@ -1,5 +1,6 @@
 use lamina_huff::huff;
 use lamina_huff::huff::bytecode::calculate_function_selector;
+use lamina::evm::{Hardfork, Word};
 use lamina::lexer;
 use lamina::parser;
 
@@ -48,7 +49,7 @@ fn test_compile_counter_contract() {
 
     // Verify automatic selector generation
     assert!(huff_code.contains("#define function getCounter() view returns (uint256)"));
-    assert!(huff_code.contains("#define function increment() view returns (uint256)"));
+    assert!(huff_code.contains("#define function increment() nonpayable returns (uint256)"));
 
     // Verify dispatcher logic is present
     assert!(huff_code.contains("Function Dispatcher (Auto-Generated)"));
@@ -100,7 +101,7 @@ fn test_compile_simple_storage() {
 
     // Verify automatic selector generation
     assert!(huff_code.contains("#define function getValue() view returns (uint256)"));
-    assert!(huff_code.contains("#define function setValue(uint256) view returns (uint256)"));
+    assert!(huff_code.contains("#define function setValue(uint256) nonpayable returns (uint256)"));
 
     // Verify dispatcher logic is present
     assert!(huff_code.contains("Function Dispatcher (Auto-Generated)"));
@@ -127,3 +128,1452 @@ fn test_function_selector_calculation() {
     let selector2 = calculate_function_selector("transferFrom", &[]);
     assert_ne!(selector1, selector2);
 }
+
+#[test]
+fn test_compile_foundry_artifact() {
+    let lamina_code = r#"
+    (begin
+      (define value-slot 0)
+
+      (define (get-value)
+        (storage-load value-slot))
+
+      (define (set-value new-value)
+        (begin
+          (storage-store value-slot new-value)
+          (storage-load value-slot)))
+    )"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+
+    let artifact = huff::compile_artifact(&expr, "SimpleStorage").unwrap();
+
+    // Selector table matches the selectors used by the dispatcher
+    let identifiers = artifact.method_identifiers();
+    assert_eq!(
+        identifiers,
+        vec![
+            (
+                "getValue()".to_string(),
+                format!("{:08x}", get_selector("get-value", &[]))
+            ),
+            (
+                "setValue(uint256)".to_string(),
+                format!("{:08x}", get_selector("set-value", &["new-value"]))
+            ),
+        ]
+    );
+
+    // Only functions that write storage lose the view mutability
+    let mutability: Vec<&str> = artifact.abi.iter().map(|f| f.state_mutability).collect();
+    assert_eq!(mutability, vec!["view", "nonpayable"]);
+
+    // Runtime code starts with the selector extraction: 0x00 calldataload 0xe0 shr
+    assert_eq!(
+        &artifact.deployed_bytecode[..6],
+        &[0x60, 0x00, 0x35, 0x60, 0xe0, 0x1c]
+    );
+
+    // Creation code is a constructor that returns the runtime code
+    assert!(artifact.bytecode.ends_with(&artifact.deployed_bytecode));
+    let size = artifact.deployed_bytecode.len() as u16;
+    assert_eq!(&artifact.bytecode[1..3], &size.to_be_bytes());
+
+    let json = artifact.to_json();
+    assert!(json.starts_with("{\"abi\":[{\"type\":\"function\",\"name\":\"getValue\""));
+    assert!(json.contains("\"deployedBytecode\":{\"object\":\"0x6000356"));
+    assert!(json.contains("\"methodIdentifiers\":{\"getValue()\":"));
+}
+
+#[test]
+fn test_symbol_table() {
+    let lamina_code = r#"
+    (begin
+      (define counter-slot 0)
+      (define (get-count) (storage-load counter-slot))
+      (define (add-to-count n) (storage-store counter-slot (+ (storage-load counter-slot) n))))"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let artifact = huff::compile_artifact(&expr, "Counter").unwrap();
+
+    // Each function maps back from its macro and ABI name to its definition
+    let names: Vec<(&str, &str, &str)> = artifact
+        .symbols
+        .iter()
+        .map(|s| {
+            (
+                s.lamina.as_str(),
+                s.huff_macro.as_str(),
+                s.function.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            ("get-count", "GET_COUNT_MACRO", "getCount"),
+            ("add-to-count", "ADD_TO_COUNT_MACRO", "addToCount"),
+        ]
+    );
+    let huff_code = huff::compile(&expr, "Counter").unwrap();
+    for symbol in &artifact.symbols {
+        assert!(huff_code.contains(&format!("#define macro {}()", symbol.huff_macro)));
+        let selector = artifact.abi.iter().find(|f| f.name == symbol.function);
+        assert_eq!(selector.map(|f| f.selector), Some(symbol.selector));
+    }
+
+    assert!(artifact.symbols_json().starts_with(
+        r#"[{"lamina":"get-count","macro":"GET_COUNT_MACRO","function":"getCount","selector":""#
+    ));
+}
+
+#[test]
+fn test_doc_comments() {
+    let lamina_code = r#"(define value 0)
+
+(define (set-value v)
+  "Store v as the new value.
+   Returns it too."
+  (storage-store value v)
+  v)
+
+(define (get-value) (storage-load value))
+"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let forms = parser::parse_all(&tokens).unwrap();
+    let expr = lamina::value::Value::list(
+        std::iter::once(lamina::value::Value::Symbol("begin".to_string())).chain(forms),
+    );
+    let options = huff::CompileOptions {
+        source: Some(lamina_code.to_string()),
+        ..Default::default()
+    };
+    let huff_code = huff::compile_with_options(&expr, "Store", &options).unwrap();
+
+    // The docstring, the definition's lines, the signature and the slots head each macro
+    let header = "\
+/// @notice Store v as the new value.
+/// Returns it too.
+/// @dev set-value, lines 3-7
+/// @dev setValue(uint256) nonpayable returns (uint256), selector 0x55241077
+/// @dev storage: VALUE_SLOT
+#define macro SET_VALUE_MACRO()";
+    assert!(huff_code.contains(header), "{}", huff_code);
+    assert!(huff_code.contains("/// @dev get-value, line 9\n"));
+
+    // Signatures and macros are in selector order, getValue (0x20965255) first
+    let position = |text: &str| huff_code.find(text).unwrap();
+    assert!(position("#define function getValue") < position("#define function setValue"));
+    assert!(position("GET_VALUE_MACRO() =") < position("SET_VALUE_MACRO() ="));
+
+    // Without the source, the lines are left out; the docstring goes into the userdoc
+    let artifact = huff::compile_artifact(&expr, "Store").unwrap();
+    assert!(huff::compile(&expr, "Store")
+        .unwrap()
+        .contains("/// @dev set-value\n"));
+    assert!(artifact.to_json().contains(
+        r#""userdoc":{"kind":"user","methods":{"setValue(uint256)":{"notice":"Store v as the new value.\nReturns it too."}},"version":1}"#
+    ));
+    let get_value = artifact.abi.iter().find(|f| f.name == "getValue").unwrap();
+    assert_eq!(get_value.notice, None);
+}
+
+#[test]
+fn test_compile_address_constant() {
+    let lamina_code = r#"
+    (begin
+      (define owner 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed)
+      (define value-slot #x01)
+
+      (define (get-value)
+        (storage-load value-slot))
+    )"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+
+    let huff_code = huff::compile(&expr, "Owned").unwrap();
+    assert!(huff_code
+        .contains("#define constant OWNER = 0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"));
+    assert!(huff_code.contains(&format!("#define constant VALUE_SLOT_SLOT = 0x{:064x}", 1)));
+}
+
+#[test]
+fn test_compile_signed_arithmetic() {
+    let lamina_code = r#"
+    (begin
+      (define (signed-div a b)
+        (s/ a b))
+
+      (define (is-negative x)
+        (s< x 0))
+
+      (define (to-int8 x)
+        (sign-extend 0 x))
+    )"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+
+    let huff_code = huff::compile(&expr, "Signed").unwrap();
+    assert!(huff_code.contains("sdiv"));
+    assert!(huff_code.contains("slt"));
+    assert!(huff_code.contains("signextend"));
+
+    // (s/ a b): b is loaded from calldata first so a ends up on top of the stack
+    let artifact = huff::compile_artifact(&expr, "Signed").unwrap();
+    let code = &artifact.deployed_bytecode;
+    let sdiv = [0x60, 0x24, 0x35, 0x60, 0x04, 0x35, 0x05];
+    assert!(code.windows(sdiv.len()).any(|w| w == sdiv));
+    let signextend = [0x60, 0x04, 0x35, 0x60, 0x00, 0x0b];
+    assert!(code.windows(signextend.len()).any(|w| w == signextend));
+}
+
+#[test]
+fn test_compile_bitwise_operations() {
+    let lamina_code = r#"
+    (begin
+      (define (low-byte x)
+        (bitwise-and x #xff))
+
+      (define (pack hi lo)
+        (bitwise-or (arithmetic-shift hi 128) lo))
+
+      (define (halve x)
+        (arithmetic-shift x -1))
+
+      (define (invert x)
+        (bitwise-not x))
+    )"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+
+    let huff_code = huff::compile(&expr, "Bits").unwrap();
+    for opcode in ["and", "or", "shl", "sar", "not"] {
+        assert!(
+            huff_code.contains(&format!("    {}\n", opcode)),
+            "missing {}",
+            opcode
+        );
+    }
+
+    // (arithmetic-shift hi 128): value first, then the shift amount, then SHL
+    let artifact = huff::compile_artifact(&expr, "Bits").unwrap();
+    let shl = [0x60, 0x04, 0x35, 0x60, 0x80, 0x1b];
+    assert!(artifact
+        .deployed_bytecode
+        .windows(shl.len())
+        .any(|w| w == shl));
+}
+
+#[test]
+fn test_compile_transient_storage() {
+    let lamina_code = r#"
+    (begin
+      (define (lock key)
+        (transient-store! key 1))
+
+      (define (locked? key)
+        (transient-load key))
+    )"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+
+    let huff_code = huff::compile(&expr, "Guard").unwrap();
+    for opcode in ["tstore", "tload"] {
+        assert!(
+            huff_code.contains(&format!("    {}\n", opcode)),
+            "missing {}",
+            opcode
+        );
+    }
+
+    // (transient-store! key 1): the value, kept as the result, then the slot
+    let artifact = huff::compile_artifact(&expr, "Guard").unwrap();
+    let tstore = [0x60, 0x01, 0x80, 0x60, 0x04, 0x35, 0x5d];
+    assert!(artifact
+        .deployed_bytecode
+        .windows(tstore.len())
+        .any(|w| w == tstore));
+
+    // Shanghai has no transient storage, so targeting it fails
+    let options = huff::CompileOptions {
+        hardfork: Hardfork::Shanghai,
+        ..Default::default()
+    };
+    let err = huff::compile_artifact_with_options(&expr, "Guard", &options).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("uses tstore, which needs the cancun hardfork, but the target is shanghai"));
+}
+
+#[test]
+fn test_compile_time_evaluation() {
+    let lamina_code = r#"
+    (begin
+      (define-for-syntax fee-bps 30)
+      (begin-for-syntax
+        (define scale (lambda (x) (* x 100))))
+
+      (define (fee amount)
+        (/ (* amount (compile-time (scale fee-bps))) 1000000))
+    )"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+
+    // The table is computed during expansion and only the literal is compiled
+    let artifact = huff::compile_artifact(&expr, "Fees").unwrap();
+    let push_3000 = [0x61, 0x0b, 0xb8];
+    assert!(artifact
+        .deployed_bytecode
+        .windows(push_3000.len())
+        .any(|w| w == push_3000));
+
+    // Compile-time definitions don't become storage slots or functions
+    let huff_code = huff::compile(&expr, "Fees").unwrap();
+    assert!(!huff_code.contains("FEE_BPS"));
+    assert!(!huff_code.contains("SCALE"));
+    assert_eq!(artifact.abi.len(), 1);
+}
+
+#[test]
+fn test_compile_interface_call() {
+    let path = std::env::temp_dir().join(format!("lamina-huff-ierc20-{}.json", std::process::id()));
+    std::fs::write(
+        &path,
+        r#"[{"type": "function", "name": "balanceOf", "stateMutability": "view",
+             "inputs": [{"name": "owner", "type": "address"}],
+             "outputs": [{"name": "", "type": "uint256"}]}]"#,
+    )
+    .unwrap();
+
+    let lamina_code = format!(
+        r#"
+    (begin
+      (define-interface IERC20 "{}")
+      (define token 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed)
+
+      (define (token-balance owner)
+        (IERC20/balanceOf token owner))
+    )"#,
+        path.display()
+    );
+
+    let tokens = lexer::lex(&lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let artifact = huff::compile_artifact(&expr, "Holder");
+    std::fs::remove_file(&path).unwrap();
+    let artifact = artifact.unwrap();
+
+    // PUSH4 balanceOf(address), then a STATICCALL since the function is a view
+    let push_selector = [0x63, 0x70, 0xa0, 0x82, 0x31];
+    let code = &artifact.deployed_bytecode;
+    assert!(code.windows(push_selector.len()).any(|w| w == push_selector));
+    assert!(code.windows(2).any(|w| w == [0x5a, 0xfa]));
+
+    // Interface functions are called, not exported
+    assert_eq!(artifact.abi.len(), 1);
+}
+
+#[test]
+fn test_compile_warnings() {
+    let lamina_code = r#"
+    (begin
+      (define balance-slot 1)
+      (define owner-slot 1)
+
+      (define (classify x)
+        (cond ((< x 10) 1)
+              (else 2)
+              ((< x 100) 3)))
+
+      (define (classify x) 4)
+    )"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+
+    let warnings = huff::warnings(&expr, "Warnings").unwrap();
+    let codes: Vec<&str> = warnings.iter().map(|w| w.code.as_str()).collect();
+    assert_eq!(
+        codes,
+        ["storage-slot-reuse", "unused-function", "unreachable-clause"]
+    );
+    assert_eq!(
+        warnings[0].to_string(),
+        "warning[storage-slot-reuse]: storage slot 1 is used by both balance-slot and owner-slot"
+    );
+
+    // Warnings are kept on the artifact, or fail the build when denied
+    let artifact = huff::compile_artifact(&expr, "Warnings").unwrap();
+    assert_eq!(artifact.warnings, warnings);
+    let options = huff::CompileOptions {
+        deny_warnings: true,
+        ..Default::default()
+    };
+    let err = huff::compile_artifact_with_options(&expr, "Warnings", &options).unwrap_err();
+    assert!(err.to_string().contains("error[unused-function]"));
+}
+
+#[test]
+fn test_allow_warnings() {
+    let lamina_code = r#"
+    (begin
+      (define balance-slot 1)
+      #:allow storage-slot-reuse
+      (define owner-slot 1)
+
+      #:allow (unreachable-clause unused-function)
+      (define (classify x)
+        (cond (#t 1)
+              ((< x 100) 3)))
+    )"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+
+    assert!(huff::warnings(&expr, "Allowed").unwrap().is_empty());
+    let options = huff::CompileOptions {
+        deny_warnings: true,
+        ..Default::default()
+    };
+    assert!(huff::compile_artifact_with_options(&expr, "Allowed", &options).is_ok());
+}
+
+#[test]
+fn test_security_lints() {
+    let path = std::env::temp_dir().join(format!("lamina-huff-itoken-{}.json", std::process::id()));
+    std::fs::write(
+        &path,
+        r#"[{"type": "function", "name": "transfer", "stateMutability": "nonpayable",
+             "inputs": [{"name": "to", "type": "address"}, {"name": "amount", "type": "uint256"}],
+             "outputs": [{"name": "", "type": "bool"}]}]"#,
+    )
+    .unwrap();
+
+    let lamina_code = format!(
+        r#"
+    (begin
+      (define-interface IToken "{}")
+      (define token 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed)
+      (define payments 0)
+      (define owner-slot 1)
+
+      (define (pay to amount)
+        (storage-array-push! payments (IToken/transfer token to amount)))
+
+      (define (is-owner)
+        (= (tx-origin) (storage-load owner-slot)))
+
+      #:allow (reentrancy tx-origin)
+      (define (pay-owner amount)
+        (storage-array-push! payments (IToken/transfer token (tx-origin) amount)))
+    )"#,
+        path.display()
+    );
+
+    let tokens = lexer::lex(&lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let warnings = huff::warnings(&expr, "Payer");
+    std::fs::remove_file(&path).unwrap();
+    let warnings = warnings.unwrap();
+
+    let messages: Vec<String> = warnings.iter().map(|w| w.to_string()).collect();
+    assert_eq!(
+        messages,
+        [
+            "warning[reentrancy]: pay writes storage after an external call, which can reenter it first",
+            "warning[tx-origin]: is-owner reads tx-origin, use caller to authenticate the sender",
+        ]
+    );
+}
+
+#[test]
+fn test_unchecked_call_lint() {
+    use lamina::diagnostics::Warnings;
+    use lamina_huff::huff::bytecode::Instruction;
+    use lamina_huff::huff::lint::lint;
+    use lamina_huff::huff::opcodes::Opcode;
+
+    let checked = [
+        Instruction::Simple(Opcode::CALL),
+        Instruction::Simple(Opcode::ISZERO),
+        Instruction::JumpToIf("fail".to_string()),
+    ];
+    let mut warnings = Warnings::default();
+    lint("checked", &checked, &mut warnings);
+    assert!(warnings.is_empty());
+
+    let unchecked = [
+        Instruction::Simple(Opcode::STATICCALL),
+        Instruction::Simple(Opcode::POP),
+    ];
+    lint("unchecked", &unchecked, &mut warnings);
+    let diagnostics = warnings.into_diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, "unchecked-call");
+}
+
+fn case_contract(datums: &[i64]) -> String {
+    let clauses: String = datums
+        .iter()
+        .map(|d| format!("(({}) {})", d, d * 10))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "(begin (define (lookup key) (case key {} (else 7))))",
+        clauses
+    )
+}
+
+#[test]
+fn test_compile_case_jump_table() {
+    // PUSH1 5, MUL: the index is scaled to the table's stub size
+    let scale = [0x60, 0x05, 0x02];
+
+    // Dense datums compile to a computed jump through one stub per value
+    let tokens = lexer::lex(&case_contract(&[1, 2, 3, 4, 5, 6])).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let dense = huff::compile_artifact(&expr, "Dense").unwrap();
+    assert!(dense
+        .deployed_bytecode
+        .windows(scale.len())
+        .any(|w| w == scale));
+
+    // Too few or too spread out datums are compared one by one
+    for datums in [&[1, 2, 3][..], &[1, 20, 300, 4000, 50000, 600000][..]] {
+        let tokens = lexer::lex(&case_contract(datums)).unwrap();
+        let expr = parser::parse(&tokens).unwrap();
+        let sparse = huff::compile_artifact(&expr, "Sparse").unwrap();
+        assert!(!sparse
+            .deployed_bytecode
+            .windows(scale.len())
+            .any(|w| w == scale));
+        assert_eq!(sparse.abi.len(), 1);
+    }
+}
+
+#[test]
+fn test_compile_cond() {
+    let lamina_code = r#"
+    (begin
+      (define (sign x)
+        (cond ((s< x 0) 2)
+              ((= x 0) 0)
+              (else 1))))"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let huff_code = huff::compile(&expr, "Sign").unwrap();
+    assert!(huff_code.contains("sign_cond_clause_1"));
+    assert!(!huff_code.contains("Function not yet implemented"));
+    assert!(huff::compile_artifact(&expr, "Sign").is_ok());
+}
+
+#[test]
+fn test_compile_and_or() {
+    let lamina_code = r#"
+    (begin
+      (define (both a b)
+        (and (> a 1) (> b 1)))
+      (define (either a b)
+        (or a b 7)))"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    assert_eq!(huff::warnings(&expr, "Logic").unwrap(), vec![]);
+    let huff_code = huff::compile(&expr, "Logic").unwrap();
+    assert!(huff_code.contains("[both_and_end_0] jumpi"));
+    assert!(huff_code.contains("[either_or_end_0] jumpi"));
+
+    // A false value ends and with it on the stack: DUP1 ISZERO PUSH2 <end> JUMPI POP
+    let code = huff::compile_artifact(&expr, "Logic")
+        .unwrap()
+        .deployed_bytecode;
+    let and = code
+        .windows(7)
+        .any(|w| w[..3] == [0x80, 0x15, 0x61] && w[5..] == [0x57, 0x50]);
+    assert!(and);
+}
+
+#[test]
+fn test_cond_literal_tests() {
+    // Literal tests are decided the way the interpreter decides them: 0 holds
+    let lamina_code = r#"
+    (begin
+      #:allow unreachable-clause
+      (define (pick x)
+        (cond (#f 1)
+              (0 2)
+              (else 3))))"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let huff_code = huff::compile(&expr, "Pick").unwrap();
+    assert!(!huff_code.contains("pick_cond_clause"));
+    assert!(huff_code.contains("0x02"));
+    assert!(!huff_code.contains("0x01"));
+    assert!(!huff_code.contains("0x03"));
+}
+
+#[test]
+fn test_free_memory_pointer() {
+    let lamina_code = r#"
+    (begin
+      (define (double x)
+        (* x 2)))"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let code = huff::compile_artifact(&expr, "Double")
+        .unwrap()
+        .deployed_bytecode;
+
+    // PUSH1 0x80 PUSH1 0x40 MSTORE sets up the allocator before dispatch
+    let init = [0x60, 0x80, 0x60, 0x40, 0x52];
+    assert!(code.windows(init.len()).any(|w| w == init));
+
+    // The return value goes through an allocation rather than offset 0
+    let alloc = [0x60, 0x40, 0x51, 0x80, 0x60, 0x20, 0x01, 0x60, 0x40, 0x52];
+    assert!(code.windows(alloc.len()).any(|w| w == alloc));
+    assert!(!code.windows(3).any(|w| w == [0x60, 0x00, 0x52]));
+}
+
+#[test]
+fn test_compile_arrays() {
+    let lamina_code = r#"
+    (begin
+      (define items 3)
+      (define (add x)
+        (storage-array-push! items x))
+      (define (second x)
+        (array-ref (array-set! (make-array 3) 1 x) 1)))"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let huff_code = huff::compile(&expr, "Arrays").unwrap();
+    assert!(!huff_code.contains("Function not yet implemented"));
+    assert!(huff_code.contains("sha3"));
+
+    let code = huff::compile_artifact(&expr, "Arrays")
+        .unwrap()
+        .deployed_bytecode;
+
+    // Storage elements live at keccak256(slot) + index: PUSH1 0x20, SWAP1, SHA3
+    let hash = [0x60, 0x20, 0x90, 0x20];
+    assert!(code.windows(hash.len()).any(|w| w == hash));
+
+    // make-array 3 allocates the length word and three elements, then stores the length
+    let make = [0x60, 0x80, 0x01, 0x60, 0x40, 0x52, 0x60, 0x03, 0x81, 0x52];
+    assert!(code.windows(make.len()).any(|w| w == make));
+
+    // Sizes have to be known at compile time
+    let tokens = lexer::lex("(begin (define (dynamic n) (array-length (make-array n))))").unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let warnings = huff::warnings(&expr, "Dynamic").unwrap();
+    assert_eq!(
+        warnings[0].to_string(),
+        "warning[unsupported]: dynamic uses forms the EVM backend can't compile, so it always reverts"
+    );
+}
+
+#[test]
+fn test_compile_string_storage() {
+    let lamina_code = r#"
+    (begin
+      (define name 5)
+      (define (token-name)
+        (storage-string-load name))
+      (define (rename)
+        (storage-string-store! name "Lamina")))"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let huff_code = huff::compile(&expr, "Named").unwrap();
+    assert!(huff_code.contains("#define function tokenName() view returns (string)"));
+    assert!(!huff_code.contains("Function not yet implemented"));
+
+    let artifact = huff::compile_artifact(&expr, "Named").unwrap();
+    let getter = artifact.abi.iter().find(|f| f.name == "tokenName").unwrap();
+    assert_eq!(getter.outputs, vec!["string".to_string()]);
+    assert!(artifact.abi_json().contains(r#""type":"string""#));
+
+    // A short string shares its slot with twice its length: PUSH32 "Lamina" ... 0x0c
+    let mut short = vec![0x7f];
+    short.extend_from_slice(b"Lamina");
+    short.extend_from_slice(&[0; 25]);
+    short.push(12);
+    let code = &artifact.deployed_bytecode;
+    assert!(code.windows(short.len()).any(|w| w == short));
+}
+
+#[test]
+fn test_compile_internal_functions() {
+    let lamina_code = r#"
+    (begin
+      (define-internal (clamp x)
+        (cond ((> x 100) 100) (else x)))
+      (define-internal (sum-squares a b)
+        (+ (* a a) (* b b)))
+      (define-internal (unused x) x)
+      #:allow unused-function
+      (define-internal (also-unused x) x)
+      (define (bounded a b)
+        (- (clamp (sum-squares a b)) (clamp b))))"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let artifact = huff::compile_artifact(&expr, "Bounded").unwrap();
+
+    // Only the public function gets a selector
+    assert_eq!(artifact.abi.len(), 1);
+    assert_eq!(artifact.abi[0].name, "bounded");
+    let huff_code = huff::compile(&expr, "Bounded").unwrap();
+    assert!(huff_code.contains("Inlined internal function clamp"));
+    assert!(!huff_code.contains("Function not yet implemented"));
+
+    let warnings: Vec<String> = artifact.warnings.iter().map(|w| w.to_string()).collect();
+    assert_eq!(
+        warnings,
+        vec!["warning[unused-function]: unused is internal and never called, so it is not compiled"]
+    );
+
+    // Recursive internal functions can't be inlined
+    let tokens = lexer::lex(
+        "(begin (define-internal (down x) (down (- x 1))) (define (start x) (down x)))",
+    )
+    .unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let warnings = huff::warnings(&expr, "Recursive").unwrap();
+    assert_eq!(
+        warnings[0].to_string(),
+        "warning[unsupported]: start uses forms the EVM backend can't compile, so it always reverts"
+    );
+    let options = huff::CompileOptions {
+        deny_warnings: true,
+        ..Default::default()
+    };
+    assert!(huff::compile_artifact_with_options(&expr, "Recursive", &options).is_err());
+}
+
+#[test]
+fn test_compile_specialized() {
+    let lamina_code = r#"
+    (begin
+      (define-internal (pow b n)
+        (cond ((= n 0) 1)
+              (else (* b (pow b (- n 1))))))
+      (define (cube x) (pow x 3))
+      (define (eighty-one) (pow 3 4)))"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let huff_code = huff::compile(&expr, "Powers").unwrap();
+    assert!(huff_code.contains("Specialized internal function pow"));
+    assert!(!huff_code.contains("Function not yet implemented"));
+
+    // A call with only literals folds: push1 81
+    let artifact = huff::compile_artifact(&expr, "Powers").unwrap();
+    let folded = [0x60, 0x51];
+    assert!(artifact
+        .deployed_bytecode
+        .windows(folded.len())
+        .any(|w| w == folded));
+
+    // Unrolling is bounded
+    let tokens = lexer::lex(
+        r#"
+    (begin
+      (define-internal (pow b n)
+        (cond ((= n 0) 1)
+              (else (* b (pow b (- n 1))))))
+      (define (huge x) (pow x 100)))"#,
+    )
+    .unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let warnings = huff::warnings(&expr, "Huge").unwrap();
+    assert_eq!(
+        warnings[0].to_string(),
+        "warning[unsupported]: huge uses forms the EVM backend can't compile, so it always reverts"
+    );
+}
+
+#[test]
+fn test_infer_mutability() {
+    let lamina_code = r#"
+    (begin
+      (define total 0)
+      (define (double x)
+        (* x 2))
+      (define (get-total)
+        (storage-load total))
+      (define (add x)
+        (storage-array-push! total x))
+      #:mutability payable
+      (define (deposit x)
+        (+ x 1)))"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let artifact = huff::compile_artifact(&expr, "Mutability").unwrap();
+    let mutability: Vec<(&str, &str)> = artifact
+        .abi
+        .iter()
+        .map(|f| (f.name.as_str(), f.state_mutability))
+        .collect();
+    assert_eq!(
+        mutability,
+        vec![
+            ("double", "pure"),
+            ("getTotal", "view"),
+            ("add", "nonpayable"),
+            ("deposit", "payable"),
+        ]
+    );
+    assert!(artifact
+        .abi_json()
+        .contains(r#""name":"double","inputs":[{"name":"x","type":"uint256","internalType":"uint256"}],"outputs":[{"name":"","type":"uint256","internalType":"uint256"}],"stateMutability":"pure""#));
+
+    // A view function that writes storage would revert under STATICCALL
+    let tokens = lexer::lex(
+        "(begin (define total 0) #:mutability view (define (add x) (storage-array-push! total x)))",
+    )
+    .unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let err = huff::compile_artifact(&expr, "Mutability").unwrap_err();
+    assert!(err.to_string().contains("add is declared view but writes storage"));
+
+    let tokens = lexer::lex("(begin #:mutability view (define total 0))").unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    assert!(huff::compile_artifact(&expr, "Mutability").is_err());
+}
+
+#[test]
+fn test_eliminate_unreachable_functions() {
+    let lamina_code = r#"
+    (begin
+      (define-internal (square x) (* x x))
+      (define-internal (cube x) (* x (square x)))
+      (define-internal (twice-cube x) (+ (cube x) (cube x)))
+      (define (area side) (square side))
+      (define (Main) 1))"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let artifact = huff::compile_artifact(&expr, "Area").unwrap();
+
+    // cube is only reached through twice-cube, which nothing calls, and the
+    // dispatcher never routes to Main
+    assert_eq!(artifact.removed, vec!["cube", "twice-cube", "Main"]);
+    let warnings: Vec<String> = artifact.warnings.iter().map(|w| w.to_string()).collect();
+    assert!(warnings.contains(&"warning[unused-function]: cube is internal and only called from functions that are never called, so it is not compiled".to_string()));
+    assert!(warnings.contains(&"warning[unused-function]: twice-cube is internal and never called, so it is not compiled".to_string()));
+
+    let huff_code = huff::compile(&expr, "Area").unwrap();
+    assert_eq!(huff_code.matches("#define macro MAIN_MACRO").count(), 1);
+    assert!(huff_code.contains("#define macro AREA_MACRO"));
+}
+
+#[test]
+fn test_compile_create2() {
+    let lamina_code = r#"
+    (begin
+      (define-for-syntax child (hex->bytevector "0x600160015500"))
+      (define (spawn salt)
+        (deploy-create2 salt (compile-time child)))
+      (define (predicted)
+        (compile-time
+          (create2-address 0x00000000000000000000000000000000deadbeef 1 (init-code-hash child)))))"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let artifact = huff::compile_artifact(&expr, "Factory").unwrap();
+    let code = &artifact.deployed_bytecode;
+
+    // The init code is written to memory left-aligned, then CREATE2 runs
+    let init_code = [0x7f, 0x60, 0x01, 0x60, 0x01, 0x55, 0x00];
+    assert!(code.windows(init_code.len()).any(|w| w == init_code));
+    assert!(code.contains(&0xf5));
+    let spawn = artifact.abi.iter().find(|f| f.name == "spawn").unwrap();
+    assert_eq!(spawn.state_mutability, "nonpayable");
+
+    // A predicted address is pushed as a literal
+    let address = lamina::evm::create2_address(
+        &lamina::evm::parse_address("0x00000000000000000000000000000000deadbeef").unwrap(),
+        &lamina::evm::Word::from_i64(1).to_be_bytes(),
+        &lamina::evm::keccak256(&[0x60, 0x01, 0x60, 0x01, 0x55, 0x00]),
+    );
+    let mut push = vec![0x73];
+    push.extend_from_slice(&address);
+    assert!(code.windows(push.len()).any(|w| w == push));
+    let predicted = artifact.abi.iter().find(|f| f.name == "predicted").unwrap();
+    assert_eq!(predicted.state_mutability, "pure");
+}
+
+#[test]
+fn test_compile_safemath() {
+    let lamina_code = r#"
+    (begin
+      (import (lamina evm safemath))
+      (define (total a b)
+        (add-checked a b))
+      (define (shares amount price)
+        (div-floor (mul-checked amount price) (sub-checked price 1)))
+      (define (blend a b)
+        (addmod (mulmod a b 97) b 97)))"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let huff_code = huff::compile(&expr, "SafeMath").unwrap();
+    assert!(!huff_code.contains("Function not yet implemented"));
+    assert!(huff_code.contains("mulmod"));
+    assert!(huff_code.contains("addmod"));
+
+    let artifact = huff::compile_artifact(&expr, "SafeMath").unwrap();
+    let code = &artifact.deployed_bytecode;
+    // add-checked: dup2 not dup2 gt iszero, then add once the check passes
+    let add_check = [0x81, 0x19, 0x81, 0x11, 0x15];
+    assert!(code.windows(add_check.len()).any(|w| w == add_check));
+    // sub-checked: dup2 dup2 lt iszero
+    let sub_check = [0x81, 0x81, 0x10, 0x15];
+    assert!(code.windows(sub_check.len()).any(|w| w == sub_check));
+    // mul-checked: dup2 dup2 mul dup2 dup2 div dup4 eq dup3 iszero or
+    let mul_check = [0x81, 0x81, 0x02, 0x81, 0x81, 0x04, 0x83, 0x14, 0x82, 0x15, 0x17];
+    assert!(code.windows(mul_check.len()).any(|w| w == mul_check));
+
+    assert!(artifact
+        .abi
+        .iter()
+        .all(|function| function.state_mutability == "pure"));
+}
+
+#[test]
+fn test_compile_value_ranges() {
+    let lamina_code = r#"
+    (begin
+      (import (lamina evm safemath))
+      (define-with-contract (bump x)
+        #:requires (< x 1000)
+        (add-checked x 1))
+      (define (scaled x)
+        (cond ((< x 100) (mul-checked x 3))
+              (else (mul-checked x 5))))
+      (define (spread x) (* (arithmetic-shift x -8) 512))
+      (define (short x)
+        (cond ((> x 10) (sub-checked 5 x))
+              (else 0))))"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let artifact = huff::compile_artifact(&expr, "Ranges").unwrap();
+    let code = &artifact.deployed_bytecode;
+    let count = |pattern: &[u8]| {
+        code.windows(pattern.len())
+            .filter(|w| w == &pattern)
+            .count()
+    };
+
+    // x < 1000 leaves room to add 1, so bump has no check
+    let add_check = [0x81, 0x19, 0x81, 0x11, 0x15];
+    assert_eq!(count(&add_check), 0);
+    // Only the else clause of scaled can overflow
+    let mul_check = [
+        0x81, 0x81, 0x02, 0x81, 0x81, 0x04, 0x83, 0x14, 0x82, 0x15, 0x17,
+    ];
+    assert_eq!(count(&mul_check), 1);
+
+    let warnings: Vec<String> = artifact.warnings.iter().map(|w| w.to_string()).collect();
+    assert_eq!(warnings.len(), 2, "{:?}", warnings);
+    assert!(warnings[0].starts_with(
+        "warning[overflow]: (* (arithmetic-shift x -8) 512) in spread can overflow: the operands reach 0xffff"
+    ));
+    assert!(warnings[0].ends_with("and 512; use mul-checked to revert instead"));
+    assert_eq!(
+        warnings[1],
+        "warning[overflow]: (sub-checked 5 x) in short always overflows and reverts"
+    );
+
+    // Without assertions the precondition isn't checked, so it can't be relied on
+    let options = huff::CompileOptions {
+        strip_assertions: true,
+        ..Default::default()
+    };
+    let stripped = huff::compile_artifact_with_options(&expr, "Ranges", &options).unwrap();
+    let code = &stripped.deployed_bytecode;
+    assert!(code.windows(add_check.len()).any(|w| w == add_check));
+}
+
+#[test]
+fn test_compile_enum() {
+    let lamina_code = r#"
+    (begin
+      (define-enum Phase (Open Closed Settled))
+      (define (next p)
+        (case p
+          ((Open) Closed)
+          ((Closed Settled) Settled)))
+      (define (valid n) (Phase? n))
+      (define (decode n) (Phase->integer (integer->Phase n))))"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let huff_code = huff::compile(&expr, "Auction").unwrap();
+    assert!(!huff_code.contains("Function not yet implemented"));
+
+    let artifact = huff::compile_artifact(&expr, "Auction").unwrap();
+    assert_eq!(artifact.abi.len(), 3);
+    // Phase?: push1 3 gt
+    let predicate = [0x60, 0x03, 0x11];
+    assert!(artifact
+        .deployed_bytecode
+        .windows(predicate.len())
+        .any(|w| w == predicate));
+
+    // A case without else has to cover every variant
+    let lamina_code = r#"
+    (begin
+      (define-enum Phase (Open Closed Settled))
+      (define (open p) (case p ((Open) 1) ((Closed) 0))))"#;
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let err = huff::compile(&expr, "Partial").unwrap_err().to_string();
+    assert!(err.contains("missing Settled"));
+}
+
+#[test]
+fn test_compile_contracts() {
+    let lamina_code = r#"
+    (begin
+      (define-with-contract (withdraw amount)
+        #:requires (> amount 0)
+        #:ensures (< result amount)
+        (- amount 1))
+      (define (positive x) (assert (> x 0) "x must be positive")))"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let huff_code = huff::compile(&expr, "Vault").unwrap();
+    assert!(!huff_code.contains("Function not yet implemented"));
+    assert!(huff_code.contains("withdraw_ensures"));
+
+    // Failed checks revert with Error(string), like Solidity's require: push32 selector
+    let selector = [0x7f, 0x08, 0xc3, 0x79, 0xa0];
+    let checked = huff::compile_artifact(&expr, "Vault").unwrap();
+    assert_eq!(checked.abi.len(), 2);
+    assert!(checked
+        .deployed_bytecode
+        .windows(selector.len())
+        .any(|w| w == selector));
+    assert!(checked
+        .deployed_bytecode
+        .windows(b"x must be positive".len())
+        .any(|w| w == b"x must be positive"));
+
+    let options = huff::CompileOptions {
+        strip_assertions: true,
+        ..Default::default()
+    };
+    let stripped = huff::compile_artifact_with_options(&expr, "Vault", &options).unwrap();
+    assert!(!stripped
+        .deployed_bytecode
+        .windows(b"x must be positive".len())
+        .any(|w| w == b"x must be positive"));
+    assert!(stripped.deployed_bytecode.len() < checked.deployed_bytecode.len());
+}
+
+#[test]
+fn test_compile_interpreter_procedures() {
+    use lamina::embed::Interpreter;
+    use lamina::value::Value;
+
+    // A procedure defined in the interpreter lowers to the define that made it
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval("(define (double amount) (* amount 2))")
+        .unwrap();
+    let double = interpreter.get("double").unwrap();
+    let expr = Value::cons(
+        Value::Symbol("begin".to_string()),
+        Value::cons(double, Value::Nil),
+    );
+
+    let artifact = huff::compile_artifact(&expr, "Doubler").unwrap();
+    assert_eq!(artifact.abi.len(), 1);
+    assert_eq!(artifact.abi[0].name, "double");
+
+    let anonymous = interpreter.eval("(lambda (x) x)").unwrap();
+    let expr = Value::cons(
+        Value::Symbol("begin".to_string()),
+        Value::cons(anonymous, Value::Nil),
+    );
+    assert!(huff::compile(&expr, "Anonymous").is_err());
+}
+
+#[test]
+fn test_compile_interpreter_only_primitives() {
+    let source = r#"
+    (begin
+      (define (get)
+        (display "get")
+        42))
+    "#;
+    let expr = parser::parse(&lexer::lex(source).unwrap()).unwrap();
+    let error = huff::compile_artifact(&expr, "Logger").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Compilation error: display is not available on target evm, guard with cond-expand"
+    );
+
+    let source = r#"
+    (begin
+      (define (get)
+        (cond-expand
+          (interpreter (display "get") 0)
+          (evm 42))))
+    "#;
+    let expr = parser::parse(&lexer::lex(source).unwrap()).unwrap();
+    let artifact = huff::compile_artifact(&expr, "Logger").unwrap();
+    assert_eq!(artifact.abi[0].name, "get");
+}
+
+#[test]
+fn test_size_report_and_budget() {
+    let lamina_code = r#"
+    (begin
+      (define (get-value) (storage-load 0))
+      (define (set-value x) (storage-store 0 (+ x 1))))"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+
+    // The dispatcher and the functions add up to the runtime code
+    let artifact = huff::compile_artifact(&expr, "Sized").unwrap();
+    let total: usize = artifact.sizes.iter().map(|size| size.bytes).sum();
+    assert_eq!(total, artifact.deployed_bytecode.len());
+    assert!(artifact.sizes.iter().any(|size| size.name == "MAIN"));
+    assert!(artifact
+        .sizes
+        .windows(2)
+        .all(|pair| pair[0].bytes >= pair[1].bytes));
+
+    let options = huff::CompileOptions {
+        size_budget: Some(16),
+        ..Default::default()
+    };
+    let err = huff::compile_artifact_with_options(&expr, "Sized", &options)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("over the budget of 16"), "{}", err);
+    assert!(err.contains("set_value"), "{}", err);
+}
+
+#[test]
+fn test_backend() {
+    use lamina_backend_api::{Options, Program, Registry};
+
+    let registry = Registry::default().with(Box::new(lamina_huff::HuffBackend));
+    assert_eq!(registry.names(), vec!["evm"]);
+    assert!(registry.find("llvm").is_none());
+    let backend = registry.find("huff").unwrap();
+    assert_eq!(backend.name(), "evm");
+
+    let tokens = lexer::lex("(begin (define (double x) (* x 2)) (define (unused) 1))").unwrap();
+    let program = Program {
+        name: "Doubler".to_string(),
+        expr: parser::parse(&tokens).unwrap(),
+        source: None,
+    };
+    let artifacts = backend.compile(&program, &Options::default()).unwrap();
+    let names: Vec<&str> = artifacts.files.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "Doubler.huff",
+            "Doubler.abi.json",
+            "Doubler.symbols.json",
+            "Doubler.json"
+        ]
+    );
+    assert_eq!(
+        artifacts.code_size,
+        Some(artifacts.sizes.iter().map(|size| size.bytes).sum())
+    );
+    assert_eq!(
+        backend.diagnostics(&program).unwrap(),
+        huff::warnings(&program.expr, "Doubler").unwrap()
+    );
+
+    let options = Options {
+        size_budget: Some(10),
+        ..Default::default()
+    };
+    let err = backend.compile(&program, &options).unwrap_err().to_string();
+    assert!(err.contains("over the budget of 10"));
+}
+
+#[test]
+fn test_compile_primitives() {
+    use lamina::embed::Interpreter;
+    use lamina::value::Value;
+
+    let interpreter = lamina_huff::primitives::with_primitives(Interpreter::builder()).build();
+    interpreter
+        .eval("(define program '(begin (define (get) 42)))")
+        .unwrap();
+
+    let Value::String(code) = interpreter
+        .eval("(compile-to-huff program \"Counter\")")
+        .unwrap()
+    else {
+        panic!("compile-to-huff should return a string");
+    };
+    let tokens = lexer::lex("(begin (define (get) 42))").unwrap();
+    assert_eq!(
+        code,
+        huff::compile(&parser::parse(&tokens).unwrap(), "Counter").unwrap()
+    );
+
+    let ir = interpreter
+        .eval("(compile-to-ir program)")
+        .unwrap()
+        .to_string();
+    assert!(ir.starts_with("(contract \"Contract\" (macro \"GET\" (takes 0) (returns 1)"));
+    assert!(ir.contains("(push \"0x2a\")"));
+    assert!(ir.contains("(macro \"MAIN\""));
+
+    let err = interpreter
+        .eval("(compile-to-huff '(begin (define total 0) #:mutability view (define total 1)))")
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("view"), "{}", err);
+    assert!(interpreter.eval("(compile-to-ir)").is_err());
+}
+
+#[test]
+fn test_shared_tails() {
+    let lamina_code = r#"
+    (begin
+      (define (one) 1)
+      (define (two) 2)
+      (define (three) 3))"#;
+
+    let tokens = lexer::lex(lamina_code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+
+    // The branches after the first jump to its copy of the return epilogue
+    let huff_code = huff::compile(&expr, "Shared").unwrap();
+    assert_eq!(huff_code.matches("shared_tail_0:").count(), 1);
+    assert_eq!(huff_code.matches("[shared_tail_0] jump").count(), 2);
+    assert_eq!(huff_code.matches("    return\n").count(), 1);
+
+    // mload(0x40), bump it by a word, mstore the result there and return it
+    let epilogue = [
+        0x60, 0x40, 0x51, 0x80, 0x60, 0x20, 0x01, 0x60, 0x40, 0x52, 0x90, 0x81, 0x52, 0x60, 0x20,
+        0x90, 0xf3,
+    ];
+    let artifact = huff::compile_artifact(&expr, "Shared").unwrap();
+    let code = &artifact.deployed_bytecode;
+    assert_eq!(
+        code.windows(epilogue.len())
+            .filter(|w| *w == epilogue)
+            .count(),
+        1
+    );
+    // PUSH2 <shared_tail_0> JUMP
+    let offset = code
+        .windows(epilogue.len())
+        .position(|w| w == epilogue)
+        .unwrap()
+        - 1;
+    assert_eq!(code[offset], 0x5b);
+    let jump = [0x61, 0x00, offset as u8, 0x56];
+    assert_eq!(code.windows(4).filter(|w| *w == jump).count(), 2);
+
+    // A tail that isn't repeated stays where it is
+    let tokens = lexer::lex("(begin (define (one) 1))").unwrap();
+    let huff_code = huff::compile(&parser::parse(&tokens).unwrap(), "Single").unwrap();
+    assert!(!huff_code.contains("shared_tail"));
+}
+
+/// Run `code` on `calldata` with the storage in `storage`, returning the
+/// return data, or the revert data as an error. Covers the opcodes the
+/// dispatcher and simple functions use.
+fn run(code: &[u8], calldata: &[u8], storage: &mut Vec<(Word, Word)>) -> Result<Vec<u8>, Vec<u8>> {
+    let word = |bytes: &[u8]| Word::from_be_bytes(bytes).unwrap();
+    let offset = |w: Word| w.to_i64().unwrap() as usize;
+    let mut stack: Vec<Word> = Vec::new();
+    let mut memory: Vec<u8> = Vec::new();
+    let mut pc = 0;
+    loop {
+        let op = code.get(pc).copied().unwrap_or(0x00);
+        pc += 1;
+        let mut pop = || stack.pop().expect("stack underflow");
+        match op {
+            0x00 => return Ok(Vec::new()),
+            0x01..=0x04 | 0x06 | 0x10 | 0x11 | 0x14 | 0x16..=0x18 | 0x1b | 0x1c => {
+                let (a, b) = (pop(), pop());
+                let bool_word = |b: bool| Word::from_i64(b as i64);
+                stack.push(match op {
+                    0x01 => a.wrapping_add(b),
+                    0x02 => a.wrapping_mul(b),
+                    0x03 => a.wrapping_sub(b),
+                    0x04 => a.div_rem(b).0,
+                    0x06 => a.div_rem(b).1,
+                    0x10 => bool_word(a < b),
+                    0x11 => bool_word(a > b),
+                    0x14 => bool_word(a == b),
+                    0x16 => a & b,
+                    0x17 => a | b,
+                    0x18 => a ^ b,
+                    0x1b => b.shift_left(a.to_i64().unwrap_or(256).min(256) as u32),
+                    _ => b.shift_right(a.to_i64().unwrap_or(256).min(256) as u32),
+                });
+            }
+            0x15 => {
+                let a = pop();
+                stack.push(Word::from_i64(a.is_zero() as i64));
+            }
+            0x19 => {
+                let a = pop();
+                stack.push(!a);
+            }
+            0x34 => stack.push(Word::ZERO),
+            0x35 => {
+                let start = offset(pop());
+                let mut bytes = [0u8; 32];
+                for (i, byte) in bytes.iter_mut().enumerate() {
+                    *byte = calldata.get(start + i).copied().unwrap_or(0);
+                }
+                stack.push(word(&bytes));
+            }
+            0x36 => stack.push(Word::from_i64(calldata.len() as i64)),
+            0x50 => {
+                pop();
+            }
+            0x51 | 0x52 => {
+                let start = offset(pop());
+                if memory.len() < start + 32 {
+                    memory.resize(start + 32, 0);
+                }
+                if op == 0x51 {
+                    stack.push(word(&memory[start..start + 32]));
+                } else {
+                    let value = pop();
+                    memory[start..start + 32].copy_from_slice(&value.to_be_bytes());
+                }
+            }
+            0x54 => {
+                let slot = pop();
+                let value = storage.iter().find(|(s, _)| *s == slot).map(|(_, v)| *v);
+                stack.push(value.unwrap_or(Word::ZERO));
+            }
+            0x55 => {
+                let (slot, value) = (pop(), pop());
+                storage.retain(|(s, _)| *s != slot);
+                storage.push((slot, value));
+            }
+            0x56 | 0x57 => {
+                let target = offset(pop());
+                if op == 0x56 || !pop().is_zero() {
+                    assert_eq!(code[target], 0x5b, "jump to a non-JUMPDEST at {}", target);
+                    pc = target;
+                }
+            }
+            0x5b => {}
+            0x5f => stack.push(Word::ZERO),
+            0x60..=0x7f => {
+                let size = (op - 0x5f) as usize;
+                stack.push(word(&code[pc..pc + size]));
+                pc += size;
+            }
+            0x80..=0x8f => {
+                let value = stack[stack.len() - 1 - (op - 0x80) as usize];
+                stack.push(value);
+            }
+            0x90..=0x9f => {
+                let top = stack.len() - 1;
+                stack.swap(top, top - 1 - (op - 0x90) as usize);
+            }
+            0xf3 | 0xfd => {
+                let (start, size) = (offset(pop()), offset(pop()));
+                if memory.len() < start + size {
+                    memory.resize(start + size, 0);
+                }
+                let data = memory[start..start + size].to_vec();
+                return if op == 0xf3 { Ok(data) } else { Err(data) };
+            }
+            _ => panic!("unsupported opcode 0x{:02x} at {}", op, pc - 1),
+        }
+    }
+}
+
+/// Calldata calling the function with `selector` on word arguments
+fn calldata(selector: u32, args: &[i64]) -> Vec<u8> {
+    let mut data = selector.to_be_bytes().to_vec();
+    for arg in args {
+        data.extend_from_slice(&Word::from_i64(*arg).to_be_bytes());
+    }
+    data
+}
+
+#[test]
+fn test_dispatch_runs_each_function() {
+    let tokens = lexer::lex(
+        r#"(begin
+             (define value-slot 0)
+             (define (get-value) (storage-load value-slot))
+             (define (set-value v) (begin (storage-store value-slot v) (storage-load value-slot)))
+             (define (double x) (* x 2))
+             (define (answer) 42)
+             (define (increment)
+               (begin
+                 (define current (storage-load value-slot))
+                 (storage-store value-slot (+ current 1))
+                 (storage-load value-slot))))"#,
+    )
+    .unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let artifact = huff::compile_artifact(&expr, "Dispatch").unwrap();
+    let code = &artifact.deployed_bytecode;
+    let selector = |name: &str| {
+        artifact
+            .abi
+            .iter()
+            .find(|function| function.name == name)
+            .unwrap()
+            .selector
+    };
+    let returned =
+        |result: Result<Vec<u8>, Vec<u8>>| Word::from_be_bytes(&result.unwrap()).unwrap();
+
+    // Each selector reaches its own function
+    let mut storage = Vec::new();
+    let result = run(code, &calldata(selector("answer"), &[]), &mut storage);
+    assert_eq!(returned(result), Word::from_i64(42));
+    let result = run(code, &calldata(selector("double"), &[21]), &mut storage);
+    assert_eq!(returned(result), Word::from_i64(42));
+    let result = run(code, &calldata(selector("setValue"), &[7]), &mut storage);
+    assert_eq!(returned(result), Word::from_i64(7));
+    let result = run(code, &calldata(selector("getValue"), &[]), &mut storage);
+    assert_eq!(returned(result), Word::from_i64(7));
+    for count in 8..=9 {
+        let result = run(code, &calldata(selector("increment"), &[]), &mut storage);
+        assert_eq!(returned(result), Word::from_i64(count));
+    }
+    let result = run(code, &calldata(selector("getValue"), &[]), &mut storage);
+    assert_eq!(returned(result), Word::from_i64(9));
+    assert_eq!(storage, vec![(Word::ZERO, Word::from_i64(9))]);
+
+    // An unknown selector, or no selector at all, reverts
+    assert_eq!(
+        run(code, &calldata(0xdeadbeef, &[]), &mut storage),
+        Err(Vec::new())
+    );
+    assert_eq!(run(code, &[], &mut storage), Err(Vec::new()));
+}
@@ -0,0 +1,117 @@
+// Intermediate representation of Lamina programs
+//
+// A program lowers to the top-level definitions it makes and the expressions
+// it evaluates. The derived forms (`let*`, `cond`, `when`, named `let`
+// and the rest) and macros are gone by then, so a backend only has the
+// handful of forms of `Expr` to compile:
+//
+//   (define (double x) (* x 2))     Def::Function { name: "double",
+//                                     lambda: Lambda { params: [x.0], .. } }
+//   (display (double 21))           Call(Global("display"),
+//                                     [Call(Global("double"), [Const(21)])])
+//
+// Every variable a program binds locally, as a parameter or with `let`,
+// `letrec` or an internal `define`, is renamed apart: it gets an id no other
+// binding in the program has, so a backend never needs scopes to tell two
+// variables of the same name apart. Top-level definitions and the
+// primitives keep their names as `Global`s.
+//
+// `lower` builds a `Program` from the forms `lamina::parser` returns; see
+// there for the forms it accepts. `printer` writes a program as text, which
+// `lx ir` prints, and `parser` reads the text back.
+
+use lamina::evaluator::equality::is_equal;
+use lamina::value::Value;
+
+pub mod lower;
+pub mod parser;
+pub mod printer;
+
+pub use lower::{lower, lower_source};
+
+/// A program: its top-level definitions, made in order, then its top-level
+/// expressions, evaluated in order
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Program {
+    pub defs: Vec<Def>,
+    pub body: Vec<Expr>,
+}
+
+/// A top-level definition
+#[derive(Clone, Debug, PartialEq)]
+pub enum Def {
+    /// `(define (name param ...) body ...)`, or a `define` of a `lambda`
+    Function { name: String, lambda: Lambda },
+    /// `(define name value)` of anything else
+    Global { name: String, value: Expr },
+}
+
+impl Def {
+    pub fn name(&self) -> &str {
+        match self {
+            Def::Function { name, .. } | Def::Global { name, .. } => name,
+        }
+    }
+}
+
+/// A local variable, renamed apart by its id
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Var {
+    /// The name in the source
+    pub name: String,
+    /// Unique among the variables of the program
+    pub id: usize,
+}
+
+/// A procedure
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lambda {
+    pub params: Vec<Var>,
+    /// Bound to the list of the arguments past `params`, if any
+    pub rest: Option<Var>,
+    pub body: Box<Expr>,
+}
+
+/// An expression
+#[derive(Clone, Debug)]
+pub enum Expr {
+    /// A literal or quoted datum
+    Const(Value),
+    Local(Var),
+    /// A top-level definition or a primitive
+    Global(String),
+    If(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// Each expression in order, to the value of the last
+    Seq(Vec<Expr>),
+    Lambda(Lambda),
+    /// Bind the variables to the values, all evaluated first, in the body
+    Let(Vec<(Var, Expr)>, Box<Expr>),
+    /// Bind the variables to the values in order, each value in the scope of
+    /// all the variables, as `letrec*` does
+    Letrec(Vec<(Var, Expr)>, Box<Expr>),
+    SetLocal(Var, Box<Expr>),
+    SetGlobal(String, Box<Expr>),
+    Call(Box<Expr>, Vec<Expr>),
+}
+
+impl PartialEq for Expr {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            // Constants are data, the same when they are `equal?`, while
+            // `Value`'s own equality tells vectors apart by identity
+            (Expr::Const(a), Expr::Const(b)) => is_equal(a, b),
+            (Expr::Local(a), Expr::Local(b)) => a == b,
+            (Expr::Global(a), Expr::Global(b)) => a == b,
+            (Expr::If(a, b, c), Expr::If(x, y, z)) => a == x && b == y && c == z,
+            (Expr::Seq(a), Expr::Seq(b)) => a == b,
+            (Expr::Lambda(a), Expr::Lambda(b)) => a == b,
+            (Expr::Let(a, b), Expr::Let(x, y)) | (Expr::Letrec(a, b), Expr::Letrec(x, y)) => {
+                a == x && b == y
+            }
+            (Expr::SetLocal(a, b), Expr::SetLocal(x, y)) => a == x && b == y,
+            (Expr::SetGlobal(a, b), Expr::SetGlobal(x, y)) => a == x && b == y,
+            (Expr::Call(a, b), Expr::Call(x, y)) => a == x && b == y,
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,587 @@
+// Lowering programs to the IR
+//
+// `lower` expands a program first, which runs its compile-time forms and
+// expands its macros, then turns what is left into a `Program`. Of the
+// special forms it knows the core ones, `quote`, `if`, `define`, `set!`,
+// `lambda` and `begin`, and rewrites these derived ones into `Expr`s:
+//
+//   (let name ((v init) ...) body ...)   a `Letrec` of the procedure, called
+//   (let* ((v init) ...) body ...)       nested `Let`s
+//   (when c e ...) (unless c e ...)      `If`
+//   (cond clause ...)                    `If`s, a `=>` clause keeping the
+//                                        value of its test in a fresh variable
+//   (and e ...)                          `If`s
+//   (or e ...)                           `If`s, each value but the last kept
+//                                        in a fresh variable
+//
+// Every other special form fails to lower, naming the form. So does a form
+// that is malformed, a variable bound twice by the same form, a `set!` of a
+// variable nothing defines, and a `define` anywhere but at the top level or
+// at the start of a body. The definitions at the start of a body become a
+// `Letrec` around the rest of it.
+
+use std::collections::HashSet;
+
+use lamina::error::Error;
+use lamina::evaluator::environment::setup_initial_env;
+use lamina::expand::expand;
+use lamina::value::Value;
+use lamina::{lexer, parser};
+
+use crate::{Def, Expr, Lambda, Program, Var};
+
+/// Lex, parse and lower the program in `source`, all its top-level forms
+pub fn lower_source(source: &str) -> Result<Program, Error> {
+    let tokens = lexer::lex(source)?;
+    let forms = parser::parse_all(&tokens)?;
+    let begin = Value::Symbol("begin".to_string());
+    lower(&Value::list(std::iter::once(begin).chain(forms)))
+}
+
+/// Lower a program, as `lamina::parser` returns it, to the IR
+pub fn lower(expr: &Value) -> Result<Program, Error> {
+    let expanded = expand(expr)?;
+    let mut forms = Vec::new();
+    splice_begins(&expanded, &mut forms);
+    Lowering::new(&forms).program(forms)
+}
+
+/// The value of a `define`
+enum Definition {
+    Value(Value),
+    /// The parameters and body of `(define (name . params) body ...)`, and
+    /// the form
+    Procedure(Value, Vec<Value>, Value),
+}
+
+struct Lowering {
+    /// The names the interpreter binds to special forms
+    special_forms: HashSet<String>,
+    /// The names defined at the top level
+    globals: HashSet<String>,
+    /// The local variables in scope, innermost last
+    scope: Vec<(String, Var)>,
+    next_id: usize,
+}
+
+impl Lowering {
+    fn new(forms: &[Value]) -> Self {
+        let special_forms = setup_initial_env()
+            .borrow()
+            .bindings
+            .iter()
+            .filter(|(name, value)| matches!(value, Value::Symbol(form) if form == *name))
+            .map(|(name, _)| name.clone())
+            .collect();
+        let globals = forms
+            .iter()
+            .filter_map(|form| match list_items(form)?.as_slice() {
+                [Value::Symbol(head), target, ..] if head == "define" => match target {
+                    Value::Symbol(name) => Some(name.clone()),
+                    Value::Pair(signature) => match &signature.0 {
+                        Value::Symbol(name) => Some(name.clone()),
+                        _ => None,
+                    },
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect();
+        Lowering {
+            special_forms,
+            globals,
+            scope: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    fn program(mut self, forms: Vec<Value>) -> Result<Program, Error> {
+        let mut program = Program::default();
+        let mut defined = HashSet::new();
+        for form in forms {
+            let Some((name, definition)) = self.definition(&form)? else {
+                program.body.push(self.expr(&form)?);
+                continue;
+            };
+            if !defined.insert(name.clone()) {
+                return Err(error(format!("{} is defined twice", name)));
+            }
+            program.defs.push(match self.define(definition)? {
+                Expr::Lambda(lambda) => Def::Function { name, lambda },
+                value => Def::Global { name, value },
+            });
+        }
+        Ok(program)
+    }
+
+    fn expr(&mut self, expr: &Value) -> Result<Expr, Error> {
+        match expr {
+            Value::Symbol(name) => self.variable(name),
+            Value::Pair(pair) => {
+                let items = list_items(expr).ok_or_else(|| malformed(expr))?;
+                if let Value::Symbol(head) = &pair.0 {
+                    if self.is_special_form(head) {
+                        return self.special_form(head, &items[1..], expr);
+                    }
+                }
+                let function = self.expr(&items[0])?;
+                let args = self.exprs(&items[1..])?;
+                Ok(Expr::Call(Box::new(function), args))
+            }
+            _ => Ok(Expr::Const(expr.clone())),
+        }
+    }
+
+    fn exprs(&mut self, exprs: &[Value]) -> Result<Vec<Expr>, Error> {
+        exprs.iter().map(|expr| self.expr(expr)).collect()
+    }
+
+    fn variable(&self, name: &str) -> Result<Expr, Error> {
+        if let Some(var) = self.lookup(name) {
+            return Ok(Expr::Local(var.clone()));
+        }
+        if self.special_forms.contains(name) {
+            return Err(error(format!("{} is a special form, not a value", name)));
+        }
+        Ok(Expr::Global(name.to_string()))
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Var> {
+        self.scope
+            .iter()
+            .rev()
+            .find(|(bound, _)| bound == name)
+            .map(|(_, var)| var)
+    }
+
+    fn is_special_form(&self, name: &str) -> bool {
+        self.lookup(name).is_none() && self.special_forms.contains(name)
+    }
+
+    fn special_form(&mut self, name: &str, args: &[Value], form: &Value) -> Result<Expr, Error> {
+        match (name, args) {
+            ("quote", [datum]) => Ok(Expr::Const(datum.clone())),
+            ("if", [test, then]) => Ok(Expr::If(
+                Box::new(self.expr(test)?),
+                Box::new(self.expr(then)?),
+                Box::new(Expr::Const(Value::Nil)),
+            )),
+            ("if", [test, then, otherwise]) => Ok(Expr::If(
+                Box::new(self.expr(test)?),
+                Box::new(self.expr(then)?),
+                Box::new(self.expr(otherwise)?),
+            )),
+            ("define", _) => Err(error(format!(
+                "{}: define is only allowed at the top level or at the start of a body",
+                form
+            ))),
+            ("set!", [Value::Symbol(target), value]) => {
+                let value = Box::new(self.expr(value)?);
+                match self.lookup(target) {
+                    Some(var) => Ok(Expr::SetLocal(var.clone(), value)),
+                    None if self.globals.contains(target) => {
+                        Ok(Expr::SetGlobal(target.clone(), value))
+                    }
+                    None => Err(error(format!("set! of undefined variable {}", target))),
+                }
+            }
+            ("lambda", [params, body @ ..]) => Ok(Expr::Lambda(self.lambda(params, body, form)?)),
+            ("begin", exprs) => Ok(sequence(self.exprs(exprs)?)),
+            ("let", [Value::Symbol(name), bindings, body @ ..]) => {
+                self.named_let(name, bindings, body, form)
+            }
+            ("let", [bindings, body @ ..]) => {
+                let bindings = self.bindings(bindings, form)?;
+                let mut names = HashSet::new();
+                if let Some((name, _)) = bindings.iter().find(|(name, _)| !names.insert(name)) {
+                    return Err(bound_twice(name, form));
+                }
+                let inits = bindings
+                    .iter()
+                    .map(|(_, init)| self.expr(init))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let depth = self.scope.len();
+                let vars: Vec<Var> = bindings.iter().map(|(name, _)| self.bind(name)).collect();
+                let body = self.body(body, form)?;
+                self.scope.truncate(depth);
+                Ok(Expr::Let(
+                    vars.into_iter().zip(inits).collect(),
+                    Box::new(body),
+                ))
+            }
+            ("let*", [bindings, body @ ..]) => {
+                let depth = self.scope.len();
+                let mut lets = Vec::new();
+                for (name, init) in self.bindings(bindings, form)? {
+                    let init = self.expr(&init)?;
+                    lets.push((self.bind(&name), init));
+                }
+                let body = self.body(body, form)?;
+                self.scope.truncate(depth);
+                Ok(lets.into_iter().rev().fold(body, |body, binding| {
+                    Expr::Let(vec![binding], Box::new(body))
+                }))
+            }
+            ("letrec", [bindings, body @ ..]) => {
+                let bindings = self.bindings(bindings, form)?;
+                let depth = self.scope.len();
+                let mut vars = Vec::new();
+                for (name, _) in &bindings {
+                    if vars.iter().any(|var: &Var| &var.name == name) {
+                        return Err(bound_twice(name, form));
+                    }
+                    vars.push(self.bind(name));
+                }
+                let inits = bindings
+                    .iter()
+                    .map(|(_, init)| self.expr(init))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let body = self.body(body, form)?;
+                self.scope.truncate(depth);
+                Ok(Expr::Letrec(
+                    vars.into_iter().zip(inits).collect(),
+                    Box::new(body),
+                ))
+            }
+            ("when" | "unless", [test, body @ ..]) if !body.is_empty() => {
+                let test = Box::new(self.expr(test)?);
+                let body = Box::new(sequence(self.exprs(body)?));
+                let nothing = Box::new(Expr::Const(Value::Nil));
+                Ok(match name {
+                    "when" => Expr::If(test, body, nothing),
+                    _ => Expr::If(test, nothing, body),
+                })
+            }
+            ("cond", clauses) => self.cond(clauses, form),
+            ("and", exprs) => self.logic(true, exprs),
+            ("or", exprs) => self.logic(false, exprs),
+            ("quote" | "if" | "set!" | "lambda" | "let" | "let*", _)
+            | ("letrec" | "when" | "unless", _) => Err(malformed(form)),
+            _ => Err(error(format!("{} can't be lowered to the IR", name))),
+        }
+    }
+
+    fn cond(&mut self, clauses: &[Value], form: &Value) -> Result<Expr, Error> {
+        // Each clause as its test and how it continues when the test holds
+        enum Then {
+            Value,
+            Body(Expr),
+            Apply(Expr),
+        }
+        let mut lowered = Vec::new();
+        for (index, clause) in clauses.iter().enumerate() {
+            let items = list_items(clause).ok_or_else(|| malformed(form))?;
+            match items.as_slice() {
+                [Value::Symbol(head), body @ ..]
+                    if head == "else" && self.lookup(head).is_none() =>
+                {
+                    if body.is_empty() || index != clauses.len() - 1 {
+                        return Err(malformed(form));
+                    }
+                    lowered.push((None, Then::Body(sequence(self.exprs(body)?))));
+                }
+                [test, Value::Symbol(arrow), receiver]
+                    if arrow == "=>" && self.lookup(arrow).is_none() =>
+                {
+                    let test = self.expr(test)?;
+                    lowered.push((Some(test), Then::Apply(self.expr(receiver)?)));
+                }
+                [test] => lowered.push((Some(self.expr(test)?), Then::Value)),
+                [test, body @ ..] => {
+                    let test = self.expr(test)?;
+                    lowered.push((Some(test), Then::Body(sequence(self.exprs(body)?))));
+                }
+                [] => return Err(malformed(form)),
+            }
+        }
+
+        let mut rest = Expr::Const(Value::Nil);
+        for (test, then) in lowered.into_iter().rev() {
+            rest = match (test, then) {
+                (None, Then::Body(body)) => body,
+                (Some(test), Then::Body(body)) => {
+                    Expr::If(Box::new(test), Box::new(body), Box::new(rest))
+                }
+                (Some(test), then) => {
+                    let value = self.fresh("cond");
+                    let then = match then {
+                        Then::Apply(receiver) => {
+                            Expr::Call(Box::new(receiver), vec![Expr::Local(value.clone())])
+                        }
+                        _ => Expr::Local(value.clone()),
+                    };
+                    Expr::Let(
+                        vec![(value.clone(), test)],
+                        Box::new(Expr::If(
+                            Box::new(Expr::Local(value)),
+                            Box::new(then),
+                            Box::new(rest),
+                        )),
+                    )
+                }
+                (None, _) => unreachable!("else clauses have a body"),
+            };
+        }
+        Ok(rest)
+    }
+
+    /// `and` when `and` holds, `or` otherwise, stopping at the first value
+    /// that decides it
+    fn logic(&mut self, and: bool, exprs: &[Value]) -> Result<Expr, Error> {
+        let Some((first, rest)) = exprs.split_first() else {
+            return Ok(Expr::Const(Value::Boolean(and)));
+        };
+        let first = self.expr(first)?;
+        if rest.is_empty() {
+            return Ok(first);
+        }
+        let rest = Box::new(self.logic(and, rest)?);
+        if and {
+            // Only #f is false, so a false value is always #f
+            return Ok(Expr::If(
+                Box::new(first),
+                rest,
+                Box::new(Expr::Const(Value::Boolean(false))),
+            ));
+        }
+        let value = self.fresh("or");
+        Ok(Expr::Let(
+            vec![(value.clone(), first)],
+            Box::new(Expr::If(
+                Box::new(Expr::Local(value.clone())),
+                Box::new(Expr::Local(value)),
+                rest,
+            )),
+        ))
+    }
+
+    fn named_let(
+        &mut self,
+        name: &str,
+        bindings: &Value,
+        body: &[Value],
+        form: &Value,
+    ) -> Result<Expr, Error> {
+        let bindings = self.bindings(bindings, form)?;
+        let inits = bindings
+            .iter()
+            .map(|(_, init)| self.expr(init))
+            .collect::<Result<Vec<_>, _>>()?;
+        let params = Value::list(bindings.into_iter().map(|(name, _)| Value::Symbol(name)));
+
+        let depth = self.scope.len();
+        let procedure = self.bind(name);
+        let lambda = self.lambda(&params, body, form)?;
+        self.scope.truncate(depth);
+        let letrec = Expr::Letrec(
+            vec![(procedure.clone(), Expr::Lambda(lambda))],
+            Box::new(Expr::Local(procedure)),
+        );
+        Ok(Expr::Call(Box::new(letrec), inits))
+    }
+
+    fn lambda(&mut self, params: &Value, body: &[Value], form: &Value) -> Result<Lambda, Error> {
+        let depth = self.scope.len();
+        let mut vars = Vec::new();
+        let mut rest = params;
+        while let Value::Pair(param) = rest {
+            let Value::Symbol(name) = &param.0 else {
+                return Err(malformed(form));
+            };
+            if vars.iter().any(|var: &Var| &var.name == name) {
+                return Err(bound_twice(name, form));
+            }
+            vars.push(self.bind(name));
+            rest = &param.1;
+        }
+        let rest = match rest {
+            Value::Nil => None,
+            Value::Symbol(name) if vars.iter().any(|var| &var.name == name) => {
+                return Err(bound_twice(name, form))
+            }
+            Value::Symbol(name) => Some(self.bind(name)),
+            _ => return Err(malformed(form)),
+        };
+        let body = self.body(body, form)?;
+        self.scope.truncate(depth);
+        Ok(Lambda {
+            params: vars,
+            rest,
+            body: Box::new(body),
+        })
+    }
+
+    /// The body of a procedure or a `let`, its definitions first
+    fn body(&mut self, body: &[Value], form: &Value) -> Result<Expr, Error> {
+        let mut forms = Vec::new();
+        for expr in body {
+            if self.is_begin(expr) {
+                splice_begins(expr, &mut forms);
+            } else {
+                forms.push(expr.clone());
+            }
+        }
+
+        let mut definitions = Vec::new();
+        let mut exprs = Vec::new();
+        for expr in &forms {
+            match self.definition(expr)? {
+                Some(_) if !exprs.is_empty() => {
+                    return Err(error(format!(
+                        "{}: define after an expression in a body",
+                        expr
+                    )))
+                }
+                Some(definition) => definitions.push(definition),
+                None => exprs.push(expr),
+            }
+        }
+        if exprs.is_empty() {
+            return Err(error(format!("{}: body has no expression", form)));
+        }
+
+        let depth = self.scope.len();
+        let mut vars = Vec::new();
+        for (name, _) in &definitions {
+            if vars.iter().any(|var: &Var| &var.name == name) {
+                return Err(error(format!("{} is defined twice", name)));
+            }
+            vars.push(self.bind(name));
+        }
+        let mut bindings = Vec::new();
+        for (var, (_, definition)) in vars.into_iter().zip(definitions) {
+            bindings.push((var, self.define(definition)?));
+        }
+        let exprs = exprs
+            .into_iter()
+            .map(|expr| self.expr(expr))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.scope.truncate(depth);
+
+        let body = sequence(exprs);
+        if bindings.is_empty() {
+            return Ok(body);
+        }
+        Ok(Expr::Letrec(bindings, Box::new(body)))
+    }
+
+    fn is_begin(&self, expr: &Value) -> bool {
+        matches!(expr, Value::Pair(pair)
+            if matches!(&pair.0, Value::Symbol(head) if head == "begin")
+                && self.is_special_form("begin"))
+    }
+
+    /// The name and value `form` defines, if it is a `define`
+    fn definition(&self, form: &Value) -> Result<Option<(String, Definition)>, Error> {
+        let Value::Pair(pair) = form else {
+            return Ok(None);
+        };
+        if !matches!(&pair.0, Value::Symbol(head) if head == "define")
+            || !self.is_special_form("define")
+        {
+            return Ok(None);
+        }
+        let items = list_items(form).ok_or_else(|| malformed(form))?;
+        match &items[1..] {
+            [Value::Symbol(name), value] => {
+                Ok(Some((name.clone(), Definition::Value(value.clone()))))
+            }
+            [Value::Pair(signature), body @ ..] if !body.is_empty() => match &signature.0 {
+                Value::Symbol(name) => Ok(Some((
+                    name.clone(),
+                    Definition::Procedure(signature.1.clone(), body.to_vec(), form.clone()),
+                ))),
+                _ => Err(malformed(form)),
+            },
+            _ => Err(malformed(form)),
+        }
+    }
+
+    fn define(&mut self, definition: Definition) -> Result<Expr, Error> {
+        match definition {
+            Definition::Value(value) => self.expr(&value),
+            Definition::Procedure(params, body, form) => {
+                Ok(Expr::Lambda(self.lambda(&params, &body, &form)?))
+            }
+        }
+    }
+
+    /// The `(name init)` pairs of a `let`
+    fn bindings(&self, bindings: &Value, form: &Value) -> Result<Vec<(String, Value)>, Error> {
+        list_items(bindings)
+            .ok_or_else(|| malformed(form))?
+            .iter()
+            .map(|binding| match list_items(binding).as_deref() {
+                Some([Value::Symbol(name), init]) => Ok((name.clone(), init.clone())),
+                _ => Err(malformed(form)),
+            })
+            .collect()
+    }
+
+    /// A fresh variable for `name`, in scope until the scope is truncated
+    fn bind(&mut self, name: &str) -> Var {
+        let var = self.fresh(name);
+        self.scope.push((name.to_string(), var.clone()));
+        var
+    }
+
+    /// A variable no other has the id of
+    fn fresh(&mut self, name: &str) -> Var {
+        let var = Var {
+            name: name.to_string(),
+            id: self.next_id,
+        };
+        self.next_id += 1;
+        var
+    }
+}
+
+/// `expr` as the forms of a `begin` it splices into, so a top-level `begin`
+/// and those nested in it are flattened
+fn splice_begins(expr: &Value, forms: &mut Vec<Value>) {
+    match expr {
+        Value::Nil => {}
+        Value::Pair(pair) if matches!(&pair.0, Value::Symbol(head) if head == "begin") => {
+            match list_items(expr) {
+                Some(items) => {
+                    for item in &items[1..] {
+                        splice_begins(item, forms);
+                    }
+                }
+                None => forms.push(expr.clone()),
+            }
+        }
+        _ => forms.push(expr.clone()),
+    }
+}
+
+/// The expressions of a sequence as one
+fn sequence(mut exprs: Vec<Expr>) -> Expr {
+    match exprs.len() {
+        0 => Expr::Const(Value::Nil),
+        1 => exprs.remove(0),
+        _ => Expr::Seq(exprs),
+    }
+}
+
+pub(crate) fn list_items(value: &Value) -> Option<Vec<Value>> {
+    let mut items = Vec::new();
+    let mut rest = value;
+    while let Value::Pair(item) = rest {
+        items.push(item.0.clone());
+        rest = &item.1;
+    }
+    matches!(rest, Value::Nil).then_some(items)
+}
+
+fn error(message: String) -> Error {
+    Error::Compilation(message)
+}
+
+fn malformed(form: &Value) -> Error {
+    error(format!("malformed {}", form))
+}
+
+fn bound_twice(name: &str, form: &Value) -> Error {
+    error(format!("{}: {} is bound twice", form, name))
+}
@@ -0,0 +1,192 @@
+// Reading the textual form of the IR back
+//
+// `parse` reads what `printer::print` writes, see there for the format, and
+// returns the same `Program`. The text is read with Lamina's reader, so
+// comments and whitespace may be anywhere, and constants are any datum it
+// reads. A form of the wrong shape, or with a head the IR has no form for,
+// fails naming the form.
+
+use lamina::error::Error;
+use lamina::lexer;
+use lamina::value::{NumberKind, Value};
+
+use crate::lower::list_items;
+use crate::printer::local_parts;
+use crate::{Def, Expr, Lambda, Program, Var};
+
+/// The program in `text`, a single `(program ...)` form
+pub fn parse(text: &str) -> Result<Program, Error> {
+    let tokens = lexer::lex(text)?;
+    let forms = lamina::parser::parse_all(&tokens)?;
+    let [form] = forms.as_slice() else {
+        return Err(error(format!(
+            "expected one (program ...) form, got {}",
+            forms.len()
+        )));
+    };
+    let items = list_items(form).ok_or_else(|| malformed(form))?;
+    let [Value::Symbol(head), items @ ..] = items.as_slice() else {
+        return Err(malformed(form));
+    };
+    if head != "program" {
+        return Err(error(format!("expected (program ...), got {}", form)));
+    }
+
+    let mut program = Program::default();
+    for item in items {
+        match list_items(item).as_deref() {
+            Some([Value::Symbol(head), ..]) if head == "define" => program.defs.push(def(item)?),
+            _ => program.body.push(expr(item)?),
+        }
+    }
+    Ok(program)
+}
+
+fn def(form: &Value) -> Result<Def, Error> {
+    let items = list_items(form).ok_or_else(|| malformed(form))?;
+    match &items[1..] {
+        [Value::Pair(_), body] => {
+            let signature = list_items(&items[1]).ok_or_else(|| malformed(form))?;
+            let (name, params) = signature.split_first().ok_or_else(|| malformed(form))?;
+            Ok(Def::Function {
+                name: self::name(name).ok_or_else(|| malformed(form))?,
+                lambda: lambda(params, body, form)?,
+            })
+        }
+        [name, value] => Ok(Def::Global {
+            name: self::name(name).ok_or_else(|| malformed(form))?,
+            value: expr(value)?,
+        }),
+        _ => Err(malformed(form)),
+    }
+}
+
+fn expr(form: &Value) -> Result<Expr, Error> {
+    match form {
+        Value::Symbol(symbol) => Ok(match local_parts(symbol) {
+            Some((name, id)) => Expr::Local(Var {
+                name: name.to_string(),
+                id,
+            }),
+            None => Expr::Global(symbol.clone()),
+        }),
+        Value::Pair(_) => {
+            let items = list_items(form).ok_or_else(|| malformed(form))?;
+            let Value::Symbol(head) = &items[0] else {
+                return Err(malformed(form));
+            };
+            match (head.as_str(), &items[1..]) {
+                ("quote", [datum]) => Ok(Expr::Const(datum.clone())),
+                ("local", _) => Ok(Expr::Local(var(form)?)),
+                ("global", [name]) => Ok(Expr::Global(
+                    self::name(name).ok_or_else(|| malformed(form))?,
+                )),
+                ("if", [test, then, otherwise]) => Ok(Expr::If(
+                    Box::new(expr(test)?),
+                    Box::new(expr(then)?),
+                    Box::new(expr(otherwise)?),
+                )),
+                ("seq", exprs) => Ok(Expr::Seq(exprs.iter().map(expr).collect::<Result<_, _>>()?)),
+                ("lambda", [params, body]) => {
+                    let params = list_items(params).ok_or_else(|| malformed(form))?;
+                    Ok(Expr::Lambda(lambda(&params, body, form)?))
+                }
+                ("let", [bindings, body]) => {
+                    Ok(Expr::Let(self::bindings(bindings)?, Box::new(expr(body)?)))
+                }
+                ("letrec", [bindings, body]) => Ok(Expr::Letrec(
+                    self::bindings(bindings)?,
+                    Box::new(expr(body)?),
+                )),
+                ("set!", [target, value]) => {
+                    let value = Box::new(expr(value)?);
+                    match expr(target)? {
+                        Expr::Local(var) => Ok(Expr::SetLocal(var, value)),
+                        Expr::Global(name) => Ok(Expr::SetGlobal(name, value)),
+                        _ => Err(malformed(form)),
+                    }
+                }
+                ("call", [function, args @ ..]) => Ok(Expr::Call(
+                    Box::new(expr(function)?),
+                    args.iter().map(expr).collect::<Result<_, _>>()?,
+                )),
+                ("quote" | "global" | "if" | "lambda" | "let" | "letrec" | "set!" | "call", _) => {
+                    Err(malformed(form))
+                }
+                _ => Err(error(format!("unknown IR form {}", form))),
+            }
+        }
+        Value::Nil => Err(malformed(form)),
+        _ => Ok(Expr::Const(form.clone())),
+    }
+}
+
+fn lambda(params: &[Value], body: &Value, form: &Value) -> Result<Lambda, Error> {
+    let (params, rest) = match params.split_last() {
+        Some((last, init)) => match list_items(last).as_deref() {
+            Some([Value::Symbol(head), rest]) if head == "rest" => (init, Some(var(rest)?)),
+            _ => (params, None),
+        },
+        None => (params, None),
+    };
+    Ok(Lambda {
+        params: params
+            .iter()
+            .map(var)
+            .collect::<Result<_, _>>()
+            .map_err(|_| malformed(form))?,
+        rest,
+        body: Box::new(expr(body)?),
+    })
+}
+
+fn bindings(bindings: &Value) -> Result<Vec<(Var, Expr)>, Error> {
+    list_items(bindings)
+        .ok_or_else(|| malformed(bindings))?
+        .iter()
+        .map(|binding| match list_items(binding).as_deref() {
+            Some([target, value]) => Ok((var(target)?, expr(value)?)),
+            _ => Err(malformed(binding)),
+        })
+        .collect()
+}
+
+/// A local variable, `name.id` or `(local "name" id)`
+fn var(form: &Value) -> Result<Var, Error> {
+    match form {
+        Value::Symbol(symbol) => match local_parts(symbol) {
+            Some((name, id)) => Ok(Var {
+                name: name.to_string(),
+                id,
+            }),
+            None => Err(error(format!("expected a local variable, got {}", symbol))),
+        },
+        _ => match list_items(form).as_deref() {
+            Some([Value::Symbol(head), name, Value::Number(NumberKind::Integer(id))])
+                if head == "local" && *id >= 0 =>
+            {
+                Ok(Var {
+                    name: self::name(name).ok_or_else(|| malformed(form))?,
+                    id: *id as usize,
+                })
+            }
+            _ => Err(malformed(form)),
+        },
+    }
+}
+
+/// A name, written as a symbol or a string
+fn name(form: &Value) -> Option<String> {
+    match form {
+        Value::Symbol(name) | Value::String(name) => Some(name.clone()),
+        _ => None,
+    }
+}
+
+fn error(message: String) -> Error {
+    Error::Parser(message)
+}
+
+fn malformed(form: &Value) -> Error {
+    error(format!("malformed IR form {}", form))
+}
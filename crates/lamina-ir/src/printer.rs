@@ -0,0 +1,250 @@
+// Textual form of the IR
+//
+// A program prints as one `(program ...)` form, its definitions first, then
+// its expressions, each starting on a line of its own:
+//
+//   (program
+//     (define slot 0)
+//     (define (double x.0) (call * x.0 2))
+//     (call display (call double slot)))
+//
+// A local variable is its name and id joined by a dot, `x.0`, and a global
+// its bare name. A name that wouldn't read back that way, such as a local
+// `-`, whose `-.1` reads as a number, or a global `x.1`, which reads as a
+// local, is spelled out as `(local "-" 1)` or `(global "x.1")`. The forms of
+// `Expr` are
+//
+//   (quote datum)  (if c t e)  (seq e ...)  (call f arg ...)  (set! var e)
+//   (lambda (param ... (rest var)) body)
+//   (let ((var e) ...) body)  (letrec ((var e) ...) body)
+//
+// where `(rest var)` is only there when the procedure takes a rest argument,
+// and a `Def::Function` is written `(define (name param ...) body)`. Symbols,
+// lists and `()` are quoted; every other constant is written as Lamina
+// writes it. A form that fits in the line is kept on one, and one that
+// doesn't has its arguments on lines of their own, indented by two. The
+// output depends on nothing but the program, so it can be diffed and read
+// back by `parser::parse` to the same `Program`.
+
+use std::fmt;
+
+use lamina::lexer::{self, Token};
+use lamina::value::Value;
+
+use crate::{Def, Expr, Lambda, Program, Var};
+
+/// The column lines are kept within where they can be
+const WIDTH: usize = 80;
+
+/// `program` in the textual form, ending with a newline
+pub fn print(program: &Program) -> String {
+    let mut items = vec![Sexp::Atom("program".to_string())];
+    items.extend(program.defs.iter().map(def));
+    items.extend(program.body.iter().map(expr));
+    let mut text = String::new();
+    layout(&Sexp::List(items), 0, 0, &mut text);
+    text.push('\n');
+    text
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&print(self))
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut text = String::new();
+        layout(&expr(self), 0, 0, &mut text);
+        f.write_str(&text)
+    }
+}
+
+/// A printed form, before it is laid out on lines
+enum Sexp {
+    Atom(String),
+    List(Vec<Sexp>),
+}
+
+impl Sexp {
+    fn list(head: &str, rest: impl IntoIterator<Item = Sexp>) -> Self {
+        let mut items = vec![Sexp::Atom(head.to_string())];
+        items.extend(rest);
+        Sexp::List(items)
+    }
+
+    fn flat(&self) -> String {
+        match self {
+            Sexp::Atom(atom) => atom.clone(),
+            Sexp::List(items) => {
+                let items: Vec<String> = items.iter().map(Sexp::flat).collect();
+                format!("({})", items.join(" "))
+            }
+        }
+    }
+
+    fn head(&self) -> Option<&str> {
+        match self {
+            Sexp::List(items) => match items.first() {
+                Some(Sexp::Atom(head)) => Some(head),
+                _ => None,
+            },
+            Sexp::Atom(_) => None,
+        }
+    }
+}
+
+fn def(def: &Def) -> Sexp {
+    match def {
+        Def::Function {
+            name: function,
+            lambda,
+        } => {
+            let mut signature = vec![name(function)];
+            signature.extend(params(lambda));
+            Sexp::list("define", [Sexp::List(signature), expr(&lambda.body)])
+        }
+        Def::Global {
+            name: global,
+            value,
+        } => Sexp::list("define", [name(global), expr(value)]),
+    }
+}
+
+fn expr(form: &Expr) -> Sexp {
+    match form {
+        Expr::Const(value @ (Value::Symbol(_) | Value::Pair(_) | Value::Nil)) => {
+            Sexp::list("quote", [Sexp::Atom(value.to_string())])
+        }
+        Expr::Const(value) => Sexp::Atom(value.to_string()),
+        Expr::Local(var) => local(var),
+        Expr::Global(name) => global(name),
+        Expr::If(test, then, otherwise) => {
+            Sexp::list("if", [expr(test), expr(then), expr(otherwise)])
+        }
+        Expr::Seq(exprs) => Sexp::list("seq", exprs.iter().map(expr)),
+        Expr::Lambda(lambda) => {
+            Sexp::list("lambda", [Sexp::List(params(lambda)), expr(&lambda.body)])
+        }
+        Expr::Let(lets, body) => Sexp::list("let", [bindings(lets), expr(body)]),
+        Expr::Letrec(lets, body) => Sexp::list("letrec", [bindings(lets), expr(body)]),
+        Expr::SetLocal(var, value) => Sexp::list("set!", [local(var), expr(value)]),
+        Expr::SetGlobal(name, value) => Sexp::list("set!", [global(name), expr(value)]),
+        Expr::Call(function, args) => Sexp::list(
+            "call",
+            std::iter::once(expr(function)).chain(args.iter().map(expr)),
+        ),
+    }
+}
+
+fn params(lambda: &Lambda) -> Vec<Sexp> {
+    let mut params: Vec<Sexp> = lambda.params.iter().map(local).collect();
+    if let Some(rest) = &lambda.rest {
+        params.push(Sexp::list("rest", [local(rest)]));
+    }
+    params
+}
+
+fn bindings(bindings: &[(Var, Expr)]) -> Sexp {
+    Sexp::List(
+        bindings
+            .iter()
+            .map(|(var, value)| Sexp::List(vec![local(var), expr(value)]))
+            .collect(),
+    )
+}
+
+fn local(var: &Var) -> Sexp {
+    let text = format!("{}.{}", var.name, var.id);
+    if reads_as_symbol(&text) {
+        return Sexp::Atom(text);
+    }
+    Sexp::list("local", [string(&var.name), Sexp::Atom(var.id.to_string())])
+}
+
+fn global(name: &str) -> Sexp {
+    if reads_as_symbol(name) && local_parts(name).is_none() {
+        return Sexp::Atom(name.to_string());
+    }
+    Sexp::list("global", [string(name)])
+}
+
+/// The name of a definition
+fn name(name: &str) -> Sexp {
+    if reads_as_symbol(name) {
+        return Sexp::Atom(name.to_string());
+    }
+    string(name)
+}
+
+fn string(text: &str) -> Sexp {
+    Sexp::Atom(Value::String(text.to_string()).to_string())
+}
+
+fn reads_as_symbol(text: &str) -> bool {
+    matches!(lexer::lex(text).as_deref(), Ok([Token::Symbol(symbol)]) if symbol == text)
+}
+
+/// The name and id of the local variable `symbol` spells, if it spells one
+pub(crate) fn local_parts(symbol: &str) -> Option<(&str, usize)> {
+    let (name, id) = symbol.rsplit_once('.')?;
+    if name.is_empty() || id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((name, id.parse().ok()?))
+}
+
+/// Write `sexp` to `text`, which ends at column `indent`, with `closing`
+/// parentheses to follow it on its last line
+fn layout(sexp: &Sexp, indent: usize, closing: usize, text: &mut String) {
+    let flat = sexp.flat();
+    let items = match sexp {
+        Sexp::List(items) if !items.is_empty() => items,
+        _ => {
+            text.push_str(&flat);
+            return;
+        }
+    };
+    // The program always has a line for each definition and expression
+    if sexp.head() != Some("program") && indent + flat.len() + closing <= WIDTH {
+        text.push_str(&flat);
+        return;
+    }
+
+    text.push('(');
+    let last = items.len() - 1;
+    // The closing parentheses after the item at `index`
+    let after = |index: usize| if index == last { closing + 1 } else { 0 };
+    match sexp.head() {
+        // A list of bindings or parameters, one to a line
+        None => {
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    text.push('\n');
+                    text.push_str(&" ".repeat(indent + 1));
+                }
+                layout(item, indent + 1, after(index), text);
+            }
+        }
+        Some(head) => {
+            text.push_str(head);
+            // What the form binds or tests stays on the line of its head
+            let same_line = match head {
+                "program" | "seq" => 0,
+                _ => 1,
+            };
+            for (index, item) in items.iter().enumerate().skip(1) {
+                if index <= same_line {
+                    text.push(' ');
+                    layout(item, indent + head.len() + 2, after(index), text);
+                } else {
+                    text.push('\n');
+                    text.push_str(&" ".repeat(indent + 2));
+                    layout(item, indent + 2, after(index), text);
+                }
+            }
+        }
+    }
+    text.push(')');
+}
@@ -0,0 +1,225 @@
+use lamina::value::Value;
+use lamina_ir::{lower_source, Def, Expr, Lambda, Var};
+
+fn var(name: &str, id: usize) -> Var {
+    Var {
+        name: name.to_string(),
+        id,
+    }
+}
+
+fn local(name: &str, id: usize) -> Expr {
+    Expr::Local(var(name, id))
+}
+
+fn global(name: &str) -> Expr {
+    Expr::Global(name.to_string())
+}
+
+fn int(n: i64) -> Expr {
+    Expr::Const(Value::from(n))
+}
+
+fn call(function: Expr, args: Vec<Expr>) -> Expr {
+    Expr::Call(Box::new(function), args)
+}
+
+fn lower_error(source: &str) -> String {
+    lower_source(source).unwrap_err().to_string()
+}
+
+#[test]
+fn test_lower_definitions() {
+    let program = lower_source(
+        r#"
+        (define slot 0)
+        (define (double x) (* x 2))
+        (define triple (lambda (x) (* x 3)))
+        (display (double slot))
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        program.defs,
+        vec![
+            Def::Global {
+                name: "slot".to_string(),
+                value: int(0),
+            },
+            Def::Function {
+                name: "double".to_string(),
+                lambda: Lambda {
+                    params: vec![var("x", 0)],
+                    rest: None,
+                    body: Box::new(call(global("*"), vec![local("x", 0), int(2)])),
+                },
+            },
+            Def::Function {
+                name: "triple".to_string(),
+                lambda: Lambda {
+                    params: vec![var("x", 1)],
+                    rest: None,
+                    body: Box::new(call(global("*"), vec![local("x", 1), int(3)])),
+                },
+            },
+        ]
+    );
+    assert_eq!(
+        program.body,
+        vec![call(
+            global("display"),
+            vec![call(global("double"), vec![global("slot")])]
+        )]
+    );
+}
+
+#[test]
+fn test_lower_renames_apart() {
+    // The inner x shadows the parameter, and list is a local, not the primitive
+    let program = lower_source("(define (f x list) (let ((x (+ x 1))) (list x)))").unwrap();
+    let Def::Function { lambda, .. } = &program.defs[0] else {
+        panic!("expected a function: {:?}", program.defs[0]);
+    };
+    assert_eq!(lambda.params, vec![var("x", 0), var("list", 1)]);
+    assert_eq!(
+        *lambda.body,
+        Expr::Let(
+            vec![(var("x", 2), call(global("+"), vec![local("x", 0), int(1)]))],
+            Box::new(call(local("list", 1), vec![local("x", 2)]))
+        )
+    );
+}
+
+#[test]
+fn test_lower_derived_forms() {
+    let program = lower_source(
+        r#"
+        (define (count-down n)
+          (let loop ((i n) (acc '()))
+            (if (= i 0) acc (loop (- i 1) (cons i acc)))))
+        (define (sign n)
+          (cond ((< n 0) 'negative) ((> n 0) => list) (else 'zero)))
+        (or #f 1)
+        (and 1 2)
+        (when #t 1 2)
+        (define (body x)
+          (define y (* x 2))
+          (set! y (+ y 1))
+          y)
+        "#,
+    )
+    .unwrap();
+
+    // A named let calls a letrec of its procedure
+    let Def::Function { lambda, .. } = &program.defs[0] else {
+        panic!("expected a function");
+    };
+    let Expr::Call(letrec, inits) = &*lambda.body else {
+        panic!("expected a call: {:?}", lambda.body);
+    };
+    assert_eq!(*inits, vec![local("n", 0), Expr::Const(Value::Nil)]);
+    let Expr::Letrec(bindings, body) = &**letrec else {
+        panic!("expected a letrec: {:?}", letrec);
+    };
+    assert_eq!(bindings[0].0, var("loop", 1));
+    assert_eq!(**body, local("loop", 1));
+
+    // cond keeps the value of a => test in a fresh variable
+    let Def::Function { lambda, .. } = &program.defs[1] else {
+        panic!("expected a function");
+    };
+    let symbol = |name: &str| Expr::Const(Value::Symbol(name.to_string()));
+    let compare = |op: &str| call(global(op), vec![local("n", 4), int(0)]);
+    assert_eq!(
+        *lambda.body,
+        Expr::If(
+            Box::new(compare("<")),
+            Box::new(symbol("negative")),
+            Box::new(Expr::Let(
+                vec![(var("cond", 5), compare(">"))],
+                Box::new(Expr::If(
+                    Box::new(local("cond", 5)),
+                    Box::new(call(global("list"), vec![local("cond", 5)])),
+                    Box::new(symbol("zero"))
+                ))
+            ))
+        )
+    );
+
+    // or keeps a value in a fresh variable to return it, and keeps going
+    // while it is false; and stops at the first false value
+    let bool = |b| Expr::Const(Value::Boolean(b));
+    assert_eq!(
+        program.body,
+        vec![
+            Expr::Let(
+                vec![(var("or", 6), bool(false))],
+                Box::new(Expr::If(
+                    Box::new(local("or", 6)),
+                    Box::new(local("or", 6)),
+                    Box::new(int(1))
+                ))
+            ),
+            Expr::If(Box::new(int(1)), Box::new(int(2)), Box::new(bool(false))),
+            Expr::If(
+                Box::new(bool(true)),
+                Box::new(Expr::Seq(vec![int(1), int(2)])),
+                Box::new(Expr::Const(Value::Nil))
+            ),
+        ]
+    );
+
+    // Internal definitions become a letrec around the rest of the body
+    let Def::Function { lambda, .. } = &program.defs[2] else {
+        panic!("expected a function");
+    };
+    assert_eq!(
+        *lambda.body,
+        Expr::Letrec(
+            vec![(var("y", 8), call(global("*"), vec![local("x", 7), int(2)]))],
+            Box::new(Expr::Seq(vec![
+                Expr::SetLocal(
+                    var("y", 8),
+                    Box::new(call(global("+"), vec![local("y", 8), int(1)]))
+                ),
+                local("y", 8),
+            ]))
+        )
+    );
+}
+
+#[test]
+fn test_lower_expands_macros() {
+    let program = lower_source(
+        r#"
+        (define-syntax swap!
+          (syntax-rules ()
+            ((_ a b) (let ((tmp a)) (set! a b) (set! b tmp)))))
+        (define x 1)
+        (define y 2)
+        (swap! x y)
+        "#,
+    )
+    .unwrap();
+    assert_eq!(program.defs.len(), 2);
+    let [Expr::Let(bindings, _)] = program.body.as_slice() else {
+        panic!("expected the expanded let: {:?}", program.body);
+    };
+    assert_eq!(bindings[0].1, global("x"));
+}
+
+#[test]
+fn test_lower_errors() {
+    assert!(lower_error("(define x 1) (define x 2)").contains("x is defined twice"));
+    assert!(lower_error("(lambda (x x) x)").contains("x is bound twice"));
+    assert!(lower_error("(let ((a 1) (a 2)) a)").contains("a is bound twice"));
+    assert!(lower_error("(set! undefined 1)").contains("set! of undefined variable undefined"));
+    assert!(lower_error("(if)").contains("malformed (if)"));
+    assert!(lower_error("(lambda (x))").contains("body has no expression"));
+    assert!(lower_error("(display (define x 1))").contains("define is only allowed"));
+    assert!(lower_error("(lambda () (display 1) (define x 1) x)")
+        .contains("define after an expression"));
+    assert!(lower_error("(case 1 ((1) 'one))").contains("case can't be lowered to the IR"));
+    assert!(lower_error("(display if)").contains("if is a special form"));
+}
@@ -0,0 +1,122 @@
+use lamina::value::Value;
+use lamina_ir::{lower_source, parser, printer, Def, Expr, Lambda, Program, Var};
+
+fn round_trip(program: &Program) {
+    let text = printer::print(program);
+    let parsed = parser::parse(&text).unwrap_or_else(|e| panic!("{}\n{}", e, text));
+    assert_eq!(&parsed, program, "{}", text);
+    assert_eq!(printer::print(&parsed), text);
+}
+
+#[test]
+fn test_print_program() {
+    let program = lower_source(
+        r#"
+        (define slot 0)
+        (define (double x) (* x 2))
+        (display (double slot))
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        printer::print(&program),
+        "\
+(program
+  (define slot 0)
+  (define (double x.0) (call * x.0 2))
+  (call display (call double slot)))
+"
+    );
+    round_trip(&program);
+}
+
+#[test]
+fn test_print_breaks_long_forms() {
+    let program = lower_source(
+        r#"
+        (define (sum-to n)
+          (let loop ((i 0) (acc 0))
+            (if (> i n) acc (loop (+ i 1) (+ acc i)))))
+        "#,
+    )
+    .unwrap();
+    let text = printer::print(&program);
+    assert!(text.lines().all(|line| line.len() <= 80), "{}", text);
+    assert!(text.contains("\n    (call (letrec ((loop.1 (lambda (i.2 acc.3)\n"));
+    round_trip(&program);
+}
+
+#[test]
+fn test_round_trip() {
+    let program = lower_source(
+        r#"
+        (define counter 0)
+        (define (bump!) (set! counter (+ counter 1)) counter)
+        (define (classify x)
+          (define limit 10)
+          (cond ((< x 0) 'negative)
+                ((memv x '(0 1 2)) => length)
+                ((> x limit) #\b)
+                (else "many")))
+        (define table (vector 1 2.5 1/3))
+        (when (bump!) (display #(1 two)) (display #u8(1 2)))
+        (let* ((a 1) (b (list a '()))) (lambda args (apply + a args)))
+        "#,
+    )
+    .unwrap();
+    round_trip(&program);
+}
+
+#[test]
+fn test_round_trip_spelled_out_names() {
+    let var = |name: &str, id| Var {
+        name: name.to_string(),
+        id,
+    };
+    // -.1 reads as a number and x.1 as a local, so both are spelled out
+    let program = Program {
+        defs: vec![Def::Function {
+            name: "f".to_string(),
+            lambda: Lambda {
+                params: vec![var("-", 1)],
+                rest: Some(var("more", 2)),
+                body: Box::new(Expr::SetGlobal(
+                    "x.1".to_string(),
+                    Box::new(Expr::Local(var("-", 1))),
+                )),
+            },
+        }],
+        body: vec![Expr::Call(
+            Box::new(Expr::Global("x.1".to_string())),
+            vec![Expr::Const(Value::Symbol("x.1".to_string()))],
+        )],
+    };
+    let text = printer::print(&program);
+    assert!(
+        text.contains("(define (f (local \"-\" 1) (rest more.2))"),
+        "{}",
+        text
+    );
+    assert!(
+        text.contains("(set! (global \"x.1\") (local \"-\" 1))"),
+        "{}",
+        text
+    );
+    assert!(
+        text.contains("(call (global \"x.1\") (quote x.1))"),
+        "{}",
+        text
+    );
+    round_trip(&program);
+}
+
+#[test]
+fn test_parse_errors() {
+    let error = |text: &str| parser::parse(text).unwrap_err().to_string();
+    assert!(error("(program) (program)").contains("expected one (program ...) form, got 2"));
+    assert!(error("(module)").contains("expected (program ...)"));
+    assert!(error("(program (if 1 2))").contains("malformed IR form (if 1 2)"));
+    assert!(error("(program (cond (1 2)))").contains("unknown IR form (cond (1 2))"));
+    assert!(error("(program (let ((x 1)) x))").contains("expected a local variable, got x"));
+    assert!(parser::parse("(program (call (seq) 1))").is_ok());
+}
@@ -0,0 +1,18 @@
+use lamina::embed::Interpreter;
+use lamina::reader::infix::Infix;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("=== Infix Reader Extension Example ===");
+
+    // Register the #i{ ... } extension on a new interpreter
+    let interpreter = Interpreter::builder().with_reader_extension(Infix).build();
+
+    interpreter.eval("(define rate 3)")?;
+    interpreter.eval("(define fee (lambda (amount) #i{ amount * rate / 100 }))")?;
+
+    for expr in ["#i{ 2 + 3 * rate }", "#i{ (2 + 3) * rate }", "(fee 250)"] {
+        println!("{} => {}", expr, interpreter.eval(expr)?);
+    }
+
+    Ok(())
+}
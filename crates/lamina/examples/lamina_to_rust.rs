@@ -1,12 +1,12 @@
-use lamina::evaluator;
-use lamina::evaluator::environment::setup_initial_env;
+use lamina::embed::Interpreter;
 use lamina::ffi;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("=== Lamina to Rust Example ===");
 
     // Register some Rust functions that Lamina can call
-    ffi::register_function("rust-multiply", |args| {
+    let interpreter = Interpreter::builder()
+        .with_function("rust-multiply", |args| {
         if args.len() != 2 {
             return Err("rust-multiply requires 2 arguments".into());
         }
@@ -15,27 +15,23 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let arg2 = ffi::value_to_f64(&args[1])?;
 
         Ok(ffi::f64_to_value(arg1 * arg2))
-    });
-
-    ffi::register_function("rust-string-length", |args| {
+    })
+        .with_function("rust-string-length", |args| {
         if args.len() != 1 {
             return Err("rust-string-length requires 1 argument".into());
         }
 
         let s = ffi::value_to_string(&args[0])?;
         Ok(ffi::i64_to_value(s.len() as i64))
-    });
-
-    // A more complex Rust function
-    ffi::register_function("rust-http-user-agent", |_args| {
+    })
+        // A more complex Rust function
+        .with_function("rust-http-user-agent", |_args| {
         // In a real implementation, this would make an actual HTTP request
         // For demonstration purposes, we'll just return a mock response
         let user_agent = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36";
         Ok(ffi::string_to_value(user_agent.to_string()))
-    });
-
-    // Create a Lamina environment
-    let env = setup_initial_env();
+    })
+        .build();
 
     // Run Lamina code that calls our Rust functions
     let code = r#"
@@ -59,9 +55,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
       (list result-1 result-2 user-agent))
     "#;
 
-    let tokens = lamina::lexer::lex(code)?;
-    let expr = lamina::parser::parse(&tokens)?;
-    let result = evaluator::eval_with_env(expr, env)?;
+    let result = interpreter.eval(code)?;
 
     println!("\nFinal result: {}", result);
 
@@ -1,14 +1,11 @@
-use lamina::evaluator;
-use lamina::evaluator::environment::setup_initial_env;
+use lamina::embed::Interpreter;
 use lamina::ffi::{self, rustlib};
-use lamina::lexer;
-use lamina::parser;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("=== Rust Module Example ===");
 
-    // Create and register a "math" module
-    rustlib::create_module("math", |module| {
+    // Create a "math" module
+    let math = rustlib::build_module("math", |module| {
         // Add some math functions
         module.add_function("add", |args| {
             if args.len() != 2 {
@@ -43,8 +40,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         });
     });
 
-    // Create and register a "string" module
-    rustlib::create_module("string", |module| {
+    // Create a "string" module
+    let string = rustlib::build_module("string", |module| {
         module.add_function("length", |args| {
             if args.len() != 1 {
                 return Err("string/length requires 1 argument".into());
@@ -66,12 +63,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         });
     });
 
-    // Set up a Lamina environment
-    let env = setup_initial_env();
-
-    // Import our Rust modules
-    rustlib::import_module("math", &env).unwrap();
-    rustlib::import_module("string", &env).unwrap();
+    // Set up an interpreter with both modules and import them
+    let interpreter = Interpreter::builder()
+        .with_module(math)
+        .with_module(string)
+        .build();
+    interpreter.import_module("math")?;
+    interpreter.import_module("string")?;
 
     // Use the modules from Lamina
     let code = r#"
@@ -109,9 +107,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
       (list (math/square x) (string/uppercase message)))
     "#;
 
-    let tokens = lexer::lex(code)?;
-    let expr = parser::parse(&tokens)?;
-    let result = evaluator::eval_with_env(expr, env)?;
+    let result = interpreter.eval(code)?;
 
     println!("\nFinal result: {}", result);
 
@@ -1,2 +1,2 @@
-// Backend interfaces will be defined here in the future
-// Current implementations like Huff are in separate crates
+// The interface compiler backends implement is in the lamina-backend-api
+// crate; the Huff backend is in lamina-huff
@@ -0,0 +1,162 @@
+// Shared output layer for the command-line tools
+//
+// Every command reports through an `Output`, which decides what reaches the
+// terminal. Human mode prints text lines: progress at the normal level, extra
+// detail at the verbose level, and always the final result and errors. JSON
+// mode prints one JSON object per line on stdout, each tagged with a "type",
+// so scripts and editors can parse a build without scraping text.
+//
+// The mode and level come from the environment, LAMINA_OUTPUT=human|json and
+// LAMINA_VERBOSITY=quiet|normal|verbose, and the command-line flags override
+// them.
+
+use crate::json::Json;
+use std::fmt;
+
+/// Environment variable choosing the output format
+pub const OUTPUT_VAR: &str = "LAMINA_OUTPUT";
+/// Environment variable choosing the verbosity
+pub const VERBOSITY_VAR: &str = "LAMINA_VERBOSITY";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Human,
+    Json,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Format> {
+        match name {
+            "human" | "text" => Some(Format::Human),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// How much a command prints; each level includes the ones below it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Results and errors only
+    Quiet,
+    #[default]
+    Normal,
+    Verbose,
+}
+
+impl Verbosity {
+    pub fn parse(name: &str) -> Option<Verbosity> {
+        match name {
+            "quiet" | "0" => Some(Verbosity::Quiet),
+            "normal" | "1" => Some(Verbosity::Normal),
+            "verbose" | "2" => Some(Verbosity::Verbose),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Output {
+    pub format: Format,
+    pub verbosity: Verbosity,
+}
+
+impl Output {
+    pub fn new(format: Format, verbosity: Verbosity) -> Self {
+        Output { format, verbosity }
+    }
+
+    /// The settings in LAMINA_OUTPUT and LAMINA_VERBOSITY; unset or
+    /// unrecognized values keep the defaults
+    pub fn from_env() -> Self {
+        let var = |name| std::env::var(name).ok();
+        Output {
+            format: var(OUTPUT_VAR)
+                .and_then(|v| Format::parse(&v))
+                .unwrap_or_default(),
+            verbosity: var(VERBOSITY_VAR)
+                .and_then(|v| Verbosity::parse(&v))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Apply the --json, --quiet and --verbose flags over these settings
+    pub fn with_flags(mut self, json: bool, quiet: bool, verbose: bool) -> Self {
+        if json {
+            self.format = Format::Json;
+        }
+        if quiet {
+            self.verbosity = Verbosity::Quiet;
+        } else if verbose {
+            self.verbosity = Verbosity::Verbose;
+        }
+        self
+    }
+
+    pub fn is_json(&self) -> bool {
+        self.format == Format::Json
+    }
+
+    pub fn is_verbose(&self) -> bool {
+        self.verbosity >= Verbosity::Verbose
+    }
+
+    /// A progress note; human mode only
+    pub fn status(&self, message: impl fmt::Display) {
+        if self.format == Format::Human && self.verbosity >= Verbosity::Normal {
+            println!("{}", message);
+        }
+    }
+
+    /// A note only worth printing when asked for; human mode only
+    pub fn detail(&self, message: impl fmt::Display) {
+        if self.format == Format::Human && self.is_verbose() {
+            println!("{}", message);
+        }
+    }
+
+    /// One record of a command's output, such as a single test outcome,
+    /// printed at the normal level
+    pub fn event(&self, kind: &str, human: impl fmt::Display, fields: Json) {
+        if self.verbosity >= Verbosity::Normal {
+            self.emit(kind, human, fields);
+        }
+    }
+
+    /// What the command produced, printed at every level
+    pub fn result(&self, kind: &str, human: impl fmt::Display, fields: Json) {
+        self.emit(kind, human, fields);
+    }
+
+    /// An error, printed at every level: to stderr in human mode, as an
+    /// "error" record in JSON mode
+    pub fn error(&self, message: impl fmt::Display) {
+        match self.format {
+            Format::Human => eprintln!("Error: {}", message),
+            Format::Json => println!("{}", record("error", message_field(&message))),
+        }
+    }
+
+    fn emit(&self, kind: &str, human: impl fmt::Display, fields: Json) {
+        match self.format {
+            Format::Human => println!("{}", human),
+            Format::Json => println!("{}", record(kind, fields)),
+        }
+    }
+}
+
+/// `fields` with a leading "type" entry
+pub fn record(kind: &str, fields: Json) -> Json {
+    let mut entries = vec![("type".to_string(), Json::from(kind))];
+    match fields {
+        Json::Object(fields) => entries.extend(fields),
+        Json::Null => {}
+        value => entries.push(("value".to_string(), value)),
+    }
+    Json::Object(entries)
+}
+
+fn message_field(message: &impl fmt::Display) -> Json {
+    Json::object([("message", Json::from(message.to_string()))])
+}
@@ -0,0 +1,176 @@
+// Assertions and function contracts
+//
+// `(assert test [message])` fails with the message, or the test itself, when
+// `test` is false. A function defined with `define-with-contract` checks each
+// `#:requires` clause before its body runs and each `#:ensures` clause after,
+// with the return value bound to `result`:
+//
+//   (define-with-contract (withdraw amount)
+//     #:requires (> amount 0)
+//     #:ensures (>= result 0)
+//     (- (balance) amount))
+//
+// The interpreter rewrites the definition into `assert`s around the body; the
+// Huff backend compiles the same clauses to reverts. Both can strip the checks
+// for optimized builds: the interpreter skips `assert` in an environment with
+// assertions turned off, and the compiler leaves them out with
+// `CompileOptions::strip_assertions`.
+
+use crate::error::Error;
+use crate::value::Value;
+
+/// Keyword introducing a precondition
+pub const REQUIRES: &str = "#:requires";
+/// Keyword introducing a postcondition
+pub const ENSURES: &str = "#:ensures";
+/// Name a postcondition refers to the return value by
+pub const RESULT: &str = "result";
+
+/// A `#:requires` or `#:ensures` clause
+#[derive(Clone, Debug)]
+pub struct Condition {
+    pub test: Value,
+    /// What a failed check reports, e.g. "withdraw: requires (> amount 0)"
+    pub message: String,
+}
+
+/// A function defined with `define-with-contract`
+#[derive(Clone, Debug)]
+pub struct Contract {
+    pub name: String,
+    /// The parameter list, as written
+    pub params: Value,
+    pub requires: Vec<Condition>,
+    pub ensures: Vec<Condition>,
+    /// The body expressions
+    pub body: Value,
+}
+
+impl Contract {
+    /// Read `(define-with-contract (name param ...) clause ... body ...)` from
+    /// the arguments of the form
+    pub fn parse(args: &Value) -> Result<Self, Error> {
+        let malformed = || {
+            Error::Runtime(
+                "Malformed define-with-contract, expected (define-with-contract (name param ...) clause ... body ...)"
+                    .into(),
+            )
+        };
+        let (name, params, mut rest) = match args {
+            Value::Pair(pair) => match &pair.0 {
+                Value::Pair(signature) => match &signature.0 {
+                    Value::Symbol(name) => (name.clone(), signature.1.clone(), &pair.1),
+                    _ => return Err(malformed()),
+                },
+                _ => return Err(malformed()),
+            },
+            _ => return Err(malformed()),
+        };
+
+        let mut requires = Vec::new();
+        let mut ensures = Vec::new();
+        while let Value::Pair(pair) = rest {
+            let (kind, conditions) = match &pair.0 {
+                Value::Symbol(keyword) if keyword == REQUIRES => ("requires", &mut requires),
+                Value::Symbol(keyword) if keyword == ENSURES => ("ensures", &mut ensures),
+                _ => break,
+            };
+            let test = match &pair.1 {
+                Value::Pair(test) => test,
+                _ => return Err(Error::Runtime(format!("{} needs a condition", pair.0))),
+            };
+            conditions.push(Condition {
+                test: test.0.clone(),
+                message: format!("{}: {} {}", name, kind, test.0),
+            });
+            rest = &test.1;
+        }
+        if !matches!(rest, Value::Pair(_)) {
+            return Err(malformed());
+        }
+
+        Ok(Contract {
+            name,
+            params,
+            requires,
+            ensures,
+            body: rest.clone(),
+        })
+    }
+
+    /// The names of the parameters
+    pub fn param_names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut current = &self.params;
+        while let Value::Pair(pair) = current {
+            if let Value::Symbol(name) = &pair.0 {
+                names.push(name.clone());
+            }
+            current = &pair.1;
+        }
+        names
+    }
+
+    /// The definition the interpreter evaluates:
+    ///
+    /// ```scheme
+    /// (define name
+    ///   (lambda (param ...)
+    ///     (begin (assert requires "message") ...
+    ///            (let ((result (begin body ...)))
+    ///              (begin (assert ensures "message") ... result)))))
+    /// ```
+    pub fn to_define(&self) -> Value {
+        let body = Value::cons(Value::Symbol("begin".into()), self.body.clone());
+        let body = if self.ensures.is_empty() {
+            body
+        } else {
+            let mut checks = assertions(&self.ensures);
+            checks.push(Value::Symbol(RESULT.into()));
+            list(vec![
+                Value::Symbol("let".into()),
+                list(vec![list(vec![Value::Symbol(RESULT.into()), body])]),
+                begin(checks),
+            ])
+        };
+        let mut steps = assertions(&self.requires);
+        steps.push(body);
+
+        list(vec![
+            Value::Symbol("define".into()),
+            Value::Symbol(self.name.clone()),
+            list(vec![
+                Value::Symbol("lambda".into()),
+                self.params.clone(),
+                begin(steps),
+            ]),
+        ])
+    }
+}
+
+fn assertions(conditions: &[Condition]) -> Vec<Value> {
+    conditions
+        .iter()
+        .map(|condition| {
+            list(vec![
+                Value::Symbol("assert".into()),
+                condition.test.clone(),
+                Value::String(condition.message.clone()),
+            ])
+        })
+        .collect()
+}
+
+fn begin(mut forms: Vec<Value>) -> Value {
+    if forms.len() == 1 {
+        return forms.remove(0);
+    }
+    Value::cons(Value::Symbol("begin".into()), list(forms))
+}
+
+fn list(items: Vec<Value>) -> Value {
+    items
+        .into_iter()
+        .rev()
+        .fold(Value::Nil, |rest, item| Value::cons(item, rest))
+}
@@ -0,0 +1,435 @@
+// Source coverage for Lamina code
+//
+// The parser remembers where every list starts. Before running, the lists that
+// the evaluator would actually evaluate (calls and special forms, not parameter
+// lists, binding lists or quoted data) become probes. While recording, the
+// evaluator counts each evaluated list by identity, and the counts are folded
+// into per-line hits for the terminal summary and the lcov report.
+//
+// The arms of `if`, `cond` and `case` are probes too, so an arm that is an
+// atom, such as the `0` of `(if (> x 0) x 0)`, still counts. An arm is known by
+// its position in the form: the cell of the `if` list holding it, or the
+// clause of the `cond` or `case`. Each arm is also a branch of the lcov report.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::lexer::{self, Token};
+use crate::parser;
+use crate::value::{Cons, Value};
+
+// Evaluation counts keyed by list address; `None` while not recording
+thread_local! {
+    static HITS: RefCell<Option<HashMap<usize, usize>>> = const { RefCell::new(None) };
+}
+
+/// Count an evaluation of `expr` if coverage is being recorded
+pub fn record(expr: &Value) {
+    if let Value::Pair(pair) = expr {
+        HITS.with(|hits| {
+            if let Some(hits) = hits.borrow_mut().as_mut() {
+                *hits.entry(Rc::as_ptr(pair) as usize).or_insert(0) += 1;
+            }
+        });
+    }
+}
+
+/// Count the evaluator taking the arm of an `if`, `cond` or `case` that
+/// `cell` holds: the cell of the `if` list, or the clause
+pub fn record_arm(cell: &Rc<Cons>) {
+    HITS.with(|hits| {
+        if let Some(hits) = hits.borrow_mut().as_mut() {
+            *hits.entry(Rc::as_ptr(cell) as usize).or_insert(0) += 1;
+        }
+    });
+}
+
+/// Run `f` with coverage recording enabled, returning its result and the counts
+fn recording<T>(f: impl FnOnce() -> T) -> (T, HashMap<usize, usize>) {
+    let previous = HITS.with(|hits| hits.borrow_mut().replace(HashMap::new()));
+    let result = f();
+    let counts = HITS.with(|hits| std::mem::replace(&mut *hits.borrow_mut(), previous));
+    (result, counts.unwrap_or_default())
+}
+
+/// Parsed source with a probe on every evaluated list
+pub struct Instrumented {
+    pub forms: Vec<Value>,
+    /// Probed lists with the 1-based line they start on. Holding the lists
+    /// keeps their addresses stable while the forms are evaluated.
+    probes: Vec<(Value, usize)>,
+    /// The arms of each `if`, `cond` and `case`, in source order, as the
+    /// cells holding them with the line each arm starts on
+    arms: Vec<Vec<(Value, usize)>>,
+}
+
+impl Instrumented {
+    pub fn new(source: &str) -> Result<Self, Error> {
+        let spanned = lexer::lex_with_spans(source)?;
+        let tokens: Vec<_> = spanned.iter().map(|(token, _)| token.clone()).collect();
+        let (forms, positions) = parser::parse_all_with_positions(&tokens)?;
+
+        let mut evaluated = HashSet::new();
+        for form in &forms {
+            evaluated_lists(form, &mut evaluated);
+        }
+
+        let mut positions: Vec<_> = positions
+            .into_iter()
+            .filter(|(list, _)| evaluated.contains(&address(list)))
+            .collect();
+        positions.sort_by_key(|(_, token)| *token);
+
+        let line = |token: usize| line_of(source, spanned[token].1.start);
+        let arms = positions
+            .iter()
+            .filter_map(|(list, token)| {
+                let cells = arm_cells(list)?;
+                let starts = item_starts(&tokens, *token);
+                Some(
+                    cells
+                        .into_iter()
+                        .map(|(index, cell)| (cell, line(starts[index])))
+                        .collect(),
+                )
+            })
+            .collect();
+        let probes = positions
+            .into_iter()
+            .map(|(list, token)| (list, line(token)))
+            .collect();
+
+        Ok(Instrumented {
+            forms,
+            probes,
+            arms,
+        })
+    }
+
+    /// Run `f` while recording, returning its result and the coverage it produced
+    pub fn measure<T>(&self, f: impl FnOnce(&[Value]) -> T) -> (T, FileCoverage) {
+        let (result, counts) = recording(|| f(&self.forms));
+
+        let mut lines = BTreeMap::new();
+        for (list, line) in &self.probes {
+            let count = counts.get(&address(list)).copied().unwrap_or(0);
+            *lines.entry(*line).or_insert(0) += count;
+        }
+        let mut branches = Vec::new();
+        for (block, arms) in self.arms.iter().enumerate() {
+            for (arm, (cell, line)) in arms.iter().enumerate() {
+                let hits = counts.get(&address(cell)).copied().unwrap_or(0);
+                *lines.entry(*line).or_insert(0) += hits;
+                branches.push(Branch {
+                    line: *line,
+                    block,
+                    arm,
+                    hits,
+                });
+            }
+        }
+        (result, FileCoverage { lines, branches })
+    }
+}
+
+/// How often an arm of an `if`, `cond` or `case` was taken
+#[derive(Clone, Debug, PartialEq)]
+pub struct Branch {
+    /// The line the arm starts on
+    pub line: usize,
+    /// The form the arm belongs to, numbered in source order
+    pub block: usize,
+    /// The arm's index among the form's arms
+    pub arm: usize,
+    pub hits: usize,
+}
+
+/// Hits per source line, for the lines holding at least one probe, and per
+/// branch arm
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FileCoverage {
+    pub lines: BTreeMap<usize, usize>,
+    pub branches: Vec<Branch>,
+}
+
+impl FileCoverage {
+    /// Lines holding a probe
+    pub fn lines_found(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Lines where at least one probe was hit
+    pub fn lines_hit(&self) -> usize {
+        self.lines.values().filter(|hits| **hits > 0).count()
+    }
+
+    /// Percentage of lines hit; a file without probes counts as fully covered
+    pub fn percent(&self) -> f64 {
+        if self.lines.is_empty() {
+            100.0
+        } else {
+            100.0 * self.lines_hit() as f64 / self.lines_found() as f64
+        }
+    }
+
+    /// Lines that hold a probe that was never hit
+    pub fn missed_lines(&self) -> Vec<usize> {
+        self.lines
+            .iter()
+            .filter(|(_, hits)| **hits == 0)
+            .map(|(line, _)| *line)
+            .collect()
+    }
+
+    /// Branch arms that were taken
+    pub fn branches_hit(&self) -> usize {
+        self.branches
+            .iter()
+            .filter(|branch| branch.hits > 0)
+            .count()
+    }
+
+    /// Lines with an arm that was never taken
+    pub fn missed_branches(&self) -> Vec<usize> {
+        let mut lines: Vec<usize> = self
+            .branches
+            .iter()
+            .filter(|branch| branch.hits == 0)
+            .map(|branch| branch.line)
+            .collect();
+        lines.dedup();
+        lines
+    }
+}
+
+/// Render coverage for several files in the lcov tracefile format
+pub fn lcov_report(files: &[(String, FileCoverage)]) -> String {
+    let mut report = String::new();
+    for (path, coverage) in files {
+        report.push_str("TN:\n");
+        report.push_str(&format!("SF:{}\n", path));
+        for branch in &coverage.branches {
+            report.push_str(&format!(
+                "BRDA:{},{},{},{}\n",
+                branch.line, branch.block, branch.arm, branch.hits
+            ));
+        }
+        report.push_str(&format!("BRF:{}\n", coverage.branches.len()));
+        report.push_str(&format!("BRH:{}\n", coverage.branches_hit()));
+        for (line, hits) in &coverage.lines {
+            report.push_str(&format!("DA:{},{}\n", line, hits));
+        }
+        report.push_str(&format!("LF:{}\n", coverage.lines_found()));
+        report.push_str(&format!("LH:{}\n", coverage.lines_hit()));
+        report.push_str("end_of_record\n");
+    }
+    report
+}
+
+fn address(list: &Value) -> usize {
+    match list {
+        Value::Pair(pair) => Rc::as_ptr(pair) as usize,
+        _ => 0,
+    }
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset].matches('\n').count() + 1
+}
+
+/// The arms of `list` if it is an `if`, `cond` or `case`, each with the
+/// index of the item holding it and the cell of `list` the arm is known by
+fn arm_cells(list: &Value) -> Option<Vec<(usize, Value)>> {
+    let Value::Pair(pair) = list else {
+        return None;
+    };
+    // An `if` arm is known by the cell holding it, a clause by itself
+    let (first, by_cell) = match &pair.0 {
+        Value::Symbol(head) if head == "if" => (2, true),
+        Value::Symbol(head) if head == "cond" => (1, false),
+        Value::Symbol(head) if head == "case" => (2, false),
+        _ => return None,
+    };
+    let mut arms = Vec::new();
+    let mut cell = list.clone();
+    let mut index = 0;
+    while let Value::Pair(pair) = cell.clone() {
+        if index >= first {
+            match &pair.0 {
+                _ if by_cell => arms.push((index, cell.clone())),
+                clause @ Value::Pair(_) => arms.push((index, clause.clone())),
+                _ => {}
+            }
+        }
+        cell = pair.1.clone();
+        index += 1;
+    }
+    Some(arms)
+}
+
+/// The index of the first token of each item of the list opened at `open`
+fn item_starts(tokens: &[Token], open: usize) -> Vec<usize> {
+    let mut starts = Vec::new();
+    let mut pos = open + 1;
+    while pos < tokens.len() && tokens[pos] != Token::RightParen {
+        starts.push(pos);
+        pos = datum_end(tokens, pos);
+    }
+    starts
+}
+
+/// The index just past the datum starting at `pos`
+fn datum_end(tokens: &[Token], mut pos: usize) -> usize {
+    while matches!(
+        tokens.get(pos),
+        Some(
+            Token::Quote
+                | Token::Quasiquote
+                | Token::Unquote
+                | Token::UnquoteSplicing
+                | Token::DatumLabel(_)
+                | Token::FoldCase
+                | Token::NoFoldCase
+        )
+    ) {
+        pos += 1;
+    }
+    if !matches!(
+        tokens.get(pos),
+        Some(Token::LeftParen | Token::VectorOpen | Token::BytevectorOpen)
+    ) {
+        return pos + 1;
+    }
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate().skip(pos) {
+        match token {
+            Token::LeftParen | Token::VectorOpen | Token::BytevectorOpen => depth += 1,
+            Token::RightParen => {
+                depth -= 1;
+                if depth == 0 {
+                    return index + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    tokens.len()
+}
+
+// Collect the addresses of the lists in `expr` that the evaluator evaluates,
+// following the shape of each special form
+fn evaluated_lists(expr: &Value, out: &mut HashSet<usize>) {
+    let pair = match expr {
+        Value::Pair(pair) => pair,
+        _ => return,
+    };
+    out.insert(address(expr));
+
+    let items = list_items(&pair.1);
+    let head = match &pair.0 {
+        Value::Symbol(head) => head.as_str(),
+        other => {
+            evaluated_lists(other, out);
+            items.iter().for_each(|item| evaluated_lists(item, out));
+            return;
+        }
+    };
+
+    match head {
+        "quote" | "define-record-type" | "import" => {}
+        // Macro definitions are templates, not code
+        "define-syntax" => {}
+        "let-syntax" | "letrec-syntax" => items
+            .iter()
+            .skip(1)
+            .for_each(|item| evaluated_lists(item, out)),
+        // Parameter lists and `(define (f x) ...)` signatures are not evaluated
+        "lambda" | "define" | "define-internal" | "define-for-syntax" => items
+            .iter()
+            .skip(1)
+            .for_each(|item| evaluated_lists(item, out)),
+        "let" | "let*" | "letrec" | "do" => {
+            // A named let's name comes before its bindings
+            let items = match items.first() {
+                Some(Value::Symbol(_)) => &items[1..],
+                _ => &items[..],
+            };
+            if let Some(bindings) = items.first() {
+                // Initializers, and a `do` variable's step
+                for binding in list_items(bindings) {
+                    list_items(&binding)
+                        .iter()
+                        .skip(1)
+                        .for_each(|init| evaluated_lists(init, out));
+                }
+            }
+            for (index, item) in items.iter().enumerate().skip(1) {
+                // The test and results of `do` are a clause, not a call
+                if head == "do" && index == 1 {
+                    list_items(item)
+                        .iter()
+                        .for_each(|item| evaluated_lists(item, out));
+                } else {
+                    evaluated_lists(item, out);
+                }
+            }
+        }
+        "cond" => {
+            for clause in &items {
+                list_items(clause)
+                    .iter()
+                    .for_each(|item| evaluated_lists(item, out));
+            }
+        }
+        // Datum lists are matched against, not evaluated
+        "case" => {
+            if let Some(key) = items.first() {
+                evaluated_lists(key, out);
+            }
+            for clause in items.iter().skip(1) {
+                list_items(clause)
+                    .iter()
+                    .skip(1)
+                    .for_each(|item| evaluated_lists(item, out));
+            }
+        }
+        "guard" => {
+            if let Some(spec) = items.first() {
+                for clause in list_items(spec).iter().skip(1) {
+                    list_items(clause)
+                        .iter()
+                        .for_each(|item| evaluated_lists(item, out));
+                }
+            }
+            items
+                .iter()
+                .skip(1)
+                .for_each(|item| evaluated_lists(item, out));
+        }
+        "define-library" => {
+            // Only the contents of `(begin ...)` declarations are evaluated
+            for declaration in items.iter().skip(1) {
+                if let Value::Pair(decl) = declaration {
+                    if matches!(&decl.0, Value::Symbol(s) if s == "begin") {
+                        list_items(&decl.1)
+                            .iter()
+                            .for_each(|item| evaluated_lists(item, out));
+                    }
+                }
+            }
+        }
+        _ => items.iter().for_each(|item| evaluated_lists(item, out)),
+    }
+}
+
+fn list_items(list: &Value) -> Vec<Value> {
+    let mut items = Vec::new();
+    let mut current = list;
+    while let Value::Pair(pair) = current {
+        items.push(pair.0.clone());
+        current = &pair.1;
+    }
+    items
+}
@@ -0,0 +1,200 @@
+// Compiler diagnostics
+//
+// Backends collect warnings while lowering a program and hand them back with
+// their output. A top-level form inside `begin` can be prefixed with
+// `#:allow code` or `#:allow (code ...)` to silence warnings it would raise, and
+// a function definition with `#:mutability view` (or `pure`, `nonpayable`,
+// `payable`) to declare what it may do; the interpreter skips these annotations.
+
+use std::fmt;
+
+use crate::error::Error;
+use crate::value::Value;
+
+/// Annotation keyword that suppresses warnings for the form after it
+pub const ALLOW: &str = "#:allow";
+/// Annotation keyword that declares the state mutability of the function after it
+pub const MUTABILITY: &str = "#:mutability";
+
+/// A function that can never be reached through the dispatcher
+pub const UNUSED_FUNCTION: &str = "unused-function";
+/// Two storage names that resolve to the same slot
+pub const STORAGE_SLOT_REUSE: &str = "storage-slot-reuse";
+/// A `cond` clause after one that always matches
+pub const UNREACHABLE_CLAUSE: &str = "unreachable-clause";
+/// Storage written after an external call, which the callee can reenter before
+pub const REENTRANCY: &str = "reentrancy";
+/// An external call whose success flag is ignored
+pub const UNCHECKED_CALL: &str = "unchecked-call";
+/// `tx-origin` used, usually to authenticate the sender
+pub const TX_ORIGIN: &str = "tx-origin";
+/// Wrapping arithmetic whose operands' ranges let it overflow, or checked
+/// arithmetic that always reverts
+pub const OVERFLOW: &str = "overflow";
+/// A function the backend has no code for, compiled to always revert
+pub const UNSUPPORTED: &str = "unsupported";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A message about a program, tagged with the code `#:allow` refers to it by
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn warning(code: &str, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            code: code.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}[{}]: {}", severity, self.code, self.message)
+    }
+}
+
+/// Warnings collected by a compiler, minus the ones the current form allows
+#[derive(Clone, Debug, Default)]
+pub struct Warnings {
+    diagnostics: Vec<Diagnostic>,
+    allowed: Vec<String>,
+}
+
+impl Warnings {
+    /// Set the codes allowed for the form being compiled
+    pub fn set_allowed(&mut self, codes: &[String]) {
+        self.allowed = codes.to_vec();
+    }
+
+    pub fn warn(&mut self, code: &str, message: impl Into<String>) {
+        if !self.allowed.iter().any(|allowed| allowed == code) {
+            self.diagnostics.push(Diagnostic::warning(code, message));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+}
+
+/// Fail with every warning rendered as an error, as `--deny-warnings` asks for
+pub fn deny_warnings(diagnostics: &[Diagnostic]) -> Result<(), Error> {
+    if diagnostics.is_empty() {
+        return Ok(());
+    }
+
+    let rendered: Vec<String> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            Diagnostic {
+                severity: Severity::Error,
+                ..diagnostic.clone()
+            }
+            .to_string()
+        })
+        .collect();
+    Err(Error::Compilation(rendered.join("\n")))
+}
+
+/// A top-level form along with the annotations in front of it
+#[derive(Clone, Debug)]
+pub struct AnnotatedForm {
+    /// Warning codes the form allows
+    pub allowed: Vec<String>,
+    /// Declared state mutability, as written after `#:mutability`
+    pub mutability: Option<String>,
+    pub form: Value,
+}
+
+/// Split a `begin` body into its forms, each with its annotations
+pub fn annotated_forms(body: &Value) -> Result<Vec<AnnotatedForm>, Error> {
+    let mut forms = Vec::new();
+    let mut allowed = Vec::new();
+    let mut mutability = None;
+    let mut current = body;
+
+    while let Value::Pair(pair) = current {
+        match &pair.0 {
+            Value::Symbol(keyword) if keyword == ALLOW => {
+                let codes = match &pair.1 {
+                    Value::Pair(codes) => codes,
+                    _ => return Err(Error::Compilation(format!("{} needs a code", ALLOW))),
+                };
+                allowed.extend(annotation_codes(&codes.0)?);
+                current = &codes.1;
+                continue;
+            }
+            Value::Symbol(keyword) if keyword == MUTABILITY => {
+                let level = match &pair.1 {
+                    Value::Pair(level) => level,
+                    _ => return Err(Error::Compilation(format!("{} needs a level", MUTABILITY))),
+                };
+                match &level.0 {
+                    Value::Symbol(name) => mutability = Some(name.clone()),
+                    other => {
+                        return Err(Error::Compilation(format!(
+                            "{} expects a mutability, got {}",
+                            MUTABILITY, other
+                        )))
+                    }
+                }
+                current = &level.1;
+                continue;
+            }
+            Value::Symbol(keyword) if keyword.starts_with("#:") => {
+                return Err(Error::Compilation(format!(
+                    "Unknown annotation: {}",
+                    keyword
+                )));
+            }
+            form => forms.push(AnnotatedForm {
+                allowed: std::mem::take(&mut allowed),
+                mutability: mutability.take(),
+                form: form.clone(),
+            }),
+        }
+        current = &pair.1;
+    }
+
+    Ok(forms)
+}
+
+// `code` or `(code ...)`
+fn annotation_codes(codes: &Value) -> Result<Vec<String>, Error> {
+    let invalid = || Error::Compilation(format!("{} expects a code or a list of codes", ALLOW));
+    match codes {
+        Value::Symbol(code) => Ok(vec![code.clone()]),
+        Value::Pair(_) => {
+            let mut result = Vec::new();
+            let mut current = codes;
+            while let Value::Pair(pair) = current {
+                match &pair.0 {
+                    Value::Symbol(code) => result.push(code.clone()),
+                    _ => return Err(invalid()),
+                }
+                current = &pair.1;
+            }
+            Ok(result)
+        }
+        _ => Err(invalid()),
+    }
+}
@@ -0,0 +1,117 @@
+// Project environment variables
+//
+// Scripts, tests and deployments read their configuration and secrets from
+// the environment with `get-environment-variable`. A project can keep them
+// out of its sources in two places:
+//
+// - `.env`, usually left out of version control, with one `NAME=value` per
+//   line:
+//
+//     # Deployment account
+//     export RPC_URL=http://localhost:8545
+//     PRIVATE_KEY="0xac09..."   # quoted values keep \n escapes
+//     GREETING='single quotes keep everything literally'
+//
+// - an `[env]` table in `lamina.toml`, with defaults meant to be committed:
+//
+//     [env]
+//     RPC_URL = "http://localhost:8545"
+//     CHAIN_ID = 31337
+//
+// A variable already set in the environment wins over `.env`, which wins over
+// `[env]`, so a value can always be overridden for one run from the shell.
+
+use std::path::Path;
+
+use crate::project::{parse_value, Manifest};
+
+/// The file holding a project's local variables
+pub const FILE: &str = ".env";
+
+/// Where a variable's value came from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    Dotenv,
+    Manifest,
+}
+
+/// The variables in the text of a `.env` file, in order
+pub fn parse(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut vars = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |message: &str| format!("{}:{}: {}", FILE, index + 1, message);
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (name, value) = line
+            .split_once('=')
+            .ok_or_else(|| error("expected NAME=value"))?;
+        let name = name.trim();
+        if !is_name(name) {
+            return Err(error(&format!("invalid variable name: {}", name)));
+        }
+        let value = parse_value(value.trim()).map_err(|message| error(&message))?;
+        vars.push((name.to_string(), value));
+    }
+    Ok(vars)
+}
+
+/// Whether `name` can name an environment variable
+pub(crate) fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The variables to set, given which are set already: the first value for a
+/// name not set yet, from `.env` before `[env]`
+pub fn resolve(
+    is_set: impl Fn(&str) -> bool,
+    dotenv: Vec<(String, String)>,
+    defaults: Vec<(String, String)>,
+) -> Vec<(String, String, Source)> {
+    let mut resolved: Vec<(String, String, Source)> = Vec::new();
+    let candidates = dotenv
+        .into_iter()
+        .map(|(name, value)| (name, value, Source::Dotenv))
+        .chain(
+            defaults
+                .into_iter()
+                .map(|(name, value)| (name, value, Source::Manifest)),
+        );
+    for (name, value, source) in candidates {
+        if is_set(&name) || resolved.iter().any(|(other, _, _)| *other == name) {
+            continue;
+        }
+        resolved.push((name, value, source));
+    }
+    resolved
+}
+
+/// Set the variables of the project at `dir` that the environment doesn't
+/// already, reading `.env` unless `dotenv` is false, and return their names
+/// with where each came from
+pub fn load(dir: &Path, dotenv: bool) -> Result<Vec<(String, Source)>, String> {
+    let local = match dotenv.then(|| std::fs::read_to_string(dir.join(FILE))) {
+        None => Vec::new(),
+        Some(Ok(text)) => parse(&text)?,
+        Some(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Some(Err(e)) => return Err(format!("cannot read {}: {}", FILE, e)),
+    };
+    let defaults = Manifest::read(dir)?
+        .map(|manifest| manifest.env)
+        .unwrap_or_default();
+
+    let resolved = resolve(|name| std::env::var_os(name).is_some(), local, defaults);
+    Ok(resolved
+        .into_iter()
+        .map(|(name, value, source)| {
+            std::env::set_var(&name, value);
+            (name, source)
+        })
+        .collect())
+}
@@ -0,0 +1,102 @@
+// Language editions
+//
+// An edition fixes the behaviors that changed as the language evolved, so old
+// scripts keep running as they were written. A file selects its edition with a
+// `(lamina-edition 2025)` form, or a `#!lamina1` line naming the language
+// version, before the code that depends on it. Files that don't select one
+// get the edition their project records in the `[package]` table of
+// `lamina.toml`, see `project::Manifest`:
+//
+//   [package]
+//   name = "vault"
+//   edition = "2025"
+//
+// and scripts outside a project get the latest edition, 2025.
+//
+// Edition 2024 evaluates only the first expression of a body: of a `lambda`,
+// a `(define (f ...) ...)`, or a `let`, `let*`, `letrec` or named `let`.
+// Edition 2025 evaluates every body expression in order and returns the value
+// of the last, as R7RS does.
+
+use std::fmt;
+
+use crate::lexer::{self, Token};
+
+/// The project manifest, which records the project's edition
+pub const MANIFEST: &str = "lamina.toml";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Edition {
+    /// Single-expression bodies
+    E2024,
+    /// Full bodies
+    #[default]
+    E2025,
+}
+
+impl Edition {
+    /// The edition new projects are created with
+    pub const LATEST: Edition = Edition::E2025;
+
+    pub fn year(&self) -> u32 {
+        match self {
+            Edition::E2024 => 2024,
+            Edition::E2025 => 2025,
+        }
+    }
+
+    pub fn from_year(year: u32) -> Option<Edition> {
+        match year {
+            2024 => Some(Edition::E2024),
+            2025 => Some(Edition::E2025),
+            _ => None,
+        }
+    }
+
+    /// The edition a `#!laminaN` line names; version 0 is the unversioned
+    /// language, edition 2024
+    pub fn from_version(version: u32) -> Option<Edition> {
+        match version {
+            0 => Some(Edition::E2024),
+            1 => Some(Edition::E2025),
+            _ => None,
+        }
+    }
+
+    /// Whether `lambda` and `let` bodies evaluate all their expressions
+    pub fn full_bodies(&self) -> bool {
+        *self >= Edition::E2025
+    }
+}
+
+impl fmt::Display for Edition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.year())
+    }
+}
+
+/// The edition `source` selects for itself, if its first form is an edition
+/// pragma
+pub fn declared(source: &str) -> Option<Edition> {
+    let tokens = lexer::lex(source).ok()?;
+    match tokens.as_slice() {
+        [Token::EditionPragma(version), ..] => Edition::from_version(version.parse().ok()?),
+        [Token::LeftParen, Token::Symbol(head), Token::Number(year), Token::RightParen, ..]
+            if head == "lamina-edition" =>
+        {
+            Edition::from_year(year.parse().ok()?)
+        }
+        _ => None,
+    }
+}
+
+/// `source` with a pragma selecting `edition` in front, unless it selects an
+/// edition of its own. The pragma goes on the first line, so line numbers are
+/// unchanged.
+pub fn with_default(source: &str, edition: Edition) -> String {
+    if declared(source).is_some() {
+        source.to_string()
+    } else {
+        format!("(lamina-edition {}) {}", edition.year(), source)
+    }
+}
@@ -1,16 +1,460 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::ops::Range;
+use std::path::Path;
 use std::rc::Rc;
 
+pub use crate::edition::Edition;
 use crate::error::Error;
 use crate::evaluator;
 use crate::evaluator::environment::setup_initial_env;
-use crate::lexer;
+use crate::evaluator::libraries::{bind_library, find_library};
+use crate::evaluator::library_manager::LibraryRegistry;
+use crate::evaluator::ports::{register_port_procedures, InputPort, OutputPort};
+pub use crate::evaluator::snapshot::Snapshot;
+use crate::expand::expand_with_env;
+use crate::ffi::foreign::{register_foreign_procedures, MethodRegistry, MethodTable};
+use crate::ffi::rustlib::{ModuleRegistry, RustModule};
+use crate::ffi::FFIRegistry;
+use crate::lexer::{self, Token};
 use crate::parser;
-use crate::value::{Environment, Value};
+use crate::reader::{Reader, ReaderExtension};
+use crate::value::{Environment, Library, Value};
 
 /// A wrapper that represents a Lamina interpreter instance
+///
+/// Each interpreter owns its Rust functions, Rust modules and libraries, so
+/// several interpreters on one thread never see each other's registrations.
 pub struct Interpreter {
     env: Rc<RefCell<Environment>>,
+    modules: ModuleRegistry,
+    libraries: Rc<RefCell<LibraryRegistry>>,
+    reader: Reader,
+    templates: RefCell<HashMap<String, Rc<Template>>>,
+    /// Whether `eval_expression` rejects changes to the global environment
+    strict: bool,
+}
+
+/// An expression with `{name}` holes for host values, compiled to a procedure
+/// taking one argument per hole
+struct Template {
+    holes: Vec<String>,
+    procedure: Value,
+}
+
+impl Template {
+    /// The template's expression as a `lambda` over its holes, each hole
+    /// replaced by its parameter `%name`
+    fn source(template: &str) -> (String, Vec<String>) {
+        let mut source = String::with_capacity(template.len() + 16);
+        let mut holes: Vec<String> = Vec::new();
+        let mut rest = template;
+
+        while let Some(c) = rest.chars().next() {
+            match c {
+                // Strings and comments are copied as they are
+                '"' => {
+                    let end = string_end(rest);
+                    source.push_str(&rest[..end]);
+                    rest = &rest[end..];
+                    continue;
+                }
+                ';' => {
+                    let end = rest.find('\n').unwrap_or(rest.len());
+                    source.push_str(&rest[..end]);
+                    rest = &rest[end..];
+                    continue;
+                }
+                // `#name{` opens a reader extension block rather than a hole
+                '{' if !source.ends_with(|c: char| c.is_ascii_alphanumeric() || c == '-') => {
+                    if let Some(name) = hole_name(&rest[1..]) {
+                        source.push('%');
+                        source.push_str(name);
+                        if !holes.iter().any(|hole| hole == name) {
+                            holes.push(name.to_string());
+                        }
+                        rest = &rest[name.len() + 2..];
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+            source.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+
+        let params: Vec<String> = holes.iter().map(|hole| format!("%{}", hole)).collect();
+        (format!("(lambda ({}) {})", params.join(" "), source), holes)
+    }
+}
+
+/// The length of the string literal at the start of `text`, or all of it when
+/// the string is unterminated
+fn string_end(text: &str) -> usize {
+    let mut escaped = false;
+    for (at, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return at + 1,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// The name of the `{name}` hole whose `{` comes just before `text`
+fn hole_name(text: &str) -> Option<&str> {
+    let end = text.find('}')?;
+    let name = &text[..end];
+    let mut chars = name.chars();
+    let first = chars.next()?;
+    let valid = (first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    valid.then_some(name)
+}
+
+/// What one top-level form evaluated by [`Interpreter::eval_all`] produced
+#[derive(Debug)]
+pub struct FormResult {
+    /// Byte range of the form in the source
+    pub span: Range<usize>,
+    /// 1-based line and column the form starts at
+    pub line: usize,
+    pub column: usize,
+    pub result: Result<Value, Error>,
+    /// What the form wrote to the current output port
+    pub output: String,
+}
+
+impl FormResult {
+    fn new(source: &str, span: Range<usize>, result: Result<Value, Error>) -> Self {
+        let (line, column) = lexer::line_and_column(source, span.start);
+        FormResult {
+            span,
+            line,
+            column,
+            result,
+            output: String::new(),
+        }
+    }
+}
+
+/// The index just past the top-level form starting at token `start`, or the
+/// end of `tokens` when the form isn't closed
+fn form_end(tokens: &[Token], start: usize) -> usize {
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate().skip(start) {
+        match token {
+            Token::LeftParen
+            | Token::VectorOpen
+            | Token::BytevectorOpen
+            | Token::ExtensionOpen(_) => depth += 1,
+            // Prefixes belong to the datum after them
+            Token::Quote
+            | Token::Quasiquote
+            | Token::Unquote
+            | Token::UnquoteSplicing
+            | Token::DatumLabel(_) => continue,
+            Token::RightParen | Token::RightBrace => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        if depth == 0 {
+            return i + 1;
+        }
+    }
+    tokens.len()
+}
+
+/// Forms that define or import into the environment they're evaluated in
+const DEFINING_FORMS: &[&str] = &[
+    "define",
+    "define-internal",
+    "define-for-syntax",
+    "define-syntax",
+    "define-record-type",
+    "define-enum",
+    "define-with-contract",
+    "define-rule",
+    "define-library",
+    "define-interface",
+    "define-test",
+    "define-property",
+    "define-bench",
+    "import",
+    "lamina-edition",
+];
+
+/// Check `expr` leaves the global environment alone. `locals` are the
+/// variables bound around it; `in_body` is whether it is in the body of a
+/// `lambda` or `let`, where definitions are local.
+fn check_policy(expr: &Value, locals: &mut Vec<String>, in_body: bool) -> Result<(), Error> {
+    let Value::Pair(pair) = expr else {
+        return Ok(());
+    };
+    let items = list_items(expr);
+    if let Value::Symbol(head) = &pair.0 {
+        let head = head.as_str();
+        if !in_body && DEFINING_FORMS.contains(&head) {
+            return Err(Error::Policy(format!(
+                "{} is not allowed in an expression",
+                head
+            )));
+        }
+        match head {
+            "quote" => return Ok(()),
+            "set!" => {
+                if let Some(Value::Symbol(name)) = items.get(1) {
+                    if !locals.contains(name) {
+                        return Err(Error::Policy(format!(
+                            "set! of global variable {} is not allowed in an expression",
+                            name
+                        )));
+                    }
+                }
+            }
+            "lambda" => {
+                let params = items.get(1).map(symbols_in).unwrap_or_default();
+                return check_body(&items[2.min(items.len())..], params, locals);
+            }
+            "let" | "let*" | "letrec" | "letrec*" | "let-values" | "let*-values" => {
+                // A named let binds its name in the body too, and each
+                // binding of `let-values` a list of formals
+                let (mut names, rest) = match items.get(1) {
+                    Some(Value::Symbol(name)) => (vec![name.clone()], &items[2.min(items.len())..]),
+                    _ => (Vec::new(), &items[1.min(items.len())..]),
+                };
+                let Some((bindings, body)) = rest.split_first() else {
+                    return Ok(());
+                };
+                // The initializers of a `letrec` see every name it binds,
+                // and those of a `let*` the names bound before them
+                let bindings: Vec<Vec<Value>> =
+                    list_items(bindings).iter().map(list_items).collect();
+                let formals =
+                    |binding: &Vec<Value>| binding.first().map(symbols_in).unwrap_or_default();
+                let depth = locals.len();
+                if matches!(head, "letrec" | "letrec*") {
+                    locals.extend(bindings.iter().flat_map(formals));
+                }
+                for binding in &bindings {
+                    for init in binding.iter().skip(1) {
+                        check_policy(init, locals, in_body)?;
+                    }
+                    if matches!(head, "let*" | "let*-values") {
+                        locals.extend(formals(binding));
+                    }
+                    names.extend(formals(binding));
+                }
+                locals.truncate(depth);
+                return check_body(body, names, locals);
+            }
+            "do" => {
+                // The variables are in scope in their steps and the rest of
+                // the loop, but not in their initializers
+                let mut names = Vec::new();
+                let mut scoped = Vec::new();
+                for spec in items.get(1).map(list_items).unwrap_or_default() {
+                    let spec = list_items(&spec);
+                    if let Some(Value::Symbol(name)) = spec.first() {
+                        names.push(name.clone());
+                    }
+                    if let Some(init) = spec.get(1) {
+                        check_policy(init, locals, in_body)?;
+                    }
+                    scoped.extend(spec.get(2).cloned());
+                }
+                scoped.extend(items.iter().skip(2).cloned());
+                return check_body(&scoped, names, locals);
+            }
+            _ => {}
+        }
+    }
+    for item in &items {
+        check_policy(item, locals, in_body)?;
+    }
+    Ok(())
+}
+
+/// Check the forms of a body binding `names`, and the names it defines
+fn check_body(
+    body: &[Value],
+    mut names: Vec<String>,
+    locals: &mut Vec<String>,
+) -> Result<(), Error> {
+    body_definitions(body, &mut names);
+    let depth = locals.len();
+    locals.extend(names);
+    let checked = body
+        .iter()
+        .try_for_each(|form| check_policy(form, locals, true));
+    locals.truncate(depth);
+    checked
+}
+
+/// Add the names the forms of a body define, looking into `begin`s, whose
+/// definitions are the body's own
+fn body_definitions(body: &[Value], names: &mut Vec<String>) {
+    for form in body {
+        match list_items(form).as_slice() {
+            [Value::Symbol(head), target, ..] if head == "define" => {
+                names.extend(symbols_in(target).into_iter().take(1));
+            }
+            [Value::Symbol(head), forms @ ..] if head == "begin" => body_definitions(forms, names),
+            _ => {}
+        }
+    }
+}
+
+/// The elements of a list, stopping at an improper tail
+fn list_items(list: &Value) -> Vec<Value> {
+    let mut items = Vec::new();
+    let mut rest = list;
+    while let Value::Pair(pair) = rest {
+        items.push(pair.0.clone());
+        rest = &pair.1;
+    }
+    items
+}
+
+/// The symbols of a parameter list or `define` target, a dotted rest
+/// parameter included, or a lone symbol
+fn symbols_in(mut value: &Value) -> Vec<String> {
+    let mut names = Vec::new();
+    while let Value::Pair(pair) = value {
+        if let Value::Symbol(name) = &pair.0 {
+            names.push(name.clone());
+        }
+        value = &pair.1;
+    }
+    if let Value::Symbol(name) = value {
+        names.push(name.clone());
+    }
+    names
+}
+
+/// Configures the registrations of a new [`Interpreter`]
+#[derive(Default)]
+pub struct InterpreterBuilder {
+    functions: FFIRegistry,
+    modules: ModuleRegistry,
+    methods: MethodRegistry,
+    libraries: Vec<Library>,
+    reader: Reader,
+    input: Option<InputPort>,
+    strip_assertions: bool,
+    edition: Edition,
+    strict: bool,
+    fold_case: bool,
+}
+
+impl InterpreterBuilder {
+    /// Bind a Rust function under `name`
+    pub fn with_function<F>(mut self, name: &str, func: F) -> Self
+    where
+        F: Fn(Vec<Value>) -> Result<Value, String> + 'static,
+    {
+        self.functions.register(name, func);
+        self
+    }
+
+    /// Make a Rust module available to `Interpreter::import_module`
+    pub fn with_module(mut self, module: RustModule) -> Self {
+        self.modules.register(module);
+        self
+    }
+
+    /// Register the methods Lamina can call on foreign objects of type `T`
+    pub fn with_methods<T: std::any::Any>(mut self, table: MethodTable<T>) -> Self {
+        self.methods.register(table);
+        self
+    }
+
+    /// Register a library and bind it under its name, as `define-library` does
+    pub fn with_library(mut self, library: Library) -> Self {
+        self.libraries.push(library);
+        self
+    }
+
+    /// Expand `#name{ ... }` blocks with a reader extension before parsing
+    pub fn with_reader_extension<E>(mut self, extension: E) -> Self
+    where
+        E: ReaderExtension + 'static,
+    {
+        self.reader.register(Rc::new(extension));
+        self
+    }
+
+    /// Read `read-line`, `read-string` and `char-ready?` input from `input`
+    /// instead of standard input
+    pub fn with_input(mut self, input: impl BufRead + 'static) -> Self {
+        self.input = Some(InputPort::from_reader(input));
+        self
+    }
+
+    /// Skip `assert` and the clauses of `define-with-contract`, as an
+    /// optimized build would
+    pub fn without_assertions(mut self) -> Self {
+        self.strip_assertions = true;
+        self
+    }
+
+    /// Evaluate code in `edition` unless it selects another with a pragma
+    pub fn with_edition(mut self, edition: Edition) -> Self {
+        self.edition = edition;
+        self
+    }
+
+    /// Read symbols case-insensitively, folding them to lower case, in the
+    /// code the interpreter evaluates and the data `read` returns
+    pub fn fold_case(mut self) -> Self {
+        self.fold_case = true;
+        self
+    }
+
+    /// Reject definitions, imports and assignments to global variables in
+    /// [`Interpreter::eval_expression`], for hosts evaluating formulas
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    pub fn build(self) -> Interpreter {
+        let env = setup_initial_env();
+        env.borrow_mut().assertions = !self.strip_assertions;
+        env.borrow_mut().edition = self.edition;
+        if let Some(input) = self.input {
+            register_port_procedures(&env, Rc::new(input));
+        }
+        if self.fold_case {
+            env.borrow_mut().fold_case = true;
+            if let Some(Value::Parameter(current)) = env.borrow().get("current-input-port") {
+                if let Value::InputPort(port) = current.get() {
+                    port.set_fold_case(true);
+                }
+            }
+        }
+        register_foreign_procedures(&env, Rc::new(self.methods));
+        let libraries = Rc::new(RefCell::new(LibraryRegistry::new()));
+        env.borrow_mut().libraries = Some(libraries.clone());
+
+        if let Err(e) = self.functions.load_into_env(&env) {
+            eprintln!("Warning: Failed to load FFI functions: {}", e);
+        }
+        for library in self.libraries {
+            bind_library(&env, Rc::new(RefCell::new(library)));
+        }
+
+        Interpreter {
+            env,
+            modules: self.modules,
+            libraries,
+            reader: self.reader,
+            templates: RefCell::new(HashMap::new()),
+            strict: self.strict,
+        }
+    }
 }
 
 impl Default for Interpreter {
@@ -21,24 +465,207 @@ impl Default for Interpreter {
 
 impl Interpreter {
     /// Create a new Lamina interpreter with a fresh environment
+    ///
+    /// Functions registered with the deprecated `ffi::register_function` are
+    /// loaded as well; use [`Interpreter::builder`] to start from a clean slate.
     pub fn new() -> Self {
-        let env = setup_initial_env();
+        let interpreter = Self::builder().build();
 
         // Load any registered FFI functions
-        if let Err(e) = crate::ffi::load_ffi_functions(&env) {
+        #[allow(deprecated)]
+        if let Err(e) = crate::ffi::load_ffi_functions(&interpreter.env) {
             eprintln!("Warning: Failed to load FFI functions: {}", e);
         }
 
-        Interpreter { env }
+        interpreter
+    }
+
+    pub fn builder() -> InterpreterBuilder {
+        InterpreterBuilder::default()
+    }
+
+    /// Import a module given to `InterpreterBuilder::with_module`, binding its
+    /// functions as `module/function`
+    pub fn import_module(&self, module_name: &str) -> Result<(), Error> {
+        self.modules
+            .import(module_name, &self.env)
+            .map_err(Error::Runtime)
+    }
+
+    /// Look up a library defined in this interpreter
+    pub fn library(&self, name: &[String]) -> Option<Rc<RefCell<Library>>> {
+        self.libraries.borrow().get(name)
+    }
+
+    /// The tokens of `code`, with reader extension blocks expanded
+    fn read(&self, code: &str) -> Result<Vec<Token>, Error> {
+        let (tokens, _) = lexer::lex_with_case(code, self.env.borrow().fold_case)?;
+        self.reader
+            .read(tokens.into_iter().map(|(token, _)| token).collect())
     }
 
     /// Evaluate a string of Lamina code and return the result
     pub fn eval(&self, code: &str) -> Result<Value, Error> {
-        let tokens = lexer::lex(code)?;
+        let tokens = self.read(code)?;
         let expr = parser::parse(&tokens)?;
         evaluator::eval_with_env(expr, self.env.clone())
     }
 
+    /// Evaluate a single expression, such as a spreadsheet formula
+    ///
+    /// On an interpreter built with [`InterpreterBuilder::strict`], an
+    /// expression that would change the global environment fails with
+    /// [`Error::Policy`] before anything is evaluated: a definition, `import`
+    /// or other defining form outside the body of a `lambda` or `let`, or a
+    /// `set!` of a variable the expression didn't bind itself. Local `let`
+    /// bindings and internal definitions are allowed. The macros the
+    /// expression uses are expanded and the result checked as well, so a
+    /// macro can't hide a definition or assignment. Only the expression is
+    /// checked, not the procedures it calls.
+    pub fn eval_expression(&self, code: &str) -> Result<Value, Error> {
+        let tokens = self.read(code)?;
+        let expr = parser::parse(&tokens)?;
+        if !self.strict {
+            return evaluator::eval_with_env(expr, self.env.clone());
+        }
+        // The form as written is checked first, as expanding it runs its
+        // compile-time forms, in a scope of their own
+        check_policy(&expr, &mut Vec::new(), false)?;
+        let mut scope = Environment::new();
+        scope.parent = Some(self.env.clone());
+        let expanded = expand_with_env(&expr, Rc::new(RefCell::new(scope)))?;
+        check_policy(&expanded, &mut Vec::new(), false)?;
+        evaluator::eval_with_env(expanded, self.env.clone())
+    }
+
+    /// Evaluate each top-level form of `source` in turn, returning what each
+    /// produced
+    ///
+    /// A form that fails doesn't stop the ones after it, so a host loading a
+    /// configuration file can report every error with its position. Source
+    /// that can't be split into forms, such as an unclosed list, ends in one
+    /// result covering the rest of it.
+    pub fn eval_all(&self, source: &str) -> Vec<FormResult> {
+        let spanned = match lexer::lex_with_case(source, self.env.borrow().fold_case) {
+            Ok((spanned, _)) => spanned,
+            Err(e) => return vec![FormResult::new(source, 0..source.len(), Err(e))],
+        };
+        let tokens: Vec<Token> = spanned.iter().map(|(token, _)| token.clone()).collect();
+
+        let mut results = Vec::new();
+        let mut start = 0;
+        while start < tokens.len() {
+            let end = form_end(&tokens, start);
+            let span = spanned[start].1.start..spanned[end - 1].1.end;
+            let (result, output) = self.capture_output(|| {
+                let tokens = self.reader.read(tokens[start..end].to_vec())?;
+                let mut value = Value::Nil;
+                for form in parser::parse_all(&tokens)? {
+                    value = evaluator::eval_with_env(form, self.env.clone())?;
+                }
+                Ok(value)
+            });
+            let mut form = FormResult::new(source, span, result);
+            form.output = output;
+            results.push(form);
+            start = end;
+        }
+        results
+    }
+
+    /// Run `f` with the current output port collecting what it writes
+    fn capture_output<T>(&self, f: impl FnOnce() -> T) -> (T, String) {
+        let Some(Value::Parameter(current)) = self.get("current-output-port") else {
+            return (f(), String::new());
+        };
+        let port = Rc::new(OutputPort::string());
+        let result = current.parameterize(Value::OutputPort(port.clone()), f);
+        (result, port.contents().unwrap_or_default())
+    }
+
+    /// Evaluate an expression with `{name}` holes filled from `values`
+    ///
+    /// ```ignore
+    /// interpreter.eval_template("(> price {threshold})", &[("threshold", Value::from(5.0))])
+    /// ```
+    ///
+    /// The first call with a template parses it into a procedure taking the
+    /// holes as arguments, cached by the template's text, so later calls only
+    /// look up the procedure and apply it; the values are never printed or
+    /// parsed. Other names in the template refer to the interpreter's
+    /// environment as in [`Interpreter::eval`].
+    pub fn eval_template(&self, template: &str, values: &[(&str, Value)]) -> Result<Value, Error> {
+        let compiled = self.template(template)?;
+        let args = compiled
+            .holes
+            .iter()
+            .map(|hole| {
+                values
+                    .iter()
+                    .find(|(name, _)| name == hole)
+                    .map(|(_, value)| value.clone())
+                    .ok_or_else(|| {
+                        Error::Runtime(format!("No value for {{{}}} in {}", hole, template))
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        evaluator::apply(&compiled.procedure, args).map_err(Error::Runtime)
+    }
+
+    /// The compiled form of `template`, compiling it on first use
+    fn template(&self, template: &str) -> Result<Rc<Template>, Error> {
+        if let Some(compiled) = self.templates.borrow().get(template) {
+            return Ok(compiled.clone());
+        }
+
+        let (source, holes) = Template::source(template);
+        let procedure = self.eval(&source)?;
+        let compiled = Rc::new(Template { holes, procedure });
+        self.templates
+            .borrow_mut()
+            .insert(template.to_string(), compiled.clone());
+        Ok(compiled)
+    }
+
+    /// Evaluate the forms of a rule file, with `(lamina rules)` imported, so
+    /// its `define-rule`s join the interpreter's rules
+    pub fn load_rules(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| Error::Runtime(format!("cannot read {}: {}", path.display(), e)))?;
+        self.eval("(import (lamina rules))")?;
+        let tokens = self.read(&source)?;
+        for form in parser::parse_all(&tokens)? {
+            evaluator::eval_with_env(form, self.env.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Fire the interpreter's rules as `(run-rules)` does, returning the names
+    /// of the rules that fired in order
+    pub fn run_rules(&self) -> Result<Vec<String>, Error> {
+        self.call_rules("run-rules")
+    }
+
+    /// The names of the rules the last run fired, in order
+    pub fn fired_rules(&self) -> Result<Vec<String>, Error> {
+        self.call_rules("rules-fired")
+    }
+
+    /// Call a procedure of `(lamina rules)` that returns rule names
+    fn call_rules(&self, procedure: &str) -> Result<Vec<String>, Error> {
+        let library = find_library(&self.env, &["lamina".to_string(), "rules".to_string()])?;
+        let environment = library.borrow().environment.clone();
+        let procedure = evaluator::environment::lookup_variable(procedure, environment)?;
+        let mut names = Vec::new();
+        let mut list = evaluator::apply(&procedure, Vec::new()).map_err(Error::Runtime)?;
+        while let Value::Pair(pair) = list {
+            names.push(pair.0.to_string());
+            list = pair.1.clone();
+        }
+        Ok(names)
+    }
+
     /// Define a variable in the interpreter's environment
     pub fn define(&self, name: &str, value: Value) {
         self.env
@@ -70,14 +697,13 @@ impl Interpreter {
         );
 
         // Call the procedure
-        match proc {
-            Value::Procedure(p) => p(args).map_err(Error::Runtime),
-            Value::RustFn(f, _) => f(args).map_err(Error::Runtime),
-            _ => Err(Error::Runtime(format!(
+        if !proc.is_procedure() {
+            return Err(Error::Runtime(format!(
                 "{} is not a procedure: {:?}",
                 proc_name, proc
-            ))),
+            )));
         }
+        evaluator::apply(&proc, args).map_err(Error::Runtime)
     }
 
     /// Register a Rust function in the Lamina environment
@@ -91,6 +717,27 @@ impl Interpreter {
             .insert(name.to_string(), crate::ffi::create_rust_fn(name, func));
     }
 
+    /// Save the interpreter's state, to roll back to with [`Interpreter::restore`]
+    ///
+    /// Every binding, closure frame, vector, record and parameter reachable
+    /// from the global environment is copied, so this takes time and memory
+    /// linear in that state; the values themselves are shared.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::take(&self.env)
+    }
+
+    /// Roll back to a snapshot of this interpreter, which stays valid for
+    /// further restores; linear in the size of the snapshot
+    pub fn restore(&self, snapshot: &Snapshot) -> Result<(), Error> {
+        if !snapshot.is_of(&self.env) {
+            return Err(Error::Runtime(
+                "Snapshot was taken from another interpreter".into(),
+            ));
+        }
+        snapshot.restore();
+        Ok(())
+    }
+
     /// Get access to the interpreter's environment
     pub fn environment(&self) -> Rc<RefCell<Environment>> {
         self.env.clone()
@@ -112,7 +759,7 @@ pub fn eval(code: &str) -> Result<Value, Error> {
 pub mod types {
     pub use crate::ffi::{
         bool_to_value, f64_to_value, i64_to_value, string_to_value, value_to_bool, value_to_f64,
-        value_to_i64, value_to_string,
+        value_to_i64, value_to_string, FromLamina,
     };
     pub use crate::value::{NumberKind, Value};
 }
@@ -0,0 +1,52 @@
+// Hex encoding shared by the evm primitives, the REPL and the test harness
+
+/// Encode bytes as a lowercase, `0x`-prefixed hex string
+pub fn encode_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(2 + bytes.len() * 2);
+    hex.push_str("0x");
+    for byte in bytes {
+        hex.push_str(&format!("{:02x}", byte));
+    }
+    hex
+}
+
+/// Decode a hex string, with or without a `0x` prefix.
+///
+/// Every byte takes two digits, so an odd number of digits is an error.
+pub fn decode_hex(s: &str) -> Result<Vec<u8>, String> {
+    decode(s, false)
+}
+
+/// Decode a hex number, with or without a `0x` prefix, to its big-endian
+/// bytes.
+///
+/// Unlike `decode_hex`, an odd number of digits is accepted and read as if it
+/// had a leading zero, so `0x100` decodes to `[0x01, 0x00]`.
+pub fn decode_quantity(s: &str) -> Result<Vec<u8>, String> {
+    decode(s, true)
+}
+
+fn decode(s: &str, pad: bool) -> Result<Vec<u8>, String> {
+    let digits = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid hex string: {}", s));
+    }
+
+    let padded = match digits.len() % 2 {
+        0 => digits.to_string(),
+        _ if pad => format!("0{}", digits),
+        _ => return Err(format!("Hex string has an odd number of digits: {}", s)),
+    };
+
+    (0..padded.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&padded[i..i + 2], 16)
+                .map_err(|_| format!("Invalid hex string: {}", s))
+        })
+        .collect()
+}
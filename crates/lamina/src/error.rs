@@ -1,9 +1,14 @@
+use std::fmt;
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub enum Error {
     #[error("Runtime error: {0}")]
     Runtime(String),
+    /// A variable bound nowhere in scope, with the message naming it and
+    /// any bound names it may be a typo of
+    #[error("Runtime error: {message}")]
+    Undefined { name: String, message: String },
     #[error("Parser error: {0}")]
     Parser(String),
     #[error("Lexer error: {0}")]
@@ -18,6 +23,10 @@ pub enum Error {
     #[error("IO error: {0}")]
     #[allow(dead_code)]
     IO(String),
+    /// Code an embedder's policy forbids, such as a definition in
+    /// `Interpreter::eval_expression` on a strict interpreter
+    #[error("Policy violation: {0}")]
+    Policy(String),
 }
 
 impl From<String> for Error {
@@ -25,3 +34,38 @@ impl From<String> for Error {
         Error::Runtime(s)
     }
 }
+
+/// How many arguments a procedure takes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Arity {
+    Exactly(usize),
+    /// Required arguments followed by a rest parameter
+    AtLeast(usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match self {
+            Arity::Exactly(n) => count == *n,
+            Arity::AtLeast(n) => count >= *n,
+        }
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Arity::Exactly(n) => write!(f, "{}", n),
+            Arity::AtLeast(n) => write!(f, "at least {}", n),
+        }
+    }
+}
+
+/// The message for calling a procedure with the wrong number of arguments,
+/// naming it by its signature, such as `(make-point x y)`
+pub fn arity_error(signature: &str, arity: Arity, got: usize) -> String {
+    format!(
+        "Wrong number of arguments to {}: expected {}, got {}",
+        signature, arity, got
+    )
+}
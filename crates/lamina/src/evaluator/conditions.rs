@@ -0,0 +1,237 @@
+// Conditions
+//
+// A condition is a record describing what went wrong, of one of a few
+// condition types ordered in a hierarchy:
+//
+//   condition
+//   └── error           error-object?
+//       ├── file-error  file-error?   a file couldn't be opened
+//       └── read-error  read-error?   `read` met text it can't parse
+//
+// A predicate holds for conditions of its type and of the types below it, so
+// every file error is also an error object. `error-object-message` and
+// `error-object-irritants` read any condition, and `(make-condition 'type
+// message irritant ...)` makes one to `raise`:
+//
+//   (guard (e ((file-error? e) (error-object-message e)))
+//     (with-input-from-file "missing.txt" read-line))
+//
+// Native procedures fail with a message rather than a value, so the ones that
+// raise a condition prefix its message with the type, as `file_error` does,
+// and the evaluator turns the message back into the condition when a handler
+// takes it. Parser and I/O errors of the evaluator itself become read and
+// file errors the same way.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::value::{Environment, Record, RecordType, Value};
+
+use super::libraries::check_args_count;
+
+const FILE_ERROR: &str = "File error: ";
+const READ_ERROR: &str = "Read error: ";
+
+/// A condition type, with the one above it
+struct ConditionType {
+    name: &'static str,
+    parent: Option<&'static str>,
+    /// Prefix of the message of a native procedure raising one
+    prefix: Option<&'static str>,
+}
+
+const TYPES: &[ConditionType] = &[
+    ConditionType {
+        name: "condition",
+        parent: None,
+        prefix: None,
+    },
+    ConditionType {
+        name: "error",
+        parent: Some("condition"),
+        prefix: None,
+    },
+    ConditionType {
+        name: "file-error",
+        parent: Some("error"),
+        prefix: Some(FILE_ERROR),
+    },
+    ConditionType {
+        name: "read-error",
+        parent: Some("error"),
+        prefix: Some(READ_ERROR),
+    },
+];
+
+thread_local! {
+    // One record type per condition type, so conditions of a type share it
+    static RECORD_TYPES: Vec<Rc<RecordType>> = TYPES
+        .iter()
+        .map(|condition_type| {
+            Rc::new(RecordType {
+                name: condition_type.name.to_string(),
+                fields: vec![("message".to_string(), false), ("irritants".to_string(), false)],
+            })
+        })
+        .collect();
+}
+
+fn find_type(name: &str) -> Option<&'static ConditionType> {
+    TYPES
+        .iter()
+        .find(|condition_type| condition_type.name == name)
+}
+
+/// The message a native procedure fails with to raise a file error
+pub fn file_error(message: impl std::fmt::Display) -> String {
+    format!("{}{}", FILE_ERROR, message)
+}
+
+/// The message a native procedure fails with to raise a read error
+pub fn read_error(message: impl std::fmt::Display) -> String {
+    format!("{}{}", READ_ERROR, message)
+}
+
+/// A condition of the type called `name`
+pub fn make_condition(name: &str, message: &str, irritants: Value) -> Option<Value> {
+    let index = TYPES
+        .iter()
+        .position(|condition_type| condition_type.name == name)?;
+    let record_type = RECORD_TYPES.with(|types| types[index].clone());
+    let values = HashMap::from([
+        ("message".to_string(), Value::String(message.to_string())),
+        ("irritants".to_string(), irritants),
+    ]);
+    Some(Value::Record(Rc::new(Record {
+        type_info: record_type,
+        values: RefCell::new(values),
+    })))
+}
+
+/// The type of `value`, if it is a condition
+fn condition_type(value: &Value) -> Option<&'static ConditionType> {
+    let Value::Record(record) = value else {
+        return None;
+    };
+    let index = TYPES
+        .iter()
+        .position(|condition_type| condition_type.name == record.type_info.name)?;
+    RECORD_TYPES
+        .with(|types| Rc::ptr_eq(&types[index], &record.type_info))
+        .then_some(&TYPES[index])
+}
+
+/// Whether `value` is a condition of type `name` or of a type below it
+pub fn is_condition(value: &Value, name: &str) -> bool {
+    let mut current = condition_type(value);
+    while let Some(condition_type) = current {
+        if condition_type.name == name {
+            return true;
+        }
+        current = condition_type.parent.and_then(find_type);
+    }
+    false
+}
+
+fn field(value: &Value, name: &str) -> Option<Value> {
+    match value {
+        Value::Record(record) if condition_type(value).is_some() => {
+            record.values.borrow().get(name).cloned()
+        }
+        _ => None,
+    }
+}
+
+/// The condition an evaluator error stands for, if it raises one
+pub fn from_error(error: &Error) -> Option<Value> {
+    let (name, message) = match error {
+        Error::IO(message) => ("file-error", message.as_str()),
+        Error::Parser(message) | Error::Lexer(message) => ("read-error", message.as_str()),
+        Error::Runtime(message) => TYPES.iter().find_map(|condition_type| {
+            let rest = message.strip_prefix(condition_type.prefix?)?;
+            Some((condition_type.name, rest))
+        })?,
+        _ => return None,
+    };
+    make_condition(name, message, Value::Nil)
+}
+
+/// The error a condition that no handler took fails the run with
+pub fn to_error(value: &Value) -> Option<Error> {
+    let condition_type = condition_type(value)?;
+    let message = match field(value, "message") {
+        Some(Value::String(message)) => message,
+        _ => String::new(),
+    };
+    Some(Error::Runtime(match condition_type.prefix {
+        Some(prefix) => format!("{}{}", prefix, message),
+        None => message,
+    }))
+}
+
+pub fn register_condition_procedures(env: &Rc<RefCell<Environment>>) {
+    let mut env = env.borrow_mut();
+    for (name, type_name) in [
+        ("condition?", "condition"),
+        ("error-object?", "error"),
+        ("file-error?", "file-error"),
+        ("read-error?", "read-error"),
+    ] {
+        env.bindings.insert(
+            name.to_string(),
+            Value::Procedure(Rc::new(move |args: Vec<Value>| {
+                check_args_count(name, &args, 1)?;
+                Ok(Value::Boolean(is_condition(&args[0], type_name)))
+            })),
+        );
+    }
+
+    for (name, field_name) in [
+        ("error-object-message", "message"),
+        ("error-object-irritants", "irritants"),
+    ] {
+        env.bindings.insert(
+            name.to_string(),
+            Value::Procedure(Rc::new(move |args: Vec<Value>| {
+                check_args_count(name, &args, 1)?;
+                field(&args[0], field_name)
+                    .ok_or_else(|| format!("{}: not a condition: {}", name, args[0]))
+            })),
+        );
+    }
+
+    // (condition-type condition): the name of its type, as a symbol
+    env.bindings.insert(
+        "condition-type".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            check_args_count("condition-type", &args, 1)?;
+            condition_type(&args[0])
+                .map(|condition_type| Value::Symbol(condition_type.name.to_string()))
+                .ok_or_else(|| format!("condition-type: not a condition: {}", args[0]))
+        })),
+    );
+
+    // (make-condition 'type message irritant ...)
+    env.bindings.insert(
+        "make-condition".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            let (name, message, irritants) = match args.as_slice() {
+                [Value::Symbol(name), Value::String(message), irritants @ ..] => {
+                    (name, message, irritants)
+                }
+                _ => {
+                    return Err(
+                        "make-condition requires a condition type, a message and irritants".into(),
+                    )
+                }
+            };
+            let irritants = irritants.iter().rev().fold(Value::Nil, |list, irritant| {
+                Value::cons(irritant.clone(), list)
+            });
+            make_condition(name, message, irritants)
+                .ok_or_else(|| format!("make-condition: unknown condition type: {}", name))
+        })),
+    );
+}
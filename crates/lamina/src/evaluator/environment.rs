@@ -1,11 +1,31 @@
 use std::cell::RefCell;
+use std::cmp;
 use std::rc::Rc;
-
-use crate::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::encoding::{decode_hex, decode_quantity, encode_hex};
+use crate::error::{arity_error, Arity, Error};
+use crate::evm::{
+    checksum_address, create2_address, keccak256, parse_address, value_to_word,
+    word_to_unsigned_value, word_to_value, Word,
+};
+use crate::ffi::foreign::{register_foreign_procedures, MethodRegistry};
+use crate::ffi::handle::register_handle_procedures;
+use crate::number::{self, arithmetic};
+use crate::printer::register_print_parameters;
+use crate::targets::register_features;
 use crate::value::{Environment, NumberKind, Value};
 
+use super::apply;
+use super::conditions::register_condition_procedures;
+use super::equality::register_equality_procedures;
+use super::generators::register_generator_procedures;
 use super::libraries;
+use super::parameters::register_parameter_procedures;
+use super::ports::{register_port_procedures, InputPort};
+use super::predicates::register_type_predicates;
 use super::special_forms::register_special_forms;
+use super::values::register_values_procedures;
 
 // Function to create a new environment with optional parent
 pub fn create_environment(parent: Option<Rc<RefCell<Environment>>>) -> Rc<RefCell<Environment>> {
@@ -23,6 +43,17 @@ pub fn setup_initial_env() -> Rc<RefCell<Environment>> {
 
     // Register standard procedures
     register_procedures(env.clone());
+    register_generator_procedures(env.clone());
+    register_parameter_procedures(&env);
+    register_condition_procedures(&env);
+    register_values_procedures(&env);
+    register_equality_procedures(&env);
+    register_type_predicates(&env);
+    register_print_parameters(&env);
+    register_features(&env);
+    register_handle_procedures(&env);
+    register_foreign_procedures(&env, Rc::new(MethodRegistry::new()));
+    register_port_procedures(&env, Rc::new(InputPort::stdin()));
 
     // Add a marker for environment type
     env.borrow_mut().bindings.insert(
@@ -46,62 +77,60 @@ pub fn setup_initial_env() -> Rc<RefCell<Environment>> {
         eprintln!("Warning: Failed to setup libraries: {}", e);
     }
 
+    // Everything bound so far is core; user code rebinding it gets flagged
+    let core = env.borrow().bindings.keys().cloned().collect();
+    env.borrow_mut().core = core;
+
     env
 }
 
+/// Check a `define` or `set!` of `name` in `env` against its core bindings.
+///
+/// Rebinding a core name such as `car` would break every later use of it, so
+/// it is reported as a warning, or refused when the environment is strict.
+pub fn check_core_rebinding(name: &str, env: &Environment) -> Result<(), Error> {
+    if !env.core.contains(name) {
+        return Ok(());
+    }
+
+    if env.strict {
+        Err(Error::Runtime(format!(
+            "Cannot redefine core binding: {}",
+            name
+        )))
+    } else {
+        eprintln!("Warning: redefining core binding: {}", name);
+        Ok(())
+    }
+}
+
 // Register basic procedures (+ - * / etc.)
 #[allow(dead_code)]
 pub fn register_procedures(env: Rc<RefCell<Environment>>) {
-    // Define standard arithmetic operators
+    // Define standard arithmetic operators, exact when their arguments are
     env.borrow_mut().bindings.insert(
         "+".to_string(),
         Value::Procedure(Rc::new(|args: Vec<Value>| {
-            let mut sum = 0.0;
-            for arg in args {
-                match arg {
-                    Value::Number(n) => sum += n.as_f64(),
-                    _ => return Err("+ requires numeric arguments".into()),
-                }
+            let mut sum = NumberKind::Integer(0);
+            for arg in &args {
+                sum = arithmetic::add(&sum, number("+", arg)?);
             }
-            Ok(Value::from(sum))
+            Ok(Value::Number(sum))
         })),
     );
 
-    // Define subtraction
+    // Define subtraction, or negation of one argument
     env.borrow_mut().bindings.insert(
         "-".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.is_empty() {
-                return Err("- requires at least one argument".into());
-            }
-
-            if args.len() == 1 {
-                // Negation
-                match &args[0] {
-                    Value::Number(n) => match n {
-                        NumberKind::Integer(i) => Ok(Value::Number(NumberKind::Integer(-i))),
-                        NumberKind::Real(r) => Ok(Value::Number(NumberKind::Real(-r))),
-                        NumberKind::Rational(num, den) => {
-                            Ok(Value::Number(NumberKind::Rational(-num, *den)))
-                        }
-                    },
-                    _ => Err("- requires numeric arguments".into()),
+        Value::Procedure(Rc::new(|args: Vec<Value>| match args.split_first() {
+            None => Err("- requires at least one argument".into()),
+            Some((n, [])) => Ok(Value::Number(arithmetic::negate(number("-", n)?))),
+            Some((first, rest)) => {
+                let mut result = number("-", first)?.clone();
+                for arg in rest {
+                    result = arithmetic::sub(&result, number("-", arg)?);
                 }
-            } else {
-                // Subtraction
-                let mut result = match &args[0] {
-                    Value::Number(n) => n.as_f64(),
-                    _ => return Err("- requires numeric arguments".into()),
-                };
-
-                for arg in args.iter().skip(1) {
-                    match arg {
-                        Value::Number(n) => result -= n.as_f64(),
-                        _ => return Err("- requires numeric arguments".into()),
-                    }
-                }
-
-                Ok(Value::from(result))
+                Ok(Value::Number(result))
             }
         })),
     );
@@ -110,201 +139,116 @@ pub fn register_procedures(env: Rc<RefCell<Environment>>) {
     env.borrow_mut().bindings.insert(
         "*".to_string(),
         Value::Procedure(Rc::new(|args: Vec<Value>| {
-            let mut product = 1.0;
-            for arg in args {
-                match arg {
-                    Value::Number(n) => product *= n.as_f64(),
-                    _ => return Err("* requires numeric arguments".into()),
-                }
+            let mut product = NumberKind::Integer(1);
+            for arg in &args {
+                product = arithmetic::mul(&product, number("*", arg)?);
             }
-            Ok(Value::from(product))
+            Ok(Value::Number(product))
         })),
     );
 
-    // Define division
+    // Define division, or the reciprocal of one argument
     env.borrow_mut().bindings.insert(
         "/".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.is_empty() {
-                return Err("/ requires at least one argument".into());
-            }
-
-            if args.len() == 1 {
-                // Reciprocal
-                match &args[0] {
-                    Value::Number(n) => {
-                        let value = n.as_f64();
-                        if value == 0.0 {
-                            return Err("Division by zero".into());
-                        }
-                        Ok(Value::from(1.0 / value))
-                    }
-                    _ => Err("/ requires numeric arguments".into()),
-                }
-            } else {
-                // Division
-                let mut result = match &args[0] {
-                    Value::Number(n) => n.as_f64(),
-                    _ => return Err("/ requires numeric arguments".into()),
-                };
-
-                for arg in args.iter().skip(1) {
-                    match arg {
-                        Value::Number(n) => {
-                            let value = n.as_f64();
-                            if value == 0.0 {
-                                return Err("Division by zero".into());
-                            }
-                            result /= value;
-                        }
-                        _ => return Err("/ requires numeric arguments".into()),
-                    }
-                }
-
-                Ok(Value::from(result))
-            }
-        })),
-    );
-
-    // Define equal for numbers
-    env.borrow_mut().bindings.insert(
-        "=".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.len() < 2 {
-                return Err("= requires at least two arguments".into());
-            }
-
-            let first = match &args[0] {
-                Value::Number(n) => n.as_f64(),
-                _ => return Err("= requires numeric arguments".into()),
-            };
-
-            for arg in args.iter().skip(1) {
-                match arg {
-                    Value::Number(n) => {
-                        if (n.as_f64() - first).abs() > f64::EPSILON {
-                            return Ok(Value::Boolean(false));
-                        }
-                    }
-                    _ => return Err("= requires numeric arguments".into()),
-                }
-            }
-
-            Ok(Value::Boolean(true))
-        })),
-    );
-
-    // Less than
-    env.borrow_mut().bindings.insert(
-        "<".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.len() < 2 {
-                return Err("< requires at least two arguments".into());
-            }
-
-            for i in 0..args.len() - 1 {
-                let a = match &args[i] {
-                    Value::Number(n) => n.as_f64(),
-                    _ => return Err("< requires numeric arguments".into()),
-                };
-
-                let b = match &args[i + 1] {
-                    Value::Number(n) => n.as_f64(),
-                    _ => return Err("< requires numeric arguments".into()),
-                };
-
-                if a >= b {
-                    return Ok(Value::Boolean(false));
+        Value::Procedure(Rc::new(|args: Vec<Value>| match args.split_first() {
+            None => Err("/ requires at least one argument".into()),
+            Some((n, [])) => Ok(Value::Number(arithmetic::div(
+                &NumberKind::Integer(1),
+                number("/", n)?,
+            )?)),
+            Some((first, rest)) => {
+                let mut result = number("/", first)?.clone();
+                for arg in rest {
+                    result = arithmetic::div(&result, number("/", arg)?)?;
                 }
+                Ok(Value::Number(result))
             }
-
-            Ok(Value::Boolean(true))
         })),
     );
 
-    // Greater than
-    env.borrow_mut().bindings.insert(
-        ">".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.len() < 2 {
-                return Err("> requires at least two arguments".into());
-            }
-
-            for i in 0..args.len() - 1 {
-                let a = match &args[i] {
-                    Value::Number(n) => n.as_f64(),
-                    _ => return Err("> requires numeric arguments".into()),
-                };
-
-                let b = match &args[i + 1] {
-                    Value::Number(n) => n.as_f64(),
-                    _ => return Err("> requires numeric arguments".into()),
-                };
-
-                if a <= b {
-                    return Ok(Value::Boolean(false));
+    // Comparisons hold when each adjacent pair of arguments is ordered so;
+    // NaN compares as nothing, so any comparison with it is false
+    for (name, holds) in [
+        ("=", cmp::Ordering::is_eq as fn(cmp::Ordering) -> bool),
+        ("<", cmp::Ordering::is_lt),
+        (">", cmp::Ordering::is_gt),
+        ("<=", cmp::Ordering::is_le),
+        (">=", cmp::Ordering::is_ge),
+    ] {
+        env.borrow_mut().bindings.insert(
+            name.to_string(),
+            Value::Procedure(Rc::new(move |args: Vec<Value>| {
+                if args.len() < 2 {
+                    return Err(format!("{} requires at least two arguments", name));
                 }
-            }
-
-            Ok(Value::Boolean(true))
-        })),
-    );
-
-    // Less than or equal
-    env.borrow_mut().bindings.insert(
-        "<=".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.len() < 2 {
-                return Err("<= requires at least two arguments".into());
-            }
-
-            for i in 0..args.len() - 1 {
-                let a = match &args[i] {
-                    Value::Number(n) => n.as_f64(),
-                    _ => return Err("<= requires numeric arguments".into()),
-                };
-
-                let b = match &args[i + 1] {
-                    Value::Number(n) => n.as_f64(),
-                    _ => return Err("<= requires numeric arguments".into()),
-                };
-
-                if a > b {
-                    return Ok(Value::Boolean(false));
+                let mut ordered = true;
+                for pair in args.windows(2) {
+                    let (a, b) = (number(name, &pair[0])?, number(name, &pair[1])?);
+                    ordered &= arithmetic::compare(a, b).is_some_and(holds);
                 }
-            }
-
-            Ok(Value::Boolean(true))
-        })),
-    );
-
-    // Greater than or equal
-    env.borrow_mut().bindings.insert(
-        ">=".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.len() < 2 {
-                return Err(">= requires at least two arguments".into());
-            }
-
-            for i in 0..args.len() - 1 {
-                let a = match &args[i] {
-                    Value::Number(n) => n.as_f64(),
-                    _ => return Err(">= requires numeric arguments".into()),
-                };
+                Ok(Value::Boolean(ordered))
+            })),
+        );
+    }
 
-                let b = match &args[i + 1] {
-                    Value::Number(n) => n.as_f64(),
-                    _ => return Err(">= requires numeric arguments".into()),
-                };
+    // Integer division, and powers, inexact if an argument is
+    for (name, divide) in [
+        (
+            "quotient",
+            arithmetic::quotient as fn(&NumberKind, &NumberKind) -> Result<NumberKind, String>,
+        ),
+        ("remainder", arithmetic::remainder),
+        ("modulo", arithmetic::modulo),
+        ("expt", arithmetic::expt),
+    ] {
+        env.borrow_mut().bindings.insert(
+            name.to_string(),
+            Value::Procedure(Rc::new(move |args: Vec<Value>| match args.as_slice() {
+                [a, b] => Ok(Value::Number(divide(number(name, a)?, number(name, b)?)?)),
+                _ => Err(format!("{} requires exactly 2 arguments", name)),
+            })),
+        );
+    }
 
-                if a < b {
-                    return Ok(Value::Boolean(false));
+    // The divisors of integers, of any number of them
+    for (name, combine, identity) in [
+        (
+            "gcd",
+            arithmetic::gcd as fn(&NumberKind, &NumberKind) -> Result<NumberKind, String>,
+            0,
+        ),
+        ("lcm", arithmetic::lcm, 1),
+    ] {
+        env.borrow_mut().bindings.insert(
+            name.to_string(),
+            Value::Procedure(Rc::new(move |args: Vec<Value>| {
+                let mut result = NumberKind::Integer(identity);
+                for arg in &args {
+                    result = combine(&result, number(name, arg)?)?;
                 }
-            }
+                Ok(Value::Number(result))
+            })),
+        );
+    }
 
-            Ok(Value::Boolean(true))
-        })),
-    );
+    // Converting between exact and inexact numbers
+    for (name, convert) in [
+        (
+            "exact",
+            arithmetic::to_exact as fn(&NumberKind) -> Result<NumberKind, String>,
+        ),
+        ("inexact->exact", arithmetic::to_exact),
+        ("inexact", |n| Ok(arithmetic::to_inexact(n))),
+        ("exact->inexact", |n| Ok(arithmetic::to_inexact(n))),
+    ] {
+        env.borrow_mut().bindings.insert(
+            name.to_string(),
+            Value::Procedure(Rc::new(move |args: Vec<Value>| match args.as_slice() {
+                [n] => Ok(Value::Number(convert(number(name, n)?)?)),
+                _ => Err(format!("{} requires exactly 1 argument", name)),
+            })),
+        );
+    }
 
     // Define boolean operations
     env.borrow_mut().bindings.insert(
@@ -313,47 +257,7 @@ pub fn register_procedures(env: Rc<RefCell<Environment>>) {
             if args.len() != 1 {
                 return Err("not requires exactly one argument".into());
             }
-            match args[0] {
-                Value::Boolean(b) => Ok(Value::Boolean(!b)),
-                _ => Ok(Value::Boolean(false)), // All non-#f values are truthy in Scheme
-            }
-        })),
-    );
-
-    // Add 'and' special form
-    env.borrow_mut().bindings.insert(
-        "and".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.is_empty() {
-                return Ok(Value::Boolean(true)); // (and) => #t
-            }
-
-            let mut result = Value::Boolean(true);
-            for arg in args {
-                if let Value::Boolean(false) = arg {
-                    return Ok(Value::Boolean(false)); // Short-circuit if any arg is #f
-                }
-                result = arg; // Return last value
-            }
-            Ok(result)
-        })),
-    );
-
-    // Add 'or' special form
-    env.borrow_mut().bindings.insert(
-        "or".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.is_empty() {
-                return Ok(Value::Boolean(false)); // (or) => #f
-            }
-
-            for arg in args {
-                if let Value::Boolean(false) = arg {
-                    continue; // Skip #f values
-                }
-                return Ok(arg); // Return first truthy value
-            }
-            Ok(Value::Boolean(false)) // No truthy values found
+            Ok(Value::Boolean(!args[0].is_truthy()))
         })),
     );
 
@@ -405,32 +309,6 @@ pub fn register_procedures(env: Rc<RefCell<Environment>>) {
         })),
     );
 
-    env.borrow_mut().bindings.insert(
-        "null?".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.len() != 1 {
-                return Err("null? requires exactly 1 argument".into());
-            }
-            match &args[0] {
-                Value::Nil => Ok(Value::Boolean(true)),
-                _ => Ok(Value::Boolean(false)),
-            }
-        })),
-    );
-
-    env.borrow_mut().bindings.insert(
-        "pair?".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.len() != 1 {
-                return Err("pair? requires exactly 1 argument".into());
-            }
-            match &args[0] {
-                Value::Pair(_) => Ok(Value::Boolean(true)),
-                _ => Ok(Value::Boolean(false)),
-            }
-        })),
-    );
-
     // Add bytevector operations
     env.borrow_mut().bindings.insert(
         "bytevector".to_string(),
@@ -515,8 +393,7 @@ pub fn register_procedures(env: Rc<RefCell<Environment>>) {
             let value = match &args[2] {
                 Value::Number(n) => match n {
                     NumberKind::Integer(i) => *i as u8,
-                    NumberKind::Real(r) => *r as u8,
-                    NumberKind::Rational(num, den) => (*num as f64 / *den as f64) as u8,
+                    other => other.as_f64() as u8,
                 },
                 _ => {
                     return Err(
@@ -557,10 +434,10 @@ pub fn register_procedures(env: Rc<RefCell<Environment>>) {
 
             for c in chars {
                 let char_val = Value::Character(c);
-                let result_val = match proc {
-                    Value::Procedure(p) => p(vec![char_val.clone()])?,
-                    _ => return Err("string-map requires a procedure as first argument".into()),
-                };
+                if !proc.is_procedure() {
+                    return Err("string-map requires a procedure as first argument".into());
+                }
+                let result_val = apply(proc, vec![char_val.clone()])?;
 
                 match result_val {
                     Value::Character(c) => result.push(c),
@@ -572,6 +449,92 @@ pub fn register_procedures(env: Rc<RefCell<Environment>>) {
         })),
     );
 
+    // Fresh symbols for code that builds code: (gensym [prefix]) and
+    // (generate-uninterned-symbol [prefix])
+    for name in ["gensym", "generate-uninterned-symbol"] {
+        env.borrow_mut().bindings.insert(
+            name.to_string(),
+            Value::Procedure(Rc::new(move |args: Vec<Value>| {
+                let prefix = match args.as_slice() {
+                    [] => "g",
+                    [Value::String(prefix)] | [Value::Symbol(prefix)] => prefix.as_str(),
+                    _ => {
+                        return Err(format!(
+                            "{} takes an optional string or symbol prefix",
+                            name
+                        ))
+                    }
+                };
+                Ok(gensym(prefix))
+            })),
+        );
+    }
+
+    // Introspection on procedures. Native procedures have no name or source,
+    // and their arity is unknown, so each answers #f for them.
+    env.borrow_mut().bindings.insert(
+        "procedure-name".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| match args.as_slice() {
+            [Value::Lambda(lambda)] => Ok(lambda
+                .name
+                .get()
+                .map_or(Value::Boolean(false), |name| Value::Symbol(name.clone()))),
+            [proc] if proc.is_procedure() => Ok(Value::Boolean(false)),
+            [other] => Err(format!(
+                "procedure-name requires a procedure, got {}",
+                other
+            )),
+            _ => Err(arity_error(
+                "(procedure-name proc)",
+                Arity::Exactly(1),
+                args.len(),
+            )),
+        })),
+    );
+
+    env.borrow_mut().bindings.insert(
+        "procedure-source".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| match args.as_slice() {
+            [Value::Lambda(lambda)] => Ok(lambda.source()),
+            [proc] if proc.is_procedure() => Ok(Value::Boolean(false)),
+            [other] => Err(format!(
+                "procedure-source requires a procedure, got {}",
+                other
+            )),
+            _ => Err(arity_error(
+                "(procedure-source proc)",
+                Arity::Exactly(1),
+                args.len(),
+            )),
+        })),
+    );
+
+    // (procedure-arity proc) is the number of arguments proc takes, or
+    // (at-least n) when it has a rest parameter
+    env.borrow_mut().bindings.insert(
+        "procedure-arity".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| match args.as_slice() {
+            [Value::Lambda(lambda)] => Ok(match lambda.arity() {
+                Arity::Exactly(n) => Value::from(n as i64),
+                Arity::AtLeast(n) => Value::cons(
+                    Value::Symbol("at-least".to_string()),
+                    Value::cons(Value::from(n as i64), Value::Nil),
+                ),
+            }),
+            [Value::Parameter(_)] => Ok(Value::from(0)),
+            [proc] if proc.is_procedure() => Ok(Value::Boolean(false)),
+            [other] => Err(format!(
+                "procedure-arity requires a procedure, got {}",
+                other
+            )),
+            _ => Err(arity_error(
+                "(procedure-arity proc)",
+                Arity::Exactly(1),
+                args.len(),
+            )),
+        })),
+    );
+
     // Add character operations
     env.borrow_mut().bindings.insert(
         "char-upcase".to_string(),
@@ -632,7 +595,7 @@ pub fn register_procedures(env: Rc<RefCell<Environment>>) {
             }
 
             let proc = &args[0];
-            if !matches!(proc, Value::Procedure(_) | Value::RustFn(_, _)) {
+            if !proc.is_procedure() {
                 return Err("First argument to string-for-each must be a procedure".into());
             }
 
@@ -665,21 +628,7 @@ pub fn register_procedures(env: Rc<RefCell<Environment>>) {
                 }
 
                 // Call the procedure with the characters
-                match proc {
-                    Value::Procedure(p) => {
-                        match p(char_args) {
-                            Ok(_) => {} // Ignore the result
-                            Err(e) => return Err(e),
-                        }
-                    }
-                    Value::RustFn(f, _) => {
-                        match f(char_args) {
-                            Ok(_) => {} // Ignore the result
-                            Err(e) => return Err(e),
-                        }
-                    }
-                    _ => unreachable!(), // We checked this above
-                }
+                apply(proc, char_args)?;
             }
 
             Ok(Value::Nil)
@@ -689,7 +638,9 @@ pub fn register_procedures(env: Rc<RefCell<Environment>>) {
     // Vector operations
     env.borrow_mut().bindings.insert(
         "vector".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| Ok(Value::Vector(Rc::new(args))))),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            Ok(Value::Vector(Rc::new(RefCell::new(args))))
+        })),
     );
 
     env.borrow_mut().bindings.insert(
@@ -701,7 +652,7 @@ pub fn register_procedures(env: Rc<RefCell<Environment>>) {
 
             match &args[0] {
                 Value::Vector(v) => {
-                    let len = v.len();
+                    let len = v.borrow().len();
                     Ok(Value::Number(NumberKind::Integer(len as i64)))
                 }
                 _ => Err("vector-length requires a vector".into()),
@@ -717,7 +668,7 @@ pub fn register_procedures(env: Rc<RefCell<Environment>>) {
             }
 
             let v = match &args[0] {
-                Value::Vector(vec) => vec.clone(),
+                Value::Vector(vec) => vec.borrow().clone(),
                 _ => return Err("vector-ref requires a vector as first argument".into()),
             };
 
@@ -742,7 +693,7 @@ pub fn register_procedures(env: Rc<RefCell<Environment>>) {
             }
 
             let proc = &args[0];
-            if !matches!(proc, Value::Procedure(_) | Value::RustFn(_, _)) {
+            if !proc.is_procedure() {
                 return Err("First argument to vector-map must be a procedure".into());
             }
 
@@ -750,7 +701,7 @@ pub fn register_procedures(env: Rc<RefCell<Environment>>) {
             let mut vectors = Vec::new();
             for arg in &args[1..] {
                 if let Value::Vector(v) = arg {
-                    vectors.push(v.clone());
+                    vectors.push(v.borrow().clone());
                 } else {
                     return Err("All arguments after the procedure must be vectors".into());
                 }
@@ -773,16 +724,12 @@ pub fn register_procedures(env: Rc<RefCell<Environment>>) {
                 }
 
                 // Call the procedure with the elements
-                let result_val = match proc {
-                    Value::Procedure(p) => p(element_args)?,
-                    Value::RustFn(f, _) => f(element_args)?,
-                    _ => unreachable!(), // We checked this above
-                };
+                let result_val = apply(proc, element_args)?;
 
                 result_vector.push(result_val);
             }
 
-            Ok(Value::Vector(Rc::new(result_vector)))
+            Ok(Value::Vector(Rc::new(RefCell::new(result_vector))))
         })),
     );
 
@@ -794,7 +741,7 @@ pub fn register_procedures(env: Rc<RefCell<Environment>>) {
             }
 
             let proc = &args[0];
-            if !matches!(proc, Value::Procedure(_) | Value::RustFn(_, _)) {
+            if !proc.is_procedure() {
                 return Err("First argument to vector-for-each must be a procedure".into());
             }
 
@@ -802,7 +749,7 @@ pub fn register_procedures(env: Rc<RefCell<Environment>>) {
             let mut vectors = Vec::new();
             for arg in &args[1..] {
                 if let Value::Vector(v) = arg {
-                    vectors.push(v.clone());
+                    vectors.push(v.borrow().clone());
                 } else {
                     return Err("All arguments after the procedure must be vectors".into());
                 }
@@ -824,27 +771,70 @@ pub fn register_procedures(env: Rc<RefCell<Environment>>) {
                 }
 
                 // Call the procedure with the elements
-                match proc {
-                    Value::Procedure(p) => {
-                        match p(element_args) {
-                            Ok(_) => {} // Ignore the result
-                            Err(e) => return Err(e),
-                        }
-                    }
-                    Value::RustFn(f, _) => {
-                        match f(element_args) {
-                            Ok(_) => {} // Ignore the result
-                            Err(e) => return Err(e),
-                        }
-                    }
-                    _ => unreachable!(), // We checked this above
-                }
+                apply(proc, element_args)?;
             }
 
             Ok(Value::Nil)
         })),
     );
 
+    // Fixed-size arrays, compiled to memory arrays by the EVM backends
+    env.borrow_mut().bindings.insert(
+        "make-array".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            libraries::check_args_count("make-array", &args, 1)?;
+            let size = libraries::number_to_i64(&args[0])?;
+            if size < 0 {
+                return Err(format!("make-array: negative size: {}", size));
+            }
+            let zero = Value::Number(NumberKind::Integer(0));
+            Ok(Value::Vector(Rc::new(RefCell::new(vec![
+                zero;
+                size as usize
+            ]))))
+        })),
+    );
+
+    env.borrow_mut().bindings.insert(
+        "array-length".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            libraries::check_args_count("array-length", &args, 1)?;
+            match &args[0] {
+                Value::Vector(v) => Ok(Value::Number(NumberKind::Integer(v.borrow().len() as i64))),
+                _ => Err("array-length requires an array".into()),
+            }
+        })),
+    );
+
+    env.borrow_mut().bindings.insert(
+        "array-ref".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            libraries::check_args_count("array-ref", &args, 2)?;
+            let array = match &args[0] {
+                Value::Vector(v) => v,
+                _ => return Err("array-ref requires an array as first argument".into()),
+            };
+            let index = array_index("array-ref", array, &args[1])?;
+            let value = array.borrow()[index].clone();
+            Ok(value)
+        })),
+    );
+
+    // Returns the array, so updates can be chained where there is no `let`
+    env.borrow_mut().bindings.insert(
+        "array-set!".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            libraries::check_args_count("array-set!", &args, 3)?;
+            let array = match &args[0] {
+                Value::Vector(v) => v,
+                _ => return Err("array-set! requires an array as first argument".into()),
+            };
+            let index = array_index("array-set!", array, &args[1])?;
+            array.borrow_mut()[index] = args[2].clone();
+            Ok(args[0].clone())
+        })),
+    );
+
     // Add numeric predicates
     env.borrow_mut().bindings.insert(
         "exact-integer?".to_string(),
@@ -854,7 +844,9 @@ pub fn register_procedures(env: Rc<RefCell<Environment>>) {
             }
 
             match &args[0] {
-                Value::Number(NumberKind::Integer(_)) => Ok(Value::Boolean(true)),
+                Value::Number(NumberKind::Integer(_) | NumberKind::BigInteger(_)) => {
+                    Ok(Value::Boolean(true))
+                }
                 _ => Ok(Value::Boolean(false)),
             }
         })),
@@ -868,8 +860,7 @@ pub fn register_procedures(env: Rc<RefCell<Environment>>) {
             }
 
             match &args[0] {
-                Value::Number(NumberKind::Integer(_)) => Ok(Value::Boolean(true)),
-                Value::Number(NumberKind::Rational(_, _)) => Ok(Value::Boolean(true)),
+                Value::Number(n) => Ok(Value::Boolean(n.is_exact())),
                 _ => Ok(Value::Boolean(false)),
             }
         })),
@@ -888,6 +879,291 @@ pub fn register_procedures(env: Rc<RefCell<Environment>>) {
             }
         })),
     );
+
+    // Classifying reals; exact numbers are always finite
+    for (name, test) in [
+        ("nan?", f64::is_nan as fn(f64) -> bool),
+        ("infinite?", f64::is_infinite),
+        ("finite?", f64::is_finite),
+    ] {
+        env.borrow_mut().bindings.insert(
+            name.to_string(),
+            Value::Procedure(Rc::new(move |args: Vec<Value>| {
+                if args.len() != 1 {
+                    return Err(format!("{} requires exactly 1 argument", name));
+                }
+
+                match &args[0] {
+                    Value::Number(NumberKind::Real(r)) => Ok(Value::Boolean(test(*r))),
+                    Value::Number(_) => Ok(Value::Boolean(test(0.0))),
+                    _ => Err(format!("{} requires a numeric argument", name)),
+                }
+            })),
+        );
+    }
+
+    // Numeric parsing and printing, in the literal syntax the lexer reads
+    env.borrow_mut().bindings.insert(
+        "string->number".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            if args.is_empty() || args.len() > 2 {
+                return Err("string->number requires 1 or 2 arguments".into());
+            }
+            let radix = radix_argument("string->number", args.get(1))?;
+            match &args[0] {
+                Value::String(s) => Ok(number::parse_number(s, radix)
+                    .map(Value::Number)
+                    .unwrap_or(Value::Boolean(false))),
+                _ => Err("string->number requires a string argument".into()),
+            }
+        })),
+    );
+
+    env.borrow_mut().bindings.insert(
+        "number->string".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            if args.is_empty() || args.len() > 2 {
+                return Err("number->string requires 1 or 2 arguments".into());
+            }
+            let radix = radix_argument("number->string", args.get(1))?;
+            match &args[0] {
+                Value::Number(n) => number::format_number(n, radix)
+                    .map(Value::String)
+                    .map_err(|e| format!("number->string: {}", e)),
+                _ => Err("number->string requires a number argument".into()),
+            }
+        })),
+    );
+
+    // Bitwise operations on two's complement integers, up to 256 bits wide
+    env.borrow_mut().bindings.insert(
+        "bitwise-and".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            fold_words("bitwise-and", &args, !Word::ZERO, |a, b| a & b)
+        })),
+    );
+
+    env.borrow_mut().bindings.insert(
+        "bitwise-or".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            fold_words("bitwise-or", &args, Word::ZERO, |a, b| a | b)
+        })),
+    );
+
+    env.borrow_mut().bindings.insert(
+        "bitwise-xor".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            fold_words("bitwise-xor", &args, Word::ZERO, |a, b| a ^ b)
+        })),
+    );
+
+    env.borrow_mut().bindings.insert(
+        "bitwise-not".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            if args.len() != 1 {
+                return Err("bitwise-not requires exactly 1 argument".into());
+            }
+            let word = value_to_word(&args[0]).map_err(|e| format!("bitwise-not: {}", e))?;
+            Ok(bitwise_result(!word, !is_negative(&args[0])))
+        })),
+    );
+
+    // (arithmetic-shift n count): left for positive counts, sign-preserving right otherwise
+    env.borrow_mut().bindings.insert(
+        "arithmetic-shift".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            if args.len() != 2 {
+                return Err("arithmetic-shift requires exactly 2 arguments".into());
+            }
+            let word = value_to_word(&args[0]).map_err(|e| format!("arithmetic-shift: {}", e))?;
+            let count = match &args[1] {
+                Value::Number(NumberKind::Integer(count)) => *count,
+                _ => return Err("arithmetic-shift requires an integer shift count".into()),
+            };
+
+            let bits = count.unsigned_abs().min(256) as u32;
+            let negative = is_negative(&args[0]);
+            if count >= 0 {
+                Ok(bitwise_result(word.shift_left(bits), negative))
+            } else if negative {
+                Ok(bitwise_result(word.shift_right_arithmetic(bits), negative))
+            } else {
+                Ok(bitwise_result(word.shift_right(bits), negative))
+            }
+        })),
+    );
+
+    // EVM addresses
+    env.borrow_mut().bindings.insert(
+        "address?".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            if args.len() != 1 {
+                return Err("address? requires exactly 1 argument".into());
+            }
+            Ok(Value::Boolean(matches!(args[0], Value::Address(_))))
+        })),
+    );
+
+    env.borrow_mut().bindings.insert(
+        "string->address".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            if args.len() != 1 {
+                return Err("string->address requires exactly 1 argument".into());
+            }
+            match &args[0] {
+                Value::String(s) => Ok(Value::Address(parse_address(s)?)),
+                _ => Err("string->address requires a string argument".into()),
+            }
+        })),
+    );
+
+    env.borrow_mut().bindings.insert(
+        "address->string".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            if args.len() != 1 {
+                return Err("address->string requires exactly 1 argument".into());
+            }
+            match &args[0] {
+                Value::Address(bytes) => Ok(Value::String(checksum_address(bytes))),
+                _ => Err("address->string requires an address argument".into()),
+            }
+        })),
+    );
+
+    // CREATE2 addresses, so factories can predict what they deploy
+    env.borrow_mut().bindings.insert(
+        "init-code-hash".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            libraries::check_args_count("init-code-hash", &args, 1)?;
+            match &args[0] {
+                Value::Bytevector(code) => Ok(Value::Bytevector(Rc::new(RefCell::new(
+                    keccak256(&code.borrow()).to_vec(),
+                )))),
+                _ => Err("init-code-hash requires a bytevector argument".into()),
+            }
+        })),
+    );
+
+    // (create2-address deployer salt init-code-hash)
+    env.borrow_mut().bindings.insert(
+        "create2-address".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            libraries::check_args_count("create2-address", &args, 3)?;
+            let deployer = match &args[0] {
+                Value::Address(bytes) => bytes,
+                _ => return Err("create2-address requires an address as first argument".into()),
+            };
+            let salt = value_to_word(&args[1]).map_err(|e| format!("create2-address: {}", e))?;
+            let hash = value_to_word(&args[2]).map_err(|e| format!("create2-address: {}", e))?;
+            Ok(Value::Address(create2_address(
+                deployer,
+                &salt.to_be_bytes(),
+                &hash.to_be_bytes(),
+            )))
+        })),
+    );
+
+    // Hex encoding
+    env.borrow_mut().bindings.insert(
+        "bytevector->hex".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            if args.len() != 1 {
+                return Err("bytevector->hex requires exactly 1 argument".into());
+            }
+            match &args[0] {
+                Value::Bytevector(bytes) => Ok(Value::String(encode_hex(&bytes.borrow()))),
+                _ => Err("bytevector->hex requires a bytevector argument".into()),
+            }
+        })),
+    );
+
+    env.borrow_mut().bindings.insert(
+        "hex->bytevector".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            if args.len() != 1 {
+                return Err("hex->bytevector requires exactly 1 argument".into());
+            }
+            match &args[0] {
+                Value::String(s) => Ok(Value::Bytevector(Rc::new(RefCell::new(decode_hex(s)?)))),
+                _ => Err("hex->bytevector requires a string argument".into()),
+            }
+        })),
+    );
+
+    env.borrow_mut().bindings.insert(
+        "string->hex".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            if args.len() != 1 {
+                return Err("string->hex requires exactly 1 argument".into());
+            }
+            match &args[0] {
+                Value::String(s) => Ok(Value::String(encode_hex(s.as_bytes()))),
+                _ => Err("string->hex requires a string argument".into()),
+            }
+        })),
+    );
+
+    // Read as unsigned, so 32 bytes of 0xff is 2^256 - 1 rather than -1
+    env.borrow_mut().bindings.insert(
+        "hex->u256".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            if args.len() != 1 {
+                return Err("hex->u256 requires exactly 1 argument".into());
+            }
+            let s = match &args[0] {
+                Value::String(s) => s,
+                _ => return Err("hex->u256 requires a string argument".into()),
+            };
+            let word = Word::from_be_bytes(&decode_quantity(s)?)?;
+            Ok(word_to_unsigned_value(word))
+        })),
+    );
+
+    // Process environment, as in R7RS (scheme process-context)
+    env.borrow_mut().bindings.insert(
+        "get-environment-variable".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            libraries::check_args_count("get-environment-variable", &args, 1)?;
+            match &args[0] {
+                Value::String(name) => Ok(std::env::var(name)
+                    .map(Value::String)
+                    .unwrap_or(Value::Boolean(false))),
+                _ => Err("get-environment-variable requires a string argument".into()),
+            }
+        })),
+    );
+
+    // An alist of (name . value), sorted by name
+    env.borrow_mut().bindings.insert(
+        "get-environment-variables".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            libraries::check_args_count("get-environment-variables", &args, 0)?;
+            let mut vars: Vec<(String, String)> = std::env::vars().collect();
+            vars.sort();
+            Ok(vars
+                .into_iter()
+                .rev()
+                .fold(Value::Nil, |list, (name, value)| {
+                    Value::cons(Value::cons(Value::String(name), Value::String(value)), list)
+                }))
+        })),
+    );
+
+    // The arguments the process was started with, the program first, until
+    // a runner gives the script its own
+    set_command_line(&env, std::env::args().collect());
+}
+
+/// Bind `command-line` in `env` to return `args`, the script's name first
+pub fn set_command_line(env: &Rc<RefCell<Environment>>, args: Vec<String>) {
+    let args = Value::list(args.into_iter().map(Value::String));
+    env.borrow_mut().bindings.insert(
+        "command-line".to_string(),
+        Value::Procedure(Rc::new(move |a: Vec<Value>| {
+            libraries::check_args_count("command-line", &a, 0)?;
+            Ok(args.clone())
+        })),
+    );
 }
 
 // Create a child environment by extending the parent with new bindings
@@ -908,43 +1184,104 @@ pub fn extend_environment(
     let mut env = Environment::new();
     env.parent = Some(parent);
 
-    for (name, value) in names.into_iter().zip(values.into_iter()) {
+    for (name, value) in names.into_iter().zip(values) {
         env.bindings.insert(name, value);
     }
 
     Ok(Rc::new(RefCell::new(env)))
 }
 
+// Combine the arguments of a variadic bitwise operation. The operation works
+// on each bit alike, so applied to the sign bits of the arguments it gives the
+// sign of the result.
+fn fold_words(
+    name: &str,
+    args: &[Value],
+    init: Word,
+    op: impl Fn(Word, Word) -> Word,
+) -> Result<Value, String> {
+    let sign = |negative: bool| if negative { Word::MAX } else { Word::ZERO };
+    let mut result = init;
+    let mut negative = init.is_negative();
+    for arg in args {
+        let word = value_to_word(arg).map_err(|e| format!("{}: {}", name, e))?;
+        result = op(result, word);
+        negative = !op(sign(negative), sign(is_negative(arg))).is_zero();
+    }
+    Ok(bitwise_result(result, negative))
+}
+
+// A negative integer, as opposed to a word, which is unsigned
+fn is_negative(value: &Value) -> bool {
+    match value {
+        Value::Number(n) => n.as_f64() < 0.0,
+        _ => false,
+    }
+}
+
+// The integer the word a bitwise operation made stands for: read as signed
+// when the result of the operation on unbounded integers is negative, so
+// `(bitwise-not 0)` is -1 and `(arithmetic-shift 1 255)` is 2^255
+fn bitwise_result(word: Word, negative: bool) -> Value {
+    if negative {
+        word_to_value(word)
+    } else {
+        word_to_unsigned_value(word)
+    }
+}
+
+// The number `arg` holds, or an error naming `name` if it isn't one
+fn number<'a>(name: &str, arg: &'a Value) -> Result<&'a NumberKind, String> {
+    match arg {
+        Value::Number(n) => Ok(n),
+        _ => Err(format!("{} requires numeric arguments", name)),
+    }
+}
+
+// Check an index into the elements of an array
+fn array_index(name: &str, array: &RefCell<Vec<Value>>, index: &Value) -> Result<usize, String> {
+    let index = libraries::number_to_i64(index)?;
+    if index < 0 || index as usize >= array.borrow().len() {
+        return Err(format!("{}: index out of bounds: {}", name, index));
+    }
+    Ok(index as usize)
+}
+
+/// A symbol no other symbol is equal to: `prefix#n` for a process-wide counter
+/// `n`. The reader splits symbols at `#`, so no source code can name it.
+pub fn gensym(prefix: &str) -> Value {
+    static COUNTER: AtomicUsize = AtomicUsize::new(1);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    Value::Symbol(format!("{}#{}", prefix, n))
+}
+
 // Look up a variable in the environment chain
 pub fn lookup_variable(name: &str, env: Rc<RefCell<Environment>>) -> Result<Value, String> {
-    let mut current_env = env;
+    lookup(name, &env).ok_or_else(|| undefined_message(name, &env))
+}
+
+/// The value `name` is bound to in `env` or a scope enclosing it
+pub fn lookup(name: &str, env: &Rc<RefCell<Environment>>) -> Option<Value> {
+    let mut current_env = env.clone();
 
     loop {
         // Check the current environment
         let env_ref = current_env.borrow();
         if let Some(value) = env_ref.bindings.get(name) {
-            return Ok(value.clone());
+            return Some(value.clone());
         }
 
         // Move to parent environment if there is one
-        match &env_ref.parent {
-            Some(parent) => {
-                let parent_clone = parent.clone();
-                drop(env_ref); // Drop the borrow before reassigning
-                current_env = parent_clone;
-            }
-            None => {
-                drop(env_ref); // Drop the borrow
-                return Err(format!("Undefined variable: {}", name));
-            }
-        }
+        let parent_clone = env_ref.parent.clone()?;
+        drop(env_ref); // Drop the borrow before reassigning
+        current_env = parent_clone;
     }
 }
 
 // Set a variable's value in the environment chain
 #[allow(dead_code)]
 pub fn set_variable(name: &str, value: Value, env: Rc<RefCell<Environment>>) -> Result<(), Error> {
-    let mut current_env = env;
+    let mut current_env = env.clone();
 
     loop {
         // Check the current environment
@@ -969,14 +1306,91 @@ pub fn set_variable(name: &str, value: Value, env: Rc<RefCell<Environment>>) ->
             }
             None => {
                 drop(env_ref); // Drop the borrow
-                return Err(Error::Runtime(format!("Undefined variable: {}", name)));
+                return Err(undefined_variable(name, &env));
             }
         }
     }
 }
 
+/// The error for a name bound nowhere in `env`, suggesting bound names it may
+/// be a typo of
+pub fn undefined_variable(name: &str, env: &Rc<RefCell<Environment>>) -> Error {
+    Error::Undefined {
+        name: name.to_string(),
+        message: undefined_message(name, env),
+    }
+}
+
+fn undefined_message(name: &str, env: &Rc<RefCell<Environment>>) -> String {
+    let mut bound = Vec::new();
+    let mut current = Some(env.clone());
+    while let Some(scope) = current {
+        bound.extend(scope.borrow().bindings.keys().cloned());
+        current = scope.borrow().parent.clone();
+    }
+
+    let suggestions = suggest_names(name, bound.iter().map(String::as_str));
+    if suggestions.is_empty() {
+        format!("Undefined variable: {}", name)
+    } else {
+        format!(
+            "Undefined variable: {} (did you mean {}?)",
+            name,
+            suggestions.join(", ")
+        )
+    }
+}
+
+/// Up to three of `candidates` close enough to `name` to be a typo of it,
+/// closest first: within an edit distance of a third of its length, and at
+/// least 1. The booleans and, for a longer name, one-character names such as
+/// `+` are never suggested, as they are close to any short name.
+pub fn suggest_names<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let length = name.chars().count();
+    let limit = (length / 3).max(1);
+    let mut close: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name && !matches!(*candidate, "#t" | "#f"))
+        .filter(|candidate| length == 1 || candidate.chars().count() > 1)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .collect();
+    close.sort();
+    close.dedup();
+    close
+        .into_iter()
+        .take(3)
+        .map(|(_, candidate)| candidate.to_string())
+        .collect()
+}
+
+/// Levenshtein distance between two strings, counted in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
 // Define a new variable in the current environment
 #[allow(dead_code)]
 pub fn define_variable(name: &str, value: Value, env: &mut Environment) {
     env.bindings.insert(name.to_string(), value);
 }
+
+/// The optional radix argument of string->number and number->string
+fn radix_argument(name: &str, radix: Option<&Value>) -> Result<u32, String> {
+    match radix {
+        None => Ok(10),
+        Some(Value::Number(NumberKind::Integer(r))) if [2, 8, 10, 16].contains(r) => Ok(*r as u32),
+        Some(_) => Err(format!("{} radix must be 2, 8, 10 or 16", name)),
+    }
+}
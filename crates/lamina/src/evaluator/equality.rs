@@ -0,0 +1,233 @@
+// Equivalence predicates, the list searches built on them, and the character
+// and string comparisons
+//
+// `eq?`, `eqv?` and `equal?` are the three R7RS equivalences, each coarser
+// than the one before:
+//
+// - `eq?` holds for the same object: the same pair, vector, procedure or
+//   record, and the same immediate value, such as a symbol, character,
+//   boolean, small integer or real. Integers past an `i64` and rationals are
+//   allocated, so two of them are only `eq?` if they are the same allocation.
+// - `eqv?` is `eq?` that also compares every number by exactness and value,
+//   so `(eqv? 1/2 1/2)` holds while `(eqv? 2 2.0)` doesn't. Reals are
+//   compared bit for bit, so NaN is `eqv?` to itself and 0.0 isn't to -0.0.
+// - `equal?` compares pairs, vectors and bytevectors element by element,
+//   recursing into them, and is `eqv?` on everything else. It walks
+//   structures with a stack of its own rather than recursing, and assumes two
+//   vectors it is already comparing are equal, so it finishes on structures
+//   that contain themselves.
+//
+// Strings are values in Lamina rather than mutable objects with an identity,
+// so all three compare them by their characters. `memq`, `memv` and `member`
+// search a list with each of them in turn, `member` with any procedure of two
+// arguments passed after the list.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use crate::value::{Environment, NumberKind, Value};
+
+/// Whether `a` and `b` are the same object
+pub fn is_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(NumberKind::BigInteger(x)), Value::Number(NumberKind::BigInteger(y))) => {
+            Rc::ptr_eq(x, y)
+        }
+        (Value::Number(NumberKind::Rational(x)), Value::Number(NumberKind::Rational(y))) => {
+            Rc::ptr_eq(x, y)
+        }
+        _ => is_eqv(a, b),
+    }
+}
+
+/// Whether `a` and `b` are the same object or numbers of the same exactness
+/// and value
+pub fn is_eqv(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Nil, Value::Nil) => true,
+        (Value::Boolean(x), Value::Boolean(y)) => x == y,
+        (Value::Number(NumberKind::Real(x)), Value::Number(NumberKind::Real(y))) => {
+            x.to_bits() == y.to_bits()
+        }
+        (Value::Number(x), Value::Number(y)) => x == y,
+        (Value::Character(x), Value::Character(y)) => x == y,
+        (Value::String(x), Value::String(y)) => x == y,
+        (Value::Symbol(x), Value::Symbol(y)) => x == y,
+        (Value::Address(x), Value::Address(y)) => x == y,
+        (Value::Pair(x), Value::Pair(y)) => Rc::ptr_eq(x, y),
+        (Value::Vector(x), Value::Vector(y)) => Rc::ptr_eq(x, y),
+        (Value::Bytevector(x), Value::Bytevector(y)) => Rc::ptr_eq(x, y),
+        (Value::Procedure(x), Value::Procedure(y)) | (Value::RustFn(x, _), Value::RustFn(y, _)) => {
+            std::ptr::addr_eq(Rc::as_ptr(x), Rc::as_ptr(y))
+        }
+        (Value::Lambda(x), Value::Lambda(y)) => Rc::ptr_eq(x, y),
+        (Value::Continuation(x), Value::Continuation(y)) => Rc::ptr_eq(x, y),
+        (Value::Library(x), Value::Library(y)) => Rc::ptr_eq(x, y),
+        // The rest are compared by identity already
+        _ => a == b,
+    }
+}
+
+/// Whether `a` and `b` print the same: pairs, vectors and bytevectors with
+/// `equal?` elements, and `eqv?` values otherwise
+pub fn is_equal(a: &Value, b: &Value) -> bool {
+    // Vector pairs being compared, by address
+    let mut comparing = HashSet::new();
+    let mut pending = vec![(a.clone(), b.clone())];
+    while let Some((a, b)) = pending.pop() {
+        match (&a, &b) {
+            (Value::Pair(x), Value::Pair(y)) => {
+                if !Rc::ptr_eq(x, y) {
+                    pending.push((x.1.clone(), y.1.clone()));
+                    pending.push((x.0.clone(), y.0.clone()));
+                }
+            }
+            (Value::Vector(x), Value::Vector(y)) => {
+                if Rc::ptr_eq(x, y) || !comparing.insert((Rc::as_ptr(x), Rc::as_ptr(y))) {
+                    continue;
+                }
+                let (x, y) = (x.borrow(), y.borrow());
+                if x.len() != y.len() {
+                    return false;
+                }
+                pending.extend(x.iter().cloned().zip(y.iter().cloned()).rev());
+            }
+            (Value::Bytevector(x), Value::Bytevector(y)) => {
+                if *x.borrow() != *y.borrow() {
+                    return false;
+                }
+            }
+            _ => {
+                if !is_eqv(&a, &b) {
+                    return false;
+                }
+            }
+        }
+    }
+    true
+}
+
+/// Register the equivalence predicates and the character and string
+/// comparisons
+pub fn register_equality_procedures(env: &Rc<RefCell<Environment>>) {
+    let mut env = env.borrow_mut();
+    for (name, procedure) in equality_procedures() {
+        env.bindings.insert(name, procedure);
+    }
+}
+
+/// `eq?`, `eqv?` and `equal?`, `memq`, `memv` and `member`, and the
+/// comparisons of characters and strings, case-sensitive and, with `-ci`, not
+pub fn equality_procedures() -> Vec<(String, Value)> {
+    let mut procedures = Vec::new();
+    for (name, equivalent) in [
+        ("eq?", is_eq as fn(&Value, &Value) -> bool),
+        ("eqv?", is_eqv),
+        ("equal?", is_equal),
+    ] {
+        procedures.push((
+            name.to_string(),
+            Value::Procedure(Rc::new(move |args: Vec<Value>| match args.as_slice() {
+                [a, b] => Ok(Value::Boolean(equivalent(a, b))),
+                _ => Err(format!("{} requires exactly 2 arguments", name)),
+            })),
+        ));
+    }
+
+    for (name, equivalent) in [
+        ("memq", is_eq as fn(&Value, &Value) -> bool),
+        ("memv", is_eqv),
+        ("member", is_equal),
+    ] {
+        procedures.push((
+            name.to_string(),
+            Value::Procedure(Rc::new(move |args: Vec<Value>| match args.as_slice() {
+                [item, list] => tail_from(list, |x| Ok(equivalent(item, x))),
+                [item, list, compare] if name == "member" => tail_from(list, |x| {
+                    Ok(super::apply(compare, vec![item.clone(), x.clone()])?.is_truthy())
+                }),
+                _ => Err(format!("{} requires exactly 2 arguments", name)),
+            })),
+        ));
+    }
+
+    for (suffix, holds) in [
+        (
+            "=?",
+            std::cmp::Ordering::is_eq as fn(std::cmp::Ordering) -> bool,
+        ),
+        ("<?", std::cmp::Ordering::is_lt),
+        (">?", std::cmp::Ordering::is_gt),
+        ("<=?", std::cmp::Ordering::is_le),
+        (">=?", std::cmp::Ordering::is_ge),
+    ] {
+        for fold_case in [false, true] {
+            let ci = if fold_case { "-ci" } else { "" };
+            let name = format!("char{}{}", ci, suffix);
+            procedures.push((
+                name.clone(),
+                comparison(name, holds, move |value| match value {
+                    Value::Character(c) if fold_case => Some(fold_char(*c).to_string()),
+                    Value::Character(c) => Some(c.to_string()),
+                    _ => None,
+                }),
+            ));
+            let name = format!("string{}{}", ci, suffix);
+            procedures.push((
+                name.clone(),
+                comparison(name, holds, move |value| match value {
+                    Value::String(s) if fold_case => Some(s.to_lowercase()),
+                    Value::String(s) => Some(s.clone()),
+                    _ => None,
+                }),
+            ));
+        }
+    }
+    procedures
+}
+
+// The first tail of `list` whose car `matches`, or #f
+fn tail_from(
+    list: &Value,
+    matches: impl Fn(&Value) -> Result<bool, String>,
+) -> Result<Value, String> {
+    let mut current = list.clone();
+    while let Value::Pair(pair) = &current {
+        if matches(&pair.0)? {
+            return Ok(current);
+        }
+        current = pair.1.clone();
+    }
+    Ok(Value::Boolean(false))
+}
+
+fn fold_char(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+// A comparison holding when each adjacent pair of arguments, as `key` gives
+// them, is ordered so
+fn comparison(
+    name: String,
+    holds: fn(std::cmp::Ordering) -> bool,
+    key: impl Fn(&Value) -> Option<String> + 'static,
+) -> Value {
+    let kind = if name.starts_with("char") {
+        "character"
+    } else {
+        "string"
+    };
+    Value::Procedure(Rc::new(move |args: Vec<Value>| {
+        if args.len() < 2 {
+            return Err(format!("{} requires at least two arguments", name));
+        }
+        let keys = args
+            .iter()
+            .map(|arg| key(arg).ok_or_else(|| format!("{} requires {} arguments", name, kind)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Value::Boolean(
+            keys.windows(2).all(|pair| holds(pair[0].cmp(&pair[1]))),
+        ))
+    }))
+}
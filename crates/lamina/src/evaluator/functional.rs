@@ -0,0 +1,201 @@
+// Functional helpers: the (lamina functional) library
+//
+//   (import (lamina functional))
+//   ((partial + 1 2) 3)                  ; 6
+//   (pipe 5 double inc)                  ; (inc (double 5))
+//   ((compose inc double) 5)             ; the same
+//   (((curry list 3) 1 2) 3)             ; (1 2 3)
+//   (-> 5 double (- 1))                  ; (- (double 5) 1)
+//   (->> 5 double (- 1))                 ; (- 1 (double 5))
+//
+// They take any procedure, closures and native or Rust functions alike, and
+// return native procedures, so what they call runs outside the caller's
+// stack: a `shift` or `yield` in a procedure passed to them can't reach a
+// `reset` or generator around the call.
+//
+// The threading macros `->` and `->>` are `syntax-rules` macros instead, so
+// they rewrite the forms where they are used: each step is a procedure or a
+// call missing its first (`->`) or last (`->>`) argument. They expand to
+// themselves, so import them under their own names.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::error::{arity_error, Arity};
+use crate::value::{Environment, Library, NumberKind, Value};
+
+use crate::lexer;
+use crate::parser;
+
+use super::environment::create_environment;
+use super::libraries::bind_library;
+use super::{apply, eval_with_env};
+
+const FUNCTIONAL_EXPORTS: &[&str] = &["partial", "pipe", "compose", "curry", "->", "->>"];
+
+const THREADING_MACROS: &str = "
+(define-syntax ->
+  (syntax-rules ()
+    ((_ x) x)
+    ((_ x (f arg ...) step ...) (-> (f x arg ...) step ...))
+    ((_ x f step ...) (-> (f x) step ...))))
+(define-syntax ->>
+  (syntax-rules ()
+    ((_ x) x)
+    ((_ x (f arg ...) step ...) (->> (f arg ... x) step ...))
+    ((_ x f step ...) (->> (f x) step ...))))
+";
+
+fn procedure_argument(name: &str, value: &Value) -> Result<Value, String> {
+    if value.is_procedure() {
+        Ok(value.clone())
+    } else {
+        Err(format!("{} requires procedures, got {}", name, value))
+    }
+}
+
+// A procedure taking `count` arguments in as many calls as it takes, then
+// calling `procedure` with them all
+fn curried(procedure: Value, count: usize, collected: Vec<Value>) -> Value {
+    Value::Procedure(Rc::new(move |args: Vec<Value>| {
+        let mut collected = collected.clone();
+        collected.extend(args);
+        if collected.len() >= count {
+            apply(&procedure, collected)
+        } else {
+            Ok(curried(procedure.clone(), count, collected))
+        }
+    }))
+}
+
+/// Bind the `(lamina functional)` library in `env`
+pub fn register_functional_library(env: Rc<RefCell<Environment>>) {
+    let functional_env = create_environment(Some(env.clone()));
+    let mut bindings = Vec::new();
+
+    // (partial f arg ...): f with its first arguments fixed
+    bindings.push((
+        "partial",
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            let Some((procedure, fixed)) = args.split_first() else {
+                return Err(arity_error("(partial f arg ...)", Arity::AtLeast(1), 0));
+            };
+            let procedure = procedure_argument("partial", procedure)?;
+            let fixed = fixed.to_vec();
+            Ok(Value::Procedure(Rc::new(move |args: Vec<Value>| {
+                let mut all = fixed.clone();
+                all.extend(args);
+                apply(&procedure, all)
+            })))
+        })),
+    ));
+
+    // (pipe x f ...): x passed through each procedure in turn
+    bindings.push((
+        "pipe",
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            let Some((value, procedures)) = args.split_first() else {
+                return Err(arity_error("(pipe x f ...)", Arity::AtLeast(1), 0));
+            };
+            procedures
+                .iter()
+                .try_fold(value.clone(), |value, procedure| {
+                    apply(&procedure_argument("pipe", procedure)?, vec![value])
+                })
+        })),
+    ));
+
+    // (compose f ... g): the procedure applying g to its arguments, then the
+    // others from right to left
+    bindings.push((
+        "compose",
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            let procedures = args
+                .iter()
+                .map(|procedure| procedure_argument("compose", procedure))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Value::Procedure(Rc::new(move |args: Vec<Value>| {
+                let Some((innermost, rest)) = procedures.split_last() else {
+                    return match args.as_slice() {
+                        [value] => Ok(value.clone()),
+                        _ => Err(arity_error("(compose)", Arity::Exactly(1), args.len())),
+                    };
+                };
+                rest.iter()
+                    .rev()
+                    .try_fold(apply(innermost, args)?, |value, procedure| {
+                        apply(procedure, vec![value])
+                    })
+            })))
+        })),
+    ));
+
+    // (curry f [n]): f taking its n arguments over several calls; n defaults
+    // to the number of parameters of a procedure made by `lambda`
+    bindings.push((
+        "curry",
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            let (procedure, count) = match args.as_slice() {
+                [procedure] => {
+                    let count = match procedure {
+                        Value::Lambda(lambda) => match lambda.arity() {
+                            Arity::Exactly(n) => n,
+                            Arity::AtLeast(_) => {
+                                return Err(format!(
+                                "curry: {} takes a rest parameter; give the number of arguments",
+                                lambda.signature()
+                            ))
+                            }
+                        },
+                        _ => {
+                            return Err(format!(
+                                "curry: the number of arguments of {} is unknown; give it",
+                                procedure
+                            ))
+                        }
+                    };
+                    (procedure, count)
+                }
+                [procedure, Value::Number(NumberKind::Integer(n))] if *n >= 0 => {
+                    (procedure, *n as usize)
+                }
+                [_, other] => {
+                    return Err(format!(
+                        "curry requires a non-negative argument count, got {}",
+                        other
+                    ))
+                }
+                _ => return Err(arity_error("(curry f [n])", Arity::AtLeast(1), args.len())),
+            };
+            Ok(curried(
+                procedure_argument("curry", procedure)?,
+                count,
+                Vec::new(),
+            ))
+        })),
+    ));
+
+    for (name, procedure) in bindings {
+        functional_env
+            .borrow_mut()
+            .bindings
+            .insert(name.to_string(), procedure);
+    }
+    let tokens = lexer::lex(THREADING_MACROS).expect("the threading macros lex");
+    for form in parser::parse_all(&tokens).expect("the threading macros parse") {
+        eval_with_env(form, functional_env.clone()).expect("the threading macros are valid");
+    }
+
+    let library = Library {
+        name: vec!["lamina".to_string(), "functional".to_string()],
+        exports: FUNCTIONAL_EXPORTS
+            .iter()
+            .map(|name| name.to_string())
+            .collect(),
+        imports: vec![],
+        renames: HashMap::new(),
+        environment: functional_env,
+    };
+    bind_library(&env, Rc::new(RefCell::new(library)));
+}
@@ -0,0 +1,107 @@
+// Generators
+//
+// `(generator body ...)` makes a procedure of no arguments. Each call runs the
+// body until it evaluates `(yield x)` and returns `x`; the frames between the
+// generator and the `yield` are kept, one-shot, and the next call resumes them.
+// Once the body returns, calls return the end-of-file object, which is how
+// `generator->list` and `generator-for-each` know to stop.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::value::{Environment, Value};
+
+use super::apply;
+use super::machine::{self, Frame, State};
+use super::ports::{eof_object, is_eof_object};
+
+/// Where a generator's body is
+pub(super) enum Generator {
+    Start(Value, Rc<RefCell<Environment>>),
+    /// Stopped at a `yield`, with the frames between it and the generator
+    Suspended(Vec<Frame>),
+    Running,
+    Done,
+}
+
+/// The procedure `(generator body ...)` evaluates to
+pub(super) fn make_generator(body: Value, env: Rc<RefCell<Environment>>) -> Value {
+    let state = Rc::new(RefCell::new(Generator::Start(body, env)));
+
+    Value::Procedure(Rc::new(move |args: Vec<Value>| {
+        if !args.is_empty() {
+            return Err("A generator takes no arguments".into());
+        }
+
+        let mut stack = vec![Frame::Generator(state.clone())];
+        let start = match state.replace(Generator::Running) {
+            Generator::Start(body, env) => machine::sequence(body, env, &mut stack),
+            Generator::Suspended(frames) => {
+                stack.extend(frames);
+                State::Return(Value::Nil)
+            }
+            Generator::Running => return Err("Generator resumed while it is running".into()),
+            Generator::Done => {
+                state.replace(Generator::Done);
+                return Ok(eof_object());
+            }
+        };
+
+        machine::run(stack, start).map_err(|e| {
+            state.replace(Generator::Done);
+            e.to_string()
+        })
+    }))
+}
+
+/// Register `generator->list` and `generator-for-each`
+pub fn register_generator_procedures(env: Rc<RefCell<Environment>>) {
+    // (generator->list gen [count])
+    env.borrow_mut().bindings.insert(
+        "generator->list".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            let limit = match args.as_slice() {
+                [_] => None,
+                [_, Value::Number(n)] if n.as_f64() >= 0.0 => Some(n.as_f64() as usize),
+                _ => {
+                    return Err("generator->list requires a generator and an optional count".into())
+                }
+            };
+
+            let mut items = Vec::new();
+            while limit != Some(items.len()) {
+                let item = apply(&args[0], Vec::new())?;
+                if is_eof_object(&item) {
+                    break;
+                }
+                items.push(item);
+            }
+            Ok(items
+                .into_iter()
+                .rev()
+                .fold(Value::Nil, |list, item| Value::cons(item, list)))
+        })),
+    );
+
+    // (generator-for-each proc gen ...): stops when any generator is exhausted
+    env.borrow_mut().bindings.insert(
+        "generator-for-each".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            if args.len() < 2 {
+                return Err("generator-for-each requires a procedure and a generator".into());
+            }
+
+            loop {
+                let mut items = Vec::new();
+                for generator in &args[1..] {
+                    let item = apply(generator, Vec::new())?;
+                    if is_eof_object(&item) {
+                        return Ok(Value::Nil);
+                    }
+                    items.push(item);
+                }
+                apply(&args[0], items)?;
+            }
+        })),
+    );
+}
@@ -1,10 +1,20 @@
 use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::rc::Rc;
 
 use crate::error::Error;
+use crate::evm::{
+    register_safemath_primitives, register_word_primitives, SAFEMATH_PRIMITIVES, WORD_PRIMITIVES,
+};
+use crate::number::arithmetic;
 use crate::value::{Environment, Library, NumberKind, Value};
+use crate::{lexer, parser};
 
-use super::environment::create_environment;
+use super::environment::{create_environment, lookup_variable};
+use super::functional::register_functional_library;
+use super::rules::register_rules_library;
+use super::stdlib;
 use crate::evaluator::library_manager;
 
 // Helper functions for EVM library
@@ -24,8 +34,8 @@ pub fn number_to_i64(val: &Value) -> Result<i64, String> {
     match val {
         Value::Number(n) => match n {
             NumberKind::Integer(i) => Ok(*i),
-            NumberKind::Real(r) => Ok(*r as i64),
-            NumberKind::Rational(num, den) => Ok(*num / *den),
+            NumberKind::BigInteger(_) => Err(format!("Integer out of range: {}", n)),
+            other => Ok(other.as_f64() as i64),
         },
         _ => Err(format!("Expected number, got {}", val)),
     }
@@ -51,6 +61,7 @@ pub fn register_base_library(env: Rc<RefCell<Environment>>) {
             name: vec!["scheme".to_string(), "base".to_string()],
             exports: vec!["append".to_string()],
             imports: vec![],
+            renames: HashMap::new(),
             environment: base_env,
         }))),
     );
@@ -76,6 +87,7 @@ pub fn register_file_library(env: Rc<RefCell<Environment>>) {
             name: vec!["scheme".to_string(), "file".to_string()],
             exports: vec!["file-exists?".to_string()],
             imports: vec![],
+            renames: HashMap::new(),
             environment: file_env,
         }))),
     );
@@ -91,12 +103,9 @@ pub fn register_math_library(env: Rc<RefCell<Environment>>) {
         Value::Procedure(Rc::new(|args| {
             check_args_count("abs", &args, 1)?;
             match &args[0] {
-                Value::Number(n) => match n {
-                    NumberKind::Integer(i) => Ok(Value::Number(NumberKind::Integer(i.abs()))),
-                    NumberKind::Real(r) => Ok(Value::Number(NumberKind::Real(r.abs()))),
-                    NumberKind::Rational(num, den) => {
-                        Ok(Value::Number(NumberKind::Rational(num.abs(), *den)))
-                    }
+                Value::Number(n) => match arithmetic::compare(n, &NumberKind::Integer(0)) {
+                    Some(Ordering::Less) => Ok(Value::Number(arithmetic::negate(n))),
+                    _ => Ok(Value::Number(n.clone())),
                 },
                 _ => Err("abs: expected number".to_string()),
             }
@@ -110,6 +119,7 @@ pub fn register_math_library(env: Rc<RefCell<Environment>>) {
             name: vec!["scheme".to_string(), "math".to_string()],
             exports: vec!["abs".to_string()],
             imports: vec![],
+            renames: HashMap::new(),
             environment: math_env,
         }))),
     );
@@ -141,6 +151,27 @@ pub fn register_evm_library(env: Rc<RefCell<Environment>>) {
         })),
     );
 
+    // Transient storage, which the simulated EVM clears after each transaction
+    evm_env.borrow_mut().bindings.insert(
+        "transient-load".to_string(),
+        Value::Procedure(Rc::new(|args| {
+            check_args_count("transient-load", &args, 1)?;
+            let _slot = number_to_i64(&args[0])?;
+            // This is a mock implementation since we're focusing on compilation
+            Ok(Value::Number(NumberKind::Integer(0)))
+        })),
+    );
+
+    evm_env.borrow_mut().bindings.insert(
+        "transient-store!".to_string(),
+        Value::Procedure(Rc::new(|args| {
+            check_args_count("transient-store!", &args, 2)?;
+            let _slot = number_to_i64(&args[0])?;
+            // This is a mock implementation since we're focusing on compilation
+            Ok(args[1].clone())
+        })),
+    );
+
     // Contract execution control
     evm_env.borrow_mut().bindings.insert(
         "revert".to_string(),
@@ -151,6 +182,9 @@ pub fn register_evm_library(env: Rc<RefCell<Environment>>) {
         })),
     );
 
+    // Signed arithmetic on 256-bit words
+    register_word_primitives(evm_env.clone());
+
     // Register the library in the parent environment
     env.borrow_mut().bindings.insert(
         "evm".to_string(),
@@ -159,23 +193,178 @@ pub fn register_evm_library(env: Rc<RefCell<Environment>>) {
             exports: vec![
                 "storage-load".to_string(),
                 "storage-store".to_string(),
+                "transient-load".to_string(),
+                "transient-store!".to_string(),
                 "revert".to_string(),
-            ],
+            ]
+            .into_iter()
+            .chain(WORD_PRIMITIVES.iter().map(|name| name.to_string()))
+            .collect(),
             imports: vec![],
+            renames: HashMap::new(),
             environment: evm_env,
         }))),
     );
 }
 
+// Checked arithmetic, imported with (import (lamina evm safemath))
+pub fn register_safemath_library(env: Rc<RefCell<Environment>>) {
+    let safemath_env = create_environment(Some(env.clone()));
+    register_safemath_primitives(safemath_env.clone());
+
+    let library = Library {
+        name: vec![
+            "lamina".to_string(),
+            "evm".to_string(),
+            "safemath".to_string(),
+        ],
+        exports: SAFEMATH_PRIMITIVES
+            .iter()
+            .map(|name| name.to_string())
+            .collect(),
+        imports: vec![],
+        renames: HashMap::new(),
+        environment: safemath_env,
+    };
+    bind_library(&env, Rc::new(RefCell::new(library)));
+}
+
 // Setup all libraries
 pub fn setup_libraries(env: Rc<RefCell<Environment>>) -> Result<(), Error> {
     register_base_library(env.clone());
     register_file_library(env.clone());
     register_math_library(env.clone());
     register_evm_library(env.clone());
+    register_safemath_library(env.clone());
+    register_rules_library(env.clone());
+    register_functional_library(env.clone());
     Ok(())
 }
 
+// Import special form: (import (library name) ...) binds the exports of each
+// library in `env`
+pub fn eval_import(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
+    for name in extract_imports(&args)? {
+        let library = find_library(&env, &name)?;
+        let library = library.borrow();
+        for export in &library.exports {
+            // A renamed export is bound under its internal name; an export
+            // the library imported itself is found in its environment too
+            let name = library.binding_name(export);
+            let found = library.environment.borrow().macro_named(name);
+            if let Some(found) = found {
+                let mut env = env.borrow_mut();
+                env.bindings.remove(export);
+                env.macros.insert(export.clone(), found);
+                continue;
+            }
+            let value = lookup_variable(name, library.environment.clone())
+                .map_err(|_| Error::Runtime(format!("{} is not defined in its library", name)))?;
+            env.borrow_mut().bindings.insert(export.clone(), value);
+        }
+    }
+    Ok(Value::Nil)
+}
+
+// The names of the libraries exporting `name`, sorted: those bound in `env`,
+// nested ones included, and the standard libraries not loaded yet
+pub fn libraries_exporting(env: &Rc<RefCell<Environment>>, name: &str) -> Vec<Vec<String>> {
+    let libraries_in = |env: &Rc<RefCell<Environment>>| -> Vec<Rc<RefCell<Library>>> {
+        env.borrow()
+            .bindings
+            .values()
+            .filter_map(|value| match value {
+                Value::Library(library) => Some(library.clone()),
+                _ => None,
+            })
+            .collect()
+    };
+
+    let mut pending = Vec::new();
+    let mut scope = Some(env.clone());
+    while let Some(current) = scope {
+        pending.extend(libraries_in(&current));
+        scope = current.borrow().parent.clone();
+    }
+
+    let mut seen = Vec::new();
+    let mut found = Vec::new();
+    while let Some(library) = pending.pop() {
+        let library = library.borrow();
+        if seen.contains(&library.name) {
+            continue;
+        }
+        seen.push(library.name.clone());
+        if library.exports.iter().any(|export| export == name) {
+            found.push(library.name.clone());
+        }
+        pending.extend(libraries_in(&library.environment));
+    }
+
+    for library in stdlib::LIBRARIES {
+        let library_name: Vec<String> = library.name.iter().map(|part| part.to_string()).collect();
+        if !seen.contains(&library_name) && stdlib_exports(library).iter().any(|e| e == name) {
+            found.push(library_name);
+        }
+    }
+    found.sort();
+    found
+}
+
+// The names a standard library exports, read from its source without
+// evaluating it
+fn stdlib_exports(library: &stdlib::StdLibrary) -> Vec<String> {
+    let Ok(form) = lexer::lex(library.source).and_then(|tokens| parser::parse(&tokens)) else {
+        return Vec::new();
+    };
+    form.iter()
+        .skip(2)
+        .filter_map(|decl| match decl {
+            Value::Pair(decl) if matches!(&decl.0, Value::Symbol(s) if s == "export") => {
+                extract_exports(&decl.1).ok()
+            }
+            _ => None,
+        })
+        .flatten()
+        .map(|(_, external)| external)
+        .collect()
+}
+
+// Find a library by name, loading it first if it is part of the standard
+// library and hasn't been imported yet
+pub(crate) fn find_library(
+    env: &Rc<RefCell<Environment>>,
+    name: &[String],
+) -> Result<Rc<RefCell<Library>>, Error> {
+    find_loaded_library(env, name).or_else(|unknown| match stdlib::find(name) {
+        Some(library) => {
+            stdlib::load(env, library)?;
+            find_loaded_library(env, name)
+        }
+        None => Err(unknown),
+    })
+}
+
+// Follow a library name through the libraries `bind_library` nests its parts in
+pub(crate) fn find_loaded_library(
+    env: &Rc<RefCell<Environment>>,
+    name: &[String],
+) -> Result<Rc<RefCell<Library>>, Error> {
+    let unknown = || Error::Runtime(format!("Unknown library: ({})", name.join(" ")));
+    let mut library = match lookup_variable(name.first().ok_or_else(unknown)?, env.clone()) {
+        Ok(Value::Library(library)) => library,
+        _ => return Err(unknown()),
+    };
+    for part in &name[1..] {
+        let next = match library.borrow().environment.borrow().bindings.get(part) {
+            Some(Value::Library(next)) => next.clone(),
+            _ => return Err(unknown()),
+        };
+        library = next;
+    }
+    Ok(library)
+}
+
 // Define-library special form implementation
 pub fn eval_define_library(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
     if let Value::Pair(name_pair) = args {
@@ -192,6 +381,7 @@ pub fn eval_define_library(args: Value, env: Rc<RefCell<Environment>>) -> Result
 
         // Process library declarations
         let mut exports = Vec::new();
+        let mut renames = HashMap::new();
         let mut imports = Vec::new();
 
         let mut remaining_decls = decls;
@@ -205,10 +395,22 @@ pub fn eval_define_library(args: Value, env: Rc<RefCell<Environment>>) -> Result
 
                     match decl_type.as_str() {
                         "export" => {
-                            let export_list = extract_exports(&decl_contents)?;
-                            exports.extend(export_list);
+                            for (internal, external) in extract_exports(&decl_contents)? {
+                                if exports.contains(&external) {
+                                    return Err(Error::Runtime(format!(
+                                        "{} is exported more than once",
+                                        external
+                                    )));
+                                }
+                                if internal != external {
+                                    renames.insert(external.clone(), internal);
+                                }
+                                exports.push(external);
+                            }
                         }
                         "import" => {
+                            // Bind the exports in the library's environment
+                            eval_import(decl_contents.clone(), lib_env.clone())?;
                             let import_list = extract_imports(&decl_contents)?;
                             imports.extend(import_list);
                         }
@@ -231,77 +433,84 @@ pub fn eval_define_library(args: Value, env: Rc<RefCell<Environment>>) -> Result
         let library = Library {
             name: lib_name.clone(),
             exports,
+            renames,
             imports,
             environment: lib_env.clone(),
         };
 
-        // Create the library value
-        let lib_rc = Rc::new(RefCell::new(library));
-        let lib_value = Value::Library(lib_rc.clone());
+        bind_library(&env, Rc::new(RefCell::new(library)));
 
-        // Register the library with the library manager
-        library_manager::register_library(lib_rc);
+        // Return information about the library
+        let library_info = format!("#<library:{}>", lib_name.join(" "));
+        Ok(Value::String(library_info))
+    } else {
+        Err(Error::Runtime("Malformed define-library form".into()))
+    }
+}
 
-        // Register the library in the global environment
-        let mut path = String::new();
-        for (i, part) in lib_name.iter().enumerate() {
-            if i > 0 {
-                path.push(' ');
-            }
-            path.push_str(part);
+/// Register a library and bind it under its name in `env`, nesting the parts of
+/// a multi-part name as libraries of their own
+pub fn bind_library(env: &Rc<RefCell<Environment>>, lib_rc: Rc<RefCell<Library>>) {
+    let lib_value = Value::Library(lib_rc.clone());
+    let lib_name = lib_rc.borrow().name.clone();
+
+    // Register the library with the library manager
+    library_manager::register_library_in(env, lib_rc);
+
+    // Register the library in the global environment
+    let mut path = String::new();
+    for (i, part) in lib_name.iter().enumerate() {
+        if i > 0 {
+            path.push(' ');
         }
+        path.push_str(part);
+    }
 
-        // Register the library in the hierarchical structure
-        if lib_name.len() == 1 {
-            env.borrow_mut()
-                .bindings
-                .insert(lib_name[0].clone(), lib_value.clone());
-        } else {
-            // For nested libraries, we need to find or create the parent libraries
-            let mut current_env = env.clone();
-            for (i, part) in lib_name.iter().enumerate() {
-                if i == lib_name.len() - 1 {
-                    // Last part, insert library
-                    current_env
-                        .borrow_mut()
-                        .bindings
-                        .insert(part.clone(), lib_value.clone());
-                } else {
-                    // Get or create parent library
-                    let parent_lib = {
-                        let current_env_ref = current_env.borrow();
-                        match current_env_ref.bindings.get(part) {
-                            Some(Value::Library(lib)) => lib.clone(),
-                            _ => {
-                                // We need to drop the current borrow before creating a new one
-                                drop(current_env_ref);
-
-                                // Create a new parent library
-                                let parent_lib = Library {
-                                    name: lib_name[0..=i].to_vec(),
-                                    exports: Vec::new(),
-                                    imports: Vec::new(),
-                                    environment: create_environment(Some(current_env.clone())),
-                                };
-                                let parent_lib_value = Rc::new(RefCell::new(parent_lib));
-                                current_env
-                                    .borrow_mut()
-                                    .bindings
-                                    .insert(part.clone(), Value::Library(parent_lib_value.clone()));
-                                parent_lib_value
-                            }
+    // Register the library in the hierarchical structure
+    if lib_name.len() == 1 {
+        env.borrow_mut()
+            .bindings
+            .insert(lib_name[0].clone(), lib_value.clone());
+    } else {
+        // For nested libraries, we need to find or create the parent libraries
+        let mut current_env = env.clone();
+        for (i, part) in lib_name.iter().enumerate() {
+            if i == lib_name.len() - 1 {
+                // Last part, insert library
+                current_env
+                    .borrow_mut()
+                    .bindings
+                    .insert(part.clone(), lib_value.clone());
+            } else {
+                // Get or create parent library
+                let parent_lib = {
+                    let current_env_ref = current_env.borrow();
+                    match current_env_ref.bindings.get(part) {
+                        Some(Value::Library(lib)) => lib.clone(),
+                        _ => {
+                            // We need to drop the current borrow before creating a new one
+                            drop(current_env_ref);
+
+                            // Create a new parent library
+                            let parent_lib = Library {
+                                name: lib_name[0..=i].to_vec(),
+                                exports: Vec::new(),
+                                imports: Vec::new(),
+                                renames: HashMap::new(),
+                                environment: create_environment(Some(current_env.clone())),
+                            };
+                            let parent_lib_value = Rc::new(RefCell::new(parent_lib));
+                            current_env
+                                .borrow_mut()
+                                .bindings
+                                .insert(part.clone(), Value::Library(parent_lib_value.clone()));
+                            parent_lib_value
                         }
-                    };
-                    current_env = parent_lib.borrow().environment.clone();
-                }
+                    }
+                };
+                current_env = parent_lib.borrow().environment.clone();
             }
         }
-
-        // Return information about the library
-        let library_info = format!("#<library:{}>", lib_name.join(" "));
-        Ok(Value::String(library_info))
-    } else {
-        Err(Error::Runtime("Malformed define-library form".into()))
     }
 }
 
@@ -337,16 +546,17 @@ fn extract_library_name(name_expr: &Value) -> Result<Vec<String>, Error> {
     Ok(result)
 }
 
-// Helper function to extract exports from export form
-fn extract_exports(export_expr: &Value) -> Result<Vec<String>, Error> {
+// Helper function to extract exports from export form: each export spec is a
+// name, or (rename internal external) to export `internal` as `external`.
+// Returns the internal and external name of each.
+fn extract_exports(export_expr: &Value) -> Result<Vec<(String, String)>, Error> {
     let mut result = Vec::new();
     let mut exports = export_expr.clone();
 
     while let Value::Pair(export_pair) = exports {
-        if let Value::Symbol(s) = &export_pair.0 {
-            result.push(s.clone());
-        } else {
-            return Err(Error::Runtime("Exports must be symbols".into()));
+        match &export_pair.0 {
+            Value::Symbol(s) => result.push((s.clone(), s.clone())),
+            spec => result.push(extract_rename(spec)?),
         }
         exports = export_pair.1.clone();
     }
@@ -354,6 +564,26 @@ fn extract_exports(export_expr: &Value) -> Result<Vec<String>, Error> {
     Ok(result)
 }
 
+fn extract_rename(spec: &Value) -> Result<(String, String), Error> {
+    let mut parts = Vec::new();
+    let mut rest = spec;
+    while let Value::Pair(pair) = rest {
+        parts.push(&pair.0);
+        rest = &pair.1;
+    }
+    match parts.as_slice() {
+        [Value::Symbol(keyword), Value::Symbol(internal), Value::Symbol(external)]
+            if keyword == "rename" =>
+        {
+            Ok((internal.clone(), external.clone()))
+        }
+        _ => Err(Error::Runtime(format!(
+            "Exports must be symbols or (rename internal external), got {}",
+            spec
+        ))),
+    }
+}
+
 // Helper function to extract imports from import form
 fn extract_imports(import_expr: &Value) -> Result<Vec<Vec<String>>, Error> {
     let mut result = Vec::new();
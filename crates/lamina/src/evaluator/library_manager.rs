@@ -3,24 +3,65 @@ use std::collections::HashMap;
 use std::rc::Rc;
 use std::thread_local;
 
-use crate::value::Library;
+use crate::value::{Environment, Library};
 
-// A global registry to track all defined libraries
+/// Libraries defined with `define-library`, by name
+#[derive(Default)]
+pub struct LibraryRegistry {
+    libraries: HashMap<Vec<String>, Rc<RefCell<Library>>>,
+}
+
+impl LibraryRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &[String]) -> Option<Rc<RefCell<Library>>> {
+        self.libraries.get(name).cloned()
+    }
+
+    pub fn register(&mut self, library: Rc<RefCell<Library>>) {
+        let name = library.borrow().name.clone();
+        self.libraries.insert(name, library);
+    }
+}
+
+// Registry for environments that don't belong to an Interpreter
 thread_local! {
-    pub static LIBRARIES: RefCell<HashMap<Vec<String>, Rc<RefCell<Library>>>> = RefCell::new(HashMap::new());
+    static LIBRARIES: RefCell<LibraryRegistry> = RefCell::new(LibraryRegistry::new());
+}
+
+/// Register a library with the registry of the interpreter owning `env`, or
+/// with the thread's shared registry for a standalone environment
+pub fn register_library_in(env: &Rc<RefCell<Environment>>, library: Rc<RefCell<Library>>) {
+    match registry_of(env) {
+        Some(registry) => registry.borrow_mut().register(library),
+        None => LIBRARIES.with(|libraries| libraries.borrow_mut().register(library)),
+    }
+}
+
+// The registry attached to the root of `env`, if any
+fn registry_of(env: &Rc<RefCell<Environment>>) -> Option<Rc<RefCell<LibraryRegistry>>> {
+    let mut current = env.clone();
+    loop {
+        let parent = current.borrow().parent.clone();
+        match parent {
+            Some(parent) => current = parent,
+            None => return current.borrow().libraries.clone(),
+        }
+    }
 }
 
 // Function to get a library by name
-#[allow(dead_code)]
+#[deprecated(note = "libraries are registered per interpreter, use `Interpreter::library`")]
 pub fn get_library(name: &[String]) -> Option<Rc<RefCell<Library>>> {
-    LIBRARIES.with(|libraries| libraries.borrow().get(&name.to_vec()).cloned())
+    LIBRARIES.with(|libraries| libraries.borrow().get(name))
 }
 
 // Function to register a library
-#[allow(dead_code)]
+#[deprecated(
+    note = "libraries are registered per interpreter, use `InterpreterBuilder::with_library`"
+)]
 pub fn register_library(library: Rc<RefCell<Library>>) {
-    let name = library.borrow().name.clone();
-    LIBRARIES.with(|libraries| {
-        libraries.borrow_mut().insert(name, library);
-    });
+    LIBRARIES.with(|libraries| libraries.borrow_mut().register(library));
 }
@@ -0,0 +1,1513 @@
+// Explicit-stack evaluator
+//
+// Evaluation runs on a stack of frames, each waiting for the value of a
+// subexpression, rather than on the Rust call stack. That makes the rest of a
+// computation something the evaluator can hold: `shift` moves the frames above
+// the nearest `reset` into a continuation, and calling the continuation pushes
+// copies of them back, so a continuation can be resumed any number of times.
+// Exception handlers and generators are frames on the same stack: raising
+// unwinds to the nearest `guard` or `with-exception-handler` frame, and `yield`
+// suspends the frames above the nearest generator frame.
+//
+// `call/cc` copies the whole stack into a continuation, with the run it was
+// captured in. Calling it replaces the stack with a copy of the one captured,
+// calling the after thunks of the `dynamic-wind` frames left and the before
+// thunks of those entered on the way.
+//
+// Procedures made by `lambda` are applied on the stack. Native procedures run
+// to completion, and a Lamina procedure they call back into runs on a stack of
+// its own, so `shift` and `yield` can't reach past a native call such as
+// `vector-map` to a `reset` or generator outside it. A `call/cc` continuation
+// can: calling it from a nested run unwinds that run as an error no handler
+// sees, until the run that captured it takes the stack over again.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use crate::contracts::Contract;
+use crate::error::{arity_error, Arity, Error};
+use crate::trace;
+use crate::value::{Cons, Environment, Lambda, Value};
+
+use super::environment::{check_core_rebinding, create_environment, lookup, undefined_variable};
+use super::equality::is_eqv;
+use super::generators::{self, Generator};
+use super::parameters::Parameter;
+use super::ports::eof_object;
+use super::{conditions, libraries, macros, rules, special_forms, values};
+
+/// The message of the error a run unwinds with when a continuation captured in
+/// a run outside it is called
+const ESCAPING: &str = "Escaping to a continuation";
+
+thread_local! {
+    /// The runs in progress, innermost last
+    static RUNS: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+    static NEXT_RUN: Cell<usize> = const { Cell::new(0) };
+    /// A continuation called from inside a run nested in the one that captured
+    /// it, with the value to return to it
+    static ESCAPE: RefCell<Option<(Rc<Continuation>, Value)>> = const { RefCell::new(None) };
+}
+
+/// The rest of a computation, applied as a procedure: the frames between a
+/// `shift` and its `reset`, or the whole stack `call/cc` captured
+#[derive(Clone)]
+pub struct Continuation {
+    frames: Vec<Frame>,
+    /// The run `call/cc` captured the stack of; `None` for `shift`
+    run: Option<usize>,
+}
+
+/// The before and after thunks of a `dynamic-wind`
+pub(super) struct Wind {
+    before: Value,
+    after: Value,
+}
+
+/// Evaluation waiting for a value
+#[derive(Clone)]
+pub(super) enum Frame {
+    /// Operator and arguments evaluated so far, and the argument expressions
+    /// left, with the whole call for error messages
+    Call {
+        env: Rc<RefCell<Environment>>,
+        values: Vec<Value>,
+        rest: Value,
+        call: Value,
+    },
+    /// `(if test ...)`, holding the consequent and optional alternative
+    If {
+        env: Rc<RefCell<Environment>>,
+        branches: Value,
+    },
+    /// `(when test body ...)`, or `unless` when `holds` is false
+    When {
+        env: Rc<RefCell<Environment>>,
+        holds: bool,
+        body: Value,
+    },
+    /// `(and expr ...)`, or `or` when `and` is false, with the expressions
+    /// after the one being evaluated
+    Logic {
+        env: Rc<RefCell<Environment>>,
+        and: bool,
+        rest: Value,
+    },
+    /// `assert` waiting for its test
+    Assert {
+        test: Value,
+        message: Option<String>,
+    },
+    /// The body expressions after the one being evaluated
+    Begin {
+        env: Rc<RefCell<Environment>>,
+        rest: Value,
+    },
+    Define {
+        env: Rc<RefCell<Environment>>,
+        name: String,
+    },
+    Set {
+        env: Rc<RefCell<Environment>>,
+        name: String,
+    },
+    /// A `cond` clause's test, with the clause body and the clauses after it
+    Cond {
+        env: Rc<RefCell<Environment>>,
+        clause: Rc<Cons>,
+        rest: Value,
+    },
+    /// A `case` key, with the clauses to match it against
+    Case {
+        env: Rc<RefCell<Environment>>,
+        clauses: Value,
+    },
+    /// The initializer of `name`; `env` evaluates the next initializer and
+    /// `body_env` the body
+    Let {
+        kind: LetKind,
+        env: Rc<RefCell<Environment>>,
+        body_env: Rc<RefCell<Environment>>,
+        name: String,
+        rest: Value,
+        body: Value,
+    },
+    /// `with-exception-handler` waiting for its handler
+    HandlerArg {
+        env: Rc<RefCell<Environment>>,
+        thunk: Value,
+    },
+    /// `with-exception-handler` waiting for its thunk
+    HandlerThunk {
+        handler: Value,
+    },
+    /// A handler installed for the frames above it
+    Handler {
+        handler: Value,
+    },
+    /// A handler is running for a `raise-continuable`; raising inside it skips
+    /// the frames down to and including the handler this many frames below
+    Mask(usize),
+    /// A `guard` body, with the handler variable and clauses
+    Guard {
+        env: Rc<RefCell<Environment>>,
+        var: String,
+        clauses: Value,
+    },
+    /// A `guard` clause test, with the condition to raise again if no clause
+    /// matches
+    GuardClause {
+        env: Rc<RefCell<Environment>>,
+        body: Value,
+        rest: Value,
+        raised: Raised,
+    },
+    /// `parameterize` waiting for the parameters and values in `rest`
+    ParameterizeArgs {
+        env: Rc<RefCell<Environment>>,
+        values: Vec<Value>,
+        rest: Value,
+        body: Value,
+    },
+    /// Old values of the parameters a `parameterize` body runs with
+    Parameterized(Vec<(Rc<Parameter>, Value)>),
+    /// `call/cc`, `dynamic-wind` or `call-with-values` waiting for the
+    /// procedure arguments in `rest`
+    ControlArgs {
+        control: Control,
+        env: Rc<RefCell<Environment>>,
+        values: Vec<Value>,
+        rest: Value,
+    },
+    /// `dynamic-wind` waiting for its before thunk, to call `thunk`
+    WindBefore {
+        wind: Rc<Wind>,
+        thunk: Value,
+    },
+    /// The thunk of a `dynamic-wind`, whose after thunk runs once it returns
+    Wind(Rc<Wind>),
+    /// Returns its value whatever it resumes with: the value of a
+    /// `dynamic-wind` thunk waiting for the after thunk, or the argument of a
+    /// continuation waiting for the thunks called on the way to it
+    Deliver(Value),
+    /// A before or after thunk to call on the way to a continuation
+    Thunk(Value),
+    /// `call-with-values` waiting for its producer, to call the consumer
+    Consume(Value),
+    Raise {
+        continuable: bool,
+    },
+    Error,
+    /// Delimits the frames `shift` captures
+    Reset,
+    Yield,
+    /// A generator's body, suspended to `state` by `yield`
+    Generator(Rc<RefCell<Generator>>),
+    /// A step of the evaluation log waiting for its value
+    Traced(usize),
+}
+
+/// A special form taking procedures, evaluated like a call and applied on the
+/// stack
+#[derive(Clone, Copy)]
+pub(super) enum Control {
+    CallCc,
+    DynamicWind,
+    CallWithValues,
+}
+
+impl Control {
+    fn name(self) -> &'static str {
+        match self {
+            Control::CallCc => "call/cc",
+            Control::DynamicWind => "dynamic-wind",
+            Control::CallWithValues => "call-with-values",
+        }
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Control::CallCc => 1,
+            Control::DynamicWind => 3,
+            Control::CallWithValues => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(super) enum LetKind {
+    Let,
+    LetStar,
+    Letrec,
+}
+
+impl LetKind {
+    fn name(self) -> &'static str {
+        match self {
+            LetKind::Let => "let",
+            LetKind::LetStar => "let*",
+            LetKind::Letrec => "letrec",
+        }
+    }
+}
+
+/// What was raised: a value from `raise`, or an error from the evaluator or a
+/// native procedure
+#[derive(Clone)]
+pub(super) enum Raised {
+    Value(Value),
+    Error(Error),
+}
+
+impl Raised {
+    /// The condition as handlers see it. File and read errors become
+    /// conditions, other errors symbols of their message, as do values raised
+    /// inside a native call, which reach here as errors.
+    fn into_value(self) -> Value {
+        let error = match self {
+            Raised::Value(value) => return value,
+            Raised::Error(error) => error,
+        };
+        if let Some(condition) = conditions::from_error(&error) {
+            return condition;
+        }
+        match error {
+            Error::Runtime(e) => match e.strip_prefix("Exception: ") {
+                Some(raised) => Value::Symbol(raised.to_string()),
+                None => Value::Symbol(e),
+            },
+            Error::Undefined { message, .. } => Value::Symbol(message),
+            error => Value::Symbol(format!("{:?}", error)),
+        }
+    }
+
+    fn into_error(self) -> Error {
+        match self {
+            Raised::Value(value) => conditions::to_error(&value)
+                .unwrap_or_else(|| Error::Runtime(format!("Exception: {:?}", value))),
+            Raised::Error(error) => error,
+        }
+    }
+}
+
+pub(super) enum State {
+    Eval(Value, Rc<RefCell<Environment>>),
+    Return(Value),
+    Raise(Raised, bool),
+}
+
+impl State {
+    fn error(message: impl Into<String>) -> State {
+        State::Raise(Raised::Error(Error::Runtime(message.into())), false)
+    }
+
+    fn from_result(result: Result<Value, Error>) -> State {
+        match result {
+            Ok(value) => State::Return(value),
+            Err(e) => State::Raise(Raised::Error(e), false),
+        }
+    }
+}
+
+/// Run the machine from `state` until `stack` is empty
+pub(super) fn run(stack: Vec<Frame>, state: State) -> Result<Value, Error> {
+    let id = NEXT_RUN.with(|next| next.replace(next.get() + 1));
+    RUNS.with(|runs| runs.borrow_mut().push(id));
+    let result = run_steps(stack, state);
+    RUNS.with(|runs| runs.borrow_mut().pop());
+    result
+}
+
+fn run_steps(mut stack: Vec<Frame>, mut state: State) -> Result<Value, Error> {
+    loop {
+        state = match state {
+            State::Eval(expr, env) => eval(expr, env, &mut stack),
+            State::Return(value) => match stack.pop() {
+                Some(frame) => resume(frame, value, &mut stack),
+                None => return Ok(value),
+            },
+            State::Raise(raised, continuable) => raise(raised, continuable, &mut stack)?,
+        };
+    }
+}
+
+/// Call a procedure from native code
+pub(super) fn call(func: &Value, args: Vec<Value>) -> Result<Value, Error> {
+    match func {
+        Value::Procedure(p) | Value::RustFn(p, _) => p(args).map_err(Error::Runtime),
+        Value::Lambda(_) | Value::Continuation(_) | Value::Parameter(_) => {
+            let mut stack = Vec::new();
+            let state = apply(func.clone(), args, &mut stack);
+            run(stack, state)
+        }
+        _ => Err(Error::Runtime(format!("Not a function: {}", func))),
+    }
+}
+
+fn eval(expr: Value, env: Rc<RefCell<Environment>>, stack: &mut Vec<Frame>) -> State {
+    crate::coverage::record(&expr);
+    if let Some(step) = trace::enter(&expr) {
+        stack.push(Frame::Traced(step));
+    }
+
+    let pair = match expr {
+        Value::Symbol(s) => {
+            return match lookup(&s, &env) {
+                Some(value) => State::Return(value),
+                None => State::Raise(Raised::Error(undefined_variable(&s, &env)), false),
+            }
+        }
+        Value::Pair(pair) => pair,
+        // Everything else evaluates to itself
+        _ => return State::Return(expr),
+    };
+
+    let args = pair.1.clone();
+    if let Value::Symbol(s) = &pair.0 {
+        // Macros are expanded before anything else sees the form
+        let found = env.borrow().macro_named(s);
+        if let Some(found) = found {
+            return match found.expand(&Value::Pair(pair)) {
+                Ok(expansion) => State::Eval(expansion, env),
+                Err(e) => State::Raise(Raised::Error(e), false),
+            };
+        }
+        match s.as_str() {
+            "lambda" => return lambda(args, env),
+            "if" => {
+                return match &args {
+                    Value::Pair(test) if matches!(test.1, Value::Pair(_)) => {
+                        stack.push(Frame::If {
+                            env: env.clone(),
+                            branches: test.1.clone(),
+                        });
+                        State::Eval(test.0.clone(), env)
+                    }
+                    _ => State::error("Malformed if expression"),
+                }
+            }
+            "when" | "unless" => {
+                return match &args {
+                    Value::Pair(test) => {
+                        stack.push(Frame::When {
+                            env: env.clone(),
+                            holds: s == "when",
+                            body: test.1.clone(),
+                        });
+                        State::Eval(test.0.clone(), env)
+                    }
+                    _ => State::error(format!("Malformed {}", s)),
+                }
+            }
+            "and" | "or" => return logic(s == "and", args, env, stack),
+            // Visibility only matters to the compilers
+            "define" | "define-internal" | "define-for-syntax" => {
+                return match &args {
+                    Value::Pair(define) if matches!(define.0, Value::Symbol(_)) => {
+                        let name = match &define.0 {
+                            Value::Symbol(name) => name.clone(),
+                            _ => unreachable!(),
+                        };
+                        match &define.1 {
+                            Value::Pair(value) => {
+                                stack.push(Frame::Define {
+                                    env: env.clone(),
+                                    name,
+                                });
+                                State::Eval(value.0.clone(), env)
+                            }
+                            _ => State::error("Malformed define"),
+                        }
+                    }
+                    _ => State::from_result(special_forms::eval_define(args, env)),
+                }
+            }
+            "set!" => {
+                return match &args {
+                    Value::Pair(set) => match (&set.0, &set.1) {
+                        (Value::Symbol(name), Value::Pair(value)) => {
+                            stack.push(Frame::Set {
+                                env: env.clone(),
+                                name: name.clone(),
+                            });
+                            State::Eval(value.0.clone(), env)
+                        }
+                        (Value::Symbol(_), _) => State::error("Malformed set!"),
+                        _ => State::error("First argument to set! must be a symbol"),
+                    },
+                    _ => State::error("Malformed set!"),
+                }
+            }
+            "cond" => return cond(args, env, stack),
+            "case" => {
+                return match &args {
+                    Value::Pair(case) => {
+                        let exhaustive = special_forms::check_case_exhaustive(&case.1, |datum| {
+                            env.borrow().enum_of(datum)
+                        });
+                        if let Err(e) = exhaustive {
+                            return State::error(e);
+                        }
+                        stack.push(Frame::Case {
+                            env: env.clone(),
+                            clauses: case.1.clone(),
+                        });
+                        State::Eval(case.0.clone(), env)
+                    }
+                    _ => State::error("Malformed case"),
+                }
+            }
+            "let" => {
+                if matches!(&args, Value::Pair(named) if matches!(named.0, Value::Symbol(_))) {
+                    return match special_forms::named_let(&args) {
+                        Ok(call) => State::Eval(call, env),
+                        Err(e) => State::Raise(Raised::Error(e), false),
+                    };
+                }
+                return let_form(LetKind::Let, args, env, stack);
+            }
+            "do" => {
+                return match special_forms::eval_do(&args) {
+                    Ok(named_let) => State::Eval(named_let, env),
+                    Err(e) => State::Raise(Raised::Error(e), false),
+                }
+            }
+            "let*" => return let_form(LetKind::LetStar, args, env, stack),
+            "letrec" => return let_form(LetKind::Letrec, args, env, stack),
+            "begin" | "begin-for-syntax" => return sequence(args, env, stack),
+            "with-exception-handler" => {
+                return match &args {
+                    Value::Pair(handler) => match &handler.1 {
+                        Value::Pair(thunk) => {
+                            stack.push(Frame::HandlerArg {
+                                env: env.clone(),
+                                thunk: thunk.0.clone(),
+                            });
+                            State::Eval(handler.0.clone(), env)
+                        }
+                        _ => State::error("with-exception-handler requires a handler and a thunk"),
+                    },
+                    _ => State::error("with-exception-handler requires a handler and a thunk"),
+                }
+            }
+            "raise" | "raise-continuable" => {
+                return match &args {
+                    Value::Pair(raised) => {
+                        stack.push(Frame::Raise {
+                            continuable: s == "raise-continuable",
+                        });
+                        State::Eval(raised.0.clone(), env)
+                    }
+                    _ => State::error(format!("{} requires an argument", s)),
+                }
+            }
+            "error" => {
+                return match &args {
+                    Value::Pair(message) => {
+                        stack.push(Frame::Error);
+                        State::Eval(message.0.clone(), env)
+                    }
+                    _ => State::error("error requires an argument"),
+                }
+            }
+            "guard" => return guard(args, env, stack),
+            "parameterize" => return parameterize(args, env, stack),
+            "reset" => {
+                stack.push(Frame::Reset);
+                return sequence(args, env, stack);
+            }
+            "shift" => return shift(args, env, stack),
+            "call-with-current-continuation" | "call/cc" => {
+                return control(Control::CallCc, args, env, stack)
+            }
+            "dynamic-wind" => return control(Control::DynamicWind, args, env, stack),
+            "call-with-values" => return control(Control::CallWithValues, args, env, stack),
+            "generator" => return State::Return(generators::make_generator(args, env)),
+            "yield" => {
+                stack.push(Frame::Yield);
+                return match &args {
+                    Value::Pair(value) => State::Eval(value.0.clone(), env),
+                    _ => State::Return(Value::Nil),
+                };
+            }
+            "define-enum" => return State::from_result(special_forms::eval_define_enum(args, env)),
+            "define-syntax" => return State::from_result(macros::eval_define_syntax(&args, &env)),
+            "let-syntax" | "letrec-syntax" => {
+                return match macros::syntax_scope(&args, &env) {
+                    Ok((scope, body)) => sequence(body, scope, stack),
+                    Err(e) => State::Raise(Raised::Error(e), false),
+                }
+            }
+            "define-with-contract" => {
+                return match Contract::parse(&args) {
+                    Ok(contract) => State::Eval(contract.to_define(), env),
+                    Err(e) => State::Raise(Raised::Error(e), false),
+                }
+            }
+            "define-rule" => {
+                return match rules::eval_define_rule(&args, &env) {
+                    Ok(call) => State::Eval(call, env),
+                    Err(e) => State::Raise(Raised::Error(e), false),
+                }
+            }
+            "assert" => return assert(args, env, stack),
+            "lamina-edition" => {
+                return State::from_result(special_forms::eval_lamina_edition(args, env))
+            }
+            "define-record-type" => {
+                return State::from_result(special_forms::eval_define_record_type(args, env))
+            }
+            "compile-time" => {
+                return State::from_result(crate::expand::eval_compile_time(args, env))
+            }
+            "include" | "include-ci" => {
+                let fold_case = s == "include-ci" || env.borrow().fold_case();
+                return match special_forms::read_included(&args, fold_case) {
+                    Ok(forms) => sequence(forms, env, stack),
+                    Err(e) => State::Raise(Raised::Error(e), false),
+                };
+            }
+            "cond-expand" => {
+                return match crate::targets::INTERPRETER.select(&args) {
+                    Ok(body) => sequence(body, env, stack),
+                    Err(e) => State::Raise(Raised::Error(e), false),
+                }
+            }
+            "quote" => return State::from_result(special_forms::eval_quote(args, env)),
+            "quasiquote" => {
+                return match special_forms::quasiquote(&args) {
+                    Ok(built) => State::Eval(built, env),
+                    Err(e) => State::Raise(Raised::Error(e), false),
+                }
+            }
+            "unquote" | "unquote-splicing" => {
+                return State::error(format!("{} outside quasiquote", s))
+            }
+            "define-library" => {
+                return State::from_result(libraries::eval_define_library(args, env))
+            }
+            "import" => return State::from_result(libraries::eval_import(args, env)),
+            "define-interface" => {
+                return State::from_result(crate::evm::abi::eval_define_interface(args, env))
+            }
+            "define-test" => {
+                return State::from_result(crate::testing::eval_define_test(args, env))
+            }
+            "define-property" => {
+                return State::from_result(crate::testing::eval_define_property(args, env))
+            }
+            "define-bench" => {
+                return State::from_result(crate::testing::bench::eval_define_bench(args, env))
+            }
+            _ => {}
+        }
+    }
+
+    // A call: evaluate the operator, then the arguments
+    let operator = pair.0.clone();
+    stack.push(Frame::Call {
+        env: env.clone(),
+        values: Vec::new(),
+        rest: args,
+        call: Value::Pair(pair),
+    });
+    State::Eval(operator, env)
+}
+
+/// Continue `frame` with the value of the expression it was waiting for
+fn resume(frame: Frame, value: Value, stack: &mut Vec<Frame>) -> State {
+    match frame {
+        Frame::Call {
+            env,
+            mut values,
+            rest,
+            call,
+        } => {
+            values.push(value);
+            match rest {
+                Value::Pair(arg) => {
+                    stack.push(Frame::Call {
+                        env: env.clone(),
+                        values,
+                        rest: arg.1.clone(),
+                        call,
+                    });
+                    State::Eval(arg.0.clone(), env)
+                }
+                _ => {
+                    let func = values.remove(0);
+                    if let Value::Lambda(lambda) = &func {
+                        let arity = lambda.arity();
+                        if !arity.accepts(values.len()) {
+                            return State::error(format!(
+                                "{}, in {}",
+                                arity_error(&lambda.signature(), arity, values.len()),
+                                call
+                            ));
+                        }
+                    }
+                    apply(func, values, stack)
+                }
+            }
+        }
+        Frame::Assert { test, message } if !value.is_truthy() => State::error(format!(
+            "Assertion failed: {}",
+            message.unwrap_or_else(|| test.to_string())
+        )),
+        Frame::Assert { .. } => State::Return(Value::Nil),
+        Frame::If { env, branches } => match &branches {
+            Value::Pair(conseq) if value.is_truthy() => {
+                crate::coverage::record_arm(conseq);
+                State::Eval(conseq.0.clone(), env)
+            }
+            Value::Pair(conseq) => match &conseq.1 {
+                Value::Pair(alt) => {
+                    crate::coverage::record_arm(alt);
+                    State::Eval(alt.0.clone(), env)
+                }
+                _ => State::Return(Value::Nil),
+            },
+            _ => State::error("Malformed if expression"),
+        },
+        Frame::When { env, holds, body } if value.is_truthy() == holds => {
+            sequence(body, env, stack)
+        }
+        Frame::When { .. } => State::Return(Value::Nil),
+        // A false value decides `and` and a true one `or`
+        Frame::Logic { env, and, rest } if value.is_truthy() == and => logic(and, rest, env, stack),
+        Frame::Logic { .. } => State::Return(value),
+        Frame::Begin { env, rest } => sequence(rest, env, stack),
+        Frame::Define { env, name } => {
+            if let Err(e) = check_core_rebinding(&name, &env.borrow()) {
+                return State::Raise(Raised::Error(e), false);
+            }
+            // A procedure is named after the first variable it is defined as
+            if let Value::Lambda(lambda) = &value {
+                let _ = lambda.name.set(name.clone());
+            }
+            trace::change(&name, &value);
+            env.borrow_mut().bindings.insert(name, value);
+            State::Return(Value::Nil)
+        }
+        Frame::Set { env, name } => {
+            let traced = trace::is_recording().then(|| value.clone());
+            let result = assign(&name, value, &env);
+            if let (Ok(_), Some(value)) = (&result, traced) {
+                trace::change(&name, &value);
+            }
+            State::from_result(result)
+        }
+        Frame::Cond { env, rest, .. } if !value.is_truthy() => cond(rest, env, stack),
+        Frame::Cond { env, clause, .. } => {
+            crate::coverage::record_arm(&clause);
+            match &clause.1 {
+                Value::Pair(body) => State::Eval(body.0.clone(), env),
+                _ => State::Return(value),
+            }
+        }
+        Frame::Case { env, clauses } => case(&value, clauses, env),
+        Frame::Let {
+            kind,
+            env,
+            body_env,
+            name,
+            rest,
+            body,
+        } => match kind {
+            LetKind::Let | LetKind::Letrec => {
+                body_env.borrow_mut().bindings.insert(name, value);
+                bindings(kind, env, body_env, rest, body, stack)
+            }
+            LetKind::LetStar => {
+                let env = create_environment(Some(env));
+                env.borrow_mut().bindings.insert(name, value);
+                bindings(kind, env.clone(), env, rest, body, stack)
+            }
+        },
+        Frame::HandlerArg { env, thunk } => {
+            stack.push(Frame::HandlerThunk { handler: value });
+            State::Eval(thunk, env)
+        }
+        Frame::HandlerThunk { handler } => {
+            if !value.is_procedure() {
+                return State::error("Thunk must be a procedure");
+            }
+            stack.push(Frame::Handler { handler });
+            apply(value, Vec::new(), stack)
+        }
+        Frame::GuardClause {
+            env,
+            body,
+            rest,
+            raised,
+        } => match &body {
+            Value::Pair(_) if value.is_truthy() => sequence(body, env, stack),
+            // A clause of only a test evaluates to the test's value
+            _ if value.is_truthy() => State::Return(value),
+            _ => guard_clauses(rest, env, raised, stack),
+        },
+        Frame::ParameterizeArgs {
+            env,
+            mut values,
+            rest,
+            body,
+        } => {
+            values.push(value);
+            parameterize_args(env, values, rest, body, stack)
+        }
+        Frame::Parameterized(saved) => {
+            restore(saved);
+            State::Return(value)
+        }
+        Frame::ControlArgs {
+            control,
+            env,
+            mut values,
+            rest,
+        } => {
+            values.push(value);
+            control_args(control, env, values, rest, stack)
+        }
+        Frame::WindBefore { wind, thunk } => {
+            stack.push(Frame::Wind(wind));
+            apply(thunk, Vec::new(), stack)
+        }
+        Frame::Wind(wind) => {
+            stack.push(Frame::Deliver(value));
+            apply(wind.after.clone(), Vec::new(), stack)
+        }
+        Frame::Deliver(value) => State::Return(value),
+        Frame::Thunk(thunk) => apply(thunk, Vec::new(), stack),
+        Frame::Consume(consumer) => apply(consumer, values::spread(value), stack),
+        Frame::Raise { continuable } => State::Raise(Raised::Value(value), continuable),
+        Frame::Error => {
+            let message = match value {
+                Value::String(s) => s,
+                other => other.to_string(),
+            };
+            State::error(format!("Error: {}", message))
+        }
+        Frame::Yield => {
+            let index = match stack
+                .iter()
+                .rposition(|frame| matches!(frame, Frame::Generator(_)))
+            {
+                Some(index) => index,
+                None => return State::error("yield outside a generator"),
+            };
+            let frames = stack.split_off(index + 1);
+            if let Some(Frame::Generator(state)) = stack.pop() {
+                *state.borrow_mut() = Generator::Suspended(frames);
+            }
+            State::Return(value)
+        }
+        Frame::Generator(state) => {
+            *state.borrow_mut() = Generator::Done;
+            State::Return(eof_object())
+        }
+        Frame::Traced(step) => {
+            trace::exit(step, &value);
+            State::Return(value)
+        }
+        // Delimiters pass the value of what they delimit through
+        Frame::Handler { .. } | Frame::Mask(_) | Frame::Guard { .. } | Frame::Reset => {
+            State::Return(value)
+        }
+    }
+}
+
+/// Apply a procedure on the stack
+fn apply(func: Value, args: Vec<Value>, stack: &mut Vec<Frame>) -> State {
+    match func {
+        Value::Lambda(lambda) => match bind_parameters(&lambda, args) {
+            Ok(env) => State::Eval(lambda.body.clone(), env),
+            Err(e) => State::error(e),
+        },
+        Value::Continuation(k) if k.run.is_some() => {
+            resume_continuation(k, values::values(args), stack)
+        }
+        Value::Continuation(k) => {
+            stack.push(Frame::Reset);
+            stack.extend(k.frames.iter().cloned());
+            State::Return(args.into_iter().next().unwrap_or(Value::Nil))
+        }
+        Value::Procedure(p) | Value::RustFn(p, _) => match p(args) {
+            Ok(value) => State::Return(value),
+            Err(e) => State::error(e),
+        },
+        Value::Parameter(parameter) if args.is_empty() => State::Return(parameter.get()),
+        Value::Parameter(_) => State::error("A parameter takes no arguments"),
+        _ => State::error(format!("Not a function: {}", func)),
+    }
+}
+
+/// Unwind to the nearest handler of `raised`, or fail the run if there is none
+fn raise(raised: Raised, continuable: bool, stack: &mut Vec<Frame>) -> Result<State, Error> {
+    if is_escaping(&raised) {
+        let owner = ESCAPE.with(|escape| {
+            let escape = escape.borrow();
+            escape.as_ref().and_then(|(k, _)| k.run) == Some(current_run())
+        });
+        if owner {
+            if let Some((k, value)) = ESCAPE.with(|escape| escape.borrow_mut().take()) {
+                return Ok(replace_stack(&k, value, stack));
+            }
+        }
+        // Handlers don't see it on its way out to the run that captured it
+        unwind(stack, 0);
+        return Err(raised.into_error());
+    }
+
+    let mut index = stack.len();
+    while index > 0 {
+        index -= 1;
+        match &stack[index] {
+            Frame::Mask(depth) => index = index.saturating_sub(*depth),
+            Frame::Handler { handler } => {
+                let handler = handler.clone();
+                let condition = raised.into_value();
+                // A continuable raise returns the handler's value to the raise;
+                // otherwise it becomes the value of `with-exception-handler`
+                if continuable {
+                    stack.push(Frame::Mask(stack.len() - index));
+                } else {
+                    unwind(stack, index);
+                }
+                return Ok(apply(handler, vec![condition], stack));
+            }
+            Frame::Guard { .. } => {
+                unwind(stack, index + 1);
+                let (env, var, clauses) = match stack.pop() {
+                    Some(Frame::Guard { env, var, clauses }) => (env, var, clauses),
+                    _ => unreachable!(),
+                };
+                let env = create_environment(Some(env));
+                env.borrow_mut()
+                    .bindings
+                    .insert(var, raised.clone().into_value());
+                return Ok(guard_clauses(clauses, env, raised, stack));
+            }
+            _ => {}
+        }
+    }
+    unwind(stack, 0);
+    Err(raised.into_error())
+}
+
+/// Drop the frames above `len`, restoring the parameters of any
+/// `parameterize` bodies among them and calling the after thunks of any
+/// `dynamic-wind` thunks. An after thunk runs to completion on a stack of its
+/// own, and what it raises is dropped with the frames.
+fn unwind(stack: &mut Vec<Frame>, len: usize) {
+    while stack.len() > len {
+        match stack.pop() {
+            Some(Frame::Wind(wind)) => {
+                let _ = call(&wind.after, Vec::new());
+            }
+            Some(frame) => leave(frame),
+            None => {}
+        }
+    }
+}
+
+/// Leave a frame without returning to it
+fn leave(frame: Frame) {
+    match frame {
+        Frame::Parameterized(saved) => restore(saved),
+        Frame::Traced(step) => trace::unwound(step),
+        _ => {}
+    }
+}
+
+fn restore(saved: Vec<(Rc<Parameter>, Value)>) {
+    for (parameter, old) in saved.into_iter().rev() {
+        parameter.replace(old);
+    }
+}
+
+fn lambda(args: Value, env: Rc<RefCell<Environment>>) -> State {
+    match args {
+        Value::Pair(pair) => match &pair.1 {
+            Value::Pair(body) => State::Return(Value::Lambda(Rc::new(Lambda::new(
+                pair.0.clone(),
+                lambda_body(body, &env),
+                env,
+            )))),
+            _ => State::error("Malformed lambda"),
+        },
+        _ => State::error("Invalid lambda form"),
+    }
+}
+
+/// The expression a lambda or `let` evaluates for its body: the first body
+/// expression in edition 2024, all of them in order from edition 2025
+pub(super) fn lambda_body(body: &Rc<Cons>, env: &Rc<RefCell<Environment>>) -> Value {
+    if env.borrow().edition().full_bodies() && !matches!(body.1, Value::Nil) {
+        Value::cons(
+            Value::Symbol("begin".to_string()),
+            Value::Pair(body.clone()),
+        )
+    } else {
+        body.0.clone()
+    }
+}
+
+/// A fresh environment for a call of `lambda` with `args`
+fn bind_parameters(lambda: &Lambda, args: Vec<Value>) -> Result<Rc<RefCell<Environment>>, String> {
+    let arity = lambda.arity();
+    if !arity.accepts(args.len()) {
+        return Err(arity_error(&lambda.signature(), arity, args.len()));
+    }
+    let env = create_environment(Some(lambda.env.clone()));
+
+    let mut params = &lambda.params;
+    let mut args = args.into_iter();
+    while let Value::Pair(param) = params {
+        let arg = args.next().unwrap_or(Value::Nil);
+        if let Value::Symbol(name) = &param.0 {
+            env.borrow_mut().bindings.insert(name.clone(), arg);
+        }
+        params = &param.1;
+    }
+    match params {
+        Value::Nil => {}
+        Value::Symbol(rest) => {
+            let rest_args = args
+                .rev()
+                .fold(Value::Nil, |list, arg| Value::cons(arg, list));
+            env.borrow_mut().bindings.insert(rest.clone(), rest_args);
+        }
+        _ => return Err("Invalid parameter list".into()),
+    }
+    Ok(env)
+}
+
+/// Evaluate a body's expressions in order, the last in tail position
+pub(super) fn sequence(
+    body: Value,
+    env: Rc<RefCell<Environment>>,
+    stack: &mut Vec<Frame>,
+) -> State {
+    match skip_annotations(body) {
+        Value::Pair(pair) => {
+            let rest = skip_annotations(pair.1.clone());
+            if matches!(rest, Value::Pair(_)) {
+                stack.push(Frame::Begin {
+                    env: env.clone(),
+                    rest,
+                });
+            }
+            State::Eval(pair.0.clone(), env)
+        }
+        _ => State::Return(Value::Nil),
+    }
+}
+
+/// `and` or `or` over `exprs`, evaluated until one decides it, the last in
+/// tail position
+fn logic(and: bool, exprs: Value, env: Rc<RefCell<Environment>>, stack: &mut Vec<Frame>) -> State {
+    match exprs {
+        Value::Nil => State::Return(Value::Boolean(and)),
+        Value::Pair(pair) => {
+            if !matches!(pair.1, Value::Nil) {
+                stack.push(Frame::Logic {
+                    env: env.clone(),
+                    and,
+                    rest: pair.1.clone(),
+                });
+            }
+            State::Eval(pair.0.clone(), env)
+        }
+        _ => State::error(format!("Malformed {}", if and { "and" } else { "or" })),
+    }
+}
+
+// Annotations such as `#:allow code` are read by the compilers
+fn skip_annotations(mut body: Value) -> Value {
+    while let Value::Pair(pair) = &body {
+        match &pair.0 {
+            Value::Symbol(keyword) if keyword.starts_with("#:") => {
+                body = match &pair.1 {
+                    Value::Pair(argument) => argument.1.clone(),
+                    _ => Value::Nil,
+                };
+            }
+            _ => break,
+        }
+    }
+    body
+}
+
+// Assign an existing variable, as `set!` does
+fn assign(name: &str, value: Value, env: &Rc<RefCell<Environment>>) -> Result<Value, Error> {
+    let mut current = env.clone();
+    loop {
+        if current.borrow().bindings.contains_key(name) {
+            check_core_rebinding(name, &current.borrow())?;
+            current
+                .borrow_mut()
+                .bindings
+                .insert(name.to_string(), value);
+            return Ok(Value::Nil);
+        }
+        let parent = current.borrow().parent.clone();
+        match parent {
+            Some(parent) => current = parent,
+            None => return Err(undefined_variable(name, env)),
+        }
+    }
+}
+
+/// Test `cond` clauses from `clauses` on
+fn cond(clauses: Value, env: Rc<RefCell<Environment>>, stack: &mut Vec<Frame>) -> State {
+    let mut current = clauses;
+    while let Value::Pair(pair) = current {
+        match &pair.0 {
+            Value::Pair(clause) => {
+                stack.push(Frame::Cond {
+                    env: env.clone(),
+                    clause: clause.clone(),
+                    rest: pair.1.clone(),
+                });
+                return State::Eval(clause.0.clone(), env);
+            }
+            Value::Symbol(s) if s == "else" => {
+                return match &pair.1 {
+                    Value::Pair(body) => State::Eval(body.0.clone(), env),
+                    _ => State::Return(Value::Nil),
+                };
+            }
+            _ => {}
+        }
+        current = pair.1.clone();
+    }
+    State::Return(Value::Nil)
+}
+
+// Case special form: (case key ((datum ...) expr) ... (else expr))
+fn case(key: &Value, clauses: Value, env: Rc<RefCell<Environment>>) -> State {
+    let mut current = clauses;
+    while let Value::Pair(pair) = current {
+        let clause = match &pair.0 {
+            Value::Pair(clause) => clause,
+            _ => return State::error("Malformed case clause"),
+        };
+        let matched = match &clause.0 {
+            Value::Symbol(s) if s == "else" => true,
+            datums => {
+                let mut datums = datums;
+                let mut found = false;
+                while let Value::Pair(datum) = datums {
+                    if case_matches(key, &datum.0) {
+                        found = true;
+                        break;
+                    }
+                    datums = &datum.1;
+                }
+                found
+            }
+        };
+        if matched {
+            crate::coverage::record_arm(clause);
+            return match &clause.1 {
+                Value::Pair(body) => State::Eval(body.0.clone(), env),
+                _ => State::Return(Value::Nil),
+            };
+        }
+        current = pair.1.clone();
+    }
+    State::Return(Value::Nil)
+}
+
+// eqv? as case compares: numbers by value and exactness, symbols by name
+fn case_matches(key: &Value, datum: &Value) -> bool {
+    is_eqv(key, datum)
+}
+
+fn let_form(
+    kind: LetKind,
+    args: Value,
+    env: Rc<RefCell<Environment>>,
+    stack: &mut Vec<Frame>,
+) -> State {
+    let (bindings_list, body) = match &args {
+        Value::Pair(pair) => match &pair.1 {
+            Value::Pair(body) => (pair.0.clone(), lambda_body(body, &env)),
+            _ => return State::error(format!("Malformed {}", kind.name())),
+        },
+        _ => return State::error(format!("Malformed {}", kind.name())),
+    };
+
+    match kind {
+        LetKind::Let => {
+            let body_env = create_environment(Some(env.clone()));
+            bindings(kind, env, body_env, bindings_list, body, stack)
+        }
+        LetKind::LetStar => bindings(kind, env.clone(), env, bindings_list, body, stack),
+        LetKind::Letrec => {
+            // Every name is in scope, unassigned, while the initializers run
+            let body_env = create_environment(Some(env));
+            let mut current = &bindings_list;
+            while let Value::Pair(binding) = current {
+                if let Value::Pair(var) = &binding.0 {
+                    if let Value::Symbol(name) = &var.0 {
+                        body_env
+                            .borrow_mut()
+                            .bindings
+                            .insert(name.clone(), Value::Nil);
+                    }
+                }
+                current = &binding.1;
+            }
+            bindings(kind, body_env.clone(), body_env, bindings_list, body, stack)
+        }
+    }
+}
+
+/// Evaluate the initializers left in `rest`, then the body
+fn bindings(
+    kind: LetKind,
+    env: Rc<RefCell<Environment>>,
+    body_env: Rc<RefCell<Environment>>,
+    rest: Value,
+    body: Value,
+    stack: &mut Vec<Frame>,
+) -> State {
+    let mut current = rest;
+    while let Value::Pair(binding) = current {
+        if let Value::Pair(var) = &binding.0 {
+            if let Value::Symbol(name) = &var.0 {
+                let init = match &var.1 {
+                    Value::Pair(init) => init.0.clone(),
+                    _ => return State::error(format!("Malformed binding in {}", kind.name())),
+                };
+                stack.push(Frame::Let {
+                    kind,
+                    env: env.clone(),
+                    body_env,
+                    name: name.clone(),
+                    rest: binding.1.clone(),
+                    body,
+                });
+                return State::Eval(init, env);
+            }
+        }
+        current = binding.1.clone();
+    }
+    State::Eval(body, body_env)
+}
+
+fn guard(args: Value, env: Rc<RefCell<Environment>>, stack: &mut Vec<Frame>) -> State {
+    let (spec, body) = match &args {
+        Value::Pair(pair) => match &pair.0 {
+            Value::Pair(spec) => (spec.clone(), pair.1.clone()),
+            _ => return State::error("Malformed guard expression"),
+        },
+        _ => return State::error("Malformed guard expression"),
+    };
+    let var = match &spec.0 {
+        Value::Symbol(var) => var.clone(),
+        _ => return State::error("Guard variable must be a symbol"),
+    };
+    if !matches!(body, Value::Pair(_)) {
+        return State::error("Malformed guard expression");
+    }
+
+    stack.push(Frame::Guard {
+        env: env.clone(),
+        var,
+        clauses: spec.1.clone(),
+    });
+    sequence(body, env, stack)
+}
+
+/// Test `guard` clauses from `clauses` on, raising again when none matches
+fn guard_clauses(
+    clauses: Value,
+    env: Rc<RefCell<Environment>>,
+    raised: Raised,
+    stack: &mut Vec<Frame>,
+) -> State {
+    let mut current = clauses;
+    while let Value::Pair(pair) = current {
+        match &pair.0 {
+            Value::Pair(clause) => {
+                stack.push(Frame::GuardClause {
+                    env: env.clone(),
+                    body: clause.1.clone(),
+                    rest: pair.1.clone(),
+                    raised,
+                });
+                return State::Eval(clause.0.clone(), env);
+            }
+            Value::Symbol(s) if s == "else" => {
+                if let Value::Pair(expr) = &pair.1 {
+                    return match &expr.0 {
+                        Value::Pair(inner) => State::Eval(inner.0.clone(), env),
+                        other => State::Eval(other.clone(), env),
+                    };
+                }
+            }
+            _ => {}
+        }
+        current = pair.1.clone();
+    }
+    State::Raise(raised, false)
+}
+
+// (assert test [message]): a no-op when assertions are off
+fn assert(args: Value, env: Rc<RefCell<Environment>>, stack: &mut Vec<Frame>) -> State {
+    let (test, message) = match &args {
+        Value::Pair(pair) => match &pair.1 {
+            Value::Nil => (pair.0.clone(), None),
+            Value::Pair(message) if matches!(message.1, Value::Nil) => match &message.0 {
+                Value::String(message) => (pair.0.clone(), Some(message.clone())),
+                _ => return State::error("assert: the message must be a string"),
+            },
+            _ => return State::error("Malformed assert"),
+        },
+        _ => return State::error("Malformed assert"),
+    };
+    if !env.borrow().assertions_enabled() {
+        return State::Return(Value::Nil);
+    }
+
+    stack.push(Frame::Assert {
+        test: test.clone(),
+        message,
+    });
+    State::Eval(test, env)
+}
+
+// (parameterize ((param value) ...) body ...)
+fn parameterize(args: Value, env: Rc<RefCell<Environment>>, stack: &mut Vec<Frame>) -> State {
+    let (bindings, body) = match &args {
+        Value::Pair(pair) => (&pair.0, pair.1.clone()),
+        _ => return State::error("Malformed parameterize"),
+    };
+
+    // Each parameter is evaluated before its value: p1 v1 p2 v2 ...
+    let mut exprs = Vec::new();
+    let mut current = bindings;
+    while let Value::Pair(binding) = current {
+        match &binding.0 {
+            Value::Pair(param) => match &param.1 {
+                Value::Pair(value) => {
+                    exprs.push(param.0.clone());
+                    exprs.push(value.0.clone());
+                }
+                _ => return State::error("Malformed binding in parameterize"),
+            },
+            _ => return State::error("Malformed binding in parameterize"),
+        }
+        current = &binding.1;
+    }
+    let rest = exprs
+        .into_iter()
+        .rev()
+        .fold(Value::Nil, |rest, expr| Value::cons(expr, rest));
+    parameterize_args(env, Vec::new(), rest, body, stack)
+}
+
+/// Evaluate the parameters and values left in `rest`, then run the body with
+/// each parameter set to its converted value
+fn parameterize_args(
+    env: Rc<RefCell<Environment>>,
+    values: Vec<Value>,
+    rest: Value,
+    body: Value,
+    stack: &mut Vec<Frame>,
+) -> State {
+    if let Value::Pair(expr) = rest {
+        stack.push(Frame::ParameterizeArgs {
+            env: env.clone(),
+            values,
+            rest: expr.1.clone(),
+            body,
+        });
+        return State::Eval(expr.0.clone(), env);
+    }
+
+    let mut bindings = Vec::new();
+    for pair in values.chunks(2) {
+        let parameter = match &pair[0] {
+            Value::Parameter(parameter) => parameter.clone(),
+            other => return State::error(format!("parameterize: not a parameter: {}", other)),
+        };
+        match parameter.convert(pair[1].clone()) {
+            Ok(value) => bindings.push((parameter, value)),
+            Err(e) => return State::error(e),
+        }
+    }
+    let saved = bindings
+        .into_iter()
+        .map(|(parameter, value)| {
+            let old = parameter.replace(value);
+            (parameter, old)
+        })
+        .collect();
+    stack.push(Frame::Parameterized(saved));
+    sequence(body, env, stack)
+}
+
+// (shift k body ...): capture the frames up to the nearest reset as k, then
+// evaluate the body in place of them, still inside the reset
+fn shift(args: Value, env: Rc<RefCell<Environment>>, stack: &mut Vec<Frame>) -> State {
+    let (name, body) = match &args {
+        Value::Pair(pair) => match &pair.0 {
+            Value::Symbol(name) => (name.clone(), pair.1.clone()),
+            _ => return State::error("shift requires a variable and a body"),
+        },
+        _ => return State::error("shift requires a variable and a body"),
+    };
+    let index = match stack
+        .iter()
+        .rposition(|frame| matches!(frame, Frame::Reset))
+    {
+        Some(index) => index,
+        None => return State::error("shift without an enclosing reset"),
+    };
+
+    let frames = stack.split_off(index + 1);
+    let env = create_environment(Some(env));
+    env.borrow_mut().bindings.insert(
+        name,
+        Value::Continuation(Rc::new(Continuation { frames, run: None })),
+    );
+    sequence(body, env, stack)
+}
+
+// (dynamic-wind before thunk after), (call/cc proc) or
+// (call-with-values producer consumer)
+fn control(
+    control: Control,
+    args: Value,
+    env: Rc<RefCell<Environment>>,
+    stack: &mut Vec<Frame>,
+) -> State {
+    let mut count = 0;
+    let mut current = &args;
+    while let Value::Pair(arg) = current {
+        count += 1;
+        current = &arg.1;
+    }
+    if count != control.arity() || !matches!(current, Value::Nil) {
+        return State::error(arity_error(
+            control.name(),
+            Arity::Exactly(control.arity()),
+            count,
+        ));
+    }
+    control_args(control, env, Vec::new(), args, stack)
+}
+
+/// Evaluate the arguments left in `rest`, then apply the procedures
+fn control_args(
+    control: Control,
+    env: Rc<RefCell<Environment>>,
+    mut values: Vec<Value>,
+    rest: Value,
+    stack: &mut Vec<Frame>,
+) -> State {
+    if let Value::Pair(expr) = rest {
+        stack.push(Frame::ControlArgs {
+            control,
+            env: env.clone(),
+            values,
+            rest: expr.1.clone(),
+        });
+        return State::Eval(expr.0.clone(), env);
+    }
+
+    match control {
+        Control::CallCc => {
+            let k = Continuation {
+                frames: stack.clone(),
+                run: Some(current_run()),
+            };
+            let proc = values.remove(0);
+            apply(proc, vec![Value::Continuation(Rc::new(k))], stack)
+        }
+        Control::DynamicWind => {
+            let after = values.pop().unwrap_or(Value::Nil);
+            let thunk = values.pop().unwrap_or(Value::Nil);
+            let before = values.pop().unwrap_or(Value::Nil);
+            stack.push(Frame::WindBefore {
+                wind: Rc::new(Wind {
+                    before: before.clone(),
+                    after,
+                }),
+                thunk,
+            });
+            apply(before, Vec::new(), stack)
+        }
+        Control::CallWithValues => {
+            let consumer = values.pop().unwrap_or(Value::Nil);
+            let producer = values.pop().unwrap_or(Value::Nil);
+            stack.push(Frame::Consume(consumer));
+            apply(producer, Vec::new(), stack)
+        }
+    }
+}
+
+fn current_run() -> usize {
+    RUNS.with(|runs| runs.borrow().last().copied().unwrap_or_default())
+}
+
+/// Return `value` to a continuation `call/cc` captured. One captured in a run
+/// still going on outside this one is left to that run to resume.
+fn resume_continuation(k: Rc<Continuation>, value: Value, stack: &mut Vec<Frame>) -> State {
+    let outside = match k.run {
+        Some(run) if run != current_run() => RUNS.with(|runs| runs.borrow().contains(&run)),
+        _ => false,
+    };
+    if outside {
+        ESCAPE.with(|escape| *escape.borrow_mut() = Some((k, value)));
+        return State::error(ESCAPING);
+    }
+    replace_stack(&k, value, stack)
+}
+
+/// Replace the stack with the one `k` captured, calling the after thunks of the
+/// `dynamic-wind` frames left, innermost first, then the before thunks of those
+/// entered, outermost first, then returning `value`
+fn replace_stack(k: &Continuation, value: Value, stack: &mut Vec<Frame>) -> State {
+    let leaving = winds(stack);
+    let entering = winds(&k.frames);
+    let shared = leaving
+        .iter()
+        .zip(&entering)
+        .take_while(|(a, b)| Rc::ptr_eq(a, b))
+        .count();
+
+    let old = std::mem::replace(stack, k.frames.clone());
+    for frame in old.into_iter().rev() {
+        leave(frame);
+    }
+    stack.push(Frame::Deliver(value));
+    for wind in entering[shared..].iter().rev() {
+        stack.push(Frame::Thunk(wind.before.clone()));
+    }
+    for wind in &leaving[shared..] {
+        stack.push(Frame::Thunk(wind.after.clone()));
+    }
+    State::Return(Value::Nil)
+}
+
+/// The `dynamic-wind` thunks `frames` are inside, outermost first
+fn winds(frames: &[Frame]) -> Vec<Rc<Wind>> {
+    frames
+        .iter()
+        .filter_map(|frame| match frame {
+            Frame::Wind(wind) => Some(wind.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether `raised` is a run unwinding to the continuation in ESCAPE. Native
+/// procedures may wrap the message of an error they pass on.
+fn is_escaping(raised: &Raised) -> bool {
+    matches!(raised, Raised::Error(Error::Runtime(message)) if message.contains(ESCAPING))
+        && ESCAPE.with(|escape| escape.borrow().is_some())
+}
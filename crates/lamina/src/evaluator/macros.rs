@@ -0,0 +1,498 @@
+// Macros: define-syntax, let-syntax, letrec-syntax and syntax-rules
+//
+//   (define-syntax swap!
+//     (syntax-rules ()
+//       ((_ a b) (let ((tmp a)) (begin (set! a b) (set! b tmp))))))
+//
+// A use of a macro is replaced by the template of the first rule whose
+// pattern matches it before anything else happens to the form, so a macro can
+// expand to special forms, definitions or other macro uses. Patterns match
+// lists, vectors and literal data; `_` matches anything, a symbol in the
+// literals list only itself, and any other symbol binds a pattern variable.
+// `p ...` matches any number of forms matching `p`, followed by the forms
+// the rest of the pattern asks for, and in a template repeats the forms around
+// the pattern variables matched that way. `(... ...)` stands for a literal
+// `...`, and `(syntax-rules ellipsis (literal ...) rule ...)` picks another
+// symbol for it.
+//
+// The variables a template binds, with `lambda`, the `let` forms or `do`,
+// are renamed afresh on each expansion, so `tmp` above can't capture a `tmp`
+// at the use site. Other symbols a template introduces keep their names and
+// refer to whatever they are bound to where the macro is used; a template
+// shouldn't bind a name it also uses for something bound outside it.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::value::{Environment, Value};
+
+use super::environment::{create_environment, gensym};
+
+/// A macro made by `syntax-rules`
+pub struct Macro {
+    pub name: String,
+    ellipsis: String,
+    literals: HashSet<String>,
+    /// Each rule's pattern and template
+    rules: Vec<(Value, Value)>,
+}
+
+/// What a pattern variable matched: one form, or the matches of each
+/// repetition of the `...` pattern it is under
+#[derive(Clone)]
+enum Match {
+    One(Value),
+    Many(Vec<Match>),
+}
+
+type Bindings = HashMap<String, Match>;
+
+fn malformed(what: &str, form: &Value) -> Error {
+    Error::Runtime(format!("Malformed {}: {}", what, form))
+}
+
+// The elements of a list and what ends it: `()` for a proper list
+fn items_and_tail(list: &Value) -> (Vec<Value>, Value) {
+    let mut items = Vec::new();
+    let mut rest = list;
+    while let Value::Pair(pair) = rest {
+        items.push(pair.0.clone());
+        rest = &pair.1;
+    }
+    (items, rest.clone())
+}
+
+fn with_tail(items: Vec<Value>, tail: Value) -> Value {
+    items
+        .into_iter()
+        .rev()
+        .fold(tail, |rest, item| Value::cons(item, rest))
+}
+
+impl Macro {
+    /// The macro `name` a `(syntax-rules ...)` form describes
+    pub fn parse(name: &str, spec: &Value) -> Result<Macro, Error> {
+        let (items, tail) = items_and_tail(spec);
+        if !matches!(items.first(), Some(Value::Symbol(head)) if head == "syntax-rules")
+            || !matches!(tail, Value::Nil)
+        {
+            return Err(Error::Runtime(format!(
+                "define-syntax of {} requires a syntax-rules form, got {}",
+                name, spec
+            )));
+        }
+        let (ellipsis, rest) = match &items[1..] {
+            [Value::Symbol(ellipsis), rest @ ..] => (ellipsis.clone(), rest),
+            rest => ("...".to_string(), rest),
+        };
+        let Some((literals, rules)) = rest.split_first() else {
+            return Err(malformed("syntax-rules", spec));
+        };
+
+        let (literals, tail) = items_and_tail(literals);
+        if !matches!(tail, Value::Nil) {
+            return Err(malformed("syntax-rules literals", spec));
+        }
+        let literals = literals
+            .iter()
+            .map(|literal| match literal {
+                Value::Symbol(literal) => Ok(literal.clone()),
+                other => Err(malformed("syntax-rules literal", other)),
+            })
+            .collect::<Result<_, _>>()?;
+
+        let rules = rules
+            .iter()
+            .map(|rule| match items_and_tail(rule) {
+                (rule, Value::Nil) if rule.len() == 2 && matches!(rule[0], Value::Pair(_)) => {
+                    Ok((rule[0].clone(), rule[1].clone()))
+                }
+                _ => Err(malformed("syntax-rules rule", rule)),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Macro {
+            name: name.to_string(),
+            ellipsis,
+            literals,
+            rules,
+        })
+    }
+
+    /// The expansion of `form`, a use of this macro
+    pub fn expand(&self, form: &Value) -> Result<Value, Error> {
+        let Value::Pair(use_args) = form else {
+            return Err(malformed("macro use", form));
+        };
+        for (pattern, template) in &self.rules {
+            // The keyword's place in the pattern is ignored
+            let Value::Pair(pattern_args) = pattern else {
+                continue;
+            };
+            let mut bindings = Bindings::new();
+            if !self.matches(&pattern_args.1, &use_args.1, &mut bindings) {
+                continue;
+            }
+            let mut variables = HashSet::new();
+            self.pattern_variables(&pattern_args.1, &mut variables);
+            let mut binders = HashSet::new();
+            binding_names(template, &mut binders);
+            let renames = binders
+                .into_iter()
+                .filter(|name| !variables.contains(name) && *name != self.ellipsis)
+                .map(|name| {
+                    let fresh = gensym(&name);
+                    (name, fresh)
+                })
+                .collect();
+            return Template {
+                ellipsis: &self.ellipsis,
+                renames: &renames,
+            }
+            .expand(template, &bindings, false);
+        }
+        Err(Error::Runtime(format!(
+            "No syntax-rules pattern of {} matches {}",
+            self.name, form
+        )))
+    }
+
+    fn is_ellipsis(&self, value: &Value) -> bool {
+        matches!(value, Value::Symbol(s) if *s == self.ellipsis)
+    }
+
+    // Match `form` against `pattern`, adding to `bindings`
+    fn matches(&self, pattern: &Value, form: &Value, bindings: &mut Bindings) -> bool {
+        match pattern {
+            Value::Symbol(s) if s == "_" => true,
+            Value::Symbol(s) if self.literals.contains(s) => {
+                matches!(form, Value::Symbol(f) if f == s)
+            }
+            Value::Symbol(s) => {
+                bindings.insert(s.clone(), Match::One(form.clone()));
+                true
+            }
+            Value::Pair(_) => {
+                let (patterns, pattern_tail) = items_and_tail(pattern);
+                let (forms, form_tail) = match form {
+                    Value::Pair(_) | Value::Nil => items_and_tail(form),
+                    _ => return false,
+                };
+                self.matches_sequence(&patterns, &pattern_tail, &forms, form_tail, bindings)
+            }
+            Value::Vector(patterns) => match form {
+                Value::Vector(forms) => {
+                    let (patterns, forms) = (patterns.borrow().clone(), forms.borrow().clone());
+                    self.matches_sequence(&patterns, &Value::Nil, &forms, Value::Nil, bindings)
+                }
+                _ => false,
+            },
+            _ => pattern == form,
+        }
+    }
+
+    // Match the elements of a list or vector, `patterns` then `pattern_tail`
+    // against `forms` then `form_tail`
+    fn matches_sequence(
+        &self,
+        patterns: &[Value],
+        pattern_tail: &Value,
+        forms: &[Value],
+        form_tail: Value,
+        bindings: &mut Bindings,
+    ) -> bool {
+        let repeated = patterns
+            .iter()
+            .position(|pattern| self.is_ellipsis(pattern))
+            .filter(|&at| at > 0);
+        let Some(at) = repeated else {
+            // Without an ellipsis, a dotted pattern's tail takes the forms
+            // left over
+            if forms.len() < patterns.len()
+                || (forms.len() > patterns.len() && matches!(pattern_tail, Value::Nil))
+            {
+                return false;
+            }
+            let (matched, rest) = forms.split_at(patterns.len());
+            return patterns
+                .iter()
+                .zip(matched)
+                .all(|(pattern, form)| self.matches(pattern, form, bindings))
+                && self.matches(pattern_tail, &with_tail(rest.to_vec(), form_tail), bindings);
+        };
+
+        let (before, after) = (&patterns[..at - 1], &patterns[at + 1..]);
+        if forms.len() < before.len() + after.len() {
+            return false;
+        }
+        let repeats = &forms[before.len()..forms.len() - after.len()];
+        let repeated = &patterns[at - 1];
+
+        let mut matches = Vec::new();
+        for form in repeats {
+            let mut each = Bindings::new();
+            if !self.matches(repeated, form, &mut each) {
+                return false;
+            }
+            matches.push(each);
+        }
+        let mut variables = HashSet::new();
+        self.pattern_variables(repeated, &mut variables);
+        for variable in variables {
+            let each = matches
+                .iter_mut()
+                .filter_map(|each| each.remove(&variable))
+                .collect();
+            bindings.insert(variable, Match::Many(each));
+        }
+
+        before
+            .iter()
+            .zip(forms)
+            .all(|(pattern, form)| self.matches(pattern, form, bindings))
+            && after
+                .iter()
+                .zip(&forms[forms.len() - after.len()..])
+                .all(|(pattern, form)| self.matches(pattern, form, bindings))
+            && self.matches(pattern_tail, &form_tail, bindings)
+    }
+
+    // The pattern variables of `pattern`
+    fn pattern_variables(&self, pattern: &Value, out: &mut HashSet<String>) {
+        match pattern {
+            Value::Symbol(s) if s != "_" && !self.literals.contains(s) && *s != self.ellipsis => {
+                out.insert(s.clone());
+            }
+            Value::Pair(pair) => {
+                self.pattern_variables(&pair.0, out);
+                self.pattern_variables(&pair.1, out);
+            }
+            Value::Vector(items) => {
+                for item in items.borrow().iter() {
+                    self.pattern_variables(item, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// The variables `lambda`, the `let` forms and `do` bind anywhere in `form`
+fn binding_names(form: &Value, out: &mut HashSet<String>) {
+    let Value::Pair(pair) = form else {
+        return;
+    };
+    let (items, _) = items_and_tail(form);
+    match &pair.0 {
+        Value::Symbol(head) if head == "quote" => return,
+        Value::Symbol(head) if head == "lambda" => {
+            if let Some(params) = items.get(1) {
+                let (params, rest) = items_and_tail(params);
+                for param in params.iter().chain([&rest]) {
+                    if let Value::Symbol(name) = param {
+                        out.insert(name.clone());
+                    }
+                }
+            }
+        }
+        Value::Symbol(head)
+            if matches!(head.as_str(), "let" | "let*" | "letrec" | "letrec*" | "do") =>
+        {
+            let bindings = match items.get(1) {
+                Some(Value::Symbol(name)) => {
+                    out.insert(name.clone());
+                    items.get(2)
+                }
+                other => other,
+            };
+            for binding in bindings.map(|b| items_and_tail(b).0).unwrap_or_default() {
+                if let Value::Pair(binding) = binding {
+                    if let Value::Symbol(name) = &binding.0 {
+                        out.insert(name.clone());
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    for item in &items {
+        binding_names(item, out);
+    }
+}
+
+/// What a template is expanded with
+struct Template<'a> {
+    ellipsis: &'a str,
+    /// Fresh names for the variables the template binds
+    renames: &'a HashMap<String, Value>,
+}
+
+impl Template<'_> {
+    fn is_ellipsis(&self, value: &Value) -> bool {
+        matches!(value, Value::Symbol(s) if s == self.ellipsis)
+    }
+
+    // Fill in `template`; within `(... template)`, `escaped` makes the
+    // ellipsis an ordinary symbol
+    fn expand(&self, template: &Value, bindings: &Bindings, escaped: bool) -> Result<Value, Error> {
+        match template {
+            Value::Symbol(s) => match bindings.get(s) {
+                Some(Match::One(value)) => Ok(value.clone()),
+                Some(Match::Many(_)) => Err(Error::Runtime(format!(
+                    "Pattern variable {} is used without {} in a template",
+                    s, self.ellipsis
+                ))),
+                None => Ok(self.renames.get(s).unwrap_or(template).clone()),
+            },
+            Value::Pair(pair) => {
+                if !escaped && self.is_ellipsis(&pair.0) {
+                    return match &pair.1 {
+                        Value::Pair(escape) if matches!(escape.1, Value::Nil) => {
+                            self.expand(&escape.0, bindings, true)
+                        }
+                        _ => Err(malformed("ellipsis escape", template)),
+                    };
+                }
+                let (items, tail) = items_and_tail(template);
+                let tail = self.expand(&tail, bindings, escaped)?;
+                Ok(with_tail(
+                    self.expand_items(&items, bindings, escaped)?,
+                    tail,
+                ))
+            }
+            Value::Vector(items) => {
+                let items = self.expand_items(&items.borrow(), bindings, escaped)?;
+                Ok(Value::Vector(Rc::new(RefCell::new(items))))
+            }
+            _ => Ok(template.clone()),
+        }
+    }
+
+    // Fill in the elements of a list or vector template, repeating those
+    // followed by ellipses
+    fn expand_items(
+        &self,
+        items: &[Value],
+        bindings: &Bindings,
+        escaped: bool,
+    ) -> Result<Vec<Value>, Error> {
+        let mut expanded = Vec::new();
+        let mut index = 0;
+        while index < items.len() {
+            let item = &items[index];
+            let mut depth = 0;
+            while !escaped
+                && items
+                    .get(index + 1 + depth)
+                    .is_some_and(|i| self.is_ellipsis(i))
+            {
+                depth += 1;
+            }
+            if depth == 0 {
+                expanded.push(self.expand(item, bindings, escaped)?);
+            } else {
+                self.repeat(item, bindings, depth, &mut expanded)?;
+            }
+            index += 1 + depth;
+        }
+        Ok(expanded)
+    }
+
+    // Fill in `item` once for each repetition of the pattern variables in it,
+    // `depth` ellipses deep
+    fn repeat(
+        &self,
+        item: &Value,
+        bindings: &Bindings,
+        depth: usize,
+        out: &mut Vec<Value>,
+    ) -> Result<(), Error> {
+        let mut symbols = HashSet::new();
+        template_symbols(item, &mut symbols);
+        let repeated: Vec<(&String, &Vec<Match>)> = symbols
+            .iter()
+            .filter_map(|s| match bindings.get_key_value(s) {
+                Some((name, Match::Many(matches))) => Some((name, matches)),
+                _ => None,
+            })
+            .collect();
+        let Some(count) = repeated.first().map(|(_, matches)| matches.len()) else {
+            return Err(Error::Runtime(format!(
+                "No pattern variable to repeat with {} in template {}",
+                self.ellipsis, item
+            )));
+        };
+        if repeated.iter().any(|(_, matches)| matches.len() != count) {
+            return Err(Error::Runtime(format!(
+                "Pattern variables repeated together in {} matched different numbers of forms",
+                item
+            )));
+        }
+        for index in 0..count {
+            let mut each = bindings.clone();
+            for (name, matches) in &repeated {
+                each.insert((*name).clone(), matches[index].clone());
+            }
+            if depth == 1 {
+                out.push(self.expand(item, &each, false)?);
+            } else {
+                self.repeat(item, &each, depth - 1, out)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn template_symbols(template: &Value, out: &mut HashSet<String>) {
+    match template {
+        Value::Symbol(s) => {
+            out.insert(s.clone());
+        }
+        Value::Pair(pair) => {
+            template_symbols(&pair.0, out);
+            template_symbols(&pair.1, out);
+        }
+        Value::Vector(items) => {
+            for item in items.borrow().iter() {
+                template_symbols(item, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `(define-syntax name (syntax-rules ...))`: bind the macro in `env`
+pub fn eval_define_syntax(args: &Value, env: &Rc<RefCell<Environment>>) -> Result<Value, Error> {
+    let (items, tail) = items_and_tail(args);
+    let [Value::Symbol(name), spec] = items.as_slice() else {
+        return Err(malformed("define-syntax", args));
+    };
+    if !matches!(tail, Value::Nil) {
+        return Err(malformed("define-syntax", args));
+    }
+    let definition = Macro::parse(name, spec)?;
+    let mut env = env.borrow_mut();
+    // The macro takes the name over from a variable defined here before
+    env.bindings.remove(name);
+    env.macros.insert(name.clone(), Rc::new(definition));
+    Ok(Value::Nil)
+}
+
+/// `(let-syntax ((name (syntax-rules ...)) ...) body ...)`, and `letrec-syntax`
+/// alike: an environment inside `env` binding the macros, and the body to
+/// evaluate in it
+pub fn syntax_scope(
+    args: &Value,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<(Rc<RefCell<Environment>>, Value), Error> {
+    let Value::Pair(pair) = args else {
+        return Err(malformed("let-syntax", args));
+    };
+    let scope = create_environment(Some(env.clone()));
+    for binding in items_and_tail(&pair.0).0 {
+        eval_define_syntax(&binding, &scope)?;
+    }
+    Ok((scope, pair.1.clone()))
+}
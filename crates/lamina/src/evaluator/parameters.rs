@@ -0,0 +1,77 @@
+// Parameter objects
+//
+// `(make-parameter value [converter])` makes a procedure of no arguments
+// returning the parameter's current value. `(parameterize ((param value) ...)
+// body ...)` gives parameters new values for the dynamic extent of the body:
+// the evaluator saves the old values in a frame and puts them back when the
+// body returns or a raise unwinds past it. Native code does the same with
+// [`Parameter::parameterize`].
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::value::{Environment, Value};
+
+use super::apply;
+
+pub struct Parameter {
+    value: RefCell<Value>,
+    /// Applied to the initial value and to each value given by `parameterize`
+    converter: Option<Value>,
+}
+
+impl Parameter {
+    pub fn new(value: Value) -> Self {
+        Parameter {
+            value: RefCell::new(value),
+            converter: None,
+        }
+    }
+
+    pub fn get(&self) -> Value {
+        self.value.borrow().clone()
+    }
+
+    /// Replace the value, returning the old one
+    pub fn replace(&self, value: Value) -> Value {
+        self.value.replace(value)
+    }
+
+    /// Run the converter on a value about to be given to the parameter
+    pub fn convert(&self, value: Value) -> Result<Value, String> {
+        match &self.converter {
+            Some(converter) => apply(converter, vec![value]),
+            None => Ok(value),
+        }
+    }
+
+    /// Run `f` with the parameter set to `value`, restoring the old value
+    /// afterwards whether or not `f` fails
+    pub fn parameterize<T>(&self, value: Value, f: impl FnOnce() -> T) -> T {
+        let old = self.replace(value);
+        let result = f();
+        self.replace(old);
+        result
+    }
+}
+
+pub fn register_parameter_procedures(env: &Rc<RefCell<Environment>>) {
+    // (make-parameter value [converter])
+    env.borrow_mut().bindings.insert(
+        "make-parameter".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            let (value, converter) = match args.as_slice() {
+                [value] => (value.clone(), None),
+                [value, converter] if converter.is_procedure() => (
+                    apply(converter, vec![value.clone()])?,
+                    Some(converter.clone()),
+                ),
+                _ => return Err("make-parameter requires a value and an optional converter".into()),
+            };
+            Ok(Value::Parameter(Rc::new(Parameter {
+                value: RefCell::new(value),
+                converter,
+            })))
+        })),
+    );
+}
@@ -0,0 +1,707 @@
+// Ports
+//
+// An input port reads from standard input or from any `BufRead` an embedder
+// supplies (`InterpreterBuilder::with_input`). Characters are taken from the
+// source a line at a time into a buffer the reading procedures consume, so
+// `read-string` can stop mid-line and `read-line` pick up after it. Reading
+// past the end returns the end-of-file object.
+//
+// `read` parses the next datum from a port, taking lines until they hold a
+// whole one, and raises a read error when they can't.
+//
+// An output port writes to standard output, a string or a file. The current
+// ports are parameter objects, so `parameterize` and the `with-...` procedures
+// redirect `display` and the reading procedures without changing the code
+// that calls them.
+//
+// `open-input-string`, `open-output-string` and the `open-...-file`
+// procedures make ports to pass explicitly. Closing a port flushes it if it
+// writes to a file, and reading or writing it after raises an error.
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Stdin, Write};
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::parser;
+use crate::value::{Environment, Record, RecordType, Value};
+
+use super::apply;
+use super::conditions::{file_error, read_error};
+use super::libraries;
+use super::parameters::Parameter;
+
+thread_local! {
+    static EOF: Value = Value::Record(Rc::new(Record {
+        type_info: Rc::new(RecordType {
+            name: "eof-object".to_string(),
+            fields: Vec::new(),
+        }),
+        values: RefCell::new(HashMap::new()),
+    }));
+}
+
+/// The end-of-file object, a record of its own type
+pub fn eof_object() -> Value {
+    EOF.with(Value::clone)
+}
+
+pub fn is_eof_object(value: &Value) -> bool {
+    match (value, eof_object()) {
+        (Value::Record(a), Value::Record(b)) => Rc::ptr_eq(a, &b),
+        _ => false,
+    }
+}
+
+enum Source {
+    Stdin(BufReader<Stdin>),
+    Reader(Box<dyn BufRead>),
+}
+
+/// A port characters are read from
+pub struct InputPort {
+    source: RefCell<Source>,
+    /// Characters read from the source and not consumed yet
+    pending: RefCell<VecDeque<char>>,
+    /// Whether `read` folds symbols to lower case, as `#!fold-case` read from
+    /// the port last switched it
+    fold_case: Cell<bool>,
+    open: Cell<bool>,
+    /// Whether the source has reached its end
+    at_end: Cell<bool>,
+}
+
+impl InputPort {
+    pub fn stdin() -> Self {
+        Self::new(Source::Stdin(BufReader::new(io::stdin())))
+    }
+
+    /// A port reading from `reader`, such as an `io::Cursor` over a string
+    pub fn from_reader(reader: impl BufRead + 'static) -> Self {
+        Self::new(Source::Reader(Box::new(reader)))
+    }
+
+    /// A port reading the characters of `s`
+    pub fn string(s: &str) -> Self {
+        Self::from_reader(io::Cursor::new(s.to_string()))
+    }
+
+    /// A port reading the file at `path`
+    pub fn file(path: &str) -> Result<Self, String> {
+        let file = File::open(path)
+            .map_err(|e| file_error(format_args!("Cannot open {}: {}", path, e)))?;
+        Ok(Self::from_reader(BufReader::new(file)))
+    }
+
+    fn new(source: Source) -> Self {
+        InputPort {
+            source: RefCell::new(source),
+            pending: RefCell::new(VecDeque::new()),
+            fold_case: Cell::new(false),
+            open: Cell::new(true),
+            at_end: Cell::new(false),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open.get()
+    }
+
+    pub fn close(&self) {
+        self.open.set(false);
+        self.pending.borrow_mut().clear();
+    }
+
+    /// The next character, or `None` at the end of input
+    pub fn read_char(&self) -> Result<Option<char>, String> {
+        let c = self.peek_char()?;
+        self.pending.borrow_mut().pop_front();
+        Ok(c)
+    }
+
+    /// The next character without consuming it, or `None` at the end of input
+    pub fn peek_char(&self) -> Result<Option<char>, String> {
+        while self.pending.borrow().is_empty() {
+            if !self.fill()? {
+                return Ok(None);
+            }
+        }
+        Ok(self.pending.borrow().front().copied())
+    }
+
+    /// The next line without its line ending, or `None` at the end of input
+    pub fn read_line(&self) -> Result<Option<String>, String> {
+        loop {
+            let newline = self.pending.borrow().iter().position(|&c| c == '\n');
+            if let Some(newline) = newline {
+                let mut line: String = self.pending.borrow_mut().drain(..=newline).collect();
+                line.pop();
+                if line.ends_with('\r') {
+                    line.pop();
+                }
+                return Ok(Some(line));
+            }
+            if !self.fill()? {
+                let rest: String = self.pending.borrow_mut().drain(..).collect();
+                return Ok((!rest.is_empty()).then_some(rest));
+            }
+        }
+    }
+
+    /// Up to `count` characters, fewer only at the end of input, or `None` if
+    /// the input is already at its end
+    pub fn read_string(&self, count: usize) -> Result<Option<String>, String> {
+        while self.pending.borrow().len() < count && self.fill()? {}
+        let mut pending = self.pending.borrow_mut();
+        if pending.is_empty() && count > 0 {
+            return Ok(None);
+        }
+        let count = count.min(pending.len());
+        Ok(Some(pending.drain(..count).collect()))
+    }
+
+    /// Fold the symbols `read` returns to lower case, until the input says
+    /// otherwise with `#!no-fold-case`
+    pub fn set_fold_case(&self, fold_case: bool) {
+        self.fold_case.set(fold_case);
+    }
+
+    /// The next datum, or `None` at the end of input. What follows the datum
+    /// on its last line is left for the next read.
+    pub fn read_datum(&self) -> Result<Option<Value>, String> {
+        loop {
+            let text: String = self.pending.borrow().iter().collect();
+            let mut rest = text.as_str();
+            let mut fold_case = self.fold_case.get();
+            let error = match parser::read_datum_with_case(&mut rest, &mut fold_case) {
+                Ok(Some(datum)) => {
+                    let count = text[..text.len() - rest.len()].chars().count();
+                    self.pending.borrow_mut().drain(..count);
+                    self.fold_case.set(fold_case);
+                    return Ok(Some(datum));
+                }
+                Ok(None) => None,
+                Err(e) => Some(e),
+            };
+            // A datum, string or comment may go on on the next line
+            let more = match &error {
+                None | Some(Error::Lexer(_)) => true,
+                Some(e) => parser::is_incomplete(e),
+            };
+            if more && self.fill()? {
+                continue;
+            }
+            self.pending.borrow_mut().clear();
+            return match error {
+                None => Ok(None),
+                Some(Error::Parser(message) | Error::Lexer(message)) => Err(read_error(message)),
+                Some(other) => Err(read_error(other)),
+            };
+        }
+    }
+
+    /// Whether a character can be read without blocking. Standard input is
+    /// ready when it has buffered input or a read has reached its end, after
+    /// which reading returns the end of file object at once; other sources are
+    /// always ready.
+    pub fn char_ready(&self) -> bool {
+        if !self.pending.borrow().is_empty() || self.at_end.get() {
+            return true;
+        }
+        match &*self.source.borrow() {
+            Source::Stdin(stdin) => !stdin.buffer().is_empty(),
+            Source::Reader(_) => true,
+        }
+    }
+
+    // Move the next line of the source into the buffer, returning false at
+    // the end of input
+    fn fill(&self) -> Result<bool, String> {
+        let mut line = String::new();
+        let read = match &mut *self.source.borrow_mut() {
+            Source::Stdin(stdin) => stdin.read_line(&mut line),
+            Source::Reader(reader) => reader.read_line(&mut line),
+        };
+        match read {
+            Ok(0) => {
+                self.at_end.set(true);
+                Ok(false)
+            }
+            Ok(_) => {
+                self.pending.borrow_mut().extend(line.chars());
+                Ok(true)
+            }
+            Err(e) => Err(format!("Failed to read input: {}", e)),
+        }
+    }
+}
+
+enum Sink {
+    Stdout,
+    String(String),
+    File(BufWriter<File>),
+}
+
+/// A port characters are written to
+pub struct OutputPort {
+    sink: RefCell<Sink>,
+    open: Cell<bool>,
+}
+
+impl OutputPort {
+    pub fn stdout() -> Self {
+        Self::new(Sink::Stdout)
+    }
+
+    /// A port collecting what is written to it, see [`OutputPort::contents`]
+    pub fn string() -> Self {
+        Self::new(Sink::String(String::new()))
+    }
+
+    /// A port writing to the file at `path`, replacing its contents
+    pub fn file(path: &str) -> Result<Self, String> {
+        let file = File::create(path)
+            .map_err(|e| file_error(format_args!("Cannot open {}: {}", path, e)))?;
+        Ok(Self::new(Sink::File(BufWriter::new(file))))
+    }
+
+    fn new(sink: Sink) -> Self {
+        OutputPort {
+            sink: RefCell::new(sink),
+            open: Cell::new(true),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open.get()
+    }
+
+    /// Flush the port and stop writing to it; a string port keeps its
+    /// contents
+    pub fn close(&self) -> Result<(), String> {
+        if !self.open.replace(false) {
+            return Ok(());
+        }
+        self.flush()
+    }
+
+    pub fn write_str(&self, s: &str) -> Result<(), String> {
+        let written = match &mut *self.sink.borrow_mut() {
+            Sink::Stdout => io::stdout().write_all(s.as_bytes()),
+            Sink::String(contents) => {
+                contents.push_str(s);
+                Ok(())
+            }
+            Sink::File(file) => file.write_all(s.as_bytes()),
+        };
+        written.map_err(|e| format!("Failed to write output: {}", e))
+    }
+
+    /// What has been written to a string port
+    pub fn contents(&self) -> Option<String> {
+        match &*self.sink.borrow() {
+            Sink::String(contents) => Some(contents.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn flush(&self) -> Result<(), String> {
+        let flushed = match &mut *self.sink.borrow_mut() {
+            Sink::Stdout => io::stdout().flush(),
+            Sink::String(_) => Ok(()),
+            Sink::File(file) => file.flush(),
+        };
+        flushed.map_err(|e| format!("Failed to write output: {}", e))
+    }
+}
+
+/// Register the port procedures, reading from `input` and writing to
+/// standard output unless given a port or redirected
+pub fn register_port_procedures(env: &Rc<RefCell<Environment>>, input: Rc<InputPort>) {
+    let input = Rc::new(Parameter::new(Value::InputPort(input)));
+    let output = Rc::new(Parameter::new(Value::OutputPort(Rc::new(
+        OutputPort::stdout(),
+    ))));
+
+    env.borrow_mut().bindings.insert(
+        "eof-object".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            if !args.is_empty() {
+                return Err("eof-object takes no arguments".into());
+            }
+            Ok(eof_object())
+        })),
+    );
+
+    env.borrow_mut().bindings.insert(
+        "input-port?".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            if args.len() != 1 {
+                return Err("input-port? requires exactly one argument".into());
+            }
+            Ok(Value::Boolean(matches!(args[0], Value::InputPort(_))))
+        })),
+    );
+
+    env.borrow_mut().bindings.insert(
+        "output-port?".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            if args.len() != 1 {
+                return Err("output-port? requires exactly one argument".into());
+            }
+            Ok(Value::Boolean(matches!(args[0], Value::OutputPort(_))))
+        })),
+    );
+
+    env.borrow_mut().bindings.insert(
+        "current-input-port".to_string(),
+        Value::Parameter(input.clone()),
+    );
+    env.borrow_mut().bindings.insert(
+        "current-output-port".to_string(),
+        Value::Parameter(output.clone()),
+    );
+
+    register_port_constructors(env);
+
+    // (read-char [port]) and (peek-char [port])
+    let current = input.clone();
+    env.borrow_mut().bindings.insert(
+        "read-char".to_string(),
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            let port = port_argument("read-char", &args, &current)?;
+            Ok(port.read_char()?.map_or_else(eof_object, Value::Character))
+        })),
+    );
+    let current = input.clone();
+    env.borrow_mut().bindings.insert(
+        "peek-char".to_string(),
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            let port = port_argument("peek-char", &args, &current)?;
+            Ok(port.peek_char()?.map_or_else(eof_object, Value::Character))
+        })),
+    );
+
+    // (read-line [port])
+    let current = input.clone();
+    env.borrow_mut().bindings.insert(
+        "read-line".to_string(),
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            let port = port_argument("read-line", &args, &current)?;
+            Ok(port.read_line()?.map_or_else(eof_object, Value::String))
+        })),
+    );
+
+    // (read [port]): the next datum, raising a read error if it is malformed
+    let current = input.clone();
+    env.borrow_mut().bindings.insert(
+        "read".to_string(),
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            let port = port_argument("read", &args, &current)?;
+            Ok(port.read_datum()?.unwrap_or_else(eof_object))
+        })),
+    );
+
+    // (read-string k [port])
+    let current = input.clone();
+    env.borrow_mut().bindings.insert(
+        "read-string".to_string(),
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            let (count, rest) = match args.split_first() {
+                Some((count, rest)) => (libraries::number_to_i64(count)?, rest),
+                None => return Err("read-string requires a count".into()),
+            };
+            if count < 0 {
+                return Err(format!("read-string: negative count: {}", count));
+            }
+            let port = port_argument("read-string", rest, &current)?;
+            Ok(port
+                .read_string(count as usize)?
+                .map_or_else(eof_object, Value::String))
+        })),
+    );
+
+    // (char-ready? [port])
+    let current = input.clone();
+    env.borrow_mut().bindings.insert(
+        "char-ready?".to_string(),
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            let port = port_argument("char-ready?", &args, &current)?;
+            Ok(Value::Boolean(port.char_ready()))
+        })),
+    );
+
+    // (display obj [port]): strings and characters without quotes
+    let current = output.clone();
+    env.borrow_mut().bindings.insert(
+        "display".to_string(),
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            let (value, rest) = args.split_first().ok_or("display requires an argument")?;
+            let port = output_port_argument("display", rest, &current)?;
+            match value {
+                Value::String(s) => port.write_str(s)?,
+                Value::Character(c) => port.write_str(&c.to_string())?,
+                other => port.write_str(&other.to_string())?,
+            }
+            Ok(Value::Nil)
+        })),
+    );
+
+    // (write obj [port])
+    let current = output.clone();
+    env.borrow_mut().bindings.insert(
+        "write".to_string(),
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            let (value, rest) = args.split_first().ok_or("write requires an argument")?;
+            let port = output_port_argument("write", rest, &current)?;
+            port.write_str(&value.to_string())?;
+            Ok(Value::Nil)
+        })),
+    );
+
+    // (write-string string [port])
+    let current = output.clone();
+    env.borrow_mut().bindings.insert(
+        "write-string".to_string(),
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            let (s, rest) = match args.split_first() {
+                Some((Value::String(s), rest)) => (s, rest),
+                _ => return Err("write-string requires a string".into()),
+            };
+            let port = output_port_argument("write-string", rest, &current)?;
+            port.write_str(s)?;
+            Ok(Value::Nil)
+        })),
+    );
+
+    // (write-char char [port])
+    let current = output.clone();
+    env.borrow_mut().bindings.insert(
+        "write-char".to_string(),
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            let (c, rest) = match args.split_first() {
+                Some((Value::Character(c), rest)) => (c, rest),
+                _ => return Err("write-char requires a character".into()),
+            };
+            let port = output_port_argument("write-char", rest, &current)?;
+            port.write_str(&c.to_string())?;
+            Ok(Value::Nil)
+        })),
+    );
+
+    // (flush-output-port [port])
+    let current = output.clone();
+    env.borrow_mut().bindings.insert(
+        "flush-output-port".to_string(),
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            output_port_argument("flush-output-port", &args, &current)?.flush()?;
+            Ok(Value::Nil)
+        })),
+    );
+
+    // (newline [port])
+    let current = output.clone();
+    env.borrow_mut().bindings.insert(
+        "newline".to_string(),
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            let port = output_port_argument("newline", &args, &current)?;
+            port.write_str("\n")?;
+            Ok(Value::Nil)
+        })),
+    );
+
+    // (with-output-to-string thunk): what the thunk writes, as a string
+    let current = output.clone();
+    env.borrow_mut().bindings.insert(
+        "with-output-to-string".to_string(),
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            let thunk = thunk_argument("with-output-to-string", &args, 0)?;
+            let port = Rc::new(OutputPort::string());
+            current.parameterize(Value::OutputPort(port.clone()), || apply(thunk, Vec::new()))?;
+            Ok(Value::String(port.contents().unwrap_or_default()))
+        })),
+    );
+
+    // (with-output-to-file path thunk)
+    let current = output;
+    env.borrow_mut().bindings.insert(
+        "with-output-to-file".to_string(),
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            let path = path_argument("with-output-to-file", &args)?;
+            let thunk = thunk_argument("with-output-to-file", &args, 1)?;
+            let port = Rc::new(OutputPort::file(path)?);
+            let result =
+                current.parameterize(Value::OutputPort(port.clone()), || apply(thunk, Vec::new()));
+            port.flush()?;
+            result
+        })),
+    );
+
+    // (with-input-from-file path thunk)
+    let current = input;
+    env.borrow_mut().bindings.insert(
+        "with-input-from-file".to_string(),
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            let path = path_argument("with-input-from-file", &args)?;
+            let thunk = thunk_argument("with-input-from-file", &args, 1)?;
+            let port = InputPort::file(path)?;
+            current.parameterize(Value::InputPort(Rc::new(port)), || apply(thunk, Vec::new()))
+        })),
+    );
+}
+
+/// Register the procedures that make, test and close ports
+fn register_port_constructors(env: &Rc<RefCell<Environment>>) {
+    let mut env = env.borrow_mut();
+
+    env.bindings.insert(
+        "open-input-string".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| match args.as_slice() {
+            [Value::String(s)] => Ok(Value::InputPort(Rc::new(InputPort::string(s)))),
+            _ => Err("open-input-string requires a string".into()),
+        })),
+    );
+    env.bindings.insert(
+        "open-output-string".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            if !args.is_empty() {
+                return Err("open-output-string takes no arguments".into());
+            }
+            Ok(Value::OutputPort(Rc::new(OutputPort::string())))
+        })),
+    );
+    // (get-output-string port): what has been written to a string port
+    env.bindings.insert(
+        "get-output-string".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| match args.as_slice() {
+            [Value::OutputPort(port)] => port
+                .contents()
+                .map(Value::String)
+                .ok_or_else(|| "get-output-string: not a string port".to_string()),
+            _ => Err("get-output-string requires an output port".into()),
+        })),
+    );
+    env.bindings.insert(
+        "open-input-file".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| match args.as_slice() {
+            [Value::String(path)] => Ok(Value::InputPort(Rc::new(InputPort::file(path)?))),
+            _ => Err("open-input-file requires a file name".into()),
+        })),
+    );
+    env.bindings.insert(
+        "open-output-file".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| match args.as_slice() {
+            [Value::String(path)] => Ok(Value::OutputPort(Rc::new(OutputPort::file(path)?))),
+            _ => Err("open-output-file requires a file name".into()),
+        })),
+    );
+
+    for name in ["close-port", "close-input-port", "close-output-port"] {
+        env.bindings.insert(
+            name.to_string(),
+            Value::Procedure(Rc::new(move |args: Vec<Value>| {
+                match (name, args.as_slice()) {
+                    ("close-port" | "close-input-port", [Value::InputPort(port)]) => port.close(),
+                    ("close-port" | "close-output-port", [Value::OutputPort(port)]) => {
+                        port.close()?
+                    }
+                    _ => return Err(format!("{} requires a port", name)),
+                }
+                Ok(Value::Nil)
+            })),
+        );
+    }
+
+    // (call-with-port port proc): proc's value, closing the port once it
+    // returns
+    env.bindings.insert(
+        "call-with-port".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| match args.as_slice() {
+            [port @ (Value::InputPort(_) | Value::OutputPort(_)), proc] => {
+                let result = apply(proc, vec![port.clone()])?;
+                match port {
+                    Value::InputPort(port) => port.close(),
+                    Value::OutputPort(port) => port.close()?,
+                    _ => {}
+                }
+                Ok(result)
+            }
+            _ => Err("call-with-port requires a port and a procedure".into()),
+        })),
+    );
+
+    // Every port is textual
+    env.bindings.insert(
+        "textual-port?".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| match args.as_slice() {
+            [value] => Ok(Value::Boolean(matches!(
+                value,
+                Value::InputPort(_) | Value::OutputPort(_)
+            ))),
+            _ => Err("textual-port? requires exactly one argument".into()),
+        })),
+    );
+    env.bindings.insert(
+        "input-port-open?".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| match args.as_slice() {
+            [Value::InputPort(port)] => Ok(Value::Boolean(port.is_open())),
+            _ => Err("input-port-open? requires an input port".into()),
+        })),
+    );
+    env.bindings.insert(
+        "output-port-open?".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| match args.as_slice() {
+            [Value::OutputPort(port)] => Ok(Value::Boolean(port.is_open())),
+            _ => Err("output-port-open? requires an output port".into()),
+        })),
+    );
+}
+
+// The optional port argument of a reading procedure
+fn port_argument(name: &str, args: &[Value], current: &Parameter) -> Result<Rc<InputPort>, String> {
+    let port = match args {
+        [] => current.get(),
+        [port] => port.clone(),
+        _ => return Err(format!("{}: too many arguments", name)),
+    };
+    match port {
+        Value::InputPort(port) if port.is_open() => Ok(port),
+        Value::InputPort(_) => Err(format!("{}: the port is closed", name)),
+        other => Err(format!("{}: not an input port: {}", name, other)),
+    }
+}
+
+// The optional port argument of a writing procedure
+fn output_port_argument(
+    name: &str,
+    args: &[Value],
+    current: &Parameter,
+) -> Result<Rc<OutputPort>, String> {
+    let port = match args {
+        [] => current.get(),
+        [port] => port.clone(),
+        _ => return Err(format!("{}: too many arguments", name)),
+    };
+    match port {
+        Value::OutputPort(port) if port.is_open() => Ok(port),
+        Value::OutputPort(_) => Err(format!("{}: the port is closed", name)),
+        other => Err(format!("{}: not an output port: {}", name, other)),
+    }
+}
+
+fn path_argument<'a>(name: &str, args: &'a [Value]) -> Result<&'a str, String> {
+    match args.first() {
+        Some(Value::String(path)) => Ok(path),
+        _ => Err(format!("{} requires a file name and a thunk", name)),
+    }
+}
+
+fn thunk_argument<'a>(name: &str, args: &'a [Value], index: usize) -> Result<&'a Value, String> {
+    match args.get(index) {
+        Some(thunk) if args.len() == index + 1 && thunk.is_procedure() => Ok(thunk),
+        _ if index == 0 => Err(format!("{} requires a thunk", name)),
+        _ => Err(format!("{} requires a file name and a thunk", name)),
+    }
+}
@@ -0,0 +1,102 @@
+// Type predicates and `type-of`
+//
+// Each predicate holds for the values of one of the disjoint types of R7RS
+// section 3.2. Some of those types are made of several kinds of value here:
+// built-in procedures, lambdas, continuations and parameters are all
+// procedures, and input and output ports are both ports. The end-of-file
+// object and multiple values are kept in records of their own types, but
+// aren't records to `record?`.
+//
+// `(type-of v)` names the type of `v` with a symbol, the predicate's name
+// without the `?`, for generic code and error messages:
+//
+//   (type-of '(1 2))        => pair
+//   (type-of (vector))      => vector
+//   (type-of car)           => procedure
+//
+// Lamina has no promises yet, so `promise?` holds for nothing.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::value::{Environment, Value};
+
+use super::ports::is_eof_object;
+use super::values::is_values;
+
+/// The name of the type of `value`, as `type-of` returns it
+pub fn type_of(value: &Value) -> &'static str {
+    match value {
+        Value::Nil => "null",
+        Value::Boolean(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::Character(_) => "char",
+        Value::String(_) => "string",
+        Value::Symbol(_) => "symbol",
+        Value::Pair(_) => "pair",
+        Value::Vector(_) => "vector",
+        Value::Bytevector(_) => "bytevector",
+        Value::Procedure(_)
+        | Value::Lambda(_)
+        | Value::Continuation(_)
+        | Value::Parameter(_)
+        | Value::RustFn(_, _) => "procedure",
+        Value::InputPort(_) | Value::OutputPort(_) => "port",
+        value if is_eof_object(value) => "eof-object",
+        value if is_values(value) => "values",
+        Value::Record(_) => "record",
+        Value::RecordType(_) => "record-type",
+        Value::Environment(_) => "environment",
+        Value::Library(_) => "library",
+        Value::Address(_) => "address",
+        Value::Opaque(_) => "opaque",
+        Value::Foreign(_, _) => "foreign",
+    }
+}
+
+/// Register the type predicates and `type-of`
+pub fn register_type_predicates(env: &Rc<RefCell<Environment>>) {
+    let mut env = env.borrow_mut();
+    for (name, procedure) in type_predicates() {
+        env.bindings.insert(name, procedure);
+    }
+}
+
+/// A predicate for each type, and `type-of`
+pub fn type_predicates() -> Vec<(String, Value)> {
+    let mut procedures = Vec::new();
+    for name in [
+        "null",
+        "boolean",
+        "number",
+        "char",
+        "string",
+        "symbol",
+        "pair",
+        "vector",
+        "bytevector",
+        "procedure",
+        "port",
+        "eof-object",
+        "record",
+        "promise",
+    ] {
+        let predicate = format!("{}?", name);
+        procedures.push((
+            predicate.clone(),
+            Value::Procedure(Rc::new(move |args: Vec<Value>| match args.as_slice() {
+                [value] => Ok(Value::Boolean(type_of(value) == name)),
+                _ => Err(format!("{} requires exactly 1 argument", predicate)),
+            })),
+        ));
+    }
+
+    procedures.push((
+        "type-of".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| match args.as_slice() {
+            [value] => Ok(Value::Symbol(type_of(value).to_string())),
+            _ => Err("type-of requires exactly 1 argument".into()),
+        })),
+    ));
+    procedures
+}
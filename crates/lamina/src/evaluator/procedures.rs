@@ -1,263 +1,125 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::rc::Rc;
 
+use crate::number::arithmetic;
 use crate::value::{NumberKind, Value};
 
+use super::equality::equality_procedures;
+use super::predicates::type_predicates;
+
 // Set up all the standard Scheme procedures
 #[allow(dead_code)]
 pub fn setup_initial_procedures(env: &mut HashMap<String, Value>) {
-    // Arithmetic operations
-    env.insert(
-        "+".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            let mut sum = 0.0;
-            for arg in args {
-                if let Value::Number(num) = arg {
-                    sum += num.as_f64();
-                } else {
-                    return Err("+ requires numeric arguments".into());
+    // Arithmetic operations, exact when their arguments are
+    for (name, combine, identity) in [
+        (
+            "+",
+            arithmetic::add as fn(&NumberKind, &NumberKind) -> NumberKind,
+            0,
+        ),
+        ("*", arithmetic::mul, 1),
+    ] {
+        env.insert(
+            name.to_string(),
+            Value::Procedure(Rc::new(move |args: Vec<Value>| {
+                let mut result = NumberKind::Integer(identity);
+                for arg in args {
+                    if let Value::Number(num) = arg {
+                        result = combine(&result, &num);
+                    } else {
+                        return Err(format!("{} requires numeric arguments", name));
+                    }
                 }
-            }
-            Ok(Value::from(sum))
-        })),
-    );
+                Ok(Value::Number(result))
+            })),
+        );
+    }
 
     env.insert(
         "-".to_string(),
         Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.is_empty() {
-                return Err("- requires at least one argument".into());
-            }
-
-            if args.len() == 1 {
-                if let Value::Number(num) = &args[0] {
-                    return Ok(Value::from(-num.as_f64()));
-                } else {
-                    return Err("- requires numeric arguments".into());
-                }
-            }
-
-            let mut _result = 0.0;
-            if let Value::Number(num) = &args[0] {
-                _result = num.as_f64();
-            } else {
-                return Err("- requires numeric arguments".into());
-            }
-
-            for arg in &args[1..] {
+            let mut numbers = Vec::with_capacity(args.len());
+            for arg in &args {
                 if let Value::Number(num) = arg {
-                    _result -= num.as_f64();
+                    numbers.push(num);
                 } else {
                     return Err("- requires numeric arguments".into());
                 }
             }
 
-            Ok(Value::from(_result))
-        })),
-    );
-
-    env.insert(
-        "*".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            let mut product = 1.0;
-            for arg in args {
-                if let Value::Number(num) = arg {
-                    product *= num.as_f64();
-                } else {
-                    return Err("* requires numeric arguments".into());
+            match numbers.split_first() {
+                None => Err("- requires at least one argument".into()),
+                Some((num, [])) => Ok(Value::Number(arithmetic::negate(num))),
+                Some((first, rest)) => {
+                    let mut result = (*first).clone();
+                    for num in rest {
+                        result = arithmetic::sub(&result, num);
+                    }
+                    Ok(Value::Number(result))
                 }
             }
-            Ok(Value::from(product))
         })),
     );
 
     env.insert(
         "/".to_string(),
         Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.is_empty() {
-                return Err("/ requires at least one argument".into());
-            }
-
-            if args.len() == 1 {
-                if let Value::Number(num) = &args[0] {
-                    let value = num.as_f64();
-                    if value == 0.0 {
-                        return Err("Division by zero".into());
-                    }
-                    return Ok(Value::from(1.0 / value));
+            let mut numbers = Vec::with_capacity(args.len());
+            for arg in &args {
+                if let Value::Number(num) = arg {
+                    numbers.push(num);
                 } else {
                     return Err("/ requires numeric arguments".into());
                 }
             }
 
-            let mut _result = 0.0;
-            if let Value::Number(num) = &args[0] {
-                _result = num.as_f64();
-            } else {
-                return Err("/ requires numeric arguments".into());
-            }
-
-            for arg in &args[1..] {
-                if let Value::Number(num) = arg {
-                    let value = num.as_f64();
-                    if value == 0.0 {
-                        return Err("Division by zero".into());
+            match numbers.split_first() {
+                None => Err("/ requires at least one argument".into()),
+                Some((num, [])) => Ok(Value::Number(arithmetic::div(
+                    &NumberKind::Integer(1),
+                    num,
+                )?)),
+                Some((first, rest)) => {
+                    let mut result = (*first).clone();
+                    for num in rest {
+                        result = arithmetic::div(&result, num)?;
                     }
-                    _result /= value;
-                } else {
-                    return Err("/ requires numeric arguments".into());
+                    Ok(Value::Number(result))
                 }
             }
-
-            Ok(Value::from(_result))
         })),
     );
 
-    // Comparison operations
-    env.insert(
-        "=".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.len() < 2 {
-                return Err("= requires at least two arguments".into());
-            }
+    // Comparison operations, exact between exact and inexact numbers
+    for (name, holds) in [
+        ("=", Ordering::is_eq as fn(Ordering) -> bool),
+        ("<", Ordering::is_lt),
+        (">", Ordering::is_gt),
+        ("<=", Ordering::is_le),
+        (">=", Ordering::is_ge),
+    ] {
+        env.insert(
+            name.to_string(),
+            Value::Procedure(Rc::new(move |args: Vec<Value>| {
+                if args.len() < 2 {
+                    return Err(format!("{} requires at least two arguments", name));
+                }
 
-            if let Value::Number(first) = &args[0] {
-                let first_val = first.as_f64();
-                for arg in &args[1..] {
-                    if let Value::Number(num) = arg {
-                        if first_val != num.as_f64() {
+                for pair in args.windows(2) {
+                    if let (Value::Number(a), Value::Number(b)) = (&pair[0], &pair[1]) {
+                        if !arithmetic::compare(a, b).is_some_and(holds) {
                             return Ok(Value::Boolean(false));
                         }
                     } else {
-                        return Err("= requires numeric arguments".into());
-                    }
-                }
-                Ok(Value::Boolean(true))
-            } else {
-                Err("= requires numeric arguments".into())
-            }
-        })),
-    );
-
-    env.insert(
-        "<".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.len() < 2 {
-                return Err("< requires at least two arguments".into());
-            }
-
-            let mut _prev = 0.0;
-            if let Value::Number(num) = &args[0] {
-                _prev = num.as_f64();
-            } else {
-                return Err("< requires numeric arguments".into());
-            }
-
-            for arg in &args[1..] {
-                if let Value::Number(num) = arg {
-                    let curr = num.as_f64();
-                    if _prev >= curr {
-                        return Ok(Value::Boolean(false));
-                    }
-                    _prev = curr;
-                } else {
-                    return Err("< requires numeric arguments".into());
-                }
-            }
-
-            Ok(Value::Boolean(true))
-        })),
-    );
-
-    env.insert(
-        ">".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.len() < 2 {
-                return Err("> requires at least two arguments".into());
-            }
-
-            let mut _prev = 0.0;
-            if let Value::Number(num) = &args[0] {
-                _prev = num.as_f64();
-            } else {
-                return Err("> requires numeric arguments".into());
-            }
-
-            for arg in &args[1..] {
-                if let Value::Number(num) = arg {
-                    let curr = num.as_f64();
-                    if _prev <= curr {
-                        return Ok(Value::Boolean(false));
-                    }
-                    _prev = curr;
-                } else {
-                    return Err("> requires numeric arguments".into());
-                }
-            }
-
-            Ok(Value::Boolean(true))
-        })),
-    );
-
-    env.insert(
-        "<=".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.len() < 2 {
-                return Err("<= requires at least two arguments".into());
-            }
-
-            let mut _prev = 0.0;
-            if let Value::Number(num) = &args[0] {
-                _prev = num.as_f64();
-            } else {
-                return Err("<= requires numeric arguments".into());
-            }
-
-            for arg in &args[1..] {
-                if let Value::Number(num) = arg {
-                    let curr = num.as_f64();
-                    if _prev > curr {
-                        return Ok(Value::Boolean(false));
+                        return Err(format!("{} requires numeric arguments", name));
                     }
-                    _prev = curr;
-                } else {
-                    return Err("<= requires numeric arguments".into());
                 }
-            }
-
-            Ok(Value::Boolean(true))
-        })),
-    );
-
-    env.insert(
-        ">=".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.len() < 2 {
-                return Err(">= requires at least two arguments".into());
-            }
 
-            let mut _prev = 0.0;
-            if let Value::Number(num) = &args[0] {
-                _prev = num.as_f64();
-            } else {
-                return Err(">= requires numeric arguments".into());
-            }
-
-            for arg in &args[1..] {
-                if let Value::Number(num) = arg {
-                    let curr = num.as_f64();
-                    if _prev < curr {
-                        return Ok(Value::Boolean(false));
-                    }
-                    _prev = curr;
-                } else {
-                    return Err(">= requires numeric arguments".into());
-                }
-            }
-
-            Ok(Value::Boolean(true))
-        })),
-    );
+                Ok(Value::Boolean(true))
+            })),
+        );
+    }
 
     // Boolean operations
     env.insert(
@@ -266,10 +128,7 @@ pub fn setup_initial_procedures(env: &mut HashMap<String, Value>) {
             if args.len() != 1 {
                 return Err("not requires exactly one argument".into());
             }
-            match &args[0] {
-                Value::Boolean(false) => Ok(Value::Boolean(true)),
-                _ => Ok(Value::Boolean(false)),
-            }
+            Ok(Value::Boolean(!args[0].is_truthy()))
         })),
     );
 
@@ -280,7 +139,7 @@ pub fn setup_initial_procedures(env: &mut HashMap<String, Value>) {
             if args.len() != 2 {
                 return Err("cons requires exactly 2 arguments".into());
             }
-            Ok(Value::Pair(Rc::new((args[0].clone(), args[1].clone()))))
+            Ok(Value::cons(args[0].clone(), args[1].clone()))
         })),
     );
 
@@ -312,94 +171,13 @@ pub fn setup_initial_procedures(env: &mut HashMap<String, Value>) {
         })),
     );
 
-    // Type predicates
-    env.insert(
-        "pair?".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.len() != 1 {
-                return Err("pair? requires exactly 1 argument".into());
-            }
-            Ok(Value::Boolean(matches!(args[0], Value::Pair(_))))
-        })),
-    );
-
-    env.insert(
-        "null?".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.len() != 1 {
-                return Err("null? requires exactly 1 argument".into());
-            }
-            Ok(Value::Boolean(matches!(args[0], Value::Nil)))
-        })),
-    );
-
-    env.insert(
-        "boolean?".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.len() != 1 {
-                return Err("boolean? requires exactly 1 argument".into());
-            }
-            Ok(Value::Boolean(matches!(args[0], Value::Boolean(_))))
-        })),
-    );
-
-    env.insert(
-        "symbol?".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.len() != 1 {
-                return Err("symbol? requires exactly 1 argument".into());
-            }
-            Ok(Value::Boolean(matches!(args[0], Value::Symbol(_))))
-        })),
-    );
-
-    env.insert(
-        "number?".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.len() != 1 {
-                return Err("number? requires exactly 1 argument".into());
-            }
-            Ok(Value::Boolean(matches!(args[0], Value::Number(_))))
-        })),
-    );
-
-    env.insert(
-        "string?".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.len() != 1 {
-                return Err("string? requires exactly 1 argument".into());
-            }
-            Ok(Value::Boolean(matches!(args[0], Value::String(_))))
-        })),
-    );
-
-    env.insert(
-        "procedure?".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.len() != 1 {
-                return Err("procedure? requires exactly 1 argument".into());
-            }
-            Ok(Value::Boolean(matches!(args[0], Value::Procedure(_))))
-        })),
-    );
-
-    env.insert(
-        "char?".to_string(),
-        Value::Procedure(Rc::new(|args: Vec<Value>| {
-            if args.len() != 1 {
-                return Err("char? requires exactly 1 argument".into());
-            }
-            Ok(Value::Boolean(matches!(args[0], Value::Character(_))))
-        })),
-    );
-
     // List operations
     env.insert(
         "list".to_string(),
         Value::Procedure(Rc::new(|args: Vec<Value>| {
             let mut result = Value::Nil;
             for arg in args.iter().rev() {
-                result = Value::Pair(Rc::new((arg.clone(), result)));
+                result = Value::cons(arg.clone(), result);
             }
             Ok(result)
         })),
@@ -468,11 +246,7 @@ pub fn setup_initial_procedures(env: &mut HashMap<String, Value>) {
             }
 
             if let Value::Number(num) = &args[0] {
-                match num {
-                    NumberKind::Integer(i) => Ok(Value::String(i.to_string())),
-                    NumberKind::Real(r) => Ok(Value::String(r.to_string())),
-                    NumberKind::Rational(n, d) => Ok(Value::String(format!("{}/{}", n, d))),
-                }
+                Ok(Value::String(num.to_string()))
             } else {
                 Err("number->string requires a number argument".into())
             }
@@ -487,13 +261,9 @@ pub fn setup_initial_procedures(env: &mut HashMap<String, Value>) {
             }
 
             if let Value::String(s) = &args[0] {
-                if let Ok(n) = s.parse::<i64>() {
-                    Ok(Value::Number(NumberKind::Integer(n)))
-                } else if let Ok(f) = s.parse::<f64>() {
-                    Ok(Value::Number(NumberKind::Real(f)))
-                } else {
-                    Ok(Value::Boolean(false))
-                }
+                Ok(crate::number::parse_number(s, 10)
+                    .map(Value::Number)
+                    .unwrap_or(Value::Boolean(false)))
             } else {
                 Err("string->number requires a string argument".into())
             }
@@ -586,7 +356,7 @@ pub fn setup_initial_procedures(env: &mut HashMap<String, Value>) {
 
                     // Append elements to the result
                     for element in elements.iter().rev() {
-                        result = Value::Pair(Rc::new((element.clone(), result)));
+                        result = Value::cons(element.clone(), result);
                     }
                 }
 
@@ -691,10 +461,12 @@ pub fn setup_initial_procedures(env: &mut HashMap<String, Value>) {
             // Construct the result list
             let mut result = Value::Nil;
             for res in results.iter().rev() {
-                result = Value::Pair(Rc::new((res.clone(), result)));
+                result = Value::cons(res.clone(), result);
             }
 
             Ok(result)
         })),
     );
+    env.extend(equality_procedures());
+    env.extend(type_predicates());
 }
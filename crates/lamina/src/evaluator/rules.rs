@@ -0,0 +1,314 @@
+// Rules: the (lamina rules) library
+//
+// A rule pairs a condition with an action:
+//
+//   (import (lamina rules))
+//   (define-rule big-order
+//     #:priority 10
+//     #:when (> total 1000)
+//     #:then (set! discount 0.1))
+//
+// `(run-rules)` fires the rules whose conditions hold, one at a time. Each
+// firing can change what the other conditions see, so every condition is tested
+// again before the next rule is chosen. When several rules could fire, the one
+// with the highest priority (default 0) goes first, then the one defined first.
+// A rule fires at most once per run, so a run always ends, and it returns the
+// names of the rules that fired in order; `(rules-fired)` gives the same list
+// until the next run.
+//
+// Each interpreter has its own rules. Defining a rule with the name of an
+// existing one replaces it in place.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::value::{Environment, Library, NumberKind, Value};
+
+use super::apply;
+use super::environment::{create_environment, lookup_variable};
+use super::libraries::bind_library;
+
+/// Keyword introducing a rule's priority
+pub const PRIORITY: &str = "#:priority";
+/// Keyword introducing a rule's condition
+pub const WHEN: &str = "#:when";
+/// Keyword introducing a rule's action
+pub const THEN: &str = "#:then";
+
+/// The procedure `define-rule` calls, bound by importing the library
+const ADD_RULE: &str = "add-rule!";
+const ADD_RULE_USAGE: &str =
+    "add-rule! requires a name, an integer priority, a condition procedure and an action procedure";
+
+struct Rule {
+    name: String,
+    priority: i64,
+    /// Procedures of no arguments
+    condition: Value,
+    action: Value,
+}
+
+#[derive(Default)]
+struct RuleBook {
+    /// In definition order
+    rules: Vec<Rule>,
+    /// The names of the rules the last run fired
+    fired: Vec<String>,
+}
+
+impl RuleBook {
+    fn add(&mut self, rule: Rule) {
+        match self.rules.iter_mut().find(|old| old.name == rule.name) {
+            Some(old) => *old = rule,
+            None => self.rules.push(rule),
+        }
+    }
+
+    /// Fire rules until none that hasn't fired has a condition that holds
+    fn run(book: &Rc<RefCell<RuleBook>>) -> Result<Vec<String>, String> {
+        let mut fired = Vec::new();
+        loop {
+            // Rules can be added or removed by actions, so take the agenda afresh
+            let candidates: Vec<(String, i64, Value, Value)> = book
+                .borrow()
+                .rules
+                .iter()
+                .filter(|rule| !fired.contains(&rule.name))
+                .map(|rule| {
+                    (
+                        rule.name.clone(),
+                        rule.priority,
+                        rule.condition.clone(),
+                        rule.action.clone(),
+                    )
+                })
+                .collect();
+
+            let mut chosen: Option<(String, i64, Value)> = None;
+            for (name, priority, condition, action) in candidates {
+                if chosen
+                    .as_ref()
+                    .is_some_and(|(_, best, _)| *best >= priority)
+                {
+                    continue;
+                }
+                let holds = apply(&condition, Vec::new())
+                    .map_err(|e| format!("In the condition of rule {}: {}", name, e))?;
+                if holds.is_truthy() {
+                    chosen = Some((name, priority, action));
+                }
+            }
+
+            let Some((name, _, action)) = chosen else {
+                break;
+            };
+            apply(&action, Vec::new())
+                .map_err(|e| format!("In the action of rule {}: {}", name, e))?;
+            fired.push(name);
+        }
+
+        book.borrow_mut().fired = fired.clone();
+        Ok(fired)
+    }
+}
+
+fn symbols(names: &[String]) -> Value {
+    names.iter().rev().fold(Value::Nil, |list, name| {
+        Value::cons(Value::Symbol(name.clone()), list)
+    })
+}
+
+/// Rewrite `(define-rule name [#:priority n] #:when condition #:then action ...)`
+/// into a call of `add-rule!`, which must have been imported
+pub fn eval_define_rule(args: &Value, env: &Rc<RefCell<Environment>>) -> Result<Value, Error> {
+    let malformed = || {
+        Error::Runtime(
+            "Malformed define-rule, expected (define-rule name [#:priority n] #:when condition #:then action ...)"
+                .into(),
+        )
+    };
+    let (name, mut rest) = match args {
+        Value::Pair(pair) => match &pair.0 {
+            Value::Symbol(name) => (name.clone(), &pair.1),
+            _ => return Err(malformed()),
+        },
+        _ => return Err(malformed()),
+    };
+
+    let mut priority = Value::Number(NumberKind::Integer(0));
+    let mut condition = None;
+    while let Value::Pair(pair) = rest {
+        let keyword = match &pair.0 {
+            Value::Symbol(keyword) if [PRIORITY, WHEN].contains(&keyword.as_str()) => keyword,
+            Value::Symbol(keyword) if keyword == THEN => break,
+            _ => return Err(malformed()),
+        };
+        let value = match &pair.1 {
+            Value::Pair(value) => value,
+            _ => return Err(Error::Runtime(format!("{} needs a value", keyword))),
+        };
+        if keyword == PRIORITY {
+            priority = value.0.clone();
+        } else {
+            condition = Some(value.0.clone());
+        }
+        rest = &value.1;
+    }
+    let (condition, actions) = match (condition, rest) {
+        (Some(condition), Value::Pair(then)) if matches!(then.1, Value::Pair(_)) => {
+            (condition, then.1.clone())
+        }
+        _ => return Err(malformed()),
+    };
+
+    if lookup_variable(ADD_RULE, env.clone()).is_err() {
+        return Err(Error::Runtime(format!(
+            "define-rule {} needs (import (lamina rules))",
+            name
+        )));
+    }
+
+    let list = |items: Vec<Value>| {
+        items
+            .into_iter()
+            .rev()
+            .fold(Value::Nil, |list, item| Value::cons(item, list))
+    };
+    let thunk = |body: Value| list(vec![Value::Symbol("lambda".into()), Value::Nil, body]);
+    Ok(list(vec![
+        Value::Symbol(ADD_RULE.into()),
+        list(vec![Value::Symbol("quote".into()), Value::Symbol(name)]),
+        priority,
+        thunk(condition),
+        thunk(Value::cons(Value::Symbol("begin".into()), actions)),
+    ]))
+}
+
+fn define(env: &Rc<RefCell<Environment>>, name: &str, procedure: Value) {
+    env.borrow_mut()
+        .bindings
+        .insert(name.to_string(), procedure);
+}
+
+/// Bind the `(lamina rules)` library, with an empty set of rules, in `env`
+pub fn register_rules_library(env: Rc<RefCell<Environment>>) {
+    let rules_env = create_environment(Some(env.clone()));
+    let book = Rc::new(RefCell::new(RuleBook::default()));
+
+    // (add-rule! 'name priority condition action)
+    let rules = book.clone();
+    define(
+        &rules_env,
+        ADD_RULE,
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            let (name, priority, condition, action) = match args.as_slice() {
+                [Value::Symbol(name), Value::Number(NumberKind::Integer(priority)), condition, action]
+                    if condition.is_procedure() && action.is_procedure() =>
+                {
+                    (name, *priority, condition, action)
+                }
+                _ => return Err(ADD_RULE_USAGE.into()),
+            };
+            rules.borrow_mut().add(Rule {
+                name: name.clone(),
+                priority,
+                condition: condition.clone(),
+                action: action.clone(),
+            });
+            Ok(Value::Symbol(name.clone()))
+        })),
+    );
+
+    let rules = book.clone();
+    define(
+        &rules_env,
+        "remove-rule!",
+        Value::Procedure(Rc::new(move |args: Vec<Value>| match args.as_slice() {
+            [Value::Symbol(name)] => {
+                let mut book = rules.borrow_mut();
+                let before = book.rules.len();
+                book.rules.retain(|rule| &rule.name != name);
+                Ok(Value::Boolean(book.rules.len() < before))
+            }
+            _ => Err("remove-rule! requires a rule name".into()),
+        })),
+    );
+
+    let rules = book.clone();
+    define(
+        &rules_env,
+        "clear-rules!",
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            if !args.is_empty() {
+                return Err("clear-rules! takes no arguments".into());
+            }
+            *rules.borrow_mut() = RuleBook::default();
+            Ok(Value::Nil)
+        })),
+    );
+
+    // The names of the rules, in the order they are considered
+    let rules = book.clone();
+    define(
+        &rules_env,
+        "rule-names",
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            if !args.is_empty() {
+                return Err("rule-names takes no arguments".into());
+            }
+            let mut ordered: Vec<(i64, String)> = rules
+                .borrow()
+                .rules
+                .iter()
+                .map(|rule| (rule.priority, rule.name.clone()))
+                .collect();
+            ordered.sort_by_key(|(priority, _)| std::cmp::Reverse(*priority));
+            let names: Vec<String> = ordered.into_iter().map(|(_, name)| name).collect();
+            Ok(symbols(&names))
+        })),
+    );
+
+    let rules = book.clone();
+    define(
+        &rules_env,
+        "run-rules",
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            if !args.is_empty() {
+                return Err("run-rules takes no arguments".into());
+            }
+            RuleBook::run(&rules).map(|fired| symbols(&fired))
+        })),
+    );
+
+    define(
+        &rules_env,
+        "rules-fired",
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            if !args.is_empty() {
+                return Err("rules-fired takes no arguments".into());
+            }
+            Ok(symbols(&book.borrow().fired))
+        })),
+    );
+
+    let library = Library {
+        name: vec!["lamina".to_string(), "rules".to_string()],
+        exports: [
+            ADD_RULE,
+            "remove-rule!",
+            "clear-rules!",
+            "rule-names",
+            "run-rules",
+            "rules-fired",
+        ]
+        .iter()
+        .map(|name| name.to_string())
+        .collect(),
+        imports: vec![],
+        renames: HashMap::new(),
+        environment: rules_env,
+    };
+    bind_library(&env, Rc::new(RefCell::new(library)));
+}
@@ -0,0 +1,161 @@
+// Checkpoints of interpreter state, for backtracking search
+//
+// A snapshot walks everything reachable from an environment and copies each
+// mutable cell it finds: environment frames (the ones closures captured
+// included), vectors, bytevectors, record fields and parameter values. The
+// values inside are shared, not copied, since everything else is immutable.
+// Restoring writes the saved contents back into the same cells, so closures
+// and data the program still holds see the rolled-back state, and anything
+// defined since the snapshot disappears from the frames.
+//
+// Taking a snapshot costs time and memory linear in the reachable state, and
+// restoring one is linear in what was saved. Snapshots can be restored any
+// number of times. State hidden inside native procedures, such as a port's
+// position or a generator's progress, and library registries are not saved.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::evaluator::parameters::Parameter;
+use crate::value::{Bindings, EnumType, Environment, Record, Value};
+
+/// The contents of every mutable cell reachable from an environment
+pub struct Snapshot {
+    frames: Vec<(Rc<RefCell<Environment>>, Frame)>,
+    vectors: Vec<Saved<Vec<Value>>>,
+    bytevectors: Vec<Saved<Vec<u8>>>,
+    records: Vec<(Rc<Record>, HashMap<String, Value>)>,
+    parameters: Vec<(Rc<Parameter>, Value)>,
+}
+
+/// A cell and its saved contents
+type Saved<T> = (Rc<RefCell<T>>, T);
+
+struct Frame {
+    bindings: Bindings,
+    enums: HashMap<String, Rc<EnumType>>,
+}
+
+impl Snapshot {
+    /// Save the state reachable from `env`
+    pub fn take(env: &Rc<RefCell<Environment>>) -> Self {
+        let mut walker = Walker {
+            seen: HashSet::new(),
+            snapshot: Snapshot {
+                frames: Vec::new(),
+                vectors: Vec::new(),
+                bytevectors: Vec::new(),
+                records: Vec::new(),
+                parameters: Vec::new(),
+            },
+        };
+        walker.frame(env);
+        walker.snapshot
+    }
+
+    /// Whether this snapshot was taken from `env`
+    pub fn is_of(&self, env: &Rc<RefCell<Environment>>) -> bool {
+        self.frames
+            .first()
+            .is_some_and(|(root, _)| Rc::ptr_eq(root, env))
+    }
+
+    /// Put every saved cell back to its contents at the time of the snapshot
+    pub fn restore(&self) {
+        for (env, frame) in &self.frames {
+            let mut env = env.borrow_mut();
+            env.bindings = frame.bindings.clone();
+            env.enums = frame.enums.clone();
+        }
+        for (vector, items) in &self.vectors {
+            *vector.borrow_mut() = items.clone();
+        }
+        for (bytes, saved) in &self.bytevectors {
+            *bytes.borrow_mut() = saved.clone();
+        }
+        for (record, values) in &self.records {
+            *record.values.borrow_mut() = values.clone();
+        }
+        for (parameter, value) in &self.parameters {
+            parameter.replace(value.clone());
+        }
+    }
+}
+
+struct Walker {
+    /// Addresses of the cells already saved, so shared and cyclic structure is
+    /// walked once
+    seen: HashSet<*const ()>,
+    snapshot: Snapshot,
+}
+
+impl Walker {
+    fn first_visit<T: ?Sized>(&mut self, rc: &Rc<T>) -> bool {
+        self.seen.insert(Rc::as_ptr(rc) as *const ())
+    }
+
+    fn frame(&mut self, env: &Rc<RefCell<Environment>>) {
+        if !self.first_visit(env) {
+            return;
+        }
+        let (frame, parent) = {
+            let env = env.borrow();
+            let frame = Frame {
+                bindings: env.bindings.clone(),
+                enums: env.enums.clone(),
+            };
+            (frame, env.parent.clone())
+        };
+        let values: Vec<Value> = frame.bindings.values().cloned().collect();
+        self.snapshot.frames.push((env.clone(), frame));
+
+        if let Some(parent) = parent {
+            self.frame(&parent);
+        }
+        for value in &values {
+            self.value(value);
+        }
+    }
+
+    fn value(&mut self, value: &Value) {
+        // Walk list spines iteratively, so long lists do not exhaust the stack
+        let mut current = value;
+        while let Value::Pair(pair) = current {
+            if !self.first_visit(pair) {
+                return;
+            }
+            self.value(&pair.0);
+            current = &pair.1;
+        }
+
+        match current {
+            Value::Vector(vector) if self.first_visit(vector) => {
+                let items = vector.borrow().clone();
+                for item in &items {
+                    self.value(item);
+                }
+                self.snapshot.vectors.push((vector.clone(), items));
+            }
+            Value::Bytevector(bytes) if self.first_visit(bytes) => {
+                let saved = bytes.borrow().clone();
+                self.snapshot.bytevectors.push((bytes.clone(), saved));
+            }
+            Value::Record(record) if self.first_visit(record) => {
+                let values = record.values.borrow().clone();
+                for value in values.values() {
+                    self.value(value);
+                }
+                self.snapshot.records.push((record.clone(), values));
+            }
+            Value::Parameter(parameter) if self.first_visit(parameter) => {
+                let value = parameter.get();
+                self.value(&value);
+                self.snapshot.parameters.push((parameter.clone(), value));
+            }
+            Value::Lambda(lambda) => self.frame(&lambda.env),
+            Value::Environment(env) => self.frame(env),
+            _ => {}
+        }
+    }
+}
@@ -2,10 +2,17 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
 
-use crate::error::Error;
-use crate::value::{Environment, Record, RecordType, Value};
-
+use crate::edition::Edition;
+use crate::error::{arity_error, Arity, Error};
+use crate::lexer;
+use crate::parser;
+use crate::value::{Cons, EnumType, Environment, Lambda, NumberKind, Record, RecordType, Value};
+
+use super::conditions::file_error;
+use super::environment::check_core_rebinding;
 use super::eval_with_env;
+use super::libraries;
+use super::machine::lambda_body;
 
 // Add this function that wasn't in our snapshot
 pub fn register_special_forms(env: Rc<RefCell<Environment>>) {
@@ -19,12 +26,19 @@ pub fn register_special_forms(env: Rc<RefCell<Environment>>) {
     env.borrow_mut()
         .bindings
         .insert("define".to_string(), Value::Symbol("define".to_string()));
+    env.borrow_mut().bindings.insert(
+        "define-internal".to_string(),
+        Value::Symbol("define-internal".to_string()),
+    );
     env.borrow_mut()
         .bindings
         .insert("set!".to_string(), Value::Symbol("set!".to_string()));
     env.borrow_mut()
         .bindings
         .insert("cond".to_string(), Value::Symbol("cond".to_string()));
+    env.borrow_mut()
+        .bindings
+        .insert("case".to_string(), Value::Symbol("case".to_string()));
     env.borrow_mut()
         .bindings
         .insert("let".to_string(), Value::Symbol("let".to_string()));
@@ -34,6 +48,9 @@ pub fn register_special_forms(env: Rc<RefCell<Environment>>) {
     env.borrow_mut()
         .bindings
         .insert("letrec".to_string(), Value::Symbol("letrec".to_string()));
+    env.borrow_mut()
+        .bindings
+        .insert("do".to_string(), Value::Symbol("do".to_string()));
     env.borrow_mut().bindings.insert(
         "with-exception-handler".to_string(),
         Value::Symbol("with-exception-handler".to_string()),
@@ -47,10 +64,51 @@ pub fn register_special_forms(env: Rc<RefCell<Environment>>) {
     env.borrow_mut()
         .bindings
         .insert("guard".to_string(), Value::Symbol("guard".to_string()));
+    for name in [
+        "define-syntax",
+        "let-syntax",
+        "letrec-syntax",
+        "raise-continuable",
+        "reset",
+        "shift",
+        "generator",
+        "yield",
+        "parameterize",
+        "when",
+        "unless",
+        "and",
+        "or",
+        "call-with-current-continuation",
+        "call/cc",
+        "dynamic-wind",
+        "call-with-values",
+        "quasiquote",
+        "unquote",
+        "unquote-splicing",
+    ] {
+        env.borrow_mut()
+            .bindings
+            .insert(name.to_string(), Value::Symbol(name.to_string()));
+    }
     env.borrow_mut().bindings.insert(
         "define-record-type".to_string(),
         Value::Symbol("define-record-type".to_string()),
     );
+    env.borrow_mut().bindings.insert(
+        "define-enum".to_string(),
+        Value::Symbol("define-enum".to_string()),
+    );
+    env.borrow_mut().bindings.insert(
+        "define-with-contract".to_string(),
+        Value::Symbol("define-with-contract".to_string()),
+    );
+    env.borrow_mut().bindings.insert(
+        "define-rule".to_string(),
+        Value::Symbol("define-rule".to_string()),
+    );
+    env.borrow_mut()
+        .bindings
+        .insert("assert".to_string(), Value::Symbol("assert".to_string()));
     env.borrow_mut()
         .bindings
         .insert("begin".to_string(), Value::Symbol("begin".to_string()));
@@ -61,89 +119,21 @@ pub fn register_special_forms(env: Rc<RefCell<Environment>>) {
         "define-library".to_string(),
         Value::Symbol("define-library".to_string()),
     );
-}
-
-// Lambda special form
-pub fn eval_lambda(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
-    if let Value::Pair(pair) = args {
-        let params = pair.0.clone();
-
-        // Get the body expression (it's the car of pair.1)
-        let body = if let Value::Pair(body_pair) = &pair.1 {
-            body_pair.0.clone()
-        } else {
-            // This should not happen with well-formed expressions
-            return Err(Error::Runtime("Malformed lambda".into()));
-        };
-
-        let env_clone = env.clone();
-        Ok(Value::Procedure(Rc::new(move |args: Vec<Value>| {
-            let new_env = Rc::new(RefCell::new(Environment {
-                parent: Some(env_clone.clone()),
-                bindings: HashMap::new(),
-            }));
-
-            // Bind parameters
-            let mut param_list = params.clone();
-            let mut arg_idx = 0;
-            while let Value::Pair(param_pair) = param_list {
-                if let Value::Symbol(name) = &param_pair.0 {
-                    if arg_idx >= args.len() {
-                        return Err(format!(
-                            "Too few arguments, expected {} got {}",
-                            arg_idx + 1,
-                            args.len()
-                        ));
-                    }
-                    new_env
-                        .borrow_mut()
-                        .bindings
-                        .insert(name.clone(), args[arg_idx].clone());
-                }
-                param_list = param_pair.1.clone();
-                arg_idx += 1;
-            }
-            if let Value::Nil = param_list {
-                // This is fine, we've bound all parameters
-            } else if let Value::Symbol(name) = param_list {
-                // This is a rest parameter
-                new_env.borrow_mut().bindings.insert(name, Value::Nil);
-            } else {
-                return Err("Invalid parameter list".into());
-            }
-
-            // Evaluate body
-            match eval_with_env(body.clone(), new_env) {
-                Ok(result) => Ok(result),
-                Err(e) => Err(e.to_string()),
-            }
-        })))
-    } else {
-        Err(Error::Runtime("Invalid lambda form".into()))
-    }
-}
-
-// If special form
-pub fn eval_if(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
-    if let Value::Pair(test_pair) = args {
-        let test = eval_with_env(test_pair.0.clone(), env.clone())?;
-        if let Value::Pair(conseq_pair) = &test_pair.1 {
-            match test {
-                Value::Boolean(false) => {
-                    if let Value::Pair(alt_pair) = &conseq_pair.1 {
-                        eval_with_env(alt_pair.0.clone(), env)
-                    } else {
-                        Ok(Value::Nil)
-                    }
-                }
-                _ => eval_with_env(conseq_pair.0.clone(), env),
-            }
-        } else {
-            Err(Error::Runtime("Malformed if expression".into()))
-        }
-    } else {
-        Err(Error::Runtime("Malformed if expression".into()))
-    }
+    env.borrow_mut()
+        .bindings
+        .insert("import".to_string(), Value::Symbol("import".to_string()));
+    env.borrow_mut().bindings.insert(
+        "define-test".to_string(),
+        Value::Symbol("define-test".to_string()),
+    );
+    env.borrow_mut().bindings.insert(
+        "define-property".to_string(),
+        Value::Symbol("define-property".to_string()),
+    );
+    env.borrow_mut().bindings.insert(
+        "lamina-edition".to_string(),
+        Value::Symbol("lamina-edition".to_string()),
+    );
 }
 
 // Define special form
@@ -162,48 +152,24 @@ pub fn eval_define(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value,
                 // Evaluate the value expression
                 let value = eval_with_env(value_expr, env.clone())?;
 
+                check_core_rebinding(name, &env.borrow())?;
                 env.borrow_mut().bindings.insert(name.clone(), value);
                 Ok(Value::Nil)
             }
             Value::Pair(proc_pair) => {
                 // For function definitions like (define (func x) body)
                 if let Value::Symbol(name) = &proc_pair.0 {
-                    let params = proc_pair.1.clone();
-                    let body = pair.1.clone();
-                    let env_clone = env.clone();
-                    let proc = Value::Procedure(Rc::new(move |args: Vec<Value>| {
-                        let new_env = Rc::new(RefCell::new(Environment {
-                            parent: Some(env_clone.clone()),
-                            bindings: HashMap::new(),
-                        }));
-
-                        // Bind parameters
-                        let mut param_list = params.clone();
-                        let mut arg_idx = 0;
-                        while let Value::Pair(param_pair) = param_list {
-                            if let Value::Symbol(param_name) = &param_pair.0 {
-                                if arg_idx >= args.len() {
-                                    return Err(format!(
-                                        "Too few arguments, expected {} got {}",
-                                        arg_idx + 1,
-                                        args.len()
-                                    ));
-                                }
-                                new_env
-                                    .borrow_mut()
-                                    .bindings
-                                    .insert(param_name.clone(), args[arg_idx].clone());
-                            }
-                            param_list = param_pair.1.clone();
-                            arg_idx += 1;
-                        }
-
-                        // Evaluate body
-                        match eval_with_env(body.clone(), new_env) {
-                            Ok(result) => Ok(result),
-                            Err(e) => Err(e.to_string()),
-                        }
-                    }));
+                    let body = match &pair.1 {
+                        Value::Pair(body) => lambda_body(body, &env),
+                        _ => return Err(Error::Runtime("Malformed define".into())),
+                    };
+                    let proc = Value::Lambda(Rc::new(Lambda::named(
+                        name,
+                        proc_pair.1.clone(),
+                        body,
+                        env.clone(),
+                    )));
+                    check_core_rebinding(name, &env.borrow())?;
                     env.borrow_mut().bindings.insert(name.clone(), proc);
                     Ok(Value::Nil)
                 } else {
@@ -221,426 +187,56 @@ pub fn eval_define(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value,
     }
 }
 
-// Set! special form
-pub fn eval_set(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
-    if let Value::Pair(pair) = args {
-        if let Value::Symbol(name) = &pair.0 {
-            // Get the actual value expression (it's the car of pair.1)
-            let value_expr = if let Value::Pair(val_pair) = &pair.1 {
-                val_pair.0.clone()
-            } else {
-                // This should not happen with well-formed expressions
-                return Err(Error::Runtime("Malformed set!".into()));
-            };
-
-            // Evaluate the value expression
-            let value = eval_with_env(value_expr, env.clone())?;
-
-            let mut current = env;
-            let mut target_env = None;
-
-            // First, find the environment that contains the variable
-            while target_env.is_none() {
-                let env_ref = current.borrow();
-                if env_ref.bindings.contains_key(name) {
-                    target_env = Some(current.clone());
-                } else if let Some(parent) = &env_ref.parent {
-                    let next = parent.clone();
-                    drop(env_ref); // Explicitly drop the borrow before reassigning
-                    current = next;
-                } else {
-                    return Err(Error::Runtime(format!("Undefined variable: {}", name)));
-                }
-            }
-
-            // Then, update the variable in the found environment
-            if let Some(env) = target_env {
-                env.borrow_mut().bindings.insert(name.clone(), value);
-                Ok(Value::Nil)
-            } else {
-                Err(Error::Runtime(format!("Undefined variable: {}", name)))
-            }
-        } else {
-            Err(Error::Runtime(
-                "First argument to set! must be a symbol".into(),
-            ))
+// (lamina-edition year): select the language edition for the code after it
+pub fn eval_lamina_edition(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
+    let edition = match &args {
+        Value::Pair(pair) if matches!(pair.1, Value::Nil) => match &pair.0 {
+            Value::Number(NumberKind::Integer(year)) => u32::try_from(*year)
+                .ok()
+                .and_then(Edition::from_year)
+                .ok_or_else(|| Error::Runtime(format!("Unknown edition: {}", year)))?,
+            _ => return Err(Error::Runtime("lamina-edition takes a year".into())),
+        },
+        _ => return Err(Error::Runtime("lamina-edition takes a year".into())),
+    };
+
+    // The edition belongs to the root environment
+    let mut root = env;
+    loop {
+        let parent = root.borrow().parent.clone();
+        match parent {
+            Some(parent) => root = parent,
+            None => break,
         }
-    } else {
-        Err(Error::Runtime("Malformed set!".into()))
-    }
-}
-
-// Cond special form
-pub fn eval_cond(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
-    let mut current = args;
-    while let Value::Pair(pair) = current {
-        let clause = &pair.0;
-        if let Value::Pair(clause_pair) = clause {
-            let test = eval_with_env(clause_pair.0.clone(), env.clone())?;
-            match test {
-                Value::Boolean(false) => {
-                    current = pair.1.clone();
-                    continue;
-                }
-                _ => {
-                    if let Value::Pair(conseq_pair) = &clause_pair.1 {
-                        return eval_with_env(conseq_pair.0.clone(), env);
-                    } else {
-                        return Ok(test);
-                    }
-                }
-            }
-        } else if let Value::Symbol(s) = clause {
-            if s == "else" {
-                if let Value::Pair(else_pair) = &pair.1 {
-                    return eval_with_env(else_pair.0.clone(), env);
-                } else {
-                    return Ok(Value::Nil);
-                }
-            }
-        }
-        current = pair.1.clone();
     }
+    root.borrow_mut().edition = edition;
     Ok(Value::Nil)
 }
 
-// Let special form
-pub fn eval_let(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
-    if let Value::Pair(pair) = args {
-        let bindings = pair.0.clone();
-
-        // Get the body expression (it's the car of pair.1)
-        let body = if let Value::Pair(body_pair) = &pair.1 {
-            body_pair.0.clone()
-        } else {
-            // This should not happen with well-formed expressions
-            return Err(Error::Runtime("Malformed let".into()));
+// (include "file" ...) and (include-ci "file" ...): the forms of the files, in
+// order, to evaluate in place of the form. `include-ci` reads them with
+// symbols case-folded, as if they started with `#!fold-case`.
+pub fn read_included(args: &Value, fold_case: bool) -> Result<Value, Error> {
+    let mut forms = Vec::new();
+    let mut rest = args;
+    while let Value::Pair(pair) = rest {
+        let Value::String(path) = &pair.0 else {
+            return Err(Error::Runtime(format!(
+                "include takes file names, got {}",
+                pair.0
+            )));
         };
-
-        // Create new environment
-        let new_env = Rc::new(RefCell::new(Environment {
-            parent: Some(env.clone()),
-            bindings: HashMap::new(),
-        }));
-
-        // Evaluate bindings
-        let mut current = bindings;
-        while let Value::Pair(binding_pair) = current {
-            if let Value::Pair(var_pair) = &binding_pair.0 {
-                if let Value::Symbol(name) = &var_pair.0 {
-                    // Get the value expression (it's the car of var_pair.1)
-                    let value_expr = if let Value::Pair(val_pair) = &var_pair.1 {
-                        val_pair.0.clone()
-                    } else {
-                        // This should not happen with well-formed expressions
-                        return Err(Error::Runtime("Malformed binding in let".into()));
-                    };
-
-                    let value = eval_with_env(value_expr, env.clone())?;
-                    new_env.borrow_mut().bindings.insert(name.clone(), value);
-                }
-            }
-            current = binding_pair.1.clone();
-        }
-
-        // Evaluate body
-        eval_with_env(body, new_env)
-    } else {
-        Err(Error::Runtime("Malformed let".into()))
-    }
-}
-
-// Let* special form
-pub fn eval_let_star(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
-    if let Value::Pair(pair) = args {
-        let bindings = pair.0.clone();
-
-        // Get the body expression (it's the car of pair.1)
-        let body = if let Value::Pair(body_pair) = &pair.1 {
-            body_pair.0.clone()
-        } else {
-            // This should not happen with well-formed expressions
-            return Err(Error::Runtime("Malformed let*".into()));
-        };
-
-        // Create new environment
-        let mut current_env = env.clone();
-
-        // Evaluate bindings sequentially
-        let mut current = bindings;
-        while let Value::Pair(binding_pair) = current {
-            if let Value::Pair(var_pair) = &binding_pair.0 {
-                if let Value::Symbol(name) = &var_pair.0 {
-                    // Get the value expression (it's the car of var_pair.1)
-                    let value_expr = if let Value::Pair(val_pair) = &var_pair.1 {
-                        val_pair.0.clone()
-                    } else {
-                        // This should not happen with well-formed expressions
-                        return Err(Error::Runtime("Malformed binding in let*".into()));
-                    };
-
-                    let value = eval_with_env(value_expr, current_env.clone())?;
-
-                    let new_env = Rc::new(RefCell::new(Environment {
-                        parent: Some(current_env.clone()),
-                        bindings: HashMap::new(),
-                    }));
-                    new_env.borrow_mut().bindings.insert(name.clone(), value);
-                    current_env = new_env;
-                }
-            }
-            current = binding_pair.1.clone();
-        }
-
-        // Evaluate body
-        eval_with_env(body, current_env)
-    } else {
-        Err(Error::Runtime("Malformed let*".into()))
-    }
-}
-
-// Letrec special form
-pub fn eval_letrec(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
-    if let Value::Pair(pair) = args {
-        let bindings = pair.0.clone();
-
-        // Get the body expression (it's the car of pair.1)
-        let body = if let Value::Pair(body_pair) = &pair.1 {
-            body_pair.0.clone()
-        } else {
-            // This should not happen with well-formed expressions
-            return Err(Error::Runtime("Malformed letrec".into()));
-        };
-
-        // Create new environment
-        let new_env = Rc::new(RefCell::new(Environment {
-            parent: Some(env.clone()),
-            bindings: HashMap::new(),
-        }));
-
-        // First pass: create bindings with undefined values
-        let mut current = bindings.clone();
-        while let Value::Pair(binding_pair) = current {
-            if let Value::Pair(var_pair) = &binding_pair.0 {
-                if let Value::Symbol(name) = &var_pair.0 {
-                    new_env
-                        .borrow_mut()
-                        .bindings
-                        .insert(name.clone(), Value::Nil);
-                }
-            }
-            current = binding_pair.1.clone();
-        }
-
-        // Second pass: evaluate bindings in the new environment
-        let mut current = bindings;
-        while let Value::Pair(binding_pair) = current {
-            if let Value::Pair(var_pair) = &binding_pair.0 {
-                if let Value::Symbol(name) = &var_pair.0 {
-                    // Get the value expression (it's the car of var_pair.1)
-                    let value_expr = if let Value::Pair(val_pair) = &var_pair.1 {
-                        val_pair.0.clone()
-                    } else {
-                        // This should not happen with well-formed expressions
-                        return Err(Error::Runtime("Malformed binding in letrec".into()));
-                    };
-
-                    let value = eval_with_env(value_expr, new_env.clone())?;
-                    new_env.borrow_mut().bindings.insert(name.clone(), value);
-                }
-            }
-            current = binding_pair.1.clone();
-        }
-
-        // Evaluate body
-        eval_with_env(body, new_env)
-    } else {
-        Err(Error::Runtime("Malformed letrec".into()))
-    }
-}
-
-// Exception handling implementation
-pub fn eval_with_exception_handler(
-    args: Value,
-    env: Rc<RefCell<Environment>>,
-) -> Result<Value, Error> {
-    if let Value::Pair(handler_pair) = args {
-        let handler = eval_with_env(handler_pair.0.clone(), env.clone())?;
-
-        if let Value::Pair(ref thunk_pair) = handler_pair.1 {
-            let thunk = eval_with_env(thunk_pair.0.clone(), env.clone())?;
-
-            match thunk {
-                Value::Procedure(f) => {
-                    // Try to call the thunk procedure with no arguments
-                    match f(vec![]) {
-                        Ok(result) => Ok(result),
-                        Err(e) => {
-                            // If the thunk raises an exception, call the handler with the exception object
-                            if let Value::Procedure(h) = handler {
-                                // Create a simple exception value from the error message
-                                let exception = Value::Symbol(e);
-                                match h(vec![exception]) {
-                                    Ok(result) => Ok(result),
-                                    Err(new_e) => Err(Error::Runtime(new_e)),
-                                }
-                            } else {
-                                Err(Error::Runtime("Handler must be a procedure".into()))
-                            }
-                        }
-                    }
-                }
-                _ => Err(Error::Runtime("Thunk must be a procedure".into())),
-            }
-        } else {
-            Err(Error::Runtime(
-                "with-exception-handler requires a handler and a thunk".into(),
-            ))
-        }
-    } else {
-        Err(Error::Runtime(
-            "with-exception-handler requires a handler and a thunk".into(),
-        ))
-    }
-}
-
-pub fn eval_raise(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
-    if let Value::Pair(pair) = args {
-        // Evaluate the argument
-        let exception = eval_with_env(pair.0.clone(), env)?;
-
-        // Raise the exception
-        Err(Error::Runtime(format!("Exception: {:?}", exception)))
-    } else {
-        Err(Error::Runtime("raise requires an argument".into()))
-    }
-}
-
-pub fn eval_error(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
-    if let Value::Pair(pair) = args {
-        // Evaluate the arguments
-        let message = eval_with_env(pair.0.clone(), env.clone())?;
-
-        // Format the error message
-        let error_msg = match message {
-            Value::String(s) => s,
-            _ => format!("{:?}", message),
-        };
-
-        // Raise the error
-        Err(Error::Runtime(format!("Error: {}", error_msg)))
-    } else {
-        Err(Error::Runtime("error requires an argument".into()))
-    }
-}
-
-pub fn eval_guard(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
-    if let Value::Pair(var_clauses_pair) = args {
-        // Extract the variable and clauses
-        if let Value::Pair(var_pair) = &var_clauses_pair.0 {
-            let exception_var = match &var_pair.0 {
-                Value::Symbol(s) => s.clone(),
-                _ => {
-                    return Err(Error::Runtime("Guard variable must be a symbol".into()));
-                }
-            };
-
-            // Get the clauses
-            let clauses = var_pair.1.clone();
-
-            // Get the body
-            if let Value::Pair(body_pair) = &var_clauses_pair.1 {
-                let body = body_pair.0.clone();
-
-                // Try to evaluate the body
-                match eval_with_env(body, env.clone()) {
-                    Ok(result) => Ok(result),
-                    Err(error) => {
-                        // An exception occurred, create a new environment with the exception bound to the variable
-                        let guard_env = Rc::new(RefCell::new(Environment {
-                            parent: Some(env.clone()),
-                            bindings: HashMap::new(),
-                        }));
-
-                        // Create an exception value from the error
-                        let exception_value = match error {
-                            Error::Runtime(e) => {
-                                if e.starts_with("Exception: ") {
-                                    // This is from a 'raise' call, extract the actual value
-                                    let symbol_content = e.trim_start_matches("Exception: ");
-                                    Value::Symbol(symbol_content.to_string())
-                                } else {
-                                    Value::Symbol(e)
-                                }
-                            }
-                            _ => Value::Symbol(format!("{:?}", error)),
-                        };
-
-                        // Bind the exception to the variable
-                        guard_env
-                            .borrow_mut()
-                            .bindings
-                            .insert(exception_var, exception_value.clone());
-
-                        // Evaluate the clauses
-                        let mut current = clauses;
-                        while let Value::Pair(clause_pair) = current {
-                            let clause = &clause_pair.0;
-
-                            if let Value::Pair(test_pair) = clause {
-                                // Evaluate the test
-                                let test = eval_with_env(test_pair.0.clone(), guard_env.clone())?;
-
-                                match test {
-                                    Value::Boolean(true) => {
-                                        // Test passed, evaluate the expression
-                                        if let Value::Pair(expr_pair) = &test_pair.1 {
-                                            return eval_with_env(expr_pair.0.clone(), guard_env);
-                                        }
-                                    }
-                                    Value::Boolean(false) => {
-                                        // Test failed, try next clause
-                                        current = clause_pair.1.clone();
-                                        continue;
-                                    }
-                                    _ => {
-                                        return Err(Error::Runtime(
-                                            "Guard test must evaluate to a boolean".into(),
-                                        ));
-                                    }
-                                }
-                            } else if let Value::Symbol(s) = clause {
-                                if s == "else" {
-                                    // Else clause, always matches
-                                    if let Value::Pair(expr_pair) = &clause_pair.1 {
-                                        if let Value::Pair(expr) = &expr_pair.0 {
-                                            return eval_with_env(expr.0.clone(), guard_env);
-                                        } else {
-                                            return eval_with_env(expr_pair.0.clone(), guard_env);
-                                        }
-                                    }
-                                }
-                            }
-
-                            current = clause_pair.1.clone();
-                        }
-
-                        // No matching clause, re-raise the exception
-                        Err(Error::Runtime(format!(
-                            "Unhandled exception: {:?}",
-                            exception_value
-                        )))
-                    }
-                }
-            } else {
-                Err(Error::Runtime("Malformed guard expression".into()))
-            }
-        } else {
-            Err(Error::Runtime("Malformed guard expression".into()))
-        }
-    } else {
-        Err(Error::Runtime("Malformed guard expression".into()))
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| Error::Runtime(file_error(format_args!("Cannot open {}: {}", path, e))))?;
+        let (tokens, _) = lexer::lex_with_case(&source, fold_case)?;
+        let tokens: Vec<_> = tokens.into_iter().map(|(token, _)| token).collect();
+        forms.extend(parser::parse_all(&tokens)?);
+        rest = &pair.1;
     }
+    Ok(forms
+        .into_iter()
+        .rev()
+        .fold(Value::Nil, |list, form| Value::cons(form, list)))
 }
 
 // Implement define-record-type form
@@ -760,11 +356,14 @@ pub fn eval_define_record_type(args: Value, env: Rc<RefCell<Environment>>) -> Re
                 let constructor_clone = constructor.clone();
                 let constructor_proc = Value::Procedure(Rc::new(move |args: Vec<Value>| {
                     if args.len() != constructor_fields_clone.len() {
-                        return Err(format!(
-                            "Constructor {} requires {} arguments, got {}",
-                            constructor_clone,
-                            constructor_fields_clone.len(),
-                            args.len()
+                        let signature = std::iter::once(constructor_clone.as_str())
+                            .chain(constructor_fields_clone.iter().map(String::as_str))
+                            .collect::<Vec<_>>()
+                            .join(" ");
+                        return Err(arity_error(
+                            &format!("({})", signature),
+                            Arity::Exactly(constructor_fields_clone.len()),
+                            args.len(),
                         ));
                     }
 
@@ -794,9 +393,10 @@ pub fn eval_define_record_type(args: Value, env: Rc<RefCell<Environment>>) -> Re
                 let predicate_clone = predicate.clone();
                 let predicate_proc = Value::Procedure(Rc::new(move |args: Vec<Value>| {
                     if args.len() != 1 {
-                        return Err(format!(
-                            "Predicate {} requires exactly 1 argument",
-                            predicate_clone
+                        return Err(arity_error(
+                            &format!("({} obj)", predicate_clone),
+                            Arity::Exactly(1),
+                            args.len(),
                         ));
                     }
 
@@ -820,9 +420,10 @@ pub fn eval_define_record_type(args: Value, env: Rc<RefCell<Environment>>) -> Re
                     // Create accessor
                     let accessor_proc = Value::Procedure(Rc::new(move |args: Vec<Value>| {
                         if args.len() != 1 {
-                            return Err(format!(
-                                "Accessor {} requires exactly 1 argument",
-                                accessor_name_clone
+                            return Err(arity_error(
+                                &format!("({} record)", accessor_name_clone),
+                                Arity::Exactly(1),
+                                args.len(),
                             ));
                         }
 
@@ -841,7 +442,7 @@ pub fn eval_define_record_type(args: Value, env: Rc<RefCell<Environment>>) -> Re
                                     Err(format!("Field {} not found in record", field_name_clone))
                                 }
                             }
-                            _ => Err(format!("Expected record, got {:?}", args[0])),
+                            _ => Err(format!("Expected record, got {}", args[0])),
                         }
                     }));
 
@@ -855,9 +456,10 @@ pub fn eval_define_record_type(args: Value, env: Rc<RefCell<Environment>>) -> Re
 
                         let mutator_proc = Value::Procedure(Rc::new(move |args: Vec<Value>| {
                             if args.len() != 2 {
-                                return Err(format!(
-                                    "Mutator {} requires exactly 2 arguments",
-                                    mutator_clone
+                                return Err(arity_error(
+                                    &format!("({} record value)", mutator_clone),
+                                    Arity::Exactly(2),
+                                    args.len(),
                                 ));
                             }
 
@@ -889,7 +491,7 @@ pub fn eval_define_record_type(args: Value, env: Rc<RefCell<Environment>>) -> Re
                                         .insert(field_name_clone.clone(), args[1].clone());
                                     Ok(Value::Nil)
                                 }
-                                _ => Err(format!("Expected record, got {:?}", args[0])),
+                                _ => Err(format!("Expected record, got {}", args[0])),
                             }
                         }));
 
@@ -926,6 +528,173 @@ pub fn eval_define_record_type(args: Value, env: Rc<RefCell<Environment>>) -> Re
     }
 }
 
+/// Read the name and variants of `(define-enum Name (Variant ...))` from the
+/// arguments of the form
+pub fn parse_define_enum(args: &Value) -> Result<EnumType, Error> {
+    let malformed = || {
+        Error::Runtime("Malformed define-enum, expected (define-enum Name (Variant ...))".into())
+    };
+    let (name, variants) = match args {
+        Value::Pair(pair) => match (&pair.0, &pair.1) {
+            (Value::Symbol(name), Value::Pair(rest)) if matches!(rest.1, Value::Nil) => {
+                (name.clone(), &rest.0)
+            }
+            _ => return Err(malformed()),
+        },
+        _ => return Err(malformed()),
+    };
+
+    let mut names = Vec::new();
+    let mut current = variants;
+    while let Value::Pair(pair) = current {
+        match &pair.0 {
+            Value::Symbol(variant) if names.contains(variant) => {
+                return Err(Error::Runtime(format!(
+                    "Variant {} appears twice in {}",
+                    variant, name
+                )))
+            }
+            Value::Symbol(variant) => names.push(variant.clone()),
+            _ => {
+                return Err(Error::Runtime(format!(
+                    "Variants of {} must be symbols",
+                    name
+                )))
+            }
+        }
+        current = &pair.1;
+    }
+    if names.is_empty() || !matches!(current, Value::Nil) {
+        return Err(malformed());
+    }
+
+    Ok(EnumType {
+        name,
+        variants: names,
+    })
+}
+
+// (define-enum Name (Variant ...)): binds each variant to its own symbol, along
+// with `Name?`, `Name->integer` and `integer->Name`
+pub fn eval_define_enum(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
+    let enum_type = Rc::new(parse_define_enum(&args)?);
+    let name = enum_type.name.clone();
+
+    let predicate = {
+        let enum_type = enum_type.clone();
+        Value::Procedure(Rc::new(move |args: Vec<Value>| match args.as_slice() {
+            [Value::Symbol(variant)] => Ok(Value::Boolean(enum_type.index(variant).is_some())),
+            [_] => Ok(Value::Boolean(false)),
+            _ => Err(arity_error(
+                &format!("({}? obj)", enum_type.name),
+                Arity::Exactly(1),
+                args.len(),
+            )),
+        }))
+    };
+
+    let to_integer = {
+        let enum_type = enum_type.clone();
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            let index = match args.as_slice() {
+                [Value::Symbol(variant)] => enum_type.index(variant),
+                [_] => None,
+                _ => {
+                    return Err(arity_error(
+                        &format!("({}->integer variant)", enum_type.name),
+                        Arity::Exactly(1),
+                        args.len(),
+                    ))
+                }
+            };
+            match index {
+                Some(index) => Ok(Value::from(index as i64)),
+                None => Err(format!(
+                    "{}->integer: not a {}: {}",
+                    enum_type.name, enum_type.name, args[0]
+                )),
+            }
+        }))
+    };
+
+    let from_integer = {
+        let enum_type = enum_type.clone();
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            let index = match args.as_slice() {
+                [n] => libraries::number_to_i64(n)?,
+                _ => {
+                    return Err(arity_error(
+                        &format!("(integer->{} n)", enum_type.name),
+                        Arity::Exactly(1),
+                        args.len(),
+                    ))
+                }
+            };
+            usize::try_from(index)
+                .ok()
+                .and_then(|index| enum_type.variants.get(index))
+                .map(|variant| Value::Symbol(variant.clone()))
+                .ok_or_else(|| format!("integer->{}: no variant {}", enum_type.name, index))
+        }))
+    };
+
+    let mut env = env.borrow_mut();
+    for variant in &enum_type.variants {
+        env.bindings
+            .insert(variant.clone(), Value::Symbol(variant.clone()));
+        env.enums.insert(variant.clone(), enum_type.clone());
+    }
+    env.bindings.insert(format!("{}?", name), predicate);
+    env.bindings
+        .insert(format!("{}->integer", name), to_integer);
+    env.bindings
+        .insert(format!("integer->{}", name), from_integer);
+    Ok(Value::Nil)
+}
+
+/// Check that the clauses of a `case` without an `else` clause name every
+/// variant of the enum their datums are from, if any; `enum_of` finds the
+/// enum of a datum
+pub fn check_case_exhaustive(
+    clauses: &Value,
+    enum_of: impl Fn(&str) -> Option<Rc<EnumType>>,
+) -> Result<(), String> {
+    let mut datums = Vec::new();
+    let mut current = clauses;
+    while let Value::Pair(pair) = current {
+        match &pair.0 {
+            Value::Pair(clause) => match &clause.0 {
+                Value::Symbol(s) if s == "else" => return Ok(()),
+                list => {
+                    let mut list = list;
+                    while let Value::Pair(datum) = list {
+                        if let Value::Symbol(name) = &datum.0 {
+                            datums.push(name.as_str());
+                        }
+                        list = &datum.1;
+                    }
+                }
+            },
+            _ => return Ok(()),
+        }
+        current = &pair.1;
+    }
+
+    let enum_type = match datums.iter().find_map(|datum| enum_of(datum)) {
+        Some(enum_type) => enum_type,
+        None => return Ok(()),
+    };
+    let missing = enum_type.missing(&datums);
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "case over {} is not exhaustive: missing {}",
+        enum_type.name,
+        missing.join(", ")
+    ))
+}
+
 // Add quote special form evaluation
 pub fn eval_quote(args: Value, _env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
     if let Value::Pair(pair) = args {
@@ -935,3 +704,316 @@ pub fn eval_quote(args: Value, _env: Rc<RefCell<Environment>>) -> Result<Value,
         Err(Error::Runtime("Malformed quote expression".into()))
     }
 }
+
+// The elements of a proper list, or an error naming `form`
+fn form_items(list: &Value, form: &str) -> Result<Vec<Value>, Error> {
+    let mut items = Vec::new();
+    let mut current = list;
+    while let Value::Pair(pair) = current {
+        items.push(pair.0.clone());
+        current = &pair.1;
+    }
+    match current {
+        Value::Nil => Ok(items),
+        _ => Err(Error::Runtime(format!("Malformed {}", form))),
+    }
+}
+
+fn symbol(name: &str) -> Value {
+    Value::Symbol(name.to_string())
+}
+
+/// `(let name ((var init) ...) body ...)` as the call it abbreviates,
+/// `((letrec ((name (lambda (var ...) body ...))) name) init ...)`, so the
+/// body sees `name` but the initializers don't
+pub fn named_let(args: &Value) -> Result<Value, Error> {
+    let items = form_items(args, "named let")?;
+    let [name, bindings, body @ ..] = items.as_slice() else {
+        return Err(Error::Runtime("Malformed named let".into()));
+    };
+    if body.is_empty() {
+        return Err(Error::Runtime("Malformed named let".into()));
+    }
+    let mut vars = Vec::new();
+    let mut inits = Vec::new();
+    for binding in form_items(bindings, "named let")? {
+        match form_items(&binding, "named let")?.as_slice() {
+            [var @ Value::Symbol(_), init] => {
+                vars.push(var.clone());
+                inits.push(init.clone());
+            }
+            _ => {
+                return Err(Error::Runtime(format!(
+                    "Malformed binding in named let: {}",
+                    binding
+                )))
+            }
+        }
+    }
+
+    let procedure = Value::cons(
+        symbol("lambda"),
+        Value::cons(Value::list(vars), Value::list(body.to_vec())),
+    );
+    let letrec = Value::list(vec![
+        symbol("letrec"),
+        Value::list(vec![Value::list(vec![name.clone(), procedure])]),
+        name.clone(),
+    ]);
+    Ok(Value::cons(letrec, Value::list(inits)))
+}
+
+/// `(do ((var init step) ...) (test result ...) command ...)` as the named
+/// let it abbreviates: each time round the test is evaluated, then the
+/// results if it holds, or else the commands and the loop again with each
+/// variable set to its step. A variable without a step keeps its value.
+pub fn eval_do(args: &Value) -> Result<Value, Error> {
+    let items = form_items(args, "do")?;
+    let [specs, exit, commands @ ..] = items.as_slice() else {
+        return Err(Error::Runtime("Malformed do".into()));
+    };
+    let mut bindings = Vec::new();
+    let mut steps = Vec::new();
+    for spec in form_items(specs, "do")? {
+        match form_items(&spec, "do")?.as_slice() {
+            [var @ Value::Symbol(_), init, step @ ..] if step.len() <= 1 => {
+                bindings.push(Value::list(vec![var.clone(), init.clone()]));
+                steps.push(step.first().unwrap_or(var).clone());
+            }
+            _ => return Err(Error::Runtime(format!("Malformed do variable: {}", spec))),
+        }
+    }
+    let Some((test, results)) = form_items(exit, "do")?
+        .split_first()
+        .map(|(test, results)| (test.clone(), results.to_vec()))
+    else {
+        return Err(Error::Runtime("do requires a test".into()));
+    };
+
+    // The loop's name is a fresh symbol, so the body can't call it by mistake
+    let name = super::environment::gensym("do-loop");
+    let mut next = commands.to_vec();
+    next.push(Value::cons(name.clone(), Value::list(steps)));
+    let body = Value::list(vec![
+        symbol("if"),
+        test,
+        Value::cons(symbol("begin"), Value::list(results)),
+        Value::cons(symbol("begin"), Value::list(next)),
+    ]);
+    Ok(Value::list(vec![
+        symbol("let"),
+        name,
+        Value::list(bindings),
+        body,
+    ]))
+}
+
+/// `(quasiquote template)` as the expression that builds the template: parts
+/// without an `unquote` are quoted, `(unquote e)` is `e`, and
+/// `(unquote-splicing e)` in a list appends the list `e` evaluates to. Each
+/// `quasiquote` inside the template nests a level, and each `unquote` inside
+/// that leaves one, so only those at the outermost level are evaluated.
+pub fn quasiquote(args: &Value) -> Result<Value, Error> {
+    match form_items(args, "quasiquote")?.as_slice() {
+        [template] => Ok(template_expr(template, 1)?.unwrap_or_else(|| quoted(template))),
+        _ => Err(Error::Runtime(
+            "quasiquote requires exactly 1 argument".into(),
+        )),
+    }
+}
+
+fn quoted(datum: &Value) -> Value {
+    Value::list(vec![symbol("quote"), datum.clone()])
+}
+
+// The operand of `(name operand)`
+fn unquote_operand<'a>(template: &'a Value, name: &str) -> Option<&'a Value> {
+    match template {
+        Value::Pair(pair) if matches!(&pair.0, Value::Symbol(head) if head == name) => {
+            match &pair.1 {
+                Value::Pair(rest) if matches!(rest.1, Value::Nil) => Some(&rest.0),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+// A step of building the expression for a template. Templates are walked
+// with a stack of these rather than the Rust stack, so a template nested any
+// depth doesn't overflow it. Each step leaves what it builds, the expression
+// or `None`, on a stack of results.
+enum Step {
+    /// Build `template` at nesting `depth`
+    Template(Value, usize),
+    /// Build a `quasiquote` or `unquote` form of an inner level at `depth`
+    Nested(Value, usize),
+    /// Leave what was built without building anything
+    Built(Option<Value>),
+    /// Combine what the tail of a list and each of its elements, from the last
+    /// to the first, built into the list the pairs make
+    FinishList(Vec<Rc<Cons>>, usize),
+    /// Rebuild an inner form, whose head is the value, around what its operand
+    /// built
+    FinishNested(Value),
+    /// Turn what the list of the elements of a vector built into a vector
+    FinishVector,
+}
+
+// The expression building `template` at nesting `depth`, or `None` if it
+// holds nothing to evaluate and can be quoted as it is
+fn template_expr(template: &Value, depth: usize) -> Result<Option<Value>, Error> {
+    let mut steps = vec![Step::Template(template.clone(), depth)];
+    let mut built: Vec<Option<Value>> = Vec::new();
+    while let Some(step) = steps.pop() {
+        match step {
+            Step::Template(template, depth) => template_steps(template, depth, &mut steps)?,
+            // The operand of an inner form is a list template of its own, so
+            // `,@,@x` splices the elements of `x` into the inner form
+            Step::Nested(form, depth) => match &form {
+                Value::Pair(pair) => {
+                    steps.push(Step::FinishNested(pair.0.clone()));
+                    list_steps(&pair.1, depth, &mut steps);
+                }
+                _ => built.push(None),
+            },
+            Step::Built(expr) => built.push(expr),
+            Step::FinishList(pairs, depth) => {
+                let parts = built.split_off(built.len() - pairs.len() - 1);
+                built.push(finish_list(&pairs, depth, parts));
+            }
+            Step::FinishNested(head) => {
+                let operand = built.pop().flatten();
+                built.push(
+                    operand.map(|operand| {
+                        Value::list(vec![native(cons_values), quoted(&head), operand])
+                    }),
+                );
+            }
+            Step::FinishVector => {
+                let list = built.pop().flatten();
+                built.push(list.map(|list| Value::list(vec![native(list_to_vector), list])));
+            }
+        }
+    }
+    Ok(built.pop().flatten())
+}
+
+// Push the steps building `template` at nesting `depth`
+fn template_steps(template: Value, depth: usize, steps: &mut Vec<Step>) -> Result<(), Error> {
+    if let Some(operand) = unquote_operand(&template, "unquote") {
+        if depth == 1 {
+            steps.push(Step::Built(Some(operand.clone())));
+        } else {
+            steps.push(Step::Nested(template, depth - 1));
+        }
+        return Ok(());
+    }
+    if unquote_operand(&template, "quasiquote").is_some() {
+        steps.push(Step::Nested(template, depth + 1));
+        return Ok(());
+    }
+    if unquote_operand(&template, "unquote-splicing").is_some() && depth == 1 {
+        return Err(Error::Runtime(format!(
+            "unquote-splicing outside a list: {}",
+            template
+        )));
+    }
+    match &template {
+        Value::Pair(_) => list_steps(&template, depth, steps),
+        Value::Vector(items) => {
+            steps.push(Step::FinishVector);
+            list_steps(&Value::list(items.borrow().iter().cloned()), depth, steps);
+        }
+        _ => steps.push(Step::Built(None)),
+    }
+    Ok(())
+}
+
+// Push the steps building a list template at nesting `depth`: its tail first,
+// then its elements from the last. The list ends where its pairs do, or at an
+// `unquote` form in its dotted tail, as in `(a . ,b)`.
+fn list_steps(template: &Value, depth: usize, steps: &mut Vec<Step>) {
+    let mut pairs = Vec::new();
+    let mut current = template;
+    while let Value::Pair(pair) = current {
+        let dotted_unquote = unquote_operand(current, "unquote").is_some()
+            || unquote_operand(current, "quasiquote").is_some();
+        if !pairs.is_empty() && dotted_unquote {
+            break;
+        }
+        pairs.push(pair.clone());
+        current = &pair.1;
+    }
+    let tail = Step::Template(current.clone(), depth);
+
+    let elements: Vec<Step> = pairs
+        .iter()
+        .map(|pair| match unquote_operand(&pair.0, "unquote-splicing") {
+            // Spliced by `finish_list`
+            Some(_) if depth == 1 => Step::Built(None),
+            Some(_) => Step::Nested(pair.0.clone(), depth - 1),
+            None => Step::Template(pair.0.clone(), depth),
+        })
+        .collect();
+    steps.push(Step::FinishList(pairs, depth));
+    steps.extend(elements);
+    steps.push(tail);
+}
+
+// The list of `pairs` from what its tail and elements built, `parts` holding
+// the tail's first and then the elements' from the last
+fn finish_list(pairs: &[Rc<Cons>], depth: usize, parts: Vec<Option<Value>>) -> Option<Value> {
+    let mut parts = parts.into_iter();
+    let mut tail = parts.next().flatten();
+    for (pair, head) in pairs.iter().rev().zip(parts) {
+        let (element, rest) = (&pair.0, &pair.1);
+        if let (Some(list), 1) = (unquote_operand(element, "unquote-splicing"), depth) {
+            tail = Some(Value::list(vec![
+                native(append_spliced),
+                list.clone(),
+                tail.unwrap_or_else(|| quoted(rest)),
+            ]));
+            continue;
+        }
+        if head.is_some() || tail.is_some() {
+            tail = Some(Value::list(vec![
+                native(cons_values),
+                head.unwrap_or_else(|| quoted(element)),
+                tail.unwrap_or_else(|| quoted(rest)),
+            ]));
+        }
+    }
+    tail
+}
+
+// The expansion calls these procedures themselves rather than the variables
+// `cons` and `append`, so a template means the same wherever those are
+// rebound
+fn native(procedure: fn(Vec<Value>) -> Result<Value, String>) -> Value {
+    Value::Procedure(Rc::new(procedure))
+}
+
+fn cons_values(args: Vec<Value>) -> Result<Value, String> {
+    let [car, cdr]: [Value; 2] = args.try_into().map_err(|_| "cons requires 2 arguments")?;
+    Ok(Value::cons(car, cdr))
+}
+
+// The elements of a spliced list followed by `rest`, which is shared
+fn append_spliced(args: Vec<Value>) -> Result<Value, String> {
+    let [list, rest]: [Value; 2] = args.try_into().map_err(|_| "append requires 2 arguments")?;
+    let items = form_items(&list, "unquote-splicing")
+        .map_err(|_| format!("unquote-splicing requires a list, got {}", list))?;
+    Ok(items
+        .into_iter()
+        .rev()
+        .fold(rest, |rest, item| Value::cons(item, rest)))
+}
+
+fn list_to_vector(args: Vec<Value>) -> Result<Value, String> {
+    let [list]: [Value; 1] = args
+        .try_into()
+        .map_err(|_| "list->vector requires 1 argument")?;
+    let items = form_items(&list, "vector template").map_err(|e| e.to_string())?;
+    Ok(Value::Vector(Rc::new(RefCell::new(items))))
+}
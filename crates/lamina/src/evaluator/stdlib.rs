@@ -0,0 +1,85 @@
+// The standard library written in Lamina
+//
+// The libraries under `stdlib/` are compiled into the binary and available to
+// `import` in every environment, as the libraries written in Rust are. Each
+// one is read and evaluated the first time it is imported, in the root
+// environment, so it costs nothing at startup and is shared by everything
+// evaluated afterwards. `load_all` evaluates them all up front, in the order
+// of `LIBRARIES`.
+//
+// A library may import the ones listed before it; importing one that isn't
+// loaded yet loads it first, so the order the libraries are evaluated in
+// only depends on the imports, never on which was asked for first.
+//
+// Without the `stdlib` feature none of them is available.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::lexer;
+use crate::parser;
+use crate::value::Environment;
+
+use super::libraries::find_loaded_library;
+
+/// A library written in Lamina, as a single `define-library` form
+pub struct StdLibrary {
+    pub name: &'static [&'static str],
+    pub source: &'static str,
+}
+
+/// The libraries in load order: each imports only the ones before it
+#[cfg(feature = "stdlib")]
+pub const LIBRARIES: &[StdLibrary] = &[
+    StdLibrary {
+        name: &["lamina", "list"],
+        source: include_str!("../../stdlib/list.lmn"),
+    },
+    StdLibrary {
+        name: &["lamina", "alist"],
+        source: include_str!("../../stdlib/alist.lmn"),
+    },
+];
+
+#[cfg(not(feature = "stdlib"))]
+pub const LIBRARIES: &[StdLibrary] = &[];
+
+/// The standard library called `name`, if there is one
+pub fn find(name: &[String]) -> Option<&'static StdLibrary> {
+    LIBRARIES
+        .iter()
+        .find(|library| library.name.iter().eq(name.iter()))
+}
+
+/// Evaluate `library` in the root of `env`, binding it there
+pub fn load(env: &Rc<RefCell<Environment>>, library: &StdLibrary) -> Result<(), Error> {
+    let name = library.name.join(" ");
+    let failed = |e: Error| Error::Runtime(format!("Failed to load ({}): {}", name, e));
+    let tokens = lexer::lex(library.source).map_err(failed)?;
+    let expr = parser::parse(&tokens).map_err(failed)?;
+    super::eval_with_env(expr, root(env)).map_err(failed)?;
+    Ok(())
+}
+
+/// Evaluate every library in `LIBRARIES` order that isn't loaded yet
+pub fn load_all(env: &Rc<RefCell<Environment>>) -> Result<(), Error> {
+    for library in LIBRARIES {
+        let name: Vec<String> = library.name.iter().map(|part| part.to_string()).collect();
+        if find_loaded_library(env, &name).is_err() {
+            load(env, library)?;
+        }
+    }
+    Ok(())
+}
+
+fn root(env: &Rc<RefCell<Environment>>) -> Rc<RefCell<Environment>> {
+    let mut current = env.clone();
+    loop {
+        let parent = current.borrow().parent.clone();
+        match parent {
+            Some(parent) => current = parent,
+            None => return current,
+        }
+    }
+}
@@ -0,0 +1,62 @@
+// Multiple values
+//
+// `(values x ...)` of one value is that value. Any other number of values is
+// kept in a record of their own type, which `call-with-values` and the
+// continuations `call/cc` captures spread back into arguments.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::value::{Environment, Record, RecordType, Value};
+
+thread_local! {
+    static VALUES: Rc<RecordType> = Rc::new(RecordType {
+        name: "values".to_string(),
+        fields: vec![("values".to_string(), false)],
+    });
+}
+
+/// `values` as a single value
+pub fn values(mut values: Vec<Value>) -> Value {
+    if values.len() == 1 {
+        return values.remove(0);
+    }
+    let list = values
+        .into_iter()
+        .rev()
+        .fold(Value::Nil, |list, value| Value::cons(value, list));
+    Value::Record(Rc::new(Record {
+        type_info: VALUES.with(Rc::clone),
+        values: RefCell::new(HashMap::from([("values".to_string(), list)])),
+    }))
+}
+
+/// Whether `value` holds any number of values other than one
+pub fn is_values(value: &Value) -> bool {
+    matches!(value, Value::Record(record) if VALUES.with(|t| Rc::ptr_eq(&record.type_info, t)))
+}
+
+/// The values `value` holds, as arguments for a procedure receiving them
+pub fn spread(value: Value) -> Vec<Value> {
+    let record = match &value {
+        Value::Record(record) if is_values(&value) => record,
+        _ => return vec![value],
+    };
+    let mut spread = Vec::new();
+    let mut current = record.values.borrow().get("values").cloned();
+    while let Some(Value::Pair(pair)) = current {
+        spread.push(pair.0.clone());
+        current = Some(pair.1.clone());
+    }
+    spread
+}
+
+/// Register `values`; `call-with-values` is a special form, so the producer
+/// and consumer run on the stack
+pub fn register_values_procedures(env: &Rc<RefCell<Environment>>) {
+    env.borrow_mut().bindings.insert(
+        "values".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| Ok(values(args)))),
+    );
+}
@@ -0,0 +1,288 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use tiny_keccak::{Hasher, Keccak};
+
+use crate::error::Error;
+use crate::evaluator::environment::lookup_variable;
+use crate::evaluator::{apply, eval_with_env};
+use crate::json::{parse_json, Json};
+use crate::value::{Environment, Value};
+
+use super::word::{value_to_word, word_to_unsigned_value, word_to_value, Word};
+
+/// Name of the procedure interface wrappers hand their calls to in the
+/// interpreter: `(eth-call target calldata)` returning the return data
+pub const ETH_CALL: &str = "eth-call";
+
+/// A function of an external contract, as described by its ABI
+#[derive(Clone, Debug, PartialEq)]
+pub struct AbiFunction {
+    pub name: String,
+    /// Canonical parameter types, e.g. `uint256`
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    pub state_mutability: String,
+}
+
+impl AbiFunction {
+    /// Canonical signature, e.g. `transfer(address,uint256)`
+    pub fn signature(&self) -> String {
+        format!("{}({})", self.name, self.inputs.join(","))
+    }
+
+    pub fn selector(&self) -> [u8; 4] {
+        let mut hash = [0u8; 32];
+        let mut keccak = Keccak::v256();
+        keccak.update(self.signature().as_bytes());
+        keccak.finalize(&mut hash);
+        [hash[0], hash[1], hash[2], hash[3]]
+    }
+
+    /// Whether the function can be called without changing state
+    pub fn is_view(&self) -> bool {
+        matches!(self.state_mutability.as_str(), "view" | "pure")
+    }
+
+    /// Selector followed by the ABI encoded arguments
+    pub fn encode_call(&self, args: &[Value]) -> Result<Vec<u8>, String> {
+        if args.len() != self.inputs.len() {
+            return Err(format!(
+                "{} expects {} arguments, got {}",
+                self.name,
+                self.inputs.len(),
+                args.len()
+            ));
+        }
+
+        let mut calldata = self.selector().to_vec();
+        for (kind, arg) in self.inputs.iter().zip(args) {
+            calldata.extend_from_slice(&encode_word(kind, arg)?);
+        }
+        Ok(calldata)
+    }
+
+    /// Decode return data: no outputs give the empty list, one output its
+    /// value, several a list of values
+    pub fn decode_output(&self, data: &[u8]) -> Result<Value, String> {
+        if data.len() < 32 * self.outputs.len() {
+            return Err(format!(
+                "{} returned {} bytes, expected {}",
+                self.name,
+                data.len(),
+                32 * self.outputs.len()
+            ));
+        }
+
+        let mut values = self
+            .outputs
+            .iter()
+            .zip(data.chunks(32))
+            .map(|(kind, word)| decode_word(kind, word))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(match values.len() {
+            1 => values.remove(0),
+            _ => values
+                .into_iter()
+                .rev()
+                .fold(Value::Nil, |list, value| Value::cons(value, list)),
+        })
+    }
+}
+
+/// Read the functions from ABI JSON: either the ABI array itself or an
+/// artifact object with an `abi` field
+pub fn parse_abi(text: &str) -> Result<Vec<AbiFunction>, String> {
+    let json = parse_json(text)?;
+    let entries = json
+        .get("abi")
+        .unwrap_or(&json)
+        .as_array()
+        .ok_or("ABI must be a JSON array")?;
+
+    entries
+        .iter()
+        .filter(|entry| entry.get("type").and_then(Json::as_str) == Some("function"))
+        .map(|entry| {
+            let name = entry
+                .get("name")
+                .and_then(Json::as_str)
+                .ok_or("ABI function without a name")?;
+            Ok(AbiFunction {
+                name: name.to_string(),
+                inputs: param_types(entry.get("inputs"))?,
+                outputs: param_types(entry.get("outputs"))?,
+                state_mutability: entry
+                    .get("stateMutability")
+                    .and_then(Json::as_str)
+                    .unwrap_or("nonpayable")
+                    .to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Read and parse an ABI file
+pub fn load_abi(path: &str) -> Result<Vec<AbiFunction>, String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|e| format!("Cannot read ABI {}: {}", path, e))?;
+    parse_abi(&text).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Name a wrapper for `function` of interface `interface` is bound to
+pub fn wrapper_name(interface: &str, function: &str) -> String {
+    format!("{}/{}", interface, function)
+}
+
+fn param_types(params: Option<&Json>) -> Result<Vec<String>, String> {
+    let params = match params {
+        Some(params) => params.as_array().ok_or("ABI parameters must be an array")?,
+        None => return Ok(Vec::new()),
+    };
+    params
+        .iter()
+        .map(|param| {
+            let kind = param
+                .get("type")
+                .and_then(Json::as_str)
+                .ok_or("ABI parameter without a type")?;
+            canonical_type(kind)
+        })
+        .collect()
+}
+
+// Only static, single-word types are supported
+fn canonical_type(kind: &str) -> Result<String, String> {
+    let canonical = match kind {
+        "uint" => "uint256".to_string(),
+        "int" => "int256".to_string(),
+        "address" | "bool" => kind.to_string(),
+        _ if bit_size(kind, "uint").is_some() || bit_size(kind, "int").is_some() => {
+            kind.to_string()
+        }
+        _ if byte_size(kind).is_some() => kind.to_string(),
+        _ => return Err(format!("Unsupported ABI type: {}", kind)),
+    };
+    Ok(canonical)
+}
+
+fn bit_size(kind: &str, prefix: &str) -> Option<usize> {
+    let bits = kind.strip_prefix(prefix)?.parse::<usize>().ok()?;
+    (bits > 0 && bits <= 256 && bits.is_multiple_of(8)).then_some(bits)
+}
+
+fn byte_size(kind: &str) -> Option<usize> {
+    let bytes = kind.strip_prefix("bytes")?.parse::<usize>().ok()?;
+    (1..=32).contains(&bytes).then_some(bytes)
+}
+
+fn encode_word(kind: &str, arg: &Value) -> Result<[u8; 32], String> {
+    match (kind, arg) {
+        ("bool", Value::Boolean(b)) => Ok(Word::from_i64(*b as i64).to_be_bytes()),
+        ("bool", _) => Err(format!("Expected a boolean for bool, got {}", arg)),
+        ("address", Value::Address(_)) => Ok(value_to_word(arg)?.to_be_bytes()),
+        ("address", _) => Err(format!("Expected an address, got {}", arg)),
+        _ => match (byte_size(kind), arg) {
+            // bytesN is left aligned
+            (Some(size), Value::Bytevector(bytes)) => {
+                let bytes = bytes.borrow();
+                if bytes.len() > size {
+                    return Err(format!("{} takes at most {} bytes", kind, size));
+                }
+                let mut word = [0u8; 32];
+                word[..bytes.len()].copy_from_slice(&bytes);
+                Ok(word)
+            }
+            (Some(_), _) => Err(format!("Expected a bytevector for {}, got {}", kind, arg)),
+            (None, _) => Ok(value_to_word(arg)?.to_be_bytes()),
+        },
+    }
+}
+
+fn decode_word(kind: &str, word: &[u8]) -> Result<Value, String> {
+    let value = Word::from_be_bytes(word)?;
+    Ok(match kind {
+        "bool" => Value::Boolean(!value.is_zero()),
+        "address" => {
+            let mut address = [0u8; 20];
+            address.copy_from_slice(&word[12..]);
+            Value::Address(address)
+        }
+        _ if kind.starts_with("int") => word_to_value(value),
+        _ => match byte_size(kind) {
+            Some(size) => Value::Bytevector(Rc::new(RefCell::new(word[..size].to_vec()))),
+            None => word_to_unsigned_value(value),
+        },
+    })
+}
+
+// define-interface special form: (define-interface Name "path/to/abi.json")
+//
+// Binds `Name/function` for every function in the ABI. A wrapper takes the
+// target address followed by the function arguments, and hands the encoded
+// call to `eth-call`, which an RPC library or a test provides.
+pub fn eval_define_interface(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
+    let (name, path) = interface_name_and_path(&args, env.clone())?;
+    let functions = load_abi(&path).map_err(Error::Runtime)?;
+
+    for function in functions {
+        let wrapper = interface_wrapper(function.clone(), env.clone());
+        env.borrow_mut()
+            .bindings
+            .insert(wrapper_name(&name, &function.name), wrapper);
+    }
+    Ok(Value::Nil)
+}
+
+fn interface_name_and_path(
+    args: &Value,
+    env: Rc<RefCell<Environment>>,
+) -> Result<(String, String), Error> {
+    if let Value::Pair(name_pair) = args {
+        if let (Value::Symbol(name), Value::Pair(path_pair)) = (&name_pair.0, &name_pair.1) {
+            return match eval_with_env(path_pair.0.clone(), env)? {
+                Value::String(path) => Ok((name.clone(), path)),
+                other => Err(Error::Runtime(format!(
+                    "define-interface path must be a string, got {}",
+                    other
+                ))),
+            };
+        }
+    }
+    Err(Error::Runtime(
+        "Malformed define-interface form, expected (define-interface Name \"abi.json\")".into(),
+    ))
+}
+
+fn interface_wrapper(function: AbiFunction, env: Rc<RefCell<Environment>>) -> Value {
+    Value::Procedure(Rc::new(move |args: Vec<Value>| {
+        let (target, args) = args
+            .split_first()
+            .ok_or_else(|| format!("{} expects a target address", function.name))?;
+        let calldata = function.encode_call(args)?;
+
+        let call = lookup_variable(ETH_CALL, env.clone()).map_err(|_| {
+            format!(
+                "No {} procedure bound to send {} to",
+                ETH_CALL, function.name
+            )
+        })?;
+        let call_args = vec![
+            target.clone(),
+            Value::Bytevector(Rc::new(RefCell::new(calldata))),
+        ];
+        if !call.is_procedure() {
+            return Err(format!("{} is not a procedure: {}", ETH_CALL, call));
+        }
+        let result = apply(&call, call_args)?;
+
+        match result {
+            Value::Bytevector(data) => function.decode_output(&data.borrow()),
+            other => Err(format!(
+                "{} must return a bytevector, got {}",
+                ETH_CALL, other
+            )),
+        }
+    }))
+}
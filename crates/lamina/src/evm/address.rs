@@ -0,0 +1,75 @@
+use tiny_keccak::{Hasher, Keccak};
+
+use crate::encoding::{decode_hex, encode_hex};
+
+/// Parse a `0x`-prefixed, 40 hex digit address.
+///
+/// All-lowercase and all-uppercase addresses are accepted as is. Mixed-case
+/// addresses must carry a valid EIP-55 checksum, so a mistyped literal is
+/// rejected instead of silently pointing at the wrong account.
+pub fn parse_address(s: &str) -> Result<[u8; 20], String> {
+    let hex = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .ok_or_else(|| format!("Address must start with 0x: {}", s))?;
+
+    if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Address must be 40 hex digits: {}", s));
+    }
+
+    let mut bytes = [0u8; 20];
+    bytes.copy_from_slice(&decode_hex(hex)?);
+
+    let has_lower = hex.chars().any(|c| c.is_ascii_lowercase());
+    let has_upper = hex.chars().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper && checksum_address(&bytes)[2..] != *hex {
+        return Err(format!("Invalid address checksum: {}", s));
+    }
+
+    Ok(bytes)
+}
+
+/// Format an address with its EIP-55 mixed-case checksum
+pub fn checksum_address(bytes: &[u8; 20]) -> String {
+    let hex = encode_hex(bytes)[2..].to_string();
+    let hash = keccak256(hex.as_bytes());
+
+    let mut result = String::from("0x");
+    for (i, c) in hex.chars().enumerate() {
+        // Uppercase a letter when the matching nibble of the hash is >= 8
+        let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+        if nibble >= 8 {
+            result.push(c.to_ascii_uppercase());
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Keccak-256 hash of `bytes`, as used for init code hashes
+pub fn keccak256(bytes: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    let mut keccak = Keccak::v256();
+    keccak.update(bytes);
+    keccak.finalize(&mut hash);
+    hash
+}
+
+/// Address of the contract `deployer` creates with CREATE2: the last 20 bytes
+/// of `keccak256(0xff ++ deployer ++ salt ++ init_code_hash)` (EIP-1014)
+pub fn create2_address(
+    deployer: &[u8; 20],
+    salt: &[u8; 32],
+    init_code_hash: &[u8; 32],
+) -> [u8; 20] {
+    let mut preimage = Vec::with_capacity(85);
+    preimage.push(0xff);
+    preimage.extend_from_slice(deployer);
+    preimage.extend_from_slice(salt);
+    preimage.extend_from_slice(init_code_hash);
+
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&keccak256(&preimage)[12..]);
+    address
+}
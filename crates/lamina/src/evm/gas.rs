@@ -0,0 +1,152 @@
+// Gas costs of the simulated EVM, by hardfork
+//
+// A `GasSchedule` prices the opcodes the simulated primitives stand for.
+// `Hardfork` presets give the costs of mainnet at that fork; chains that
+// price storage differently, or forks not listed, are described by a TOML
+// file naming a preset to start from and the costs it changes:
+//
+//   hardfork = "shanghai"
+//   cold_sload = 2600
+//   sstore_set = 22100
+//
+// Keys are the field names of `GasSchedule`, in any table. The file is read
+// with `project::toml_entries`, as the manifest is.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::project::{toml_entries, Entry};
+
+/// The hardforks there are presets for, oldest first
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Hardfork {
+    Shanghai,
+    /// Shanghai with transient storage (EIP-1153)
+    #[default]
+    Cancun,
+}
+
+impl Hardfork {
+    pub const ALL: [Hardfork; 2] = [Hardfork::Shanghai, Hardfork::Cancun];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Hardfork::Shanghai => "shanghai",
+            Hardfork::Cancun => "cancun",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Hardfork> {
+        Hardfork::ALL
+            .into_iter()
+            .find(|fork| fork.name().eq_ignore_ascii_case(name))
+    }
+}
+
+impl fmt::Display for Hardfork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// What each priced opcode costs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GasSchedule {
+    /// The preset the costs started from
+    pub hardfork: Hardfork,
+    /// First access of a storage slot in a transaction (EIP-2929)
+    pub cold_sload: u64,
+    /// Later accesses of the slot, and writes that change nothing (EIP-2200)
+    pub warm_access: u64,
+    /// A write setting a slot that was zero at the start of the transaction
+    pub sstore_set: u64,
+    /// A write changing a slot that wasn't zero
+    pub sstore_reset: u64,
+    pub create2: u64,
+    /// Hashing the init code for CREATE2's address, per 32-byte word
+    pub keccak_word: u64,
+    /// Init code, per 32-byte word (EIP-3860)
+    pub init_code_word: u64,
+    /// CALLER, ORIGIN, CALLVALUE and the other environment reads
+    pub base: u64,
+    /// TLOAD and TSTORE, in the hardforks that have them
+    pub transient: Option<u64>,
+}
+
+impl GasSchedule {
+    pub fn for_hardfork(hardfork: Hardfork) -> Self {
+        let shanghai = GasSchedule {
+            hardfork,
+            cold_sload: 2100,
+            warm_access: 100,
+            sstore_set: 20000,
+            sstore_reset: 2900,
+            create2: 32000,
+            keccak_word: 6,
+            init_code_word: 2,
+            base: 2,
+            transient: None,
+        };
+        match hardfork {
+            Hardfork::Shanghai => shanghai,
+            Hardfork::Cancun => GasSchedule {
+                transient: Some(100),
+                ..shanghai
+            },
+        }
+    }
+
+    /// Gas of CREATE2 with `init_code_len` bytes of init code
+    pub fn create2_cost(&self, init_code_len: usize) -> u64 {
+        let words = init_code_len.div_ceil(32) as u64;
+        self.create2 + (self.keccak_word + self.init_code_word) * words
+    }
+
+    /// The schedule a TOML file describes
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let entries = toml_entries(text)?;
+        let hardfork = match entries.iter().find(|entry| entry.key == "hardfork") {
+            Some(entry) => Hardfork::from_name(&entry.value)
+                .ok_or_else(|| format!("line {}: unknown hardfork {}", entry.line, entry.value))?,
+            None => Hardfork::default(),
+        };
+        let mut schedule = GasSchedule::for_hardfork(hardfork);
+        for Entry {
+            key, value, line, ..
+        } in entries
+        {
+            if key == "hardfork" {
+                continue;
+            }
+            let cost = value
+                .parse()
+                .map_err(|_| format!("line {}: {} is not a gas cost", line, value))?;
+            match key.as_str() {
+                "cold_sload" => schedule.cold_sload = cost,
+                "warm_access" => schedule.warm_access = cost,
+                "sstore_set" => schedule.sstore_set = cost,
+                "sstore_reset" => schedule.sstore_reset = cost,
+                "create2" => schedule.create2 = cost,
+                "keccak_word" => schedule.keccak_word = cost,
+                "init_code_word" => schedule.init_code_word = cost,
+                "base" => schedule.base = cost,
+                "transient" => schedule.transient = Some(cost),
+                _ => return Err(format!("line {}: unknown gas cost {}", line, key)),
+            }
+        }
+        Ok(schedule)
+    }
+
+    /// The schedule the TOML file at `path` describes
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+impl Default for GasSchedule {
+    fn default() -> Self {
+        Self::for_hardfork(Hardfork::default())
+    }
+}
@@ -0,0 +1,25 @@
+// EVM support for the interpreter
+//
+// The `evm` library registered in the initial environment only mocks storage,
+// which is enough for code that is compiled rather than run. The simulator in
+// this module keeps real chain state so contract code can be executed and
+// tested off-chain before it is compiled with a backend.
+
+pub mod abi;
+pub mod address;
+pub mod gas;
+pub mod primitives;
+pub mod simulator;
+pub mod trace;
+pub mod word;
+
+pub use abi::{load_abi, parse_abi, AbiFunction, ETH_CALL};
+pub use address::{checksum_address, create2_address, keccak256, parse_address};
+pub use gas::{GasSchedule, Hardfork};
+pub use primitives::{
+    register_safemath_primitives, register_word_primitives, SAFEMATH_PRIMITIVES, WORD_PRIMITIVES,
+};
+pub use simulator::{
+    register_simulated_evm, unregister_simulated_evm, EvmState, REVERT_PREFIX, SIMULATED_PRIMITIVES,
+};
+pub use word::{value_to_word, word_to_unsigned_value, word_to_value, Word};
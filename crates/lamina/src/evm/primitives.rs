@@ -0,0 +1,143 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::evaluator::libraries::check_args_count;
+use crate::value::{Environment, Value};
+
+use super::simulator::REVERT_PREFIX;
+use super::word::{value_to_word, word_to_unsigned_value, word_to_value, Word};
+
+/// Names of the word-level primitives, for library export lists
+pub const WORD_PRIMITIVES: [&str; 4] = ["s<", "s/", "smod", "sign-extend"];
+
+/// Bind the 256-bit word primitives (signed arithmetic) into `env`
+pub fn register_word_primitives(env: Rc<RefCell<Environment>>) {
+    let mut env = env.borrow_mut();
+
+    env.bindings.insert(
+        "s<".to_string(),
+        Value::Procedure(Rc::new(|args| {
+            let (a, b) = word_args("s<", &args)?;
+            Ok(Value::Boolean(a.signed_lt(b)))
+        })),
+    );
+
+    env.bindings.insert(
+        "s/".to_string(),
+        Value::Procedure(Rc::new(|args| {
+            let (a, b) = word_args("s/", &args)?;
+            Ok(word_to_value(a.signed_div(b)))
+        })),
+    );
+
+    env.bindings.insert(
+        "smod".to_string(),
+        Value::Procedure(Rc::new(|args| {
+            let (a, b) = word_args("smod", &args)?;
+            Ok(word_to_value(a.signed_rem(b)))
+        })),
+    );
+
+    // (sign-extend byte x), the same operand order as SIGNEXTEND
+    env.bindings.insert(
+        "sign-extend".to_string(),
+        Value::Procedure(Rc::new(|args| {
+            let (byte, x) = word_args("sign-extend", &args)?;
+            Ok(word_to_value(x.sign_extend(byte)))
+        })),
+    );
+}
+
+/// Names exported by the `(lamina evm safemath)` library
+pub const SAFEMATH_PRIMITIVES: [&str; 6] = [
+    "add-checked",
+    "sub-checked",
+    "mul-checked",
+    "div-floor",
+    "addmod",
+    "mulmod",
+];
+
+/// Bind the checked arithmetic of `(lamina evm safemath)` into `env`.
+///
+/// Operands and results are unsigned 256-bit words, results read back as
+/// integers from 0 to 2^256 - 1. Like Solidity's checked arithmetic, an
+/// overflow or a division by zero reverts rather than wrapping; `addmod` and
+/// `mulmod` never overflow and yield zero for a zero modulus, as on the EVM.
+pub fn register_safemath_primitives(env: Rc<RefCell<Environment>>) {
+    let mut env = env.borrow_mut();
+
+    env.bindings.insert(
+        "add-checked".to_string(),
+        Value::Procedure(Rc::new(|args| {
+            let (a, b) = word_args("add-checked", &args)?;
+            a.checked_add(b)
+                .map(word_to_unsigned_value)
+                .ok_or_else(|| reverted("add-checked", "overflow"))
+        })),
+    );
+
+    env.bindings.insert(
+        "sub-checked".to_string(),
+        Value::Procedure(Rc::new(|args| {
+            let (a, b) = word_args("sub-checked", &args)?;
+            a.checked_sub(b)
+                .map(word_to_unsigned_value)
+                .ok_or_else(|| reverted("sub-checked", "underflow"))
+        })),
+    );
+
+    env.bindings.insert(
+        "mul-checked".to_string(),
+        Value::Procedure(Rc::new(|args| {
+            let (a, b) = word_args("mul-checked", &args)?;
+            a.checked_mul(b)
+                .map(word_to_unsigned_value)
+                .ok_or_else(|| reverted("mul-checked", "overflow"))
+        })),
+    );
+
+    env.bindings.insert(
+        "div-floor".to_string(),
+        Value::Procedure(Rc::new(|args| {
+            let (a, b) = word_args("div-floor", &args)?;
+            if b.is_zero() {
+                return Err(reverted("div-floor", "division by zero"));
+            }
+            Ok(word_to_unsigned_value(a.div_rem(b).0))
+        })),
+    );
+
+    env.bindings.insert(
+        "addmod".to_string(),
+        Value::Procedure(Rc::new(|args| {
+            let (a, b, n) = word_args3("addmod", &args)?;
+            Ok(word_to_unsigned_value(a.add_mod(b, n)))
+        })),
+    );
+
+    env.bindings.insert(
+        "mulmod".to_string(),
+        Value::Procedure(Rc::new(|args| {
+            let (a, b, n) = word_args3("mulmod", &args)?;
+            Ok(word_to_unsigned_value(a.mul_mod(b, n)))
+        })),
+    );
+}
+
+fn reverted(name: &str, reason: &str) -> String {
+    format!("{}: {}: {}", REVERT_PREFIX, name, reason)
+}
+
+fn word_args3(name: &str, args: &[Value]) -> Result<(Word, Word, Word), String> {
+    check_args_count(name, args, 3)?;
+    let word = |value| value_to_word(value).map_err(|e| format!("{}: {}", name, e));
+    Ok((word(&args[0])?, word(&args[1])?, word(&args[2])?))
+}
+
+fn word_args(name: &str, args: &[Value]) -> Result<(Word, Word), String> {
+    check_args_count(name, args, 2)?;
+    let a = value_to_word(&args[0]).map_err(|e| format!("{}: {}", name, e))?;
+    let b = value_to_word(&args[1]).map_err(|e| format!("{}: {}", name, e))?;
+    Ok((a, b))
+}
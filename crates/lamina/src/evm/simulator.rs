@@ -0,0 +1,622 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use num_bigint::{BigInt, Sign};
+
+use crate::encoding::{decode_hex, encode_hex};
+use crate::evaluator::equality::is_equal;
+use crate::evaluator::libraries::{check_args_count, number_to_i64};
+use crate::json::Json;
+use crate::printer::Limits;
+use crate::value::{Environment, NumberKind, Value};
+use crate::{lexer, parser};
+
+use super::address::{checksum_address, create2_address, keccak256, parse_address};
+use super::gas::GasSchedule;
+use super::primitives::{register_word_primitives, WORD_PRIMITIVES};
+use super::trace::Trace;
+use super::word::{value_to_word, Word};
+
+/// Prefix of the error produced by `revert` in a simulated context.
+/// Test runners use it to tell a reverted call apart from a failing one.
+pub const REVERT_PREFIX: &str = "Reverted";
+
+/// Simulated chain state for a single contract
+#[derive(Clone, Debug)]
+pub struct EvmState {
+    /// Contract storage, keyed by slot. Unset slots read as zero.
+    pub storage: BTreeMap<Word, Value>,
+    /// Elements of the storage arrays, keyed by the slot holding their length.
+    /// Compiled code keeps them at `keccak256(slot) + index`, which doesn't fit
+    /// the slot keys above.
+    pub arrays: BTreeMap<Word, Vec<Value>>,
+    /// Address of the contract itself, the deployer of what it creates
+    pub address: [u8; 20],
+    /// Init code of the contracts created with `deploy-create2`, by address
+    pub created: BTreeMap<[u8; 20], Vec<u8>>,
+    /// Address of the account calling the contract
+    pub caller: Value,
+    /// Address of the account that started the transaction
+    pub origin: Value,
+    /// Wei sent along with the call
+    pub callvalue: Value,
+    /// Transient storage (EIP-1153), keyed by slot, cleared when the
+    /// transaction ends
+    pub transient: BTreeMap<Word, Value>,
+    /// Steps of the primitives called so far, when tracing
+    pub trace: Option<Trace>,
+}
+
+impl Default for EvmState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EvmState {
+    pub fn new() -> Self {
+        EvmState {
+            storage: BTreeMap::new(),
+            arrays: BTreeMap::new(),
+            address: [0; 20],
+            created: BTreeMap::new(),
+            caller: Value::Address([0; 20]),
+            origin: Value::Address([0; 20]),
+            callvalue: Value::Number(NumberKind::Integer(0)),
+            transient: BTreeMap::new(),
+            trace: None,
+        }
+    }
+
+    /// A state that records a trace of the primitives called on it
+    pub fn traced() -> Self {
+        Self::traced_with(GasSchedule::default())
+    }
+
+    /// A state that records a trace priced with `schedule`
+    pub fn traced_with(schedule: GasSchedule) -> Self {
+        EvmState {
+            trace: Some(Trace::with_schedule(schedule)),
+            ..Self::new()
+        }
+    }
+
+    /// Read a storage slot
+    pub fn load(&self, slot: Word) -> Value {
+        self.storage
+            .get(&slot)
+            .cloned()
+            .unwrap_or(Value::Number(NumberKind::Integer(0)))
+    }
+
+    /// Write a storage slot
+    pub fn store(&mut self, slot: Word, value: Value) {
+        self.storage.insert(slot, value);
+    }
+
+    /// Read a transient storage slot
+    pub fn transient_load(&self, slot: Word) -> Value {
+        self.transient
+            .get(&slot)
+            .cloned()
+            .unwrap_or(Value::Number(NumberKind::Integer(0)))
+    }
+
+    /// End the current transaction, clearing transient storage
+    pub fn end_transaction(&mut self) {
+        self.transient.clear();
+    }
+
+    /// Elements of the storage array whose length is kept at `slot`
+    pub fn array(&self, slot: Word) -> &[Value] {
+        self.arrays.get(&slot).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Append to a storage array, keeping its length slot in step, and return the new length
+    pub fn push(&mut self, slot: Word, value: Value) -> usize {
+        let array = self.arrays.entry(slot).or_default();
+        array.push(value);
+        let length = array.len();
+        self.store(slot, Value::Number(NumberKind::Integer(length as i64)));
+        length
+    }
+
+    /// Create a contract with CREATE2, returning its address, or `None` when
+    /// one already lives there
+    pub fn create2(&mut self, salt: &[u8; 32], init_code: &[u8]) -> Option<[u8; 20]> {
+        let address = create2_address(&self.address, salt, &keccak256(init_code));
+        if self.created.contains_key(&address) {
+            return None;
+        }
+        self.created.insert(address, init_code.to_vec());
+        Some(address)
+    }
+
+    /// The chain state as a JSON object, for `:state save`. Values are kept
+    /// as their written form, and storage slots and array lengths are keyed
+    /// by the slot in decimal. The trace and transient storage, which only
+    /// lasts a transaction, aren't part of it.
+    pub fn to_json(&self) -> Result<Json, String> {
+        let storage = self
+            .storage
+            .iter()
+            .map(|(slot, value)| Ok((slot.to_string(), saved_value(value)?)))
+            .collect::<Result<_, String>>()?;
+        let arrays = self
+            .arrays
+            .iter()
+            .map(|(slot, values)| {
+                let values = values.iter().map(saved_value).collect::<Result<_, _>>()?;
+                Ok((slot.to_string(), Json::Array(values)))
+            })
+            .collect::<Result<_, String>>()?;
+        let created = self
+            .created
+            .iter()
+            .map(|(address, init_code)| (checksum_address(address), encode_hex(init_code).into()))
+            .collect();
+        Ok(Json::object([
+            ("address", checksum_address(&self.address).into()),
+            ("caller", saved_value(&self.caller)?),
+            ("origin", saved_value(&self.origin)?),
+            ("callvalue", saved_value(&self.callvalue)?),
+            ("storage", Json::Object(storage)),
+            ("arrays", Json::Object(arrays)),
+            ("created", Json::Object(created)),
+        ]))
+    }
+
+    /// The chain state `to_json` saved; fields it lacks start out as in a
+    /// new state
+    pub fn from_json(json: &Json) -> Result<EvmState, String> {
+        let mut state = EvmState::new();
+        if let Some(address) = json.get("address") {
+            state.address = parse_address(string_field("address", address)?)?;
+        }
+        for (name, field) in [
+            ("caller", &mut state.caller),
+            ("origin", &mut state.origin),
+            ("callvalue", &mut state.callvalue),
+        ] {
+            if let Some(value) = json.get(name) {
+                *field = read_value(name, value)?;
+            }
+        }
+        for (slot, value) in object_field(json, "storage")? {
+            state
+                .storage
+                .insert(parse_slot(slot)?, read_value(slot, value)?);
+        }
+        for (slot, values) in object_field(json, "arrays")? {
+            let values = values
+                .as_array()
+                .ok_or_else(|| format!("arrays: {} isn't a list of values", slot))?
+                .iter()
+                .map(|value| read_value(slot, value))
+                .collect::<Result<_, _>>()?;
+            state.arrays.insert(parse_slot(slot)?, values);
+        }
+        for (address, init_code) in object_field(json, "created")? {
+            let init_code = decode_hex(string_field(address, init_code)?)?;
+            state.created.insert(parse_address(address)?, init_code);
+        }
+        Ok(state)
+    }
+}
+
+// The written form of `value`, whatever the print limits, when reading it
+// gives the value back
+fn saved_value(value: &Value) -> Result<Json, String> {
+    let limits = Limits::default().set();
+    let text = value.to_string();
+    limits.set();
+    match read_datum(&text) {
+        Ok(read) if is_equal(&read, value) => Ok(Json::String(text)),
+        _ => Err(format!("Can't save {}", text)),
+    }
+}
+
+fn read_datum(text: &str) -> Result<Value, String> {
+    let tokens = lexer::lex(text).map_err(|e| e.to_string())?;
+    parser::parse(&tokens).map_err(|e| e.to_string())
+}
+
+fn read_value(name: &str, json: &Json) -> Result<Value, String> {
+    read_datum(string_field(name, json)?).map_err(|e| format!("{}: {}", name, e))
+}
+
+fn string_field<'a>(name: &str, json: &'a Json) -> Result<&'a str, String> {
+    json.as_str()
+        .ok_or_else(|| format!("{}: expected a string, found {}", name, json))
+}
+
+fn object_field<'a>(json: &'a Json, name: &str) -> Result<&'a [(String, Json)], String> {
+    match json.get(name) {
+        None => Ok(&[]),
+        Some(Json::Object(entries)) => Ok(entries),
+        Some(other) => Err(format!("{}: expected an object, found {}", name, other)),
+    }
+}
+
+fn parse_slot(slot: &str) -> Result<Word, String> {
+    slot.parse::<BigInt>()
+        .ok()
+        .filter(|slot| slot.sign() != Sign::Minus)
+        .and_then(|slot| Word::from_bigint(&slot))
+        .ok_or_else(|| format!("Invalid storage slot: {}", slot))
+}
+
+/// Names bound by [`register_simulated_evm`], besides the word primitives
+pub const SIMULATED_PRIMITIVES: [&str; 15] = [
+    "storage-load",
+    "storage-store",
+    "transient-load",
+    "transient-store!",
+    "storage-string-load",
+    "storage-string-store!",
+    "storage-array-length",
+    "storage-array-ref",
+    "storage-array-set!",
+    "storage-array-push!",
+    "deploy-create2",
+    "revert",
+    "caller",
+    "tx-origin",
+    "callvalue",
+];
+
+/// Bind the evm primitives directly into `env`, backed by `state`.
+///
+/// Unlike the mock `evm` library, reads observe earlier writes and `revert`
+/// aborts the current call with an error starting with [`REVERT_PREFIX`].
+pub fn register_simulated_evm(env: Rc<RefCell<Environment>>, state: Rc<RefCell<EvmState>>) {
+    register_word_primitives(env.clone());
+
+    let load_state = state.clone();
+    env.borrow_mut().bindings.insert(
+        "storage-load".to_string(),
+        Value::Procedure(Rc::new(move |args| {
+            check_args_count("storage-load", &args, 1)?;
+            let slot = storage_word("storage-load", &args[0])?;
+            let mut state = load_state.borrow_mut();
+            let value = state.load(slot);
+            if let Some(trace) = &mut state.trace {
+                trace.record_load("storage-load", (slot, None), &args, &value);
+            }
+            Ok(value)
+        })),
+    );
+
+    let store_state = state.clone();
+    env.borrow_mut().bindings.insert(
+        "storage-store".to_string(),
+        Value::Procedure(Rc::new(move |args| {
+            check_args_count("storage-store", &args, 2)?;
+            let slot = storage_word("storage-store", &args[0])?;
+            storage_word("storage-store", &args[1])?;
+            let mut state = store_state.borrow_mut();
+            let before = state.load(slot);
+            state.store(slot, args[1].clone());
+            if let Some(trace) = &mut state.trace {
+                let location = (slot, None);
+                trace.record_store("storage-store", location, &args, None, &before, &args[1]);
+            }
+            Ok(Value::Nil)
+        })),
+    );
+
+    let transient_load_state = state.clone();
+    env.borrow_mut().bindings.insert(
+        "transient-load".to_string(),
+        Value::Procedure(Rc::new(move |args| {
+            check_args_count("transient-load", &args, 1)?;
+            let slot = storage_word("transient-load", &args[0])?;
+            let mut state = transient_load_state.borrow_mut();
+            let value = state.transient_load(slot);
+            if let Some(trace) = &mut state.trace {
+                let gas = transient_gas(&trace.schedule, "transient-load")?;
+                trace.record("TLOAD", "transient-load", &args, Some(&value), gas);
+            }
+            Ok(value)
+        })),
+    );
+
+    let transient_store_state = state.clone();
+    env.borrow_mut().bindings.insert(
+        "transient-store!".to_string(),
+        Value::Procedure(Rc::new(move |args| {
+            check_args_count("transient-store!", &args, 2)?;
+            let slot = storage_word("transient-store!", &args[0])?;
+            let mut state = transient_store_state.borrow_mut();
+            state.transient.insert(slot, args[1].clone());
+            if let Some(trace) = &mut state.trace {
+                let gas = transient_gas(&trace.schedule, "transient-store!")?;
+                trace.record("TSTORE", "transient-store!", &args, None, gas);
+            }
+            Ok(args[1].clone())
+        })),
+    );
+
+    let string_load_state = state.clone();
+    env.borrow_mut().bindings.insert(
+        "storage-string-load".to_string(),
+        Value::Procedure(Rc::new(move |args| {
+            check_args_count("storage-string-load", &args, 1)?;
+            let slot = storage_word("storage-string-load", &args[0])?;
+            let mut state = string_load_state.borrow_mut();
+            let value = match state.load(slot) {
+                Value::String(s) => Value::String(s),
+                // An unset slot holds the empty string
+                Value::Number(NumberKind::Integer(0)) => Value::String(String::new()),
+                other => {
+                    return Err(format!(
+                        "storage-string-load: slot {} holds {}, not a string",
+                        slot, other
+                    ))
+                }
+            };
+            if let Some(trace) = &mut state.trace {
+                trace.record_load("storage-string-load", (slot, None), &args, &value);
+            }
+            Ok(value)
+        })),
+    );
+
+    // Returns the length in bytes, like the compiled form
+    let string_store_state = state.clone();
+    env.borrow_mut().bindings.insert(
+        "storage-string-store!".to_string(),
+        Value::Procedure(Rc::new(move |args| {
+            check_args_count("storage-string-store!", &args, 2)?;
+            let slot = storage_word("storage-string-store!", &args[0])?;
+            let length = match &args[1] {
+                Value::String(s) => s.len(),
+                other => return Err(format!("storage-string-store!: not a string: {}", other)),
+            };
+            let mut state = string_store_state.borrow_mut();
+            let before = state.load(slot);
+            state.store(slot, args[1].clone());
+            let length = Value::Number(NumberKind::Integer(length as i64));
+            if let Some(trace) = &mut state.trace {
+                let location = (slot, None);
+                let result = Some(&length);
+                trace.record_store(
+                    "storage-string-store!",
+                    location,
+                    &args,
+                    result,
+                    &before,
+                    &args[1],
+                );
+            }
+            Ok(length)
+        })),
+    );
+
+    let length_state = state.clone();
+    env.borrow_mut().bindings.insert(
+        "storage-array-length".to_string(),
+        Value::Procedure(Rc::new(move |args| {
+            check_args_count("storage-array-length", &args, 1)?;
+            let slot = storage_word("storage-array-length", &args[0])?;
+            let mut state = length_state.borrow_mut();
+            let length = Value::Number(NumberKind::Integer(state.array(slot).len() as i64));
+            if let Some(trace) = &mut state.trace {
+                trace.record_load("storage-array-length", (slot, None), &args, &length);
+            }
+            Ok(length)
+        })),
+    );
+
+    let ref_state = state.clone();
+    env.borrow_mut().bindings.insert(
+        "storage-array-ref".to_string(),
+        Value::Procedure(Rc::new(move |args| {
+            check_args_count("storage-array-ref", &args, 2)?;
+            let slot = storage_word("storage-array-ref", &args[0])?;
+            let mut state = ref_state.borrow_mut();
+            let index = array_index("storage-array-ref", state.array(slot), &args[1])?;
+            let value = state.array(slot)[index].clone();
+            if let Some(trace) = &mut state.trace {
+                trace.record_load("storage-array-ref", (slot, Some(index)), &args, &value);
+            }
+            Ok(value)
+        })),
+    );
+
+    let set_state = state.clone();
+    env.borrow_mut().bindings.insert(
+        "storage-array-set!".to_string(),
+        Value::Procedure(Rc::new(move |args| {
+            check_args_count("storage-array-set!", &args, 3)?;
+            let slot = storage_word("storage-array-set!", &args[0])?;
+            let mut state = set_state.borrow_mut();
+            let index = array_index("storage-array-set!", state.array(slot), &args[1])?;
+            let before = std::mem::replace(
+                &mut state.arrays.entry(slot).or_default()[index],
+                args[2].clone(),
+            );
+            if let Some(trace) = &mut state.trace {
+                let location = (slot, Some(index));
+                let result = Some(&args[2]);
+                trace.record_store(
+                    "storage-array-set!",
+                    location,
+                    &args,
+                    result,
+                    &before,
+                    &args[2],
+                );
+            }
+            Ok(args[2].clone())
+        })),
+    );
+
+    let push_state = state.clone();
+    env.borrow_mut().bindings.insert(
+        "storage-array-push!".to_string(),
+        Value::Procedure(Rc::new(move |args| {
+            check_args_count("storage-array-push!", &args, 2)?;
+            let slot = storage_word("storage-array-push!", &args[0])?;
+            let mut state = push_state.borrow_mut();
+            let length = state.push(slot, args[1].clone());
+            let result = Value::Number(NumberKind::Integer(length as i64));
+            if let Some(trace) = &mut state.trace {
+                let location = (slot, Some(length - 1));
+                let zero = Value::Number(NumberKind::Integer(0));
+                let result = Some(&result);
+                trace.record_store(
+                    "storage-array-push!",
+                    location,
+                    &args,
+                    result,
+                    &zero,
+                    &args[1],
+                );
+            }
+            Ok(result)
+        })),
+    );
+
+    // Like CREATE2 in compiled code, a second deployment to the same address reverts
+    let create_state = state.clone();
+    env.borrow_mut().bindings.insert(
+        "deploy-create2".to_string(),
+        Value::Procedure(Rc::new(move |args| {
+            check_args_count("deploy-create2", &args, 2)?;
+            let salt = value_to_word(&args[0]).map_err(|e| format!("deploy-create2: {}", e))?;
+            let init_code = match &args[1] {
+                Value::Bytevector(code) => code.borrow().clone(),
+                _ => return Err("deploy-create2 requires init code as a bytevector".into()),
+            };
+            let mut state = create_state.borrow_mut();
+            let created = state.create2(&salt.to_be_bytes(), &init_code);
+            if let Some(trace) = &mut state.trace {
+                let gas = trace.schedule.create2_cost(init_code.len());
+                let result = created.map(Value::Address);
+                trace.record("CREATE2", "deploy-create2", &args, result.as_ref(), gas);
+            }
+            match created {
+                Some(address) => Ok(Value::Address(address)),
+                None => Err(format!(
+                    "{}: deploy-create2: a contract already exists at that address",
+                    REVERT_PREFIX
+                )),
+            }
+        })),
+    );
+
+    let revert_state = state.clone();
+    env.borrow_mut().bindings.insert(
+        "revert".to_string(),
+        Value::Procedure(Rc::new(move |args| {
+            check_args_count("revert", &args, 1)?;
+            if let Some(trace) = &mut revert_state.borrow_mut().trace {
+                trace.record("REVERT", "revert", &args, None, 0);
+            }
+            let reason = match &args[0] {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            Err(format!("{}: {}", REVERT_PREFIX, reason))
+        })),
+    );
+
+    let caller_state = state.clone();
+    env.borrow_mut().bindings.insert(
+        "caller".to_string(),
+        Value::Procedure(Rc::new(move |args| {
+            check_args_count("caller", &args, 0)?;
+            let mut state = caller_state.borrow_mut();
+            let value = state.caller.clone();
+            if let Some(trace) = &mut state.trace {
+                trace.record("CALLER", "caller", &args, Some(&value), trace.schedule.base);
+            }
+            Ok(value)
+        })),
+    );
+
+    let origin_state = state.clone();
+    env.borrow_mut().bindings.insert(
+        "tx-origin".to_string(),
+        Value::Procedure(Rc::new(move |args| {
+            check_args_count("tx-origin", &args, 0)?;
+            let mut state = origin_state.borrow_mut();
+            let value = state.origin.clone();
+            if let Some(trace) = &mut state.trace {
+                trace.record(
+                    "ORIGIN",
+                    "tx-origin",
+                    &args,
+                    Some(&value),
+                    trace.schedule.base,
+                );
+            }
+            Ok(value)
+        })),
+    );
+
+    let callvalue_state = state;
+    env.borrow_mut().bindings.insert(
+        "callvalue".to_string(),
+        Value::Procedure(Rc::new(move |args| {
+            check_args_count("callvalue", &args, 0)?;
+            let mut state = callvalue_state.borrow_mut();
+            let value = state.callvalue.clone();
+            if let Some(trace) = &mut state.trace {
+                trace.record(
+                    "CALLVALUE",
+                    "callvalue",
+                    &args,
+                    Some(&value),
+                    trace.schedule.base,
+                );
+            }
+            Ok(value)
+        })),
+    );
+}
+
+// What TLOAD or TSTORE costs under `schedule`, which fails when its hardfork
+// has no transient storage
+fn transient_gas(schedule: &GasSchedule, name: &str) -> Result<u64, String> {
+    schedule.transient.ok_or_else(|| {
+        format!(
+            "{} needs the cancun hardfork, but the trace is priced at {}",
+            name, schedule.hardfork
+        )
+    })
+}
+
+// A storage slot or stored word: an integer from 0 to 2^256 - 1, or an
+// address or 32-byte bytevector
+fn storage_word(name: &str, value: &Value) -> Result<Word, String> {
+    let negative = match value {
+        Value::Number(NumberKind::Integer(i)) => *i < 0,
+        Value::Number(NumberKind::BigInteger(n)) => n.sign() == Sign::Minus,
+        Value::Number(NumberKind::Real(r)) => *r < 0.0,
+        _ => false,
+    };
+    if negative {
+        return Err(format!("{}: {} is negative, not a uint256", name, value));
+    }
+    value_to_word(value).map_err(|e| format!("{}: {}", name, e))
+}
+
+// Check an index into a storage array
+fn array_index(name: &str, array: &[Value], index: &Value) -> Result<usize, String> {
+    let index = number_to_i64(index)?;
+    if index < 0 || index as usize >= array.len() {
+        return Err(format!("{}: index out of bounds: {}", name, index));
+    }
+    Ok(index as usize)
+}
+
+/// Remove the bindings added by [`register_simulated_evm`] from `env`
+pub fn unregister_simulated_evm(env: Rc<RefCell<Environment>>) {
+    let mut env = env.borrow_mut();
+    for name in SIMULATED_PRIMITIVES.iter().chain(WORD_PRIMITIVES.iter()) {
+        env.bindings.remove(name);
+    }
+}
@@ -0,0 +1,263 @@
+// Step traces of the simulated EVM
+//
+// A traced `EvmState` records each call of a simulated primitive as a step:
+// the opcode it stands for, the arguments it took off the stack, what it
+// pushed back, and for writes the storage it changed. The simulator runs
+// Lamina rather than bytecode, so steps are numbered in the order the
+// primitives ran rather than by program counter, and arithmetic in between
+// isn't traced.
+//
+// Each step carries the gas its opcode would cost under EIP-2929 and
+// EIP-2200, at the prices of the trace's gas schedule: for Cancun, 2100 for
+// the first access of a slot in the run and 100 after, and for a write 20000
+// to set a slot that started at zero, 2900 to change one that didn't, and
+// 100 when it was already changed or the value stays the same. Only the
+// primitives are priced, so totals are a lower bound.
+//
+// `to_json_lines` gives one JSON object per step:
+//
+//   {"step":0,"op":"SSTORE","primitive":"storage-store","stack":["0","5"],
+//    "result":null,"gas":22100,"gasUsed":22100,
+//    "storage":{"slot":0,"before":"0","after":"5"}}
+//
+// and `render` a table for the terminal.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::encoding::encode_hex;
+use crate::json::Json;
+use crate::value::{NumberKind, Value};
+
+use super::gas::GasSchedule;
+use super::word::Word;
+
+/// A traced storage location: a slot, or an element of the storage array
+/// whose length is kept in the slot
+pub type Location = (Word, Option<usize>);
+
+/// A write to storage
+#[derive(Clone, Debug, PartialEq)]
+pub struct StorageWrite {
+    pub slot: Word,
+    /// Element of the storage array at `slot`, for array writes
+    pub index: Option<usize>,
+    pub before: Value,
+    pub after: Value,
+}
+
+/// One call of a simulated primitive
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceStep {
+    pub index: usize,
+    /// The EVM opcode the primitive compiles to, e.g. `SSTORE`
+    pub op: &'static str,
+    pub primitive: &'static str,
+    /// Arguments, in the order the primitive took them
+    pub stack: Vec<Value>,
+    /// What the primitive returned; `None` when it reverted or returns nothing
+    pub result: Option<Value>,
+    pub gas: u64,
+    /// Gas of this step and every one before it
+    pub gas_used: u64,
+    pub storage: Option<StorageWrite>,
+}
+
+/// The steps of a run, with what the gas of the next step depends on
+#[derive(Clone, Debug, Default)]
+pub struct Trace {
+    pub steps: Vec<TraceStep>,
+    /// What the steps cost
+    pub schedule: GasSchedule,
+    /// Locations accessed so far, warm for the rest of the run
+    accessed: BTreeSet<Location>,
+    /// Value of each location written, before the run first wrote it
+    original: BTreeMap<Location, Value>,
+}
+
+fn is_zero(value: &Value) -> bool {
+    matches!(value, Value::Number(NumberKind::Integer(0)))
+}
+
+impl Trace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A trace pricing its steps with `schedule`
+    pub fn with_schedule(schedule: GasSchedule) -> Self {
+        Trace {
+            schedule,
+            ..Self::default()
+        }
+    }
+
+    fn access(&mut self, location: Location) -> u64 {
+        if self.accessed.insert(location) {
+            self.schedule.cold_sload
+        } else {
+            self.schedule.warm_access
+        }
+    }
+
+    /// Record a step that touches no storage
+    pub fn record(
+        &mut self,
+        op: &'static str,
+        primitive: &'static str,
+        stack: &[Value],
+        result: Option<&Value>,
+        gas: u64,
+    ) {
+        let gas_used = self.steps.last().map_or(0, |step| step.gas_used) + gas;
+        self.steps.push(TraceStep {
+            index: self.steps.len(),
+            op,
+            primitive,
+            stack: stack.to_vec(),
+            result: result.cloned(),
+            gas,
+            gas_used,
+            storage: None,
+        });
+    }
+
+    /// Record a read of `location`
+    pub fn record_load(
+        &mut self,
+        primitive: &'static str,
+        location: Location,
+        stack: &[Value],
+        result: &Value,
+    ) {
+        let gas = self.access(location);
+        self.record("SLOAD", primitive, stack, Some(result), gas);
+    }
+
+    /// Record a write of `after` over `before` at `location`
+    pub fn record_store(
+        &mut self,
+        primitive: &'static str,
+        location: Location,
+        stack: &[Value],
+        result: Option<&Value>,
+        before: &Value,
+        after: &Value,
+    ) {
+        let cold = if self.accessed.insert(location) {
+            self.schedule.cold_sload
+        } else {
+            0
+        };
+        let original = self
+            .original
+            .entry(location)
+            .or_insert_with(|| before.clone());
+        let gas = cold
+            + if before == after || before != original {
+                self.schedule.warm_access
+            } else if is_zero(original) {
+                self.schedule.sstore_set
+            } else {
+                self.schedule.sstore_reset
+            };
+        self.record("SSTORE", primitive, stack, result, gas);
+        if let Some(step) = self.steps.last_mut() {
+            step.storage = Some(StorageWrite {
+                slot: location.0,
+                index: location.1,
+                before: before.clone(),
+                after: after.clone(),
+            });
+        }
+    }
+}
+
+fn show(value: &Value) -> String {
+    match value {
+        Value::Bytevector(bytes) => encode_hex(&bytes.borrow()),
+        _ => value.to_string(),
+    }
+}
+
+impl TraceStep {
+    pub fn to_json(&self) -> Json {
+        let storage = match &self.storage {
+            Some(write) => {
+                // Slots too wide for a JSON number are written as a string
+                let slot = match write.slot.to_i64() {
+                    Some(slot) if (0..1 << 53).contains(&slot) => Json::Number(slot as f64),
+                    _ => Json::from(write.slot.to_string()),
+                };
+                let mut fields = vec![("slot", slot)];
+                if let Some(index) = write.index {
+                    fields.push(("index", Json::from(index)));
+                }
+                fields.push(("before", Json::from(show(&write.before))));
+                fields.push(("after", Json::from(show(&write.after))));
+                Json::object(fields)
+            }
+            None => Json::Null,
+        };
+        Json::object([
+            ("step", Json::from(self.index)),
+            ("op", Json::from(self.op)),
+            ("primitive", Json::from(self.primitive)),
+            (
+                "stack",
+                Json::Array(self.stack.iter().map(|v| Json::from(show(v))).collect()),
+            ),
+            (
+                "result",
+                self.result
+                    .as_ref()
+                    .map_or(Json::Null, |v| Json::from(show(v))),
+            ),
+            ("gas", Json::from(self.gas as usize)),
+            ("gasUsed", Json::from(self.gas_used as usize)),
+            ("storage", storage),
+        ])
+    }
+}
+
+/// The steps as JSON lines, one object per step
+pub fn to_json_lines(steps: &[TraceStep]) -> String {
+    steps
+        .iter()
+        .map(|step| format!("{}\n", step.to_json()))
+        .collect()
+}
+
+/// The steps as a table, one line per step
+pub fn render(steps: &[TraceStep]) -> String {
+    let mut text = format!(
+        "{:>4}  {:<9} {:>6} {:>7}  call\n",
+        "step", "op", "gas", "total"
+    );
+    for step in steps {
+        let mut call = format!("({}", step.primitive);
+        for value in &step.stack {
+            call += &format!(" {}", show(value));
+        }
+        call.push(')');
+        if let Some(result) = &step.result {
+            call += &format!(" => {}", show(result));
+        }
+        if let Some(write) = &step.storage {
+            let location = match write.index {
+                Some(index) => format!("{}[{}]", write.slot, index),
+                None => write.slot.to_string(),
+            };
+            call += &format!(
+                "  [slot {}: {} -> {}]",
+                location,
+                show(&write.before),
+                show(&write.after)
+            );
+        }
+        text += &format!(
+            "{:>4}  {:<9} {:>6} {:>7}  {}\n",
+            step.index, step.op, step.gas, step.gas_used, call
+        );
+    }
+    text
+}
@@ -0,0 +1,398 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+use num_bigint::{BigInt, Sign};
+
+use crate::number::arithmetic::from_bigint;
+use crate::value::{NumberKind, Value};
+
+/// A 256-bit EVM word, stored as four little-endian 64-bit limbs.
+///
+/// Lamina integers convert to words as two's complement, the same way a
+/// negative Solidity `int` is laid out on the stack, so any integer from
+/// -2^255 to 2^256 - 1 fits. Results are read back as integers, `i64`s while
+/// they fit and big integers past that: signed by the signed primitives such
+/// as `s/`, unsigned by the rest, such as the checked arithmetic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Word([u64; 4]);
+
+impl Word {
+    pub const ZERO: Word = Word([0; 4]);
+    pub const ONE: Word = Word([1, 0, 0, 0]);
+    pub const MAX: Word = Word([u64::MAX; 4]);
+
+    /// Sign-extend an `i64` to 256 bits
+    pub fn from_i64(value: i64) -> Self {
+        let fill = if value < 0 { u64::MAX } else { 0 };
+        Word([value as u64, fill, fill, fill])
+    }
+
+    /// Interpret up to 32 big-endian bytes as an unsigned word
+    pub fn from_be_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() > 32 {
+            return Err(format!("Word is at most 32 bytes, got {}", bytes.len()));
+        }
+
+        let mut padded = [0u8; 32];
+        padded[32 - bytes.len()..].copy_from_slice(bytes);
+
+        let mut limbs = [0u64; 4];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let start = 32 - (i + 1) * 8;
+            let mut chunk = [0u8; 8];
+            chunk.copy_from_slice(&padded[start..start + 8]);
+            *limb = u64::from_be_bytes(chunk);
+        }
+        Ok(Word(limbs))
+    }
+
+    pub fn to_be_bytes(self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (i, limb) in self.0.iter().enumerate() {
+            let start = 32 - (i + 1) * 8;
+            bytes[start..start + 8].copy_from_slice(&limb.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// The value as an `i64`, if the signed interpretation of the word fits
+    pub fn to_i64(self) -> Option<i64> {
+        let low = self.0[0] as i64;
+        (Word::from_i64(low) == self).then_some(low)
+    }
+
+    /// Two's complement of an integer from -2^255 to 2^256 - 1, or `None`
+    /// outside that range
+    pub fn from_bigint(value: &BigInt) -> Option<Self> {
+        let (sign, bytes) = value.to_bytes_be();
+        if sign != Sign::Minus {
+            return Word::from_be_bytes(&bytes).ok();
+        }
+        // Past -2^255 the negation wraps around to a positive word
+        let word = Word::from_be_bytes(&bytes).ok()?.negate();
+        word.is_negative().then_some(word)
+    }
+
+    /// The word read as unsigned
+    pub fn to_unsigned(self) -> BigInt {
+        BigInt::from_bytes_be(Sign::Plus, &self.to_be_bytes())
+    }
+
+    /// The word read as signed, in two's complement
+    pub fn to_signed(self) -> BigInt {
+        if self.is_negative() {
+            -self.negate().to_unsigned()
+        } else {
+            self.to_unsigned()
+        }
+    }
+
+    pub fn is_zero(self) -> bool {
+        self == Word::ZERO
+    }
+
+    /// Whether the top bit is set, i.e. the word is negative as a signed value
+    pub fn is_negative(self) -> bool {
+        self.bit(255)
+    }
+
+    pub fn bit(self, index: usize) -> bool {
+        (self.0[index / 64] >> (index % 64)) & 1 == 1
+    }
+
+    /// Two's complement negation, wrapping at 2^256
+    pub fn negate(self) -> Self {
+        (!self).wrapping_add(Word::ONE)
+    }
+
+    pub fn wrapping_add(self, other: Word) -> Self {
+        let mut limbs = [0u64; 4];
+        let mut carry = false;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let (sum, c1) = self.0[i].overflowing_add(other.0[i]);
+            let (sum, c2) = sum.overflowing_add(carry as u64);
+            *limb = sum;
+            carry = c1 || c2;
+        }
+        Word(limbs)
+    }
+
+    pub fn wrapping_sub(self, other: Word) -> Self {
+        self.wrapping_add(other.negate())
+    }
+
+    fn set_bit(&mut self, index: usize) {
+        self.0[index / 64] |= 1 << (index % 64);
+    }
+
+    /// SHL: shift left, dropping bits shifted past 256
+    pub fn shift_left(self, bits: u32) -> Self {
+        let bits = bits as usize;
+        let mut result = Word::ZERO;
+        for i in 0..256usize.saturating_sub(bits) {
+            if self.bit(i) {
+                result.set_bit(i + bits);
+            }
+        }
+        result
+    }
+
+    /// SHR: logical shift right, filling with zeros
+    pub fn shift_right(self, bits: u32) -> Self {
+        let bits = bits as usize;
+        let mut result = Word::ZERO;
+        for i in bits.min(256)..256 {
+            if self.bit(i) {
+                result.set_bit(i - bits);
+            }
+        }
+        result
+    }
+
+    /// SAR: arithmetic shift right, filling with the sign bit
+    pub fn shift_right_arithmetic(self, bits: u32) -> Self {
+        if self.is_negative() {
+            !((!self).shift_right(bits))
+        } else {
+            self.shift_right(bits)
+        }
+    }
+
+    /// Unsigned division with remainder; division by zero yields zero for both, as on the EVM
+    pub fn div_rem(self, divisor: Word) -> (Word, Word) {
+        if divisor.is_zero() {
+            return (Word::ZERO, Word::ZERO);
+        }
+
+        let mut quotient = Word::ZERO;
+        let mut remainder = Word::ZERO;
+        for i in (0..256).rev() {
+            remainder = remainder.shift_left(1);
+            remainder.0[0] |= self.bit(i) as u64;
+            if remainder >= divisor {
+                remainder = remainder.wrapping_sub(divisor);
+                quotient.set_bit(i);
+            }
+        }
+        (quotient, remainder)
+    }
+
+    /// ADD, or `None` when the sum doesn't fit in 256 bits
+    pub fn checked_add(self, other: Word) -> Option<Self> {
+        let sum = self.wrapping_add(other);
+        (sum >= self).then_some(sum)
+    }
+
+    /// SUB, or `None` when `other` is greater
+    pub fn checked_sub(self, other: Word) -> Option<Self> {
+        (self >= other).then(|| self.wrapping_sub(other))
+    }
+
+    /// MUL, keeping the low 256 bits of the product
+    pub fn wrapping_mul(self, other: Word) -> Self {
+        let [a, b, c, d, ..] = self.widening_mul(other);
+        Word([a, b, c, d])
+    }
+
+    /// MUL, or `None` when the product doesn't fit in 256 bits
+    pub fn checked_mul(self, other: Word) -> Option<Self> {
+        let product = self.widening_mul(other);
+        product[4..]
+            .iter()
+            .all(|limb| *limb == 0)
+            .then(|| Word([product[0], product[1], product[2], product[3]]))
+    }
+
+    /// ADDMOD: the sum is taken to 257 bits, and a zero modulus yields zero
+    pub fn add_mod(self, other: Word, modulus: Word) -> Self {
+        let sum = self.wrapping_add(other);
+        let carry = (sum < self) as u64;
+        let [a, b, c, d] = sum.0;
+        Word::wide_rem([a, b, c, d, carry, 0, 0, 0], modulus)
+    }
+
+    /// MULMOD: the product is taken to 512 bits, and a zero modulus yields zero
+    pub fn mul_mod(self, other: Word, modulus: Word) -> Self {
+        Word::wide_rem(self.widening_mul(other), modulus)
+    }
+
+    // The full 512-bit product, as little-endian limbs
+    fn widening_mul(self, other: Word) -> [u64; 8] {
+        let mut limbs = [0u64; 8];
+        for i in 0..4 {
+            let mut carry = 0u128;
+            for j in 0..4 {
+                let t = self.0[i] as u128 * other.0[j] as u128 + limbs[i + j] as u128 + carry;
+                limbs[i + j] = t as u64;
+                carry = t >> 64;
+            }
+            limbs[i + 4] = carry as u64;
+        }
+        limbs
+    }
+
+    // Remainder of a 512-bit number, zero for a zero modulus
+    fn wide_rem(limbs: [u64; 8], modulus: Word) -> Self {
+        if modulus.is_zero() {
+            return Word::ZERO;
+        }
+
+        let mut remainder = Word::ZERO;
+        for i in (0..512).rev() {
+            // The remainder is below the modulus, so it overflows by at most one bit
+            let overflow = remainder.is_negative();
+            remainder = remainder.shift_left(1);
+            remainder.0[0] |= (limbs[i / 64] >> (i % 64)) & 1;
+            if overflow || remainder >= modulus {
+                remainder = remainder.wrapping_sub(modulus);
+            }
+        }
+        remainder
+    }
+
+    fn abs(self) -> Self {
+        if self.is_negative() {
+            self.negate()
+        } else {
+            self
+        }
+    }
+
+    /// SLT: signed less-than
+    pub fn signed_lt(self, other: Word) -> bool {
+        match (self.is_negative(), other.is_negative()) {
+            (true, false) => true,
+            (false, true) => false,
+            _ => self < other,
+        }
+    }
+
+    /// SDIV: signed division truncating towards zero. `x / 0` is 0 and
+    /// `-2^255 / -1` wraps to `-2^255`.
+    pub fn signed_div(self, divisor: Word) -> Self {
+        let (quotient, _) = self.abs().div_rem(divisor.abs());
+        if self.is_negative() != divisor.is_negative() {
+            quotient.negate()
+        } else {
+            quotient
+        }
+    }
+
+    /// SMOD: signed remainder, taking the sign of the dividend. `x % 0` is 0.
+    pub fn signed_rem(self, divisor: Word) -> Self {
+        let (_, remainder) = self.abs().div_rem(divisor.abs());
+        if self.is_negative() {
+            remainder.negate()
+        } else {
+            remainder
+        }
+    }
+
+    /// SIGNEXTEND: extend the sign bit of the low `byte + 1` bytes to the full word
+    pub fn sign_extend(self, byte: Word) -> Self {
+        let byte = match byte.to_i64() {
+            Some(b) if (0..31).contains(&b) => b as usize,
+            _ => return self,
+        };
+
+        let sign_bit = byte * 8 + 7;
+        let mut limbs = self.0;
+        for i in sign_bit + 1..256 {
+            if self.bit(sign_bit) {
+                limbs[i / 64] |= 1 << (i % 64);
+            } else {
+                limbs[i / 64] &= !(1 << (i % 64));
+            }
+        }
+        Word(limbs)
+    }
+}
+
+/// Written as the unsigned integer
+impl fmt::Display for Word {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_unsigned())
+    }
+}
+
+impl Not for Word {
+    type Output = Word;
+
+    fn not(self) -> Word {
+        Word(self.0.map(|limb| !limb))
+    }
+}
+
+impl BitAnd for Word {
+    type Output = Word;
+
+    fn bitand(self, other: Word) -> Word {
+        Word([0, 1, 2, 3].map(|i| self.0[i] & other.0[i]))
+    }
+}
+
+impl BitOr for Word {
+    type Output = Word;
+
+    fn bitor(self, other: Word) -> Word {
+        Word([0, 1, 2, 3].map(|i| self.0[i] | other.0[i]))
+    }
+}
+
+impl BitXor for Word {
+    type Output = Word;
+
+    fn bitxor(self, other: Word) -> Word {
+        Word([0, 1, 2, 3].map(|i| self.0[i] ^ other.0[i]))
+    }
+}
+
+impl PartialOrd for Word {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Unsigned ordering
+impl Ord for Word {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.iter().rev().cmp(other.0.iter().rev())
+    }
+}
+
+/// Convert an integer, integral real or bytevector of at most 32 bytes to a word
+pub fn value_to_word(value: &Value) -> Result<Word, String> {
+    match value {
+        Value::Number(NumberKind::Integer(i)) => Ok(Word::from_i64(*i)),
+        Value::Number(NumberKind::BigInteger(n)) => {
+            Word::from_bigint(n).ok_or_else(|| format!("{} doesn't fit in a 256-bit word", n))
+        }
+        Value::Number(NumberKind::Real(r)) if r.fract() == 0.0 && r.abs() < i64::MAX as f64 => {
+            Ok(Word::from_i64(*r as i64))
+        }
+        Value::Bytevector(bytes) => Word::from_be_bytes(&bytes.borrow()),
+        Value::Address(bytes) => Word::from_be_bytes(bytes),
+        _ => Err(format!(
+            "Expected an integer or 32-byte word, got {}",
+            value
+        )),
+    }
+}
+
+/// Convert a word back to the integer it is read as signed
+pub fn word_to_value(word: Word) -> Value {
+    match word.to_i64() {
+        Some(i) => Value::Number(NumberKind::Integer(i)),
+        None => Value::Number(from_bigint(word.to_signed())),
+    }
+}
+
+/// Convert a word back to the integer it is read as unsigned, from 0 to
+/// 2^256 - 1
+pub fn word_to_unsigned_value(word: Word) -> Value {
+    match word.to_i64() {
+        Some(i) if i >= 0 => Value::Number(NumberKind::Integer(i)),
+        _ => Value::Number(from_bigint(word.to_unsigned())),
+    }
+}
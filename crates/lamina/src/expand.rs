@@ -0,0 +1,206 @@
+// Compile-time phase
+//
+// `define-for-syntax` and `begin-for-syntax` forms run while a program is
+// expanded, in an environment of their own, and `(compile-time expr)` is
+// replaced by the literal value of `expr` in that environment. Backends expand
+// a program before lowering it, so the compiled code only sees the results.
+//
+// The interpreter has a single phase: there the forms behave like `define`,
+// `begin` and plain evaluation of `expr`.
+//
+// `cond-expand` keeps the clause the target being compiled for selects, its
+// body spliced in place of the form; see `targets`.
+//
+// Macros defined with `define-syntax` or `let-syntax` are expanded here, so
+// backends never see a macro use; see `evaluator::macros`.
+//
+// Procedures defined in the interpreter can be lowered too: a named procedure
+// value placed among a program's forms expands to the `define` that makes it.
+// Only its source is carried over, so the variables it refers to must be
+// defined in the program it lands in.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::evaluator::environment::setup_initial_env;
+use crate::evaluator::eval_with_env;
+use crate::evaluator::macros::{eval_define_syntax, syntax_scope};
+use crate::evaluator::special_forms::eval_define;
+use crate::targets::{self, Target, INTERPRETER};
+use crate::value::{Environment, NumberKind, Value};
+
+/// Expand `expr` with a fresh compile-time environment
+pub fn expand(expr: &Value) -> Result<Value, Error> {
+    expand_with_env(expr, setup_initial_env())
+}
+
+/// Expand `expr`, running its compile-time forms in `env`.
+///
+/// Compile-time definitions are removed from the result; a program that
+/// consists of nothing else expands to the empty list.
+pub fn expand_with_env(expr: &Value, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
+    Ok(expand_form(expr, &env, &INTERPRETER)?.unwrap_or(Value::Nil))
+}
+
+/// Expand `expr` to be compiled for `target`, failing on the first call to a
+/// primitive the target lacks
+pub fn expand_for(expr: &Value, target: &Target) -> Result<Value, Error> {
+    if let Some(unavailable) = targets::find_unavailable(expr, target)? {
+        return Err(Error::Compilation(
+            target.unavailable_message(&unavailable.primitive),
+        ));
+    }
+    Ok(expand_form(expr, &setup_initial_env(), target)?.unwrap_or(Value::Nil))
+}
+
+// compile-time special form for the interpreter: (compile-time expr)
+pub fn eval_compile_time(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
+    eval_with_env(compile_time_argument(&args)?, env)
+}
+
+// Expand one form, returning `None` when it only exists at compile time
+fn expand_form(
+    expr: &Value,
+    env: &Rc<RefCell<Environment>>,
+    target: &Target,
+) -> Result<Option<Value>, Error> {
+    let pair = match expr {
+        Value::Pair(pair) => pair,
+        Value::Lambda(lambda) => {
+            return lambda.definition().map(Some).ok_or_else(|| {
+                Error::Compilation(format!(
+                    "Cannot lower an anonymous procedure {}",
+                    lambda.signature()
+                ))
+            })
+        }
+        _ => return Ok(Some(expr.clone())),
+    };
+
+    if let Value::Symbol(head) = &pair.0 {
+        let found = env.borrow().macro_named(head);
+        if let Some(found) = found {
+            return expand_form(&found.expand(expr)?, env, target);
+        }
+        match head.as_str() {
+            "quote" => return Ok(Some(expr.clone())),
+            "define-syntax" => {
+                eval_define_syntax(&pair.1, env)?;
+                return Ok(None);
+            }
+            "let-syntax" | "letrec-syntax" => {
+                let (scope, body) = syntax_scope(&pair.1, env)?;
+                let begin = Value::cons(Value::Symbol("begin".to_string()), body);
+                return expand_form(&begin, &scope, target);
+            }
+            // The edition only concerns the front end
+            "lamina-edition" => return Ok(None),
+            "define-for-syntax" => {
+                eval_define(pair.1.clone(), env.clone())?;
+                return Ok(None);
+            }
+            "begin-for-syntax" => {
+                let mut forms = &pair.1;
+                while let Value::Pair(form) = forms {
+                    eval_with_env(form.0.clone(), env.clone())?;
+                    forms = &form.1;
+                }
+                return Ok(None);
+            }
+            "compile-time" => {
+                let value = eval_with_env(compile_time_argument(&pair.1)?, env.clone())?;
+                return to_literal(value).map(Some);
+            }
+            "cond-expand" => {
+                let body = target.select(&pair.1)?;
+                let begin = Value::cons(Value::Symbol("begin".to_string()), body);
+                return expand_form(&begin, env, target);
+            }
+            _ => {}
+        }
+    }
+
+    expand_list(expr, env, target).map(Some)
+}
+
+// Expand the elements of a list, dropping the ones that only exist at compile time
+fn expand_list(
+    list: &Value,
+    env: &Rc<RefCell<Environment>>,
+    target: &Target,
+) -> Result<Value, Error> {
+    // Expand in source order so earlier compile-time definitions are visible
+    let mut expanded = Vec::new();
+    let mut rest = list;
+    while let Value::Pair(pair) = rest {
+        match cond_expand_body(&pair.0, target)? {
+            Some(body) => splice(&body, &mut expanded, env, target)?,
+            None => expanded.extend(expand_form(&pair.0, env, target)?),
+        }
+        rest = &pair.1;
+    }
+    Ok(expanded
+        .into_iter()
+        .rev()
+        .fold(rest.clone(), |rest, form| Value::cons(form, rest)))
+}
+
+// The body `target` selects, if `expr` is a `cond-expand` form
+fn cond_expand_body(expr: &Value, target: &Target) -> Result<Option<Value>, Error> {
+    match expr {
+        Value::Pair(pair) if matches!(&pair.0, Value::Symbol(head) if head == "cond-expand") => {
+            target.select(&pair.1).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+// Expand the forms of `body` into `expanded`
+fn splice(
+    body: &Value,
+    expanded: &mut Vec<Value>,
+    env: &Rc<RefCell<Environment>>,
+    target: &Target,
+) -> Result<(), Error> {
+    let mut rest = body;
+    while let Value::Pair(pair) = rest {
+        expanded.extend(expand_form(&pair.0, env, target)?);
+        rest = &pair.1;
+    }
+    Ok(())
+}
+
+fn compile_time_argument(args: &Value) -> Result<Value, Error> {
+    match args {
+        Value::Pair(pair) if matches!(pair.1, Value::Nil) => Ok(pair.0.clone()),
+        _ => Err(Error::Runtime(
+            "compile-time expects exactly one expression".into(),
+        )),
+    }
+}
+
+// The syntax that evaluates to `value`
+fn to_literal(value: Value) -> Result<Value, Error> {
+    match value {
+        // Arithmetic works on reals; integral results become integer literals
+        Value::Number(NumberKind::Real(r)) if r.fract() == 0.0 && r.abs() < i64::MAX as f64 => {
+            Ok(Value::Number(NumberKind::Integer(r as i64)))
+        }
+        Value::Number(_)
+        | Value::String(_)
+        | Value::Boolean(_)
+        | Value::Character(_)
+        | Value::Address(_)
+        | Value::Bytevector(_)
+        | Value::Nil => Ok(value),
+        Value::Symbol(_) | Value::Pair(_) | Value::Vector(_) => Ok(Value::cons(
+            Value::Symbol("quote".to_string()),
+            Value::cons(value, Value::Nil),
+        )),
+        other => Err(Error::Compilation(format!(
+            "compile-time result cannot be embedded as a literal: {}",
+            other
+        ))),
+    }
+}
@@ -0,0 +1,179 @@
+// Foreign objects: Rust values passed through Lamina without conversion
+//
+// `foreign_to_value` wraps any Rust value in a `Value::Foreign`, which Lamina
+// code can hold, store and pass back to Rust, where `get_foreign` borrows it
+// again as its own type. Nothing is copied on the way. Lamina calls methods on
+// a foreign object with `(call-method object 'name arg ...)`, looked up in the
+// `MethodTable` registered for its type with
+// `InterpreterBuilder::with_methods`.
+
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::value::{Environment, Value};
+
+/// A method as registered, taking the object itself and the call's arguments
+type Method<T> = Rc<dyn Fn(&T, Vec<Value>) -> Result<Value, String>>;
+
+/// The methods Lamina can call on foreign objects of type `T`
+pub struct MethodTable<T> {
+    methods: HashMap<String, Method<T>>,
+}
+
+impl<T: Any> Default for MethodTable<T> {
+    fn default() -> Self {
+        MethodTable {
+            methods: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Any> MethodTable<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a method, called as `(call-method object 'name arg ...)`
+    pub fn add_method<F>(&mut self, name: &str, method: F)
+    where
+        F: Fn(&T, Vec<Value>) -> Result<Value, String> + 'static,
+    {
+        self.methods.insert(name.to_string(), Rc::new(method));
+    }
+}
+
+/// A method with its receiver's type erased
+type ErasedMethod = Rc<dyn Fn(&dyn Any, Vec<Value>) -> Result<Value, String>>;
+
+/// Method tables by the type they belong to
+#[derive(Default)]
+pub struct MethodRegistry {
+    tables: HashMap<TypeId, HashMap<String, ErasedMethod>>,
+}
+
+impl MethodRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the methods of `table`, replacing any of the same name
+    pub fn register<T: Any>(&mut self, table: MethodTable<T>) {
+        let methods = self.tables.entry(TypeId::of::<T>()).or_default();
+        for (name, method) in table.methods {
+            let erased: ErasedMethod =
+                Rc::new(
+                    move |object: &dyn Any, args| match object.downcast_ref::<T>() {
+                        Some(object) => method(object, args),
+                        None => Err("Foreign object does not match its method table".into()),
+                    },
+                );
+            methods.insert(name, erased);
+        }
+    }
+
+    /// Call the method `name` of `object`
+    pub fn call(
+        &self,
+        object: &Rc<dyn Any>,
+        type_name: &str,
+        name: &str,
+        args: Vec<Value>,
+    ) -> Result<Value, String> {
+        let object: &dyn Any = object.as_ref();
+        let method = self
+            .tables
+            .get(&object.type_id())
+            .and_then(|methods| methods.get(name))
+            .ok_or_else(|| format!("No method {} on {}", name, type_name))?;
+        method(object, args)
+    }
+
+    /// Whether `object` has a method `name`
+    pub fn has_method(&self, object: &Rc<dyn Any>, name: &str) -> bool {
+        let object: &dyn Any = object.as_ref();
+        self.tables
+            .get(&object.type_id())
+            .is_some_and(|methods| methods.contains_key(name))
+    }
+}
+
+/// Wrap a Rust value for Lamina, printed as `#<foreign type-name>`
+pub fn foreign_to_value<T: Any>(object: T) -> Value {
+    Value::Foreign(Rc::new(object), std::any::type_name::<T>())
+}
+
+/// Borrow the Rust value in a foreign object, if it is a `T`
+pub fn get_foreign<T: Any>(value: &Value) -> Result<&T, String> {
+    let type_name = std::any::type_name::<T>();
+    match value {
+        Value::Foreign(object, _) => object
+            .downcast_ref::<T>()
+            .ok_or_else(|| format!("Expected a foreign {}, got {}", type_name, value)),
+        _ => Err(format!("Expected a foreign {}, got {}", type_name, value)),
+    }
+}
+
+fn define(env: &Rc<RefCell<Environment>>, name: &str, procedure: Value) {
+    env.borrow_mut()
+        .bindings
+        .insert(name.to_string(), procedure);
+}
+
+/// Bind the procedures on foreign objects, calling methods from `methods`
+pub fn register_foreign_procedures(env: &Rc<RefCell<Environment>>, methods: Rc<MethodRegistry>) {
+    define(
+        env,
+        "foreign?",
+        Value::Procedure(Rc::new(|args: Vec<Value>| match args.as_slice() {
+            [value] => Ok(Value::Boolean(matches!(value, Value::Foreign(..)))),
+            _ => Err("foreign? requires exactly 1 argument".into()),
+        })),
+    );
+
+    define(
+        env,
+        "foreign-type",
+        Value::Procedure(Rc::new(|args: Vec<Value>| match args.as_slice() {
+            [Value::Foreign(_, type_name)] => Ok(Value::String(type_name.to_string())),
+            _ => Err("foreign-type requires a foreign object".into()),
+        })),
+    );
+
+    // (call-method object 'name arg ...)
+    let registry = methods.clone();
+    define(
+        env,
+        "call-method",
+        Value::Procedure(Rc::new(move |mut args: Vec<Value>| {
+            if args.len() < 2 {
+                return Err("call-method requires an object and a method name".into());
+            }
+            let rest = args.split_off(2);
+            match (&args[0], &args[1]) {
+                (Value::Foreign(object, type_name), Value::Symbol(name)) => {
+                    registry.call(object, type_name, name, rest)
+                }
+                (Value::Foreign(..), _) => Err("call-method requires a method name symbol".into()),
+                (other, _) => Err(format!(
+                    "call-method requires a foreign object, got {}",
+                    other
+                )),
+            }
+        })),
+    );
+
+    // (has-method? object 'name)
+    define(
+        env,
+        "has-method?",
+        Value::Procedure(Rc::new(move |args: Vec<Value>| match args.as_slice() {
+            [Value::Foreign(object, _), Value::Symbol(name)] => {
+                Ok(Value::Boolean(methods.has_method(object, name)))
+            }
+            [_, Value::Symbol(_)] => Ok(Value::Boolean(false)),
+            _ => Err("has-method? requires an object and a method name".into()),
+        })),
+    );
+}
@@ -0,0 +1,310 @@
+// Handles to Rust objects, for FFI modules wrapping external resources
+//
+// A handle owns a Rust object, such as a file, socket or database connection,
+// and runs its finalizer exactly once: when Lamina code calls `(close! handle)`,
+// or when the last reference to the handle is dropped, whichever comes first.
+// Registries that look resources up by name hold them in a `WeakTable`, so the
+// registry alone does not keep a resource open.
+//
+//   (define conn (db/connect "app.db"))
+//   (call-with-handle conn (lambda (c) (db/query c "select 1")))
+//   (handle-closed? conn)   ; => #t
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+use crate::evaluator::apply;
+use crate::value::{Environment, Value};
+
+type Finalizer = Box<dyn FnOnce(Box<dyn Any>)>;
+
+/// A Rust object owned by Lamina, shown as `#<type-name>`
+pub struct Opaque {
+    type_name: String,
+    object: RefCell<Option<Box<dyn Any>>>,
+    finalizer: RefCell<Option<Finalizer>>,
+}
+
+impl Opaque {
+    /// A handle that drops `object` when it is closed
+    pub fn new<T: Any>(type_name: &str, object: T) -> Self {
+        Opaque {
+            type_name: type_name.to_string(),
+            object: RefCell::new(Some(Box::new(object))),
+            finalizer: RefCell::new(None),
+        }
+    }
+
+    /// A handle that passes `object` to `finalizer` when it is closed
+    pub fn with_finalizer<T: Any>(
+        type_name: &str,
+        object: T,
+        finalizer: impl FnOnce(T) + 'static,
+    ) -> Self {
+        let handle = Self::new(type_name, object);
+        *handle.finalizer.borrow_mut() = Some(Box::new(move |object: Box<dyn Any>| {
+            if let Ok(object) = object.downcast::<T>() {
+                finalizer(*object);
+            }
+        }));
+        handle
+    }
+
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.object.borrow().is_none()
+    }
+
+    /// Run `f` on the object, failing if the handle is closed or holds
+    /// something other than a `T`
+    pub fn with<T: Any, R>(&self, f: impl FnOnce(&mut T) -> R) -> Result<R, String> {
+        let mut object = self.object.borrow_mut();
+        let object = object
+            .as_mut()
+            .ok_or_else(|| format!("{} handle is closed", self.type_name))?;
+        let object = object
+            .downcast_mut::<T>()
+            .ok_or_else(|| format!("{} handle holds a different type", self.type_name))?;
+        Ok(f(object))
+    }
+
+    /// Finalize the object now, returning whether the handle was still open
+    pub fn close(&self) -> bool {
+        let object = self.object.borrow_mut().take();
+        let finalizer = self.finalizer.borrow_mut().take();
+        match (object, finalizer) {
+            (Some(object), Some(finalizer)) => {
+                finalizer(object);
+                true
+            }
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+impl Drop for Opaque {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Wrap a Rust object in a handle value
+pub fn opaque_to_value<T: Any>(type_name: &str, object: T) -> Value {
+    Value::Opaque(Rc::new(Opaque::new(type_name, object)))
+}
+
+/// The handle in `value`, checking it holds a `type_name` object
+pub fn value_to_opaque(value: &Value, type_name: &str) -> Result<Rc<Opaque>, String> {
+    match value {
+        Value::Opaque(handle) if handle.type_name == type_name => Ok(handle.clone()),
+        Value::Opaque(handle) => Err(format!(
+            "Expected a {} handle, got a {} handle",
+            type_name, handle.type_name
+        )),
+        _ => Err(format!("Expected a {} handle, got {}", type_name, value)),
+    }
+}
+
+/// Handles by key, held weakly: an entry goes away once nothing else refers
+/// to its handle
+#[derive(Default)]
+pub struct WeakTable {
+    entries: HashMap<String, Weak<Opaque>>,
+}
+
+impl WeakTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, key: &str, handle: &Rc<Opaque>) {
+        self.prune();
+        self.entries.insert(key.to_string(), Rc::downgrade(handle));
+    }
+
+    /// The handle under `key`, if it is still alive
+    pub fn get(&self, key: &str) -> Option<Rc<Opaque>> {
+        self.entries.get(key).and_then(Weak::upgrade)
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Rc<Opaque>> {
+        self.entries.remove(key).and_then(|weak| weak.upgrade())
+    }
+
+    /// The keys of the live entries, in no particular order
+    pub fn keys(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|(_, weak)| weak.strong_count() > 0)
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Forget the entries whose handles were dropped
+    fn prune(&mut self) {
+        self.entries.retain(|_, weak| weak.strong_count() > 0);
+    }
+}
+
+const WEAK_TABLE: &str = "weak-table";
+
+fn handle_argument(name: &str, args: &[Value]) -> Result<Rc<Opaque>, String> {
+    match args.first() {
+        Some(Value::Opaque(handle)) => Ok(handle.clone()),
+        _ => Err(format!("{} requires a handle", name)),
+    }
+}
+
+/// Weak tables key on strings, symbols and numbers
+fn table_key(name: &str, key: &Value) -> Result<String, String> {
+    match key {
+        Value::String(_) | Value::Symbol(_) | Value::Number(_) => Ok(key.to_string()),
+        _ => Err(format!("{} keys must be strings, symbols or numbers", name)),
+    }
+}
+
+fn with_table<R>(
+    name: &str,
+    args: &[Value],
+    f: impl FnOnce(&mut WeakTable) -> R,
+) -> Result<R, String> {
+    let table = args
+        .first()
+        .ok_or_else(|| format!("{} requires a weak table", name))
+        .and_then(|table| value_to_opaque(table, WEAK_TABLE))?;
+    table.with(f)
+}
+
+fn define(env: &Rc<RefCell<Environment>>, name: &str, procedure: Value) {
+    env.borrow_mut()
+        .bindings
+        .insert(name.to_string(), procedure);
+}
+
+pub fn register_handle_procedures(env: &Rc<RefCell<Environment>>) {
+    define(
+        env,
+        "handle?",
+        Value::Procedure(Rc::new(|args: Vec<Value>| match args.as_slice() {
+            [value] => Ok(Value::Boolean(matches!(value, Value::Opaque(_)))),
+            _ => Err("handle? requires exactly 1 argument".into()),
+        })),
+    );
+
+    define(
+        env,
+        "handle-closed?",
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            Ok(Value::Boolean(
+                handle_argument("handle-closed?", &args)?.is_closed(),
+            ))
+        })),
+    );
+
+    // (close! handle) runs the finalizer; closing twice does nothing
+    define(
+        env,
+        "close!",
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            handle_argument("close!", &args)?.close();
+            Ok(Value::Nil)
+        })),
+    );
+
+    // (call-with-handle handle proc) closes the handle once proc returns or
+    // fails
+    define(
+        env,
+        "call-with-handle",
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            let handle = handle_argument("call-with-handle", &args)?;
+            let proc = match args.get(1) {
+                Some(proc) if proc.is_procedure() && args.len() == 2 => proc,
+                _ => return Err("call-with-handle requires a handle and a procedure".into()),
+            };
+            let result = apply(proc, vec![args[0].clone()]);
+            handle.close();
+            result
+        })),
+    );
+
+    define(
+        env,
+        "make-weak-table",
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            if !args.is_empty() {
+                return Err("make-weak-table takes no arguments".into());
+            }
+            Ok(opaque_to_value(WEAK_TABLE, WeakTable::new()))
+        })),
+    );
+
+    // (weak-table-set! table key handle)
+    define(
+        env,
+        "weak-table-set!",
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            let name = "weak-table-set!";
+            let (key, handle) = match args.as_slice() {
+                [_, key, Value::Opaque(handle)] => (table_key(name, key)?, handle.clone()),
+                [_, _, _] => return Err(format!("{} values must be handles", name)),
+                _ => return Err(format!("{} requires a table, a key and a handle", name)),
+            };
+            with_table(name, &args, |table| table.insert(&key, &handle))?;
+            Ok(Value::Nil)
+        })),
+    );
+
+    // (weak-table-ref table key [default]) is default, or #f, once the
+    // handle is gone
+    define(
+        env,
+        "weak-table-ref",
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            let name = "weak-table-ref";
+            let (key, default) = match args.as_slice() {
+                [_, key] => (table_key(name, key)?, Value::Boolean(false)),
+                [_, key, default] => (table_key(name, key)?, default.clone()),
+                _ => {
+                    return Err(format!(
+                        "{} requires a table, a key and an optional default",
+                        name
+                    ))
+                }
+            };
+            let handle = with_table(name, &args, |table| table.get(&key))?;
+            Ok(handle.map_or(default, Value::Opaque))
+        })),
+    );
+
+    define(
+        env,
+        "weak-table-delete!",
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            let name = "weak-table-delete!";
+            let key = match args.as_slice() {
+                [_, key] => table_key(name, key)?,
+                _ => return Err(format!("{} requires a table and a key", name)),
+            };
+            with_table(name, &args, |table| table.remove(&key))?;
+            Ok(Value::Nil)
+        })),
+    );
+
+    define(
+        env,
+        "weak-table-count",
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            let count = with_table("weak-table-count", &args, |table| table.keys().len())?;
+            Ok(Value::Number(crate::value::NumberKind::Integer(
+                count as i64,
+            )))
+        })),
+    );
+}
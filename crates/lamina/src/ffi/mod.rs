@@ -1,3 +1,5 @@
+pub mod foreign;
+pub mod handle;
 pub mod rustlib;
 
 use std::cell::RefCell;
@@ -52,12 +54,15 @@ impl FFIRegistry {
     }
 }
 
-// Global registry
+// Functions registered through the thread-wide functions below
 thread_local! {
     static FFI_REGISTRY: RefCell<FFIRegistry> = RefCell::new(FFIRegistry::new());
 }
 
 /// Register a Rust function that can be called from Lamina
+#[deprecated(
+    note = "functions are registered per interpreter, use `InterpreterBuilder::with_function`"
+)]
 pub fn register_function<F>(name: &str, func: F)
 where
     F: Fn(Vec<Value>) -> Result<Value, String> + 'static,
@@ -68,6 +73,9 @@ where
 }
 
 /// Load all registered functions into the given environment
+#[deprecated(
+    note = "functions are registered per interpreter, use `InterpreterBuilder::with_function`"
+)]
 pub fn load_ffi_functions(env: &Rc<RefCell<Environment>>) -> Result<(), Error> {
     FFI_REGISTRY.with(|registry| registry.borrow().load_into_env(env))
 }
@@ -134,6 +142,71 @@ pub fn value_to_string(value: &Value) -> Result<String, String> {
     }
 }
 
+/// A Rust type Lamina values convert to
+pub trait FromLamina: Sized {
+    fn from_lamina(value: &Value) -> Result<Self, String>;
+}
+
+impl FromLamina for Value {
+    fn from_lamina(value: &Value) -> Result<Self, String> {
+        Ok(value.clone())
+    }
+}
+
+impl FromLamina for i64 {
+    fn from_lamina(value: &Value) -> Result<Self, String> {
+        value_to_i64(value)
+    }
+}
+
+impl FromLamina for f64 {
+    fn from_lamina(value: &Value) -> Result<Self, String> {
+        value_to_f64(value)
+    }
+}
+
+impl FromLamina for bool {
+    fn from_lamina(value: &Value) -> Result<Self, String> {
+        value_to_bool(value)
+    }
+}
+
+impl FromLamina for String {
+    fn from_lamina(value: &Value) -> Result<Self, String> {
+        value_to_string(value)
+    }
+}
+
+/// A proper list or a vector, converting each element
+impl<T: FromLamina> FromLamina for Vec<T> {
+    fn from_lamina(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Vector(items) => items.borrow().iter().map(T::from_lamina).collect(),
+            Value::Nil | Value::Pair(_) => {
+                let mut items = Vec::new();
+                let mut rest = value;
+                while let Value::Pair(pair) = rest {
+                    items.push(T::from_lamina(&pair.0)?);
+                    rest = &pair.1;
+                }
+                match rest {
+                    Value::Nil => Ok(items),
+                    _ => Err(format!("Cannot convert improper list {} to a Vec", value)),
+                }
+            }
+            _ => Err(format!("Cannot convert {} to a Vec", value)),
+        }
+    }
+}
+
+impl<T: FromLamina> TryFrom<Value> for Vec<T> {
+    type Error = String;
+
+    fn try_from(value: Value) -> Result<Self, String> {
+        Vec::from_lamina(&value)
+    }
+}
+
 /// Convenience function to create a RustFn value directly from a function
 /// This helps prevent "dead code" warnings since we're explicitly constructing RustFn variants
 #[allow(dead_code)]
@@ -41,37 +41,68 @@ impl RustModule {
     }
 }
 
-// Registry of all available Rust modules
+/// Rust modules available for import, by name
+#[derive(Default)]
+pub struct ModuleRegistry {
+    modules: HashMap<String, RustModule>,
+}
+
+impl ModuleRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, module: RustModule) {
+        self.modules.insert(module.name.clone(), module);
+    }
+
+    /// Import the functions of a registered module into the given environment
+    pub fn import(&self, module_name: &str, env: &Rc<RefCell<Environment>>) -> Result<(), String> {
+        match self.modules.get(module_name) {
+            Some(module) => {
+                module.import_into_env(env);
+                Ok(())
+            }
+            None => Err(format!("Rust module not found: {}", module_name)),
+        }
+    }
+}
+
+// Modules registered through the thread-wide functions below
 thread_local! {
-    static MODULES: RefCell<HashMap<String, RustModule>> = RefCell::new(HashMap::new());
+    static MODULES: RefCell<ModuleRegistry> = RefCell::new(ModuleRegistry::new());
 }
 
 /// Register a Rust module for use in Lamina
+#[deprecated(
+    note = "modules are registered per interpreter, use `InterpreterBuilder::with_module`"
+)]
 pub fn register_module(module: RustModule) {
-    MODULES.with(|modules| {
-        modules.borrow_mut().insert(module.name.clone(), module);
-    });
+    MODULES.with(|modules| modules.borrow_mut().register(module));
 }
 
 /// Import a Rust module into the given environment
+#[deprecated(note = "modules are registered per interpreter, use `Interpreter::import_module`")]
 pub fn import_module(module_name: &str, env: &Rc<RefCell<Environment>>) -> Result<(), String> {
-    MODULES.with(|modules| {
-        let modules = modules.borrow();
-        if let Some(module) = modules.get(module_name) {
-            module.import_into_env(env);
-            Ok(())
-        } else {
-            Err(format!("Rust module not found: {}", module_name))
-        }
-    })
+    MODULES.with(|modules| modules.borrow().import(module_name, env))
 }
 
-/// Utility function to create and register a module in one step
-pub fn create_module<F>(name: &str, setup_fn: F)
+/// Build a module with `setup_fn`
+pub fn build_module<F>(name: &str, setup_fn: F) -> RustModule
 where
     F: FnOnce(&mut RustModule),
 {
     let mut module = RustModule::new(name);
     setup_fn(&mut module);
-    register_module(module);
+    module
+}
+
+/// Utility function to create and register a module in one step
+#[deprecated(note = "use `build_module` with `InterpreterBuilder::with_module`")]
+#[allow(deprecated)]
+pub fn create_module<F>(name: &str, setup_fn: F)
+where
+    F: FnOnce(&mut RustModule),
+{
+    register_module(build_module(name, setup_fn));
 }
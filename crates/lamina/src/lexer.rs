@@ -1,5 +1,6 @@
 use crate::error::Error;
 use logos::Logos;
+use std::ops::Range;
 
 #[derive(Logos, Debug, PartialEq, Clone)]
 pub enum Token {
@@ -12,6 +13,28 @@ pub enum Token {
     #[token("'")]
     Quote,
 
+    #[token("`")]
+    Quasiquote,
+
+    #[token(",")]
+    Unquote,
+
+    #[token(",@")]
+    UnquoteSplicing,
+
+    #[token("#(")]
+    VectorOpen,
+
+    #[token("#u8(")]
+    BytevectorOpen,
+
+    // `#0=` labels the datum after it, so `#0#` can refer to it again
+    #[regex(r"#[0-9]+=", callback = |lex| { let slice = lex.slice(); slice[1..slice.len() - 1].parse::<usize>().ok() })]
+    DatumLabel(usize),
+
+    #[regex(r"#[0-9]+#", callback = |lex| { let slice = lex.slice(); slice[1..slice.len() - 1].parse::<usize>().ok() })]
+    DatumReference(usize),
+
     #[token("#t")]
     #[token("#true")]
     TrueValue,
@@ -21,11 +44,28 @@ pub enum Token {
     FalseValue,
 
     #[regex(r"[a-zA-Z!$%&*/:<=>?^_~+\-][a-zA-Z0-9!$%&*/:<=>?^_~+\-\.]*", priority = 1, callback = |lex| lex.slice().to_string())]
+    // Keywords such as `#:allow` are symbols that keep their prefix
+    #[regex(r"#:[a-zA-Z][a-zA-Z0-9\-]*", callback = |lex| lex.slice().to_string())]
+    // The ellipsis of `syntax-rules`
+    #[token("...", |lex| lex.slice().to_string())]
     Symbol(String),
 
-    #[regex(r"-?[0-9]+(\.[0-9]+)?", priority = 2, callback = |lex| lex.slice().to_string())]
+    // Anything shaped like a numeric literal; the parser reads it with
+    // `number::parse_number`, which rejects digits outside the radix
+    #[regex(r"(#[bBoOdDeEiI]){0,2}[+-]?([0-9]+(/[0-9]+)?|([0-9]+\.[0-9]*|\.[0-9]+)([eE][+-]?[0-9]+)?|[0-9]+[eE][+-]?[0-9]+)", priority = 2, callback = |lex| lex.slice().to_string())]
+    #[regex(r"(#[eEiI])?#[xX](#[eEiI])?[+-]?[0-9a-fA-F]+(/[0-9a-fA-F]+)?", priority = 2, callback = |lex| lex.slice().to_string())]
+    #[regex(r"[+-](inf|nan)\.0", priority = 2, callback = |lex| lex.slice().to_string())]
+    #[regex(r"((#[eEiI])?#[xX](#[eEiI])?[+-]?|0[xX])([0-9a-fA-F]+(\.[0-9a-fA-F]*)?|\.[0-9a-fA-F]+)[pP][+-]?[0-9]+", priority = 3, callback = |lex| lex.slice().to_string())]
     Number(String),
 
+    #[regex(r"#[xX][0-9a-fA-F]+", priority = 3, callback = |lex| lex.slice()[2..].to_string())]
+    #[regex(r"0[xX][0-9a-fA-F]+", priority = 3, callback = |lex| lex.slice()[2..].to_string())]
+    HexNumber(String),
+
+    // A 0x literal of exactly 40 hex digits is an address, not a number
+    #[regex(r"0[xX][0-9a-fA-F]{40}", priority = 4, callback = |lex| lex.slice().to_string())]
+    Address(String),
+
     #[regex(r#""([^"\\]|\\t|\\n|\\")*""#, callback = |lex| {
         let slice = lex.slice();
         let content = &slice[1..slice.len() - 1];
@@ -40,6 +80,28 @@ pub enum Token {
     })]
     Character(String),
 
+    // Opens a reader extension block such as `#i{`, keeping the extension name
+    #[regex(r"#[a-zA-Z][a-zA-Z0-9\-]*\{", callback = |lex| {
+        let slice = lex.slice();
+        slice[1..slice.len() - 1].to_string()
+    })]
+    ExtensionOpen(String),
+
+    #[token("}")]
+    RightBrace,
+
+    // `#!fold-case` reads the symbols after it case-folded, until
+    // `#!no-fold-case`; the lexer drops both
+    #[token("#!fold-case")]
+    FoldCase,
+
+    #[token("#!no-fold-case")]
+    NoFoldCase,
+
+    // `#!laminaN` selects the edition of language version N
+    #[regex(r"#!lamina[0-9]+", callback = |lex| lex.slice()[8..].to_string())]
+    EditionPragma(String),
+
     // Skip whitespace and comments
     #[regex(r"[ \t\n\r]+", logos::skip)]
     #[regex(r";[^\n]*", logos::skip)]
@@ -48,15 +110,181 @@ pub enum Token {
 }
 
 pub fn lex(input: &str) -> Result<Vec<Token>, Error> {
-    let lexer = Token::lexer(input);
+    Ok(lex_with_spans(input)?
+        .into_iter()
+        .map(|(token, _)| token)
+        .collect())
+}
+
+/// A token and the byte range it was read from
+pub type SpannedToken = (Token, Range<usize>);
+
+/// Lex `input`, keeping the byte range each token was read from
+pub fn lex_with_spans(input: &str) -> Result<Vec<SpannedToken>, Error> {
+    Ok(lex_with_case(input, false)?.0)
+}
+
+/// Lex `input`, folding symbols to lower case from the start when
+/// `fold_case` is set. `#!fold-case` and `#!no-fold-case` switch folding for
+/// the rest of the input; the result says whether it is on at the end.
+pub fn lex_with_case(input: &str, mut fold_case: bool) -> Result<(Vec<SpannedToken>, bool), Error> {
+    let mut lexer = Token::lexer(input);
     let mut tokens = Vec::new();
 
-    for token_result in lexer {
+    while let Some(token_result) = lexer.next() {
         match token_result {
-            Ok(token) => tokens.push(token),
+            Ok(Token::FoldCase) => fold_case = true,
+            Ok(Token::NoFoldCase) => fold_case = false,
+            Ok(Token::Symbol(name)) if fold_case => {
+                tokens.push((Token::Symbol(name.to_lowercase()), lexer.span()))
+            }
+            Ok(token) => tokens.push((token, lexer.span())),
             Err(_) => return Err(Error::Lexer("Invalid input".to_string())),
         }
     }
 
-    Ok(tokens)
+    Ok((tokens, fold_case))
+}
+
+/// Lex the tokens of the first datum in `input`, as `lex_with_case` would,
+/// stopping after its last one so the text after it isn't scanned. The
+/// tokens run to the end of `input` if the datum isn't complete.
+pub fn lex_datum_with_case(
+    input: &str,
+    mut fold_case: bool,
+) -> Result<(Vec<SpannedToken>, bool), Error> {
+    let mut lexer = Token::lexer(input);
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+
+    while let Some(token_result) = lexer.next() {
+        let token = match token_result {
+            Ok(Token::FoldCase) => {
+                fold_case = true;
+                continue;
+            }
+            Ok(Token::NoFoldCase) => {
+                fold_case = false;
+                continue;
+            }
+            Ok(Token::Symbol(name)) if fold_case => Token::Symbol(name.to_lowercase()),
+            Ok(token) => token,
+            Err(_) => return Err(Error::Lexer("Invalid input".to_string())),
+        };
+        // A prefix such as `'` or `#0=` needs the datum after it
+        let complete = match token {
+            Token::LeftParen
+            | Token::VectorOpen
+            | Token::BytevectorOpen
+            | Token::ExtensionOpen(_) => {
+                depth += 1;
+                false
+            }
+            Token::RightParen | Token::RightBrace => {
+                depth = depth.saturating_sub(1);
+                true
+            }
+            Token::Quote
+            | Token::Quasiquote
+            | Token::Unquote
+            | Token::UnquoteSplicing
+            | Token::DatumLabel(_) => false,
+            _ => true,
+        };
+        tokens.push((token, lexer.span()));
+        if complete && depth == 0 {
+            break;
+        }
+    }
+
+    Ok((tokens, fold_case))
+}
+
+/// The 1-based line and column of the byte at `offset` in `input`
+pub fn line_and_column(input: &str, offset: usize) -> (usize, usize) {
+    let before = &input[..offset];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Text between tokens the parser doesn't see
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TriviaKind {
+    Whitespace,
+    /// A `;` comment, up to but not including the end of its line
+    Comment,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub span: Range<usize>,
+}
+
+/// A token with the trivia before it. Text the lexer can't read becomes a
+/// `Token::Error` spanning it rather than stopping the scan.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LosslessToken {
+    pub token: Token,
+    pub span: Range<usize>,
+    pub leading: Vec<Trivia>,
+}
+
+/// The tokens of a source along with its trivia, for tooling such as the
+/// formatter that must reproduce the text exactly: the spans of the trivia
+/// and tokens, in order, cover the whole input
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct LosslessTokens {
+    pub tokens: Vec<LosslessToken>,
+    /// Trivia after the last token
+    pub trailing: Vec<Trivia>,
+}
+
+/// Lex `input` keeping comments and whitespace, without failing on invalid
+/// text
+pub fn lex_lossless(input: &str) -> LosslessTokens {
+    let mut lexer = Token::lexer(input);
+    let mut tokens = Vec::new();
+    let mut end = 0;
+
+    while let Some(token_result) = lexer.next() {
+        let span = lexer.span();
+        tokens.push(LosslessToken {
+            token: token_result.unwrap_or(Token::Error),
+            leading: trivia(input, end..span.start),
+            span: span.clone(),
+        });
+        end = span.end;
+    }
+
+    LosslessTokens {
+        tokens,
+        trailing: trivia(input, end..input.len()),
+    }
+}
+
+// Split the text the lexer skipped into whitespace runs and comments
+fn trivia(input: &str, span: Range<usize>) -> Vec<Trivia> {
+    let mut pieces = Vec::new();
+    let mut start = span.start;
+    while start < span.end {
+        let rest = &input[start..span.end];
+        let (kind, len) = if rest.starts_with(';') {
+            (TriviaKind::Comment, rest.find('\n').unwrap_or(rest.len()))
+        } else {
+            let len = rest
+                .find(|c: char| !c.is_whitespace())
+                .unwrap_or(rest.len());
+            (TriviaKind::Whitespace, len)
+        };
+        pieces.push(Trivia {
+            kind,
+            span: start..start + len,
+        });
+        start += len;
+    }
+    pieces
 }
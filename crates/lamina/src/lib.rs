@@ -1,11 +1,29 @@
 // Export the main modules
 pub mod backends;
+pub mod cli;
+pub mod contracts;
+pub mod coverage;
+pub mod diagnostics;
+pub mod dotenv;
+pub mod edition;
 pub mod embed;
+pub mod encoding;
 pub mod error;
 pub mod evaluator;
+pub mod evm;
+pub mod expand;
 pub mod ffi;
+pub mod json;
 pub mod lexer;
+pub mod number;
 pub mod parser;
+pub mod printer;
+pub mod project;
+pub mod reader;
+pub mod repl;
+pub mod targets;
+pub mod testing;
+pub mod trace;
 pub mod value;
 
 use std::cell::RefCell;
@@ -1,46 +1,104 @@
-mod error;
-mod evaluator;
-mod lexer;
-mod parser;
-mod value;
-
-use rustyline::Editor;
+use lamina::cli::Output;
+use lamina::edition::Edition;
+use lamina::evaluator::environment::setup_initial_env;
+use lamina::json::Json;
+use lamina::repl::{self, Session};
+use lamina::value::Value;
+use lamina::{evaluator, lexer, parser};
 use std::fs;
-use value::Value;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+
+    // --strict turns redefinition of core bindings into an error
+    let strict = args.iter().any(|arg| arg == "--strict");
+    args.retain(|arg| arg != "--strict");
+
+    // --no-assertions skips assert and contract clauses
+    let assertions = !args.iter().any(|arg| arg == "--no-assertions");
+    args.retain(|arg| arg != "--no-assertions");
+
+    // --edition YEAR, for files that don't select one themselves
+    let mut edition = Edition::default();
+    if let Some(at) = args.iter().position(|arg| arg == "--edition") {
+        let year = args.get(at + 1).and_then(|year| year.parse().ok());
+        match year.and_then(Edition::from_year) {
+            Some(selected) => edition = selected,
+            None => {
+                eprintln!("Error: --edition takes 2024 or 2025");
+                std::process::exit(1);
+            }
+        }
+        args.drain(at..at + 2);
+    }
 
-    if args.len() > 1 {
-        let filename = &args[1];
+    // --json, -q/--quiet and -v/--verbose pick the output mode, over
+    // LAMINA_OUTPUT and LAMINA_VERBOSITY
+    let flag = |names: &[&str]| args.iter().any(|arg| names.contains(&arg.as_str()));
+    let out = Output::from_env().with_flags(
+        flag(&["--json"]),
+        flag(&["-q", "--quiet"]),
+        flag(&["-v", "--verbose"]),
+    );
+    args.retain(|arg| !["--json", "-q", "--quiet", "-v", "--verbose"].contains(&arg.as_str()));
+
+    if let Some(filename) = args.first() {
         if !filename.ends_with(".lmn") {
-            eprintln!("Error: File must have .lmn extension");
+            out.error("File must have .lmn extension");
             std::process::exit(1);
         }
-        let content = fs::read_to_string(filename)?;
-        execute(&content)?;
+        out.detail(format!("Running {}", filename));
+        let result = fs::read_to_string(filename)
+            .map_err(|e| e.into())
+            .and_then(|content| execute(&content, strict, assertions, edition));
+        match result {
+            Ok(value) if out.is_json() => out.result(
+                "result",
+                "",
+                Json::object([
+                    ("file", Json::from(filename.as_str())),
+                    ("value", Json::from(value.to_string())),
+                ]),
+            ),
+            Ok(_) => {}
+            Err(e) => {
+                out.error(format!("{}: {}", filename, e));
+                std::process::exit(1);
+            }
+        }
     } else {
-        repl()?;
+        repl(strict, &out)?;
     }
     Ok(())
 }
 
-fn execute(source: &str) -> Result<Value, Box<dyn std::error::Error>> {
+/// Evaluate the forms of a file in order, returning the value of the last
+fn execute(
+    source: &str,
+    strict: bool,
+    assertions: bool,
+    edition: Edition,
+) -> Result<Value, Box<dyn std::error::Error>> {
     let tokens = lexer::lex(source)?;
-    let ast = parser::parse(&tokens)?;
-    Ok(evaluator::eval(ast)?)
+    let forms = parser::parse_all(&tokens)?;
+    let env = setup_initial_env();
+    env.borrow_mut().strict = strict;
+    env.borrow_mut().assertions = assertions;
+    env.borrow_mut().edition = edition;
+
+    let mut result = Value::Nil;
+    for form in forms {
+        result = evaluator::eval_with_env(form, env.clone())?;
+    }
+    Ok(result)
 }
 
-fn repl() -> Result<(), Box<dyn std::error::Error>> {
-    let mut rl = Editor::<(), rustyline::history::DefaultHistory>::new()?;
-    println!("Lamina R7RS-small (Press Ctrl+C to exit)");
+fn repl(strict: bool, out: &Output) -> Result<(), Box<dyn std::error::Error>> {
+    out.status("Lamina R7RS-small (Press Ctrl+C or type :quit to exit, :help for commands)");
 
-    while let Ok(line) = rl.readline("λ> ") {
-        let _ = rl.add_history_entry(&line);
-        match execute(&line) {
-            Ok(val) => println!("{}", val),
-            Err(e) => eprintln!("Error: {}", e),
-        }
-    }
+    let mut session = Session::new();
+    session.set_strict(strict);
+    session.set_auto_import(true);
+    repl::run(&mut session, repl::history_path().as_deref())?;
     Ok(())
 }
@@ -0,0 +1,269 @@
+// Arithmetic on the numeric tower
+//
+// An operation on exact numbers gives an exact result, so integers never
+// overflow or lose precision and `(/ 1 3)` is `1/3`; an operation with an
+// inexact operand gives a real. Two `i64`s are combined directly, and only an
+// overflow or a rational operand moves the work to `BigRational`.
+//
+// Results are normalized: a rational whose denominator is 1 is an integer,
+// and a big integer that fits is an `Integer`. Comparisons between exact and
+// inexact numbers are exact, so `=` and `<` stay transitive when a real can't
+// hold an integer exactly.
+
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{FromPrimitive, Pow, Signed, ToPrimitive, Zero};
+
+use crate::value::NumberKind;
+
+/// An exact integer, as an `Integer` if it fits
+pub fn from_bigint(n: BigInt) -> NumberKind {
+    match n.to_i64() {
+        Some(i) => NumberKind::Integer(i),
+        None => NumberKind::BigInteger(Rc::new(n)),
+    }
+}
+
+/// An exact number, as an integer if it is whole
+pub fn from_rational(r: BigRational) -> NumberKind {
+    if r.is_integer() {
+        from_bigint(r.to_integer())
+    } else {
+        NumberKind::Rational(Rc::new(r))
+    }
+}
+
+/// The value of an exact number, `None` for a real
+pub fn to_rational(n: &NumberKind) -> Option<BigRational> {
+    match n {
+        NumberKind::Integer(i) => Some(BigRational::from_integer(BigInt::from(*i))),
+        NumberKind::BigInteger(b) => Some(BigRational::from_integer((**b).clone())),
+        NumberKind::Rational(r) => Some((**r).clone()),
+        NumberKind::Real(_) => None,
+    }
+}
+
+pub fn is_exact_zero(n: &NumberKind) -> bool {
+    matches!(n, NumberKind::Integer(0))
+}
+
+/// Whether `n` is an integer, exact or a real without a fraction
+pub fn is_integer(n: &NumberKind) -> bool {
+    match n {
+        NumberKind::Integer(_) | NumberKind::BigInteger(_) => true,
+        NumberKind::Rational(_) => false,
+        NumberKind::Real(r) => r.is_finite() && r.fract() == 0.0,
+    }
+}
+
+// Exact operands combine as `i64`s while that doesn't overflow, then as
+// rationals; any real operand makes the result a real
+fn combine(
+    a: &NumberKind,
+    b: &NumberKind,
+    small: fn(i64, i64) -> Option<i64>,
+    exact: fn(BigRational, BigRational) -> BigRational,
+    real: fn(f64, f64) -> f64,
+) -> NumberKind {
+    if let (NumberKind::Integer(x), NumberKind::Integer(y)) = (a, b) {
+        if let Some(result) = small(*x, *y) {
+            return NumberKind::Integer(result);
+        }
+    }
+    match (to_rational(a), to_rational(b)) {
+        (Some(x), Some(y)) => from_rational(exact(x, y)),
+        _ => NumberKind::Real(real(a.as_f64(), b.as_f64())),
+    }
+}
+
+pub fn add(a: &NumberKind, b: &NumberKind) -> NumberKind {
+    combine(a, b, i64::checked_add, |x, y| x + y, |x, y| x + y)
+}
+
+pub fn sub(a: &NumberKind, b: &NumberKind) -> NumberKind {
+    combine(a, b, i64::checked_sub, |x, y| x - y, |x, y| x - y)
+}
+
+pub fn mul(a: &NumberKind, b: &NumberKind) -> NumberKind {
+    combine(a, b, i64::checked_mul, |x, y| x * y, |x, y| x * y)
+}
+
+/// `a / b`, exact when both are; dividing by an exact zero is an error
+pub fn div(a: &NumberKind, b: &NumberKind) -> Result<NumberKind, String> {
+    if is_exact_zero(b) {
+        return Err("Division by zero".into());
+    }
+    Ok(match (to_rational(a), to_rational(b)) {
+        (Some(x), Some(y)) => from_rational(x / y),
+        _ => NumberKind::Real(a.as_f64() / b.as_f64()),
+    })
+}
+
+pub fn negate(n: &NumberKind) -> NumberKind {
+    match n {
+        NumberKind::Integer(i) => match i.checked_neg() {
+            Some(negated) => NumberKind::Integer(negated),
+            None => from_bigint(-BigInt::from(*i)),
+        },
+        NumberKind::BigInteger(b) => from_bigint(-(**b).clone()),
+        NumberKind::Rational(r) => NumberKind::Rational(Rc::new(-(**r).clone())),
+        NumberKind::Real(r) => NumberKind::Real(-r),
+    }
+}
+
+/// How `a` compares to `b`, `None` if either is NaN
+pub fn compare(a: &NumberKind, b: &NumberKind) -> Option<Ordering> {
+    if let (NumberKind::Integer(x), NumberKind::Integer(y)) = (a, b) {
+        return Some(x.cmp(y));
+    }
+    match (exact_value(a), exact_value(b)) {
+        (Ok(x), Ok(y)) => Some(x.cmp(&y)),
+        (Err(x), Err(y)) => x.partial_cmp(&y),
+        // An infinity is beyond every finite number
+        (Err(x), Ok(_)) => infinity_ordering(x),
+        (Ok(_), Err(y)) => infinity_ordering(y).map(Ordering::reverse),
+    }
+}
+
+// How the infinity `r` compares to a finite number
+fn infinity_ordering(r: f64) -> Option<Ordering> {
+    match r {
+        r if r.is_nan() => None,
+        r if r > 0.0 => Some(Ordering::Greater),
+        _ => Some(Ordering::Less),
+    }
+}
+
+// The exact value of a number, finite reals included, or the real that has
+// none
+fn exact_value(n: &NumberKind) -> Result<BigRational, f64> {
+    match n {
+        NumberKind::Real(r) => BigRational::from_float(*r).ok_or(*r),
+        exact => Ok(to_rational(exact).unwrap()),
+    }
+}
+
+/// The exact number equal to `n`; infinities and NaN have none
+pub fn to_exact(n: &NumberKind) -> Result<NumberKind, String> {
+    match n {
+        NumberKind::Real(r) => BigRational::from_float(*r)
+            .map(from_rational)
+            .ok_or_else(|| format!("exact: {} has no exact value", n)),
+        exact => Ok(exact.clone()),
+    }
+}
+
+/// The real nearest to `n`
+pub fn to_inexact(n: &NumberKind) -> NumberKind {
+    NumberKind::Real(n.as_f64())
+}
+
+// The integer `n` is, and whether it is inexact, for the integer division
+// procedures and `gcd`
+fn integer_operand(name: &str, n: &NumberKind) -> Result<(BigInt, bool), String> {
+    match n {
+        NumberKind::Integer(i) => Ok((BigInt::from(*i), false)),
+        NumberKind::BigInteger(b) => Ok(((**b).clone(), false)),
+        NumberKind::Real(r) if is_integer(n) => Ok((BigInt::from_f64(*r).unwrap(), true)),
+        _ => Err(format!("{}: expected an integer, got {}", name, n)),
+    }
+}
+
+fn integer_result(n: BigInt, inexact: bool) -> NumberKind {
+    if inexact {
+        NumberKind::Real(n.to_f64().unwrap_or(f64::NAN))
+    } else {
+        from_bigint(n)
+    }
+}
+
+// An integer division of `a` by `b`, inexact if either is
+fn divide_integers(
+    name: &str,
+    a: &NumberKind,
+    b: &NumberKind,
+    divide: fn(&BigInt, &BigInt) -> BigInt,
+) -> Result<NumberKind, String> {
+    let (x, x_inexact) = integer_operand(name, a)?;
+    let (y, y_inexact) = integer_operand(name, b)?;
+    if y.is_zero() {
+        return Err(format!("{}: division by zero", name));
+    }
+    Ok(integer_result(divide(&x, &y), x_inexact || y_inexact))
+}
+
+/// `a / b` truncated toward zero
+pub fn quotient(a: &NumberKind, b: &NumberKind) -> Result<NumberKind, String> {
+    divide_integers("quotient", a, b, |x, y| x / y)
+}
+
+/// What `quotient` leaves, with the sign of `a`
+pub fn remainder(a: &NumberKind, b: &NumberKind) -> Result<NumberKind, String> {
+    divide_integers("remainder", a, b, |x, y| x % y)
+}
+
+/// What dividing `a` by `b` rounded toward negative infinity leaves, with the
+/// sign of `b`
+pub fn modulo(a: &NumberKind, b: &NumberKind) -> Result<NumberKind, String> {
+    divide_integers("modulo", a, b, |x, y| x.mod_floor(y))
+}
+
+/// The greatest common divisor, never negative
+pub fn gcd(a: &NumberKind, b: &NumberKind) -> Result<NumberKind, String> {
+    let (x, x_inexact) = integer_operand("gcd", a)?;
+    let (y, y_inexact) = integer_operand("gcd", b)?;
+    Ok(integer_result(x.gcd(&y), x_inexact || y_inexact))
+}
+
+/// The least common multiple, never negative
+pub fn lcm(a: &NumberKind, b: &NumberKind) -> Result<NumberKind, String> {
+    let (x, x_inexact) = integer_operand("lcm", a)?;
+    let (y, y_inexact) = integer_operand("lcm", b)?;
+    Ok(integer_result(x.lcm(&y), x_inexact || y_inexact))
+}
+
+/// Exact exponents this large would take more memory than any machine has
+const MAX_EXACT_EXPONENT: u64 = 1 << 24;
+
+/// `base` raised to `exponent`: exact when `base` is exact and `exponent` an
+/// exact integer, a real otherwise
+pub fn expt(base: &NumberKind, exponent: &NumberKind) -> Result<NumberKind, String> {
+    let power = match exponent {
+        NumberKind::Integer(i) => BigInt::from(*i),
+        NumberKind::BigInteger(b) => (**b).clone(),
+        _ => return Ok(NumberKind::Real(base.as_f64().powf(exponent.as_f64()))),
+    };
+    let Some(base) = to_rational(base) else {
+        return Ok(NumberKind::Real(match power.to_i32() {
+            Some(power) => base.as_f64().powi(power),
+            None => base.as_f64().powf(exponent.as_f64()),
+        }));
+    };
+    // 0, 1 and -1 stay small whatever the power
+    if base.is_zero() {
+        return match power.sign() {
+            num_bigint::Sign::Minus => Err("expt: division by zero".into()),
+            num_bigint::Sign::NoSign => Ok(NumberKind::Integer(1)),
+            num_bigint::Sign::Plus => Ok(NumberKind::Integer(0)),
+        };
+    }
+    if base.abs() == BigRational::from_integer(1.into()) {
+        let odd = power.is_odd();
+        return Ok(from_rational(if odd { base } else { base.abs() }));
+    }
+    let magnitude = power
+        .abs()
+        .to_u64()
+        .filter(|magnitude| *magnitude <= MAX_EXACT_EXPONENT)
+        .ok_or_else(|| format!("expt: exponent {} is too large", power))?;
+    let raised = base.pow(magnitude as u32);
+    Ok(from_rational(if power.is_negative() {
+        raised.recip()
+    } else {
+        raised
+    }))
+}
@@ -0,0 +1,261 @@
+// Numeric literal syntax shared by the lexer, string->number and number->string
+//
+// A literal is an optional set of prefixes, `#x` `#o` `#b` `#d` for the radix
+// and `#e` `#i` for exactness, in either order, then a signed integer, an
+// `n/d` rational, in radix 10 a decimal with an optional exponent, or in
+// radix 16 a hex float such as `#x1.8p3`, whose exponent is a power of two.
+// `0x` is accepted for hexadecimal as well, and `+inf.0`, `-inf.0` and
+// `+nan.0` name the special reals. Parsing does not depend on the locale: the
+// decimal point is always `.` and there are no digit separators.
+//
+// Integers and rationals are exact, however many digits they take; decimals
+// and the special values are reals unless `#e` says otherwise.
+//
+// `format_number` writes numbers back in the same syntax, so that reading a
+// formatted number gives the number back: reals carry a `.` or an exponent or
+// name a special value, with the fewest digits that read back as the same
+// real, and rationals are kept in lowest terms.
+//
+// `arithmetic` computes with them.
+
+pub mod arithmetic;
+
+use std::fmt;
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Num, Zero};
+
+use crate::value::NumberKind;
+use arithmetic::{from_bigint, from_rational};
+
+/// Read a numeric literal, in `radix` unless a prefix says otherwise; `None`
+/// when `text` is not one
+pub fn parse_number(text: &str, radix: u32) -> Option<NumberKind> {
+    let mut radix_prefix = None;
+    let mut exact = None;
+    let mut rest = text;
+    while let Some(after) = rest.strip_prefix('#') {
+        let mut chars = after.chars();
+        match chars.next()?.to_ascii_lowercase() {
+            c @ ('x' | 'o' | 'b' | 'd') if radix_prefix.is_none() => {
+                radix_prefix = Some(match c {
+                    'x' => 16,
+                    'o' => 8,
+                    'b' => 2,
+                    _ => 10,
+                });
+            }
+            c @ ('e' | 'i') if exact.is_none() => exact = Some(c == 'e'),
+            _ => return None,
+        }
+        rest = chars.as_str();
+    }
+    let mut radix = radix_prefix.unwrap_or(radix);
+    if radix_prefix.is_none() {
+        if let Some(hex) = rest.strip_prefix("0x").or_else(|| rest.strip_prefix("0X")) {
+            radix = 16;
+            rest = hex;
+        }
+    }
+
+    let number = parse_real(rest, radix)?;
+    match exact {
+        Some(true) => to_exact(number, rest),
+        Some(false) => Some(NumberKind::Real(number.as_f64())),
+        None => Some(number),
+    }
+}
+
+/// A signed integer, rational, decimal or special real, without prefixes
+fn parse_real(text: &str, radix: u32) -> Option<NumberKind> {
+    match text {
+        "+inf.0" => return Some(NumberKind::Real(f64::INFINITY)),
+        "-inf.0" => return Some(NumberKind::Real(f64::NEG_INFINITY)),
+        "+nan.0" | "-nan.0" => return Some(NumberKind::Real(f64::NAN)),
+        _ => {}
+    }
+
+    let (negative, unsigned) = match text.as_bytes().first()? {
+        b'-' => (true, &text[1..]),
+        b'+' => (false, &text[1..]),
+        _ => (false, text),
+    };
+
+    if let Some((numerator, denominator)) = unsigned.split_once('/') {
+        let numerator = parse_digits(numerator, radix)?;
+        let denominator = parse_digits(denominator, radix)?;
+        return rational(if negative { -numerator } else { numerator }, denominator);
+    }
+
+    if is_digits(unsigned, radix) {
+        let magnitude = parse_digits(unsigned, radix)?;
+        return Some(from_bigint(if negative { -magnitude } else { magnitude }));
+    }
+
+    if radix == 10 && is_decimal(unsigned) {
+        return text.parse().ok().map(NumberKind::Real);
+    }
+    if radix == 16 {
+        let real = parse_hex_float(unsigned)?;
+        return Some(NumberKind::Real(if negative { -real } else { real }));
+    }
+    None
+}
+
+/// `digits [. digits] p [sign] digits`, hexadecimal digits scaled by a
+/// decimal power of two
+fn parse_hex_float(text: &str) -> Option<f64> {
+    let (mantissa, exponent) = text.split_once(['p', 'P'])?;
+    let exponent = exponent.parse::<i32>().ok()?;
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+    let mut value = 0.0;
+    for c in whole.chars().chain(fraction.chars()) {
+        value = value * 16.0 + c.to_digit(16)? as f64;
+    }
+    let scale = exponent.checked_sub(4 * fraction.len() as i32)?;
+    Some(value * 2f64.powi(scale))
+}
+
+fn is_digits(text: &str, radix: u32) -> bool {
+    !text.is_empty() && text.chars().all(|c| c.is_digit(radix))
+}
+
+fn parse_digits(text: &str, radix: u32) -> Option<BigInt> {
+    if !is_digits(text, radix) {
+        return None;
+    }
+    BigInt::from_str_radix(text, radix).ok()
+}
+
+/// `digits [. digits] [e [sign] digits]`, with a digit before or after the
+/// point
+fn is_decimal(text: &str) -> bool {
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(at) => (&text[..at], Some(&text[at + 1..])),
+        None => (text, None),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let mantissa_ok = (whole.chars().all(|c| c.is_ascii_digit())
+        && fraction.chars().all(|c| c.is_ascii_digit()))
+        && !(whole.is_empty() && fraction.is_empty());
+    let exponent_ok = exponent.is_none_or(|exponent| {
+        let digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        is_digits(digits, 10)
+    });
+    mantissa_ok && exponent_ok
+}
+
+/// `numerator/denominator` in lowest terms, as an integer when it is whole
+fn rational(numerator: BigInt, denominator: BigInt) -> Option<NumberKind> {
+    if denominator.is_zero() {
+        return None;
+    }
+    Some(from_rational(BigRational::new(numerator, denominator)))
+}
+
+/// The exact number a literal read as `number` denotes; decimals are read
+/// digit by digit from `text`, so `#e0.1` is exactly 1/10
+fn to_exact(number: NumberKind, text: &str) -> Option<NumberKind> {
+    let real = match number {
+        NumberKind::Real(real) => real,
+        exact => return Some(exact),
+    };
+    if !real.is_finite() {
+        return None;
+    }
+    if !is_decimal(text.trim_start_matches(['+', '-'])) {
+        return binary_fraction(real);
+    }
+
+    let unsigned = text.trim_start_matches(['+', '-']);
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(at) => (&unsigned[..at], unsigned[at + 1..].parse::<i32>().ok()?),
+        None => (unsigned, 0),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let mut numerator = format!("{}{}", whole, fraction).parse::<BigInt>().ok()?;
+    if text.starts_with('-') {
+        numerator = -numerator;
+    }
+
+    let scale = exponent - fraction.len() as i32;
+    let power = num_traits::pow(BigInt::from(10), scale.unsigned_abs() as usize);
+    if scale >= 0 {
+        Some(from_bigint(numerator * power))
+    } else {
+        rational(numerator, power)
+    }
+}
+
+/// A real as the fraction with a power of two denominator it is exactly
+fn binary_fraction(real: f64) -> Option<NumberKind> {
+    BigRational::from_float(real).map(from_rational)
+}
+
+/// Write a number so that `parse_number` reads it back, in `radix` for exact
+/// numbers; reals are only written in radix 10
+pub fn format_number(number: &NumberKind, radix: u32) -> Result<String, String> {
+    if ![2, 8, 10, 16].contains(&radix) {
+        return Err(format!("Radix must be 2, 8, 10 or 16, got {}", radix));
+    }
+    match number {
+        NumberKind::Integer(i) => Ok(format_integer(*i, radix)),
+        NumberKind::BigInteger(n) => Ok(n.to_str_radix(radix)),
+        NumberKind::Rational(r) => Ok(format!(
+            "{}/{}",
+            r.numer().to_str_radix(radix),
+            r.denom().to_str_radix(radix)
+        )),
+        NumberKind::Real(_) if radix != 10 => {
+            Err("Inexact numbers can only be written in radix 10".into())
+        }
+        NumberKind::Real(r) => Ok(format_real(*r)),
+    }
+}
+
+/// Numbers display in radix 10, as `format_number` writes them
+impl fmt::Display for NumberKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NumberKind::Integer(i) => write!(f, "{}", i),
+            NumberKind::BigInteger(n) => write!(f, "{}", n),
+            NumberKind::Rational(r) => write!(f, "{}/{}", r.numer(), r.denom()),
+            NumberKind::Real(r) => write!(f, "{}", format_real(*r)),
+        }
+    }
+}
+
+fn format_integer(i: i64, radix: u32) -> String {
+    let magnitude = i.unsigned_abs();
+    let digits = match radix {
+        2 => format!("{:b}", magnitude),
+        8 => format!("{:o}", magnitude),
+        16 => format!("{:x}", magnitude),
+        _ => magnitude.to_string(),
+    };
+    if i < 0 {
+        format!("-{}", digits)
+    } else {
+        digits
+    }
+}
+
+// Very large and very small magnitudes are written with an exponent, `1e21`
+// and `1e-7` rather than their 22 and 9 digits
+fn format_real(r: f64) -> String {
+    if r.is_nan() {
+        "+nan.0".to_string()
+    } else if r.is_infinite() {
+        if r > 0.0 { "+inf.0" } else { "-inf.0" }.to_string()
+    } else if r != 0.0 && !(1e-7..1e21).contains(&r.abs()) {
+        format!("{:e}", r)
+    } else if r.fract() == 0.0 {
+        format!("{}.0", r)
+    } else {
+        r.to_string()
+    }
+}
@@ -1,21 +1,16 @@
+use crate::edition::Edition;
 use crate::error::Error;
-use crate::lexer::Token;
+use crate::evm::parse_address;
+use crate::lexer::{self, Token};
+use crate::number;
 use crate::value::{NumberKind, Value};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
 // Helper function to parse a number string into a NumberKind
-fn parse_number(n: String) -> Result<NumberKind, Error> {
-    if n.contains('.') {
-        match n.parse::<f64>() {
-            Ok(f) => Ok(NumberKind::Real(f)),
-            Err(_) => Err(Error::Parser(format!("Invalid number: {}", n))),
-        }
-    } else {
-        match n.parse::<i64>() {
-            Ok(i) => Ok(NumberKind::Integer(i)),
-            Err(_) => Err(Error::Parser(format!("Invalid number: {}", n))),
-        }
-    }
+fn parse_number(n: &str) -> Result<NumberKind, Error> {
+    number::parse_number(n, 10).ok_or_else(|| Error::Parser(format!("Invalid number: {}", n)))
 }
 
 pub fn parse(tokens: &[Token]) -> Result<Value, Error> {
@@ -23,7 +18,7 @@ pub fn parse(tokens: &[Token]) -> Result<Value, Error> {
         return Err(Error::Parser("No tokens to parse".to_string()));
     }
 
-    let (expr, pos) = parse_expr(tokens, 0)?;
+    let (expr, pos) = parse_expr(tokens, 0, &mut Reading::default())?;
     if pos != tokens.len() {
         return Err(Error::Parser("Extra tokens at end of input".to_string()));
     }
@@ -31,29 +26,251 @@ pub fn parse(tokens: &[Token]) -> Result<Value, Error> {
     Ok(expr)
 }
 
-fn parse_expr(tokens: &[Token], pos: usize) -> Result<(Value, usize), Error> {
-    if pos >= tokens.len() {
-        return Err(Error::Parser("Unexpected end of input".to_string()));
+/// Parse the datum the token stream starts with, returning it with the
+/// number of tokens it took
+pub fn parse_datum(tokens: &[Token]) -> Result<(Value, usize), Error> {
+    parse_expr(tokens, 0, &mut Reading::default())
+}
+
+/// Read the first datum in `input` and advance `input` past it, or return
+/// `None` if only whitespace and comments are left. Only the datum's own text
+/// is lexed, so reading a long input a datum at a time takes time linear in
+/// its length.
+pub fn read_datum(input: &mut &str) -> Result<Option<Value>, Error> {
+    read_datum_with_case(input, &mut false)
+}
+
+/// `read_datum`, folding symbols to lower case while `fold_case` is set.
+/// `#!fold-case` and `#!no-fold-case` read along with the datum update it.
+pub fn read_datum_with_case(
+    input: &mut &str,
+    fold_case: &mut bool,
+) -> Result<Option<Value>, Error> {
+    let (tokens, folding) = lexer::lex_datum_with_case(input, *fold_case)?;
+    let end = match tokens.last() {
+        Some((_, span)) => span.end,
+        None => {
+            *fold_case = folding;
+            *input = "";
+            return Ok(None);
+        }
+    };
+    let (tokens, _): (Vec<_>, Vec<_>) = tokens.into_iter().unzip();
+    let (datum, _) = parse_datum(&tokens)?;
+    *fold_case = folding;
+    *input = &input[end..];
+    Ok(Some(datum))
+}
+
+/// Whether `error` is from the tokens ending inside a datum, so that more
+/// input could complete it
+pub fn is_incomplete(error: &Error) -> bool {
+    matches!(error, Error::Parser(message) if message.starts_with("Unexpected end of input"))
+}
+
+/// Parse every top-level form in the token stream, in source order
+pub fn parse_all(tokens: &[Token]) -> Result<Vec<Value>, Error> {
+    Ok(parse_all_with_positions(tokens)?.0)
+}
+
+/// A parsed list and the index of its opening parenthesis token
+pub type ListPosition = (Value, usize);
+
+/// Parse every top-level form, also returning each parsed list together with
+/// the index of its opening token. The lists are shared with the returned
+/// forms, so they can be recognised by identity while the forms are evaluated.
+pub fn parse_all_with_positions(
+    tokens: &[Token],
+) -> Result<(Vec<Value>, Vec<ListPosition>), Error> {
+    let mut forms = Vec::new();
+    let mut reading = Reading::default();
+    let mut pos = 0;
+
+    while pos < tokens.len() {
+        // A datum label is only in scope in the top-level form defining it
+        reading.labels.clear();
+        let (expr, new_pos) = parse_expr(tokens, pos, &mut reading)?;
+        forms.push(expr);
+        pos = new_pos;
     }
 
-    match &tokens[pos] {
-        Token::LeftParen => parse_list(tokens, pos + 1),
-        Token::RightParen => Err(Error::Parser("Unexpected right parenthesis".to_string())),
-        Token::Quote => {
-            let (quoted_expr, new_pos) = parse_expr(tokens, pos + 1)?;
-            let quote_sym = Value::Symbol("quote".to_string());
-            let quoted_pair = Rc::new((quoted_expr, Value::Nil));
-            let result = Value::Pair(Rc::new((quote_sym, Value::Pair(quoted_pair))));
-            Ok((result, new_pos))
+    Ok((forms, reading.positions))
+}
+
+/// What the parser keeps while reading top-level forms
+#[derive(Default)]
+struct Reading {
+    positions: Vec<ListPosition>,
+    /// The datum of each `#n=` label; while the datum is still being read,
+    /// a placeholder that `#n#` inside it stands for
+    labels: HashMap<usize, Value>,
+}
+
+/// A datum the parser has started reading but not finished
+enum Open {
+    /// A list, with the index of its opening parenthesis
+    List { start: usize, items: Vec<Value> },
+    /// `#(`, or `#u8(` when `bytes` is set
+    Vector { bytes: bool, items: Vec<Value> },
+    /// `'x`, `` `x ``, `,x` or `,@x`, waiting for `x`
+    Abbreviation(&'static str),
+    /// `#n=`, waiting for the datum it labels, with the placeholder that
+    /// `#n#` inside it reads as
+    Label(usize, Rc<RefCell<Vec<Value>>>),
+}
+
+/// Read the datum starting at `pos`, returning it with the position after it.
+///
+/// The data it is nested in are kept on a stack of their own rather than
+/// the Rust stack, so data nested any depth are read without overflowing it.
+fn parse_expr(
+    tokens: &[Token],
+    mut pos: usize,
+    reading: &mut Reading,
+) -> Result<(Value, usize), Error> {
+    let mut open: Vec<Open> = Vec::new();
+    loop {
+        let Some(token) = tokens.get(pos) else {
+            let within = open
+                .iter()
+                .rev()
+                .find_map(|datum| match datum {
+                    Open::List { .. } => Some(" in list"),
+                    Open::Vector { .. } => Some(" in vector"),
+                    _ => None,
+                })
+                .unwrap_or("");
+            return Err(Error::Parser(format!("Unexpected end of input{}", within)));
+        };
+        pos += 1;
+        let mut datum = match token {
+            Token::LeftParen => {
+                open.push(Open::List {
+                    start: pos - 1,
+                    items: Vec::new(),
+                });
+                continue;
+            }
+            Token::VectorOpen | Token::BytevectorOpen => {
+                open.push(Open::Vector {
+                    bytes: matches!(token, Token::BytevectorOpen),
+                    items: Vec::new(),
+                });
+                continue;
+            }
+            // 'x `x ,x and ,@x abbreviate (quote x), (quasiquote x), (unquote x)
+            // and (unquote-splicing x)
+            Token::Quote => {
+                open.push(Open::Abbreviation("quote"));
+                continue;
+            }
+            Token::Quasiquote => {
+                open.push(Open::Abbreviation("quasiquote"));
+                continue;
+            }
+            Token::Unquote => {
+                open.push(Open::Abbreviation("unquote"));
+                continue;
+            }
+            Token::UnquoteSplicing => {
+                open.push(Open::Abbreviation("unquote-splicing"));
+                continue;
+            }
+            Token::DatumLabel(label) => {
+                let placeholder = Rc::new(RefCell::new(Vec::new()));
+                reading
+                    .labels
+                    .insert(*label, Value::Vector(placeholder.clone()));
+                open.push(Open::Label(*label, placeholder));
+                continue;
+            }
+            // The lexer folds case itself; the directives stand for nothing
+            Token::FoldCase | Token::NoFoldCase => continue,
+            Token::RightParen => match open.pop() {
+                Some(Open::List { start, items }) => {
+                    let list = items
+                        .into_iter()
+                        .rev()
+                        .fold(Value::Nil, |rest, item| Value::cons(item, rest));
+                    if let Value::Pair(_) = list {
+                        reading.positions.push((list.clone(), start));
+                    }
+                    list
+                }
+                Some(Open::Vector {
+                    bytes: false,
+                    items,
+                }) => Value::Vector(Rc::new(RefCell::new(items))),
+                Some(Open::Vector { bytes: true, items }) => {
+                    let bytes = items
+                        .iter()
+                        .map(|item| match item {
+                            Value::Number(NumberKind::Integer(n)) if (0..=255).contains(n) => {
+                                Ok(*n as u8)
+                            }
+                            other => Err(Error::Parser(format!(
+                                "Invalid byte in bytevector literal: {}",
+                                other
+                            ))),
+                        })
+                        .collect::<Result<Vec<u8>, Error>>()?;
+                    Value::Bytevector(Rc::new(RefCell::new(bytes)))
+                }
+                _ => return Err(Error::Parser("Unexpected right parenthesis".to_string())),
+            },
+            token => parse_atom(token, reading)?,
+        };
+
+        // Finish the data the datum completes, innermost first
+        loop {
+            match open.pop() {
+                None => return Ok((datum, pos)),
+                Some(Open::List { start, mut items }) => {
+                    items.push(datum);
+                    open.push(Open::List { start, items });
+                    break;
+                }
+                Some(Open::Vector { bytes, mut items }) => {
+                    items.push(datum);
+                    open.push(Open::Vector { bytes, items });
+                    break;
+                }
+                Some(Open::Abbreviation(name)) => {
+                    let quote_sym = Value::Symbol(name.to_string());
+                    datum = Value::cons(quote_sym, Value::cons(datum, Value::Nil));
+                }
+                Some(Open::Label(label, placeholder)) => {
+                    datum = finish_labelled(label, datum, &placeholder)?;
+                    reading.labels.insert(label, datum.clone());
+                }
+            }
         }
-        Token::Symbol(s) => Ok((Value::Symbol(s.clone()), pos + 1)),
-        Token::Number(n) => {
-            let num_kind = parse_number(n.clone())?;
-            Ok((Value::Number(num_kind), pos + 1))
+    }
+}
+
+/// The datum a single token stands for
+fn parse_atom(token: &Token, reading: &Reading) -> Result<Value, Error> {
+    match token {
+        Token::DatumReference(label) => match reading.labels.get(label) {
+            Some(datum) => Ok(datum.clone()),
+            None => Err(Error::Parser(format!("Undefined datum label: #{}#", label))),
+        },
+        Token::Symbol(s) => Ok(Value::Symbol(s.clone())),
+        Token::Number(n) => Ok(Value::Number(parse_number(n)?)),
+        Token::HexNumber(digits) => match number::parse_number(digits, 16) {
+            Some(n) => Ok(Value::Number(n)),
+            None => Err(Error::Parser(format!(
+                "Hex literal out of range: {}",
+                digits
+            ))),
+        },
+        Token::Address(literal) => {
+            let bytes = parse_address(literal).map_err(Error::Parser)?;
+            Ok(Value::Address(bytes))
         }
-        Token::String(s) => Ok((Value::String(s.clone()), pos + 1)),
-        Token::TrueValue => Ok((Value::Boolean(true), pos + 1)),
-        Token::FalseValue => Ok((Value::Boolean(false), pos + 1)),
+        Token::String(s) => Ok(Value::String(s.clone())),
+        Token::TrueValue => Ok(Value::Boolean(true)),
+        Token::FalseValue => Ok(Value::Boolean(false)),
         Token::Character(c) => {
             let ch = match c.as_str() {
                 "space" => ' ',
@@ -61,26 +278,93 @@ fn parse_expr(tokens: &[Token], pos: usize) -> Result<(Value, usize), Error> {
                 s if s.len() == 1 => s.chars().next().unwrap(),
                 _ => return Err(Error::Parser(format!("Invalid character: {}", c))),
             };
-            Ok((Value::Character(ch), pos + 1))
+            Ok(Value::Character(ch))
+        }
+        Token::ExtensionOpen(name) => Err(Error::Parser(format!(
+            "No reader extension registered for #{}{{",
+            name
+        ))),
+        Token::RightBrace => Err(Error::Parser("Unexpected right brace".to_string())),
+        // Read as the equivalent (lamina-edition year) form
+        Token::EditionPragma(version) => {
+            let edition = version
+                .parse()
+                .ok()
+                .and_then(Edition::from_version)
+                .ok_or_else(|| {
+                    Error::Parser(format!("Unknown language version: lamina{}", version))
+                })?;
+            Ok(Value::cons(
+                Value::Symbol("lamina-edition".to_string()),
+                Value::cons(Value::from(edition.year() as i64), Value::Nil),
+            ))
         }
-        Token::Error => Err(Error::Parser("Invalid token".to_string())),
+        // `Token::Error`; the tokens that open or close a datum never get here
+        _ => Err(Error::Parser("Invalid token".to_string())),
     }
 }
 
-fn parse_list(tokens: &[Token], pos: usize) -> Result<(Value, usize), Error> {
-    if pos >= tokens.len() {
-        return Err(Error::Parser("Unexpected end of input in list".to_string()));
+/// The datum labelled `#label=`, once it is read. References to the label
+/// from inside it were read as `placeholder`, and are replaced by the datum
+/// now. Pairs can't be changed after they are made, so a datum can only
+/// contain itself through vector elements: `#0=#(a #0#)` reads, while
+/// `#0=(a . #0#)` is an error.
+fn finish_labelled(
+    label: usize,
+    datum: Value,
+    placeholder: &Rc<RefCell<Vec<Value>>>,
+) -> Result<Value, Error> {
+    let cyclic = || {
+        Error::Parser(format!(
+            "#{}# can only refer to its own datum from inside a vector",
+            label
+        ))
+    };
+    // One reference is the label's entry, the other the caller's
+    if Rc::strong_count(placeholder) > 2 {
+        if is_placeholder(&datum, placeholder) {
+            return Err(cyclic());
+        }
+        fill_placeholder(&datum, &datum, placeholder, &mut HashSet::new()).map_err(|_| cyclic())?;
     }
+    Ok(datum)
+}
+
+fn is_placeholder(value: &Value, placeholder: &Rc<RefCell<Vec<Value>>>) -> bool {
+    matches!(value, Value::Vector(v) if Rc::ptr_eq(v, placeholder))
+}
 
-    match &tokens[pos] {
-        Token::RightParen => Ok((Value::Nil, pos + 1)),
-        _ => {
-            let (car, new_pos) = parse_expr(tokens, pos)?;
-            if new_pos >= tokens.len() {
-                return Err(Error::Parser("Unexpected end of input in list".to_string()));
+/// Replace the vector elements within `value` that are `placeholder` with
+/// `datum`, failing if a pair holds it
+fn fill_placeholder(
+    value: &Value,
+    datum: &Value,
+    placeholder: &Rc<RefCell<Vec<Value>>>,
+    seen: &mut HashSet<*const ()>,
+) -> Result<(), ()> {
+    let mut current = value;
+    while let Value::Pair(pair) = current {
+        if !seen.insert(Rc::as_ptr(pair) as *const ()) {
+            return Ok(());
+        }
+        if is_placeholder(&pair.0, placeholder) || is_placeholder(&pair.1, placeholder) {
+            return Err(());
+        }
+        fill_placeholder(&pair.0, datum, placeholder, seen)?;
+        current = &pair.1;
+    }
+    if let Value::Vector(vector) = current {
+        if is_placeholder(current, placeholder) || !seen.insert(Rc::as_ptr(vector) as *const ()) {
+            return Ok(());
+        }
+        let items = vector.borrow().clone();
+        for (index, item) in items.iter().enumerate() {
+            if is_placeholder(item, placeholder) {
+                vector.borrow_mut()[index] = datum.clone();
+            } else {
+                fill_placeholder(item, datum, placeholder, seen)?;
             }
-            let (cdr, final_pos) = parse_list(tokens, new_pos)?;
-            Ok((Value::Pair(Rc::new((car, cdr))), final_pos))
         }
     }
+    Ok(())
 }
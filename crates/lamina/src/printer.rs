@@ -0,0 +1,359 @@
+// Printing values
+//
+// `display`, `write`, the REPL and error messages all print values through
+// `Display`, which shows at most what three parameters allow:
+//
+//   *print-length*          elements of a list, vector or bytevector
+//   *print-depth*           levels of lists and vectors nested in each other
+//   *print-string-length*   characters of a string
+//
+// Each is #f for no limit, the default, or a count. What a limit leaves out
+// prints as `...`:
+//
+//   (parameterize ((*print-length* 3)) (write '(1 2 3 4 5)))   ; (1 2 3 ...)
+//   (parameterize ((*print-depth* 2)) (write '(1 (2 (3)))))    ; (1 (2 ...))
+//
+// The parameters belong to the thread rather than to an environment, since
+// error messages are formatted where no environment is at hand; every
+// environment binds the same three. The REPL's `:print` sets them for the
+// rest of a session.
+//
+// The printer keeps its own stack rather than recursing, so deeply nested
+// data prints without overflowing the Rust one, and labels the lists and
+// vectors that contain themselves, so cyclic data prints as `#0=#(1 #0#)`
+// rather than forever.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+
+use crate::evaluator::parameters::Parameter;
+use crate::value::{Environment, NumberKind, Value};
+
+/// What a limit leaves out prints as this
+pub const ELISION: &str = "...";
+
+thread_local! {
+    static PRINT_LENGTH: Rc<Parameter> = Rc::new(Parameter::new(Value::Boolean(false)));
+    static PRINT_DEPTH: Rc<Parameter> = Rc::new(Parameter::new(Value::Boolean(false)));
+    static PRINT_STRING_LENGTH: Rc<Parameter> = Rc::new(Parameter::new(Value::Boolean(false)));
+}
+
+/// The limits on what is printed; `None` shows everything
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub length: Option<usize>,
+    pub depth: Option<usize>,
+    pub string_length: Option<usize>,
+}
+
+fn limit(parameter: &Parameter) -> Option<usize> {
+    match parameter.get() {
+        Value::Number(NumberKind::Integer(n)) if n >= 0 => Some(n as usize),
+        _ => None,
+    }
+}
+
+fn limit_value(limit: Option<usize>) -> Value {
+    match limit {
+        Some(n) => Value::Number(NumberKind::Integer(n as i64)),
+        None => Value::Boolean(false),
+    }
+}
+
+impl Limits {
+    /// The limits the parameters hold now
+    pub fn current() -> Self {
+        Limits {
+            length: PRINT_LENGTH.with(|p| limit(p)),
+            depth: PRINT_DEPTH.with(|p| limit(p)),
+            string_length: PRINT_STRING_LENGTH.with(|p| limit(p)),
+        }
+    }
+
+    /// Give the parameters these limits, returning the ones they had
+    pub fn set(self) -> Self {
+        let old = Self::current();
+        PRINT_LENGTH.with(|p| p.replace(limit_value(self.length)));
+        PRINT_DEPTH.with(|p| p.replace(limit_value(self.depth)));
+        PRINT_STRING_LENGTH.with(|p| p.replace(limit_value(self.string_length)));
+        old
+    }
+}
+
+/// Bind `*print-length*`, `*print-depth*` and `*print-string-length*`
+pub fn register_print_parameters(env: &Rc<RefCell<Environment>>) {
+    let mut env = env.borrow_mut();
+    for (name, parameter) in [
+        ("*print-length*", &PRINT_LENGTH),
+        ("*print-depth*", &PRINT_DEPTH),
+        ("*print-string-length*", &PRINT_STRING_LENGTH),
+    ] {
+        let parameter = parameter.with(Rc::clone);
+        env.bindings
+            .insert(name.to_string(), Value::Parameter(parameter));
+    }
+}
+
+/// Write `value` within the current limits
+pub fn write(value: &Value, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match value {
+        Value::Pair(_) | Value::Vector(_) => Printer::new(value).print(value, f),
+        _ => write_atom(value, &Limits::current(), f),
+    }
+}
+
+fn write_atom(value: &Value, limits: &Limits, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match value {
+        Value::String(s) => match limits.string_length {
+            Some(n) if s.chars().count() > n => {
+                let shown: String = s.chars().take(n).collect();
+                write!(f, "\"{}{}\"", shown, ELISION)
+            }
+            _ => write!(f, "\"{}\"", s),
+        },
+        Value::Bytevector(bytes) => {
+            let bytes = bytes.borrow();
+            write!(f, "#u8(")?;
+            for (i, byte) in bytes.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " ")?;
+                }
+                if limits.length == Some(i) {
+                    return write!(f, "{})", ELISION);
+                }
+                write!(f, "{}", byte)?;
+            }
+            write!(f, ")")
+        }
+        _ => value.fmt_atom(f),
+    }
+}
+
+fn address(value: &Value) -> Option<*const ()> {
+    match value {
+        Value::Pair(pair) => Some(Rc::as_ptr(pair) as *const ()),
+        Value::Vector(vector) => Some(Rc::as_ptr(vector) as *const ()),
+        _ => None,
+    }
+}
+
+/// What is left to print, innermost last
+enum Task {
+    /// A value, nested `depth` lists and vectors deep
+    Value(Value, usize),
+    Text(&'static str),
+    /// The rest of a list after its first `index` elements
+    ListRest(Value, usize, usize),
+    /// The elements of a vector from `index` on
+    VectorRest(Rc<RefCell<Vec<Value>>>, usize, usize),
+}
+
+struct Printer {
+    limits: Limits,
+    /// The pairs and vectors reachable from themselves, with their label
+    /// once one was written
+    cycles: HashMap<*const (), Option<usize>>,
+    next_label: usize,
+}
+
+impl Printer {
+    fn new(value: &Value) -> Self {
+        Printer {
+            limits: Limits::current(),
+            cycles: find_cycles(value),
+            next_label: 0,
+        }
+    }
+
+    fn is_labelled(&self, value: &Value) -> bool {
+        address(value).is_some_and(|key| self.cycles.contains_key(&key))
+    }
+
+    fn print(&mut self, value: &Value, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut tasks = vec![Task::Value(value.clone(), 0)];
+        while let Some(task) = tasks.pop() {
+            match task {
+                Task::Value(value, depth) => self.value(value, depth, &mut tasks, f)?,
+                Task::Text(text) => write!(f, "{}", text)?,
+                Task::ListRest(rest, index, depth) => match rest {
+                    Value::Nil => {}
+                    Value::Pair(pair) if !self.is_labelled(&rest) => {
+                        if self.limits.length == Some(index) {
+                            write!(f, " {}", ELISION)?;
+                            continue;
+                        }
+                        write!(f, " ")?;
+                        tasks.push(Task::ListRest(pair.1.clone(), index + 1, depth));
+                        tasks.push(Task::Value(pair.0.clone(), depth + 1));
+                    }
+                    tail => {
+                        write!(f, " . ")?;
+                        tasks.push(Task::Value(tail, depth + 1));
+                    }
+                },
+                Task::VectorRest(vector, index, depth) => {
+                    let Some(item) = vector.borrow().get(index).cloned() else {
+                        continue;
+                    };
+                    if index > 0 {
+                        write!(f, " ")?;
+                    }
+                    if self.limits.length == Some(index) {
+                        write!(f, "{}", ELISION)?;
+                        continue;
+                    }
+                    tasks.push(Task::VectorRest(vector.clone(), index + 1, depth));
+                    tasks.push(Task::Value(item, depth + 1));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn value(
+        &mut self,
+        value: Value,
+        depth: usize,
+        tasks: &mut Vec<Task>,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        if !matches!(value, Value::Pair(_) | Value::Vector(_)) {
+            return write_atom(&value, &self.limits, f);
+        }
+        if self.limits.depth.is_some_and(|limit| depth >= limit) {
+            return write!(f, "{}", ELISION);
+        }
+        if let Some(label) = address(&value).and_then(|key| self.cycles.get_mut(&key)) {
+            if let Some(n) = label {
+                return write!(f, "#{}#", n);
+            }
+            *label = Some(self.next_label);
+            write!(f, "#{}=", self.next_label)?;
+            self.next_label += 1;
+        }
+        match value {
+            Value::Pair(pair) => {
+                write!(f, "(")?;
+                tasks.push(Task::Text(")"));
+                if self.limits.length == Some(0) {
+                    write!(f, "{}", ELISION)?;
+                    return Ok(());
+                }
+                tasks.push(Task::ListRest(pair.1.clone(), 1, depth));
+                tasks.push(Task::Value(pair.0.clone(), depth + 1));
+            }
+            Value::Vector(vector) => {
+                write!(f, "#(")?;
+                tasks.push(Task::Text(")"));
+                tasks.push(Task::VectorRest(vector, 0, depth));
+            }
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+}
+
+/// The pairs and vectors reachable from themselves, found depth first by
+/// marking what is reached again while it is still being visited
+fn find_cycles(value: &Value) -> HashMap<*const (), Option<usize>> {
+    enum Visit {
+        Enter(Value),
+        Exit(*const ()),
+    }
+
+    let mut cycles = HashMap::new();
+    let mut visiting = HashSet::new();
+    let mut done = HashSet::new();
+    let mut visits = vec![Visit::Enter(value.clone())];
+    while let Some(visit) = visits.pop() {
+        let value = match visit {
+            Visit::Enter(value) => value,
+            Visit::Exit(key) => {
+                visiting.remove(&key);
+                done.insert(key);
+                continue;
+            }
+        };
+        let Some(key) = address(&value) else {
+            continue;
+        };
+        if done.contains(&key) {
+            continue;
+        }
+        if !visiting.insert(key) {
+            cycles.insert(key, None);
+            continue;
+        }
+        visits.push(Visit::Exit(key));
+        match &value {
+            Value::Pair(pair) => {
+                visits.push(Visit::Enter(pair.1.clone()));
+                visits.push(Visit::Enter(pair.0.clone()));
+            }
+            Value::Vector(vector) => {
+                visits.extend(vector.borrow().iter().rev().cloned().map(Visit::Enter));
+            }
+            _ => {}
+        }
+    }
+    cycles
+}
+
+/// `value` written within `width` columns where it can be: a list or vector
+/// too wide for one line puts each element on a line of its own, lined up
+/// after the opening parenthesis. Data that contains itself, data nested
+/// past half the width, and anything printed under a limit are written on
+/// one line.
+pub fn pretty(value: &Value, width: usize) -> String {
+    if Limits::current() != Limits::default() || !find_cycles(value).is_empty() {
+        return value.to_string();
+    }
+    let mut out = String::new();
+    pretty_into(value, 0, width, &mut out);
+    out
+}
+
+fn pretty_into(value: &Value, indent: usize, width: usize, out: &mut String) {
+    let flat = value.to_string();
+    if indent + flat.chars().count() <= width || indent >= width / 2 {
+        out.push_str(&flat);
+        return;
+    }
+    let (open, items, tail) = match value {
+        Value::Pair(_) => {
+            let mut items = Vec::new();
+            let mut rest = value.clone();
+            while let Value::Pair(pair) = rest {
+                items.push(pair.0.clone());
+                rest = pair.1.clone();
+            }
+            let tail = (!matches!(rest, Value::Nil)).then_some(rest);
+            ("(", items, tail)
+        }
+        Value::Vector(vector) => ("#(", vector.borrow().clone(), None),
+        _ => {
+            out.push_str(&flat);
+            return;
+        }
+    };
+    out.push_str(open);
+    let indent = indent + open.len();
+    let newline = |out: &mut String| {
+        out.push('\n');
+        out.push_str(&" ".repeat(indent));
+    };
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            newline(out);
+        }
+        pretty_into(item, indent, width, out);
+    }
+    if let Some(tail) = tail {
+        newline(out);
+        out.push_str(". ");
+        pretty_into(&tail, indent + 2, width, out);
+    }
+    out.push(')');
+}
@@ -0,0 +1,276 @@
+// Projects
+//
+// A project is a directory with a `lamina.toml` manifest. Its `[package]`
+// table names the project and says how `lx build` builds it:
+//
+//   [package]
+//   name = "vault"
+//   version = "0.1.0"
+//   edition = "2025"
+//   target = "evm"
+//   entry = "src/main.lmn"
+//
+// `target` is the target `lx build` compiles for when `--target` isn't given,
+// and `entry` the file it builds when no path is. Manifests written before
+// they existed leave them out, and then the interpreter checks `src`. An
+// `[env]` table holds defaults for environment variables, see `dotenv`.
+//
+// `toml_entries` reads the subset of TOML the manifest and gas schedules are
+// written in: `[table]` headers, and `key = value` lines whose value is a
+// quoted string or a bare word, with `#` comments. `.env` files share its
+// values.
+//
+// `lx new` and `lx init` create the manifest along with a starter
+// `src/main.lmn` for the target and a `.gitignore` for the build output.
+
+use std::path::{Path, PathBuf};
+
+use crate::dotenv;
+use crate::edition::{Edition, MANIFEST};
+use crate::targets;
+
+/// The version new projects start at
+pub const INITIAL_VERSION: &str = "0.1.0";
+
+/// The file new projects start with
+pub const ENTRY: &str = "src/main.lmn";
+
+/// The `[package]` table of a project's manifest
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Manifest {
+    pub name: String,
+    pub version: Option<String>,
+    pub edition: Option<Edition>,
+    /// The target `lx build` compiles for by default
+    pub target: Option<String>,
+    /// The file `lx build` builds by default, relative to the project
+    pub entry: Option<PathBuf>,
+    /// The defaults of the `[env]` table, in order
+    pub env: Vec<(String, String)>,
+}
+
+impl Manifest {
+    /// The manifest of a new project called `name` built for `target`
+    pub fn new(name: &str, target: &str) -> Self {
+        Manifest {
+            name: name.to_string(),
+            version: Some(INITIAL_VERSION.to_string()),
+            edition: Some(Edition::LATEST),
+            target: Some(target.to_string()),
+            entry: Some(PathBuf::from(ENTRY)),
+            env: Vec::new(),
+        }
+    }
+
+    /// The manifest of the project at `dir`, or `None` when there is none
+    pub fn read(dir: &Path) -> Result<Option<Self>, String> {
+        match std::fs::read_to_string(dir.join(MANIFEST)) {
+            Ok(text) => Self::parse(&text).map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("cannot read {}: {}", MANIFEST, e)),
+        }
+    }
+
+    /// The `[package]` and `[env]` tables of manifest `text`; other tables
+    /// are skipped
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut manifest = Manifest {
+            name: String::new(),
+            version: None,
+            edition: None,
+            target: None,
+            entry: None,
+            env: Vec::new(),
+        };
+        let entries = toml_entries(text).map_err(|e| format!("{}: {}", MANIFEST, e))?;
+        for Entry {
+            table,
+            key,
+            value,
+            line,
+        } in entries
+        {
+            let error = |message: String| format!("{}: line {}: {}", MANIFEST, line, message);
+            match (table.as_str(), key.as_str()) {
+                ("package", "name") => manifest.name = value,
+                ("package", "version") => manifest.version = Some(value),
+                ("package", "edition") => {
+                    let edition = value
+                        .parse()
+                        .ok()
+                        .and_then(Edition::from_year)
+                        .ok_or_else(|| error(format!("unknown edition {}", value)))?;
+                    manifest.edition = Some(edition);
+                }
+                ("package", "target") => manifest.target = Some(value),
+                ("package", "entry") => manifest.entry = Some(PathBuf::from(value)),
+                ("env", _) if !dotenv::is_name(&key) => {
+                    return Err(error(format!("invalid variable name: {}", key)));
+                }
+                ("env", _) => manifest.env.push((key, value)),
+                // Unknown keys and tables are left to newer versions of lx
+                _ => {}
+            }
+        }
+        if manifest.name.is_empty() {
+            return Err(format!("{}: [package] has no name", MANIFEST));
+        }
+        Ok(manifest)
+    }
+
+    /// The manifest as `lamina.toml` text, leaving out the fields not set
+    pub fn render(&self) -> String {
+        let mut text = format!("[package]\nname = \"{}\"\n", self.name);
+        if let Some(version) = &self.version {
+            text.push_str(&format!("version = \"{}\"\n", version));
+        }
+        if let Some(edition) = self.edition {
+            text.push_str(&format!("edition = \"{}\"\n", edition.year()));
+        }
+        if let Some(target) = &self.target {
+            text.push_str(&format!("target = \"{}\"\n", target));
+        }
+        if let Some(entry) = &self.entry {
+            text.push_str(&format!("entry = \"{}\"\n", entry.display()));
+        }
+        if !self.env.is_empty() {
+            text.push_str("\n[env]\n");
+            for (name, value) in &self.env {
+                text.push_str(&format!("{} = {:?}\n", name, value));
+            }
+        }
+        text
+    }
+}
+
+/// A `key = value` line of a TOML file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// The `[table]` the line is in, empty before the first header
+    pub table: String,
+    pub key: String,
+    pub value: String,
+    /// The line number, from 1
+    pub line: usize,
+}
+
+/// The entries of TOML `text`, in order. A double-quoted value keeps `\n`,
+/// `\t` and `\r` escapes, a single-quoted one is taken as is, and a bare one
+/// runs to a ` #` comment.
+pub fn toml_entries(text: &str) -> Result<Vec<Entry>, String> {
+    let mut entries = Vec::new();
+    let mut table = String::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |message: String| format!("line {}: {}", index + 1, message);
+        if let Some(header) = line.strip_prefix('[') {
+            let (name, _) = header
+                .split_once(']')
+                .ok_or_else(|| error("expected ] after the table name".to_string()))?;
+            table = name.trim().to_string();
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| error("expected key = value".to_string()))?;
+        entries.push(Entry {
+            table: table.clone(),
+            key: key.trim().trim_matches('"').to_string(),
+            value: parse_value(value.trim()).map_err(error)?,
+            line: index + 1,
+        });
+    }
+    Ok(entries)
+}
+
+/// A value: double-quoted with escapes, single-quoted as is, or bare up to a
+/// ` #` comment
+pub(crate) fn parse_value(value: &str) -> Result<String, String> {
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut parsed = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Ok(parsed),
+                '\\' => parsed.push(match chars.next() {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some(other) => other,
+                    None => break,
+                }),
+                c => parsed.push(c),
+            }
+        }
+        return Err("unterminated string".to_string());
+    }
+    if let Some(rest) = value.strip_prefix('\'') {
+        return rest
+            .split_once('\'')
+            .map(|(parsed, _)| parsed.to_string())
+            .ok_or_else(|| "unterminated string".to_string());
+    }
+    let bare = match value.find(" #") {
+        Some(comment) => &value[..comment],
+        None => value,
+    };
+    Ok(bare.trim().to_string())
+}
+
+/// The `.gitignore` of a new project: the build output, and the local
+/// variables of `.env`
+pub const GITIGNORE: &str = "/out\n.env\n";
+
+/// The `src/main.lmn` a new project built for `target` starts with: a
+/// counter contract for the EVM, a script otherwise
+pub fn starter(name: &str, target: &str) -> String {
+    if target == targets::EVM.name {
+        "\
+;; A counter kept in storage slot 0
+(define counter-slot 0)
+
+(define (get-counter)
+  (storage-load counter-slot))
+
+(define (increment)
+  (storage-store counter-slot (+ (storage-load counter-slot) 1))
+  (storage-load counter-slot))
+"
+        .to_string()
+    } else {
+        format!("(display \"Hello from {}!\")\n(newline)\n", name)
+    }
+}
+
+/// Create a project called `name` for `target` in `dir`, which may already
+/// exist: its manifest, starter file and `.gitignore`. Fails without writing
+/// anything when `dir` already has a manifest or the target is unknown.
+pub fn create(dir: &Path, name: &str, target: &str) -> Result<(), String> {
+    if targets::find(target).is_none() {
+        return Err(format!(
+            "unknown target {} (use interpreter or evm)",
+            target
+        ));
+    }
+    let manifest = dir.join(MANIFEST);
+    if manifest.exists() {
+        return Err(format!("{} already exists", manifest.display()));
+    }
+
+    let write = |path: PathBuf, contents: String| {
+        std::fs::write(&path, contents).map_err(|e| format!("{}: {}", path.display(), e))
+    };
+    std::fs::create_dir_all(dir.join("src")).map_err(|e| e.to_string())?;
+    let entry = dir.join(ENTRY);
+    if !entry.exists() {
+        write(entry, starter(name, target))?;
+    }
+    let gitignore = dir.join(".gitignore");
+    if !gitignore.exists() {
+        write(gitignore, GITIGNORE.to_string())?;
+    }
+    write(manifest, Manifest::new(name, target).render())
+}
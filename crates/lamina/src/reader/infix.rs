@@ -0,0 +1,124 @@
+// Infix arithmetic, as a reader extension
+//
+// `#i{ 2 + 3 * x }` reads as `(+ 2 (* 3 x))`. Operands are numbers, symbols
+// and parenthesized infix expressions; `-` in front of an operand negates it.
+// Binary operators bind from loosest to tightest as comparisons (`=`, `<`,
+// `>`, `<=`, `>=`), then `+` and `-`, then `*` and `/`, all left associative.
+// Operators are symbols, so they must be separated from their operands by
+// whitespace: `x*y` reads as a single symbol.
+//
+// It only uses the public reader hook, and doubles as an example of writing
+// an extension.
+
+use crate::error::Error;
+use crate::lexer::Token;
+
+use super::ReaderExtension;
+
+/// The `#i{ ... }` infix reader extension
+pub struct Infix;
+
+impl ReaderExtension for Infix {
+    fn name(&self) -> &str {
+        "i"
+    }
+
+    fn expand(&self, tokens: &[Token]) -> Result<Vec<Token>, Error> {
+        let mut pos = 0;
+        let mut out = Vec::new();
+        expression(tokens, &mut pos, 0, &mut out)?;
+        if pos < tokens.len() {
+            return Err(Error::Parser(format!(
+                "Unexpected token in infix expression: {:?}",
+                tokens[pos]
+            )));
+        }
+        Ok(out)
+    }
+}
+
+/// How tightly a binary operator binds, if the token is one
+fn precedence(token: &Token) -> Option<u8> {
+    match token {
+        Token::Symbol(op) => match op.as_str() {
+            "=" | "<" | ">" | "<=" | ">=" => Some(1),
+            "+" | "-" => Some(2),
+            "*" | "/" => Some(3),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Read operands joined by operators binding tighter than `min`, writing the
+/// prefix form to `out`
+fn expression(
+    tokens: &[Token],
+    pos: &mut usize,
+    min: u8,
+    out: &mut Vec<Token>,
+) -> Result<(), Error> {
+    let mut lhs = Vec::new();
+    operand(tokens, pos, &mut lhs)?;
+
+    while let Some(token) = tokens.get(*pos) {
+        let Some(binding) = precedence(token).filter(|binding| *binding > min) else {
+            break;
+        };
+        *pos += 1;
+
+        let mut rhs = Vec::new();
+        expression(tokens, pos, binding, &mut rhs)?;
+
+        let mut combined = vec![Token::LeftParen, token.clone()];
+        combined.append(&mut lhs);
+        combined.append(&mut rhs);
+        combined.push(Token::RightParen);
+        lhs = combined;
+    }
+
+    out.append(&mut lhs);
+    Ok(())
+}
+
+/// Read a single operand: a literal, a symbol, a negation or a group
+fn operand(tokens: &[Token], pos: &mut usize, out: &mut Vec<Token>) -> Result<(), Error> {
+    let token = tokens
+        .get(*pos)
+        .ok_or_else(|| Error::Parser("Expected an operand in infix expression".to_string()))?;
+    *pos += 1;
+
+    match token {
+        Token::Symbol(op) if op == "-" => {
+            out.extend([Token::LeftParen, token.clone()]);
+            operand(tokens, pos, out)?;
+            out.push(Token::RightParen);
+        }
+        Token::LeftParen => {
+            expression(tokens, pos, 0, out)?;
+            if tokens.get(*pos) != Some(&Token::RightParen) {
+                return Err(Error::Parser("Expected ) in infix expression".to_string()));
+            }
+            *pos += 1;
+        }
+        Token::Symbol(_) if precedence(token).is_some() => {
+            return Err(Error::Parser(format!(
+                "Expected an operand in infix expression, found {:?}",
+                token
+            )));
+        }
+        Token::Symbol(_)
+        | Token::Number(_)
+        | Token::HexNumber(_)
+        | Token::Address(_)
+        | Token::TrueValue
+        | Token::FalseValue => out.push(token.clone()),
+        token => {
+            return Err(Error::Parser(format!(
+                "Unexpected token in infix expression: {:?}",
+                token
+            )))
+        }
+    }
+    Ok(())
+}
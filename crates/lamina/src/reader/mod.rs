@@ -0,0 +1,91 @@
+// Reader extensions
+//
+// A reader extension rewrites the tokens of a `#name{ ... }` block before the
+// parser sees them, so an embedder can accept surface syntax the reader doesn't
+// know, such as infix arithmetic, and turn it into ordinary forms. Extensions
+// are opt-in: they are registered on an interpreter with
+// `InterpreterBuilder::with_reader_extension`, and a block no extension claims
+// is a parse error.
+
+pub mod infix;
+
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::lexer::Token;
+
+/// Rewrites the contents of `#name{ ... }` blocks into tokens to parse
+pub trait ReaderExtension {
+    /// The name between `#` and `{` that opens this extension's blocks
+    fn name(&self) -> &str;
+
+    /// The tokens to parse in place of a block, given the tokens between its
+    /// braces. Blocks nested inside have already been expanded.
+    fn expand(&self, tokens: &[Token]) -> Result<Vec<Token>, Error>;
+}
+
+/// The reader extensions registered on an interpreter
+#[derive(Clone, Default)]
+pub struct Reader {
+    extensions: Vec<Rc<dyn ReaderExtension>>,
+}
+
+impl Reader {
+    /// Register an extension, replacing any registered under the same name
+    pub fn register(&mut self, extension: Rc<dyn ReaderExtension>) {
+        self.extensions
+            .retain(|registered| registered.name() != extension.name());
+        self.extensions.push(extension);
+    }
+
+    /// Expand every extension block in a token stream
+    pub fn read(&self, tokens: Vec<Token>) -> Result<Vec<Token>, Error> {
+        if !tokens
+            .iter()
+            .any(|token| matches!(token, Token::ExtensionOpen(_)))
+        {
+            return Ok(tokens);
+        }
+
+        let (expanded, end) = self.read_until_brace(&tokens, 0)?;
+        if end < tokens.len() {
+            return Err(Error::Parser("Unexpected right brace".to_string()));
+        }
+        Ok(expanded)
+    }
+
+    /// Expand blocks from `pos` up to the first unmatched right brace or the
+    /// end of input, returning the tokens and the position of that brace
+    fn read_until_brace(
+        &self,
+        tokens: &[Token],
+        mut pos: usize,
+    ) -> Result<(Vec<Token>, usize), Error> {
+        let mut out = Vec::new();
+        while pos < tokens.len() {
+            match &tokens[pos] {
+                Token::RightBrace => return Ok((out, pos)),
+                Token::ExtensionOpen(name) => {
+                    let extension = self
+                        .extensions
+                        .iter()
+                        .find(|extension| extension.name() == name)
+                        .ok_or_else(|| {
+                            Error::Parser(format!("No reader extension registered for #{}{{", name))
+                        })?;
+                    let (block, end) = self.read_until_brace(tokens, pos + 1)?;
+                    if end == tokens.len() {
+                        return Err(Error::Parser(format!("Unclosed #{}{{ block", name)));
+                    }
+                    out.extend(extension.expand(&block)?);
+                    pos = end + 1;
+                }
+                token => {
+                    out.push(token.clone());
+                    pos += 1;
+                }
+            }
+        }
+        Ok((out, pos))
+    }
+}
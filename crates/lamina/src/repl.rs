@@ -0,0 +1,753 @@
+// Interactive session state and `:` commands for the REPL
+
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Editor, Helper};
+
+use crate::encoding::encode_hex;
+use crate::error::Error;
+use crate::evaluator;
+use crate::evaluator::environment::{lookup_variable, setup_initial_env};
+use crate::evaluator::libraries::{eval_import, libraries_exporting};
+use crate::evm::{
+    parse_address, register_simulated_evm, unregister_simulated_evm, EvmState,
+    SIMULATED_PRIMITIVES, WORD_PRIMITIVES,
+};
+use crate::expand::expand_with_env;
+use crate::json::parse_json;
+use crate::printer::{self, Limits};
+use crate::trace::{self, Step};
+use crate::value::{Environment, NumberKind, Value};
+use crate::{lexer, parser};
+
+/// State kept across REPL inputs
+pub struct Session {
+    env: Rc<RefCell<Environment>>,
+    /// Simulated chain state while the target is `evm`
+    evm: Option<Rc<RefCell<EvmState>>>,
+    /// Refuse to rebind core names instead of warning
+    strict: bool,
+    /// The step of the evaluation log `:back` and `:forward` last showed
+    cursor: Option<usize>,
+    /// Import the library that exports a name an input uses before running it
+    auto_import: bool,
+    /// The inputs evaluated without error since the session started, for
+    /// `:save`
+    inputs: Vec<String>,
+    /// Lowers source to the text of its IR for `:lower`; the IR is in a
+    /// crate of its own, so the host supplies it
+    lowering: Option<Lowering>,
+}
+
+/// Source to the text of the IR it lowers to
+pub type Lowering = Box<dyn Fn(&str) -> Result<String, String>>;
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session {
+            env: setup_initial_env(),
+            evm: None,
+            strict: false,
+            cursor: None,
+            auto_import: false,
+            inputs: Vec::new(),
+            lowering: None,
+        }
+    }
+
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+        self.env.borrow_mut().strict = strict;
+    }
+
+    /// Whether an input using a name that exactly one known library exports,
+    /// and that isn't imported, imports the library before it is evaluated
+    pub fn set_auto_import(&mut self, auto_import: bool) {
+        self.auto_import = auto_import;
+    }
+
+    /// Show the IR an input lowers to with `:lower`, as `lowering` prints it
+    pub fn set_lowering(&mut self, lowering: impl Fn(&str) -> Result<String, String> + 'static) {
+        self.lowering = Some(Box::new(lowering));
+    }
+
+    /// Start over from the initial environment, keeping the target and
+    /// strictness but dropping all definitions, simulated chain state and the
+    /// evaluation log
+    pub fn reset(&mut self) {
+        trace::clear();
+        self.cursor = None;
+        self.inputs.clear();
+        self.env = setup_initial_env();
+        self.env.borrow_mut().strict = self.strict;
+        if self.evm.is_some() {
+            let state = Rc::new(RefCell::new(EvmState::new()));
+            register_simulated_evm(self.env.clone(), state.clone());
+            self.evm = Some(state);
+        }
+    }
+
+    /// Handle one line of input, returning the text to print
+    pub fn handle(&mut self, line: &str) -> Result<String, String> {
+        let line = line.trim();
+        match line.strip_prefix(':') {
+            Some(command) => self.command(command),
+            None if line.is_empty() => Ok(String::new()),
+            None => {
+                let output = self.eval(line);
+                self.end_transaction();
+                let output = output?;
+                self.inputs.push(line.to_string());
+                Ok(output)
+            }
+        }
+    }
+
+    /// Evaluate each form of the file at `path`, showing the last value. The
+    /// file's text counts as one input, so `:save` keeps what it defined.
+    fn load(&mut self, path: &str) -> Result<String, String> {
+        let source = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let tokens = lexer::lex(&source).map_err(|e| e.to_string())?;
+        let mut value = Value::Nil;
+        for form in parser::parse_all(&tokens).map_err(|e| e.to_string())? {
+            let result = evaluator::eval_with_env(form, self.env.clone());
+            self.end_transaction();
+            value = result.map_err(|e| e.to_string())?;
+        }
+        self.inputs.push(source.trim_end().to_string());
+        Ok(display(&value))
+    }
+
+    /// Write the inputs evaluated so far to `path`, one per line, so `:load`
+    /// can replay them
+    fn save(&self, path: &str) -> Result<String, String> {
+        let mut source = self.inputs.join("\n");
+        source.push('\n');
+        std::fs::write(path, source).map_err(|e| format!("{}: {}", path, e))?;
+        Ok(format!("saved {} inputs to {}", self.inputs.len(), path))
+    }
+
+    fn eval(&self, source: &str) -> Result<String, String> {
+        let tokens = lexer::lex(source).map_err(|e| e.to_string())?;
+        let ast = parser::parse(&tokens).map_err(|e| e.to_string())?;
+
+        // Imports happen before the input runs, so its effects happen once,
+        // and each is noted before the value, or the error it didn't fix
+        let mut notes = Vec::new();
+        if self.auto_import {
+            for name in unbound_names(&ast, &self.env) {
+                if let Ok(Some(note)) = self.import_for(&name) {
+                    notes.push(note);
+                }
+            }
+        }
+        let result = evaluator::eval_with_env(ast, self.env.clone())
+            .map(|val| display(&val))
+            .map_err(|error| match self.import_hint(&error) {
+                Some(hint) => format!("{} ({})", error, hint),
+                None => error.to_string(),
+            });
+        if notes.is_empty() {
+            return result;
+        }
+        let notes = notes.join("\n");
+        match result {
+            Ok(output) if output.is_empty() => Ok(notes),
+            Ok(output) => Ok(format!("{}\n{}", notes, output)),
+            Err(error) => Err(format!("{}\n{}", error, notes)),
+        }
+    }
+
+    /// Each input runs as a transaction of its own on the simulated chain,
+    /// so transient storage doesn't outlive it
+    fn end_transaction(&self) {
+        if let Some(state) = &self.evm {
+            state.borrow_mut().end_transaction();
+        }
+    }
+
+    /// Import the library exporting `name` and note it, or say which
+    /// libraries could if several do
+    fn import_for(&self, name: &str) -> Result<Option<String>, String> {
+        match self.libraries_exporting(name).as_slice() {
+            [] => Ok(None),
+            [library] => {
+                let spec = format!("({})", library);
+                let tokens = lexer::lex(&spec).map_err(|e| e.to_string())?;
+                let spec = parser::parse(&tokens).map_err(|e| e.to_string())?;
+                eval_import(spec, self.env.clone()).map_err(|e| e.to_string())?;
+                Ok(Some(format!("; imported {} for {}", library, name)))
+            }
+            several => Err(format!("exported by {}", several.join(", "))),
+        }
+    }
+
+    /// With auto-import on, which libraries export the undefined name `error`
+    /// is about, when no single one does to import it from
+    fn import_hint(&self, error: &Error) -> Option<String> {
+        let Error::Undefined { name, .. } = error else {
+            return None;
+        };
+        if !self.auto_import {
+            return None;
+        }
+        match self.libraries_exporting(name).as_slice() {
+            several @ [_, _, ..] => Some(format!("exported by {}", several.join(", "))),
+            _ => None,
+        }
+    }
+
+    fn libraries_exporting(&self, name: &str) -> Vec<String> {
+        libraries_exporting(&self.env, name)
+            .iter()
+            .map(|library| format!("({})", library.join(" ")))
+            .collect()
+    }
+
+    fn command(&mut self, command: &str) -> Result<String, String> {
+        // Commands that show a compilation stage take the rest of the line
+        if let Some((name, source)) = command.split_once(char::is_whitespace) {
+            match name {
+                "expand" => return expand_source(source),
+                "why" => return self.why(source),
+                "print" => return print_limits(source),
+                "load" => return self.load(source.trim()),
+                "save" => return self.save(source.trim()),
+                "state" => return self.state(source.trim()),
+                "lower" => {
+                    return match &self.lowering {
+                        Some(lowering) => lowering(source),
+                        None => Err(":lower isn't available in this REPL".to_string()),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut parts = command.split_whitespace();
+        let name = parts.next().unwrap_or_default();
+        let arg = parts.next();
+
+        match (name, arg) {
+            ("target", None) => Ok(self.target().to_string()),
+            ("target", Some("evm")) => {
+                if self.evm.is_none() {
+                    let state = Rc::new(RefCell::new(EvmState::new()));
+                    register_simulated_evm(self.env.clone(), state.clone());
+                    self.evm = Some(state);
+                }
+                Ok("target: evm".to_string())
+            }
+            ("target", Some("interpreter")) => {
+                if self.evm.take().is_some() {
+                    unregister_simulated_evm(self.env.clone());
+                }
+                Ok("target: interpreter".to_string())
+            }
+            ("target", Some(other)) => Err(format!("Unknown target: {}", other)),
+            ("storage", None) => {
+                let state = self.evm_state()?.borrow();
+                if state.storage.is_empty() {
+                    return Ok("(empty)".to_string());
+                }
+                let slots: Vec<String> = state
+                    .storage
+                    .iter()
+                    .map(|(slot, value)| format!("{} => {}", slot, value))
+                    .collect();
+                Ok(slots.join("\n"))
+            }
+            ("caller", Some(address)) => {
+                let address = parse_address(address)?;
+                self.evm_state()?.borrow_mut().caller = Value::Address(address);
+                Ok(format!("caller: {}", Value::Address(address)))
+            }
+            ("value", Some(wei)) => {
+                let wei = parse_integer(wei)?;
+                self.evm_state()?.borrow_mut().callvalue = Value::Number(NumberKind::Integer(wei));
+                Ok(format!("value: {}", wei))
+            }
+            ("record", None) => self.record(trace::DEFAULT_CAPACITY),
+            ("record", Some("off")) => {
+                trace::stop();
+                Ok("recording stopped".to_string())
+            }
+            ("record", Some(steps)) => match steps.parse() {
+                Ok(steps) if steps > 0 => self.record(steps),
+                _ => Err(format!("Invalid step count: {}", steps)),
+            },
+            ("back", count) => self.travel(count, false),
+            ("forward", count) => self.travel(count, true),
+            ("reset", None) => {
+                self.reset();
+                Ok("environment reset".to_string())
+            }
+            ("auto-import", None) => Ok(auto_import_state(self.auto_import)),
+            ("auto-import", Some("on")) => {
+                self.auto_import = true;
+                Ok(auto_import_state(true))
+            }
+            ("auto-import", Some("off")) => {
+                self.auto_import = false;
+                Ok(auto_import_state(false))
+            }
+            ("print", None) => print_limits(""),
+            ("env", None) => Ok(self.definitions()),
+            ("help", None) => Ok(HELP.to_string()),
+            _ => Err(format!("Unknown command :{} (try :help)", command)),
+        }
+    }
+
+    /// `:state save <file>` writes the simulated chain state to file as
+    /// JSON, and `:state load <file>` replaces it with what file holds
+    fn state(&mut self, args: &str) -> Result<String, String> {
+        let (action, path) = match args.split_once(char::is_whitespace) {
+            Some((action, path)) => (action, path.trim()),
+            None => return Err(":state needs save or load and a file".to_string()),
+        };
+        let state = self.evm_state()?;
+        match action {
+            "save" => {
+                let json = state.borrow().to_json()?;
+                std::fs::write(path, format!("{}\n", json))
+                    .map_err(|e| format!("{}: {}", path, e))?;
+                Ok(format!("saved chain state to {}", path))
+            }
+            "load" => {
+                let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+                let mut loaded = EvmState::from_json(&parse_json(&text)?)
+                    .map_err(|e| format!("{}: {}", path, e))?;
+                let mut state = state.borrow_mut();
+                loaded.trace = state.trace.take();
+                *state = loaded;
+                Ok(format!(
+                    "loaded chain state from {}: {} storage slots",
+                    path,
+                    state.storage.len()
+                ))
+            }
+            other => Err(format!(
+                "Unknown :state action {} (use save or load)",
+                other
+            )),
+        }
+    }
+
+    /// The names the session bound, with their values, one per line
+    fn definitions(&self) -> String {
+        let initial = setup_initial_env();
+        let initial = initial.borrow();
+        let env = self.env.borrow();
+        let mut names: Vec<&String> = env
+            .bindings
+            .keys()
+            .filter(|name| initial.bindings.get(name).is_none())
+            .filter(|name| {
+                self.evm.is_none()
+                    || !(SIMULATED_PRIMITIVES.contains(&name.as_str())
+                        || WORD_PRIMITIVES.contains(&name.as_str()))
+            })
+            .collect();
+        if names.is_empty() {
+            return "(no definitions)".to_string();
+        }
+        names.sort();
+        names
+            .iter()
+            .map(|name| {
+                format!(
+                    "{} = {}",
+                    name,
+                    env.bindings
+                        .get(name.as_str())
+                        .map(display)
+                        .unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn record(&mut self, steps: usize) -> Result<String, String> {
+        trace::start(steps);
+        self.cursor = None;
+        Ok(format!("recording the last {} steps", steps))
+    }
+
+    /// Move through the log `count` steps at a time and show where that lands
+    fn travel(&mut self, count: Option<&str>, forward: bool) -> Result<String, String> {
+        let count: usize = match count {
+            Some(count) => count
+                .parse()
+                .map_err(|_| format!("Invalid step count: {}", count))?,
+            None => 1,
+        };
+        let cursor = self.cursor;
+        let step = trace::with_log(|log| {
+            let first = log.steps().next()?.index;
+            let last = log.steps().next_back()?.index;
+            let index = match (cursor, forward) {
+                // Starting past the end of the log
+                (None, false) => (last + 1).saturating_sub(count).max(first),
+                (None, true) => return None,
+                (Some(at), false) => at.saturating_sub(count).max(first),
+                (Some(at), true) => at.saturating_add(count).min(last),
+            };
+            log.get(index).map(|step| (index, format_step(step)))
+        })
+        .ok_or("Nothing recorded (start with :record)")?;
+        match step {
+            Some((index, shown)) => {
+                self.cursor = Some(index);
+                Ok(shown)
+            }
+            None => Err("Nothing recorded (start with :record)".to_string()),
+        }
+    }
+
+    /// Show the step that produced the value of `source`, and how it got out
+    fn why(&self, source: &str) -> Result<String, String> {
+        let value = trace::paused(|| {
+            let tokens = lexer::lex(source).map_err(|e| e.to_string())?;
+            let expr = parser::parse(&tokens).map_err(|e| e.to_string())?;
+            evaluator::eval_with_env(expr, self.env.clone()).map_err(|e| e.to_string())
+        })?;
+        let path = trace::with_log(|log| {
+            log.why(&value)
+                .into_iter()
+                .map(format_step)
+                .collect::<Vec<_>>()
+        })
+        .ok_or("Nothing recorded (start with :record)")?;
+        match path.split_first() {
+            Some((producer, [])) => Ok(producer.clone()),
+            Some((producer, through)) => Ok(format!(
+                "{}\nreturned through:\n{}",
+                producer,
+                through.join("\n")
+            )),
+            None => Err(format!("No recorded step produced {}", display(&value))),
+        }
+    }
+
+    fn target(&self) -> &'static str {
+        if self.evm.is_some() {
+            "evm"
+        } else {
+            "interpreter"
+        }
+    }
+
+    fn evm_state(&self) -> Result<&Rc<RefCell<EvmState>>, String> {
+        self.evm
+            .as_ref()
+            .ok_or_else(|| "Not available for the interpreter target (use :target evm)".to_string())
+    }
+}
+
+const HELP: &str = "\
+:expand <expr>             show expr after the compile-time phase
+:lower <expr>              show the IR expr lowers to (lx repl)
+:target [evm|interpreter]  show or switch the execution target
+:storage                   dump simulated storage slots (evm target)
+:caller <address>          set the simulated caller (evm target)
+:value <wei>               set the simulated call value (evm target)
+:state save|load <file>    write or read the simulated chain state as JSON
+                           (evm target)
+:load <file>               evaluate each form of file
+:save <file>               write the inputs evaluated so far to file
+:reset                     restore the initial environment
+:env                       list the names defined in this session
+:record [steps|off]        log evaluation steps, keeping the last 10000
+:back [n]                  show the step n steps back in the log
+:forward [n]               show the step n steps forward in the log
+:why <expr>                find the step that produced expr's value
+:print [limit n|off]       show or set a print limit: length, depth or
+                           string-length
+:auto-import [on|off]      show or set whether a name only one library
+                           exports imports that library
+:quit                      leave the REPL";
+
+/// Columns a value is shown within before it is broken over several lines
+const WIDTH: usize = 80;
+
+/// The commands whose argument is a file path
+const PATH_COMMANDS: [&str; 4] = [":load", ":save", ":state save", ":state load"];
+
+/// Line editing for the REPL: completes the file path after `:load` and
+/// `:save`, and keeps reading lines while an input's parentheses are open
+#[derive(Default)]
+pub struct ReplHelper {
+    files: FilenameCompleter,
+}
+
+impl ReplHelper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let input = line[..pos].trim_start();
+        let takes_path = PATH_COMMANDS.iter().any(|command| {
+            input
+                .strip_prefix(command)
+                .is_some_and(|rest| rest.starts_with(char::is_whitespace))
+        });
+        if takes_path {
+            self.files.complete(line, pos, ctx)
+        } else {
+            Ok((pos, Vec::new()))
+        }
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        if is_incomplete(ctx.input()) {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
+impl Helper for ReplHelper {}
+
+fn auto_import_state(on: bool) -> String {
+    format!("auto-import: {}", if on { "on" } else { "off" })
+}
+
+/// Show the print limits, or set one given as `<limit> <n|off>`
+fn print_limits(args: &str) -> Result<String, String> {
+    let mut limits = Limits::current();
+    let mut parts = args.split_whitespace();
+    if let Some(name) = parts.next() {
+        let value = match parts.next() {
+            Some("off") => None,
+            Some(n) => Some(
+                n.parse::<usize>()
+                    .map_err(|_| format!("Invalid limit: {}", n))?,
+            ),
+            None => return Err(format!(":print {} needs a count or off", name)),
+        };
+        match name {
+            "length" => limits.length = value,
+            "depth" => limits.depth = value,
+            "string-length" => limits.string_length = value,
+            _ => return Err(format!("Unknown print limit: {}", name)),
+        }
+        limits.set();
+    }
+    let show = |limit: Option<usize>| limit.map_or("off".to_string(), |n| n.to_string());
+    Ok(format!(
+        "length: {}, depth: {}, string-length: {}",
+        show(limits.length),
+        show(limits.depth),
+        show(limits.string_length)
+    ))
+}
+
+fn parse_integer(s: &str) -> Result<i64, String> {
+    let parsed = match s.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16),
+        None => s.parse::<i64>(),
+    };
+    parsed.map_err(|_| format!("Invalid integer: {}", s))
+}
+
+fn format_step(step: &Step) -> String {
+    format!("{}{}", "  ".repeat(step.depth), step)
+}
+
+/// Show `source` after the compile-time phase, one top-level form per line
+pub fn expand_source(source: &str) -> Result<String, String> {
+    let tokens = lexer::lex(source).map_err(|e| e.to_string())?;
+    let forms = parser::parse_all(&tokens).map_err(|e| e.to_string())?;
+
+    // Compile-time definitions are shared by the forms that follow them
+    let env = setup_initial_env();
+    let mut expanded = Vec::new();
+    for form in forms {
+        let form = expand_with_env(&form, env.clone()).map_err(|e| e.to_string())?;
+        if !matches!(form, Value::Nil) {
+            expanded.push(form.to_string());
+        }
+    }
+    Ok(expanded.join("\n"))
+}
+
+/// The symbols `expr` refers to that neither `env` nor `expr` itself binds,
+/// each once, in the order they first appear. Quoted data is left out.
+fn unbound_names(expr: &Value, env: &Rc<RefCell<Environment>>) -> Vec<String> {
+    let (mut bound, mut used) = (Vec::new(), Vec::new());
+    collect_names(expr, &mut bound, &mut used);
+    used.into_iter()
+        .filter(|name| !bound.contains(name))
+        .filter(|name| lookup_variable(name, env.clone()).is_err())
+        .collect()
+}
+
+fn collect_names(expr: &Value, bound: &mut Vec<String>, used: &mut Vec<String>) {
+    match expr {
+        Value::Symbol(name) if !used.contains(name) => used.push(name.clone()),
+        Value::Pair(pair) => {
+            if let Value::Symbol(head) = &pair.0 {
+                if head == "quote" {
+                    return;
+                }
+                bound.extend(names_bound_by(head, &pair.1));
+            }
+            collect_names(&pair.0, bound, used);
+            collect_names(&pair.1, bound, used);
+        }
+        _ => {}
+    }
+}
+
+/// The names a `define`, `lambda` or `let` form with arguments `args` binds
+fn names_bound_by(head: &str, args: &Value) -> Vec<String> {
+    let Value::Pair(args) = args else {
+        return Vec::new();
+    };
+    match head {
+        "define" | "lambda" => symbols_in(&args.0),
+        "let" | "let*" | "letrec" => {
+            // A named let binds its name too
+            let (mut names, bindings) = match (&args.0, &args.1) {
+                (Value::Symbol(name), Value::Pair(rest)) => (vec![name.clone()], &rest.0),
+                _ => (Vec::new(), &args.0),
+            };
+            let mut rest = bindings;
+            while let Value::Pair(pair) = rest {
+                if let Value::Pair(binding) = &pair.0 {
+                    names.extend(symbols_in(&binding.0));
+                }
+                rest = &pair.1;
+            }
+            names
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// The symbols of a parameter list or `define` target, a dotted tail
+/// included, or a lone symbol
+fn symbols_in(mut value: &Value) -> Vec<String> {
+    let mut names = Vec::new();
+    while let Value::Pair(pair) = value {
+        if let Value::Symbol(name) = &pair.0 {
+            names.push(name.clone());
+        }
+        value = &pair.1;
+    }
+    if let Value::Symbol(name) = value {
+        names.push(name.clone());
+    }
+    names
+}
+
+// Bytevectors are usually hashes or calldata, so show their hex alongside
+fn display(val: &Value) -> String {
+    match val {
+        Value::Bytevector(bytes) => format!("{} ; {}", val, encode_hex(&bytes.borrow())),
+        _ => printer::pretty(val, WIDTH),
+    }
+}
+
+/// Whether `source` leaves a list, string or `#name{` block open, so the
+/// REPL should read another line before evaluating it. Extra closing
+/// parentheses don't count; evaluation reports them.
+pub fn is_incomplete(source: &str) -> bool {
+    let mut depth = 0i64;
+    let mut chars = source.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => {
+                chars.by_ref().find(|&c| c == '\n');
+            }
+            '"' => loop {
+                match chars.next() {
+                    None => return true,
+                    Some('"') => break,
+                    Some('\\') => {
+                        chars.next();
+                    }
+                    Some(_) => {}
+                }
+            },
+            '#' if chars.as_str().starts_with('\\') => {
+                chars.nth(1);
+            }
+            '(' | '{' => depth += 1,
+            ')' | '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    depth > 0
+}
+
+/// Where the REPL keeps its history between runs: `LAMINA_HISTORY`, or
+/// `.lamina_history` in the home directory
+pub fn history_path() -> Option<PathBuf> {
+    match std::env::var_os("LAMINA_HISTORY") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => std::env::var_os("HOME").map(|home| Path::new(&home).join(".lamina_history")),
+    }
+}
+
+/// Read, evaluate and print inputs with `session` until end of input,
+/// Ctrl+C or `:quit`, loading the history from `history` first and saving it
+/// there after
+pub fn run(session: &mut Session, history: Option<&Path>) -> rustyline::Result<()> {
+    let mut rl = Editor::<ReplHelper, DefaultHistory>::new()?;
+    rl.set_helper(Some(ReplHelper::new()));
+    if let Some(path) = history {
+        // No history yet on a first run
+        let _ = rl.load_history(path);
+    }
+
+    while let Ok(line) = rl.readline("λ> ") {
+        let _ = rl.add_history_entry(&line);
+        if matches!(line.trim(), ":quit" | ":q") {
+            break;
+        }
+        match session.handle(&line) {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => println!("{}", output),
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    }
+
+    match history {
+        Some(path) => rl.save_history(path),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,296 @@
+// Targets
+//
+// A program runs in the interpreter or is compiled for a target such as the
+// EVM, and some primitives only exist in the interpreter: there is no console
+// or file system on chain. Each target lists the features `cond-expand`
+// tests and the primitives it lacks, so code meant for both guards the
+// interpreter-only parts:
+//
+//   (define (transfer to amount)
+//     (cond-expand
+//       (interpreter (display "transfer ") (write amount) (newline))
+//       (else #f))
+//     (storage-store to amount))
+//
+// `cond-expand` keeps the body of its first clause whose requirement the
+// target meets. A requirement is a feature, `else`, or `(and req ...)`,
+// `(or req ...)` or `(not req)` of requirements. The interpreter selects its
+// clause while evaluating; a backend expands the program for its target, and
+// a call left to a primitive the target lacks stops the compilation rather
+// than failing when the contract runs.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::evaluator::libraries::check_args_count;
+use crate::lexer;
+use crate::parser;
+use crate::value::{Environment, Value};
+
+/// Where a program runs
+pub struct Target {
+    /// The name `--target` selects it by
+    pub name: &'static str,
+    /// The features `cond-expand` requirements test
+    pub features: &'static [&'static str],
+    /// Primitives the target can't run
+    pub unavailable: &'static [&'static str],
+}
+
+/// Console, file, port and process primitives
+const INTERPRETER_ONLY: &[&str] = &[
+    "display",
+    "write",
+    "write-string",
+    "newline",
+    "read",
+    "read-line",
+    "read-string",
+    "char-ready?",
+    "current-input-port",
+    "current-output-port",
+    "with-input-from-file",
+    "with-output-to-file",
+    "with-output-to-string",
+    "get-environment-variable",
+    "get-environment-variables",
+];
+
+pub const INTERPRETER: Target = Target {
+    name: "interpreter",
+    features: &["lamina", "r7rs", "interpreter"],
+    unavailable: &[],
+};
+
+pub const EVM: Target = Target {
+    name: "evm",
+    features: &["lamina", "evm"],
+    unavailable: INTERPRETER_ONLY,
+};
+
+const TARGETS: &[&Target] = &[&INTERPRETER, &EVM];
+
+/// The target called `name`
+pub fn find(name: &str) -> Option<&'static Target> {
+    TARGETS.iter().copied().find(|target| target.name == name)
+}
+
+impl Target {
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.features.contains(&feature)
+    }
+
+    pub fn is_available(&self, primitive: &str) -> bool {
+        !self.unavailable.contains(&primitive)
+    }
+
+    /// The error for calling `primitive` on this target
+    pub fn unavailable_message(&self, primitive: &str) -> String {
+        format!(
+            "{} is not available on target {}, guard with cond-expand",
+            primitive, self.name
+        )
+    }
+
+    /// Whether the target meets a `cond-expand` requirement
+    pub fn meets(&self, requirement: &Value) -> Result<bool, Error> {
+        let pair = match requirement {
+            Value::Symbol(feature) => return Ok(feature == "else" || self.has_feature(feature)),
+            Value::Pair(pair) => pair,
+            _ => return Err(unknown_requirement(requirement)),
+        };
+        let mut operands = Vec::new();
+        let mut rest = &pair.1;
+        while let Value::Pair(operand) = rest {
+            operands.push(&operand.0);
+            rest = &operand.1;
+        }
+        match &pair.0 {
+            Value::Symbol(op) if op == "and" => {
+                for operand in operands {
+                    if !self.meets(operand)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            Value::Symbol(op) if op == "or" => {
+                for operand in operands {
+                    if self.meets(operand)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            Value::Symbol(op) if op == "not" && operands.len() == 1 => {
+                Ok(!self.meets(operands[0])?)
+            }
+            _ => Err(unknown_requirement(requirement)),
+        }
+    }
+
+    /// The body of the first clause of `(cond-expand clause ...)` whose
+    /// requirement the target meets, or the empty list when none does
+    pub fn select(&self, clauses: &Value) -> Result<Value, Error> {
+        let mut rest = clauses;
+        while let Value::Pair(clauses) = rest {
+            let Value::Pair(clause) = &clauses.0 else {
+                return Err(Error::Runtime(format!(
+                    "Malformed cond-expand clause: {}",
+                    clauses.0
+                )));
+            };
+            if self.meets(&clause.0)? {
+                return Ok(clause.1.clone());
+            }
+            rest = &clauses.1;
+        }
+        Ok(Value::Nil)
+    }
+}
+
+fn unknown_requirement(requirement: &Value) -> Error {
+    Error::Runtime(format!("Unknown cond-expand requirement: {}", requirement))
+}
+
+/// A call to a primitive a target lacks
+pub struct Unavailable {
+    pub primitive: String,
+    /// The call, as it appears in the program
+    pub call: Value,
+}
+
+/// The first call in `expr` to a primitive `target` lacks.
+///
+/// Quoted data and the forms run at compile time are left alone, as are the
+/// `cond-expand` clauses the target doesn't select and primitives the
+/// program defines itself.
+pub fn find_unavailable(expr: &Value, target: &Target) -> Result<Option<Unavailable>, Error> {
+    if target.unavailable.is_empty() {
+        return Ok(None);
+    }
+    let mut defined = HashSet::new();
+    defined_names(expr, &mut defined);
+    find_call(expr, target, &defined)
+}
+
+fn find_call(
+    expr: &Value,
+    target: &Target,
+    defined: &HashSet<String>,
+) -> Result<Option<Unavailable>, Error> {
+    let Value::Pair(pair) = expr else {
+        return Ok(None);
+    };
+    if let Value::Symbol(head) = &pair.0 {
+        match head.as_str() {
+            "quote" | "define-for-syntax" | "begin-for-syntax" | "compile-time" => return Ok(None),
+            "cond-expand" => {
+                let body = target.select(&pair.1)?;
+                return find_in_items(&body, target, defined);
+            }
+            _ if !target.is_available(head) && !defined.contains(head) => {
+                return Ok(Some(Unavailable {
+                    primitive: head.clone(),
+                    call: expr.clone(),
+                }))
+            }
+            _ => {}
+        }
+    }
+    find_in_items(expr, target, defined)
+}
+
+fn find_in_items(
+    list: &Value,
+    target: &Target,
+    defined: &HashSet<String>,
+) -> Result<Option<Unavailable>, Error> {
+    let mut rest = list;
+    while let Value::Pair(item) = rest {
+        if let Some(found) = find_call(&item.0, target, defined)? {
+            return Ok(Some(found));
+        }
+        rest = &item.1;
+    }
+    Ok(None)
+}
+
+// Collect the names `define` binds anywhere in `expr`
+fn defined_names(expr: &Value, out: &mut HashSet<String>) {
+    let Value::Pair(pair) = expr else {
+        return;
+    };
+    if matches!(&pair.0, Value::Symbol(head) if head == "define" || head == "define-internal") {
+        if let Value::Pair(define) = &pair.1 {
+            match &define.0 {
+                Value::Symbol(name) => {
+                    out.insert(name.clone());
+                }
+                Value::Pair(signature) => {
+                    if let Value::Symbol(name) = &signature.0 {
+                        out.insert(name.clone());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    let mut rest = expr;
+    while let Value::Pair(item) = rest {
+        defined_names(&item.0, out);
+        rest = &item.1;
+    }
+}
+
+/// Check the program in `source` calls only what `target` provides, pointing
+/// at the first call that isn't with its line and column
+pub fn check_source(source: &str, target: &Target) -> Result<(), Error> {
+    let spanned = lexer::lex_with_spans(source)?;
+    let tokens: Vec<_> = spanned.iter().map(|(token, _)| token.clone()).collect();
+    let (forms, positions) = parser::parse_all_with_positions(&tokens)?;
+    let program = Value::list(forms);
+
+    let Some(unavailable) = find_unavailable(&program, target)? else {
+        return Ok(());
+    };
+    let message = target.unavailable_message(&unavailable.primitive);
+    let starts: HashMap<usize, usize> = positions
+        .iter()
+        .map(|(list, token)| (address(list), spanned[*token].1.start))
+        .collect();
+    Err(Error::Compilation(
+        match starts.get(&address(&unavailable.call)) {
+            Some(&offset) => {
+                let (line, column) = lexer::line_and_column(source, offset);
+                format!("line {}, column {}: {}", line, column, message)
+            }
+            None => message,
+        },
+    ))
+}
+
+fn address(value: &Value) -> usize {
+    match value {
+        Value::Pair(pair) => Rc::as_ptr(pair) as usize,
+        _ => 0,
+    }
+}
+
+/// Bind `features`, which lists the interpreter's features
+pub fn register_features(env: &Rc<RefCell<Environment>>) {
+    env.borrow_mut().bindings.insert(
+        "features".to_string(),
+        Value::Procedure(Rc::new(|args: Vec<Value>| {
+            check_args_count("features", &args, 0)?;
+            Ok(Value::list(
+                INTERPRETER
+                    .features
+                    .iter()
+                    .map(|feature| Value::Symbol(feature.to_string())),
+            ))
+        })),
+    );
+}
@@ -0,0 +1,193 @@
+// Microbenchmarks for Lamina code
+//
+// Bench files declare cases with `(define-bench "name" thunk)`. Each thunk is
+// warmed up, its iteration count is calibrated from the warmup speed, and the
+// time per call is the median over several timed samples. Results can be
+// saved as a baseline and later runs compared against it.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::evaluator::{apply, eval_with_env};
+use crate::lexer;
+use crate::parser;
+use crate::value::{Environment, Value};
+
+use super::{load_forms, test_name_and_body, Target};
+
+/// A benchmark declared by a bench file
+#[derive(Clone)]
+pub struct BenchCase {
+    pub name: String,
+    pub thunk: Value,
+}
+
+// Benchmarks declared by the file currently being evaluated
+thread_local! {
+    static BENCHES: RefCell<Vec<BenchCase>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Options controlling a benchmark run
+#[derive(Clone, Debug)]
+pub struct BenchOptions {
+    pub target: Target,
+    /// Time spent running a case before measuring, also used to calibrate
+    pub warmup: Duration,
+    /// Total time spent measuring a case
+    pub measurement: Duration,
+    /// Number of timed samples the measurement is split into
+    pub samples: usize,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        BenchOptions {
+            target: Target::Interpreter,
+            warmup: Duration::from_millis(100),
+            measurement: Duration::from_millis(500),
+            samples: 10,
+        }
+    }
+}
+
+/// Result of one benchmark
+#[derive(Clone, Debug)]
+pub struct BenchResult {
+    pub name: String,
+    /// Median time per call, or the error the thunk raised
+    pub outcome: Result<f64, String>,
+    /// Calls per timed sample
+    pub iterations: u64,
+}
+
+// define-bench special form: (define-bench "name" thunk)
+pub fn eval_define_bench(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
+    let (name, body) = test_name_and_body(args, env.clone(), "define-bench")?;
+    let thunk = eval_with_env(body, env)?;
+    BENCHES.with(|benches| benches.borrow_mut().push(BenchCase { name, thunk }));
+    Ok(Value::Nil)
+}
+
+/// Evaluate `forms` in a fresh environment for `target` and return the benchmarks they declare
+pub fn collect_benches(forms: &[Value], target: Target) -> Result<Vec<BenchCase>, Error> {
+    BENCHES.with(|benches| benches.borrow_mut().clear());
+    load_forms(forms, target)?;
+    Ok(BENCHES.with(|benches| std::mem::take(&mut *benches.borrow_mut())))
+}
+
+/// Run every benchmark declared in `source`
+pub fn run_benches(source: &str, options: &BenchOptions) -> Result<Vec<BenchResult>, Error> {
+    let tokens = lexer::lex(source)?;
+    let forms = parser::parse_all(&tokens)?;
+    let cases = collect_benches(&forms, options.target)?;
+
+    Ok(cases.iter().map(|case| run_bench(case, options)).collect())
+}
+
+fn run_bench(case: &BenchCase, options: &BenchOptions) -> BenchResult {
+    let mut result = BenchResult {
+        name: case.name.clone(),
+        outcome: Ok(0.0),
+        iterations: 0,
+    };
+
+    // Warm up, counting how many calls fit into the warmup period
+    let start = Instant::now();
+    let mut warmup_calls: u64 = 0;
+    while warmup_calls == 0 || start.elapsed() < options.warmup {
+        if let Err(e) = call(&case.thunk) {
+            result.outcome = Err(e);
+            return result;
+        }
+        warmup_calls += 1;
+    }
+
+    // Size each sample so the samples together take about the measurement time
+    let per_call = start.elapsed().as_nanos() as f64 / warmup_calls as f64;
+    let samples = options.samples.max(1);
+    let sample_time = options.measurement.as_nanos() as f64 / samples as f64;
+    result.iterations = ((sample_time / per_call.max(1.0)) as u64).max(1);
+
+    let mut timings = Vec::with_capacity(samples);
+    for _ in 0..samples {
+        let start = Instant::now();
+        for _ in 0..result.iterations {
+            if let Err(e) = call(&case.thunk) {
+                result.outcome = Err(e);
+                return result;
+            }
+        }
+        timings.push(start.elapsed().as_nanos() as f64 / result.iterations as f64);
+    }
+
+    timings.sort_by(f64::total_cmp);
+    result.outcome = Ok(timings[timings.len() / 2]);
+    result
+}
+
+fn call(thunk: &Value) -> Result<Value, String> {
+    if thunk.is_procedure() {
+        apply(thunk, Vec::new())
+    } else {
+        Err(format!("Not a procedure: {}", thunk))
+    }
+}
+
+/// Stored ns/op per benchmark name, one `name<TAB>ns` pair per line
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Baseline {
+    pub entries: BTreeMap<String, f64>,
+}
+
+impl Baseline {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut entries = BTreeMap::new();
+        for (number, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (name, ns) = line
+                .rsplit_once('\t')
+                .ok_or_else(|| format!("Malformed baseline line {}: {}", number + 1, line))?;
+            let ns = ns
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| format!("Malformed baseline line {}: {}", number + 1, line))?;
+            entries.insert(name.to_string(), ns);
+        }
+        Ok(Baseline { entries })
+    }
+
+    /// Build a baseline from the benchmarks that succeeded
+    pub fn from_results(results: &[BenchResult]) -> Self {
+        let entries = results
+            .iter()
+            .filter_map(|result| match result.outcome {
+                Ok(ns) => Some((result.name.clone(), ns)),
+                Err(_) => None,
+            })
+            .collect();
+        Baseline { entries }
+    }
+
+    /// Add or replace the entries of `other`
+    pub fn merge(&mut self, other: Baseline) {
+        self.entries.extend(other.entries);
+    }
+
+    pub fn to_text(&self) -> String {
+        self.entries
+            .iter()
+            .map(|(name, ns)| format!("{}\t{:.1}\n", name, ns))
+            .collect()
+    }
+
+    /// Relative change of `ns` against the stored time for `name`, in percent
+    pub fn change(&self, name: &str, ns: f64) -> Option<f64> {
+        let base = *self.entries.get(name)?;
+        (base > 0.0).then(|| (ns - base) / base * 100.0)
+    }
+}
@@ -0,0 +1,319 @@
+// Differential testing of numeric semantics against a reference Scheme
+//
+// Numeric expressions are generated from a seed, evaluated by the interpreter
+// and by an R7RS implementation found on PATH (chibi-scheme, then guile), and
+// the results compared. Numbers agree when they have the same exactness and,
+// for exact numbers, the same value; reals may differ in their last digits.
+// An error on both sides agrees too, whatever the messages say.
+//
+// Divergences that aren't fixed yet are listed by expression in a known
+// issues file, in the format of the R7RS known failures, so a run is clean
+// when exactly those expressions diverge.
+
+use std::cell::RefCell;
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::process::Command;
+use std::rc::Rc;
+
+use crate::evaluator::environment::setup_initial_env;
+use crate::evaluator::eval_with_env;
+use crate::lexer;
+use crate::number::parse_number;
+use crate::parser;
+use crate::value::{Environment, NumberKind};
+
+use super::fuzz::Rng;
+use super::r7rs::KnownFailures;
+
+/// Literals the generated expressions are built from: small and word-sized
+/// integers, integers past an `i64`, rationals and reals
+const LITERALS: [&str; 18] = [
+    "0",
+    "1",
+    "-1",
+    "2",
+    "7",
+    "-12",
+    "9223372036854775807",
+    "-9223372036854775808",
+    "18446744073709551616",
+    "1/2",
+    "-3/4",
+    "22/7",
+    "100000000000000000000/3",
+    "0.5",
+    "-2.25",
+    "0.1",
+    "1e10",
+    "0.0",
+];
+
+/// Integers for the integer division procedures, one of them inexact
+const INTEGERS: [&str; 8] = [
+    "0",
+    "1",
+    "-7",
+    "12",
+    "9223372036854775807",
+    "-9223372036854775808",
+    "18446744073709551616",
+    "6.0",
+];
+
+/// Exponents for `expt`, kept small so exact powers stay printable
+const EXPONENTS: [&str; 6] = ["-2", "0", "1", "2", "3", "10"];
+
+const ARITHMETIC: [&str; 4] = ["+", "-", "*", "/"];
+const INTEGER_DIVISION: [&str; 5] = ["quotient", "remainder", "modulo", "gcd", "lcm"];
+const COMPARISONS: [&str; 5] = ["=", "<", "<=", ">", ">="];
+
+/// How deep generated expressions nest
+const MAX_DEPTH: u64 = 3;
+
+/// An R7RS implementation to compare with
+#[derive(Clone, Debug)]
+pub struct Reference {
+    pub name: &'static str,
+    pub path: PathBuf,
+    args: &'static [&'static str],
+}
+
+/// The implementations tried, in order, with the arguments that make them
+/// run an R7RS program file
+const REFERENCES: [(&str, &[&str]); 2] = [
+    ("chibi-scheme", &[]),
+    ("guile", &["--no-auto-compile", "--r7rs"]),
+];
+
+impl Reference {
+    /// The first reference implementation on PATH
+    pub fn find() -> Option<Reference> {
+        let path = env::var_os("PATH")?;
+        REFERENCES.iter().find_map(|&(name, args)| {
+            env::split_paths(&path)
+                .map(|dir| dir.join(name))
+                .find(|candidate| candidate.is_file())
+                .map(|path| Reference { name, path, args })
+        })
+    }
+
+    /// What each expression evaluates to, as `write` prints it, or `error`
+    pub fn evaluate(&self, expressions: &[String]) -> Result<Vec<String>, String> {
+        let mut program = String::from(
+            "(import (scheme base) (scheme write))\n\
+             (define-syntax check\n  \
+               (syntax-rules ()\n    \
+                 ((_ expr) (begin (guard (e (#t (display \"error\"))) (write expr)) (newline)))))\n",
+        );
+        for expression in expressions {
+            let _ = writeln!(program, "(check {})", expression);
+        }
+
+        let file = env::temp_dir().join(format!("lamina-differential-{}.scm", std::process::id()));
+        fs::write(&file, program).map_err(|e| format!("{}: {}", file.display(), e))?;
+        let output = Command::new(&self.path).args(self.args).arg(&file).output();
+        let _ = fs::remove_file(&file);
+        let output = output.map_err(|e| format!("{}: {}", self.name, e))?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let results: Vec<String> = stdout.lines().map(str::to_string).collect();
+        if results.len() != expressions.len() {
+            return Err(format!(
+                "{} printed {} results for {} expressions: {}",
+                self.name,
+                results.len(),
+                expressions.len(),
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Ok(results)
+    }
+}
+
+/// `count` numeric expressions generated from `seed`; the same seed always
+/// gives the same expressions, so known issues stay valid between runs
+pub fn generate_expressions(seed: u64, count: usize) -> Vec<String> {
+    let mut rng = Rng::new(seed);
+    (0..count)
+        .map(|_| {
+            if rng.next_u64().is_multiple_of(4) {
+                let op = pick(&mut rng, &COMPARISONS);
+                let a = expression(&mut rng, 1);
+                format!("({} {} {})", op, a, expression(&mut rng, 1))
+            } else {
+                expression(&mut rng, 0)
+            }
+        })
+        .collect()
+}
+
+fn pick<'a>(rng: &mut Rng, choices: &[&'a str]) -> &'a str {
+    choices[(rng.next_u64() % choices.len() as u64) as usize]
+}
+
+fn expression(rng: &mut Rng, depth: u64) -> String {
+    if depth >= MAX_DEPTH || rng.next_u64() % (MAX_DEPTH + 1) < depth {
+        return pick(rng, &LITERALS).to_string();
+    }
+    match rng.next_u64() % 8 {
+        0..=3 => {
+            let op = pick(rng, &ARITHMETIC);
+            let operands: Vec<String> = (0..rng.next_u64() % 3 + 1)
+                .map(|_| expression(rng, depth + 1))
+                .collect();
+            format!("({} {})", op, operands.join(" "))
+        }
+        4 | 5 => integer_expression(rng, depth),
+        6 => {
+            let base = expression(rng, depth + 1);
+            format!("(expt {} {})", base, pick(rng, &EXPONENTS))
+        }
+        _ => {
+            let op = pick(rng, &["exact", "inexact"]);
+            format!("({} {})", op, expression(rng, depth + 1))
+        }
+    }
+}
+
+// An expression of integers, so the integer division procedures mostly get
+// arguments they accept
+fn integer_expression(rng: &mut Rng, depth: u64) -> String {
+    if depth >= MAX_DEPTH || rng.next_u64() % (MAX_DEPTH + 1) < depth {
+        return pick(rng, &INTEGERS).to_string();
+    }
+    let op = match rng.next_u64() % 3 {
+        0 => pick(rng, &["+", "-", "*"]),
+        _ => pick(rng, &INTEGER_DIVISION),
+    };
+    let a = integer_expression(rng, depth + 1);
+    format!("({} {} {})", op, a, integer_expression(rng, depth + 1))
+}
+
+/// One expression and what each side made of it
+#[derive(Clone, Debug)]
+pub struct Comparison {
+    pub expression: String,
+    /// The interpreter's result, or `error`
+    pub lamina: String,
+    pub reference: String,
+}
+
+impl Comparison {
+    pub fn agrees(&self) -> bool {
+        results_agree(&self.lamina, &self.reference)
+    }
+}
+
+/// The comparisons of a run, in the order the expressions were generated
+#[derive(Clone, Debug, Default)]
+pub struct DifferentialReport {
+    pub reference: String,
+    pub comparisons: Vec<Comparison>,
+}
+
+impl DifferentialReport {
+    pub fn divergences(&self) -> Vec<&Comparison> {
+        self.comparisons.iter().filter(|c| !c.agrees()).collect()
+    }
+
+    /// Divergences not listed in `known`
+    pub fn unexpected_divergences(&self, known: &KnownFailures) -> Vec<&Comparison> {
+        self.comparisons
+            .iter()
+            .filter(|c| !c.agrees() && !known.keys.contains(&c.expression))
+            .collect()
+    }
+
+    /// Expressions listed in `known` that agree now
+    pub fn fixed(&self, known: &KnownFailures) -> Vec<&Comparison> {
+        self.comparisons
+            .iter()
+            .filter(|c| c.agrees() && known.keys.contains(&c.expression))
+            .collect()
+    }
+
+    /// The known issues file listing every divergence of this run
+    pub fn known_issues(&self) -> KnownFailures {
+        KnownFailures {
+            keys: self
+                .divergences()
+                .iter()
+                .map(|c| c.expression.clone())
+                .collect(),
+        }
+    }
+
+    /// How many expressions agreed, then each divergence
+    pub fn render(&self) -> String {
+        let divergences = self.divergences();
+        let mut out = format!(
+            "{} of {} expressions agree with {}\n",
+            self.comparisons.len() - divergences.len(),
+            self.comparisons.len(),
+            self.reference
+        );
+        for c in divergences {
+            let _ = write!(
+                out,
+                "\n{}\n  lamina:    {}\n  {:<10} {}\n",
+                c.expression,
+                c.lamina,
+                format!("{}:", self.reference),
+                c.reference
+            );
+        }
+        out
+    }
+}
+
+/// Evaluate `expressions` with the interpreter and with `reference`
+pub fn run_differential(
+    reference: &Reference,
+    expressions: &[String],
+) -> Result<DifferentialReport, String> {
+    let expected = reference.evaluate(expressions)?;
+    let env = setup_initial_env();
+    let comparisons = expressions
+        .iter()
+        .zip(expected)
+        .map(|(expression, reference)| Comparison {
+            expression: expression.clone(),
+            lamina: evaluate(expression, env.clone()),
+            reference,
+        })
+        .collect();
+    Ok(DifferentialReport {
+        reference: reference.name.to_string(),
+        comparisons,
+    })
+}
+
+/// What the interpreter makes of `expression`, or `error` if it fails or
+/// panics
+pub fn evaluate(expression: &str, env: Rc<RefCell<Environment>>) -> String {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let tokens = lexer::lex(expression).ok()?;
+        let form = parser::parse(&tokens).ok()?;
+        eval_with_env(form, env).ok()
+    }));
+    match result {
+        Ok(Some(value)) => value.to_string(),
+        _ => "error".to_string(),
+    }
+}
+
+/// Whether two printed results mean the same: numbers of the same exactness
+/// and value, reals to within rounding, or the same text otherwise
+pub fn results_agree(lamina: &str, reference: &str) -> bool {
+    match (parse_number(lamina, 10), parse_number(reference, 10)) {
+        (Some(NumberKind::Real(a)), Some(NumberKind::Real(b))) => {
+            (a.is_nan() && b.is_nan()) || a == b || (a - b).abs() <= 1e-12 * a.abs().max(b.abs())
+        }
+        (Some(a), Some(b)) => a == b,
+        _ => lamina == reference,
+    }
+}
@@ -0,0 +1,120 @@
+// Input generation and shrinking for property tests
+
+use crate::evm::Word;
+
+/// Small values that tend to expose boundary bugs in contract arithmetic; the
+/// boundaries of wider words are added by `interesting_words`
+const INTERESTING_VALUES: [i64; 8] = [0, 1, 2, 255, 256, 65_535, 1 << 32, i64::MAX];
+
+/// Upper bound on shrink attempts so a flaky property cannot loop forever
+const MAX_SHRINK_STEPS: usize = 1_000;
+
+/// Small deterministic PRNG (xorshift64*), so failing runs can be replayed by seed
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // xorshift must never be seeded with zero
+        Rng(seed ^ 0x9E37_79B9_7F4A_7C15 | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Generate a uint256 argument, biased towards boundary values and
+    /// addresses
+    pub fn next_argument(&mut self) -> Word {
+        match self.next_u64() % 4 {
+            0 => {
+                let words = interesting_words();
+                words[(self.next_u64() % words.len() as u64) as usize]
+            }
+            // An address is the low 160 bits of a word
+            1 => self.next_word() & Word::MAX.shift_right(96),
+            _ => {
+                // Pick a bit width first so small magnitudes are as likely as large ones
+                let bits = (self.next_u64() % 256 + 1) as u32;
+                self.next_word() & Word::MAX.shift_right(256 - bits)
+            }
+        }
+    }
+
+    fn next_word(&mut self) -> Word {
+        let mut bytes = [0u8; 32];
+        for chunk in bytes.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next_u64().to_be_bytes());
+        }
+        Word::from_be_bytes(&bytes).expect("32 bytes fit in a word")
+    }
+}
+
+// `INTERESTING_VALUES` and the largest address, int256 and uint256
+fn interesting_words() -> Vec<Word> {
+    let mut words: Vec<Word> = INTERESTING_VALUES
+        .iter()
+        .map(|&i| Word::from_i64(i))
+        .collect();
+    let top_bit = Word::ONE.shift_left(255);
+    words.extend([
+        Word::MAX.shift_right(96),
+        top_bit.wrapping_sub(Word::ONE),
+        top_bit,
+        Word::MAX,
+    ]);
+    words
+}
+
+/// Simpler values to try in place of `value`, most aggressive first
+pub fn shrink_candidates(value: Word) -> Vec<Word> {
+    let mut candidates = Vec::new();
+    if value.is_zero() {
+        return candidates;
+    }
+    candidates.push(Word::ZERO);
+
+    // Take off half of the value, then a quarter and so on down to one, so a
+    // full-width word shrinks in about as many steps as it has bits
+    let mut step = value.shift_right(1);
+    while !step.is_zero() {
+        let candidate = value.wrapping_sub(step);
+        if !candidates.contains(&candidate) {
+            candidates.push(candidate);
+        }
+        step = step.shift_right(1);
+    }
+    candidates
+}
+
+/// Greedily shrink a failing input, one argument at a time, while `still_fails` holds
+pub fn shrink<F>(mut args: Vec<Word>, mut still_fails: F) -> Vec<Word>
+where
+    F: FnMut(&[Word]) -> bool,
+{
+    let mut steps = 0;
+    let mut progress = true;
+
+    while progress && steps < MAX_SHRINK_STEPS {
+        progress = false;
+
+        for i in 0..args.len() {
+            for candidate in shrink_candidates(args[i]) {
+                steps += 1;
+                let mut attempt = args.clone();
+                attempt[i] = candidate;
+                if still_fails(&attempt) {
+                    args = attempt;
+                    progress = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    args
+}
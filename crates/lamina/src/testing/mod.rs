@@ -0,0 +1,461 @@
+// Test harness for Lamina source files
+//
+// Test files declare cases with `define-test` and `define-property`. Every case
+// runs in a freshly evaluated copy of the file, so contract state written by one
+// case (or one fuzz run) never leaks into the next.
+
+pub mod bench;
+#[cfg(feature = "differential")]
+pub mod differential;
+pub mod fuzz;
+pub mod r7rs;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::coverage::{FileCoverage, Instrumented};
+use crate::encoding::encode_hex;
+use crate::error::Error;
+use crate::evaluator::environment::setup_initial_env;
+use crate::evaluator::{apply, eval_with_env};
+use crate::evm::trace::{Trace, TraceStep};
+use crate::evm::{
+    register_simulated_evm, word_to_unsigned_value, EvmState, GasSchedule, Word, REVERT_PREFIX,
+};
+use crate::lexer;
+use crate::parser;
+use crate::value::{Environment, Value};
+
+use fuzz::Rng;
+
+/// A test case declared by a test file
+#[derive(Clone)]
+pub struct TestCase {
+    pub name: String,
+    pub kind: TestKind,
+}
+
+#[derive(Clone)]
+pub enum TestKind {
+    /// `(define-test "name" thunk)`: passes unless the thunk raises or returns #f
+    Unit(Value),
+    /// `(define-property "name" (lambda (args ...) ...))`: must hold for all inputs
+    Property { arity: usize, property: Value },
+}
+
+// Cases declared by the file currently being evaluated
+thread_local! {
+    static TESTS: RefCell<Vec<TestCase>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Backend semantics the test cases are executed with
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Target {
+    /// Plain interpreter environment
+    Interpreter,
+    /// Interpreter with the evm primitives bound to a simulated contract
+    Evm,
+}
+
+/// Options controlling a test run
+#[derive(Clone, Debug)]
+pub struct TestOptions {
+    pub target: Target,
+    /// Run `define-property` cases; they are skipped otherwise
+    pub fuzz: bool,
+    /// Number of generated inputs per property
+    pub runs: usize,
+    /// Seed for input generation; derived from the clock when absent
+    pub seed: Option<u64>,
+    /// Record which lines of the file the run executes
+    pub coverage: bool,
+    /// Trace the simulated EVM through each failing case, with `Target::Evm`
+    pub trace: bool,
+    /// What the steps of those traces cost
+    pub gas_schedule: GasSchedule,
+}
+
+impl Default for TestOptions {
+    fn default() -> Self {
+        TestOptions {
+            target: Target::Interpreter,
+            fuzz: false,
+            runs: 100,
+            seed: None,
+            coverage: false,
+            trace: false,
+            gas_schedule: GasSchedule::default(),
+        }
+    }
+}
+
+/// How a single case ended
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    Passed,
+    Failed(String),
+    Skipped,
+}
+
+/// Result of one test case
+#[derive(Clone, Debug)]
+pub struct TestResult {
+    pub name: String,
+    pub property: bool,
+    pub outcome: Outcome,
+    /// Generated inputs that were executed (properties only)
+    pub runs: usize,
+    /// Generated inputs whose execution reverted and were thrown away
+    pub discarded: usize,
+    /// Shrunk failing input (properties only)
+    pub counterexample: Option<Vec<Value>>,
+    /// The simulated EVM's steps through the failing run, with `trace` set
+    pub trace: Option<Vec<TraceStep>>,
+}
+
+/// Results of running one test file
+#[derive(Clone, Debug)]
+pub struct TestReport {
+    pub seed: u64,
+    pub results: Vec<TestResult>,
+    /// Lines executed while collecting and running the cases, with `coverage` set
+    pub coverage: Option<FileCoverage>,
+}
+
+impl TestReport {
+    pub fn passed(&self) -> usize {
+        self.count(|outcome| matches!(outcome, Outcome::Passed))
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(|outcome| matches!(outcome, Outcome::Failed(_)))
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.count(|outcome| matches!(outcome, Outcome::Skipped))
+    }
+
+    fn count(&self, predicate: impl Fn(&Outcome) -> bool) -> usize {
+        self.results
+            .iter()
+            .filter(|result| predicate(&result.outcome))
+            .count()
+    }
+}
+
+/// Result of executing a case once
+enum CaseOutcome {
+    Pass,
+    Discard,
+    Fail(String),
+}
+
+// define-test special form: (define-test "name" thunk)
+pub fn eval_define_test(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
+    let (name, body) = test_name_and_body(args, env.clone(), "define-test")?;
+    let thunk = eval_with_env(body, env)?;
+    register_test(TestCase {
+        name,
+        kind: TestKind::Unit(thunk),
+    });
+    Ok(Value::Nil)
+}
+
+// define-property special form: (define-property "name" (lambda (args ...) body))
+pub fn eval_define_property(args: Value, env: Rc<RefCell<Environment>>) -> Result<Value, Error> {
+    let (name, body) = test_name_and_body(args, env.clone(), "define-property")?;
+
+    // The arity has to come from the lambda syntax, procedures don't expose it
+    let arity = lambda_arity(&body).ok_or_else(|| {
+        Error::Runtime("define-property requires a lambda with a fixed parameter list".into())
+    })?;
+    let property = eval_with_env(body, env)?;
+
+    register_test(TestCase {
+        name,
+        kind: TestKind::Property { arity, property },
+    });
+    Ok(Value::Nil)
+}
+
+fn test_name_and_body(
+    args: Value,
+    env: Rc<RefCell<Environment>>,
+    form: &str,
+) -> Result<(String, Value), Error> {
+    if let Value::Pair(name_pair) = args {
+        if let Value::Pair(body_pair) = &name_pair.1 {
+            let name = match eval_with_env(name_pair.0.clone(), env)? {
+                Value::String(s) => s,
+                other => {
+                    return Err(Error::Runtime(format!(
+                        "{} name must be a string, got {}",
+                        form, other
+                    )))
+                }
+            };
+            return Ok((name, body_pair.0.clone()));
+        }
+    }
+    Err(Error::Runtime(format!("Malformed {} form", form)))
+}
+
+// Count the parameters of a `(lambda (a b ...) body)` expression
+fn lambda_arity(expr: &Value) -> Option<usize> {
+    if let Value::Pair(pair) = expr {
+        if let (Value::Symbol(head), Value::Pair(rest)) = (&pair.0, &pair.1) {
+            if head == "lambda" {
+                let mut count = 0;
+                let mut params = &rest.0;
+                while let Value::Pair(param_pair) = params {
+                    count += 1;
+                    params = &param_pair.1;
+                }
+                return matches!(params, Value::Nil).then_some(count);
+            }
+        }
+    }
+    None
+}
+
+fn register_test(case: TestCase) {
+    TESTS.with(|tests| tests.borrow_mut().push(case));
+}
+
+/// Evaluate `forms` in a fresh environment for `target` and return the cases they declare
+pub fn collect_tests(forms: &[Value], target: Target) -> Result<Vec<TestCase>, Error> {
+    collect_tests_in(forms, target, &Rc::new(RefCell::new(EvmState::new())))
+}
+
+// Collect the cases of `forms`, with the evm primitives bound to `state`
+fn collect_tests_in(
+    forms: &[Value],
+    target: Target,
+    state: &Rc<RefCell<EvmState>>,
+) -> Result<Vec<TestCase>, Error> {
+    TESTS.with(|tests| tests.borrow_mut().clear());
+    load_forms_in(forms, target, state)?;
+    Ok(TESTS.with(|tests| std::mem::take(&mut *tests.borrow_mut())))
+}
+
+// Evaluate `forms` in a fresh environment for `target`
+fn load_forms(forms: &[Value], target: Target) -> Result<(), Error> {
+    load_forms_in(forms, target, &Rc::new(RefCell::new(EvmState::new())))
+}
+
+fn load_forms_in(
+    forms: &[Value],
+    target: Target,
+    state: &Rc<RefCell<EvmState>>,
+) -> Result<(), Error> {
+    let env = setup_initial_env();
+    if target == Target::Evm {
+        register_simulated_evm(env.clone(), state.clone());
+    }
+
+    for form in forms {
+        eval_with_env(form.clone(), env.clone())?;
+    }
+    Ok(())
+}
+
+/// Run every case declared in `source`
+pub fn run_source(source: &str, options: &TestOptions) -> Result<TestReport, Error> {
+    if options.coverage {
+        let instrumented = Instrumented::new(source)?;
+        let (report, coverage) = instrumented.measure(|forms| run_forms(forms, options));
+        return report.map(|report| TestReport {
+            coverage: Some(coverage),
+            ..report
+        });
+    }
+
+    let tokens = lexer::lex(source)?;
+    let forms = parser::parse_all(&tokens)?;
+    run_forms(&forms, options)
+}
+
+fn run_forms(forms: &[Value], options: &TestOptions) -> Result<TestReport, Error> {
+    let cases = collect_tests(forms, options.target)?;
+
+    let seed = options.seed.unwrap_or_else(clock_seed);
+    let mut rng = Rng::new(seed);
+
+    let results = cases
+        .iter()
+        .enumerate()
+        .map(|(index, case)| match &case.kind {
+            TestKind::Unit(_) => run_unit(forms, index, case, options),
+            TestKind::Property { arity, .. } => {
+                run_property(forms, index, case, *arity, options, &mut rng)
+            }
+        })
+        .collect();
+
+    Ok(TestReport {
+        seed,
+        results,
+        coverage: None,
+    })
+}
+
+fn run_unit(forms: &[Value], index: usize, case: &TestCase, options: &TestOptions) -> TestResult {
+    let outcome = match run_case(forms, index, options.target, Vec::new()) {
+        CaseOutcome::Pass => Outcome::Passed,
+        CaseOutcome::Discard => Outcome::Failed("test reverted".into()),
+        CaseOutcome::Fail(message) => Outcome::Failed(message),
+    };
+    let trace = match outcome {
+        Outcome::Failed(_) => trace_case(forms, index, options, Vec::new()),
+        _ => None,
+    };
+
+    TestResult {
+        name: case.name.clone(),
+        property: false,
+        outcome,
+        runs: 0,
+        discarded: 0,
+        counterexample: None,
+        trace,
+    }
+}
+
+fn run_property(
+    forms: &[Value],
+    index: usize,
+    case: &TestCase,
+    arity: usize,
+    options: &TestOptions,
+    rng: &mut Rng,
+) -> TestResult {
+    let mut result = TestResult {
+        name: case.name.clone(),
+        property: true,
+        outcome: Outcome::Passed,
+        runs: 0,
+        discarded: 0,
+        counterexample: None,
+        trace: None,
+    };
+
+    if !options.fuzz {
+        result.outcome = Outcome::Skipped;
+        return result;
+    }
+
+    for _ in 0..options.runs {
+        let inputs: Vec<Word> = (0..arity).map(|_| rng.next_argument()).collect();
+        result.runs += 1;
+
+        match run_case(forms, index, options.target, to_values(&inputs)) {
+            CaseOutcome::Pass => {}
+            CaseOutcome::Discard => result.discarded += 1,
+            CaseOutcome::Fail(_) => {
+                let shrunk = fuzz::shrink(inputs, |candidate| {
+                    matches!(
+                        run_case(forms, index, options.target, to_values(candidate)),
+                        CaseOutcome::Fail(_)
+                    )
+                });
+
+                // Report the message of the shrunk input, not the original one
+                let message = match run_case(forms, index, options.target, to_values(&shrunk)) {
+                    CaseOutcome::Fail(message) => message,
+                    _ => "property failed".to_string(),
+                };
+
+                result.outcome = Outcome::Failed(message);
+                result.trace = trace_case(forms, index, options, to_values(&shrunk));
+                result.counterexample = Some(to_values(&shrunk));
+                break;
+            }
+        }
+    }
+
+    result
+}
+
+// Execute case `index` against a freshly deployed copy of the file
+fn run_case(forms: &[Value], index: usize, target: Target, args: Vec<Value>) -> CaseOutcome {
+    let state = Rc::new(RefCell::new(EvmState::new()));
+    run_case_in(forms, index, target, args, &state)
+}
+
+// Execute case `index` again, tracing the simulated EVM, when `options` asks
+// for traces; the steps of loading the file are left out
+fn trace_case(
+    forms: &[Value],
+    index: usize,
+    options: &TestOptions,
+    args: Vec<Value>,
+) -> Option<Vec<TraceStep>> {
+    if !options.trace || options.target != Target::Evm {
+        return None;
+    }
+    let state = Rc::new(RefCell::new(EvmState::traced_with(
+        options.gas_schedule.clone(),
+    )));
+    run_case_in(forms, index, options.target, args, &state);
+    let trace = state.borrow_mut().trace.take()?;
+    Some(trace.steps)
+}
+
+fn run_case_in(
+    forms: &[Value],
+    index: usize,
+    target: Target,
+    args: Vec<Value>,
+    state: &Rc<RefCell<EvmState>>,
+) -> CaseOutcome {
+    let cases = match collect_tests_in(forms, target, state) {
+        Ok(cases) => cases,
+        Err(e) => return CaseOutcome::Fail(e.to_string()),
+    };
+    // Loading the file deploys the contract; the case runs as a call of its own
+    state.borrow_mut().end_transaction();
+    if let Some(trace) = &mut state.borrow_mut().trace {
+        *trace = Trace::with_schedule(trace.schedule.clone());
+    }
+
+    let procedure = match cases.get(index).map(|case| &case.kind) {
+        Some(TestKind::Unit(thunk)) => thunk.clone(),
+        Some(TestKind::Property { property, .. }) => property.clone(),
+        None => return CaseOutcome::Fail("test file declared different cases on reload".into()),
+    };
+
+    let result = if procedure.is_procedure() {
+        apply(&procedure, args)
+    } else {
+        Err(format!("Not a procedure: {}", procedure))
+    };
+
+    match result {
+        Ok(Value::Boolean(false)) => CaseOutcome::Fail("returned #f".into()),
+        Ok(_) => CaseOutcome::Pass,
+        Err(message) if message.contains(&format!("{}: ", REVERT_PREFIX)) => CaseOutcome::Discard,
+        Err(message) => CaseOutcome::Fail(message),
+    }
+}
+
+/// Render a test input for a failure report; bytevectors are shown as hex
+pub fn format_input(value: &Value) -> String {
+    match value {
+        Value::Bytevector(bytes) => encode_hex(&bytes.borrow()),
+        _ => value.to_string(),
+    }
+}
+
+fn to_values(inputs: &[Word]) -> Vec<Value> {
+    inputs
+        .iter()
+        .map(|&word| word_to_unsigned_value(word))
+        .collect()
+}
+
+fn clock_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,303 @@
+// R7RS conformance suite runner
+//
+// Suites use the format of chibi-scheme's r7rs-tests.scm: `(test-begin
+// "section")` starts a section, `(test expected expr)` passes when both sides
+// evaluate to values that print the same, and any other top-level form (usually
+// a definition) is evaluated for the tests after it. Each section runs in its
+// own environment.
+//
+// Forms are split from the source text before lexing, so syntax the reader
+// doesn't support fails the test using it rather than the whole suite. Tests
+// known to fail are listed in a file of `section<TAB>test` lines; a run is
+// clean when exactly those tests fail.
+
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::Write;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+
+use crate::evaluator::environment::setup_initial_env;
+use crate::evaluator::eval_with_env;
+use crate::lexer;
+use crate::parser;
+use crate::value::{Environment, Value};
+
+/// Result of one `(test expected expr)` form
+#[derive(Clone, Debug)]
+pub struct SuiteResult {
+    pub section: String,
+    /// Source of the test form, with whitespace collapsed
+    pub test: String,
+    /// Why the test failed, if it did
+    pub failure: Option<String>,
+}
+
+impl SuiteResult {
+    /// The line identifying this test in a known failures file
+    pub fn key(&self) -> String {
+        format!("{}\t{}", self.section, self.test)
+    }
+}
+
+/// Results of a suite run, in source order
+#[derive(Clone, Debug, Default)]
+pub struct SuiteReport {
+    pub results: Vec<SuiteResult>,
+}
+
+impl SuiteReport {
+    pub fn passed(&self) -> usize {
+        self.results.iter().filter(|r| r.failure.is_none()).count()
+    }
+
+    /// Tests that failed but aren't listed in `known`
+    pub fn unexpected_failures(&self, known: &KnownFailures) -> Vec<&SuiteResult> {
+        self.results
+            .iter()
+            .filter(|r| r.failure.is_some() && !known.keys.contains(&r.key()))
+            .collect()
+    }
+
+    /// Tests listed in `known` that pass now
+    pub fn fixed(&self, known: &KnownFailures) -> Vec<&SuiteResult> {
+        self.results
+            .iter()
+            .filter(|r| r.failure.is_none() && known.keys.contains(&r.key()))
+            .collect()
+    }
+
+    /// Passed and total tests of each section, in source order
+    pub fn sections(&self) -> Vec<(String, usize, usize)> {
+        let mut sections: Vec<(String, usize, usize)> = Vec::new();
+        for result in &self.results {
+            if sections.last().map(|s| &s.0) != Some(&result.section) {
+                sections.push((result.section.clone(), 0, 0));
+            }
+            let section = sections.last_mut().unwrap();
+            section.1 += usize::from(result.failure.is_none());
+            section.2 += 1;
+        }
+        sections
+    }
+
+    /// Conformance per section, then the failing tests and why they fail
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let width = self
+            .sections()
+            .iter()
+            .map(|(name, _, _)| name.len())
+            .max()
+            .unwrap_or(0);
+        for (name, passed, total) in self.sections() {
+            let _ = writeln!(
+                out,
+                "{:<width$}  {:>3}/{:<3} {:>5.1}%",
+                name,
+                passed,
+                total,
+                percent(passed, total)
+            );
+        }
+        let total = self.results.len();
+        let _ = writeln!(
+            out,
+            "{:<width$}  {:>3}/{:<3} {:>5.1}%",
+            "Total",
+            self.passed(),
+            total,
+            percent(self.passed(), total)
+        );
+
+        for result in &self.results {
+            if let Some(failure) = &result.failure {
+                let _ = write!(
+                    out,
+                    "\n{}: {}\n  {}\n",
+                    result.section, result.test, failure
+                );
+            }
+        }
+        out
+    }
+}
+
+fn percent(passed: usize, total: usize) -> f64 {
+    if total == 0 {
+        100.0
+    } else {
+        passed as f64 * 100.0 / total as f64
+    }
+}
+
+/// Tests expected to fail, one `section<TAB>test` line each
+#[derive(Clone, Debug, Default)]
+pub struct KnownFailures {
+    pub keys: BTreeSet<String>,
+}
+
+impl KnownFailures {
+    /// Parse a known failures file, skipping blank lines and `#` comments
+    pub fn parse(text: &str) -> Self {
+        let keys = text
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+        KnownFailures { keys }
+    }
+
+    /// The tests that failed in `report`
+    pub fn from_report(report: &SuiteReport) -> Self {
+        let keys = report
+            .results
+            .iter()
+            .filter(|r| r.failure.is_some())
+            .map(SuiteResult::key)
+            .collect();
+        KnownFailures { keys }
+    }
+
+    pub fn to_text(&self) -> String {
+        self.keys.iter().map(|key| format!("{}\n", key)).collect()
+    }
+}
+
+/// Run every test of a suite against the interpreter
+pub fn run_suite(source: &str) -> SuiteReport {
+    let mut report = SuiteReport::default();
+    let mut section = String::from("(no section)");
+    let mut env = setup_initial_env();
+    let mut seen = HashSet::new();
+
+    for text in split_forms(source) {
+        let test = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let form = read(&text);
+
+        match head(&text) {
+            Some("test-begin") => {
+                if let Ok(Value::Pair(pair)) = &form {
+                    if let Value::Pair(name) = &pair.1 {
+                        if let Value::String(name) = &name.0 {
+                            section = name.clone();
+                        }
+                    }
+                }
+                env = setup_initial_env();
+            }
+            Some("test-end") => {}
+            Some("test") => {
+                // The same test twice in a section would share a known failure line
+                if !seen.insert(format!("{}\t{}", section, test)) {
+                    continue;
+                }
+                let failure = form.and_then(|form| run_test(&form, env.clone())).err();
+                report.results.push(SuiteResult {
+                    section: section.clone(),
+                    test,
+                    failure,
+                });
+            }
+            _ => {
+                // Setup forms that fail show up in the tests relying on them
+                if let Ok(form) = form {
+                    let _ = eval_guarded(form, env.clone());
+                }
+            }
+        }
+    }
+    report
+}
+
+/// Evaluate both sides of `(test expected expr)` and compare them
+fn run_test(form: &Value, env: Rc<RefCell<Environment>>) -> Result<(), String> {
+    let args = match form {
+        Value::Pair(pair) => &pair.1,
+        _ => return Err("malformed test".to_string()),
+    };
+    let (expected, expr) = match args {
+        Value::Pair(first) => match &first.1 {
+            Value::Pair(second) if matches!(second.1, Value::Nil) => {
+                (first.0.clone(), second.0.clone())
+            }
+            _ => return Err("test expects an expected value and an expression".to_string()),
+        },
+        _ => return Err("test expects an expected value and an expression".to_string()),
+    };
+
+    let expected = eval_guarded(expected, env.clone())?;
+    let actual = eval_guarded(expr, env)?;
+    if expected.to_string() == actual.to_string() {
+        Ok(())
+    } else {
+        Err(format!("expected {}, got {}", expected, actual))
+    }
+}
+
+/// Evaluate a form, turning errors and interpreter panics into messages
+fn eval_guarded(form: Value, env: Rc<RefCell<Environment>>) -> Result<Value, String> {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| eval_with_env(form, env)));
+
+    match result {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err("interpreter panicked".to_string()),
+    }
+}
+
+fn read(text: &str) -> Result<Value, String> {
+    let tokens = lexer::lex(text).map_err(|e| e.to_string())?;
+    parser::parse(&tokens).map_err(|e| e.to_string())
+}
+
+/// The name a form starts with, taken from the source so that forms the
+/// reader rejects are still recognised
+fn head(text: &str) -> Option<&str> {
+    text.strip_prefix('(')?
+        .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+        .next()
+}
+
+/// Split source text into its top-level parenthesized forms
+fn split_forms(source: &str) -> Vec<String> {
+    let mut forms = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut chars = source.char_indices().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            ';' => while chars.next_if(|(_, c)| *c != '\n').is_some() {},
+            '"' => {
+                while let Some((_, c)) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '#' if chars.peek().map(|(_, c)| *c) == Some('\\') => {
+                chars.next();
+                chars.next();
+            }
+            '(' => {
+                if depth == 0 {
+                    start = index;
+                }
+                depth += 1;
+            }
+            ')' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    forms.push(source[start..=index].to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    forms
+}
@@ -0,0 +1,263 @@
+// Time-travel evaluation log for the debugger
+//
+// While recording, the evaluator logs each list it evaluates as a step: the
+// expression, the bindings it defined or assigned, and the value it returned.
+// Steps are numbered in the order they start and kept in a ring buffer of a
+// fixed number of steps, so a long run keeps only its most recent history and
+// memory stays bounded. The REPL walks the log with `:back` and `:forward`,
+// and `:why` finds the step that produced a value.
+//
+// A step holds its expression and values by reference, so recording doesn't
+// copy data, but it does keep alive whatever the retained steps refer to.
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::value::Value;
+
+/// How many steps a recording keeps unless told otherwise
+pub const DEFAULT_CAPACITY: usize = 10_000;
+
+/// One evaluation of a list
+#[derive(Clone, Debug)]
+pub struct Step {
+    /// Position in the order steps started, counting evicted steps
+    pub index: usize,
+    /// How many steps enclose this one
+    pub depth: usize,
+    pub expr: Value,
+    /// Bindings defined or assigned directly by this step, in order
+    pub changes: Vec<(String, Value)>,
+    /// The value returned; `None` while running, or if it raised
+    pub result: Option<Value>,
+    /// Position in the order steps returned
+    completed: Option<usize>,
+}
+
+/// At most this many characters of an expression or value are shown
+const SHOWN: usize = 72;
+
+fn shown(value: &Value) -> String {
+    let text = value.to_string();
+    match text.char_indices().nth(SHOWN) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text,
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} {}", self.index, shown(&self.expr))?;
+        match &self.result {
+            Some(result) => write!(f, " => {}", shown(result))?,
+            None => write!(f, " => (did not return)")?,
+        }
+        for (name, value) in &self.changes {
+            write!(f, "\n    {} := {}", name, shown(value))?;
+        }
+        Ok(())
+    }
+}
+
+/// The steps of a recording, oldest first
+#[derive(Clone, Debug, Default)]
+pub struct Log {
+    steps: VecDeque<Step>,
+    capacity: usize,
+    started: usize,
+    completed: usize,
+    /// Steps still running, innermost last
+    open: Vec<usize>,
+}
+
+impl Log {
+    pub fn new(capacity: usize) -> Self {
+        Log {
+            capacity: capacity.max(1),
+            ..Log::default()
+        }
+    }
+
+    pub fn steps(&self) -> impl DoubleEndedIterator<Item = &Step> {
+        self.steps.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// The step numbered `index`, unless it has been evicted
+    pub fn get(&self, index: usize) -> Option<&Step> {
+        let first = self.steps.front()?.index;
+        self.steps.get(index.checked_sub(first)?)
+    }
+
+    fn get_mut(&mut self, index: usize) -> Option<&mut Step> {
+        let first = self.steps.front()?.index;
+        self.steps.get_mut(index.checked_sub(first)?)
+    }
+
+    fn enter(&mut self, expr: &Value) -> usize {
+        let index = self.started;
+        self.started += 1;
+        if self.steps.len() == self.capacity {
+            self.steps.pop_front();
+        }
+        self.steps.push_back(Step {
+            index,
+            depth: self.open.len(),
+            expr: expr.clone(),
+            changes: Vec::new(),
+            result: None,
+            completed: None,
+        });
+        self.open.push(index);
+        index
+    }
+
+    fn exit(&mut self, index: usize, value: &Value) {
+        // Steps a raise unwound past never return; forget them here
+        if let Some(at) = self.open.iter().rposition(|open| *open == index) {
+            self.open.truncate(at);
+        }
+        let completed = self.completed;
+        self.completed += 1;
+        if let Some(step) = self.get_mut(index) {
+            step.result = Some(value.clone());
+            step.completed = Some(completed);
+        }
+    }
+
+    fn change(&mut self, name: &str, value: &Value) {
+        let Some(&index) = self.open.last() else {
+            return;
+        };
+        if let Some(step) = self.get_mut(index) {
+            step.changes.push((name.to_string(), value.clone()));
+        }
+    }
+
+    /// The step that produced `value`, then the steps that passed it on
+    /// outwards
+    ///
+    /// The value is traced from the latest step that returned it down through
+    /// the nested steps that returned it, taking the last to return at each
+    /// level, as `begin` and procedure bodies return their last expression.
+    /// A binding assigned the value counts as producing it when no step
+    /// returned it.
+    pub fn why(&self, value: &Value) -> Vec<&Step> {
+        let returned = |step: &&Step| step.result.as_ref() == Some(value);
+        let latest = self
+            .steps
+            .iter()
+            .filter(returned)
+            .max_by_key(|step| step.completed);
+
+        let Some(outer) = latest else {
+            return self
+                .steps
+                .iter()
+                .rev()
+                .find(|step| step.changes.iter().any(|(_, changed)| changed == value))
+                .into_iter()
+                .collect();
+        };
+
+        // A step nested in another starts after it and returns before it
+        let mut path = vec![outer];
+        while let Some(child) = self
+            .steps
+            .iter()
+            .filter(returned)
+            .filter(|step| {
+                let parent = path[path.len() - 1];
+                step.index > parent.index
+                    && step.depth == parent.depth + 1
+                    && step.completed < parent.completed
+            })
+            .max_by_key(|step| step.completed)
+        {
+            path.push(child);
+        }
+        path.reverse();
+        path
+    }
+}
+
+thread_local! {
+    static LOG: RefCell<Option<Log>> = const { RefCell::new(None) };
+    static RECORDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Start a new recording keeping the last `capacity` steps
+pub fn start(capacity: usize) {
+    LOG.with(|log| *log.borrow_mut() = Some(Log::new(capacity)));
+    RECORDING.with(|recording| recording.set(true));
+}
+
+/// Stop recording, keeping the log to look through
+pub fn stop() {
+    RECORDING.with(|recording| recording.set(false));
+}
+
+/// Stop recording and drop the log
+pub fn clear() {
+    stop();
+    LOG.with(|log| *log.borrow_mut() = None);
+}
+
+pub fn is_recording() -> bool {
+    RECORDING.with(Cell::get)
+}
+
+/// Run `f` with the log, if there is one
+pub fn with_log<T>(f: impl FnOnce(&Log) -> T) -> Option<T> {
+    LOG.with(|log| log.borrow().as_ref().map(f))
+}
+
+/// Run `f` without recording the evaluation it does
+pub fn paused<T>(f: impl FnOnce() -> T) -> T {
+    let was_recording = RECORDING.with(|recording| recording.replace(false));
+    let result = f();
+    RECORDING.with(|recording| recording.set(was_recording));
+    result
+}
+
+fn recording_log<T>(f: impl FnOnce(&mut Log) -> T) -> Option<T> {
+    if !is_recording() {
+        return None;
+    }
+    LOG.with(|log| log.borrow_mut().as_mut().map(f))
+}
+
+/// Log the start of an evaluation of `expr` if recording, returning its step
+pub fn enter(expr: &Value) -> Option<usize> {
+    match expr {
+        Value::Pair(_) => recording_log(|log| log.enter(expr)),
+        _ => None,
+    }
+}
+
+/// Log that step `index` returned `value`
+pub fn exit(index: usize, value: &Value) {
+    recording_log(|log| log.exit(index, value));
+}
+
+/// Log that step `index` was unwound by a raise and will not return
+pub fn unwound(index: usize) {
+    recording_log(|log| {
+        if let Some(at) = log.open.iter().rposition(|open| *open == index) {
+            log.open.truncate(at);
+        }
+    });
+}
+
+/// Log that `name` was defined or assigned `value`
+pub fn change(name: &str, value: &Value) {
+    recording_log(|log| log.change(name, value));
+}
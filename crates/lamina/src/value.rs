@@ -1,11 +1,43 @@
-use std::cell::RefCell;
+use std::any::Any;
+use std::cell::{OnceCell, RefCell};
 use std::fmt;
 use std::rc::Rc;
 
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::ToPrimitive;
+
+use crate::edition::Edition;
+use crate::error::Arity;
+use crate::evaluator::library_manager::LibraryRegistry;
+use crate::evaluator::machine::Continuation;
+use crate::evaluator::macros::Macro;
+use crate::evaluator::parameters::Parameter;
+use crate::evaluator::ports::{InputPort, OutputPort};
+use crate::evm::checksum_address;
+use crate::ffi::handle::Opaque;
+use crate::printer;
+
 #[derive(Clone)]
 pub struct Environment {
     pub parent: Option<Rc<RefCell<Environment>>>,
-    pub bindings: std::collections::HashMap<String, Value>,
+    pub bindings: Bindings,
+    /// Names bound by `setup_initial_env`; rebinding them warns
+    pub core: std::collections::HashSet<String>,
+    /// Reject rebinding core names instead of warning
+    pub strict: bool,
+    /// Library registry of the owning interpreter, set on the root environment
+    pub libraries: Option<Rc<RefCell<LibraryRegistry>>>,
+    /// Enums defined here, by variant name, so `case` can check it covers them
+    pub enums: std::collections::HashMap<String, Rc<EnumType>>,
+    /// Macros defined here by `define-syntax` or `let-syntax`
+    pub macros: std::collections::HashMap<String, Rc<Macro>>,
+    /// Check `assert`s; only read on the root environment
+    pub assertions: bool,
+    /// The language edition; only read on the root environment
+    pub edition: Edition,
+    /// Read source with symbols case-folded; only read on the root environment
+    pub fold_case: bool,
 }
 
 #[allow(dead_code)]
@@ -15,11 +47,158 @@ impl Default for Environment {
     }
 }
 
+thread_local! {
+    /// Emptied frames of dropped environments, for the environments of later
+    /// calls to reuse rather than allocate
+    static SPARE_FRAMES: RefCell<Vec<Vec<(String, Value)>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Most frames kept for reuse
+const MAX_SPARE_FRAMES: usize = 64;
+
+/// Most variables bound in a frame before it becomes a hash map. A procedure
+/// call or `let` binds a few, which are quicker to find by comparing names
+/// than by hashing them.
+const MAX_FRAME_BINDINGS: usize = 8;
+
+/// The variables an environment binds: a frame of name-value pairs while
+/// there are few, as in the environment of a call, and a hash map once there
+/// are many, as in the global environment
+#[derive(Clone)]
+pub enum Bindings {
+    Frame(Vec<(String, Value)>),
+    Map(std::collections::HashMap<String, Value>),
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings::new()
+    }
+}
+
+impl Bindings {
+    /// No bindings, in a frame left by a dropped environment if there is one
+    pub fn new() -> Self {
+        let frame = SPARE_FRAMES
+            .try_with(|spare| spare.borrow_mut().pop())
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        Bindings::Frame(frame)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        match self {
+            Bindings::Frame(frame) => frame.iter().find(|(n, _)| n == name).map(|(_, v)| v),
+            Bindings::Map(map) => map.get(name),
+        }
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Bind `name`, returning the value it was bound to before
+    pub fn insert(&mut self, name: String, value: Value) -> Option<Value> {
+        let frame = match self {
+            Bindings::Frame(frame) => frame,
+            Bindings::Map(map) => return map.insert(name, value),
+        };
+        if let Some((_, old)) = frame.iter_mut().find(|(n, _)| *n == name) {
+            return Some(std::mem::replace(old, value));
+        }
+        if frame.len() < MAX_FRAME_BINDINGS {
+            frame.push((name, value));
+        } else {
+            let mut map: std::collections::HashMap<_, _> =
+                std::mem::take(frame).into_iter().collect();
+            map.insert(name, value);
+            *self = Bindings::Map(map);
+        }
+        None
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Value> {
+        match self {
+            Bindings::Frame(frame) => {
+                let index = frame.iter().position(|(n, _)| n == name)?;
+                Some(frame.remove(index).1)
+            }
+            Bindings::Map(map) => map.remove(name),
+        }
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (&String, &Value)> + '_> {
+        match self {
+            Bindings::Frame(frame) => Box::new(frame.iter().map(|(n, v)| (n, v))),
+            Bindings::Map(map) => Box::new(map.iter()),
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.iter().map(|(name, _)| name)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Bindings::Frame(frame) => frame.len(),
+            Bindings::Map(map) => map.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<'a> IntoIterator for &'a Bindings {
+    type Item = (&'a String, &'a Value);
+    type IntoIter = Box<dyn Iterator<Item = (&'a String, &'a Value)> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl Drop for Environment {
+    fn drop(&mut self) {
+        let Bindings::Frame(mut frame) =
+            std::mem::replace(&mut self.bindings, Bindings::Frame(Vec::new()))
+        else {
+            return;
+        };
+        if frame.capacity() == 0 {
+            return;
+        }
+        // Dropping the values may drop other environments, which put their
+        // own frames back first
+        frame.clear();
+        let _ = SPARE_FRAMES.try_with(|spare| {
+            let mut spare = spare.borrow_mut();
+            if spare.len() < MAX_SPARE_FRAMES {
+                spare.push(frame);
+            }
+        });
+    }
+}
+
 impl Environment {
     pub fn new() -> Self {
         Environment {
             parent: None,
-            bindings: std::collections::HashMap::new(),
+            bindings: Bindings::new(),
+            core: std::collections::HashSet::new(),
+            strict: false,
+            libraries: None,
+            enums: std::collections::HashMap::new(),
+            macros: std::collections::HashMap::new(),
+            assertions: true,
+            edition: Edition::default(),
+            fold_case: false,
         }
     }
 
@@ -35,6 +214,75 @@ impl Environment {
     pub fn set(&mut self, key: String, value: Value) {
         self.bindings.insert(key, value);
     }
+
+    /// Whether `assert` and contract clauses are checked, as set on the root
+    /// environment
+    pub fn assertions_enabled(&self) -> bool {
+        match &self.parent {
+            Some(parent) => parent.borrow().assertions_enabled(),
+            None => self.assertions,
+        }
+    }
+
+    /// The language edition, as set on the root environment
+    pub fn edition(&self) -> Edition {
+        match &self.parent {
+            Some(parent) => parent.borrow().edition(),
+            None => self.edition,
+        }
+    }
+
+    /// Whether source is read case-folded, as set on the root environment
+    pub fn fold_case(&self) -> bool {
+        match &self.parent {
+            Some(parent) => parent.borrow().fold_case(),
+            None => self.fold_case,
+        }
+    }
+
+    /// The macro `name` refers to here, unless a variable shadows it
+    pub fn macro_named(&self, name: &str) -> Option<Rc<Macro>> {
+        if self.bindings.contains_key(name) {
+            return None;
+        }
+        self.macros.get(name).cloned().or_else(|| {
+            self.parent
+                .as_ref()
+                .and_then(|p| p.borrow().macro_named(name))
+        })
+    }
+
+    /// The enum `variant` belongs to, if it names one
+    pub fn enum_of(&self, variant: &str) -> Option<Rc<EnumType>> {
+        self.enums.get(variant).cloned().or_else(|| {
+            self.parent
+                .as_ref()
+                .and_then(|p| p.borrow().enum_of(variant))
+        })
+    }
+}
+
+// An enumeration defined by `define-enum`. Its variants are symbols, numbered
+// from 0 in the order they are declared.
+#[derive(Clone, Debug)]
+pub struct EnumType {
+    pub name: String,
+    pub variants: Vec<String>,
+}
+
+impl EnumType {
+    pub fn index(&self, variant: &str) -> Option<usize> {
+        self.variants.iter().position(|v| v == variant)
+    }
+
+    /// The variants none of `datums` name
+    pub fn missing(&self, datums: &[&str]) -> Vec<&str> {
+        self.variants
+            .iter()
+            .map(String::as_str)
+            .filter(|variant| !datums.contains(variant))
+            .collect()
+    }
 }
 
 // Define a record type structure
@@ -57,12 +305,119 @@ pub struct Library {
     pub name: Vec<String>, // Library name (e.g., (scheme base))
     #[allow(dead_code)]
     pub exports: Vec<String>, // List of exported symbols
+    /// The name each export renamed with `(rename internal external)` has
+    /// in the library's environment, by exported name
+    pub renames: std::collections::HashMap<String, String>,
     #[allow(dead_code)]
     pub imports: Vec<Vec<String>>, // List of imported libraries
     #[allow(dead_code)]
     pub environment: Rc<RefCell<Environment>>, // Library's environment
 }
 
+impl Library {
+    /// The name `export` is bound under in the library's environment
+    pub fn binding_name<'a>(&'a self, export: &'a str) -> &'a str {
+        self.renames.get(export).map_or(export, String::as_str)
+    }
+}
+
+// A procedure created by `lambda` or `(define (name param ...) body)`
+pub struct Lambda {
+    pub params: Value,
+    pub body: Value,
+    pub env: Rc<RefCell<Environment>>,
+    /// The name it was first defined under, for error messages
+    pub name: OnceCell<String>,
+}
+
+impl Lambda {
+    pub fn new(params: Value, body: Value, env: Rc<RefCell<Environment>>) -> Self {
+        Lambda {
+            params,
+            body,
+            env,
+            name: OnceCell::new(),
+        }
+    }
+
+    pub fn named(name: &str, params: Value, body: Value, env: Rc<RefCell<Environment>>) -> Self {
+        let lambda = Self::new(params, body, env);
+        let _ = lambda.name.set(name.to_string());
+        lambda
+    }
+
+    pub fn arity(&self) -> Arity {
+        let mut required = 0;
+        let mut params = &self.params;
+        while let Value::Pair(param) = params {
+            required += 1;
+            params = &param.1;
+        }
+        match params {
+            Value::Nil => Arity::Exactly(required),
+            _ => Arity::AtLeast(required),
+        }
+    }
+
+    /// How a call looks, `(name param ...)`, or `(lambda (param ...))` for an
+    /// anonymous procedure
+    pub fn signature(&self) -> String {
+        match self.name.get() {
+            Some(name) => Value::cons(Value::Symbol(name.clone()), self.params.clone()).to_string(),
+            None => format!("(lambda {})", self.params),
+        }
+    }
+
+    /// The `(lambda params body)` form the procedure was made from
+    pub fn source(&self) -> Value {
+        let body = Value::cons(self.body.clone(), Value::Nil);
+        Value::cons(
+            Value::Symbol("lambda".to_string()),
+            Value::cons(self.params.clone(), body),
+        )
+    }
+
+    /// The `(define (name param ...) body)` form defining a named procedure,
+    /// which is how backends lower one
+    pub fn definition(&self) -> Option<Value> {
+        let name = self.name.get()?;
+        let head = Value::cons(Value::Symbol(name.clone()), self.params.clone());
+        let body = Value::cons(self.body.clone(), Value::Nil);
+        Some(Value::cons(
+            Value::Symbol("define".to_string()),
+            Value::cons(head, body),
+        ))
+    }
+}
+
+/// A pair's car and cdr
+///
+/// Dropping the last reference to a list frees its pairs in a loop rather
+/// than recursively, so a list of any length, or data nested any depth, is
+/// dropped without overflowing the Rust stack.
+pub struct Cons(pub Value, pub Value);
+
+impl Drop for Cons {
+    fn drop(&mut self) {
+        if !matches!(self.0, Value::Pair(_)) && !matches!(self.1, Value::Pair(_)) {
+            return;
+        }
+        let mut pending = vec![
+            std::mem::replace(&mut self.0, Value::Nil),
+            std::mem::replace(&mut self.1, Value::Nil),
+        ];
+        while let Some(value) = pending.pop() {
+            if let Value::Pair(pair) = value {
+                // Shared pairs are someone else's to free
+                if let Ok(mut cons) = Rc::try_unwrap(pair) {
+                    pending.push(std::mem::replace(&mut cons.0, Value::Nil));
+                    pending.push(std::mem::replace(&mut cons.1, Value::Nil));
+                }
+            }
+        }
+    }
+}
+
 #[derive(Clone)]
 pub enum Value {
     Nil,
@@ -71,10 +426,18 @@ pub enum Value {
     Character(char),
     String(String),
     Symbol(String),
-    Pair(Rc<(Value, Value)>),
+    Pair(Rc<Cons>),
     #[allow(dead_code)]
-    Vector(Rc<Vec<Value>>),
+    Vector(Rc<RefCell<Vec<Value>>>),
     Procedure(Rc<dyn Fn(Vec<Value>) -> Result<Value, String>>),
+    // Applied on the evaluator's stack, so its body can capture continuations
+    Lambda(Rc<Lambda>),
+    // Captured by `shift`
+    Continuation(Rc<Continuation>),
+    InputPort(Rc<InputPort>),
+    OutputPort(Rc<OutputPort>),
+    // Made by `make-parameter`; calling it returns its current value
+    Parameter(Rc<Parameter>),
     #[allow(dead_code)]
     Environment(Rc<RefCell<Environment>>),
     // Add Record types
@@ -85,6 +448,12 @@ pub enum Value {
     Bytevector(Rc<RefCell<Vec<u8>>>),
     // Add Library
     Library(Rc<RefCell<Library>>),
+    // 20-byte EVM account address
+    Address([u8; 20]),
+    // Rust object owned through an FFI handle, finalized when closed or dropped
+    Opaque(Rc<Opaque>),
+    // Rust value shared with Lamina as is, with the name of its type
+    Foreign(Rc<dyn Any>, &'static str),
     // Add RustFn to represent foreign Rust functions
     #[allow(dead_code)]
     RustFn(Rc<dyn Fn(Vec<Value>) -> Result<Value, String>>, String),
@@ -100,36 +469,53 @@ impl fmt::Debug for Value {
             Value::String(s) => write!(f, "String({})", s),
             Value::Symbol(s) => write!(f, "Symbol({})", s),
             Value::Pair(p) => write!(f, "Pair({:?}, {:?})", p.0, p.1),
-            Value::Vector(v) => write!(f, "Vector({:?})", v),
-            Value::Procedure(_) => write!(f, "Procedure"),
+            Value::Vector(v) => write!(f, "Vector({:?})", v.borrow()),
+            Value::Procedure(_) | Value::Lambda(_) => write!(f, "Procedure"),
+            Value::Continuation(_) => write!(f, "Continuation"),
+            Value::InputPort(_) => write!(f, "InputPort"),
+            Value::OutputPort(_) => write!(f, "OutputPort"),
+            Value::Parameter(_) => write!(f, "Parameter"),
             Value::Environment(_) => write!(f, "Environment"),
             Value::RecordType(rt) => write!(f, "RecordType({})", rt.name),
             Value::Record(r) => write!(f, "Record({})", r.type_info.name),
             Value::Bytevector(bytes) => write!(f, "Bytevector({:?})", bytes.borrow()),
             Value::Library(lib) => write!(f, "Library({:?})", lib.borrow().name),
+            Value::Address(bytes) => write!(f, "Address({})", checksum_address(bytes)),
+            Value::Opaque(handle) => write!(f, "Opaque({})", handle.type_name()),
+            Value::Foreign(_, type_name) => write!(f, "Foreign({})", type_name),
             Value::RustFn(_, name) => write!(f, "RustFn({})", name),
         }
     }
 }
 
+/// A number of the numeric tower. Exact integers are held in an `i64` while
+/// they fit and as a `BigInteger` once they don't, and exact non-integers as
+/// `Rational`s in lowest terms; `number::arithmetic` keeps results in that
+/// form, so each number has one representation and derived equality is
+/// `eqv?`.
 #[derive(Clone, Debug, PartialEq)]
 pub enum NumberKind {
     Integer(i64),
+    BigInteger(Rc<BigInt>),
+    Rational(Rc<BigRational>),
     Real(f64),
-    #[allow(dead_code)]
-    Rational(i64, i64),
 }
 
 impl NumberKind {
-    #[allow(dead_code)]
     pub fn as_f64(&self) -> f64 {
         match self {
             NumberKind::Integer(i) => *i as f64,
+            NumberKind::BigInteger(n) => n.to_f64().unwrap_or(f64::NAN),
+            NumberKind::Rational(r) => r.to_f64().unwrap_or(f64::NAN),
             NumberKind::Real(r) => *r,
-            NumberKind::Rational(n, d) => *n as f64 / *d as f64,
         }
     }
 
+    /// Whether the number is exact, an integer or rational
+    pub fn is_exact(&self) -> bool {
+        !matches!(self, NumberKind::Real(_))
+    }
+
     pub fn to_u8(&self) -> Result<u8, String> {
         match self {
             NumberKind::Integer(i) => {
@@ -139,6 +525,7 @@ impl NumberKind {
                     Err(format!("Integer value {} out of range for u8", i))
                 }
             }
+            NumberKind::BigInteger(n) => Err(format!("Integer value {} out of range for u8", n)),
             NumberKind::Real(r) => {
                 if r.is_finite() && (0.0..=255.0).contains(r) {
                     Ok(*r as u8)
@@ -146,18 +533,57 @@ impl NumberKind {
                     Err(format!("Real value {} out of range for u8", r))
                 }
             }
-            NumberKind::Rational(n, d) => {
-                let value = *n as f64 / *d as f64;
+            NumberKind::Rational(r) => {
+                let value = self.as_f64();
                 if value.is_finite() && (0.0..=255.0).contains(&value) {
                     Ok(value as u8)
                 } else {
-                    Err(format!("Rational value {}/{} out of range for u8", n, d))
+                    Err(format!("Rational value {} out of range for u8", r))
                 }
             }
         }
     }
 }
 
+/// The elements of a list, from [`Value::iter`]
+pub struct ListIter<'a> {
+    rest: &'a Value,
+}
+
+impl<'a> Iterator for ListIter<'a> {
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<&'a Value> {
+        match self.rest {
+            Value::Pair(pair) => {
+                self.rest = &pair.1;
+                Some(&pair.0)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Value {
+    type Item = &'a Value;
+    type IntoIter = ListIter<'a>;
+
+    fn into_iter(self) -> ListIter<'a> {
+        self.iter()
+    }
+}
+
+/// Collects into a proper list
+impl FromIterator<Value> for Value {
+    fn from_iter<I: IntoIterator<Item = Value>>(items: I) -> Self {
+        let items: Vec<Value> = items.into_iter().collect();
+        items
+            .into_iter()
+            .rev()
+            .fold(Value::Nil, |rest, item| Value::cons(item, rest))
+    }
+}
+
 // Implement From trait for Value
 impl From<f64> for Value {
     fn from(value: f64) -> Self {
@@ -173,18 +599,15 @@ impl From<i64> for Value {
 
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        printer::write(self, f)
+    }
+}
+
+impl Value {
+    /// Write a value that isn't a pair or vector, in full
+    pub(crate) fn fmt_atom(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Value::Number(n) => match n {
-                NumberKind::Integer(i) => write!(f, "{}", i),
-                NumberKind::Real(r) => {
-                    if r.fract() == 0.0 {
-                        write!(f, "{}.0", r)
-                    } else {
-                        write!(f, "{}", r)
-                    }
-                }
-                NumberKind::Rational(num, den) => write!(f, "{}/{}", num, den),
-            },
+            Value::Number(n) => write!(f, "{}", n),
             Value::Symbol(s) => write!(f, "{}", s),
             Value::String(s) => write!(f, "\"{}\"", s),
             Value::Boolean(b) => {
@@ -196,30 +619,22 @@ impl fmt::Display for Value {
             }
             Value::Character(c) => write!(f, "#\\{}", c),
             Value::Nil => write!(f, "()"),
-            Value::Pair(_p) => {
-                let mut current = self;
-                let mut is_first = true;
-                write!(f, "(")?;
-                loop {
-                    match current {
-                        Value::Pair(pair) => {
-                            if !is_first {
-                                write!(f, " ")?;
-                            }
-                            write!(f, "{}", pair.0)?;
-                            current = &pair.1;
-                            is_first = false;
-                        }
-                        Value::Nil => break,
-                        _ => {
-                            write!(f, " . {}", current)?;
-                            break;
-                        }
-                    }
-                }
-                write!(f, ")")
-            }
+            Value::Pair(_) | Value::Vector(_) => fmt::Display::fmt(self, f),
+            Value::Lambda(lambda) => match lambda.name.get() {
+                Some(name) => write!(f, "#<procedure {} {}>", name, lambda.params),
+                None => write!(f, "#<procedure>"),
+            },
             Value::Procedure(_) => write!(f, "#<procedure>"),
+            Value::Continuation(_) => write!(f, "#<continuation>"),
+            Value::InputPort(_) => write!(f, "#<input-port>"),
+            Value::OutputPort(_) => write!(f, "#<output-port>"),
+            Value::Parameter(_) => write!(f, "#<parameter>"),
+            Value::Address(bytes) => write!(f, "{}", checksum_address(bytes)),
+            Value::Opaque(handle) if handle.is_closed() => {
+                write!(f, "#<{} closed>", handle.type_name())
+            }
+            Value::Opaque(handle) => write!(f, "#<{}>", handle.type_name()),
+            Value::Foreign(_, type_name) => write!(f, "#<foreign {}>", type_name),
             Value::Library(lib) => {
                 let name = &lib.borrow().name;
                 write!(f, "#<library:{}>", name.join(" "))
@@ -241,16 +656,6 @@ impl fmt::Display for Value {
                 }
                 write!(f, ")")
             }
-            Value::Vector(v) => {
-                write!(f, "#(")?;
-                for (i, val) in v.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, " ")?;
-                    }
-                    write!(f, "{}", val)?;
-                }
-                write!(f, ")")
-            }
             Value::Environment(_) => write!(f, "#<environment>"),
             Value::RustFn(_, name) => write!(f, "#<rust-function:{}>", name),
         }
@@ -268,10 +673,30 @@ impl PartialEq for Value {
             (Value::String(a), Value::String(b)) => a == b,
             (Value::Symbol(a), Value::Symbol(b)) => a == b,
             (Value::Pair(a), Value::Pair(b)) => {
-                // Compare car and cdr
-                a.0 == b.0 && a.1 == b.1
+                // Walk the cdrs in a loop so long lists compare without
+                // recursing once per element
+                let (mut a, mut b) = (a, b);
+                loop {
+                    if Rc::ptr_eq(a, b) {
+                        return true;
+                    }
+                    if a.0 != b.0 {
+                        return false;
+                    }
+                    match (&a.1, &b.1) {
+                        (Value::Pair(next_a), Value::Pair(next_b)) => {
+                            a = next_a;
+                            b = next_b;
+                        }
+                        (rest_a, rest_b) => return rest_a == rest_b,
+                    }
+                }
             }
             (Value::Vector(a), Value::Vector(b)) => {
+                if Rc::ptr_eq(a, b) {
+                    return true;
+                }
+                let (a, b) = (a.borrow(), b.borrow());
                 if a.len() != b.len() {
                     return false;
                 }
@@ -284,6 +709,13 @@ impl PartialEq for Value {
             (Value::Environment(a), Value::Environment(b)) => Rc::ptr_eq(a, b),
             (Value::RecordType(a), Value::RecordType(b)) => Rc::ptr_eq(a, b),
             (Value::Record(a), Value::Record(b)) => Rc::ptr_eq(a, b),
+            (Value::InputPort(a), Value::InputPort(b)) => Rc::ptr_eq(a, b),
+            (Value::OutputPort(a), Value::OutputPort(b)) => Rc::ptr_eq(a, b),
+            (Value::Parameter(a), Value::Parameter(b)) => Rc::ptr_eq(a, b),
+            (Value::Opaque(a), Value::Opaque(b)) => Rc::ptr_eq(a, b),
+            (Value::Foreign(a, _), Value::Foreign(b, _)) => {
+                std::ptr::addr_eq(Rc::as_ptr(a), Rc::as_ptr(b))
+            }
             // Other combinations are not equal
             _ => false,
         }
@@ -294,6 +726,43 @@ impl PartialEq for Value {
 impl Value {
     // Create a new Pair (cons cell)
     pub fn cons(car: Value, cdr: Value) -> Self {
-        Value::Pair(Rc::new((car, cdr)))
+        Value::Pair(Rc::new(Cons(car, cdr)))
+    }
+
+    /// The proper list of `items`
+    pub fn list(items: impl IntoIterator<Item = Value>) -> Self {
+        items.into_iter().collect()
+    }
+
+    /// The association list of `entries`, `((key . value) ...)` with each
+    /// key a symbol
+    pub fn alist(entries: &[(&str, Value)]) -> Self {
+        entries
+            .iter()
+            .map(|(key, value)| Value::cons(Value::Symbol(key.to_string()), value.clone()))
+            .collect()
+    }
+
+    /// The elements of a list, up to its end or the improper tail of a
+    /// dotted one
+    pub fn iter(&self) -> ListIter<'_> {
+        ListIter { rest: self }
+    }
+
+    /// Whether a test that evaluates to this value holds: everything but #f
+    /// does, as in Scheme, including 0 and the empty list
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Boolean(false))
+    }
+
+    pub fn is_procedure(&self) -> bool {
+        matches!(
+            self,
+            Value::Procedure(_)
+                | Value::RustFn(_, _)
+                | Value::Lambda(_)
+                | Value::Continuation(_)
+                | Value::Parameter(_)
+        )
     }
 }
@@ -0,0 +1,62 @@
+//! Allocations made by procedure calls, counted by a global allocator. A test
+//! target of its own, so other tests don't allocate while one counts.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use lamina::execute;
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Allocations per round of a loop of `rounds` calls of `call`
+fn allocations_per_call(call: &str, rounds: usize) -> usize {
+    let run = |count: usize| {
+        let code = format!(
+            "(let loop ((i 0)) (if (= i {}) 'done (begin {} (loop (+ i 1)))))",
+            count, call
+        );
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        execute(&code).unwrap();
+        ALLOCATIONS.load(Ordering::Relaxed) - before
+    };
+    // What running the loop costs apart from its rounds cancels out
+    let short = run(rounds);
+    let long = run(2 * rounds);
+    (long - short) / rounds
+}
+
+#[test]
+fn test_call_frames_are_reused() {
+    execute("(define (three a b c) c)").unwrap();
+    execute("(define (add-to n) (lambda (x) (+ x n)))").unwrap();
+    execute("(define add5 (add-to 5))").unwrap();
+
+    // A call takes the frame a finished call left, so over a native call of
+    // the same arguments it allocates only its environment and the names of
+    // its parameters
+    let three = allocations_per_call("(three 1 2 i)", 1000);
+    let plus = allocations_per_call("(+ 1 2 i)", 1000);
+    assert_eq!(three, plus + 4);
+
+    // A closure's frame is reused the same way; the rest is the call in its
+    // body
+    let add5 = allocations_per_call("(add5 i)", 1000);
+    let plus5 = allocations_per_call("(+ 5 i)", 1000);
+    assert_eq!(add5, plus5 + 6);
+}
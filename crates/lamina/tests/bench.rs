@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use lamina::testing::bench::{run_benches, Baseline, BenchOptions};
+
+fn quick_options() -> BenchOptions {
+    BenchOptions {
+        warmup: Duration::from_millis(1),
+        measurement: Duration::from_millis(5),
+        samples: 3,
+        ..BenchOptions::default()
+    }
+}
+
+#[test]
+fn test_benches_are_collected_and_timed() {
+    let source = r#"
+(define square (lambda (x) (* x x)))
+(define-bench "square" (lambda () (square 12)))
+(define-bench "broken" (lambda () (car 1)))
+"#;
+    let results = run_benches(source, &quick_options()).unwrap();
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].name, "square");
+    assert!(results[0].outcome.as_ref().is_ok_and(|ns| *ns > 0.0));
+    assert!(results[0].iterations >= 1);
+    assert!(results[1].outcome.is_err());
+
+    // Only successful benchmarks make it into a baseline
+    let baseline = Baseline::from_results(&results);
+    assert_eq!(baseline.entries.len(), 1);
+}
+
+#[test]
+fn test_baseline_round_trip_and_change() {
+    let baseline = Baseline::parse("fib 10\t200.0\nadd\t50.0\n").unwrap();
+    assert_eq!(baseline.entries["fib 10"], 200.0);
+    assert_eq!(Baseline::parse(&baseline.to_text()).unwrap(), baseline);
+
+    assert_eq!(baseline.change("fib 10", 250.0), Some(25.0));
+    assert_eq!(baseline.change("add", 45.0), Some(-10.0));
+    assert_eq!(baseline.change("missing", 1.0), None);
+
+    assert!(Baseline::parse("no tab here").is_err());
+}
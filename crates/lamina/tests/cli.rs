@@ -0,0 +1,49 @@
+use lamina::cli::{self, Format, Output, Verbosity};
+use lamina::json::{parse_json, Json};
+
+#[test]
+fn test_output_settings() {
+    let default = Output::default();
+    assert_eq!(default.format, Format::Human);
+    assert_eq!(default.verbosity, Verbosity::Normal);
+
+    // Flags override whatever the environment chose
+    let env = Output::new(Format::Human, Verbosity::Quiet);
+    let out = env.with_flags(true, false, true);
+    assert!(out.is_json());
+    assert!(out.is_verbose());
+    assert_eq!(env.with_flags(false, false, false), env);
+    assert_eq!(
+        Output::default().with_flags(false, true, false).verbosity,
+        Verbosity::Quiet
+    );
+
+    assert_eq!(Format::parse("json"), Some(Format::Json));
+    assert_eq!(Verbosity::parse("verbose"), Some(Verbosity::Verbose));
+    assert_eq!(Verbosity::parse("loud"), None);
+}
+
+#[test]
+fn test_json_records() {
+    let fields = Json::object([
+        ("name", Json::from("adds \"one\"\n")),
+        ("passed", Json::from(3usize)),
+        ("ratio", Json::from(0.5)),
+        ("ok", Json::from(true)),
+        ("seed", Json::Null),
+    ]);
+    let line = cli::record("test", fields).to_string();
+    assert_eq!(
+        line,
+        r#"{"type":"test","name":"adds \"one\"\n","passed":3,"ratio":0.5,"ok":true,"seed":null}"#
+    );
+
+    // What the writer prints reads back unchanged
+    let parsed = parse_json(&line).unwrap();
+    assert_eq!(parsed.get("type").and_then(Json::as_str), Some("test"));
+    assert_eq!(
+        parsed.get("name").and_then(Json::as_str),
+        Some("adds \"one\"\n")
+    );
+    assert_eq!(parsed.to_string(), line);
+}
@@ -0,0 +1,100 @@
+use lamina::embed::Interpreter;
+
+fn reader(input: &str) -> Interpreter {
+    Interpreter::builder()
+        .with_input(std::io::Cursor::new(input.to_string()))
+        .build()
+}
+
+#[test]
+fn test_read() {
+    let interpreter = reader("(a #(1 2)) 42 ; comment\n\"two\nlines\" (b\n c)\n; end\n");
+    let eval = |code: &str| interpreter.eval(code).unwrap().to_string();
+
+    assert_eq!(eval("(read)"), "(a #(1 2))");
+    assert_eq!(eval("(read)"), "42");
+    assert_eq!(eval("(read (current-input-port))"), "\"two\nlines\"");
+    assert_eq!(eval("(read)"), "(b c)");
+    assert_eq!(eval("(eof-object? (read))"), "#t");
+}
+
+#[test]
+fn test_read_errors() {
+    let guarded =
+        "(guard (e ((read-error? e) (list (error-object? e) (error-object-message e)))) (read))";
+    assert_eq!(
+        reader("(a b\n").eval(guarded).unwrap().to_string(),
+        "(#t \"Unexpected end of input in list\")"
+    );
+    assert_eq!(
+        reader("#u8(300)\n").eval(guarded).unwrap().to_string(),
+        "(#t \"Invalid byte in bytevector literal: 300\")"
+    );
+
+    // Unhandled, the message says what kind of error it was
+    let error = reader(")").eval("(read)").unwrap_err().to_string();
+    assert!(
+        error.contains("Read error: Unexpected right parenthesis"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn test_file_errors() {
+    let interpreter = Interpreter::builder().build();
+    let eval = |code: &str| interpreter.eval(code).unwrap().to_string();
+    let missing = std::env::temp_dir().join(format!("lamina-missing-{}.txt", std::process::id()));
+    let missing = missing.display();
+
+    assert_eq!(
+        eval(&format!(
+            "(guard (e ((file-error? e) (condition-type e))) (with-input-from-file \"{}\" read-line))",
+            missing
+        )),
+        "file-error"
+    );
+    let error = interpreter
+        .eval(&format!("(with-input-from-file \"{}\" read-line)", missing))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("File error: Cannot open"), "{}", error);
+
+    // Other errors are not conditions
+    assert_eq!(
+        eval("(guard (e ((error-object? e) 'condition) (else 'other)) (car 1))"),
+        "other"
+    );
+}
+
+#[test]
+fn test_condition_hierarchy() {
+    let interpreter = Interpreter::builder().build();
+    let eval = |code: &str| interpreter.eval(code).unwrap().to_string();
+
+    eval("(define e (make-condition 'read-error \"bad\" 1 'x))");
+    assert_eq!(
+        eval("(list (condition? e) (error-object? e) (read-error? e) (file-error? e))"),
+        "(#t #t #t #f)"
+    );
+    assert_eq!(eval("(error-object-irritants e)"), "(1 x)");
+    assert_eq!(
+        eval("(error-object? (make-condition 'condition \"base\"))"),
+        "#f"
+    );
+    assert_eq!(
+        eval("(list (condition? 'e) (read-error? \"bad\"))"),
+        "(#f #f)"
+    );
+
+    // Raised conditions reach guards as they are
+    assert_eq!(
+        eval("(guard (c ((read-error? c) (error-object-message c))) (raise e))"),
+        "\"bad\""
+    );
+    assert!(interpreter
+        .eval("(make-condition 'warning \"w\")")
+        .unwrap_err()
+        .to_string()
+        .contains("unknown condition type"));
+}
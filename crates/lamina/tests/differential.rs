@@ -0,0 +1,90 @@
+#![cfg(feature = "differential")]
+
+use lamina::evaluator::environment::setup_initial_env;
+use lamina::testing::differential::{
+    evaluate, generate_expressions, results_agree, run_differential, Reference,
+};
+use lamina::testing::r7rs::KnownFailures;
+
+const KNOWN_ISSUES: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/differential/known-issues.txt"
+);
+
+const SEED: u64 = 509;
+const EXPRESSIONS: usize = 2_000;
+
+#[test]
+fn test_result_comparison() {
+    assert!(results_agree("1/3", "1/3"));
+    assert!(results_agree("0.30000000000000004", "0.3"));
+    assert!(results_agree("1e21", "1e+21"));
+    assert!(results_agree("+nan.0", "+nan.0"));
+    assert!(results_agree("error", "error"));
+    assert!(results_agree("#t", "#t"));
+    // Exactness has to match
+    assert!(!results_agree("2", "2.0"));
+    assert!(!results_agree("1/2", "0.5"));
+    assert!(!results_agree("error", "0"));
+
+    // The same seed gives the same expressions, which the interpreter reads
+    let expressions = generate_expressions(SEED, 50);
+    assert_eq!(expressions, generate_expressions(SEED, 50));
+    let env = setup_initial_env();
+    assert_eq!(evaluate("(+ 1/2 1/3)", env.clone()), "5/6");
+    assert_eq!(evaluate("(/ 1 0)", env), "error");
+}
+
+// Compares the interpreter with chibi-scheme or guile, and checks that exactly
+// the known issues diverge. The report is written next to the other test
+// outputs; set DIFFERENTIAL_BLESS=1 to rewrite the known issues.
+#[test]
+fn test_numeric_differential() {
+    let Some(reference) = Reference::find() else {
+        eprintln!("No chibi-scheme or guile on PATH, skipping the differential run");
+        return;
+    };
+    let expressions = generate_expressions(SEED, EXPRESSIONS);
+    let report = run_differential(&reference, &expressions).unwrap();
+
+    let report_path =
+        std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("differential-report.txt");
+    std::fs::write(&report_path, report.render()).unwrap();
+
+    if std::env::var_os("DIFFERENTIAL_BLESS").is_some() {
+        let header = format!(
+            "# Generated expressions whose results differ from {}, one per line\n",
+            reference.name
+        );
+        let known = report.known_issues();
+        std::fs::write(KNOWN_ISSUES, format!("{}{}", header, known.to_text())).unwrap();
+        return;
+    }
+
+    let known = KnownFailures::parse(&std::fs::read_to_string(KNOWN_ISSUES).unwrap());
+    let describe = |c: &&lamina::testing::differential::Comparison| {
+        format!(
+            "{}: lamina {}, {} {}",
+            c.expression, c.lamina, reference.name, c.reference
+        )
+    };
+    let unexpected: Vec<String> = report
+        .unexpected_divergences(&known)
+        .iter()
+        .map(describe)
+        .collect();
+    let fixed: Vec<String> = report.fixed(&known).iter().map(describe).collect();
+
+    assert!(
+        unexpected.is_empty(),
+        "Results differing from {} that are not known issues:\n{}",
+        reference.name,
+        unexpected.join("\n")
+    );
+    assert!(
+        fixed.is_empty(),
+        "Results agreeing with {} that are still listed in known-issues.txt:\n{}",
+        reference.name,
+        fixed.join("\n")
+    );
+}
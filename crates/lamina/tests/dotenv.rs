@@ -0,0 +1,109 @@
+use lamina::dotenv::{self, Source};
+use lamina::edition;
+use lamina::embed::Interpreter;
+use lamina::project::Manifest;
+use lamina::value::Value;
+
+fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_parse_dotenv() {
+    let text = r#"
+# Deployment account
+export RPC_URL=http://localhost:8545 # local node
+KEY="0xab\ncd"
+QUOTED='keeps \n # as is'
+EMPTY=
+"#;
+    assert_eq!(
+        dotenv::parse(text).unwrap(),
+        vars(&[
+            ("RPC_URL", "http://localhost:8545"),
+            ("KEY", "0xab\ncd"),
+            ("QUOTED", "keeps \\n # as is"),
+            ("EMPTY", ""),
+        ])
+    );
+
+    assert_eq!(
+        dotenv::parse("A=1\nno value here").unwrap_err(),
+        ".env:2: expected NAME=value"
+    );
+    assert!(dotenv::parse("1A=1").is_err());
+    assert!(dotenv::parse("A=\"open").is_err());
+}
+
+#[test]
+fn test_resolve_precedence() {
+    let resolved = dotenv::resolve(
+        |name| name == "FROM_SHELL",
+        vars(&[("FROM_SHELL", "dotenv"), ("RPC", "local")]),
+        vars(&[
+            ("RPC", "default"),
+            ("CHAIN", "1"),
+            ("FROM_SHELL", "default"),
+        ]),
+    );
+    assert_eq!(
+        resolved,
+        vec![
+            ("RPC".to_string(), "local".to_string(), Source::Dotenv),
+            ("CHAIN".to_string(), "1".to_string(), Source::Manifest),
+        ]
+    );
+}
+
+#[test]
+fn test_load_project_env() {
+    let dir = std::env::temp_dir().join(format!("lamina-dotenv-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    assert_eq!(dotenv::load(&dir, true), Ok(Vec::new()));
+
+    std::fs::write(
+        dir.join(edition::MANIFEST),
+        "[package]\nname = \"vault\"\n\n[env]\nLAMINA_TEST_CHAIN = 31337\nLAMINA_TEST_RPC = \"http://default\"\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join(dotenv::FILE), "LAMINA_TEST_RPC=http://local\n").unwrap();
+
+    // .env overrides the manifest's default for LAMINA_TEST_RPC
+    assert_eq!(
+        Manifest::read(&dir).unwrap().unwrap().env,
+        vars(&[
+            ("LAMINA_TEST_CHAIN", "31337"),
+            ("LAMINA_TEST_RPC", "http://default")
+        ])
+    );
+    let loaded = dotenv::load(&dir, true).unwrap();
+    assert_eq!(
+        loaded,
+        vec![
+            ("LAMINA_TEST_RPC".to_string(), Source::Dotenv),
+            ("LAMINA_TEST_CHAIN".to_string(), Source::Manifest),
+        ]
+    );
+
+    let interpreter = Interpreter::builder().build();
+    assert_eq!(
+        interpreter
+            .eval("(get-environment-variable \"LAMINA_TEST_RPC\")")
+            .unwrap(),
+        Value::String("http://local".to_string())
+    );
+    assert_eq!(
+        interpreter
+            .eval("(get-environment-variable \"LAMINA_TEST_UNSET\")")
+            .unwrap(),
+        Value::Boolean(false)
+    );
+
+    // Variables set already are left alone
+    assert_eq!(dotenv::load(&dir, true), Ok(Vec::new()));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,116 @@
+use lamina::edition::{self, Edition};
+use lamina::execute;
+use lamina::project::Manifest;
+use lamina::{lexer, parser};
+
+#[test]
+fn test_edition_bodies() {
+    // Edition 2025, the default, evaluates every body expression and returns
+    // the last
+    assert_eq!(execute("((lambda () 1 2))").unwrap(), "2");
+    assert_eq!(
+        execute("(begin (define (f x) (display x) (* x 2)) (f 2))").unwrap(),
+        "4"
+    );
+    for form in ["let", "let*", "letrec"] {
+        assert_eq!(
+            execute(&format!("({form} ((x 1)) (set! x (+ x 1)) (* x 10))")).unwrap(),
+            "20",
+            "{form}"
+        );
+    }
+    assert_eq!(
+        execute("(let loop ((i 0) (n 0)) (set! n (+ n i)) (if (= i 3) n (loop (+ i 1) n)))")
+            .unwrap(),
+        "6"
+    );
+
+    // Edition 2024 evaluates only the first
+    assert_eq!(
+        execute("(begin (lamina-edition 2024) ((lambda () 1 2)))").unwrap(),
+        "1"
+    );
+    assert_eq!(
+        execute("(begin (lamina-edition 2024) (define (f x) (+ x 1) (* x 10)) (f 2))").unwrap(),
+        "3"
+    );
+    assert_eq!(
+        execute("(begin (lamina-edition 2024) (let ((x 1)) x 2))").unwrap(),
+        "1"
+    );
+
+    assert_eq!(
+        execute("(begin (lamina-edition 2025) ((lambda () 1 2)))").unwrap(),
+        "2"
+    );
+    assert_eq!(
+        execute(
+            "(begin (lamina-edition 2025)
+                    (define n 0)
+                    (define (bump!) (set! n (+ n 1)) (set! n (+ n 1)) n)
+                    (bump!))"
+        )
+        .unwrap(),
+        "2"
+    );
+
+    assert!(execute("(lamina-edition 1999)").is_err());
+}
+
+#[test]
+fn test_edition_pragma() {
+    let tokens = lexer::lex("#!lamina1\n(+ 1 2)").unwrap();
+    let forms = parser::parse_all(&tokens).unwrap();
+    assert_eq!(forms[0].to_string(), "(lamina-edition 2025)");
+
+    assert!(parser::parse_all(&lexer::lex("#!lamina9").unwrap()).is_err());
+}
+
+#[test]
+fn test_declared_edition() {
+    assert_eq!(edition::declared("#!lamina0\n(f)"), Some(Edition::E2024));
+    assert_eq!(edition::declared("#!lamina1\n(f)"), Some(Edition::E2025));
+    assert_eq!(
+        edition::declared("(lamina-edition 2025) (f)"),
+        Some(Edition::E2025)
+    );
+    assert_eq!(edition::declared("(f) (lamina-edition 2025)"), None);
+
+    assert_eq!(
+        edition::with_default("(f)\n(g)", Edition::E2025),
+        "(lamina-edition 2025) (f)\n(g)"
+    );
+    assert_eq!(
+        edition::with_default("#!lamina0\n(f)", Edition::E2025),
+        "#!lamina0\n(f)"
+    );
+}
+
+#[test]
+fn test_project_edition() {
+    let dir = std::env::temp_dir().join(format!("lamina-edition-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let edition = || Manifest::read(&dir).map(|manifest| manifest.and_then(|m| m.edition));
+    assert_eq!(edition(), Ok(None));
+
+    let manifest = dir.join(edition::MANIFEST);
+    std::fs::write(&manifest, Manifest::new("vault", "evm").render()).unwrap();
+    assert_eq!(edition(), Ok(Some(Edition::E2025)));
+
+    // Only the edition of [package] is the project's
+    std::fs::write(
+        &manifest,
+        "[package]\nname = \"vault\"\n\n[tool.old]\nedition = \"2024\"\n",
+    )
+    .unwrap();
+    assert_eq!(edition(), Ok(None));
+
+    std::fs::write(
+        &manifest,
+        "[package]\nname = \"vault\"\nedition = \"2031\"\n",
+    )
+    .unwrap();
+    assert!(edition().is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
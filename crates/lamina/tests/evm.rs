@@ -0,0 +1,421 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use num_bigint::BigInt;
+
+use lamina::evaluator;
+use lamina::evaluator::environment::setup_initial_env;
+use lamina::evm::{
+    parse_abi, register_simulated_evm, EvmState, GasSchedule, Hardfork, Word, ETH_CALL,
+};
+use lamina::lexer;
+use lamina::parser;
+use lamina::value::Value;
+
+// Evaluate code with the simulated evm primitives bound
+fn eval_evm(code: &str) -> Result<String, String> {
+    let env = setup_initial_env();
+    register_simulated_evm(env.clone(), Rc::new(RefCell::new(EvmState::new())));
+
+    let tokens = lexer::lex(code).map_err(|e| e.to_string())?;
+    let expr = parser::parse(&tokens).map_err(|e| e.to_string())?;
+    evaluator::eval_with_env(expr, env)
+        .map(|value| value.to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[test]
+fn test_signed_comparison() {
+    assert_eq!(eval_evm("(s< -1 0)").unwrap(), "#t");
+    assert_eq!(eval_evm("(s< 0 -1)").unwrap(), "#f");
+    assert_eq!(eval_evm("(s< -5 -3)").unwrap(), "#t");
+}
+
+#[test]
+fn test_signed_division() {
+    assert_eq!(eval_evm("(s/ -7 2)").unwrap(), "-3");
+    assert_eq!(eval_evm("(s/ 7 -2)").unwrap(), "-3");
+    assert_eq!(eval_evm("(s/ -7 -2)").unwrap(), "3");
+    assert_eq!(eval_evm("(s/ 5 0)").unwrap(), "0");
+}
+
+#[test]
+fn test_signed_modulo() {
+    // The result takes the sign of the dividend
+    assert_eq!(eval_evm("(smod -7 3)").unwrap(), "-1");
+    assert_eq!(eval_evm("(smod 7 -3)").unwrap(), "1");
+    assert_eq!(eval_evm("(smod 7 0)").unwrap(), "0");
+}
+
+#[test]
+fn test_sign_extend() {
+    assert_eq!(eval_evm("(sign-extend 0 #xff)").unwrap(), "-1");
+    assert_eq!(eval_evm("(sign-extend 0 #x7f)").unwrap(), "127");
+    assert_eq!(eval_evm("(sign-extend 1 #x8000)").unwrap(), "-32768");
+    assert_eq!(eval_evm("(sign-extend 31 #xff)").unwrap(), "255");
+}
+
+#[test]
+fn test_word_overflow() {
+    // -2^63 / -1 no longer fits an i64
+    let min = Word::from_i64(i64::MIN);
+    let quotient = min.signed_div(Word::from_i64(-1));
+    assert_eq!(quotient.to_i64(), None);
+    assert_eq!(quotient.to_be_bytes()[24], 0x80);
+
+    // The most negative 256-bit value divided by -1 wraps to itself
+    let mut bytes = [0u8; 32];
+    bytes[0] = 0x80;
+    let most_negative = Word::from_be_bytes(&bytes).unwrap();
+    assert_eq!(most_negative.signed_div(Word::from_i64(-1)), most_negative);
+}
+
+#[test]
+fn test_big_integer_words() {
+    // Big integers convert as two's complement and come back signed
+    assert_eq!(
+        eval_evm("(s/ (- (expt 2 100)) 2)").unwrap(),
+        "-633825300114114700748351602688"
+    );
+    assert_eq!(eval_evm("(s< (- (expt 2 100)) -1)").unwrap(), "#t");
+    assert_eq!(
+        eval_evm("(= (sign-extend 31 (- (expt 2 255))) (- (expt 2 255)))").unwrap(),
+        "#t"
+    );
+    assert!(eval_evm("(s/ (- -1 (expt 2 255)) 1)").is_err());
+
+    let max = Word::from_bigint(&((BigInt::from(1) << 256) - 1)).unwrap();
+    assert_eq!(max, Word::from_i64(-1));
+    assert_eq!(max.to_signed(), BigInt::from(-1));
+    assert_eq!(max.to_unsigned(), (BigInt::from(1) << 256) - 1);
+    assert_eq!(Word::from_bigint(&(BigInt::from(1) << 256)), None);
+
+    // Words at or above 2^255 are unsigned outside the signed primitives
+    let two_255 = "57896044618658097711785492504343953926634992332820282019728792003956564819968";
+    assert_eq!(eval_evm("(arithmetic-shift 1 255)").unwrap(), two_255);
+    assert_eq!(eval_evm("(bitwise-or (expt 2 255) 0)").unwrap(), two_255);
+    assert_eq!(
+        eval_evm("(= (arithmetic-shift (expt 2 255) -1) (expt 2 254))").unwrap(),
+        "#t"
+    );
+    assert_eq!(
+        eval_evm("(arithmetic-shift -1 255)").unwrap(),
+        format!("-{}", two_255)
+    );
+    assert_eq!(
+        eval_evm("(s/ (expt 2 255) 1)").unwrap(),
+        format!("-{}", two_255)
+    );
+
+    let functions = parse_abi(
+        r#"[{"type": "function", "name": "u", "inputs": [], "outputs": [{"type": "uint256"}]},
+            {"type": "function", "name": "i", "inputs": [], "outputs": [{"type": "int256"}]}]"#,
+    )
+    .unwrap();
+    let mut data = [0u8; 32];
+    data[0] = 0x80;
+    assert_eq!(
+        functions[0].decode_output(&data).unwrap().to_string(),
+        two_255
+    );
+    assert_eq!(
+        functions[1].decode_output(&data).unwrap().to_string(),
+        format!("-{}", two_255)
+    );
+}
+
+const ERC20_ABI: &str = r#"[
+  {"type": "function", "name": "balanceOf", "stateMutability": "view",
+   "inputs": [{"name": "owner", "type": "address"}],
+   "outputs": [{"name": "", "type": "uint256"}]},
+  {"type": "function", "name": "transfer", "stateMutability": "nonpayable",
+   "inputs": [{"name": "to", "type": "address"}, {"name": "amount", "type": "uint"}],
+   "outputs": [{"name": "", "type": "bool"}]},
+  {"type": "event", "name": "Transfer", "inputs": []}
+]"#;
+
+#[test]
+fn test_parse_abi() {
+    let functions = parse_abi(ERC20_ABI).unwrap();
+    assert_eq!(functions.len(), 2);
+
+    let transfer = &functions[1];
+    assert_eq!(transfer.signature(), "transfer(address,uint256)");
+    assert_eq!(transfer.selector(), [0xa9, 0x05, 0x9c, 0xbb]);
+    assert!(functions[0].is_view());
+    assert!(!transfer.is_view());
+
+    // Build artifacts keep the ABI under an `abi` key
+    let artifact = format!(r#"{{"contractName": "IERC20", "abi": {}}}"#, ERC20_ABI);
+    assert_eq!(parse_abi(&artifact).unwrap(), functions);
+
+    assert!(
+        parse_abi(r#"[{"type": "function", "name": "f", "inputs": [{"type": "string"}]}]"#)
+            .unwrap_err()
+            .contains("Unsupported ABI type: string")
+    );
+}
+
+#[test]
+fn test_define_interface() {
+    let path = std::env::temp_dir().join(format!("lamina-ierc20-{}.json", std::process::id()));
+    std::fs::write(&path, ERC20_ABI).unwrap();
+
+    // Record the calldata and answer every call with the word 42
+    let env = setup_initial_env();
+    let calls = Rc::new(RefCell::new(Vec::new()));
+    let recorded = calls.clone();
+    env.borrow_mut().bindings.insert(
+        ETH_CALL.to_string(),
+        Value::Procedure(Rc::new(move |args: Vec<Value>| {
+            if let Value::Bytevector(data) = &args[1] {
+                recorded.borrow_mut().push(data.borrow().clone());
+            }
+            let mut word = vec![0u8; 32];
+            word[31] = 42;
+            Ok(Value::Bytevector(Rc::new(RefCell::new(word))))
+        })),
+    );
+
+    let code = format!(
+        r#"(begin
+             (define-interface IERC20 "{}")
+             (IERC20/balanceOf 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed
+                               0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359))"#,
+        path.display()
+    );
+    let tokens = lexer::lex(&code).unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let result = evaluator::eval_with_env(expr, env.clone());
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(result.unwrap().to_string(), "42");
+
+    let calls = calls.borrow();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].len(), 36);
+    assert_eq!(calls[0][..4], [0x70, 0xa0, 0x82, 0x31]);
+    assert_eq!(calls[0][16], 0xfb);
+
+    // Arguments are checked against the ABI before anything is sent
+    let tokens =
+        lexer::lex("(IERC20/transfer 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed 1)").unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let err = evaluator::eval_with_env(expr, env).unwrap_err().to_string();
+    assert!(err.contains("transfer expects 2 arguments, got 1"));
+}
+
+#[test]
+fn test_storage_words() {
+    // Slots and values span the full uint256 range
+    assert_eq!(
+        eval_evm("(begin (storage-store (expt 2 100) 1) (storage-load (expt 2 100)))").unwrap(),
+        "1"
+    );
+    let max = "(- (expt 2 256) 1)";
+    assert_eq!(
+        eval_evm(&format!(
+            "(begin (storage-store {max} {max}) (= (storage-load {max}) {max}))"
+        ))
+        .unwrap(),
+        "#t"
+    );
+    assert_eq!(eval_evm("(storage-load (expt 2 100))").unwrap(), "0");
+
+    for (slot, value) in [
+        ("-1", "1"),
+        ("0", "-5"),
+        ("0", "(expt 2 256)"),
+        ("0", "1/2"),
+    ] {
+        let code = format!("(storage-store {} {})", slot, value);
+        assert!(eval_evm(&code).is_err(), "{} was accepted", code);
+    }
+    assert!(eval_evm("(storage-load (expt 2 256))").is_err());
+}
+
+#[test]
+fn test_storage_arrays() {
+    let code = "(begin
+                  (storage-array-push! 3 11)
+                  (storage-array-push! 3 22)
+                  (storage-array-set! 3 1 99)
+                  (list (storage-array-length 3) (storage-load 3)
+                        (storage-array-ref 3 0) (storage-array-ref 3 1)))";
+    assert_eq!(eval_evm(code).unwrap(), "(2 2 11 99)");
+
+    assert!(eval_evm("(storage-array-ref 3 0)")
+        .unwrap_err()
+        .contains("index out of bounds"));
+    assert!(eval_evm("(begin (storage-array-push! 3 1) (storage-array-set! 3 1 0))").is_err());
+}
+
+#[test]
+fn test_storage_strings() {
+    assert_eq!(eval_evm("(storage-string-load 5)").unwrap(), "\"\"");
+    assert_eq!(
+        eval_evm("(begin (storage-string-store! 5 \"Lamina\") (storage-string-load 5))").unwrap(),
+        "\"Lamina\""
+    );
+    assert_eq!(
+        eval_evm("(storage-string-store! 5 \"Lamina\")").unwrap(),
+        "6"
+    );
+    assert!(eval_evm("(storage-string-store! 5 42)").is_err());
+    assert!(eval_evm("(begin (storage-store 5 42) (storage-string-load 5))").is_err());
+}
+
+#[test]
+fn test_deploy_create2() {
+    // The simulated contract lives at the zero address
+    assert_eq!(
+        eval_evm("(deploy-create2 7 (bytevector 1 2 3))").unwrap(),
+        eval_evm(
+            "(create2-address 0x0000000000000000000000000000000000000000 7 (init-code-hash (bytevector 1 2 3)))"
+        )
+        .unwrap()
+    );
+    assert_eq!(
+        eval_evm("(begin (deploy-create2 1 (bytevector 0)) (address? (deploy-create2 2 (bytevector 0))))")
+            .unwrap(),
+        "#t"
+    );
+
+    // The same salt and init code can only be deployed once
+    let err =
+        eval_evm("(begin (deploy-create2 1 (bytevector 0)) (deploy-create2 1 (bytevector 0)))")
+            .unwrap_err();
+    assert!(err.contains("Reverted: deploy-create2"));
+}
+
+#[test]
+fn test_tx_origin() {
+    let env = setup_initial_env();
+    let state = Rc::new(RefCell::new(EvmState::new()));
+    register_simulated_evm(env.clone(), state.clone());
+    state.borrow_mut().origin = Value::Address([0x11; 20]);
+
+    let tokens = lexer::lex("(tx-origin)").unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let origin = evaluator::eval_with_env(expr, env).unwrap();
+    assert_eq!(origin.to_string(), Value::Address([0x11; 20]).to_string());
+}
+
+#[test]
+fn test_transient_storage() {
+    // Transient slots are read back within a transaction, and store returns the value
+    assert_eq!(
+        eval_evm("(begin (transient-store! 3 9) (+ (transient-load 3) (transient-load 4)))")
+            .unwrap(),
+        "9"
+    );
+    assert_eq!(eval_evm("(transient-store! 3 9)").unwrap(), "9");
+
+    // Ending the transaction clears them, leaving storage alone
+    let env = setup_initial_env();
+    let state = Rc::new(RefCell::new(EvmState::traced()));
+    register_simulated_evm(env.clone(), state.clone());
+    let eval = |code: &str| {
+        let tokens = lexer::lex(code).unwrap();
+        let expr = parser::parse(&tokens).unwrap();
+        evaluator::eval_with_env(expr, env.clone()).map(|value| value.to_string())
+    };
+    eval("(begin (transient-store! 0 1) (storage-store 0 2))").unwrap();
+    state.borrow_mut().end_transaction();
+    assert_eq!(eval("(transient-load 0)").unwrap(), "0");
+    assert_eq!(eval("(storage-load 0)").unwrap(), "2");
+    let steps = state.borrow_mut().trace.take().unwrap().steps;
+    let ops: Vec<(&str, u64)> = steps.iter().map(|step| (step.op, step.gas)).collect();
+    assert_eq!(ops[0], ("TSTORE", 100));
+    assert_eq!(ops[2], ("TLOAD", 100));
+
+    // A trace priced before Cancun has no transient storage to charge for
+    let env = setup_initial_env();
+    let shanghai = GasSchedule::for_hardfork(Hardfork::Shanghai);
+    let state = Rc::new(RefCell::new(EvmState::traced_with(shanghai)));
+    register_simulated_evm(env.clone(), state);
+    let tokens = lexer::lex("(transient-load 0)").unwrap();
+    let expr = parser::parse(&tokens).unwrap();
+    let err = evaluator::eval_with_env(expr, env).unwrap_err();
+    assert!(err.to_string().contains("needs the cancun hardfork"));
+}
+
+// Evaluate code with (lamina evm safemath) imported
+fn eval_safemath(code: &str) -> Result<String, String> {
+    eval_evm(&format!("(begin (import (lamina evm safemath)) {})", code))
+}
+
+#[test]
+fn test_safemath() {
+    assert_eq!(eval_safemath("(add-checked 2 3)").unwrap(), "5");
+    assert_eq!(eval_safemath("(sub-checked 5 3)").unwrap(), "2");
+    assert_eq!(eval_safemath("(mul-checked 6 7)").unwrap(), "42");
+    assert_eq!(eval_safemath("(div-floor 7 2)").unwrap(), "3");
+
+    // Overflow, underflow and division by zero revert instead of wrapping
+    let max = "(bitwise-not 0)";
+    let reverts = [
+        format!("(add-checked {} 1)", max),
+        "(sub-checked 3 5)".to_string(),
+        "(mul-checked (arithmetic-shift 1 128) (arithmetic-shift 1 128))".to_string(),
+        "(div-floor 7 0)".to_string(),
+    ];
+    for code in &reverts {
+        let err = eval_safemath(code).unwrap_err();
+        assert!(err.contains("Reverted"), "{}: {}", code, err);
+    }
+    assert_eq!(
+        eval_safemath("(mul-checked (arithmetic-shift 1 127) 2)").unwrap(),
+        eval_evm("(arithmetic-shift 1 128)").unwrap()
+    );
+
+    // addmod and mulmod keep the full intermediate result, as on the EVM
+    assert_eq!(eval_safemath("(mulmod 10 10 7)").unwrap(), "2");
+    assert_eq!(
+        eval_safemath(&format!("(addmod {} 2 7)", max)).unwrap(),
+        "3"
+    );
+    assert_eq!(
+        eval_safemath(&format!("(mulmod {} {} 12)", max, max)).unwrap(),
+        "9"
+    );
+    assert_eq!(eval_safemath("(addmod 5 5 0)").unwrap(), "0");
+
+    // Results are unsigned up to 2^256 - 1
+    let top = "(- (expt 2 256) 1)";
+    assert_eq!(
+        eval_safemath("(> (add-checked (expt 2 255) 1) 0)").unwrap(),
+        "#t"
+    );
+    assert_eq!(
+        eval_safemath("(= (mul-checked (expt 2 254) 2) (expt 2 255))").unwrap(),
+        "#t"
+    );
+    assert_eq!(
+        eval_safemath(&format!("(= (add-checked (- {} 1) 1) {})", top, top)).unwrap(),
+        "#t"
+    );
+    assert_eq!(
+        eval_safemath(&format!("(= (div-floor {} 1) {})", top, top)).unwrap(),
+        "#t"
+    );
+    assert_eq!(
+        eval_safemath(&format!("(= (mulmod {} 1 {}) 0)", top, top)).unwrap(),
+        "#t"
+    );
+    assert!(eval_safemath("(addmod (expt 2 255) 0 (expt 2 256))").is_err());
+    assert_eq!(
+        eval_safemath(&format!("(= (sub-checked {} 1) (- (expt 2 256) 2))", top)).unwrap(),
+        "#t"
+    );
+    assert_eq!(
+        eval_safemath("(sub-checked (expt 2 255) (- (expt 2 255) 1))").unwrap(),
+        "1"
+    );
+    assert_eq!(
+        eval_safemath("(= (addmod (expt 2 255) 1 (- (expt 2 256) 1)) (+ (expt 2 255) 1))").unwrap(),
+        "#t"
+    );
+
+    assert!(eval_evm("(add-checked 2 3)").is_err());
+    assert!(eval_evm("(import (lamina evm nothing))").is_err());
+}
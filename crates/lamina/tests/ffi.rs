@@ -34,7 +34,9 @@ fn test_registering_rust_function() {
     }
 }
 
+// Covers the deprecated thread-wide module registry
 #[test]
+#[allow(deprecated)]
 fn test_rust_module() {
     // Create a Lamina environment
     let env = setup_initial_env();
@@ -129,6 +131,118 @@ fn test_embedding_api() {
     }
 }
 
+#[test]
+fn test_eval_all() {
+    let interpreter = embed::Interpreter::builder().build();
+    let source =
+        "(define port 8080)\n(display \"listening\") port\n  (undefined-name)\n'(1 2)\n#(1\n";
+    let results = interpreter.eval_all(source);
+    assert_eq!(results.len(), 6);
+
+    // Each form has its span, value and output
+    assert_eq!(&source[results[0].span.clone()], "(define port 8080)");
+    assert_eq!(&source[results[1].span.clone()], "(display \"listening\")");
+    assert_eq!(results[1].output, "listening");
+    assert_eq!((results[2].line, results[2].column), (2, 23));
+    assert_eq!(results[2].result.as_ref().unwrap().to_string(), "8080");
+    assert_eq!(results[2].output, "");
+
+    // An error doesn't stop the forms after it
+    assert_eq!((results[3].line, results[3].column), (3, 3));
+    assert!(results[3].result.is_err());
+    assert_eq!(&source[results[4].span.clone()], "'(1 2)");
+    assert_eq!(results[4].result.as_ref().unwrap().to_string(), "(1 2)");
+
+    // An unclosed form takes the rest of the source
+    assert_eq!(&source[results[5].span.clone()], "#(1");
+    assert!(matches!(
+        results[5].result,
+        Err(lamina::error::Error::Parser(_))
+    ));
+}
+
+#[test]
+fn test_strict_eval_expression() {
+    use lamina::error::Error;
+
+    let interpreter = embed::Interpreter::builder().strict().build();
+    interpreter.eval("(define price 12)").unwrap();
+    let eval = |code: &str| interpreter.eval_expression(code);
+
+    // Formulas may bind and assign locals
+    assert_eq!(
+        eval("(let ((tax 2)) (begin (set! tax 3) (* price tax)))")
+            .unwrap()
+            .to_string(),
+        "36"
+    );
+    assert_eq!(
+        eval("((lambda (x) (begin (define y 2) (set! y x) y)) 5)")
+            .unwrap()
+            .to_string(),
+        "5"
+    );
+    assert_eq!(
+        eval("((lambda args (set! args 0) args))")
+            .unwrap()
+            .to_string(),
+        "0"
+    );
+    for code in [
+        "(letrec* ((x 1)) (set! x 2))",
+        "(let-values (((x y) (values 1 2))) (set! y x))",
+        "(let*-values (((x) (values 1)) ((y) (values x))) (set! y x))",
+        "(letrec ((n 0) (bump (lambda () (set! n (+ n 1))))) (bump))",
+        "(let* ((x 1) (y (set! x 2))) x)",
+    ] {
+        assert!(
+            !matches!(eval(code), Err(Error::Policy(_))),
+            "{} was rejected",
+            code
+        );
+    }
+
+    // but not change the global environment
+    for code in [
+        "(define total 1)",
+        "(begin (define total 1) total)",
+        "(if #t (define total 1) 0)",
+        "(import (lamina functional))",
+        "(set! price 0)",
+        "(let ((x 1)) (set! price x))",
+        "(lambda () (set! price 0))",
+    ] {
+        assert!(
+            matches!(eval(code), Err(Error::Policy(_))),
+            "{} was allowed",
+            code
+        );
+    }
+    assert_eq!(
+        eval("(set! price 0)").unwrap_err().to_string(),
+        "Policy violation: set! of global variable price is not allowed in an expression"
+    );
+    assert_eq!(interpreter.get("price").unwrap().to_string(), "12");
+    assert!(interpreter.get("total").is_none());
+
+    // Macros the host defines are checked by what they expand to
+    interpreter
+        .eval("(define-syntax setg (syntax-rules () ((_ name value) (set! name value))))")
+        .unwrap();
+    interpreter
+        .eval("(define-syntax twice (syntax-rules () ((_ x) (* 2 x))))")
+        .unwrap();
+    assert!(matches!(eval("(setg price 0)"), Err(Error::Policy(_))));
+    assert_eq!(eval("(let ((x 1)) (setg x 2) x)").unwrap().to_string(), "2");
+    assert_eq!(eval("(twice price)").unwrap().to_string(), "24");
+    assert_eq!(interpreter.get("price").unwrap().to_string(), "12");
+
+    // Without strict mode, expressions may define
+    let interpreter = embed::Interpreter::builder().build();
+    interpreter.eval_expression("(define total 1)").unwrap();
+    assert!(interpreter.get("total").is_some());
+}
+
 #[test]
 fn test_function_error_handling() {
     // Create a Lamina interpreter
@@ -197,6 +311,48 @@ fn test_type_conversions() {
     assert_eq!(original_string, round_trip_string);
 }
 
+#[test]
+fn test_list_conversions() {
+    let list = Value::list(vec![Value::from(1), Value::from(2), Value::from(3)]);
+    assert_eq!(list.to_string(), "(1 2 3)");
+    assert_eq!(Value::list(Vec::new()).to_string(), "()");
+
+    let alist = Value::alist(&[
+        ("name", Value::String("ada".into())),
+        ("age", Value::from(36)),
+    ]);
+    assert_eq!(alist.to_string(), "((name . \"ada\") (age . 36))");
+
+    // Lists collect from and iterate over their elements
+    let squares: Value = (1..=3).map(|n| Value::from(n * n)).collect();
+    assert_eq!(squares.to_string(), "(1 4 9)");
+    let mut total = 0;
+    for item in &squares {
+        total += ffi::value_to_i64(item).unwrap();
+    }
+    assert_eq!(total, 14);
+    assert_eq!(
+        Value::cons(Value::from(1), Value::from(2)).iter().count(),
+        1
+    );
+
+    // And convert to vectors of Rust values, from lists or Lamina vectors
+    let interpreter = embed::Interpreter::new();
+    let numbers: Vec<i64> = interpreter.eval("'(1 2 3)").unwrap().try_into().unwrap();
+    assert_eq!(numbers, vec![1, 2, 3]);
+    let names = Vec::<String>::try_from(interpreter.eval("#(\"a\" \"b\")").unwrap()).unwrap();
+    assert_eq!(names, vec!["a", "b"]);
+    let nested: Vec<Vec<bool>> = interpreter
+        .eval("'((#t) () (#f #t))")
+        .unwrap()
+        .try_into()
+        .unwrap();
+    assert_eq!(nested, vec![vec![true], vec![], vec![false, true]]);
+    assert!(Vec::<i64>::try_from(interpreter.eval("'(1 \"two\")").unwrap()).is_err());
+    assert!(Vec::<i64>::try_from(Value::cons(Value::from(1), Value::from(2))).is_err());
+    assert!(Vec::<i64>::try_from(Value::from(1)).is_err());
+}
+
 #[test]
 fn test_direct_rustfn_creation() {
     // This test directly constructs a RustFn variant to remove the "dead code" warning
@@ -1,5 +1,8 @@
 use lamina::embed;
 use lamina::ffi;
+use lamina::lexer;
+use lamina::reader::infix::Infix;
+use lamina::reader::ReaderExtension;
 use lamina::value::{NumberKind, Value};
 
 #[test]
@@ -29,7 +32,9 @@ fn test_bidirectional_ffi() {
     }
 }
 
+// Covers the deprecated thread-wide module registry
 #[test]
+#[allow(deprecated)]
 fn test_rust_module() {
     // Create a Lamina interpreter
     let interpreter = embed::init();
@@ -60,3 +65,384 @@ fn test_rust_module() {
         panic!("Expected Real number result, got: {:?}", result);
     }
 }
+
+#[test]
+fn test_interpreters_keep_registrations_separate() {
+    let module = ffi::rustlib::build_module("greeting", |module| {
+        module.add_function("hello", |_args| Ok(ffi::string_to_value("hi".into())));
+    });
+
+    let first = embed::Interpreter::builder()
+        .with_function("answer", |_args| Ok(ffi::i64_to_value(42)))
+        .with_module(module)
+        .build();
+    let second = embed::Interpreter::builder().build();
+
+    assert_eq!(first.eval("(answer)").unwrap().to_string(), "42");
+    assert!(second.eval("(answer)").is_err());
+
+    first.import_module("greeting").unwrap();
+    assert_eq!(
+        first.eval("(greeting/hello)").unwrap().to_string(),
+        "\"hi\""
+    );
+    assert!(second.import_module("greeting").is_err());
+
+    // Libraries defined in one interpreter are not visible from the other
+    first
+        .eval("(define-library (shared util) (export id) (begin (define id (lambda (x) x))))")
+        .unwrap();
+    let name = vec!["shared".to_string(), "util".to_string()];
+    assert!(first.library(&name).is_some());
+    assert!(second.library(&name).is_none());
+}
+
+#[test]
+fn test_infix_reader_extension() {
+    let interpreter = embed::Interpreter::builder()
+        .with_reader_extension(Infix)
+        .build();
+    interpreter.eval("(define x 4)").unwrap();
+
+    assert_eq!(
+        interpreter.eval("#i{ 2 + 3 * x }").unwrap().to_string(),
+        "14"
+    );
+    assert_eq!(
+        interpreter.eval("#i{ (2 + 3) * x }").unwrap().to_string(),
+        "20"
+    );
+    assert_eq!(
+        interpreter.eval("#i{ 10 - 4 - 3 }").unwrap().to_string(),
+        "3"
+    );
+    assert_eq!(
+        interpreter.eval("#i{ - x + 1 < 0 }").unwrap().to_string(),
+        "#t"
+    );
+    assert_eq!(
+        interpreter
+            .eval("(list #i{ x * x } 1)")
+            .unwrap()
+            .to_string(),
+        "(16 1)"
+    );
+
+    let infix = Infix;
+    let tokens = lexer::lex("1 + 2 * x").unwrap();
+    assert_eq!(
+        infix.expand(&tokens).unwrap(),
+        lexer::lex("(+ 1 (* 2 x))").unwrap()
+    );
+    assert!(interpreter.eval("#i{ 1 + }").is_err());
+
+    // Blocks are only read by interpreters that registered the extension
+    let plain = embed::Interpreter::builder().build();
+    let err = plain.eval("#i{ 1 + 2 }").unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("No reader extension registered for #i{"));
+}
+
+#[test]
+fn test_eval_template() {
+    let interpreter = embed::Interpreter::builder()
+        .with_reader_extension(Infix)
+        .build();
+    interpreter.eval("(define price 8)").unwrap();
+    let eval = |template: &str, values: &[(&str, Value)]| {
+        interpreter
+            .eval_template(template, values)
+            .map(|value| value.to_string())
+    };
+
+    let rule = "(> price {threshold})";
+    assert_eq!(
+        eval(rule, &[("threshold", Value::from(5.0))]).unwrap(),
+        "#t"
+    );
+    assert_eq!(
+        eval(rule, &[("threshold", Value::from(10.0))]).unwrap(),
+        "#f"
+    );
+
+    // The cached form looks names up when it runs
+    interpreter.set("price", Value::from(12.0)).unwrap();
+    assert_eq!(
+        eval(rule, &[("threshold", Value::from(10.0))]).unwrap(),
+        "#t"
+    );
+
+    // A hole used twice takes one value; braces in strings are left alone
+    assert_eq!(
+        eval(
+            "(list {x} \"{x}\" {y} {x})",
+            &[("y", Value::from(2)), ("x", Value::from(1))]
+        )
+        .unwrap(),
+        "(1 \"{x}\" 2 1)"
+    );
+
+    // Holes work inside reader extension blocks
+    assert_eq!(
+        eval("#i{ price * {rate} }", &[("rate", Value::from(2))]).unwrap(),
+        "24.0"
+    );
+
+    let err = eval(rule, &[]).unwrap_err();
+    assert!(err.to_string().contains("No value for {threshold}"));
+    assert!(eval("(> price", &[]).is_err());
+}
+
+#[test]
+fn test_load_rules() {
+    let path = std::env::temp_dir().join(format!("lamina-rules-{}.lmn", std::process::id()));
+    std::fs::write(
+        &path,
+        "(define-rule flag-large #:priority 1 #:when (> amount limit) #:then (set! flagged #t))
+         (define-rule approve #:when (not flagged) #:then (set! approved #t))",
+    )
+    .unwrap();
+
+    let interpreter = embed::Interpreter::builder().build();
+    interpreter.define("amount", Value::from(500.0));
+    interpreter.define("limit", Value::from(100.0));
+    interpreter.define("flagged", Value::Boolean(false));
+    interpreter.define("approved", Value::Boolean(false));
+    interpreter.load_rules(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(interpreter.run_rules().unwrap(), ["flag-large"]);
+    assert_eq!(interpreter.fired_rules().unwrap(), ["flag-large"]);
+    assert_eq!(interpreter.get("approved").unwrap().to_string(), "#f");
+
+    interpreter.set("flagged", Value::Boolean(false)).unwrap();
+    interpreter.set("amount", Value::from(50.0)).unwrap();
+    assert_eq!(interpreter.run_rules().unwrap(), ["approve"]);
+    assert!(interpreter.load_rules("missing.lmn").is_err());
+}
+
+#[test]
+fn test_supplied_input() {
+    let interpreter = embed::Interpreter::builder()
+        .with_input(std::io::Cursor::new("alice\r\nbob and carol\n"))
+        .build();
+    let eval = |code: &str| interpreter.eval(code).unwrap().to_string();
+
+    assert_eq!(eval("(input-port? (current-input-port))"), "#t");
+    assert_eq!(eval("(char-ready?)"), "#t");
+    assert_eq!(eval("(read-line)"), "\"alice\"");
+    assert_eq!(eval("(read-string 3)"), "\"bob\"");
+    assert_eq!(eval("(read-line (current-input-port))"), "\" and carol\"");
+    assert_eq!(eval("(eof-object? (read-line))"), "#t");
+    assert_eq!(eval("(eof-object? (read-string 1))"), "#t");
+
+    // read-string stops early at the end of input
+    let short = embed::Interpreter::builder()
+        .with_input(std::io::Cursor::new("ab"))
+        .build();
+    assert_eq!(short.eval("(read-string 5)").unwrap().to_string(), "\"ab\"");
+}
+
+#[test]
+fn test_without_assertions() {
+    let interpreter = embed::Interpreter::builder().without_assertions().build();
+    let eval = |code: &str| interpreter.eval(code).unwrap().to_string();
+
+    assert_eq!(eval("(begin (assert #f \"skipped\") 'ran)"), "ran");
+    eval("(define-with-contract (halve x) #:requires (> x 0) (/ x 2))");
+    assert_eq!(eval("(halve -4)"), "-2");
+}
+
+#[test]
+fn test_with_edition() {
+    let interpreter = embed::Interpreter::builder()
+        .with_edition(embed::Edition::E2025)
+        .build();
+    assert_eq!(
+        interpreter.eval("((lambda () 1 2))").unwrap().to_string(),
+        "2"
+    );
+}
+
+#[test]
+fn test_snapshot_backtracking() {
+    let interpreter = embed::Interpreter::builder().build();
+    interpreter
+        .eval(
+            "(begin
+               (define total 0)
+               (define picks (let ((n 0)) (lambda (step) (begin (set! n (+ n step)) n)))))",
+        )
+        .unwrap();
+
+    // Depth-first search for a subset summing to the target, rolling the
+    // interpreter back whenever a choice overshoots or runs out of items
+    fn search(
+        interpreter: &embed::Interpreter,
+        items: &[i64],
+        target: f64,
+        chosen: &mut Vec<i64>,
+    ) -> bool {
+        let total = ffi::value_to_f64(&interpreter.get("total").unwrap()).unwrap();
+        if total == target {
+            return true;
+        }
+        let Some((&item, rest)) = items.split_first() else {
+            return false;
+        };
+        if total < target {
+            let before = interpreter.snapshot();
+            interpreter
+                .eval(&format!(
+                    "(begin (set! total (+ total {})) (picks 1))",
+                    item
+                ))
+                .unwrap();
+            chosen.push(item);
+            if search(interpreter, rest, target, chosen) {
+                return true;
+            }
+            chosen.pop();
+            interpreter.restore(&before).unwrap();
+        }
+        search(interpreter, rest, target, chosen)
+    }
+
+    let mut chosen = Vec::new();
+    assert!(search(&interpreter, &[5, 8, 3, 4], 12.0, &mut chosen));
+    assert_eq!(chosen, vec![5, 3, 4]);
+    // The counter captured by the closure was rolled back with each choice
+    assert_eq!(interpreter.eval("(picks 0)").unwrap().to_string(), "3");
+
+    // Definitions made after a snapshot go away, and it can be restored again
+    let snapshot = interpreter.snapshot();
+    interpreter.eval("(define extra 1)").unwrap();
+    interpreter.restore(&snapshot).unwrap();
+    assert!(interpreter.get("extra").is_none());
+    interpreter.eval("(set! total 0)").unwrap();
+    interpreter.restore(&snapshot).unwrap();
+    assert_eq!(interpreter.eval("total").unwrap().to_string(), "12");
+
+    let other = embed::Interpreter::builder().build();
+    assert!(other.restore(&snapshot).is_err());
+}
+
+#[test]
+fn test_handles_and_weak_tables() {
+    use lamina::ffi::handle::{value_to_opaque, Opaque};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    // A resource whose finalizer counts how often it ran
+    let finalized = Rc::new(Cell::new(0));
+    let counter = finalized.clone();
+    let next_id = Cell::new(0);
+    let interpreter = embed::Interpreter::builder()
+        .with_function("open-resource", move |_args| {
+            let counter = counter.clone();
+            next_id.set(next_id.get() + 1);
+            let handle = Opaque::with_finalizer("resource", next_id.get(), move |_id: i64| {
+                counter.set(counter.get() + 1)
+            });
+            Ok(Value::Opaque(Rc::new(handle)))
+        })
+        .with_function("resource-id", |args| {
+            let handle = value_to_opaque(&args[0], "resource")?;
+            handle.with(|id: &mut i64| ffi::i64_to_value(*id))
+        })
+        .build();
+    let eval = |code: &str| interpreter.eval(code).map(|value| value.to_string());
+
+    eval("(define r (open-resource))").unwrap();
+    assert_eq!(eval("(handle? r)").unwrap(), "#t");
+    assert_eq!(eval("(resource-id r)").unwrap(), "1");
+    assert_eq!(eval("r").unwrap(), "#<resource>");
+
+    // Closing finalizes once, and the object is gone afterwards
+    eval("(close! r)").unwrap();
+    eval("(close! r)").unwrap();
+    assert_eq!(finalized.get(), 1);
+    assert_eq!(eval("(handle-closed? r)").unwrap(), "#t");
+    assert_eq!(eval("r").unwrap(), "#<resource closed>");
+    assert!(eval("(resource-id r)").is_err());
+
+    // call-with-handle closes the handle even when the procedure fails
+    assert_eq!(
+        eval("(call-with-handle (open-resource) (lambda (h) (resource-id h)))").unwrap(),
+        "2"
+    );
+    assert!(eval("(call-with-handle (open-resource) (lambda (h) (car h)))").is_err());
+    assert_eq!(finalized.get(), 3);
+
+    // Dropping the last reference finalizes too, and a weak table does not
+    // count as a reference
+    eval("(define table (make-weak-table))").unwrap();
+    eval("(define db (open-resource))").unwrap();
+    eval("(weak-table-set! table \"db\" db)").unwrap();
+    assert_eq!(
+        eval("(resource-id (weak-table-ref table \"db\"))").unwrap(),
+        "4"
+    );
+    assert_eq!(eval("(weak-table-count table)").unwrap(), "1");
+    eval("(set! db #f)").unwrap();
+    assert_eq!(finalized.get(), 4);
+    assert_eq!(eval("(weak-table-ref table \"db\")").unwrap(), "#f");
+    assert_eq!(eval("(weak-table-ref table \"db\" 'gone)").unwrap(), "gone");
+    assert_eq!(eval("(weak-table-count table)").unwrap(), "0");
+}
+
+#[test]
+fn test_foreign_objects() {
+    use lamina::ffi::foreign::{foreign_to_value, get_foreign, MethodTable};
+
+    struct Matrix {
+        rows: usize,
+        cells: Vec<f64>,
+    }
+
+    let mut methods = MethodTable::<Matrix>::new();
+    methods.add_method("rows", |matrix, _args| {
+        Ok(ffi::i64_to_value(matrix.rows as i64))
+    });
+    methods.add_method("get", |matrix, args| {
+        let row = ffi::value_to_i64(&args[0])? as usize;
+        let column = ffi::value_to_i64(&args[1])? as usize;
+        Ok(ffi::f64_to_value(matrix.cells[row * matrix.rows + column]))
+    });
+
+    let interpreter = embed::Interpreter::builder()
+        .with_methods(methods)
+        .with_function("trace", |args| {
+            let matrix = get_foreign::<Matrix>(&args[0])?;
+            let trace = (0..matrix.rows)
+                .map(|i| matrix.cells[i * matrix.rows + i])
+                .sum();
+            Ok(ffi::f64_to_value(trace))
+        })
+        .build();
+    let matrix = foreign_to_value(Matrix {
+        rows: 2,
+        cells: vec![1.0, 2.0, 3.0, 4.0],
+    });
+    interpreter.define("m", matrix.clone());
+    let eval = |code: &str| interpreter.eval(code).map(|value| value.to_string());
+
+    // The object Lamina holds is the one Rust made, not a copy
+    assert_eq!(interpreter.get("m").unwrap(), matrix);
+    assert!(get_foreign::<Matrix>(&matrix).is_ok());
+    assert!(get_foreign::<String>(&matrix).is_err());
+
+    assert_eq!(eval("(foreign? m)").unwrap(), "#t");
+    assert!(eval("m").unwrap().starts_with("#<foreign "));
+    assert!(eval("m").unwrap().ends_with("Matrix>"));
+    assert_eq!(eval("(call-method m 'rows)").unwrap(), "2");
+    assert_eq!(eval("(call-method m 'get 1 0)").unwrap(), "3.0");
+    assert_eq!(eval("(trace m)").unwrap(), "5.0");
+    assert_eq!(eval("(has-method? m 'get)").unwrap(), "#t");
+    assert_eq!(eval("(has-method? m 'invert)").unwrap(), "#f");
+    assert!(eval("(call-method m 'invert)")
+        .unwrap_err()
+        .to_string()
+        .contains("No method invert"));
+}
@@ -0,0 +1,190 @@
+use lamina::lexer::{self, Token, TriviaKind};
+use lamina::parser;
+use lamina::value::Value;
+
+#[test]
+fn test_lossless_lexing() {
+    let source = "; header\n(define x 1) ; trailing\n";
+    let lexed = lexer::lex_lossless(source);
+
+    // Token and trivia spans together reproduce the source
+    let mut text = String::new();
+    for token in &lexed.tokens {
+        for trivia in &token.leading {
+            text.push_str(&source[trivia.span.clone()]);
+        }
+        text.push_str(&source[token.span.clone()]);
+    }
+    for trivia in &lexed.trailing {
+        text.push_str(&source[trivia.span.clone()]);
+    }
+    assert_eq!(text, source);
+
+    let tokens: Vec<Token> = lexed.tokens.iter().map(|t| t.token.clone()).collect();
+    assert_eq!(tokens, lexer::lex(source).unwrap());
+
+    let kinds: Vec<TriviaKind> = lexed.tokens[0].leading.iter().map(|t| t.kind).collect();
+    assert_eq!(kinds, vec![TriviaKind::Comment, TriviaKind::Whitespace]);
+    let kinds: Vec<TriviaKind> = lexed.trailing.iter().map(|t| t.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            TriviaKind::Whitespace,
+            TriviaKind::Comment,
+            TriviaKind::Whitespace
+        ]
+    );
+
+    // Invalid text becomes an error token and lexing carries on
+    let lexed = lexer::lex_lossless("(a \"unterminated");
+    assert!(lexed.tokens.iter().any(|t| t.token == Token::Error));
+    assert_eq!(lexed.tokens[0].token, Token::LeftParen);
+}
+
+fn read(source: &str) -> Result<Value, String> {
+    let tokens = lexer::lex(source).map_err(|e| e.to_string())?;
+    parser::parse(&tokens).map_err(|e| e.to_string())
+}
+
+#[test]
+fn test_vector_and_label_literals() {
+    assert_eq!(
+        lexer::lex("#(1) #u8(2) #12= #12#").unwrap(),
+        vec![
+            Token::VectorOpen,
+            Token::Number("1".to_string()),
+            Token::RightParen,
+            Token::BytevectorOpen,
+            Token::Number("2".to_string()),
+            Token::RightParen,
+            Token::DatumLabel(12),
+            Token::DatumReference(12),
+        ]
+    );
+
+    // Written back as read
+    for source in [
+        "#(1 \"two\" (3 #()))",
+        "#u8(0 16 255)",
+        "#0=#(1 #0#)",
+        "#0=(a #(b #0#))",
+        "(#0=#(#0#) #1=#(#1#))",
+    ] {
+        assert_eq!(read(source).unwrap().to_string(), source);
+    }
+
+    // A label shares its datum; only cycles are labelled when written
+    let shared = read("(#0=(x y) #0#)").unwrap();
+    assert_eq!(shared.to_string(), "((x y) (x y))");
+    let Value::Pair(outer) = &shared else {
+        panic!("expected a list")
+    };
+    let (Value::Pair(first), Value::Pair(rest)) = (&outer.0, &outer.1) else {
+        panic!("expected two elements")
+    };
+    let Value::Pair(second) = &rest.0 else {
+        panic!("expected a list")
+    };
+    assert!(std::rc::Rc::ptr_eq(first, second));
+
+    let Value::Vector(cycle) = read("#0=#(1 #0#)").unwrap() else {
+        panic!("expected a vector")
+    };
+    assert!(
+        matches!(&cycle.borrow()[1], Value::Vector(inner) if std::rc::Rc::ptr_eq(inner, &cycle))
+    );
+
+    assert!(read("#u8(1 256)").unwrap_err().contains("Invalid byte"));
+    assert!(read("#u8(a)").is_err());
+    assert!(read("#0#").unwrap_err().contains("Undefined datum label"));
+    // Pairs can't be changed once made, so can't contain themselves
+    assert!(read("#0=(a #0#)").is_err());
+    assert!(read("#0=#0#").is_err());
+}
+
+#[test]
+fn test_fold_case() {
+    use lamina::embed::Interpreter;
+    use std::io::Cursor;
+
+    // The directives switch folding for the rest of the input
+    assert_eq!(
+        read("(#!fold-case Hello \"Str\" #!no-fold-case World)")
+            .unwrap()
+            .to_string(),
+        "(hello \"Str\" World)"
+    );
+    let (tokens, fold_case) = lexer::lex_with_case("ABC #!fold-case", false).unwrap();
+    assert_eq!(tokens[0].0, Token::Symbol("ABC".into()));
+    assert!(fold_case);
+
+    // A case-insensitive interpreter folds its source and what `read` returns
+    let interpreter = Interpreter::builder()
+        .fold_case()
+        .with_input(Cursor::new("Apple #!no-fold-case Banana"))
+        .build();
+    interpreter.eval("(DEFINE Total 3)").unwrap();
+    assert_eq!(interpreter.eval("total").unwrap().to_string(), "3");
+    assert_eq!(
+        interpreter
+            .eval("(list (read) (read))")
+            .unwrap()
+            .to_string(),
+        "(apple Banana)"
+    );
+
+    // include-ci folds the file it reads, include only when the interpreter does
+    let dir = std::env::temp_dir().join(format!("lamina-fold-case-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("defs.lmn");
+    std::fs::write(&file, "(define Greeting 'Hi)").unwrap();
+    let path = file.display().to_string();
+    let interpreter = Interpreter::builder().build();
+    interpreter
+        .eval(&format!("(include-ci \"{}\")", path))
+        .unwrap();
+    assert_eq!(interpreter.eval("greeting").unwrap().to_string(), "hi");
+    interpreter
+        .eval(&format!("(include \"{}\")", path))
+        .unwrap();
+    assert_eq!(interpreter.eval("Greeting").unwrap().to_string(), "Hi");
+    assert!(interpreter.eval("(include \"no-such-file.lmn\")").is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_read_datum() {
+    // One datum at a time, leaving the rest of the input
+    let mut input = "(a (b)) 'c ; comment\n#(1 2) \"s\"  ";
+    let mut data = Vec::new();
+    while let Some(datum) = parser::read_datum(&mut input).unwrap() {
+        data.push(datum.to_string());
+    }
+    assert_eq!(data, ["(a (b))", "(quote c)", "#(1 2)", "\"s\""]);
+    assert_eq!(input, "");
+
+    // The text after a datum isn't lexed until it is read
+    let mut input = "(x) ]";
+    assert_eq!(
+        parser::read_datum(&mut input).unwrap().unwrap().to_string(),
+        "(x)"
+    );
+    assert_eq!(input, " ]");
+    assert!(parser::read_datum(&mut input).is_err());
+
+    // A datum the input ends inside of is incomplete
+    let mut input = "(a (b)";
+    let error = parser::read_datum(&mut input).unwrap_err();
+    assert!(parser::is_incomplete(&error));
+    assert_eq!(input, "(a (b)");
+
+    // Directives read with a datum carry over to the next
+    let mut input = "#!fold-case Abc Def #!no-fold-case Ghi";
+    let mut fold_case = false;
+    let mut data = Vec::new();
+    while let Some(datum) = parser::read_datum_with_case(&mut input, &mut fold_case).unwrap() {
+        data.push(datum.to_string());
+    }
+    assert_eq!(data, ["abc", "def", "Ghi"]);
+    assert!(!fold_case);
+}
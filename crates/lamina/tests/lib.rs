@@ -7,10 +7,24 @@ mod tests {
 }
 
 // Include all the test modules
+mod bench;
+mod cli;
+mod conditions;
+mod differential;
+mod dotenv;
+mod editions;
+mod evm;
 mod ffi;
 mod ffi_integration;
+mod lexer;
 mod libraries;
+mod number;
 mod primitives;
 mod procedures;
+mod project;
 mod r7rs_core;
+mod r7rs_suite;
+mod repl;
 mod special_forms;
+mod stdlib;
+mod testing;
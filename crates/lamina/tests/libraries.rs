@@ -1,3 +1,5 @@
+#![allow(deprecated)]
+
 use lamina::evaluator::library_manager::get_library;
 use lamina::execute;
 use lamina::value::Value;
@@ -204,10 +206,10 @@ fn test_standard_libraries() {
     }
 
     // Use the standard operations
-    assert_eq!(execute("(+ 1 2)").unwrap(), "3.0");
-    assert_eq!(execute("(- 5 2)").unwrap(), "3.0");
-    assert_eq!(execute("(* 2 3)").unwrap(), "6.0");
-    assert_eq!(execute("(/ 6 2)").unwrap(), "3.0");
+    assert_eq!(execute("(+ 1 2)").unwrap(), "3");
+    assert_eq!(execute("(- 5 2)").unwrap(), "3");
+    assert_eq!(execute("(* 2 3)").unwrap(), "6");
+    assert_eq!(execute("(/ 6 2)").unwrap(), "3");
     assert_eq!(execute("(< 2 3)").unwrap(), "#t");
     assert_eq!(execute("(> 4 1)").unwrap(), "#t");
     assert_eq!(execute("(= 2 2)").unwrap(), "#t");
@@ -284,3 +286,152 @@ fn test_library_importing_library() {
     // Use the derived function directly from the global environment
     assert_eq!(execute("(derived-func 2)").unwrap(), "16.0");
 }
+
+#[test]
+fn test_import_binds_exports() {
+    execute(
+        "(define-library (example tax) (export rate) (begin (define rate 7) (define hidden 1)))",
+    )
+    .unwrap();
+    execute("(import (example tax))").unwrap();
+    assert_eq!(execute("rate").unwrap(), "7");
+    assert!(execute("hidden").is_err());
+
+    assert!(execute("(import (example missing))").is_err());
+}
+
+#[test]
+fn test_rules_library() {
+    let run = |rules: &str| {
+        execute(&format!(
+            "(begin (import (lamina rules)) (clear-rules!) (define total 1500) (define discount 0) (define shipping 10) {})",
+            rules
+        ))
+    };
+
+    // Higher priorities fire first, then rules in definition order; conditions
+    // are tested again after each firing
+    assert_eq!(
+        run("(define-rule free-shipping
+               #:when (> discount 0)
+               #:then (set! shipping 0))
+             (define-rule big-order
+               #:priority 10
+               #:when (> total 1000)
+               #:then (set! discount 1))
+             (define-rule small-order
+               #:when (< total 100)
+               #:then (set! discount 5))
+             (list (run-rules) discount shipping (rules-fired))")
+        .unwrap(),
+        "((big-order free-shipping) 1 0 (big-order free-shipping))"
+    );
+
+    // A rule fires once per run even if its condition still holds
+    assert_eq!(
+        run("(define count 0)
+             (define-rule tick #:when #t #:then (set! count (+ count 1)))
+             (run-rules)
+             (run-rules)
+             count")
+        .unwrap(),
+        "2"
+    );
+
+    // Redefining a rule replaces it
+    assert_eq!(
+        run("(define-rule a #:when #f #:then 1)
+             (define-rule b #:priority 5 #:when #t #:then 2)
+             (define-rule a #:priority 9 #:when #t #:then 3)
+             (list (rule-names) (run-rules) (remove-rule! 'a) (rule-names))")
+        .unwrap(),
+        "((a b) (a b) #t (b))"
+    );
+
+    let err = run("(define-rule broken #:when (car '()) #:then 1) (run-rules)").unwrap_err();
+    assert!(err.contains("In the condition of rule broken"), "{}", err);
+    assert!(run("(define-rule no-action #:when #t)").is_err());
+
+    // execute shares one environment, so check the import in a fresh one
+    let fresh = lamina::embed::Interpreter::builder().build();
+    let err = fresh
+        .eval("(define-rule r #:when #t #:then 1)")
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("needs (import (lamina rules))"), "{}", err);
+}
+
+#[test]
+fn test_functional_library() {
+    use lamina::embed::Interpreter;
+
+    let interpreter = Interpreter::builder()
+        .with_function("triple", |args| match args.as_slice() {
+            [Value::Number(n)] => Ok(Value::from(n.as_f64() * 3.0)),
+            _ => Err("triple requires a number".into()),
+        })
+        .build();
+    let eval = |code: &str| interpreter.eval(code).map(|value| value.to_string());
+    eval("(import (lamina functional))").unwrap();
+    eval("(define (inc x) (+ x 1))").unwrap();
+    eval("(define (add3 a b c) (list a b c))").unwrap();
+
+    // Closures, native procedures and Rust functions compose alike
+    assert_eq!(eval("((partial + 1 2) 3)").unwrap(), "6");
+    assert_eq!(eval("((partial add3 1) 2 3)").unwrap(), "(1 2 3)");
+    assert_eq!(eval("(pipe 5 inc triple)").unwrap(), "18.0");
+    assert_eq!(eval("(pipe 5)").unwrap(), "5");
+    assert_eq!(eval("((compose inc triple) 5)").unwrap(), "16.0");
+    assert_eq!(
+        eval("((compose (partial list 0) triple +) 1 2)").unwrap(),
+        "(0 9.0)"
+    );
+    assert_eq!(eval("((compose) 7)").unwrap(), "7");
+
+    // Curried procedures take their arguments over any number of calls
+    assert_eq!(eval("(((curry add3) 1) 2 3)").unwrap(), "(1 2 3)");
+    assert_eq!(eval("((((curry add3) 1) 2) 3)").unwrap(), "(1 2 3)");
+    assert_eq!(eval("(((curry list 2) 'a) 'b)").unwrap(), "(a b)");
+    assert!(eval("(curry list)").is_err());
+    assert!(eval("(pipe 1 2)").is_err());
+
+    // Threading a value through calls as their first or last argument
+    assert_eq!(eval("(-> 5 inc (- 1) triple)").unwrap(), "15.0");
+    assert_eq!(eval("(->> 5 inc (- 1) (list 'a))").unwrap(), "(a -5)");
+    assert_eq!(eval("(-> 7)").unwrap(), "7");
+    assert_eq!(eval("(-> 3 (add3 1 2))").unwrap(), "(3 1 2)");
+    assert_eq!(eval("(->> 3 (add3 1 2))").unwrap(), "(1 2 3)");
+}
+
+#[test]
+fn test_export_rename_and_reexport() {
+    use lamina::embed::Interpreter;
+
+    let interpreter = Interpreter::builder().build();
+    let eval = |code: &str| interpreter.eval(code).map(|value| value.to_string());
+    eval(
+        "(define-library (shapes area)
+           (export (rename square-area area) perimeter)
+           (begin
+             (define (square-area side) (* side side))
+             (define (perimeter side) (* 4 side))))",
+    )
+    .unwrap();
+
+    // A library re-exports what it imports, under its own name or another
+    eval(
+        "(define-library (shapes)
+           (import (shapes area))
+           (export area (rename perimeter square-perimeter)))",
+    )
+    .unwrap();
+
+    eval("(import (shapes))").unwrap();
+    assert_eq!(eval("(area 3)").unwrap(), "9");
+    assert_eq!(eval("(square-perimeter 3)").unwrap(), "12");
+    assert!(eval("square-area").is_err());
+    assert!(eval("perimeter").is_err());
+
+    assert!(eval("(define-library (bad) (export (rename a)))").is_err());
+    assert!(eval("(define-library (twice) (export a (rename b a)))").is_err());
+}
@@ -0,0 +1,238 @@
+use lamina::execute;
+use lamina::number::{format_number, parse_number};
+use lamina::value::NumberKind;
+
+#[test]
+fn test_numeric_literal_syntax() {
+    let cases = [
+        ("42", "42"),
+        ("+42", "42"),
+        ("-7", "-7"),
+        ("1/2", "1/2"),
+        ("6/4", "3/2"),
+        ("-4/2", "-2"),
+        ("1.5", "1.5"),
+        (".5", "0.5"),
+        ("2.", "2.0"),
+        ("1e3", "1000.0"),
+        ("-2.5E-1", "-0.25"),
+        ("#xff", "255"),
+        ("#x-ff", "-255"),
+        ("0x10", "16"),
+        ("#b101", "5"),
+        ("#o17", "15"),
+        ("#d10", "10"),
+        ("#e1.25", "5/4"),
+        ("#e1e3", "1000"),
+        ("#i3/4", "0.75"),
+        ("#x#e10", "16"),
+        ("#i#b11", "3.0"),
+        ("+inf.0", "+inf.0"),
+        ("-inf.0", "-inf.0"),
+        ("+nan.0", "+nan.0"),
+        ("1e21", "1e21"),
+        ("-12e20", "-1.2e21"),
+        ("1e-8", "1e-8"),
+        ("1e-7", "0.0000001"),
+        ("1e20", "100000000000000000000.0"),
+        ("#x1.8p3", "12.0"),
+        ("#x-.8p1", "-1.0"),
+        ("0x1p-2", "0.25"),
+        ("#e#x1.8p0", "3/2"),
+    ];
+
+    // The lexer and string->number read the same syntax the same way
+    for (literal, expected) in cases {
+        assert_eq!(execute(literal).unwrap(), expected, "reading {}", literal);
+        assert_eq!(
+            execute(&format!("(string->number \"{}\")", literal)).unwrap(),
+            expected,
+            "string->number of {}",
+            literal
+        );
+    }
+
+    for text in [
+        "abc", "1/0", "1e", "--1", ".", "#b2", "#x#x1", "1.5/2", "#x1.8", "#x.p1",
+    ] {
+        assert_eq!(parse_number(text, 10), None, "{} should not parse", text);
+    }
+    assert!(execute("#b102").is_err());
+    assert_eq!(execute("(string->number \"1e\")").unwrap(), "#f");
+    assert_eq!(execute("(number->string 255 2)").unwrap(), "\"11111111\"");
+    assert_eq!(execute("(number->string 1/3 16)").unwrap(), "\"1/3\"");
+    assert!(execute("(number->string 1.5 16)").is_err());
+}
+
+#[test]
+fn test_special_reals() {
+    // Inexact division by zero gives an infinity or NaN; exact division fails
+    assert_eq!(execute("(/ 1.0 0.0)").unwrap(), "+inf.0");
+    assert_eq!(execute("(/ -1 0.0)").unwrap(), "-inf.0");
+    assert_eq!(execute("(/ 0.0 0.0)").unwrap(), "+nan.0");
+    assert!(execute("(/ 1.0 0)").is_err());
+    assert_eq!(execute("(- +inf.0 +inf.0)").unwrap(), "+nan.0");
+    assert_eq!(execute("(* 1e200 1e200)").unwrap(), "+inf.0");
+
+    // NaN is neither equal to nor ordered with anything, itself included
+    for comparison in ["=", "<", ">", "<=", ">="] {
+        assert_eq!(
+            execute(&format!("({} +nan.0 +nan.0)", comparison)).unwrap(),
+            "#f",
+            "{}",
+            comparison
+        );
+        assert_eq!(
+            execute(&format!("({} 1 +nan.0)", comparison)).unwrap(),
+            "#f",
+            "{}",
+            comparison
+        );
+    }
+    assert_eq!(execute("(< -inf.0 0 +inf.0)").unwrap(), "#t");
+    assert_eq!(execute("(= +inf.0 +inf.0)").unwrap(), "#t");
+
+    assert_eq!(execute("(nan? (/ 0.0 0.0))").unwrap(), "#t");
+    assert_eq!(execute("(infinite? -inf.0)").unwrap(), "#t");
+    assert_eq!(execute("(finite? +nan.0)").unwrap(), "#f");
+    assert_eq!(execute("(finite? 1/3)").unwrap(), "#t");
+}
+
+/// A small xorshift generator, so failures replay with the same inputs
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+#[test]
+fn test_number_round_trip() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..20_000 {
+        let radix = [2, 8, 10, 16][(rng.next() % 4) as usize];
+        let number = match rng.next() % 4 {
+            0 => NumberKind::Integer(rng.next() as i64),
+            1 => NumberKind::Integer((rng.next() % 2001) as i64 - 1000),
+            2 => {
+                // Reals are only written in radix 10
+                let real = f64::from_bits(rng.next());
+                let number = NumberKind::Real(real);
+                let text = format_number(&number, 10).unwrap();
+                let parsed = parse_number(&text, 10).unwrap();
+                match parsed {
+                    NumberKind::Real(back) if real.is_nan() => assert!(back.is_nan()),
+                    NumberKind::Real(back) => {
+                        assert_eq!(back.to_bits(), real.to_bits(), "{}", text)
+                    }
+                    other => panic!("{} read back as {:?}", text, other),
+                }
+                continue;
+            }
+            _ => {
+                let numerator = (rng.next() % 10_000) as i64 - 5_000;
+                let denominator = (rng.next() % 999) as i64 + 2;
+                match parse_number(&format!("{}/{}", numerator, denominator), 10) {
+                    Some(number) => number,
+                    None => panic!("{}/{} did not parse", numerator, denominator),
+                }
+            }
+        };
+
+        let text = format_number(&number, radix).unwrap();
+        let parsed = parse_number(&text, radix);
+        assert_eq!(
+            parsed.as_ref(),
+            Some(&number),
+            "{:?} written in radix {} as {} read back as {:?}",
+            number,
+            radix,
+            text,
+            parsed
+        );
+    }
+}
+
+#[test]
+fn test_numeric_tower_arithmetic() {
+    let cases = [
+        // Exact integers never overflow or lose precision
+        ("(+ 9223372036854775807 1)", "9223372036854775808"),
+        ("(- -9223372036854775808 1)", "-9223372036854775809"),
+        ("(* 4294967296 4294967296)", "18446744073709551616"),
+        ("(- (+ 9007199254740993 0) 9007199254740992)", "1"),
+        ("(- (* 99999999999 99999999999) 9999999999800000000000)", "1"),
+        // Division of exact numbers is exact
+        ("(/ 1 3)", "1/3"),
+        ("(/ 6 4)", "3/2"),
+        ("(/ 6 3)", "2"),
+        ("(/ 3)", "1/3"),
+        ("(+ 1/2 1/3)", "5/6"),
+        ("(* 2/3 3/2)", "1"),
+        ("(- 1/2)", "-1/2"),
+        // An inexact operand makes the result inexact
+        ("(+ 1/2 0.5)", "1.0"),
+        ("(* 2 1.5)", "3.0"),
+        ("(/ 1 2.0)", "0.5"),
+        // Integer division, exact unless an argument is inexact
+        ("(quotient 17 5)", "3"),
+        ("(quotient -17 5)", "-3"),
+        ("(remainder -17 5)", "-2"),
+        ("(modulo -17 5)", "3"),
+        ("(modulo 17 -5)", "-3"),
+        ("(modulo 17.0 5)", "2.0"),
+        ("(quotient 100000000000000000000 3)", "33333333333333333333"),
+        ("(gcd 12 18)", "6"),
+        ("(gcd -12 18 8)", "2"),
+        ("(gcd)", "0"),
+        ("(lcm 4 6)", "12"),
+        ("(lcm -4 6 10)", "60"),
+        ("(lcm)", "1"),
+        // Exact powers stay exact, negative ones included
+        ("(expt 2 100)", "1267650600228229401496703205376"),
+        ("(expt 2/3 3)", "8/27"),
+        ("(expt 2 -2)", "1/4"),
+        ("(expt -1 1000001)", "-1"),
+        ("(expt 0 0)", "1"),
+        ("(expt 2.0 3)", "8.0"),
+        ("(expt 4 0.5)", "2.0"),
+        // Conversions between exact and inexact numbers
+        ("(exact 0.5)", "1/2"),
+        ("(exact 3.0)", "3"),
+        ("(inexact 1/4)", "0.25"),
+        ("(exact->inexact 1/3)", "0.3333333333333333"),
+        ("(exact? 1/3)", "#t"),
+        ("(exact? (expt 10 30))", "#t"),
+        ("(inexact? (exact->inexact 1/3))", "#t"),
+        // Comparisons are exact across exactness
+        ("(= 1/2 0.5)", "#t"),
+        ("(< 1/3 0.3333333333333333)", "#f"),
+        ("(> 1/3 0.3333333333333333)", "#t"),
+        ("(= 9007199254740993 9007199254740992.0)", "#f"),
+        ("(< 9007199254740992.0 9007199254740993)", "#t"),
+        ("(< 1 3/2 2 (expt 10 20))", "#t"),
+        ("(>= 2 2 1/2)", "#t"),
+        ("(finite? (expt 10 400))", "#t"),
+        ("(case (/ 4 2) ((2) 'two) (else 'other))", "two"),
+        ("(case 2.0 ((2) 'exact) (else 'inexact))", "inexact"),
+    ];
+    for (code, expected) in cases {
+        assert_eq!(execute(code).unwrap(), expected, "{}", code);
+    }
+
+    for code in [
+        "(/ 1 0)",
+        "(/ 0)",
+        "(quotient 1 0)",
+        "(modulo 1/2 1)",
+        "(expt 0 -1)",
+        "(exact +inf.0)",
+        "(gcd 1.5 2)",
+    ] {
+        assert!(execute(code).is_err(), "{} should fail", code);
+    }
+}
@@ -11,10 +11,10 @@ fn test_literal_expressions() {
 
 #[test]
 fn test_basic_arithmetic() {
-    assert_eq!(execute("(+ 1 2)").unwrap(), "3.0");
-    assert_eq!(execute("(- 5 3)").unwrap(), "2.0");
-    assert_eq!(execute("(* 4 3)").unwrap(), "12.0");
-    assert_eq!(execute("(/ 6 2)").unwrap(), "3.0");
+    assert_eq!(execute("(+ 1 2)").unwrap(), "3");
+    assert_eq!(execute("(- 5 3)").unwrap(), "2");
+    assert_eq!(execute("(* 4 3)").unwrap(), "12");
+    assert_eq!(execute("(/ 6 2)").unwrap(), "3");
 }
 
 #[test]
@@ -25,13 +25,11 @@ fn test_boolean_operations() {
     assert_eq!(execute("(not #f)").unwrap(), "#t");
 }
 
-// Note: We're not defining number? ourselves as that would cause infinite recursion
-// and the built-in is not yet implemented
 #[test]
 fn test_numeric_predicates() {
-    // Using a simpler check
-    assert_eq!(execute("42").unwrap(), "42");
-    assert_eq!(execute("#t").unwrap(), "#t");
+    assert_eq!(execute("(number? 42)").unwrap(), "#t");
+    assert_eq!(execute("(number? 1/2)").unwrap(), "#t");
+    assert_eq!(execute("(number? \"42\")").unwrap(), "#f");
 }
 
 #[test]
@@ -44,6 +42,319 @@ fn test_numeric_comparisons() {
 
 #[test]
 fn test_advanced_arithmetic() {
-    assert_eq!(execute("(+ 1 2 3)").unwrap(), "6.0");
-    assert_eq!(execute("(* 2 3 4)").unwrap(), "24.0");
+    assert_eq!(execute("(+ 1 2 3)").unwrap(), "6");
+    assert_eq!(execute("(* 2 3 4)").unwrap(), "24");
+}
+
+#[test]
+fn test_hex_literals() {
+    assert_eq!(execute("#xff").unwrap(), "255");
+    assert_eq!(execute("0xDEADBEEF").unwrap(), "3735928559");
+    assert_eq!(execute("(string->number \"#x10\")").unwrap(), "16");
+    assert_eq!(execute("(string->number \"ff\" 16)").unwrap(), "255");
+    assert_eq!(execute("(string->number \"101\" 2)").unwrap(), "5");
+    assert_eq!(execute("(string->number \"zz\" 16)").unwrap(), "#f");
+    assert_eq!(
+        execute("#xffffffffffffffffff").unwrap(),
+        "4722366482869645213695"
+    );
+}
+
+#[test]
+fn test_address_literals() {
+    // Addresses print with their EIP-55 checksum
+    assert_eq!(
+        execute("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed").unwrap(),
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+    );
+    assert_eq!(
+        execute("(address? 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed)").unwrap(),
+        "#t"
+    );
+    assert_eq!(execute("(address? 42)").unwrap(), "#f");
+
+    // A mixed-case literal with a bad checksum is rejected
+    assert!(execute("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD").is_err());
+
+    assert_eq!(
+        execute(
+            "(address->string (string->address \"0xfb6916095ca1df60bb79ce92ce3ea74c37c5d359\"))"
+        )
+        .unwrap(),
+        "\"0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359\""
+    );
+}
+
+#[test]
+fn test_bitwise_operations() {
+    assert_eq!(execute("(bitwise-and #xff #x0f)").unwrap(), "15");
+    assert_eq!(execute("(bitwise-or 8 4 1)").unwrap(), "13");
+    assert_eq!(execute("(bitwise-xor 6 3)").unwrap(), "5");
+    assert_eq!(execute("(bitwise-not 0)").unwrap(), "-1");
+    assert_eq!(execute("(bitwise-and -1 #xff)").unwrap(), "255");
+    assert_eq!(execute("(bitwise-and)").unwrap(), "-1");
+}
+
+#[test]
+fn test_arithmetic_shift() {
+    assert_eq!(execute("(arithmetic-shift 1 8)").unwrap(), "256");
+    assert_eq!(execute("(arithmetic-shift 256 -4)").unwrap(), "16");
+    // Right shifts keep the sign
+    assert_eq!(execute("(arithmetic-shift -16 -2)").unwrap(), "-4");
+    assert_eq!(execute("(arithmetic-shift -1 -300)").unwrap(), "-1");
+
+    // Shifting past 64 bits produces a big integer, and big integers shift
+    assert_eq!(
+        execute("(arithmetic-shift 1 100)").unwrap(),
+        "1267650600228229401496703205376"
+    );
+    assert_eq!(
+        execute("(= (arithmetic-shift 1 200) (expt 2 200))").unwrap(),
+        "#t"
+    );
+    assert_eq!(
+        execute("(arithmetic-shift (arithmetic-shift 1 100) -100)").unwrap(),
+        "1"
+    );
+    assert_eq!(
+        execute("(arithmetic-shift (- (expt 2 100)) -99)").unwrap(),
+        "-2"
+    );
+    assert_eq!(
+        execute("(bitwise-and (- (expt 2 100) 1) (expt 2 99))").unwrap(),
+        "633825300114114700748351602688"
+    );
+    assert!(execute("(arithmetic-shift (expt 2 256) 0)").is_err());
+}
+
+#[test]
+fn test_create2_address() {
+    // Examples from EIP-1014
+    assert_eq!(
+        execute("(create2-address 0x0000000000000000000000000000000000000000 0 (init-code-hash (bytevector 0)))")
+            .unwrap(),
+        "0x4D1A2e2bB4F88F0250f26Ffff098B0b30B26BF38"
+    );
+    assert_eq!(
+        execute(
+            "(create2-address 0x00000000000000000000000000000000deadbeef 3405691582 (init-code-hash (hex->bytevector \"0xdeadbeef\")))"
+        )
+        .unwrap(),
+        "0x60f3f640a8508fC6a86d45DF051962668E1e8AC7"
+    );
+    assert_eq!(
+        execute("(bytevector->hex (init-code-hash (bytevector)))").unwrap(),
+        "\"0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470\""
+    );
+    assert!(execute("(create2-address 42 0 0)").is_err());
+    assert!(execute("(init-code-hash 42)").is_err());
+}
+
+#[test]
+fn test_hex_encoding() {
+    assert_eq!(
+        execute("(bytevector->hex (bytevector 222 173 190 239))").unwrap(),
+        "\"0xdeadbeef\""
+    );
+    assert_eq!(
+        execute("(hex->bytevector \"0xdeadbeef\")").unwrap(),
+        "#u8(222 173 190 239)"
+    );
+    // A byte string needs two digits a byte
+    assert!(execute("(hex->bytevector \"abc\")").is_err());
+    assert_eq!(
+        execute("(hex->bytevector \"0x0abc\")").unwrap(),
+        "#u8(10 188)"
+    );
+    assert!(execute("(hex->bytevector \"0xzz\")").is_err());
+    assert_eq!(execute("(string->hex \"hi\")").unwrap(), "\"0x6869\"");
+
+    assert_eq!(execute("(hex->u256 \"0x0100\")").unwrap(), "256");
+    // A number may have an odd number of digits
+    assert_eq!(execute("(hex->u256 \"0x100\")").unwrap(), "256");
+    // and is never negative, however high its top bit
+    assert_eq!(
+        execute(&format!(
+            "(= (hex->u256 \"0x{}\") (- (expt 2 256) 1))",
+            "ff".repeat(32)
+        ))
+        .unwrap(),
+        "#t"
+    );
+    assert_eq!(
+        execute("(hex->u256 \"0x8000000000000000\")").unwrap(),
+        "9223372036854775808"
+    );
+    assert!(execute(&format!("(hex->u256 \"0x{}\")", "ff".repeat(33))).is_err());
+}
+
+#[test]
+fn test_arrays() {
+    assert_eq!(execute("(make-array 3)").unwrap(), "#(0 0 0)");
+    assert_eq!(execute("(array-length (make-array 4))").unwrap(), "4");
+    assert_eq!(
+        execute("(array-ref (array-set! (array-set! (make-array 3) 2 7) 1 42) 1)").unwrap(),
+        "42"
+    );
+    assert_eq!(
+        execute("(begin (define a (make-array 2)) (array-set! a 0 5) a)").unwrap(),
+        "#(5 0)"
+    );
+    assert!(execute("(array-ref (make-array 3) 3)").is_err());
+    assert!(execute("(array-set! (make-array 3) -1 0)").is_err());
+}
+
+#[test]
+fn test_equivalence_predicates() {
+    assert_eq!(execute("(eq? 'a 'a)").unwrap(), "#t");
+    assert_eq!(execute("(eq? '() '())").unwrap(), "#t");
+    assert_eq!(execute("(eq? (list 1) (list 1))").unwrap(), "#f");
+    assert_eq!(execute("(let ((p (list 1))) (eq? p p))").unwrap(), "#t");
+    assert_eq!(execute("(eq? car car)").unwrap(), "#t");
+    assert_eq!(
+        execute("(eq? 100000000000000000000 100000000000000000000)").unwrap(),
+        "#f"
+    );
+
+    assert_eq!(
+        execute("(eqv? 100000000000000000000 100000000000000000000)").unwrap(),
+        "#t"
+    );
+    assert_eq!(execute("(eqv? 1/2 (/ 2 4))").unwrap(), "#t");
+    assert_eq!(execute("(eqv? 2 2.0)").unwrap(), "#f");
+    assert_eq!(execute("(eqv? 0.0 -0.0)").unwrap(), "#f");
+    assert_eq!(execute("(eqv? #\\a #\\a)").unwrap(), "#t");
+    assert_eq!(execute("(eqv? \"abc\" \"abc\")").unwrap(), "#t");
+    assert_eq!(execute("(eqv? (vector 1) (vector 1))").unwrap(), "#f");
+
+    assert_eq!(
+        execute("(equal? '(1 (2 #(3 \"x\"))) '(1 (2 #(3 \"x\"))))").unwrap(),
+        "#t"
+    );
+    assert_eq!(execute("(equal? '(1 2) '(1 2 3))").unwrap(), "#f");
+    assert_eq!(
+        execute("(equal? (bytevector 1 2) (bytevector 1 2))").unwrap(),
+        "#t"
+    );
+    assert_eq!(execute("(equal? 2 2.0)").unwrap(), "#f");
+    assert!(execute("(equal? 1)").is_err());
+
+    // Vectors that contain themselves
+    assert_eq!(
+        execute(
+            "(let ((a (make-array 2)) (b (make-array 2)))
+               (array-set! a 0 a)
+               (array-set! b 0 b)
+               (equal? a b))"
+        )
+        .unwrap(),
+        "#t"
+    );
+    assert_eq!(
+        execute(
+            "(let ((a (make-array 2)) (b (make-array 2)))
+               (array-set! a 0 a)
+               (array-set! b 0 b)
+               (array-set! b 1 1)
+               (equal? a b))"
+        )
+        .unwrap(),
+        "#f"
+    );
+
+    assert_eq!(
+        execute("(case 1/2 ((1/2) 'half) (else 'other))").unwrap(),
+        "half"
+    );
+    assert_eq!(
+        execute("(case 2 ((2.0) 'real) (else 'exact))").unwrap(),
+        "exact"
+    );
+}
+
+#[test]
+fn test_character_and_string_comparisons() {
+    assert_eq!(execute("(char=? #\\a #\\a #\\a)").unwrap(), "#t");
+    assert_eq!(execute("(char<? #\\a #\\b #\\c)").unwrap(), "#t");
+    assert_eq!(execute("(char<? #\\a #\\c #\\b)").unwrap(), "#f");
+    assert_eq!(execute("(char>=? #\\b #\\b #\\a)").unwrap(), "#t");
+    assert_eq!(execute("(char=? #\\a #\\A)").unwrap(), "#f");
+    assert_eq!(execute("(char-ci=? #\\a #\\A)").unwrap(), "#t");
+    assert_eq!(execute("(char-ci<? #\\a #\\B)").unwrap(), "#t");
+    assert!(execute("(char=? #\\a \"a\")").is_err());
+    assert!(execute("(char<? #\\a)").is_err());
+
+    assert_eq!(execute("(string=? \"abc\" \"abc\" \"abc\")").unwrap(), "#t");
+    assert_eq!(execute("(string<? \"abc\" \"abd\")").unwrap(), "#t");
+    assert_eq!(execute("(string<? \"ab\" \"abc\")").unwrap(), "#t");
+    assert_eq!(execute("(string>? \"b\" \"abc\")").unwrap(), "#t");
+    assert_eq!(execute("(string<=? \"a\" \"a\" \"b\")").unwrap(), "#t");
+    assert_eq!(execute("(string=? \"Hello\" \"hello\")").unwrap(), "#f");
+    assert_eq!(execute("(string-ci=? \"Hello\" \"hELLO\")").unwrap(), "#t");
+    assert_eq!(execute("(string-ci>? \"B\" \"a\")").unwrap(), "#t");
+    assert!(execute("(string=? \"a\" 'a)").is_err());
+}
+
+#[test]
+fn test_type_predicates() {
+    assert_eq!(execute("(null? '())").unwrap(), "#t");
+    assert_eq!(execute("(pair? '())").unwrap(), "#f");
+    assert_eq!(execute("(boolean? #f)").unwrap(), "#t");
+    assert_eq!(execute("(char? #\\a)").unwrap(), "#t");
+    assert_eq!(execute("(string? 'a)").unwrap(), "#f");
+    assert_eq!(execute("(symbol? 'a)").unwrap(), "#t");
+    assert_eq!(execute("(vector? (vector 1 2))").unwrap(), "#t");
+    assert_eq!(execute("(vector? '(1 2))").unwrap(), "#f");
+    assert_eq!(execute("(bytevector? (bytevector 1 2))").unwrap(), "#t");
+    assert_eq!(execute("(bytevector? (vector 1 2))").unwrap(), "#f");
+    assert_eq!(execute("(procedure? car)").unwrap(), "#t");
+    assert_eq!(execute("(procedure? (lambda (x) x))").unwrap(), "#t");
+    assert_eq!(execute("(procedure? (make-parameter 1))").unwrap(), "#t");
+    assert_eq!(execute("(port? (open-input-string \"\"))").unwrap(), "#t");
+    assert_eq!(execute("(eof-object? (eof-object))").unwrap(), "#t");
+    assert_eq!(execute("(promise? 1)").unwrap(), "#f");
+
+    execute("(define-record-type point (make-point x y) point? (x point-x))").unwrap();
+    assert_eq!(execute("(record? (make-point 1 2))").unwrap(), "#t");
+    assert_eq!(execute("(record? (eof-object))").unwrap(), "#f");
+    assert_eq!(execute("(record? (vector))").unwrap(), "#f");
+
+    // Each value belongs to exactly one type
+    assert_eq!(execute("(type-of '())").unwrap(), "null");
+    assert_eq!(execute("(type-of '(1 2))").unwrap(), "pair");
+    assert_eq!(execute("(type-of 1.5)").unwrap(), "number");
+    assert_eq!(execute("(type-of \"a\")").unwrap(), "string");
+    assert_eq!(execute("(type-of (vector))").unwrap(), "vector");
+    assert_eq!(execute("(type-of (lambda () 1))").unwrap(), "procedure");
+    assert_eq!(execute("(type-of (current-output-port))").unwrap(), "port");
+    assert_eq!(execute("(type-of (eof-object))").unwrap(), "eof-object");
+    assert_eq!(execute("(type-of (make-point 1 2))").unwrap(), "record");
+    assert!(execute("(type-of)").is_err());
+}
+
+#[test]
+fn test_command_line() {
+    use lamina::evaluator::environment::{set_command_line, setup_initial_env};
+    use lamina::evaluator::eval_with_env;
+    use lamina::{lexer, parser};
+
+    // The process's own arguments, the program first
+    assert!(execute("(pair? (command-line))").is_ok());
+    assert!(execute("(command-line 1)").is_err());
+
+    let env = setup_initial_env();
+    set_command_line(
+        &env,
+        vec![
+            "script.lmn".to_string(),
+            "--to".to_string(),
+            "bob".to_string(),
+        ],
+    );
+    let tokens = lexer::lex("(command-line)").unwrap();
+    let form = parser::parse(&tokens).unwrap();
+    assert_eq!(
+        eval_with_env(form, env).unwrap().to_string(),
+        "(\"script.lmn\" \"--to\" \"bob\")"
+    );
 }
@@ -2,14 +2,14 @@ use lamina::execute;
 
 #[test]
 fn test_procedure_calls() {
-    assert_eq!(execute("(+ 1 2 3)").unwrap(), "6.0");
+    assert_eq!(execute("(+ 1 2 3)").unwrap(), "6");
     assert_eq!(execute("(cons 1 (cons 2 '()))").unwrap(), "(1 2)");
 }
 
 #[test]
 fn test_lambda_expressions() {
-    assert_eq!(execute("((lambda (x) (+ x 1)) 5)").unwrap(), "6.0");
-    assert_eq!(execute("((lambda (x y) (+ x y)) 3 4)").unwrap(), "7.0");
+    assert_eq!(execute("((lambda (x) (+ x 1)) 5)").unwrap(), "6");
+    assert_eq!(execute("((lambda (x y) (+ x y)) 3 4)").unwrap(), "7");
 }
 
 // The current implementation returns the procedure not the result
@@ -20,5 +20,291 @@ fn test_higher_order_functions() {
     assert_eq!(result, "#<procedure>");
 
     // Test a different pattern that works with current implementation
-    assert_eq!(execute("((lambda (x y) (+ x y)) 5 10)").unwrap(), "15.0");
+    assert_eq!(execute("((lambda (x y) (+ x y)) 5 10)").unwrap(), "15");
+}
+
+#[test]
+fn test_gensym() {
+    let first = execute("(gensym)").unwrap();
+    let second = execute("(gensym)").unwrap();
+    assert!(first.starts_with("g#"));
+    assert_ne!(first, second);
+
+    let named = execute("(generate-uninterned-symbol \"tmp\")").unwrap();
+    assert!(named.starts_with("tmp#"));
+    assert!(execute("(gensym 'loop)").unwrap().starts_with("loop#"));
+
+    // Source code can't name a generated symbol
+    assert!(execute(&format!("'{}", named)).is_err());
+}
+
+#[test]
+fn test_redirected_output() {
+    assert_eq!(
+        execute(
+            "(with-output-to-string
+               (lambda () (begin (display \"x = \") (write \"a\") (newline))))"
+        )
+        .unwrap(),
+        "\"x = \"a\"\n\""
+    );
+
+    let path = format!("{}/redirected.txt", env!("CARGO_TARGET_TMPDIR"));
+    assert_eq!(
+        execute(&format!(
+            "(begin
+               (with-output-to-file \"{path}\"
+                 (lambda () (begin (display \"first\") (newline) (display 2))))
+               (with-input-from-file \"{path}\"
+                 (lambda () (list (read-line) (read-line) (eof-object? (read-line))))))"
+        ))
+        .unwrap(),
+        "(\"first\" \"2\" #t)"
+    );
+    std::fs::remove_file(path).unwrap();
+
+    // The current port is a parameter, so parameterize redirects it too
+    assert_eq!(
+        execute(
+            "(with-output-to-string
+               (lambda ()
+                 (parameterize ((current-output-port (current-output-port)))
+                   (write-string \"kept\"))))"
+        )
+        .unwrap(),
+        "\"kept\""
+    );
+}
+
+#[test]
+fn test_port_objects() {
+    assert_eq!(
+        execute(
+            "(begin
+               (define in (open-input-string \"ab (c d) 42\"))
+               (list (read-char in) (peek-char in) (read-char in) (read in) (read in)
+                     (eof-object? (read in)) (eof-object? (read-char in))))"
+        )
+        .unwrap(),
+        "(#\\a #\\b #\\b (c d) 42 #t #t)"
+    );
+    assert_eq!(
+        execute(
+            "(begin
+               (define out (open-output-string))
+               (write 'sym out)
+               (write-char #\\space out)
+               (display \"text\" out)
+               (write \"text\" out)
+               (get-output-string out))"
+        )
+        .unwrap(),
+        "\"sym text\"text\"\""
+    );
+
+    // A file written through one port reads back through another
+    let path = format!("{}/ports.txt", env!("CARGO_TARGET_TMPDIR"));
+    assert_eq!(
+        execute(&format!(
+            "(begin
+               (call-with-port (open-output-file \"{path}\")
+                 (lambda (port) (write '(1 2) port) (newline port)))
+               (define in (open-input-file \"{path}\"))
+               (define datum (read in))
+               (close-port in)
+               (list datum (input-port-open? in) (port? in) (textual-port? 5)))"
+        ))
+        .unwrap(),
+        "((1 2) #f #t #f)"
+    );
+    std::fs::remove_file(path).unwrap();
+
+    let err = execute("(begin (define p (open-output-string)) (close-port p) (display 1 p))")
+        .unwrap_err();
+    assert!(err.contains("display: the port is closed"));
+}
+
+#[test]
+fn test_arity_errors() {
+    let err = execute("(begin (define (add a b) (+ a b)) (add 1))").unwrap_err();
+    assert!(
+        err.contains("Wrong number of arguments to (add a b): expected 2, got 1, in (add 1)"),
+        "{}",
+        err
+    );
+
+    let err = execute("((lambda (x) x) 1 2)").unwrap_err();
+    assert!(err.contains("(lambda (x)): expected 1, got 2"), "{}", err);
+
+    assert_eq!(execute("((lambda parts parts) 1 2 3)").unwrap(), "(1 2 3)");
+
+    let err = execute(
+        "(begin (define-record-type point (make-point x y) point? (x point-x)) (make-point 1))",
+    )
+    .unwrap_err();
+    assert!(
+        err.contains("(make-point x y): expected 2, got 1"),
+        "{}",
+        err
+    );
+
+    // Procedures are named after the variable they are first defined as
+    assert_eq!(
+        execute("(begin (define add (lambda (a b) a)) add)").unwrap(),
+        "#<procedure add (a b)>"
+    );
+}
+
+#[test]
+fn test_procedure_introspection() {
+    let square = "(define (square x) (* x x))";
+    let with_square = |expr: &str| execute(&format!("(begin {} {})", square, expr));
+
+    assert_eq!(with_square("square").unwrap(), "#<procedure square (x)>");
+    assert_eq!(with_square("(procedure-name square)").unwrap(), "square");
+    assert_eq!(
+        with_square("(procedure-source square)").unwrap(),
+        "(lambda (x) (* x x))"
+    );
+    assert_eq!(with_square("(procedure-arity square)").unwrap(), "1");
+
+    assert_eq!(execute("(procedure-name (lambda (x) x))").unwrap(), "#f");
+    assert_eq!(
+        execute("(procedure-arity (lambda args args))").unwrap(),
+        "(at-least 0)"
+    );
+
+    // Native procedures have no name, source or known arity
+    assert_eq!(execute("(procedure-name car)").unwrap(), "#f");
+    assert_eq!(execute("(procedure-source car)").unwrap(), "#f");
+    assert_eq!(execute("(procedure-arity car)").unwrap(), "#f");
+    assert!(execute("(procedure-name 1)").is_err());
+}
+
+#[test]
+fn test_print_limits() {
+    let written = |limits: &str, value: &str| {
+        execute(&format!(
+            "(parameterize ({}) (with-output-to-string (lambda () (write {}))))",
+            limits, value
+        ))
+        .unwrap()
+    };
+
+    assert_eq!(
+        written("(*print-length* 3)", "'(1 2 3 4 5)"),
+        "\"(1 2 3 ...)\""
+    );
+    assert_eq!(written("(*print-length* 3)", "'(1 2 3)"), "\"(1 2 3)\"");
+    assert_eq!(written("(*print-length* 2)", "#(1 2 3)"), "\"#(1 2 ...)\"");
+    assert_eq!(
+        written("(*print-length* 2)", "#u8(1 2 3)"),
+        "\"#u8(1 2 ...)\""
+    );
+    assert_eq!(
+        written("(*print-depth* 2)", "'(1 (2 (3)))"),
+        "\"(1 (2 ...))\""
+    );
+    assert_eq!(written("(*print-depth* 0)", "#(1)"), "\"...\"");
+    assert_eq!(
+        written("(*print-string-length* 3)", "'(\"abcdef\" \"ab\")"),
+        "\"(\"abc...\" \"ab\")\""
+    );
+    assert_eq!(written("(*print-length* #f)", "'(1 2 3)"), "\"(1 2 3)\"");
+    assert_eq!(execute("(*print-depth*)").unwrap(), "#f");
+
+    // Error messages are limited the same way
+    let error = execute("(parameterize ((*print-length* 2)) ('(1 2 3 4) 5))").unwrap_err();
+    assert!(
+        error.to_string().contains("Not a function: (1 2 ...)"),
+        "{}",
+        error
+    );
+
+    // Nesting too deep for a recursive printer
+    let deep = (0..20_000).fold(lamina::value::Value::Nil, |inner, _| {
+        lamina::value::Value::cons(inner, lamina::value::Value::Nil)
+    });
+    assert_eq!(deep.to_string().len(), 40_002);
+}
+
+#[test]
+fn test_long_lists() {
+    use lamina::printer::Limits;
+    use lamina::value::{NumberKind, Value};
+
+    const LENGTH: usize = 1_000_000;
+    let build = || {
+        (0..LENGTH as i64).rev().fold(Value::Nil, |rest, n| {
+            Value::cons(Value::Number(NumberKind::Integer(n)), rest)
+        })
+    };
+    let (list, same) = (build(), build());
+    assert!(list == same);
+    assert!(list != Value::cons(Value::Nil, Value::Nil));
+
+    let old = Limits {
+        length: Some(3),
+        ..Limits::default()
+    }
+    .set();
+    assert_eq!(list.to_string(), "(0 1 2 ...)");
+    old.set();
+
+    // Read, evaluated and dropped without a recursion per element
+    let source = format!("(car (cdr '({})))", "1 ".repeat(LENGTH));
+    assert_eq!(execute(&source).unwrap(), "1");
+    drop(list);
+    drop(same);
+}
+
+#[test]
+fn test_deeply_nested_data() {
+    use lamina::value::Value;
+    use lamina::{lexer, parser};
+
+    const DEPTH: usize = 100_000;
+    // Read without a recursion per level of nesting
+    let source = format!("{}x{}", "(".repeat(DEPTH), ")".repeat(DEPTH));
+    let tokens = lexer::lex(&source).unwrap();
+    let mut datum = parser::parse(&tokens).unwrap();
+    for _ in 0..DEPTH {
+        datum = match datum {
+            Value::Pair(pair) => pair.0.clone(),
+            other => panic!("expected a list, got {}", other),
+        };
+    }
+    assert_eq!(datum.to_string(), "x");
+
+    let source = format!("{}x{}", "('".repeat(DEPTH), ")".repeat(DEPTH));
+    assert!(parser::parse(&lexer::lex(&source).unwrap()).is_ok());
+    assert!(parser::parse(&lexer::lex(&"(".repeat(DEPTH)).unwrap())
+        .unwrap_err()
+        .to_string()
+        .contains("Unexpected end of input in list"));
+}
+#[test]
+fn test_tail_calls() {
+    // Calls in tail position don't grow the evaluator's stack, so loops
+    // written as recursion run in constant space
+    execute(
+        "(begin
+           (define (count-down n) (if (= n 0) 'done (count-down (- n 1))))
+           (define (by-cond n) (cond ((= n 0) 'done) (else (by-cond (- n 1)))))
+           (define (by-case n) (case n ((0) 'done) (else (by-case (- n 1)))))
+           (define (by-let n) (let ((m (- n 1))) (if (< m 0) 'done (by-let m))))
+           (define (by-begin n) (if (= n 0) 'done (begin n (by-begin (- n 1)))))
+           (define (even-steps? n) (if (= n 0) #t (odd-steps? (- n 1))))
+           (define (odd-steps? n) (if (= n 0) #f (even-steps? (- n 1)))))",
+    )
+    .unwrap();
+    for procedure in ["count-down", "by-cond", "by-case", "by-let", "by-begin"] {
+        assert_eq!(
+            execute(&format!("({} 100000)", procedure)).unwrap(),
+            "done",
+            "{}",
+            procedure
+        );
+    }
+    assert_eq!(execute("(even-steps? 100001)").unwrap(), "#f");
 }
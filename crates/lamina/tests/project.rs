@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+
+use lamina::edition::Edition;
+use lamina::project::{self, Entry, Manifest};
+
+#[test]
+fn test_manifest() {
+    let manifest = Manifest::new("vault", "evm");
+    let text = manifest.render();
+    assert_eq!(
+        text,
+        "[package]\nname = \"vault\"\nversion = \"0.1.0\"\nedition = \"2025\"\ntarget = \"evm\"\nentry = \"src/main.lmn\"\n"
+    );
+    assert_eq!(Manifest::parse(&text), Ok(manifest));
+
+    // Manifests from before targets and entries keep building src with the interpreter
+    let old = Manifest::parse("[package]\nname = \"vault\"\nedition = \"2024\"\n").unwrap();
+    assert_eq!(old.edition, Some(Edition::E2024));
+    assert_eq!((old.target, old.entry), (None, None));
+
+    // Only [package] is read, and it needs a name
+    let text = "[package]\nname = \"vault\" # the vault\n\n[env]\ntarget = \"x\"\n";
+    assert_eq!(Manifest::parse(text).unwrap().target, None);
+    assert!(Manifest::parse("[package]\nversion = \"1.0.0\"\n").is_err());
+    assert!(Manifest::parse("[package]\nname = \"vault\"\nedition = \"2031\"\n").is_err());
+
+    // [env] holds variable defaults, and renders back
+    let text =
+        "[package]\nname = \"vault\"\n\n[env]\nCHAIN_ID = 31337 # anvil\nRPC = \"http://x\"\n";
+    let manifest = Manifest::parse(text).unwrap();
+    assert_eq!(
+        manifest.env,
+        vec![
+            ("CHAIN_ID".to_string(), "31337".to_string()),
+            ("RPC".to_string(), "http://x".to_string()),
+        ]
+    );
+    assert_eq!(Manifest::parse(&manifest.render()), Ok(manifest));
+    assert!(Manifest::parse("[package]\nname = \"vault\"\n[env]\n1A = 1\n").is_err());
+}
+
+#[test]
+fn test_toml_entries() {
+    let entries = project::toml_entries("top = 1\n[gas] # costs\nbase = '2' # two\n").unwrap();
+    assert_eq!(
+        entries,
+        vec![
+            Entry {
+                table: String::new(),
+                key: "top".to_string(),
+                value: "1".to_string(),
+                line: 1,
+            },
+            Entry {
+                table: "gas".to_string(),
+                key: "base".to_string(),
+                value: "2".to_string(),
+                line: 3,
+            },
+        ]
+    );
+    assert_eq!(
+        project::toml_entries("a = 1\nb").unwrap_err(),
+        "line 2: expected key = value"
+    );
+    assert!(project::toml_entries("a = \"open").is_err());
+}
+
+#[test]
+fn test_create_project() {
+    let dir = std::env::temp_dir().join(format!("lamina-project-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    project::create(&dir, "vault", "evm").unwrap();
+    let manifest = Manifest::read(&dir).unwrap().unwrap();
+    assert_eq!(manifest.target.as_deref(), Some("evm"));
+    assert_eq!(manifest.entry, Some(PathBuf::from("src/main.lmn")));
+    assert_eq!(manifest.edition, Some(Edition::LATEST));
+    let main = std::fs::read_to_string(dir.join("src/main.lmn")).unwrap();
+    assert!(main.contains("(storage-store counter-slot"));
+    assert_eq!(
+        std::fs::read_to_string(dir.join(".gitignore")).unwrap(),
+        project::GITIGNORE
+    );
+
+    // A project is only created once, and for a known target
+    assert!(project::create(&dir, "vault", "evm")
+        .unwrap_err()
+        .ends_with("already exists"));
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(project::create(&dir, "vault", "wasm").is_err());
+    assert!(!dir.exists());
+
+    // Files already there are kept
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(dir.join("src/main.lmn"), "(f)\n").unwrap();
+    project::create(&dir, "vault", "interpreter").unwrap();
+    assert_eq!(
+        std::fs::read_to_string(dir.join("src/main.lmn")).unwrap(),
+        "(f)\n"
+    );
+    assert!(project::starter("vault", "interpreter").contains("Hello from vault!"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,370 @@
+;; R7RS-small conformance tests
+;;
+;; Adapted from chibi-scheme's tests/r7rs-tests.scm, keeping its sections and
+;; `(test expected expr)` format. Tests that need `equal?` semantics compare
+;; printed values instead, and tests relying on output ports are left out.
+
+(test-begin "4.1 Primitive expression types")
+
+(define x 28)
+(test 28 x)
+(test 'a (quote a))
+(test '(+ 1 2) '(+ 1 2))
+(test "abc" '"abc")
+(test 145932 '145932)
+(test #t '#t)
+(test '#(a b c) (quote #(a b c)))
+(test 7 (+ 3 4))
+(test 12 ((if #f + *) 3 4))
+(test 8 ((lambda (x) (+ x x)) 4))
+(define reverse-subtract (lambda (x y) (- y x)))
+(test 3 (reverse-subtract 7 10))
+(define add4 (let ((x 4)) (lambda (y) (+ x y))))
+(test 10 (add4 6))
+(test '(3 4 5 6) ((lambda x x) 3 4 5 6))
+(test '(5 6) ((lambda (x y . z) z) 3 4 5 6))
+(test 'yes (if (> 3 2) 'yes 'no))
+(test 'no (if (> 2 3) 'yes 'no))
+(test 1 (if (> 3 2) (- 3 2) (+ 3 2)))
+(define y 2)
+(test 3 (begin (set! y (+ y 1)) y))
+
+(test-end)
+
+(test-begin "4.2 Derived expression types")
+
+(test 'greater (cond ((> 3 2) 'greater) ((< 3 2) 'less)))
+(test 'equal (cond ((> 3 3) 'greater) ((< 3 3) 'less) (else 'equal)))
+(test 2 (cond ((assv 'b '((a 1) (b 2))) => cadr) (else #f)))
+(test 'composite (case (* 2 3) ((2 3 5 7) 'prime) ((1 4 6 8 9) 'composite)))
+(test 'c (case (car '(c d)) ((a e i o u) 'vowel) ((w y) 'semivowel) (else => (lambda (x) x))))
+(test #t (and (= 2 2) (> 2 1)))
+(test #f (and (= 2 2) (< 2 1)))
+(test '(f g) (and 1 2 'c '(f g)))
+(test #t (and))
+(test #t (or (= 2 2) (> 2 1)))
+(test #f (or #f #f #f))
+(test '(b c) (or (memq 'b '(a b c)) (/ 3 0)))
+(test 'greater (when (> 3 2) 'greater))
+(test 'less (unless (> 2 3) 'less))
+(test 6 (let ((x 2) (y 3)) (* x y)))
+(test 35 (let ((x 2) (y 3)) (let ((x 7) (z (+ x y))) (* z x))))
+(test 70 (let ((x 2) (y 3)) (let* ((x 7) (z (+ x y))) (* z x))))
+(test #t (letrec ((even? (lambda (n) (if (zero? n) #t (odd? (- n 1))))) (odd? (lambda (n) (if (zero? n) #f (even? (- n 1)))))) (even? 88)))
+(test 5 (letrec* ((p (lambda (x) (+ 1 (q (- x 1))))) (q (lambda (y) (if (zero? y) 0 (+ 1 (p (- y 1)))))) (x (p 5)) (y x)) y))
+(test 10 (let-values (((a b) (values 1 2)) ((c d) (values 3 4))) (+ a b c d)))
+(test '(x y x y) (let ((a 'a) (b 'b) (x 'x) (y 'y)) (let*-values (((a b) (values x y)) ((x y) (values a b))) (list a b x y))))
+(test 25 (let ((x '(1 3 5 7 9))) (do ((x x (cdr x)) (sum 0 (+ sum (car x)))) ((null? x) sum))))
+(test '((6 1 3) (-5 -2)) (let loop ((numbers '(3 -2 1 6 -5)) (nonneg '()) (neg '())) (cond ((null? numbers) (list nonneg neg)) ((>= (car numbers) 0) (loop (cdr numbers) (cons (car numbers) nonneg) neg)) ((< (car numbers) 0) (loop (cdr numbers) nonneg (cons (car numbers) neg))))))
+(test 3 (force (delay (+ 1 2))))
+(test '(3 3) (let ((p (delay (+ 1 2)))) (list (force p) (force p))))
+(test 3 (force (make-promise 3)))
+(test '(list 3 4) `(list ,(+ 1 2) 4))
+(test '(a 3 4 5 6 b) `(a ,(+ 1 2) ,@(map abs '(4 -5 6)) b))
+
+(test-end)
+
+(test-begin "4.3 Macros")
+
+(define-syntax swap! (syntax-rules () ((_ a b) (let ((tmp a)) (set! a b) (set! b tmp)))))
+(test '(2 1) (let ((x 1) (y 2)) (swap! x y) (list x y)))
+(define-syntax my-or (syntax-rules () ((_) #f) ((_ e) e) ((_ e r ...) (let ((t e)) (if t t (my-or r ...))))))
+(test 7 (let ((x #f) (y 7) (temp 8)) (my-or x y)))
+(test 'outer (let ((x 'outer)) (let-syntax ((m (syntax-rules () ((m) x)))) (let ((x 'inner)) (m)))))
+
+(test-end)
+
+(test-begin "5 Program structure")
+
+(define add3 (lambda (x) (+ x 3)))
+(test 6 (add3 3))
+(define (double x) (* x 2))
+(test 10 (double 5))
+(test 45 (let ((x 5)) (define foo (lambda (y) (bar x y))) (define bar (lambda (a b) (+ (* a b) a))) (foo (+ x 3))))
+(define-values (q r) (floor/ 7 2))
+(test '(3 1) (list q r))
+(define-record-type <pare> (kons x y) pare? (x kar set-kar!) (y kdr))
+(test #t (pare? (kons 1 2)))
+(test #f (pare? (cons 1 2)))
+(test 1 (kar (kons 1 2)))
+(test 2 (kdr (kons 1 2)))
+(test 3 (let ((k (kons 1 2))) (set-kar! k 3) (kar k)))
+
+(test-end)
+
+(test-begin "6.1 Equivalence predicates")
+
+(test #t (eqv? 'a 'a))
+(test #f (eqv? 'a 'b))
+(test #t (eqv? 2 2))
+(test #t (eqv? '() '()))
+(test #t (eqv? 100000000 100000000))
+(test #f (eqv? (cons 1 2) (cons 1 2)))
+(test #f (eqv? (lambda () 1) (lambda () 2)))
+(test #t (let ((p (lambda (x) x))) (eqv? p p)))
+(test #f (eqv? #f 'nil))
+(test #t (eq? 'a 'a))
+(test #t (eq? '() '()))
+(test #t (eq? car car))
+(test #t (let ((x '(a))) (eq? x x)))
+(test #t (equal? 'a 'a))
+(test #t (equal? '(a) '(a)))
+(test #t (equal? '(a (b) c) '(a (b) c)))
+(test #t (equal? "abc" "abc"))
+(test #f (equal? "abc" "abcd"))
+(test #t (equal? 2 2))
+(test #t (equal? (make-vector 5 'a) (make-vector 5 'a)))
+
+(test-end)
+
+(test-begin "6.2 Numbers")
+
+(test #t (complex? 3))
+(test #t (real? 3))
+(test #t (rational? 1/2))
+(test #t (integer? 3.0))
+(test #f (integer? 3.5))
+(test #t (exact? 3))
+(test #f (exact? 3.0))
+(test #t (inexact? 3.0))
+(test #t (exact-integer? 32))
+(test #f (exact-integer? 32.0))
+(test #t (number? 3))
+(test #t (= 1 1.0))
+(test #t (< 1 2 3))
+(test #f (< 1 1 2))
+(test #t (>= 3 2 2))
+(test #t (zero? 0))
+(test #t (positive? 1))
+(test #t (negative? -1))
+(test #t (odd? 3))
+(test #t (even? 0))
+(test 4 (max 3 4))
+(test 4.0 (max 3.9 4))
+(test 3 (min 3 4))
+(test 7 (+ 3 4))
+(test 3 (+ 3))
+(test 0 (+))
+(test 4 (* 4))
+(test 1 (*))
+(test -1 (- 3 4))
+(test -6 (- 3 4 5))
+(test -3 (- 3))
+(test 3.5 (/ 7 2.0))
+(test 7 (abs -7))
+(test 2 (floor-quotient 5 2))
+(test -3 (floor-quotient -5 2))
+(test 1 (floor-remainder -5 2))
+(test -2 (truncate-quotient -5 2))
+(test -1 (truncate-remainder -5 2))
+(test 1 (modulo 13 4))
+(test 1 (remainder 13 4))
+(test 3 (modulo -13 4))
+(test -1 (remainder -13 4))
+(test 4 (gcd 32 -36))
+(test 288 (lcm 32 -36))
+(test -5.0 (floor -4.3))
+(test -4.0 (ceiling -4.3))
+(test -4.0 (truncate -4.3))
+(test -4.0 (round -4.3))
+(test 4.0 (round 3.5))
+(test 7 (round 7))
+(test 3 (sqrt 9))
+(test 1000 (expt 10 3))
+(test 25 (square 5))
+(test 3 (exact 3.0))
+(test 3.0 (inexact 3))
+(test 100 (string->number "100"))
+(test 256 (string->number "100" 16))
+(test #f (string->number "abc"))
+(test "100" (number->string 100))
+(test "ff" (number->string 255 16))
+
+(test-end)
+
+(test-begin "6.3 Booleans")
+
+(test #t #t)
+(test #f #f)
+(test #f '#f)
+(test #f (not #t))
+(test #f (not 3))
+(test #f (not (list 3)))
+(test #t (not #f))
+(test #f (not '()))
+(test #f (not (list)))
+(test #f (not 'nil))
+(test #t (boolean? #f))
+(test #f (boolean? 0))
+(test #f (boolean? '()))
+(test #t (boolean=? #t #t))
+(test #f (boolean=? #t #f))
+
+(test-end)
+
+(test-begin "6.4 Pairs and lists")
+
+(test #t (pair? '(a . b)))
+(test #t (pair? '(a b c)))
+(test #f (pair? '()))
+(test '(a) (cons 'a '()))
+(test '((a) b c d) (cons '(a) '(b c d)))
+(test '("a" b c) (cons "a" '(b c)))
+(test 'a (car '(a b c)))
+(test '(a) (car '((a) b c d)))
+(test '(b c d) (cdr '((a) b c d)))
+(test #t (list? '(a b c)))
+(test #t (list? '()))
+(test #t (null? '()))
+(test #f (null? '(a)))
+(test '(a 7 c) (list 'a (+ 3 4) 'c))
+(test '() (list))
+(test 3 (length '(a b c)))
+(test 3 (length '(a (b) (c d e))))
+(test 0 (length '()))
+(test '(x y) (append '(x) '(y)))
+(test '(a b c d) (append '(a) '(b c d)))
+(test '(a (b) (c)) (append '(a (b)) '((c))))
+(test '(c b a) (reverse '(a b c)))
+(test '((e (f)) d (b c) a) (reverse '(a (b c) d (e (f)))))
+(test '(c d) (list-tail '(a b c d) 2))
+(test 'c (list-ref '(a b c d) 2))
+(test '(a b c) (memq 'a '(a b c)))
+(test '(b c) (memq 'b '(a b c)))
+(test #f (memq 'a '(b c d)))
+(test '((a) c) (member (list 'a) '(b (a) c)))
+(test '(101 102) (memv 101 '(100 101 102)))
+(test '(a 1) (assq 'a '((a 1) (b 2) (c 3))))
+(test '(b 2) (assq 'b '((a 1) (b 2) (c 3))))
+(test #f (assq 'd '((a 1) (b 2) (c 3))))
+(test '((a)) (assoc (list 'a) '(((a)) ((b)) ((c)))))
+(test '(5 7) (assv 5 '((2 3) (5 7) (11 13))))
+(test '(1 2 3) (list-copy '(1 2 3)))
+(test '(3 3) (make-list 2 3))
+(test 'b (cadr '(a b c)))
+(test '(c) (cddr '(a b c)))
+
+(test-end)
+
+(test-begin "6.5 Symbols")
+
+(test #t (symbol? 'foo))
+(test #t (symbol? (car '(a b))))
+(test #f (symbol? "bar"))
+(test #t (symbol? 'nil))
+(test #f (symbol? '()))
+(test #t (symbol=? 'a 'a))
+(test #f (symbol=? 'a 'b))
+(test "flying-fish" (symbol->string 'flying-fish))
+(test "Martin" (symbol->string 'Martin))
+(test 'mISSISSIppi (string->symbol "mISSISSIppi"))
+
+(test-end)
+
+(test-begin "6.6 Characters")
+
+(test #t (char? #\a))
+(test #f (char? "a"))
+(test #t (char=? #\a #\a))
+(test #t (char<? #\a #\b))
+(test #f (char<? #\b #\a))
+(test #t (char-ci=? #\a #\A))
+(test #t (char-alphabetic? #\a))
+(test #f (char-alphabetic? #\1))
+(test #t (char-numeric? #\1))
+(test #t (char-whitespace? #\space))
+(test #t (char-upper-case? #\A))
+(test #t (char-lower-case? #\a))
+(test 3 (digit-value #\3))
+(test #f (digit-value #\a))
+(test 97 (char->integer #\a))
+(test #\a (integer->char 97))
+(test #\A (char-upcase #\a))
+(test #\a (char-downcase #\A))
+
+(test-end)
+
+(test-begin "6.7 Strings")
+
+(test #t (string? "a"))
+(test #f (string? #\a))
+(test "aaa" (make-string 3 #\a))
+(test "abc" (string #\a #\b #\c))
+(test 3 (string-length "abc"))
+(test 0 (string-length ""))
+(test #\b (string-ref "abc" 1))
+(test #t (string=? "abc" "abc"))
+(test #f (string=? "abc" "abd"))
+(test #t (string<? "abc" "abd"))
+(test #t (string-ci=? "abc" "ABC"))
+(test "bc" (substring "abc" 1 3))
+(test "abcdef" (string-append "abc" "def"))
+(test "" (string-append))
+(test '(#\a #\b) (string->list "ab"))
+(test "ab" (list->string '(#\a #\b)))
+(test "abc" (string-copy "abc"))
+(test "b" (string-copy "abc" 1 2))
+(test "ABC" (string-upcase "abc"))
+(test "abc" (string-downcase "ABC"))
+
+(test-end)
+
+(test-begin "6.8 Vectors")
+
+(test #t (vector? (make-vector 3)))
+(test #f (vector? '(a)))
+(test (vector 'a 'a) (make-vector 2 'a))
+(test (vector 'a 'b 'c) (vector 'a 'b 'c))
+(test 8 (vector-ref (vector 1 1 2 3 5 8 13 21) 5))
+(test 3 (vector-length (vector 1 2 3)))
+(test (vector 0 "Sue" 0) (let ((v (vector 0 0 0))) (vector-set! v 1 "Sue") v))
+(test '(1 2) (vector->list (vector 1 2)))
+(test (vector 1 2) (list->vector '(1 2)))
+(test (vector 1 2 3 4) (vector-append (vector 1 2) (vector 3 4)))
+(test (vector 'x 'x) (let ((v (make-vector 2 0))) (vector-fill! v 'x) v))
+(test (vector 2 3) (vector-copy (vector 1 2 3) 1))
+
+(test-end)
+
+(test-begin "6.9 Bytevectors")
+
+(test #t (bytevector? (bytevector 1 2)))
+(test #f (bytevector? (vector 1 2)))
+(test (bytevector 7 7) (make-bytevector 2 7))
+(test 8 (bytevector-u8-ref (bytevector 1 3 5 8 13) 3))
+(test 3 (bytevector-length (bytevector 1 2 3)))
+(test (bytevector 1 9) (let ((bv (bytevector 1 2))) (bytevector-u8-set! bv 1 9) bv))
+(test (bytevector 1 2 3 4) (bytevector-append (bytevector 1 2) (bytevector 3 4)))
+
+(test-end)
+
+(test-begin "6.10 Control features")
+
+(test #t (procedure? car))
+(test #f (procedure? 'car))
+(test #t (procedure? (lambda (x) (* x x))))
+(test #f (procedure? '(lambda (x) (* x x))))
+(test 7 (apply + (list 3 4)))
+(test 30 (apply + 10 (list 20)))
+(test '(b e h) (map cadr '((a b) (d e) (g h))))
+(test '(5 7 9) (map + '(1 2 3) '(4 5 6)))
+(test "ABC" (string-map char-upcase "abc"))
+(test (vector 2 4) (vector-map (lambda (x) (* x 2)) (vector 1 2)))
+(test 3 (let ((n 0)) (for-each (lambda (x) (set! n (+ n x))) '(1 2)) n))
+(test -3 (call-with-current-continuation (lambda (exit) (for-each (lambda (x) (if (negative? x) (exit x))) '(54 0 37 -3 245 19)) #t)))
+(test 5 (call-with-values (lambda () (values 4 5)) (lambda (a b) b)))
+(test '(1 2) (call-with-values (lambda () (values 1 2)) list))
+(test '(connect talk disconnect) (let ((path '())) (dynamic-wind (lambda () (set! path (cons 'connect path))) (lambda () (set! path (cons 'talk path))) (lambda () (set! path (cons 'disconnect path)))) (reverse path)))
+
+(test-end)
+
+(test-begin "6.11 Exceptions")
+
+(test 'caught (with-exception-handler (lambda (e) 'caught) (lambda () (raise-continuable 'oops))))
+(test 42 (guard (e (#t 42)) (raise 'oops)))
+(test 'sym (guard (e ((symbol? e) e)) (raise 'sym)))
+(test "an error" (guard (e ((error-object? e) (error-object-message e))) (error "an error" 1 2)))
+(test '(1 2) (guard (e ((error-object? e) (error-object-irritants e))) (error "msg" 1 2)))
+(test 'fallback (guard (e ((string? e) 'string) (else 'fallback)) (raise 1)))
+
+(test-end)
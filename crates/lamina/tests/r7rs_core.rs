@@ -72,20 +72,20 @@ fn test_standard_procedures() {
         execute("(string-for-each (lambda (c) (set! count (+ count 1))) \"hello\")").unwrap(),
         ""
     );
-    assert_eq!(execute("count").unwrap(), "5.0");
+    assert_eq!(execute("count").unwrap(), "5");
 
     // Vector operations - note that vectors are displayed as #(...) in Scheme
     assert_eq!(execute("(define v (vector 1 2 3))").unwrap(), "");
     assert_eq!(
         execute("(vector-map (lambda (x) (* x 2)) v)").unwrap(),
-        "#(2.0 4.0 6.0)"
+        "#(2 4 6)"
     );
     assert_eq!(execute("(define sum 0)").unwrap(), "");
     assert_eq!(
         execute("(vector-for-each (lambda (x) (set! sum (+ sum x))) v)").unwrap(),
         ""
     );
-    assert_eq!(execute("sum").unwrap(), "6.0");
+    assert_eq!(execute("sum").unwrap(), "6");
 
     // Numeric operations
     assert_eq!(execute("(exact-integer? 42)").unwrap(), "#t");
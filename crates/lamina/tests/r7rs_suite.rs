@@ -0,0 +1,43 @@
+use lamina::testing::r7rs::{run_suite, KnownFailures};
+
+const SUITE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/r7rs/r7rs-tests.scm");
+const KNOWN_FAILURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/r7rs/known-failures.txt");
+
+// Runs the R7RS suite and checks that exactly the known failures fail. The
+// per-section report is written next to the other test outputs; set
+// R7RS_BLESS=1 to rewrite the known failures after a feature lands.
+#[test]
+fn test_r7rs_conformance() {
+    let source = std::fs::read_to_string(SUITE).unwrap();
+    let report = run_suite(&source);
+
+    let report_path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("r7rs-report.txt");
+    std::fs::write(&report_path, report.render()).unwrap();
+
+    if std::env::var_os("R7RS_BLESS").is_some() {
+        let header =
+            "# Tests in r7rs-tests.scm the interpreter fails, one section<TAB>test line each\n";
+        let known = KnownFailures::from_report(&report);
+        std::fs::write(KNOWN_FAILURES, format!("{}{}", header, known.to_text())).unwrap();
+        return;
+    }
+
+    let known = KnownFailures::parse(&std::fs::read_to_string(KNOWN_FAILURES).unwrap());
+    let unexpected: Vec<String> = report
+        .unexpected_failures(&known)
+        .iter()
+        .map(|r| format!("{}: {}", r.key(), r.failure.as_deref().unwrap_or("")))
+        .collect();
+    let fixed: Vec<String> = report.fixed(&known).iter().map(|r| r.key()).collect();
+
+    assert!(
+        unexpected.is_empty(),
+        "R7RS tests failing that are not known failures:\n{}",
+        unexpected.join("\n")
+    );
+    assert!(
+        fixed.is_empty(),
+        "R7RS tests passing that are still listed in known-failures.txt:\n{}",
+        fixed.join("\n")
+    );
+}
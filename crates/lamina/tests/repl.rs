@@ -0,0 +1,402 @@
+use lamina::repl::Session;
+
+#[test]
+fn test_repl_evm_target() {
+    let mut session = Session::new();
+
+    // Definitions persist between inputs
+    session.handle("(define x 7)").unwrap();
+    assert_eq!(session.handle("x").unwrap(), "7");
+
+    assert_eq!(session.handle(":target").unwrap(), "interpreter");
+    assert!(session.handle(":storage").is_err());
+    assert!(session.handle("(storage-load 0)").is_err());
+
+    session.handle(":target evm").unwrap();
+    session.handle("(storage-store 1 x)").unwrap();
+    session.handle("(storage-store 0 5)").unwrap();
+    assert_eq!(session.handle(":storage").unwrap(), "0 => 5\n1 => 7");
+
+    session
+        .handle(":caller 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed")
+        .unwrap();
+    assert_eq!(
+        session.handle("(caller)").unwrap(),
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+    );
+    assert!(session
+        .handle(":caller 0x5AAeb6053F3E94C9b9A09f33669435E7Ef1BeAed")
+        .is_err());
+
+    // Each input is a transaction of its own, so transient storage is gone by the next
+    assert_eq!(session.handle("(transient-store! 0 x)").unwrap(), "7");
+    assert_eq!(session.handle("(transient-load 0)").unwrap(), "0");
+
+    session.handle(":value 0x10").unwrap();
+    assert_eq!(session.handle("(callvalue)").unwrap(), "16");
+
+    session.handle(":target interpreter").unwrap();
+    assert!(session.handle("(caller)").is_err());
+    assert_eq!(session.handle("x").unwrap(), "7");
+
+    assert!(session.handle(":target wasm").is_err());
+    assert!(session.handle(":nope").is_err());
+}
+
+#[test]
+fn test_repl_core_bindings() {
+    let mut session = Session::new();
+
+    // Redefining a core primitive is allowed with a warning, and :reset restores it
+    session.handle("(define car 5)").unwrap();
+    assert_eq!(session.handle("car").unwrap(), "5");
+    session.handle("(define y 1)").unwrap();
+    assert_eq!(session.handle(":reset").unwrap(), "environment reset");
+    assert_eq!(session.handle("(car (list 1 2))").unwrap(), "1");
+    assert!(session.handle("y").is_err());
+
+    // Strict mode refuses to rebind core names, local bindings may still shadow them
+    session.set_strict(true);
+    assert!(session.handle("(define car 5)").is_err());
+    assert!(session.handle("(set! cdr 5)").is_err());
+    assert_eq!(session.handle("((lambda (car) car) 3)").unwrap(), "3");
+    assert_eq!(session.handle("(car (list 1 2))").unwrap(), "1");
+
+    // Strictness and the target survive a reset
+    session.handle(":target evm").unwrap();
+    session.handle("(storage-store 0 1)").unwrap();
+    session.handle(":reset").unwrap();
+    assert_eq!(session.handle(":target").unwrap(), "evm");
+    assert_eq!(session.handle(":storage").unwrap(), "(empty)");
+    assert!(session.handle("(define car 5)").is_err());
+}
+
+#[test]
+fn test_repl_stage_commands() {
+    let mut session = Session::new();
+
+    assert_eq!(
+        session
+            .handle(":expand (define-for-syntax n 3) (define x (compile-time (* n 2)))")
+            .unwrap(),
+        "(define x 6)"
+    );
+    assert_eq!(session.handle(":expand (+ 1 2)").unwrap(), "(+ 1 2)");
+
+    // Expansion doesn't touch the session's environment
+    assert!(session.handle("n").is_err());
+
+    // Lowering is up to the host
+    assert!(session.handle(":lower (+ 1 2)").is_err());
+    session.set_lowering(|source| Ok(format!("lowered {}", source)));
+    assert_eq!(session.handle(":lower (+ 1 2)").unwrap(), "lowered (+ 1 2)");
+    assert!(session.handle(":optimize (+ 1 2)").is_err());
+}
+
+#[test]
+fn test_repl_time_travel() {
+    let mut session = Session::new();
+    assert!(session.handle(":back").is_err());
+
+    session.handle(":record").unwrap();
+    session.handle("(define factor 4)").unwrap();
+    session
+        .handle("(define (scale x) (let ((y (* x factor))) y))")
+        .unwrap();
+    assert_eq!(session.handle("(+ 1 (scale 5))").unwrap(), "21");
+
+    // The last step to start was (* x factor), inside the call to scale
+    assert_eq!(
+        session.handle(":back").unwrap(),
+        "      #5 (* x factor) => 20"
+    );
+    assert_eq!(session.handle(":back 2").unwrap(), "  #3 (scale 5) => 20");
+    assert_eq!(
+        session.handle(":forward").unwrap(),
+        "    #4 (let ((y (* x factor))) y) => 20"
+    );
+    assert_eq!(
+        session.handle(":back 100").unwrap(),
+        "#0 (define factor 4) => ()\n    factor := 4"
+    );
+
+    // :why follows a value back to the step that computed it
+    session.handle("(define total (scale 2))").unwrap();
+    assert_eq!(
+        session.handle(":why total").unwrap(),
+        "      #9 (* x factor) => 8\n\
+         returned through:\n    #8 (let ((y (* x factor))) y) => 8\n  #7 (scale 2) => 8"
+    );
+    assert!(session.handle(":why 99").is_err());
+
+    // Only the most recent steps are kept
+    session.handle(":record 2").unwrap();
+    session.handle("(+ 1 (+ 2 (+ 3 4)))").unwrap();
+    assert_eq!(
+        session.handle(":back 10").unwrap(),
+        "  #1 (+ 2 (+ 3 4)) => 9"
+    );
+
+    session.handle(":record off").unwrap();
+    session.handle("(+ 5 5)").unwrap();
+    assert_eq!(
+        session.handle(":forward 10").unwrap(),
+        "    #2 (+ 3 4) => 7"
+    );
+    assert!(session.handle(":record zero").is_err());
+}
+
+#[test]
+fn test_print_command() {
+    let mut session = Session::new();
+    assert_eq!(
+        session.handle(":print").unwrap(),
+        "length: off, depth: off, string-length: off"
+    );
+    assert_eq!(
+        session.handle(":print length 2").unwrap(),
+        "length: 2, depth: off, string-length: off"
+    );
+    assert_eq!(session.handle("'(1 2 3)").unwrap(), "(1 2 ...)");
+    assert_eq!(session.handle("(*print-length*)").unwrap(), "2");
+    session.handle(":print length off").unwrap();
+    assert_eq!(session.handle("'(1 2 3)").unwrap(), "(1 2 3)");
+    assert!(session.handle(":print width 2").is_err());
+    assert!(session.handle(":print depth").is_err());
+}
+
+#[test]
+fn test_auto_import() {
+    let mut session = Session::new();
+    assert_eq!(session.handle(":auto-import").unwrap(), "auto-import: off");
+    assert!(session.handle("(storage-load 0)").is_err());
+
+    // A name only one library exports imports it, noting so before the value
+    assert_eq!(
+        session.handle(":auto-import on").unwrap(),
+        "auto-import: on"
+    );
+    assert_eq!(
+        session.handle("(storage-load 0)").unwrap(),
+        "; imported (evm) for storage-load\n0"
+    );
+    assert_eq!(session.handle("(storage-load 1)").unwrap(), "0");
+
+    // Standard libraries are found before they are loaded, and each missing
+    // name imports its library in turn
+    assert_eq!(
+        session
+            .handle("(list (fold-left + 0 '(1 2 3)) (alist-ref 1 (list (cons 1 'one)) = #f))")
+            .unwrap_or_else(|e| e),
+        "; imported (lamina list) for fold-left\n; imported (lamina alist) for alist-ref\n(6 one)"
+    );
+
+    // When several libraries export it, the error names them
+    session
+        .handle("(define-library (first) (export twice) (begin (define (twice x) (* 2 x))))")
+        .unwrap();
+    session
+        .handle("(define-library (second) (export twice) (begin (define (twice x) (+ x x))))")
+        .unwrap();
+    let err = session.handle("(twice 2)").unwrap_err();
+    assert!(err.contains("exported by (first), (second)"), "{}", err);
+
+    // Names no library exports fail as usual
+    let err = session.handle("(no-such-procedure)").unwrap_err();
+    assert_eq!(err, "Runtime error: Undefined variable: no-such-procedure");
+
+    session.handle(":auto-import off").unwrap();
+    assert!(session.handle("(rule-names)").is_err());
+}
+
+#[test]
+fn test_auto_import_runs_input_once() {
+    let mut session = Session::new();
+    session.handle(":auto-import on").unwrap();
+    session.handle("(define n 0)").unwrap();
+
+    // The import happens before the input runs, so its effects happen once
+    assert_eq!(
+        session
+            .handle("(begin (set! n (+ n 1)) (storage-load 0))")
+            .unwrap(),
+        "; imported (evm) for storage-load\n0"
+    );
+    assert_eq!(session.handle("n").unwrap(), "1");
+
+    // A name the input binds itself imports nothing
+    assert_eq!(
+        session
+            .handle("(let ((fold-left 1)) (set! n (+ n fold-left)) n)")
+            .unwrap(),
+        "2"
+    );
+}
+
+#[test]
+fn test_load_and_save() {
+    use lamina::repl::ReplHelper;
+    use rustyline::completion::Completer;
+    use rustyline::history::DefaultHistory;
+    use rustyline::Context;
+
+    let dir = std::env::temp_dir().join(format!("lamina-repl-load-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let defs = dir.join("defs.lmn");
+    std::fs::write(&defs, "(define x 2)\n(define (double n) (* n 2))\n").unwrap();
+
+    // Loading evaluates the file into the session; saving writes what was
+    // evaluated, loaded files included
+    let mut session = Session::new();
+    session
+        .handle(&format!(":load {}", defs.display()))
+        .unwrap();
+    assert_eq!(session.handle("(double x)").unwrap(), "4");
+    assert!(session.handle("(undefined-thing)").is_err());
+    session.handle("(define y (double x))").unwrap();
+    let saved = dir.join("session.lmn");
+    assert_eq!(
+        session
+            .handle(&format!(":save {}", saved.display()))
+            .unwrap(),
+        format!("saved 3 inputs to {}", saved.display())
+    );
+
+    let mut replayed = Session::new();
+    replayed
+        .handle(&format!(":load {}", saved.display()))
+        .unwrap();
+    assert_eq!(replayed.handle("y").unwrap(), "4");
+    assert!(replayed.handle(":load /no/such/file.lmn").is_err());
+
+    // Only the argument of :load and :save completes to paths
+    let helper = ReplHelper::new();
+    let history = DefaultHistory::new();
+    let ctx = Context::new(&history);
+    let line = format!(":load {}/de", dir.display());
+    let (start, candidates) = helper.complete(&line, line.len(), &ctx).unwrap();
+    assert_eq!(start, ":load ".len());
+    let paths: Vec<&str> = candidates.iter().map(|c| c.replacement.as_str()).collect();
+    assert_eq!(paths, [defs.display().to_string()]);
+    let line = format!("(display \"{}/de", dir.display());
+    assert!(helper
+        .complete(&line, line.len(), &ctx)
+        .unwrap()
+        .1
+        .is_empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_state_save_and_load() {
+    let dir = std::env::temp_dir().join(format!("lamina-repl-state-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("state.json");
+    let save = format!(":state save {}", file.display());
+    let load = format!(":state load {}", file.display());
+
+    let mut session = Session::new();
+    assert!(session.handle(&save).is_err());
+    session.handle(":target evm").unwrap();
+    session
+        .handle("(storage-store 0 100000000000000000000)")
+        .unwrap();
+    session
+        .handle("(storage-string-store! 1 \"hello\")")
+        .unwrap();
+    session.handle("(storage-array-push! 2 1/2)").unwrap();
+    session.handle("(storage-array-push! 2 'sym)").unwrap();
+    session
+        .handle(":caller 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed")
+        .unwrap();
+    session.handle(":value 7").unwrap();
+    assert_eq!(
+        session.handle(&save).unwrap(),
+        format!("saved chain state to {}", file.display())
+    );
+
+    // A new session picks up where the last one left off
+    let mut resumed = Session::new();
+    resumed.handle(":target evm").unwrap();
+    resumed.handle("(storage-store 5 1)").unwrap();
+    assert_eq!(
+        resumed.handle(&load).unwrap(),
+        format!(
+            "loaded chain state from {}: 3 storage slots",
+            file.display()
+        )
+    );
+    assert_eq!(
+        resumed.handle("(storage-load 0)").unwrap(),
+        "100000000000000000000"
+    );
+    assert_eq!(
+        resumed.handle("(storage-string-load 1)").unwrap(),
+        "\"hello\""
+    );
+    assert_eq!(resumed.handle("(storage-array-ref 2 1)").unwrap(), "sym");
+    assert_eq!(resumed.handle("(storage-array-length 2)").unwrap(), "2");
+    assert_eq!(resumed.handle("(storage-load 5)").unwrap(), "0");
+    assert_eq!(
+        resumed.handle("(caller)").unwrap(),
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+    );
+    assert_eq!(resumed.handle("(callvalue)").unwrap(), "7");
+
+    // Values that can't be read back aren't saved
+    resumed.handle("(storage-array-push! 3 car)").unwrap();
+    assert!(resumed.handle(&save).is_err());
+
+    std::fs::write(&file, "{\"storage\": {\"x\": \"1\"}}").unwrap();
+    assert!(resumed.handle(&load).is_err());
+    assert!(resumed.handle(":state load /no/such/state.json").is_err());
+    assert!(resumed.handle(":state").is_err());
+    assert!(resumed.handle(":state merge state.json").is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_multi_line_input() {
+    use lamina::repl::is_incomplete;
+
+    assert!(!is_incomplete("(+ 1 2)"));
+    assert!(!is_incomplete("x"));
+    assert!(is_incomplete("(define (f x)"));
+    assert!(is_incomplete("(define (f x)\n  (* x"));
+    assert!(!is_incomplete("(define (f x)\n  (* x 2))"));
+    assert!(is_incomplete("(display \"(unclosed"));
+    assert!(!is_incomplete("(display \")\")"));
+    assert!(!is_incomplete("(display \"\\\"(\")"));
+    assert!(!is_incomplete("(list #\\( 1)"));
+    assert!(!is_incomplete("(+ 1 2) ; (comment"));
+    assert!(is_incomplete("#i{"));
+    // Too many closing parentheses are for the evaluator to report
+    assert!(!is_incomplete("(+ 1 2))"));
+
+    let mut session = Session::new();
+    session.handle("(define (f x)\n  (* x 2))").unwrap();
+    assert_eq!(session.handle("(f\n 21)").unwrap(), "42");
+}
+
+#[test]
+fn test_env_and_pretty_printing() {
+    let mut session = Session::new();
+    assert_eq!(session.handle(":env").unwrap(), "(no definitions)");
+    session.handle("(define y '(a b))").unwrap();
+    session.handle("(define x 1)").unwrap();
+    assert_eq!(session.handle(":env").unwrap(), "x = 1\ny = (a b)");
+    session.handle(":target evm").unwrap();
+    assert_eq!(session.handle(":env").unwrap(), "x = 1\ny = (a b)");
+
+    // Results too wide for a line put each element on its own
+    let long = format!("\"{}\"", "a".repeat(40));
+    assert_eq!(
+        session
+            .handle(&format!("(list 1 (list {} {}) 2)", long, long))
+            .unwrap(),
+        format!("(1\n ({}\n  {})\n 2)", long, long)
+    );
+    assert_eq!(session.handle("(list 1 2 3)").unwrap(), "(1 2 3)");
+}
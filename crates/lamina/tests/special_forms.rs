@@ -22,20 +22,62 @@ fn test_assignment_expressions() {
 
 #[test]
 fn test_let_expressions() {
-    assert_eq!(execute("(let ((x 1) (y 2)) (+ x y))").unwrap(), "3.0");
+    assert_eq!(execute("(let ((x 1) (y 2)) (+ x y))").unwrap(), "3");
 }
 
 #[test]
 fn test_let_star_expressions() {
+    assert_eq!(execute("(let* ((x 1) (y (+ x 1))) (+ x y))").unwrap(), "3");
+}
+
+#[test]
+fn test_letrec_expressions() {
+    assert_eq!(execute("(letrec ((x 1) (y 2)) (+ x y))").unwrap(), "3");
+}
+
+#[test]
+fn test_named_let() {
     assert_eq!(
-        execute("(let* ((x 1) (y (+ x 1))) (+ x y))").unwrap(),
-        "3.0"
+        execute("(let loop ((i 0) (acc '())) (if (= i 3) acc (loop (+ i 1) (cons i acc))))")
+            .unwrap(),
+        "(2 1 0)"
     );
+    // The name is bound in the body, not in the initializers
+    execute("(define count 10)").unwrap();
+    assert_eq!(
+        execute("(let count ((n count)) (if (= n 0) 'done (count (- n 1))))").unwrap(),
+        "done"
+    );
+    // Loops run in constant space
+    assert_eq!(
+        execute("(let loop ((n 100000)) (if (= n 0) 'done (loop (- n 1))))").unwrap(),
+        "done"
+    );
+    assert!(execute("(let loop ((i)) i)").is_err());
 }
 
 #[test]
-fn test_letrec_expressions() {
-    assert_eq!(execute("(letrec ((x 1) (y 2)) (+ x y))").unwrap(), "3.0");
+fn test_do_loops() {
+    assert_eq!(execute("(do ((i 0 (+ i 1))) ((= i 10) i))").unwrap(), "10");
+    // A variable without a step keeps its value; the commands run each time
+    assert_eq!(
+        execute(
+            "(let ((seen '()))
+               (do ((i 3 (- i 1)) (limit 0))
+                   ((= i limit) seen)
+                 (set! seen (cons i seen))))"
+        )
+        .unwrap(),
+        "(1 2 3)"
+    );
+    // The steps see the variables' old values
+    assert_eq!(
+        execute("(do ((a 1 b) (b 2 a) (n 0 (+ n 1))) ((= n 3) (list a b)))").unwrap(),
+        "(2 1)"
+    );
+    assert_eq!(execute("(do ((i 0 (+ i 1))) ((= i 3)))").unwrap(), "");
+    assert!(execute("(do ((i 0 (+ i 1))))").is_err());
+    assert!(execute("(do ((i)) (#t))").is_err());
 }
 
 #[test]
@@ -44,3 +86,699 @@ fn test_quoting() {
     assert_eq!(execute("'(1 2 3)").unwrap(), "(1 2 3)");
     assert_eq!(execute("(quote hello)").unwrap(), "hello");
 }
+
+#[test]
+fn test_compile_time_forms() {
+    use lamina::expand::expand;
+    use lamina::lexer;
+    use lamina::parser;
+
+    // The interpreter has a single phase, so the forms behave like their runtime versions
+    assert_eq!(
+        execute("(begin (define-for-syntax base 40) (+ (compile-time base) 2))").unwrap(),
+        "42"
+    );
+
+    let source = "(begin (define-for-syntax items (list 1 2)) (define x (compile-time (cons 0 items))) (f (compile-time (* 2 21))))";
+    let expr = parser::parse(&lexer::lex(source).unwrap()).unwrap();
+    assert_eq!(
+        expand(&expr).unwrap().to_string(),
+        "(begin (define x (quote (0 1 2))) (f 42))"
+    );
+
+    let source = "(compile-time (lambda (x) x))";
+    let expr = parser::parse(&lexer::lex(source).unwrap()).unwrap();
+    assert!(expand(&expr).is_err());
+}
+
+#[test]
+fn test_cond_expand() {
+    use lamina::expand::expand_for;
+    use lamina::lexer;
+    use lamina::parser;
+    use lamina::targets::{self, EVM};
+
+    assert_eq!(
+        execute("(cond-expand (evm 1) ((and lamina (not evm)) 2) (else 3))").unwrap(),
+        "2"
+    );
+    assert_eq!(
+        execute("(cond-expand ((or evm interpreter) (define picked 'both) picked))").unwrap(),
+        "both"
+    );
+    assert_eq!(execute("(cond-expand (evm 1))").unwrap(), "");
+    assert_eq!(execute("(features)").unwrap(), "(lamina r7rs interpreter)");
+    assert!(execute("(cond-expand ((library (scheme base)) 1))").is_err());
+
+    // Compiling for the EVM keeps its clause, spliced in place of the form
+    let source =
+        "(begin (cond-expand (interpreter (define (log x) (display x))) (else)) (define (f) (cond-expand (evm 42) (else (display 0)))))";
+    let expr = parser::parse(&lexer::lex(source).unwrap()).unwrap();
+    assert_eq!(
+        expand_for(&expr, &EVM).unwrap().to_string(),
+        "(begin (define (f) 42))"
+    );
+
+    // Outside one, interpreter-only primitives stop the compilation
+    let source = "(begin (define (f) (display 1) 42))";
+    let expr = parser::parse(&lexer::lex(source).unwrap()).unwrap();
+    assert_eq!(
+        expand_for(&expr, &EVM).unwrap_err().to_string(),
+        "Compilation error: display is not available on target evm, guard with cond-expand"
+    );
+
+    // A program may define its own procedure of the same name
+    let source =
+        "(begin (define (write key value) (storage-store key value)) (define (f) (write 0 1)))";
+    let expr = parser::parse(&lexer::lex(source).unwrap()).unwrap();
+    assert!(expand_for(&expr, &EVM).is_ok());
+
+    // Checking the source points at the call
+    let error = targets::check_source("(define (f)\n  (newline)\n  1)", &EVM).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Compilation error: line 2, column 3: newline is not available on target evm, guard with cond-expand"
+    );
+    assert!(targets::check_source("(define (f) '(display 1))", &EVM).is_ok());
+}
+
+#[test]
+fn test_allow_annotations() {
+    // Warning annotations are for the compilers; the interpreter skips them
+    assert_eq!(
+        execute("(begin #:allow storage-slot-reuse (define x 5) #:allow (a b) (+ x 1))").unwrap(),
+        "6"
+    );
+}
+
+#[test]
+fn test_case() {
+    assert_eq!(
+        execute("(case (+ 1 2) ((1 2) 'low) ((3 4) 'mid) (else 'high))").unwrap(),
+        "mid"
+    );
+    assert_eq!(
+        execute("(case 9 ((1 2) 'low) (else 'high))").unwrap(),
+        "high"
+    );
+    assert_eq!(execute("(case 'b ((a) 1) ((b c) 2))").unwrap(), "2");
+    assert_eq!(execute("(case 5 ((1) 1))").unwrap(), "");
+}
+
+#[test]
+fn test_define_internal() {
+    // Visibility is for the compilers; the interpreter defines the function as usual
+    assert_eq!(
+        execute("(begin (define-internal double (lambda (x) (* x 2))) (double 21))").unwrap(),
+        "42"
+    );
+}
+
+#[test]
+fn test_undefined_variable_suggestions() {
+    let err = execute("(car (lisst 1 2))").unwrap_err();
+    assert!(err.contains("Undefined variable: lisst (did you mean list"));
+
+    let err = execute("(let ((total 1)) (set! totl 2))").unwrap_err();
+    assert!(err.contains("Undefined variable: totl (did you mean total"));
+
+    // Nothing within two edits, so no suggestion
+    let err = execute("frobnicate-everything").unwrap_err();
+    assert!(err.ends_with("Undefined variable: frobnicate-everything"));
+
+    // A short name only gets names one edit away, and never #t, #f or an
+    // operator such as +
+    let err = execute("qq").unwrap_err();
+    assert!(err.ends_with("Undefined variable: qq"));
+    let err = execute("(let ((count 1)) cnt)").unwrap_err();
+    assert!(err.ends_with("Undefined variable: cnt"));
+    let err = execute("(let ((tally 1)) tallly)").unwrap_err();
+    assert!(err.contains("(did you mean tally?)"));
+}
+
+#[test]
+fn test_delimited_continuations() {
+    // k is the rest of the reset body, and can be called more than once
+    assert_eq!(
+        execute("(+ 1 (reset (+ 10 (shift k (k (k 100))))))").unwrap(),
+        "121"
+    );
+    // Not calling k discards the rest of the reset body
+    assert_eq!(execute("(reset (+ 1 (shift k 5)))").unwrap(), "5");
+    // k outlives the reset that delimited it
+    assert_eq!(
+        execute("(begin (define saved #f) (reset (* 2 (shift k (set! saved k)))) (saved 10))")
+            .unwrap(),
+        "20"
+    );
+
+    let err = execute("(+ 1 (shift k (k 1)))").unwrap_err();
+    assert!(err.contains("shift without an enclosing reset"));
+}
+
+#[test]
+fn test_call_cc() {
+    // Escaping discards the rest of the computation
+    assert_eq!(
+        execute("(+ 1 (call/cc (lambda (k) (+ 10 (k 5)))))").unwrap(),
+        "6"
+    );
+    assert_eq!(
+        execute("(call-with-current-continuation (lambda (k) 7))").unwrap(),
+        "7"
+    );
+    // Escaping out of a procedure a native procedure calls back into
+    assert_eq!(
+        execute(
+            "(call/cc (lambda (exit)
+               (vector-for-each (lambda (x) (if (< x 0) (exit x) #f)) #(54 0 -3 19))
+               #t))"
+        )
+        .unwrap(),
+        "-3"
+    );
+    // Handlers don't see the escape
+    assert_eq!(
+        execute("(call/cc (lambda (k) (guard (e (#t 'caught)) (k 'escaped))))").unwrap(),
+        "escaped"
+    );
+    // Re-entering a continuation after call/cc has returned
+    assert_eq!(
+        execute(
+            "(begin
+               (define n 0)
+               (define saved #f)
+               (define result (+ 100 (call/cc (lambda (k) (set! saved k) 0))))
+               (set! n (+ n 1))
+               (if (< n 3) (saved n) (list n result)))"
+        )
+        .unwrap(),
+        "(3 102)"
+    );
+
+    let err = execute("(call/cc)").unwrap_err();
+    assert!(err.contains("Wrong number of arguments to call/cc"));
+}
+
+#[test]
+fn test_dynamic_wind() {
+    assert_eq!(
+        execute(
+            "(begin
+               (define path '())
+               (define (note x) (set! path (cons x path)))
+               (list (dynamic-wind (lambda () (note 'in))
+                                   (lambda () (note 'body) 'value)
+                                   (lambda () (note 'out)))
+                     path))"
+        )
+        .unwrap(),
+        "(value (out body in))"
+    );
+    // Escaping with a continuation runs the after thunk
+    assert_eq!(
+        execute(
+            "(begin
+               (set! path '())
+               (call/cc (lambda (k)
+                 (dynamic-wind (lambda () (note 'in))
+                               (lambda () (k 'escaped))
+                               (lambda () (note 'out)))))
+               path)"
+        )
+        .unwrap(),
+        "(out in)"
+    );
+    // Re-entering runs the before thunk again
+    assert_eq!(
+        execute(
+            "(begin
+               (set! path '())
+               (define again #f)
+               (define rounds 0)
+               (dynamic-wind (lambda () (note 'in))
+                             (lambda () (call/cc (lambda (k) (set! again k))) (note 'body))
+                             (lambda () (note 'out)))
+               (set! rounds (+ rounds 1))
+               (if (< rounds 2) (again #f) path))"
+        )
+        .unwrap(),
+        "(out body in out body in)"
+    );
+    // So does raising past it to a guard
+    assert_eq!(
+        execute(
+            "(begin
+               (set! path '())
+               (guard (e (#t (note e)))
+                 (dynamic-wind (lambda () (note 'in))
+                               (lambda () (raise 'boom))
+                               (lambda () (note 'out))))
+               path)"
+        )
+        .unwrap(),
+        "(boom out in)"
+    );
+}
+
+#[test]
+fn test_multiple_values() {
+    assert_eq!(
+        execute("(call-with-values (lambda () (values 4 5)) (lambda (a b) b))").unwrap(),
+        "5"
+    );
+    assert_eq!(
+        execute("(call-with-values (lambda () (values 1 2)) list)").unwrap(),
+        "(1 2)"
+    );
+    assert_eq!(
+        execute("(call-with-values (lambda () (values)) (lambda args (null? args)))").unwrap(),
+        "#t"
+    );
+    // One value is just that value
+    assert_eq!(execute("(+ 1 (values 2))").unwrap(), "3");
+    assert_eq!(
+        execute("(call-with-values (lambda () 3) (lambda (x) (* x 2)))").unwrap(),
+        "6"
+    );
+    // A continuation called with several arguments returns them as values
+    assert_eq!(
+        execute("(call-with-values (lambda () (call/cc (lambda (k) (k 1 2 3)))) list)").unwrap(),
+        "(1 2 3)"
+    );
+}
+
+#[test]
+fn test_and_or_short_circuit() {
+    // Evaluation stops at the first value that decides the form
+    assert_eq!(execute("(or 1 (car '()))").unwrap(), "1");
+    assert_eq!(execute("(and #f (car '()))").unwrap(), "#f");
+    assert_eq!(execute("(or (memq 'b '(a b c)) (/ 3 0))").unwrap(), "(b c)");
+    assert_eq!(execute("(and)").unwrap(), "#t");
+    assert_eq!(execute("(or)").unwrap(), "#f");
+    // The last expression is in tail position
+    execute("(define (down n) (or (= n 0) (down (- n 1))))").unwrap();
+    assert_eq!(execute("(down 100000)").unwrap(), "#t");
+}
+
+#[test]
+fn test_truthiness() {
+    // Everything but #f is true, 0 and the empty list included
+    assert_eq!(execute("(if 0 'yes 'no)").unwrap(), "yes");
+    assert_eq!(execute("(if '() 'yes 'no)").unwrap(), "yes");
+    assert_eq!(execute("(if #f 'yes 'no)").unwrap(), "no");
+    assert_eq!(execute("(cond (#f 'a) (\"\" 'b) (else 'c))").unwrap(), "b");
+    // A clause of only a test evaluates to its value
+    assert_eq!(execute("(cond (#f 1) (7))").unwrap(), "7");
+    assert_eq!(execute("(not 0)").unwrap(), "#f");
+    assert_eq!(execute("(and 1 #f 2)").unwrap(), "#f");
+    assert_eq!(execute("(and 1 '() 2)").unwrap(), "2");
+    assert_eq!(execute("(or #f 0 1)").unwrap(), "0");
+    assert_eq!(execute("(when 0 'ran)").unwrap(), "ran");
+    assert_eq!(execute("(when #f 'ran)").unwrap(), "");
+    assert_eq!(execute("(unless #f 'first 'last)").unwrap(), "last");
+    assert_eq!(execute("(unless 0 'ran)").unwrap(), "");
+    // Guard clauses test the same way
+    assert_eq!(
+        execute("(guard (e (e 'caught)) (raise 'boom))").unwrap(),
+        "caught"
+    );
+    assert_eq!(
+        execute("(guard (e ((car e))) (raise (list 5)))").unwrap(),
+        "5"
+    );
+    assert_eq!(execute("(assert 0)").unwrap(), "");
+}
+
+#[test]
+fn test_exceptions_on_the_stack() {
+    // Handlers see the raised value itself
+    assert_eq!(execute("(guard (e (#t e)) (raise 42))").unwrap(), "42");
+    // A guard without a matching clause raises again to the next handler
+    assert_eq!(
+        execute("(guard (e (#t (list 'outer e))) (guard (e (#f 'no)) (raise 'inner)))").unwrap(),
+        "(outer inner)"
+    );
+    // A continuable raise returns the handler's value where it was raised
+    assert_eq!(
+        execute("(with-exception-handler (lambda (e) 10) (lambda () (+ 1 (raise-continuable 5))))")
+            .unwrap(),
+        "11"
+    );
+
+    let err = execute("(raise 'boom)").unwrap_err();
+    assert!(err.contains("Exception: Symbol(boom)"));
+}
+
+#[test]
+fn test_generators() {
+    assert_eq!(
+        execute("(generator->list (generator (yield 1) (yield 2) (yield 3)))").unwrap(),
+        "(1 2 3)"
+    );
+    // yield works from procedures the generator body calls
+    assert_eq!(
+        execute(
+            "(begin
+               (define walk
+                 (lambda (tree)
+                   (if (pair? tree)
+                       (begin (walk (car tree)) (walk (cdr tree)))
+                       (if (null? tree) #f (yield tree)))))
+               (generator->list (generator (walk '((a b) (c (d)) e)))))"
+        )
+        .unwrap(),
+        "(a b c d e)"
+    );
+    // Infinite generators are resumed lazily
+    assert_eq!(
+        execute(
+            "(begin
+               (define count-from
+                 (lambda (i) (begin (yield i) (count-from (+ i 1)))))
+               (generator->list (generator (count-from 1)) 3))"
+        )
+        .unwrap(),
+        "(1 2 3)"
+    );
+    assert_eq!(
+        execute(
+            "(begin
+               (define total 0)
+               (generator-for-each (lambda (x) (set! total (+ total x)))
+                                   (generator (yield 1) (yield 2)))
+               total)"
+        )
+        .unwrap(),
+        "3"
+    );
+    assert_eq!(
+        execute("(begin (define g (generator (yield 1))) (g) (eof-object? (g)))").unwrap(),
+        "#t"
+    );
+
+    let err = execute("(yield 1)").unwrap_err();
+    assert!(err.contains("yield outside a generator"));
+}
+
+#[test]
+fn test_parameterize() {
+    assert_eq!(
+        execute(
+            "(begin
+               (define p (make-parameter 10))
+               (list (p) (parameterize ((p 20)) (p)) (p)))"
+        )
+        .unwrap(),
+        "(10 20 10)"
+    );
+    // The converter runs on the initial value and on each parameterized one
+    assert_eq!(
+        execute(
+            "(begin
+               (define p (make-parameter 1 (lambda (x) (* x 2))))
+               (list (p) (parameterize ((p 5)) (p))))"
+        )
+        .unwrap(),
+        "(2 10)"
+    );
+    // A raise out of the body restores the old value
+    assert_eq!(
+        execute(
+            "(begin
+               (define p (make-parameter 'outer))
+               (guard (e (#t (p)))
+                 (parameterize ((p 'inner)) (raise 'oops))))"
+        )
+        .unwrap(),
+        "outer"
+    );
+
+    let err = execute("(parameterize ((car 1)) 2)").unwrap_err();
+    assert!(err.contains("not a parameter"));
+}
+
+#[test]
+fn test_define_enum() {
+    let define = "(define-enum Phase (Open Closed Settled))";
+    assert_eq!(
+        execute(&format!(
+            "(begin {define} (list Open (Phase? Closed) (Phase? 'Other) (Phase->integer Settled) (integer->Phase 1)))"
+        ))
+        .unwrap(),
+        "(Open #t #f 2 Closed)"
+    );
+    assert_eq!(
+        execute(&format!(
+            "(begin {define}
+               (case (integer->Phase 0)
+                 ((Open) 'bidding)
+                 ((Closed Settled) 'done)))"
+        ))
+        .unwrap(),
+        "bidding"
+    );
+
+    // A case without else has to name every variant
+    let err = execute(&format!(
+        "(begin {define} (case Open ((Open) 1) ((Closed) 2)))"
+    ))
+    .unwrap_err();
+    assert!(err.contains("not exhaustive: missing Settled"));
+    assert_eq!(
+        execute(&format!("(begin {define} (case Open ((Open) 1) (else 2)))")).unwrap(),
+        "1"
+    );
+
+    assert!(execute(&format!("(begin {define} (integer->Phase 3))"))
+        .unwrap_err()
+        .contains("no variant 3"));
+    assert!(execute("(define-enum Phase (Open Open))").is_err());
+}
+
+#[test]
+fn test_assertions_and_contracts() {
+    assert_eq!(
+        execute("(begin (assert (> 2 1) \"ordered\") 'ok)").unwrap(),
+        "ok"
+    );
+    let err = execute("(assert (> 1 2) \"1 is not above 2\")").unwrap_err();
+    assert!(err.contains("Assertion failed: 1 is not above 2"));
+    // Without a message the test is reported
+    let err = execute("(assert (> 1 2))").unwrap_err();
+    assert!(err.contains("Assertion failed: (> 1 2)"));
+    // A failed assertion can be handled like any other error
+    assert_eq!(
+        execute("(guard (e (#t 'handled)) (assert #f))").unwrap(),
+        "handled"
+    );
+
+    let define = "(define-with-contract (withdraw balance amount)
+                    #:requires (> amount 0)
+                    #:requires (<= amount balance)
+                    #:ensures (>= result 0)
+                    (- balance amount))";
+    assert_eq!(
+        execute(&format!("(begin {define} (withdraw 10 4))")).unwrap(),
+        "6"
+    );
+    let err = execute(&format!("(begin {define} (withdraw 10 0))")).unwrap_err();
+    assert!(err.contains("withdraw: requires (> amount 0)"));
+
+    // The postcondition sees the return value as result
+    let err = execute(
+        "(begin
+           (define-with-contract (broken x) #:ensures (> result x) (- x 1))
+           (broken 5))",
+    )
+    .unwrap_err();
+    assert!(err.contains("broken: ensures (> result x)"));
+}
+
+#[test]
+fn test_syntax_rules() {
+    // Variables bound by the template don't capture the caller's
+    let swap = "(define-syntax swap!
+                  (syntax-rules ()
+                    ((_ a b) (let ((tmp a)) (begin (set! a b) (set! b tmp))))))";
+    assert_eq!(
+        execute(&format!(
+            "(begin {swap} (define tmp 1) (define y 2) (swap! tmp y) (list tmp y))"
+        ))
+        .unwrap(),
+        "(2 1)"
+    );
+    let my_or = "(define-syntax my-or
+                   (syntax-rules ()
+                     ((_) #f)
+                     ((_ e) e)
+                     ((_ e r ...) (let ((t e)) (if t t (my-or r ...))))))";
+    assert_eq!(
+        execute(&format!("(begin {my_or} (define t 5) (my-or #f t))")).unwrap(),
+        "5"
+    );
+    assert_eq!(execute(&format!("(begin {my_or} (my-or))")).unwrap(), "#f");
+
+    // Nested ellipses, and one following another to flatten
+    assert_eq!(
+        execute(
+            "(begin
+               (define-syntax lets
+                 (syntax-rules () ((_ ((n v) ...) e) ((lambda (n ...) e) v ...))))
+               (lets ((p 1) (q 2)) (list p q)))"
+        )
+        .unwrap(),
+        "(1 2)"
+    );
+    assert_eq!(
+        execute(
+            "(begin
+               (define-syntax flat (syntax-rules () ((_ (a ...) ...) '(a ... ...))))
+               (flat (1 2) (3) ()))"
+        )
+        .unwrap(),
+        "(1 2 3)"
+    );
+    // Patterns may continue after an ellipsis and match inside vectors
+    assert_eq!(
+        execute("(begin (define-syntax last (syntax-rules () ((_ a ... z) 'z))) (last 1 2 3))")
+            .unwrap(),
+        "3"
+    );
+    assert_eq!(
+        execute(
+            "(begin
+               (define-syntax vec (syntax-rules () ((_ #(a ...)) (list a ...))))
+               (vec #(1 2 3)))"
+        )
+        .unwrap(),
+        "(1 2 3)"
+    );
+
+    // Literals only match themselves
+    let my_if = "(define-syntax my-if
+                   (syntax-rules (then else) ((_ c then t else e) (if c t e))))";
+    assert_eq!(
+        execute(&format!("(begin {my_if} (my-if #f then 1 else 2))")).unwrap(),
+        "2"
+    );
+    let err = execute(&format!("(begin {my_if} (my-if #f 1 2))")).unwrap_err();
+    assert!(err.contains("my-if"), "{err}");
+
+    // A custom ellipsis, and (... ...) to write one in a template
+    assert_eq!(
+        execute(
+            "(begin
+               (define-syntax my-list (syntax-rules ::: () ((_ a :::) (list a :::))))
+               (my-list 1 2))"
+        )
+        .unwrap(),
+        "(1 2)"
+    );
+    assert_eq!(
+        execute(
+            "(begin
+               (define-syntax be-like-begin
+                 (syntax-rules ()
+                   ((_ name)
+                    (define-syntax name
+                      (syntax-rules () ((name e (... ...)) (begin e (... ...))))))))
+               (be-like-begin sequence)
+               (sequence 1 2 3))"
+        )
+        .unwrap(),
+        "3"
+    );
+
+    // Local macros, and bindings that shadow a macro
+    assert_eq!(
+        execute("(let-syntax ((double (syntax-rules () ((_ x) (* x 2))))) (double 21))").unwrap(),
+        "42"
+    );
+    assert_eq!(
+        execute(&format!("(begin {swap} (let ((swap! 3)) swap!))")).unwrap(),
+        "3"
+    );
+
+    // Macros are exported from libraries like procedures
+    assert_eq!(
+        execute(
+            "(begin
+               (define-library (test macros)
+                 (export unless*)
+                 (begin
+                   (define-syntax unless*
+                     (syntax-rules () ((_ c e) (if c #f e))))))
+               (import (test macros))
+               (unless* #f 'ran))"
+        )
+        .unwrap(),
+        "ran"
+    );
+}
+
+#[test]
+fn test_quasiquote() {
+    execute("(define qq-x 5)").unwrap();
+    execute("(define qq-list '(a b c))").unwrap();
+
+    // The reader abbreviations stand for the quasiquote forms
+    assert_eq!(
+        execute("'(`a ,b ,@c)").unwrap(),
+        "((quasiquote a) (unquote b) (unquote-splicing c))"
+    );
+
+    assert_eq!(execute("`(1 2 3)").unwrap(), "(1 2 3)");
+    assert_eq!(execute("`(1 ,qq-x ,@qq-list)").unwrap(), "(1 5 a b c)");
+    assert_eq!(
+        execute("`(,@qq-list 4 ,@'() ,@qq-list)").unwrap(),
+        "(a b c 4 a b c)"
+    );
+    assert_eq!(
+        execute("`((nested ,qq-x) (+ 1 2))").unwrap(),
+        "((nested 5) (+ 1 2))"
+    );
+    assert_eq!(execute("`#(1 ,qq-x ,@qq-list)").unwrap(), "#(1 5 a b c)");
+    assert_eq!(execute("`,qq-x").unwrap(), "5");
+
+    // Only unquotes at the outermost level are evaluated
+    assert_eq!(
+        execute("`(a `(b ,(c ,qq-x)))").unwrap(),
+        "(a (quasiquote (b (unquote (c 5)))))"
+    );
+    assert_eq!(
+        execute("`(a `(b ,,qq-x ,@,@qq-list))").unwrap(),
+        "(a (quasiquote (b (unquote 5) (unquote-splicing a b c))))"
+    );
+
+    // The template doesn't depend on what cons or append are bound to
+    assert_eq!(
+        execute("(let ((cons list) (append list)) `(1 ,qq-x ,@qq-list))").unwrap(),
+        "(1 5 a b c)"
+    );
+
+    assert!(execute("`(1 ,@qq-x)").is_err());
+    assert!(execute("`,@qq-list").is_err());
+    assert!(execute("(unquote qq-x)").is_err());
+}
+
+#[test]
+fn test_deeply_nested_quasiquote() {
+    const DEPTH: usize = 100_000;
+    execute("(define qq-deep-x 5)").unwrap();
+    execute(
+        "(define (qq-depth x) \
+           (let loop ((x x) (n 0)) (if (pair? x) (loop (car x) (+ n 1)) (list n x))))",
+    )
+    .unwrap();
+
+    // Built without a recursion per level of nesting
+    let literal = format!("(qq-depth `{}x{})", "(".repeat(DEPTH), ")".repeat(DEPTH));
+    assert_eq!(execute(&literal).unwrap(), format!("({} x)", DEPTH));
+    let unquoted = format!(
+        "(qq-depth `{},qq-deep-x{})",
+        "(".repeat(DEPTH),
+        ")".repeat(DEPTH)
+    );
+    assert_eq!(execute(&unquoted).unwrap(), format!("({} 5)", DEPTH));
+}
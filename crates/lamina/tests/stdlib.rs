@@ -0,0 +1,112 @@
+#![cfg(feature = "stdlib")]
+
+use std::time::{Duration, Instant};
+
+use lamina::embed::Interpreter;
+use lamina::evaluator::stdlib::{self, LIBRARIES};
+
+// Generous for debug builds on a loaded machine; a regression that evaluates
+// the library sources at startup, or makes loading them quadratic, blows it
+const STARTUP_BUDGET: Duration = Duration::from_millis(50);
+const LOAD_BUDGET: Duration = Duration::from_millis(200);
+
+fn name(parts: &[&str]) -> Vec<String> {
+    parts.iter().map(|part| part.to_string()).collect()
+}
+
+#[test]
+fn test_stdlib_list() {
+    let interpreter = Interpreter::builder().build();
+    interpreter.eval("(import (lamina list))").unwrap();
+    let eval = |code: &str| interpreter.eval(code).unwrap().to_string();
+
+    assert_eq!(eval("(reverse '(1 2 3))"), "(3 2 1)");
+    assert_eq!(eval("(filter (lambda (x) (> x 1)) '(1 2 3))"), "(2 3)");
+    assert_eq!(eval("(remove (lambda (x) (> x 1)) '(1 2 3))"), "(1)");
+    assert_eq!(eval("(list-ref '(a b c) 2)"), "c");
+    assert_eq!(eval("(last '(a b c))"), "c");
+    assert_eq!(eval("(fold-right cons '() '(1 2))"), "(1 2)");
+    assert_eq!(
+        eval("(fold-left (lambda (acc x) (cons x acc)) '() '(1 2))"),
+        "(2 1)"
+    );
+    assert_eq!(eval("(find (lambda (x) (> x 1)) '(1 2 3))"), "2");
+    assert_eq!(eval("(any (lambda (x) (> x 5)) '(1 2 3))"), "#f");
+    assert_eq!(eval("(every (lambda (x) (> x 0)) '(1 2 3))"), "#t");
+}
+
+#[test]
+fn test_stdlib_alist() {
+    let interpreter = Interpreter::builder().build();
+    interpreter.eval("(import (lamina alist))").unwrap();
+    interpreter
+        .eval("(define prices (list (cons 1 'low) (cons 2 'high)))")
+        .unwrap();
+    let eval = |code: &str| interpreter.eval(code).unwrap().to_string();
+
+    assert_eq!(eval("(alist-ref 2 prices = 'none)"), "high");
+    assert_eq!(eval("(alist-ref 3 prices = 'none)"), "none");
+    assert_eq!(eval("(alist-keys (alist-set 2 'mid prices =))"), "(2 1)");
+    assert_eq!(eval("(alist-values (alist-delete 1 prices =))"), "(high)");
+    // Importing (lamina alist) doesn't bind what it imports itself
+    assert!(interpreter.eval("(reverse '(1 2))").is_err());
+}
+
+#[test]
+fn test_stdlib_loads_lazily() {
+    let interpreter = Interpreter::builder().build();
+    assert!(interpreter.library(&name(&["lamina", "list"])).is_none());
+
+    // (lamina alist) imports (lamina list), which loads first
+    interpreter.eval("(import (lamina alist))").unwrap();
+    assert!(interpreter.library(&name(&["lamina", "list"])).is_some());
+    assert!(interpreter.library(&name(&["lamina", "alist"])).is_some());
+
+    let err = interpreter.eval("(import (lamina nothing))").unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Unknown library: (lamina nothing)"));
+}
+
+#[test]
+fn test_stdlib_load_order() {
+    // Every library imports only the ones before it
+    for (index, library) in LIBRARIES.iter().enumerate() {
+        let interpreter = Interpreter::builder().build();
+        stdlib::load(&interpreter.environment(), library).unwrap();
+        for other in &LIBRARIES[index + 1..] {
+            assert!(
+                interpreter.library(&name(other.name)).is_none(),
+                "({}) loads ({}), which comes after it",
+                library.name.join(" "),
+                other.name.join(" ")
+            );
+        }
+    }
+
+    let interpreter = Interpreter::builder().build();
+    stdlib::load_all(&interpreter.environment()).unwrap();
+    for library in LIBRARIES {
+        assert!(interpreter.library(&name(library.name)).is_some());
+    }
+}
+
+#[test]
+fn test_stdlib_startup_budget() {
+    // Warm up, so one-time costs don't count against startup
+    Interpreter::builder().build();
+
+    let runs = 10;
+    let start = Instant::now();
+    for _ in 0..runs {
+        Interpreter::builder().build();
+    }
+    let startup = start.elapsed() / runs;
+    assert!(startup < STARTUP_BUDGET, "startup took {:?}", startup);
+
+    let interpreter = Interpreter::builder().build();
+    let start = Instant::now();
+    stdlib::load_all(&interpreter.environment()).unwrap();
+    let load = start.elapsed();
+    assert!(load < LOAD_BUDGET, "loading the stdlib took {:?}", load);
+}
@@ -0,0 +1,256 @@
+use lamina::coverage::lcov_report;
+use lamina::evm::{GasSchedule, Word};
+use lamina::testing::fuzz::{shrink, Rng};
+use lamina::testing::{run_source, Outcome, Target, TestOptions};
+use lamina::value::{NumberKind, Value};
+
+const CONTRACT: &str = r#"
+(define set-value (lambda (x) (storage-store 0 x)))
+(define get-value (lambda () (storage-load 0)))
+
+(define-test "starts empty" (lambda () (= (get-value) 0)))
+
+(define-property "stores any value"
+  (lambda (x) (begin (set-value x) (= (get-value) x))))
+
+(define-property "value stays zero"
+  (lambda (x) (begin (set-value x) (= (get-value) 0))))
+
+(define-property "reverts are discarded"
+  (lambda (x) (if (> x 10) (revert "too large") #t)))
+"#;
+
+fn evm_options(fuzz: bool) -> TestOptions {
+    TestOptions {
+        target: Target::Evm,
+        fuzz,
+        runs: 50,
+        seed: Some(42),
+        coverage: false,
+        trace: false,
+        gas_schedule: GasSchedule::default(),
+    }
+}
+
+#[test]
+fn test_unit_tests_run_without_fuzz() {
+    let report = run_source(CONTRACT, &evm_options(false)).unwrap();
+
+    assert_eq!(report.results.len(), 4);
+    assert_eq!(report.results[0].outcome, Outcome::Passed);
+    assert_eq!(report.passed(), 1);
+    assert_eq!(report.skipped(), 3);
+}
+
+#[test]
+fn test_failing_property_is_shrunk() {
+    let report = run_source(CONTRACT, &evm_options(true)).unwrap();
+
+    let holds = &report.results[1];
+    assert_eq!(holds.outcome, Outcome::Passed);
+    assert_eq!(holds.runs, 50);
+
+    let broken = &report.results[2];
+    assert!(matches!(broken.outcome, Outcome::Failed(_)));
+    match broken.counterexample.as_deref() {
+        Some([Value::Number(NumberKind::Integer(1))]) => {}
+        other => panic!("Expected counterexample (1), got {:?}", other),
+    }
+
+    let reverting = &report.results[3];
+    assert_eq!(reverting.outcome, Outcome::Passed);
+    assert!(reverting.discarded > 0);
+}
+
+#[test]
+fn test_same_seed_replays_same_inputs() {
+    let mut a = Rng::new(7);
+    let mut b = Rng::new(7);
+    for _ in 0..20 {
+        assert_eq!(a.next_argument(), b.next_argument());
+    }
+}
+
+#[test]
+fn test_arguments_cover_full_words() {
+    let mut rng = Rng::new(7);
+    let arguments: Vec<Word> = (0..1_000).map(|_| rng.next_argument()).collect();
+    let top_bit = Word::ONE.shift_left(255);
+    let address_bits = Word::MAX.shift_right(96);
+
+    assert!(arguments.contains(&Word::MAX));
+    assert!(arguments.contains(&top_bit));
+    assert!(arguments
+        .iter()
+        .any(|&word| word > top_bit && word < Word::MAX));
+    assert!(arguments
+        .iter()
+        .any(|&word| word > Word::from_i64(i64::MAX) && word <= address_bits));
+}
+
+#[test]
+fn test_shrink_finds_boundary() {
+    let shrunk = shrink(vec![Word::from_i64(1 << 20), Word::from_i64(5)], |args| {
+        args[0] >= Word::from_i64(100)
+    });
+    assert_eq!(shrunk, vec![Word::from_i64(100), Word::ZERO]);
+
+    // A full-width word shrinks to the boundary too
+    let top_bit = Word::ONE.shift_left(255);
+    let shrunk = shrink(vec![Word::MAX], |args| args[0] >= top_bit);
+    assert_eq!(shrunk, vec![top_bit]);
+}
+
+#[test]
+fn test_storage_requires_evm_target() {
+    let report = run_source(CONTRACT, &TestOptions::default()).unwrap();
+    assert!(matches!(report.results[0].outcome, Outcome::Failed(_)));
+}
+
+#[test]
+fn test_coverage_reports_unexecuted_lines() {
+    let source = r#"
+(define sign (lambda (x)
+  (if (< x 0)
+      (- 0 1)
+      1)))
+(define unused (lambda (x)
+  (* x 2)))
+
+(define-test "positive" (lambda () (= (sign 5) 1)))
+"#;
+    let options = TestOptions {
+        coverage: true,
+        ..TestOptions::default()
+    };
+    let report = run_source(source, &options).unwrap();
+    assert_eq!(report.passed(), 1);
+
+    let coverage = report.coverage.unwrap();
+    // The untaken branch and the body of the uncalled procedure are missed,
+    // and the taken `1` counts though it is an atom
+    assert_eq!(coverage.missed_lines(), vec![4, 7]);
+    assert_eq!(coverage.lines_found(), 7);
+    assert_eq!(coverage.missed_branches(), vec![4]);
+
+    let lcov = lcov_report(&[("sign.lmn".to_string(), coverage)]);
+    assert!(lcov.starts_with("TN:\nSF:sign.lmn\nBRDA:4,0,0,0\nBRDA:5,0,1,1\nBRF:2\nBRH:1\n"));
+    assert!(lcov.contains("DA:4,0\n"));
+    assert!(lcov.contains("DA:5,1\n"));
+    assert!(lcov.contains("LF:7\nLH:5\nend_of_record\n"));
+
+    // Arms on the line of their `if` are still told apart as branches
+    let source = r#"
+(define (sign x) (if (> x 0) 'pos 0))
+(define (grade n) (cond ((> n 90) 'a) ((> n 50) 'b) (else 'c)))
+(define (kind n) (case n ((1) 'one) (else 'many)))
+(define-test "positive" (lambda () (eq? (sign 5) 'pos)))
+(define-test "grades" (lambda () (eq? (grade 70) 'b)))
+(define-test "kinds" (lambda () (eq? (kind 1) 'one)))
+"#;
+    let coverage = run_source(source, &options).unwrap().coverage.unwrap();
+    assert!(coverage.missed_lines().is_empty());
+    let taken: Vec<(usize, usize, usize)> = coverage
+        .branches
+        .iter()
+        .map(|branch| (branch.block, branch.arm, branch.hits))
+        .collect();
+    assert_eq!(
+        taken,
+        vec![
+            (0, 0, 1),
+            (0, 1, 0),
+            (1, 0, 0),
+            (1, 1, 1),
+            (1, 2, 0),
+            (2, 0, 1),
+            (2, 1, 0),
+        ]
+    );
+
+    // Coverage is off by default
+    let report = run_source(source, &TestOptions::default()).unwrap();
+    assert!(report.coverage.is_none());
+}
+
+#[test]
+fn test_failing_cases_are_traced() {
+    use lamina::evm::trace::{render, to_json_lines};
+
+    let source = r#"
+(define-test "deposit"
+  (lambda () (begin (storage-store 0 5) (storage-store 0 7) (= (storage-load 0) 8))))
+(define-test "empty" (lambda () (= (storage-load 1) 0)))
+"#;
+    let options = TestOptions {
+        trace: true,
+        ..evm_options(false)
+    };
+    let report = run_source(source, &options).unwrap();
+    assert!(report.results[1].trace.is_none());
+
+    let steps = report.results[0].trace.as_deref().unwrap();
+    let ops: Vec<(&str, u64)> = steps.iter().map(|step| (step.op, step.gas)).collect();
+    // Setting a zero slot, changing it again, then a warm read
+    assert_eq!(
+        ops,
+        vec![("SSTORE", 22100), ("SSTORE", 100), ("SLOAD", 100)]
+    );
+    assert_eq!(steps[2].gas_used, 22300);
+
+    let lines = to_json_lines(steps);
+    assert_eq!(
+        lines.lines().next().unwrap(),
+        r#"{"step":0,"op":"SSTORE","primitive":"storage-store","stack":["0","5"],"result":null,"gas":22100,"gasUsed":22100,"storage":{"slot":0,"before":"0","after":"5"}}"#
+    );
+    assert!(render(steps).contains("(storage-store 0 7)  [slot 0: 5 -> 7]"));
+
+    // Traces are only taken on the simulated EVM
+    let options = TestOptions {
+        trace: true,
+        ..TestOptions::default()
+    };
+    let report = run_source(source, &options).unwrap();
+    assert!(report.results[0].trace.is_none());
+}
+
+#[test]
+fn test_gas_schedules() {
+    use lamina::evm::Hardfork;
+
+    let cancun = GasSchedule::default();
+    assert_eq!(cancun.hardfork, Hardfork::Cancun);
+    assert_eq!(cancun.transient, Some(100));
+    let shanghai = GasSchedule::for_hardfork(Hardfork::Shanghai);
+    assert_eq!(shanghai.transient, None);
+    assert_eq!(shanghai.cold_sload, cancun.cold_sload);
+    // 32000, plus hashing and EIP-3860's charge for two words of init code
+    assert_eq!(shanghai.create2_cost(33), 32016);
+    assert_eq!(Hardfork::from_name("Shanghai"), Some(Hardfork::Shanghai));
+
+    let custom = GasSchedule::parse(
+        "# a chain with pricier storage\n[gas]\nhardfork = \"shanghai\"\ncold_sload = 2600\nsstore_set = 22100\n",
+    )
+    .unwrap();
+    assert_eq!(custom.hardfork, Hardfork::Shanghai);
+    assert_eq!(custom.cold_sload, 2600);
+    assert_eq!(custom.sstore_set, 22100);
+    assert_eq!(custom.warm_access, 100);
+    assert!(GasSchedule::parse("hardfork = \"london\"").is_err());
+    assert!(GasSchedule::parse("sload = 1").is_err());
+    assert!(GasSchedule::parse("cold_sload = lots").is_err());
+
+    // Traces are priced with the schedule of the run
+    let source = r#"
+(define-test "deposit" (lambda () (begin (storage-store 0 5) (= (storage-load 0) 8))))
+"#;
+    let options = TestOptions {
+        trace: true,
+        gas_schedule: custom,
+        ..evm_options(false)
+    };
+    let report = run_source(source, &options).unwrap();
+    let steps = report.results[0].trace.as_deref().unwrap();
+    let gas: Vec<u64> = steps.iter().map(|step| step.gas).collect();
+    assert_eq!(gas, vec![2600 + 22100, 100]);
+}
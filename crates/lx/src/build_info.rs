@@ -0,0 +1,205 @@
+// Build metadata: build-info.json
+//
+// A build with a backend writes `build-info.json` next to its artifacts,
+// recording what produced them:
+//
+//   {"format": 1,
+//    "compiler": {"name": "lx", "version": "0.1.0", "commit": "0d1f2e3a4b5c"},
+//    "target": "evm",
+//    "options": {"denyWarnings": false, "sizeBudget": null, "edition": 2025},
+//    "sources": [{"path": "src/token.lmn", "keccak256": "0x...",
+//                 "contract": "token",
+//                 "artifacts": [{"path": "token.json", "keccak256": "0x..."}, ...],
+//                 "timings": {"read": 0.4, "compile": 12.9, "write": 0.2}}]}
+//
+// Hashes are of the file contents, artifact paths are relative to the output
+// directory, and timings are in milliseconds. Building some of a project's
+// files keeps the entries of the others, as long as the target is the same.
+//
+// Rebuilding from the same sources with the same compiler and options gives
+// artifacts with the same hashes, so the file is enough to check a build is
+// reproducible. `lx deploy` uses it to refuse an artifact that was edited
+// after it was built, or whose source has changed since.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use lamina::encoding::encode_hex;
+use lamina::evm::keccak256;
+use lamina::json::{parse_json, Json};
+
+/// Name of the file in the output directory
+pub const FILE: &str = "build-info.json";
+
+/// Version of the layout of the file
+const FORMAT: usize = 1;
+
+/// How long each stage of building one file took
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Timings {
+    /// Reading, parsing and expanding the source
+    pub read: Duration,
+    pub compile: Duration,
+    /// Writing the artifacts
+    pub write: Duration,
+}
+
+/// What building one source file produced
+#[derive(Clone, Debug)]
+pub struct Record {
+    pub source: PathBuf,
+    pub source_hash: [u8; 32],
+    pub contract: String,
+    /// Artifact file names in the output directory, with their hashes
+    pub artifacts: Vec<(String, [u8; 32])>,
+    pub timings: Timings,
+}
+
+/// Build options as recorded, e.g. `("denyWarnings", Json::from(false))`
+pub type Options = Vec<(&'static str, Json)>;
+
+fn millis(duration: Duration) -> Json {
+    Json::from((duration.as_secs_f64() * 1e6).round() / 1e3)
+}
+
+fn hash(bytes: &[u8]) -> Json {
+    Json::from(encode_hex(&keccak256(bytes)))
+}
+
+impl Record {
+    fn to_json(&self) -> Json {
+        let artifacts = self
+            .artifacts
+            .iter()
+            .map(|(path, hash)| {
+                Json::object([
+                    ("path", Json::from(path.as_str())),
+                    ("keccak256", Json::from(encode_hex(hash))),
+                ])
+            })
+            .collect();
+        Json::object([
+            ("path", Json::from(self.source.display().to_string())),
+            ("keccak256", Json::from(encode_hex(&self.source_hash))),
+            ("contract", Json::from(self.contract.as_str())),
+            ("artifacts", Json::Array(artifacts)),
+            (
+                "timings",
+                Json::object([
+                    ("read", millis(self.timings.read)),
+                    ("compile", millis(self.timings.compile)),
+                    ("write", millis(self.timings.write)),
+                ]),
+            ),
+        ])
+    }
+}
+
+/// The compiler that is running, with the commit it was built from when known
+fn compiler() -> Json {
+    let commit = match env!("LX_GIT_COMMIT") {
+        "" => Json::Null,
+        commit => Json::from(commit),
+    };
+    Json::object([
+        ("name", Json::from("lx")),
+        ("version", Json::from(env!("CARGO_PKG_VERSION"))),
+        ("commit", commit),
+    ])
+}
+
+/// Write the build info for `records` to `out_dir`, keeping the entries of
+/// sources not built this time from an earlier build for the same target
+pub fn write(
+    out_dir: &Path,
+    target: &str,
+    options: Options,
+    records: &[Record],
+) -> Result<(), String> {
+    let path = out_dir.join(FILE);
+    let mut sources: Vec<Json> = records.iter().map(Record::to_json).collect();
+
+    let earlier = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|text| parse_json(&text).ok())
+        .filter(|info| info.get("target").and_then(Json::as_str) == Some(target));
+    if let Some(entries) = earlier
+        .as_ref()
+        .and_then(|info| info.get("sources")?.as_array())
+    {
+        let built = |entry: &Json| {
+            let source = entry.get("path").and_then(Json::as_str);
+            records
+                .iter()
+                .any(|record| Some(record.source.display().to_string().as_str()) == source)
+        };
+        let kept: Vec<Json> = entries
+            .iter()
+            .filter(|entry| !built(entry))
+            .cloned()
+            .collect();
+        sources.splice(0..0, kept);
+    }
+
+    let info = Json::object([
+        ("format", Json::from(FORMAT)),
+        ("compiler", compiler()),
+        ("target", Json::from(target)),
+        ("options", Json::object(options)),
+        ("sources", Json::Array(sources)),
+    ]);
+    std::fs::create_dir_all(out_dir).map_err(|e| e.to_string())?;
+    std::fs::write(&path, format!("{}\n", info))
+        .map_err(|e| format!("cannot write {}: {}", path.display(), e))
+}
+
+/// Check that `artifact` is what its build recorded and that the source it
+/// was built from hasn't changed since. An artifact with no build info, or
+/// not listed in it, passes.
+pub fn check_fresh(artifact: &Path) -> Result<(), String> {
+    let dir = artifact.parent().unwrap_or(Path::new(""));
+    let path = dir.join(FILE);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(format!("cannot read {}: {}", path.display(), e)),
+    };
+    let info = parse_json(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let name = artifact
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let listed = |entry: &&Json| {
+        entry
+            .get("path")
+            .and_then(Json::as_str)
+            .is_some_and(|path| path == name)
+    };
+    let sources = info.get("sources").and_then(Json::as_array).unwrap_or(&[]);
+    let Some((source, recorded)) = sources.iter().find_map(|source| {
+        let artifacts = source.get("artifacts")?.as_array()?;
+        Some((source, artifacts.iter().find(listed)?))
+    }) else {
+        return Ok(());
+    };
+
+    let contents = std::fs::read(artifact).map_err(|e| e.to_string())?;
+    if Some(&hash(&contents)) != recorded.get("keccak256") {
+        return Err(format!(
+            "{} was changed after it was built; build it again",
+            name
+        ));
+    }
+
+    let source_path = source.get("path").and_then(Json::as_str).unwrap_or("");
+    let contents = std::fs::read(source_path)
+        .map_err(|e| format!("cannot read its source {}: {}", source_path, e))?;
+    if Some(&hash(&contents)) != source.get("keccak256") {
+        return Err(format!(
+            "{} changed since {} was built; build it again",
+            source_path, name
+        ));
+    }
+    Ok(())
+}
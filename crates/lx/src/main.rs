@@ -1,11 +1,56 @@
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+mod build_info;
+
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use lamina::cli::Output;
+use lamina::coverage::{self, FileCoverage};
+use lamina::diagnostics::{Diagnostic, Severity};
+use lamina::dotenv;
+use lamina::edition;
+use lamina::encoding::{decode_hex, decode_quantity, encode_hex};
+use lamina::evaluator::environment::{set_command_line, setup_initial_env};
+use lamina::evaluator::eval_with_env;
+use lamina::evm::trace::{self as evm_trace, TraceStep};
+use lamina::evm::{
+    checksum_address, create2_address, keccak256, parse_address, GasSchedule, Hardfork, Word,
+};
+use lamina::json::{parse_json, Json};
+use lamina::lexer;
+use lamina::parser;
+use lamina::project::{self, Manifest};
+use lamina::repl;
+use lamina::testing::bench::{self, Baseline, BenchOptions};
+use lamina::testing::{self, Outcome, Target, TestOptions};
+use lamina::value::Value;
+use lamina_backend_api::{Backend, FunctionSize, Options as BackendOptions, Program, Registry};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
 
 #[derive(Parser)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Cli {
     #[command(subcommand)]
-    command: Commands,
+    command: Option<Commands>,
+    /// Script to run, as `lx run` does
+    file: Option<PathBuf>,
+    /// Arguments for the script, which (command-line) returns after its name
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true, requires = "file")]
+    args: Vec<String>,
+    /// Print one JSON object per line instead of text (or LAMINA_OUTPUT=json)
+    #[arg(long, global = true)]
+    json: bool,
+    /// Print only results and errors (or LAMINA_VERBOSITY=quiet)
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Print extra detail, such as functions left out of a build
+    #[arg(short, long, global = true)]
+    verbose: bool,
+    /// Don't read variables from the project's .env when running scripts,
+    /// tests and deployments
+    #[arg(long, global = true)]
+    no_dotenv: bool,
 }
 
 #[derive(Subcommand)]
@@ -14,44 +59,1378 @@ enum Commands {
     New {
         /// Name of the project
         name: String,
+        /// Target the project builds for: interpreter or evm
+        #[arg(short, long, default_value = "interpreter")]
+        target: String,
     },
     /// Initialize a Lamina project in the current directory
-    Init {},
+    Init {
+        /// Target the project builds for: interpreter or evm
+        #[arg(short, long, default_value = "interpreter")]
+        target: String,
+    },
     /// Build the Lamina project
     Build {
-        /// Optional target backend (default: interpreter)
+        /// Source file or directory of .lmn files (default: the manifest's
+        /// entry, or src)
+        path: Option<PathBuf>,
+        /// Target backend, or interpreter to only check the sources (default:
+        /// the manifest's target, or interpreter)
+        #[arg(short, long)]
+        target: Option<String>,
+        /// Fail the build when the compiler raises warnings
+        #[arg(long)]
+        deny_warnings: bool,
+        /// Number of files to build at once (default: one per CPU)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+        /// Where backends write their artifacts
+        #[arg(long, default_value = "out")]
+        out_dir: PathBuf,
+        /// Print how the runtime code of each contract divides between its functions
+        #[arg(long)]
+        size_report: bool,
+        /// Fail when a contract's runtime code is larger, in bytes (default: the
+        /// target's limit)
+        #[arg(long)]
+        size_budget: Option<usize>,
+        /// Hardfork the evm target compiles for: shanghai or cancun (default)
+        #[arg(long)]
+        hardfork: Option<String>,
+    },
+    /// Deploy a compiled contract
+    Deploy {
+        /// Foundry artifact (`<Name>.json`) holding the creation bytecode
+        artifact: PathBuf,
+        /// Deploy through CREATE2, at an address fixed by the salt
+        #[arg(long)]
+        create2: bool,
+        /// CREATE2 salt, decimal or 0x-prefixed hex
+        #[arg(long, requires = "create2", default_value = "0")]
+        salt: String,
+        /// Account running CREATE2 (default: the deterministic deployment proxy)
+        #[arg(long, requires = "create2")]
+        deployer: Option<String>,
+        /// Fail unless the contract is predicted to land at this address
+        #[arg(long, requires = "create2")]
+        expect: Option<String>,
+        /// Deploy even if build-info.json shows the artifact is stale
+        #[arg(long)]
+        allow_stale: bool,
+    },
+    /// Report compiler warnings, security lints included, without building
+    Lint {
+        /// Source file or directory of .lmn files
+        path: PathBuf,
+        /// Target backend to lint for (default: evm)
         #[arg(short, long)]
         target: Option<String>,
+        /// Fail when any warning is raised
+        #[arg(long)]
+        deny_warnings: bool,
     },
     /// Run a Lamina script
     Run {
         /// Path to the script
         script: PathBuf,
+        /// Arguments for the script, which (command-line) returns after its name
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Start an interactive session, keeping history between runs
+    Repl {
+        /// Refuse to redefine core bindings instead of warning
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Print a source file after the compile-time phase
+    Expand {
+        /// Path to the source file
+        path: PathBuf,
+    },
+    /// Print the IR a source file lowers to
+    Ir {
+        /// Path to the source file
+        path: PathBuf,
+    },
+    /// Run the tests declared with define-test and define-property
+    Test {
+        /// Test file or directory of .lmn files (default: tests)
+        path: Option<PathBuf>,
+        /// Execution target: interpreter (default) or evm
+        #[arg(short, long)]
+        target: Option<String>,
+        /// Fuzz define-property cases with generated inputs
+        #[arg(long)]
+        fuzz: bool,
+        /// Number of generated inputs per property
+        #[arg(long, default_value_t = 100)]
+        runs: usize,
+        /// Seed for input generation, to replay a failing run
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Measure line and branch coverage and write an lcov report
+        #[arg(long)]
+        coverage: bool,
+        /// Where to write the lcov report
+        #[arg(long, default_value = "lcov.info", requires = "coverage")]
+        lcov: PathBuf,
+        /// Fail when total line coverage is below this percentage
+        #[arg(long, requires = "coverage")]
+        min_coverage: Option<f64>,
+        /// Write a step trace of the simulated EVM for each failing case,
+        /// printing short ones
+        #[arg(long)]
+        trace_failing: bool,
+        /// Where to write the traces, as JSON lines
+        #[arg(long, default_value = "traces", requires = "trace_failing")]
+        trace_dir: PathBuf,
+        /// Price traced steps at this hardfork: shanghai or cancun (default)
+        #[arg(long, requires = "trace_failing")]
+        hardfork: Option<String>,
+        /// Price traced steps with the costs in this TOML file
+        #[arg(long, requires = "trace_failing", conflicts_with = "hardfork")]
+        gas_schedule: Option<PathBuf>,
+    },
+    /// Run the benchmarks declared with define-bench
+    Bench {
+        /// Bench file or directory of .lmn files (default: benches)
+        path: Option<PathBuf>,
+        /// Execution target: interpreter (default) or evm
+        #[arg(short, long)]
+        target: Option<String>,
+        /// Baseline file to compare against
+        #[arg(long, default_value = "bench-baseline.txt")]
+        baseline: PathBuf,
+        /// Store the results in the baseline file
+        #[arg(long)]
+        save_baseline: bool,
+        /// Slowdown against the baseline, in percent, that fails the run
+        #[arg(long, default_value_t = 10.0)]
+        threshold: f64,
+    },
+    /// Print a script completing lx's subcommands and flags in a shell
+    Completions {
+        /// bash, zsh, fish, elvish or powershell
+        shell: Shell,
     },
 }
 
 fn main() {
     let cli = Cli::parse();
+    let out = Output::from_env().with_flags(cli.json, cli.quiet, cli.verbose);
+    let registry = backends();
 
-    match cli.command {
-        Commands::New { name } => {
-            println!("Creating new project: {}", name);
-            // TODO: Implement project creation
+    let command = match (cli.command, cli.file) {
+        (Some(command), _) => command,
+        (None, Some(script)) => Commands::Run {
+            script,
+            args: cli.args,
+        },
+        (None, None) => {
+            let _ = Cli::command().print_help();
+            std::process::exit(2);
         }
-        Commands::Init {} => {
-            println!("Initializing project in current directory");
-            // TODO: Implement project initialization
+    };
+
+    match command {
+        Commands::New { name, target } => {
+            out.status(format!("Creating new project: {}", name));
+            if let Err(e) = project::create(Path::new(&name), &name, &target) {
+                out.error(format!("{}: {}", name, e));
+                std::process::exit(1);
+            }
         }
-        Commands::Build { target } => {
-            match target {
-                Some(t) => println!("Building project with target: {}", t),
-                None => println!("Building project with default target"),
+        Commands::Init { target } => {
+            out.status("Initializing project in current directory");
+            let dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+            let name = dir.file_name().map_or("project".to_string(), |name| {
+                name.to_string_lossy().into_owned()
+            });
+            if let Err(e) = project::create(Path::new("."), &name, &target) {
+                out.error(e);
+                std::process::exit(1);
             }
-            // TODO: Implement build
         }
-        Commands::Run { script } => {
-            println!("Running script: {:?}", script);
-            // TODO: Implement script running
+        Commands::Build {
+            path,
+            target,
+            deny_warnings,
+            jobs,
+            out_dir,
+            size_report,
+            size_budget,
+            hardfork,
+        } => {
+            let manifest = Manifest::read(Path::new(".")).unwrap_or_else(|e| {
+                out.error(e);
+                std::process::exit(1);
+            });
+            let manifest = manifest.as_ref();
+            let from_manifest = target.is_none();
+            let target = target.or_else(|| manifest.and_then(|m| m.target.clone()));
+            let backend = match target.as_deref() {
+                None | Some("interpreter") => None,
+                // Point at the manifest when the target it names isn't built in
+                Some(name) if from_manifest && registry.find(name).is_none() => {
+                    out.error(format!(
+                        "{}: unknown target: {} (available: interpreter, {})",
+                        edition::MANIFEST,
+                        name,
+                        registry.names().join(", ")
+                    ));
+                    std::process::exit(1);
+                }
+                Some(name) => Some(find_backend(&out, &registry, name)),
+            };
+            if let Some(name) = hardfork
+                .as_deref()
+                .filter(|n| Hardfork::from_name(n).is_none())
+            {
+                out.error(format!(
+                    "unknown hardfork: {} (use shanghai or cancun)",
+                    name
+                ));
+                std::process::exit(1);
+            }
+            let options = BuildOptions {
+                backend,
+                deny_warnings,
+                jobs: jobs.unwrap_or_else(default_jobs).max(1),
+                out_dir,
+                size_report,
+                size_budget,
+                hardfork,
+            };
+            let path = path
+                .or_else(|| manifest.and_then(|m| m.entry.clone()))
+                .unwrap_or_else(|| PathBuf::from("src"));
+            if !build_files(&out, &path, &options) {
+                std::process::exit(1);
+            }
+        }
+        Commands::Deploy {
+            artifact,
+            create2,
+            salt,
+            deployer,
+            expect,
+            allow_stale,
+        } => {
+            load_project_env(&out, !cli.no_dotenv);
+            if let (false, Err(e)) = (allow_stale, build_info::check_fresh(&artifact)) {
+                out.error(format!("{}: {}", artifact.display(), e));
+                std::process::exit(1);
+            }
+            if !create2 {
+                out.status(format!("Deploying {:?}", artifact));
+                // TODO: Implement deployment
+                return;
+            }
+            let deployer = deployer.as_deref().unwrap_or(CREATE2_DEPLOYER);
+            if let Err(e) = predict_create2(&out, &artifact, &salt, deployer, expect.as_deref()) {
+                out.error(format!("{}: {}", artifact.display(), e));
+                std::process::exit(1);
+            }
+        }
+        Commands::Lint {
+            path,
+            target,
+            deny_warnings,
+        } => {
+            let backend = find_backend(&out, &registry, target.as_deref().unwrap_or("evm"));
+            let (clean, warned) = lint_files(&out, &path, backend);
+            if !clean || (deny_warnings && warned) {
+                std::process::exit(1);
+            }
+        }
+        Commands::Run { script, args } => {
+            load_project_env(&out, !cli.no_dotenv);
+            out.detail(format!("Running {}", script.display()));
+            match run_script(&script, args) {
+                Ok(value) if out.is_json() => out.result(
+                    "result",
+                    "",
+                    Json::object([
+                        ("file", Json::from(script.display().to_string())),
+                        ("value", Json::from(value.to_string())),
+                    ]),
+                ),
+                Ok(_) => {}
+                Err(e) => {
+                    out.error(format!("{}: {}", script.display(), e));
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Repl { strict } => {
+            load_project_env(&out, !cli.no_dotenv);
+            out.status("Lamina REPL (Press Ctrl+C or type :quit to exit, :help for commands)");
+            let mut session = repl::Session::new();
+            session.set_strict(strict);
+            session.set_auto_import(true);
+            session.set_lowering(|source| {
+                let program = lamina_ir::lower_source(source).map_err(|e| e.to_string())?;
+                Ok(lamina_ir::printer::print(&program).trim_end().to_string())
+            });
+            if let Err(e) = repl::run(&mut session, repl::history_path().as_deref()) {
+                out.error(e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Expand { path } => {
+            let expanded = read_source(&path).and_then(|source| repl::expand_source(&source));
+            match expanded {
+                Ok(expanded) => out.result(
+                    "expand",
+                    &expanded,
+                    Json::object([
+                        ("path", Json::from(path.display().to_string())),
+                        ("source", Json::from(expanded.as_str())),
+                    ]),
+                ),
+                Err(e) => {
+                    out.error(format!("{}: {}", path.display(), e));
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Ir { path } => {
+            let ir = read_source(&path)
+                .and_then(|source| lamina_ir::lower_source(&source).map_err(|e| e.to_string()));
+            match ir {
+                Ok(program) => {
+                    let text = lamina_ir::printer::print(&program);
+                    out.result(
+                        "ir",
+                        text.trim_end(),
+                        Json::object([
+                            ("path", Json::from(path.display().to_string())),
+                            ("ir", Json::from(text.as_str())),
+                        ]),
+                    )
+                }
+                Err(e) => {
+                    out.error(format!("{}: {}", path.display(), e));
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Test {
+            path,
+            target,
+            fuzz,
+            runs,
+            seed,
+            coverage,
+            lcov,
+            min_coverage,
+            trace_failing,
+            trace_dir,
+            hardfork,
+            gas_schedule,
+        } => {
+            load_project_env(&out, !cli.no_dotenv);
+            let options = TestOptions {
+                target: parse_target(&out, target.as_deref()),
+                fuzz,
+                runs,
+                seed,
+                coverage,
+                trace: trace_failing,
+                gas_schedule: parse_gas_schedule(
+                    &out,
+                    hardfork.as_deref(),
+                    gas_schedule.as_deref(),
+                ),
+            };
+            if trace_failing && options.target != Target::Evm {
+                out.error("--trace-failing traces the simulated EVM; use it with --target evm");
+                std::process::exit(1);
+            }
+            let path = path.unwrap_or_else(|| PathBuf::from("tests"));
+            let (passed, files) = run_tests(&out, &path, &options, &trace_dir);
+
+            let covered = !coverage || report_coverage(&out, &files, &lcov, min_coverage);
+            if !passed || !covered {
+                std::process::exit(1);
+            }
+        }
+        Commands::Bench {
+            path,
+            target,
+            baseline,
+            save_baseline,
+            threshold,
+        } => {
+            let options = BenchOptions {
+                target: parse_target(&out, target.as_deref()),
+                ..BenchOptions::default()
+            };
+            let path = path.unwrap_or_else(|| PathBuf::from("benches"));
+            if !run_benches(&out, &path, &options, &baseline, save_baseline, threshold) {
+                std::process::exit(1);
+            }
+        }
+        Commands::Completions { shell } => {
+            clap_complete::generate(shell, &mut Cli::command(), "lx", &mut std::io::stdout());
         }
     }
-} 
\ No newline at end of file
+}
+
+/// Set the variables of the project in the current directory that the
+/// environment doesn't already, from its .env unless `read_dotenv` is false and
+/// from the `[env]` table of lamina.toml, exiting when either is malformed
+fn load_project_env(out: &Output, read_dotenv: bool) {
+    match dotenv::load(Path::new("."), read_dotenv) {
+        Ok(loaded) => {
+            for (name, source) in loaded {
+                let from = match source {
+                    dotenv::Source::Dotenv => dotenv::FILE,
+                    dotenv::Source::Manifest => edition::MANIFEST,
+                };
+                out.detail(format!("{} set from {}", name, from));
+            }
+        }
+        Err(e) => {
+            out.error(e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The deterministic deployment proxy, deployed at the same address on most chains
+const CREATE2_DEPLOYER: &str = "0x4e59b44847b379578588920ca78fbf26c0b4956c";
+
+/// Print where CREATE2 puts the contract in `artifact`, checking it against
+/// `expect` when given
+fn predict_create2(
+    out: &Output,
+    artifact: &Path,
+    salt: &str,
+    deployer: &str,
+    expect: Option<&str>,
+) -> Result<(), String> {
+    let json = parse_json(&std::fs::read_to_string(artifact).map_err(|e| e.to_string())?)?;
+    let bytecode = json
+        .get("bytecode")
+        .and_then(|bytecode| bytecode.get("object").or(Some(bytecode)))
+        .and_then(|object| object.as_str())
+        .ok_or("artifact has no bytecode")?;
+    let init_code = decode_hex(bytecode.trim_start_matches("0x"))?;
+
+    let salt = match salt.strip_prefix("0x") {
+        Some(hex) => Word::from_be_bytes(&decode_quantity(hex)?)?,
+        None => Word::from_i64(
+            salt.parse()
+                .map_err(|_| format!("invalid salt: {}", salt))?,
+        ),
+    };
+    let deployer = parse_address(deployer)?;
+    let init_code_hash = keccak256(&init_code);
+    let address = create2_address(&deployer, &salt.to_be_bytes(), &init_code_hash);
+
+    let fields = [
+        ("deployer", checksum_address(&deployer)),
+        ("salt", encode_hex(&salt.to_be_bytes())),
+        ("initCodeHash", encode_hex(&init_code_hash)),
+        ("address", checksum_address(&address)),
+    ];
+    out.result(
+        "create2",
+        format!(
+            "deployer: {}\nsalt: {}\ninit code hash: {}\naddress: {}",
+            fields[0].1, fields[1].1, fields[2].1, fields[3].1
+        ),
+        Json::object(fields.map(|(key, value)| (key, Json::from(value)))),
+    );
+
+    if let Some(expect) = expect {
+        if parse_address(expect)? != address {
+            return Err(format!(
+                "predicted address {} does not match {}",
+                checksum_address(&address),
+                expect
+            ));
+        }
+        out.status(format!("address matches {}", expect));
+    }
+    Ok(())
+}
+
+/// The backends lx can build with, each behind the Cargo feature of the crate
+/// providing it
+fn backends() -> Registry {
+    let registry = Registry::default();
+    #[cfg(feature = "huff")]
+    let registry = registry.with(Box::new(lamina_huff::HuffBackend));
+    registry
+}
+
+/// The backend called `name`, exiting when there is none
+fn find_backend<'a>(out: &Output, registry: &'a Registry, name: &str) -> &'a dyn Backend {
+    registry.find(name).unwrap_or_else(|| {
+        out.error(format!(
+            "unknown target: {} (available: {})",
+            name,
+            registry.names().join(", ")
+        ));
+        std::process::exit(1);
+    })
+}
+
+fn parse_target(out: &Output, target: Option<&str>) -> Target {
+    match target {
+        None | Some("interpreter") => Target::Interpreter,
+        Some("evm") | Some("huff") => Target::Evm,
+        Some(other) => {
+            out.error(format!("unknown target: {}", other));
+            std::process::exit(1);
+        }
+    }
+}
+
+fn parse_gas_schedule(out: &Output, hardfork: Option<&str>, file: Option<&Path>) -> GasSchedule {
+    match (hardfork, file) {
+        (_, Some(file)) => GasSchedule::load(file).unwrap_or_else(|e| {
+            out.error(e);
+            std::process::exit(1);
+        }),
+        (Some(name), None) => match Hardfork::from_name(name) {
+            Some(hardfork) => GasSchedule::for_hardfork(hardfork),
+            None => {
+                out.error(format!(
+                    "unknown hardfork: {} (use shanghai or cancun)",
+                    name
+                ));
+                std::process::exit(1);
+            }
+        },
+        (None, None) => GasSchedule::default(),
+    }
+}
+
+/// Run every test file under `path`, returning whether all of them passed and
+/// the coverage of each file when it was measured
+fn run_tests(
+    out: &Output,
+    path: &Path,
+    options: &TestOptions,
+    trace_dir: &Path,
+) -> (bool, Vec<(String, FileCoverage)>) {
+    let files = match test_files(path) {
+        Ok(files) => files,
+        Err(e) => {
+            out.error(format!("cannot read {}: {}", path.display(), e));
+            return (false, Vec::new());
+        }
+    };
+
+    let (mut passed, mut failed, mut skipped) = (0, 0, 0);
+    let mut coverage = Vec::new();
+
+    for file in files {
+        let source = match read_source(&file) {
+            Ok(source) => source,
+            Err(e) => {
+                out.error(format!("cannot read {}: {}", file.display(), e));
+                failed += 1;
+                continue;
+            }
+        };
+
+        let report = match testing::run_source(&source, options) {
+            Ok(report) => report,
+            Err(e) => {
+                out.error(format!("{}: {}", file.display(), e));
+                failed += 1;
+                continue;
+            }
+        };
+
+        out.status(format!(
+            "running {} tests from {}",
+            report.results.len(),
+            file.display()
+        ));
+        for result in &report.results {
+            let kind = if result.property { "property" } else { "test" };
+            let mut fields = vec![
+                ("file", Json::from(file.display().to_string())),
+                ("name", Json::from(result.name.as_str())),
+                ("property", Json::from(result.property)),
+            ];
+            let human = match &result.outcome {
+                Outcome::Passed if result.property => {
+                    fields.push(("outcome", Json::from("ok")));
+                    fields.push(("runs", Json::from(result.runs)));
+                    fields.push(("discarded", Json::from(result.discarded)));
+                    format!(
+                        "{} {} ... ok ({} runs, {} discarded)",
+                        kind, result.name, result.runs, result.discarded
+                    )
+                }
+                Outcome::Passed => {
+                    fields.push(("outcome", Json::from("ok")));
+                    format!("{} {} ... ok", kind, result.name)
+                }
+                Outcome::Skipped => {
+                    fields.push(("outcome", Json::from("skipped")));
+                    format!("{} {} ... skipped (use --fuzz)", kind, result.name)
+                }
+                Outcome::Failed(message) => {
+                    fields.push(("outcome", Json::from("failed")));
+                    let mut human = format!("{} {} ... FAILED", kind, result.name);
+                    if let Some(inputs) = &result.counterexample {
+                        let inputs: Vec<String> =
+                            inputs.iter().map(testing::format_input).collect();
+                        human += &format!("\n    counterexample: ({})", inputs.join(" "));
+                        human += &format!("\n    replay with: --seed {}", report.seed);
+                        fields.push((
+                            "counterexample",
+                            Json::Array(inputs.into_iter().map(Json::from).collect()),
+                        ));
+                        fields.push(("seed", Json::from(report.seed.to_string())));
+                    }
+                    fields.push(("message", Json::from(message.as_str())));
+                    let mut human = format!("{}\n    {}", human, message);
+                    if let Some(steps) = &result.trace {
+                        match write_trace(trace_dir, &file, &result.name, steps) {
+                            Ok(trace_file) => {
+                                human += &format!(
+                                    "\n    trace: {} ({} steps)",
+                                    trace_file.display(),
+                                    steps.len()
+                                );
+                                if steps.len() <= SHORT_TRACE {
+                                    for line in evm_trace::render(steps).lines() {
+                                        human += &format!("\n    {}", line);
+                                    }
+                                }
+                                fields
+                                    .push(("trace", Json::from(trace_file.display().to_string())));
+                            }
+                            Err(e) => out.error(e),
+                        }
+                    }
+                    human
+                }
+            };
+            // Failures are reported even when quiet
+            if matches!(result.outcome, Outcome::Failed(_)) {
+                out.result("test", human, Json::object(fields));
+            } else {
+                out.event("test", human, Json::object(fields));
+            }
+        }
+
+        passed += report.passed();
+        failed += report.failed();
+        skipped += report.skipped();
+        if let Some(file_coverage) = report.coverage {
+            coverage.push((file.display().to_string(), file_coverage));
+        }
+    }
+
+    let status = if failed == 0 { "ok" } else { "FAILED" };
+    out.result(
+        "test-result",
+        format!(
+            "\ntest result: {}. {} passed; {} failed; {} skipped",
+            status, passed, failed, skipped
+        ),
+        Json::object([
+            ("ok", Json::from(failed == 0)),
+            ("passed", Json::from(passed)),
+            ("failed", Json::from(failed)),
+            ("skipped", Json::from(skipped)),
+        ]),
+    );
+    (failed == 0, coverage)
+}
+
+/// Traces of at most this many steps are printed as well as written
+const SHORT_TRACE: usize = 20;
+
+/// Write the trace of the failing case `name` of `file` under `dir`, as
+/// `<file stem>-<name>.jsonl`, returning the path written
+fn write_trace(
+    dir: &Path,
+    file: &Path,
+    name: &str,
+    steps: &[TraceStep],
+) -> Result<PathBuf, String> {
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let path = dir.join(format!("{}-{}.jsonl", stem, name));
+    std::fs::create_dir_all(dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+    std::fs::write(&path, evm_trace::to_json_lines(steps))
+        .map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Print a coverage summary and write the lcov report, returning whether the
+/// total meets `min_coverage`
+fn report_coverage(
+    out: &Output,
+    files: &[(String, FileCoverage)],
+    lcov: &Path,
+    min_coverage: Option<f64>,
+) -> bool {
+    out.status("\ncoverage:");
+    for (path, file_coverage) in files {
+        let missed: Vec<String> = file_coverage
+            .missed_lines()
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+        let missed_branches: Vec<String> = file_coverage
+            .missed_branches()
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+        out.event(
+            "coverage",
+            format!(
+                "  {} {:>5.1}% ({}/{} lines, {}/{} branches){}{}",
+                path,
+                file_coverage.percent(),
+                file_coverage.lines_hit(),
+                file_coverage.lines_found(),
+                file_coverage.branches_hit(),
+                file_coverage.branches.len(),
+                if missed.is_empty() {
+                    String::new()
+                } else {
+                    format!(" missed: {}", missed.join(", "))
+                },
+                if missed_branches.is_empty() {
+                    String::new()
+                } else {
+                    format!(" untaken branches: {}", missed_branches.join(", "))
+                }
+            ),
+            Json::object([
+                ("file", Json::from(path.as_str())),
+                ("percent", Json::from(file_coverage.percent())),
+                ("hit", Json::from(file_coverage.lines_hit())),
+                ("found", Json::from(file_coverage.lines_found())),
+                (
+                    "missed",
+                    Json::Array(
+                        file_coverage
+                            .missed_lines()
+                            .iter()
+                            .map(|&line| Json::from(line))
+                            .collect(),
+                    ),
+                ),
+                ("branchesHit", Json::from(file_coverage.branches_hit())),
+                ("branchesFound", Json::from(file_coverage.branches.len())),
+                (
+                    "untakenBranches",
+                    Json::Array(
+                        file_coverage
+                            .missed_branches()
+                            .iter()
+                            .map(|&line| Json::from(line))
+                            .collect(),
+                    ),
+                ),
+            ]),
+        );
+    }
+
+    let found: usize = files.iter().map(|(_, c)| c.lines_found()).sum();
+    let hit: usize = files.iter().map(|(_, c)| c.lines_hit()).sum();
+    let total = if found == 0 {
+        100.0
+    } else {
+        100.0 * hit as f64 / found as f64
+    };
+    let branches_found: usize = files.iter().map(|(_, c)| c.branches.len()).sum();
+    let branches_hit: usize = files.iter().map(|(_, c)| c.branches_hit()).sum();
+    out.result(
+        "coverage-total",
+        format!(
+            "  total {:.1}% ({}/{} lines, {}/{} branches)",
+            total, hit, found, branches_hit, branches_found
+        ),
+        Json::object([
+            ("percent", Json::from(total)),
+            ("hit", Json::from(hit)),
+            ("found", Json::from(found)),
+            ("branchesHit", Json::from(branches_hit)),
+            ("branchesFound", Json::from(branches_found)),
+        ]),
+    );
+
+    if let Err(e) = std::fs::write(lcov, coverage::lcov_report(files)) {
+        out.error(format!("cannot write {}: {}", lcov.display(), e));
+        return false;
+    }
+    out.status(format!("  wrote {}", lcov.display()));
+
+    match min_coverage {
+        Some(min) if total < min => {
+            out.error(format!(
+                "coverage {:.1}% is below the required {:.1}%",
+                total, min
+            ));
+            false
+        }
+        _ => true,
+    }
+}
+
+/// Run every bench file under `path` and compare against the baseline,
+/// returning whether all benchmarks ran without regressing
+fn run_benches(
+    out: &Output,
+    path: &Path,
+    options: &BenchOptions,
+    baseline_path: &Path,
+    save_baseline: bool,
+    threshold: f64,
+) -> bool {
+    let files = match test_files(path) {
+        Ok(files) => files,
+        Err(e) => {
+            out.error(format!("cannot read {}: {}", path.display(), e));
+            return false;
+        }
+    };
+
+    let mut baseline = match std::fs::read_to_string(baseline_path) {
+        Ok(text) => match Baseline::parse(&text) {
+            Ok(baseline) => baseline,
+            Err(e) => {
+                out.error(format!("{}: {}", baseline_path.display(), e));
+                return false;
+            }
+        },
+        Err(_) => Baseline::default(),
+    };
+
+    let (mut failed, mut regressed) = (0, 0);
+    let mut results = Vec::new();
+
+    for file in files {
+        let source = match read_source(&file) {
+            Ok(source) => source,
+            Err(e) => {
+                out.error(format!("cannot read {}: {}", file.display(), e));
+                failed += 1;
+                continue;
+            }
+        };
+
+        let file_results = match bench::run_benches(&source, options) {
+            Ok(results) => results,
+            Err(e) => {
+                out.error(format!("{}: {}", file.display(), e));
+                failed += 1;
+                continue;
+            }
+        };
+
+        out.status(format!(
+            "running {} benchmarks from {}",
+            file_results.len(),
+            file.display()
+        ));
+        for result in &file_results {
+            let mut fields = vec![
+                ("file", Json::from(file.display().to_string())),
+                ("name", Json::from(result.name.as_str())),
+            ];
+            let human = match result.outcome {
+                Ok(ns) => {
+                    let change = baseline.change(&result.name, ns);
+                    let comparison = match change {
+                        Some(change) if change > threshold => {
+                            regressed += 1;
+                            format!(" ({:+.1}%, REGRESSED)", change)
+                        }
+                        Some(change) => format!(" ({:+.1}%)", change),
+                        None => String::new(),
+                    };
+                    fields.push(("outcome", Json::from("ok")));
+                    fields.push(("nsPerOp", Json::from(ns)));
+                    fields.push(("iterations", Json::from(result.iterations as f64)));
+                    fields.push(("change", change.map_or(Json::Null, Json::from)));
+                    format!(
+                        "bench {} ... {:.1} ns/op ({} iterations){}",
+                        result.name, ns, result.iterations, comparison
+                    )
+                }
+                Err(ref message) => {
+                    failed += 1;
+                    fields.push(("outcome", Json::from("failed")));
+                    fields.push(("message", Json::from(message.as_str())));
+                    format!("bench {} ... FAILED\n    {}", result.name, message)
+                }
+            };
+            if result.outcome.is_err() {
+                out.result("bench", human, Json::object(fields));
+            } else {
+                out.event("bench", human, Json::object(fields));
+            }
+        }
+        results.extend(file_results);
+    }
+
+    if save_baseline {
+        baseline.merge(Baseline::from_results(&results));
+        if let Err(e) = std::fs::write(baseline_path, baseline.to_text()) {
+            out.error(format!("cannot write {}: {}", baseline_path.display(), e));
+            return false;
+        }
+        out.status(format!("\nsaved baseline to {}", baseline_path.display()));
+    }
+
+    let status = if failed == 0 && regressed == 0 {
+        "ok"
+    } else {
+        "FAILED"
+    };
+    out.result(
+        "bench-result",
+        format!(
+            "\nbench result: {}. {} run; {} failed; {} regressed",
+            status,
+            results.len(),
+            failed,
+            regressed
+        ),
+        Json::object([
+            ("ok", Json::from(failed == 0 && regressed == 0)),
+            ("run", Json::from(results.len())),
+            ("failed", Json::from(failed)),
+            ("regressed", Json::from(regressed)),
+        ]),
+    );
+    failed == 0 && regressed == 0
+}
+
+struct BuildOptions<'a> {
+    /// `None` only checks the sources, as the interpreter would run them
+    backend: Option<&'a dyn Backend>,
+    deny_warnings: bool,
+    jobs: usize,
+    out_dir: PathBuf,
+    size_report: bool,
+    size_budget: Option<usize>,
+    hardfork: Option<String>,
+}
+
+/// What building one file produced
+struct Built {
+    contract: String,
+    /// Where the main artifact was written, when built with a backend
+    artifact: Option<PathBuf>,
+    warnings: Vec<Diagnostic>,
+    removed: Vec<String>,
+    /// Runtime code size, and the bytes of each function, for targets that
+    /// limit it
+    size: Option<usize>,
+    sizes: Vec<FunctionSize>,
+    /// What went into the artifacts, when built with a backend
+    record: Option<build_info::Record>,
+}
+
+fn default_jobs() -> usize {
+    std::thread::available_parallelism().map_or(1, |jobs| jobs.get())
+}
+
+/// Build every file under `path`, `options.jobs` at a time, returning whether
+/// all of them built. Files are independent compilation units, and their
+/// results are reported in file order whichever finishes first.
+fn build_files(out: &Output, path: &Path, options: &BuildOptions) -> bool {
+    let files = match test_files(path) {
+        Ok(files) => files,
+        Err(e) => {
+            out.error(format!("cannot read {}: {}", path.display(), e));
+            return false;
+        }
+    };
+    out.detail(format!(
+        "building {} files, {} at a time",
+        files.len(),
+        options.jobs
+    ));
+
+    let results = parallel_map(&files, options.jobs, |file| build_file(file, options));
+
+    let mut failed = 0;
+    let mut records = Vec::new();
+    for (file, result) in files.iter().zip(results) {
+        let mut built = match result {
+            Ok(built) => built,
+            Err(e) => {
+                out.error(format!("{}: {}", file.display(), e));
+                failed += 1;
+                continue;
+            }
+        };
+        for diagnostic in &built.warnings {
+            print_diagnostic(out, file, diagnostic);
+        }
+        for function in &built.removed {
+            out.detail(format!(
+                "{}: removed unreachable function {}",
+                file.display(),
+                function
+            ));
+        }
+        if let (true, Some(size)) = (options.size_report, built.size) {
+            let budget = options
+                .size_budget
+                .or_else(|| options.backend.and_then(|backend| backend.size_limit()));
+            print_size_report(out, file, &built, size, budget);
+        }
+        records.extend(built.record.take());
+        let human = match &built.artifact {
+            Some(artifact) => format!("built {} -> {}", file.display(), artifact.display()),
+            None => format!("checked {}", file.display()),
+        };
+        out.event(
+            "build",
+            human,
+            Json::object([
+                ("file", Json::from(file.display().to_string())),
+                ("contract", Json::from(built.contract)),
+                (
+                    "artifact",
+                    built.artifact.map_or(Json::Null, |artifact| {
+                        Json::from(artifact.display().to_string())
+                    }),
+                ),
+            ]),
+        );
+    }
+
+    if let Some(backend) = options.backend {
+        let recorded = [
+            ("denyWarnings", Json::from(options.deny_warnings)),
+            (
+                "sizeBudget",
+                options.size_budget.map_or(Json::Null, Json::from),
+            ),
+            (
+                "edition",
+                match Manifest::read(Path::new(".")) {
+                    Ok(Some(Manifest {
+                        edition: Some(edition),
+                        ..
+                    })) => Json::from(edition.year() as usize),
+                    _ => Json::Null,
+                },
+            ),
+        ];
+        let written = build_info::write(
+            &options.out_dir,
+            backend.name(),
+            recorded.to_vec(),
+            &records,
+        );
+        if let Err(e) = written {
+            out.error(e);
+            failed = files.len().max(1);
+        }
+    }
+
+    let succeeded = files.len().saturating_sub(failed);
+    out.result(
+        "build-result",
+        format!("{} built, {} failed", succeeded, failed),
+        Json::object([
+            ("ok", Json::from(failed == 0)),
+            ("built", Json::from(succeeded)),
+            ("failed", Json::from(failed)),
+        ]),
+    );
+    failed == 0
+}
+
+/// Print the runtime code size of a built contract against the budget, and
+/// the bytes each function takes, largest first
+fn print_size_report(out: &Output, file: &Path, built: &Built, size: usize, budget: Option<usize>) {
+    let mut human = format!("{}: {} bytes of runtime code", file.display(), size);
+    if let Some(budget) = budget {
+        human += &format!(
+            ", {:.1}% of {}",
+            size as f64 * 100.0 / budget as f64,
+            budget
+        );
+    }
+    for size in &built.sizes {
+        human += &format!("\n    {:>6}  {}", size.bytes, size.name);
+    }
+    let functions = built
+        .sizes
+        .iter()
+        .map(|size| {
+            Json::object([
+                ("name", Json::from(size.name.as_str())),
+                ("bytes", Json::from(size.bytes)),
+            ])
+        })
+        .collect();
+    out.result(
+        "size",
+        human,
+        Json::object([
+            ("file", Json::from(file.display().to_string())),
+            ("contract", Json::from(built.contract.as_str())),
+            ("bytes", Json::from(size)),
+            ("budget", budget.map_or(Json::Null, Json::from)),
+            ("functions", Json::Array(functions)),
+        ]),
+    );
+}
+
+/// Build one source file: without a backend, check it reads and expands;
+/// with one, compile it and write the files the backend produces
+fn build_file(file: &Path, options: &BuildOptions) -> Result<Built, String> {
+    let started = Instant::now();
+    let source_hash = keccak256(&std::fs::read(file).map_err(|e| e.to_string())?);
+    let (contract, expr) = read_contract(file)?;
+    let Some(backend) = options.backend else {
+        lamina::expand::expand(&expr).map_err(|e| e.to_string())?;
+        return Ok(Built {
+            contract,
+            artifact: None,
+            warnings: Vec::new(),
+            removed: Vec::new(),
+            size: None,
+            sizes: Vec::new(),
+            record: None,
+        });
+    };
+    // Point at the call the backend would reject
+    let source = read_source(file)?;
+    if let Some(target) = lamina::targets::find(backend.name()) {
+        lamina::targets::check_source(&source, target).map_err(|e| e.to_string())?;
+    }
+    let read = started.elapsed();
+
+    let program = Program {
+        name: contract.clone(),
+        expr,
+        source: Some(source),
+    };
+    let backend_options = BackendOptions {
+        deny_warnings: options.deny_warnings,
+        size_budget: options.size_budget,
+        hardfork: options.hardfork.clone(),
+        ..Default::default()
+    };
+    let artifacts = backend
+        .compile(&program, &backend_options)
+        .map_err(|e| e.to_string())?;
+    let compile = started.elapsed() - read;
+
+    std::fs::create_dir_all(&options.out_dir).map_err(|e| e.to_string())?;
+    for output in &artifacts.files {
+        std::fs::write(options.out_dir.join(&output.name), &output.contents)
+            .map_err(|e| format!("cannot write {}: {}", output.name, e))?;
+    }
+    let record = build_info::Record {
+        source: file.to_path_buf(),
+        source_hash,
+        contract: contract.clone(),
+        artifacts: artifacts
+            .files
+            .iter()
+            .map(|output| (output.name.clone(), keccak256(&output.contents)))
+            .collect(),
+        timings: build_info::Timings {
+            read,
+            compile,
+            write: started.elapsed() - read - compile,
+        },
+    };
+
+    Ok(Built {
+        artifact: Some(
+            options
+                .out_dir
+                .join(format!("{}.{}", contract, backend.extension())),
+        ),
+        contract,
+        warnings: artifacts.warnings,
+        removed: artifacts.removed,
+        size: artifacts.code_size,
+        sizes: artifacts.sizes,
+        record: Some(record),
+    })
+}
+
+/// Stack for the build threads, as large as the main thread's, since
+/// compiling deeply nested code recurses
+const BUILD_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// `f` applied to every item on up to `jobs` threads, with the results in the
+/// order of `items`
+fn parallel_map<T: Sync, R: Send>(items: &[T], jobs: usize, f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let results: Vec<Mutex<Option<R>>> = items.iter().map(|_| Mutex::new(None)).collect();
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.min(items.len()) {
+            std::thread::Builder::new()
+                .stack_size(BUILD_STACK_SIZE)
+                .spawn_scoped(scope, || {
+                    // Each thread takes the next item left until none are
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            break;
+                        };
+                        *results[index].lock().unwrap() = Some(f(item));
+                    }
+                })
+                .expect("failed to spawn a build thread");
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|result| result.into_inner().unwrap().expect("every item is built"))
+        .collect()
+}
+
+/// Print the warnings compiling each file under `path` with `backend` raises,
+/// returning whether every file compiled and whether any warning was raised
+fn lint_files(out: &Output, path: &Path, backend: &dyn Backend) -> (bool, bool) {
+    let files = match test_files(path) {
+        Ok(files) => files,
+        Err(e) => {
+            out.error(format!("cannot read {}: {}", path.display(), e));
+            return (false, false);
+        }
+    };
+
+    let (mut clean, mut warned) = (true, false);
+    for file in files {
+        match lint_file(&file, backend) {
+            Ok(diagnostics) => {
+                for diagnostic in &diagnostics {
+                    print_diagnostic(out, &file, diagnostic);
+                }
+                warned |= !diagnostics.is_empty();
+            }
+            Err(e) => {
+                out.error(format!("{}: {}", file.display(), e));
+                clean = false;
+            }
+        }
+    }
+    (clean, warned)
+}
+
+fn print_diagnostic(out: &Output, file: &Path, diagnostic: &Diagnostic) {
+    out.result(
+        "diagnostic",
+        format!("{}: {}", file.display(), diagnostic),
+        Json::object([
+            ("file", Json::from(file.display().to_string())),
+            ("severity", Json::from(severity(diagnostic))),
+            ("code", Json::from(diagnostic.code.as_str())),
+            ("message", Json::from(diagnostic.message.as_str())),
+        ]),
+    );
+}
+
+fn severity(diagnostic: &Diagnostic) -> &'static str {
+    match diagnostic.severity {
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+    }
+}
+
+/// The warnings compiling a contract source file with `backend` raises
+fn lint_file(file: &Path, backend: &dyn Backend) -> Result<Vec<Diagnostic>, String> {
+    let (name, expr) = read_contract(file)?;
+    backend
+        .diagnostics(&Program {
+            name,
+            expr,
+            source: None,
+        })
+        .map_err(|e| e.to_string())
+}
+
+/// A source file, selecting the project's edition unless the file selects its
+/// own
+fn read_source(file: &Path) -> Result<String, String> {
+    let source = std::fs::read_to_string(file).map_err(|e| e.to_string())?;
+    match Manifest::read(Path::new("."))?.and_then(|manifest| manifest.edition) {
+        Some(project) => Ok(edition::with_default(&source, project)),
+        None => Ok(source),
+    }
+}
+
+/// Evaluate the top-level forms of the script at `path` in order, returning
+/// the value of the last; `(command-line)` gives the script's path, then
+/// `args`
+fn run_script(path: &Path, args: Vec<String>) -> Result<Value, String> {
+    let source = read_source(path)?;
+    let tokens = lexer::lex(&source).map_err(|e| e.to_string())?;
+    let forms = parser::parse_all(&tokens).map_err(|e| e.to_string())?;
+    let env = setup_initial_env();
+    let mut command_line = vec![path.display().to_string()];
+    command_line.extend(args);
+    set_command_line(&env, command_line);
+
+    let mut value = Value::Nil;
+    for form in forms {
+        value = eval_with_env(form, env.clone()).map_err(|e| e.to_string())?;
+    }
+    Ok(value)
+}
+
+/// The contract in a source file, named after the file, with its top-level
+/// forms in the single begin form the compiler takes
+fn read_contract(file: &Path) -> Result<(String, Value), String> {
+    let source = read_source(file)?;
+    let tokens = lexer::lex(&source).map_err(|e| e.to_string())?;
+    let forms = parser::parse_all(&tokens).map_err(|e| e.to_string())?;
+
+    let expr = match forms.as_slice() {
+        [Value::Pair(pair)] if matches!(&pair.0, Value::Symbol(sym) if sym == "begin") => {
+            forms[0].clone()
+        }
+        _ => {
+            let tokens =
+                lexer::lex(&format!("(begin\n{}\n)", source)).map_err(|e| e.to_string())?;
+            parser::parse(&tokens).map_err(|e| e.to_string())?
+        }
+    };
+
+    let name = file.file_stem().map_or("Contract".to_string(), |stem| {
+        stem.to_string_lossy().into_owned()
+    });
+    Ok((name, expr))
+}
+
+/// A single file, or the .lmn files directly inside a directory in name order
+fn test_files(path: &Path) -> std::io::Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(path)? {
+        let file = entry?.path();
+        if file.extension().is_some_and(|ext| ext == "lmn") {
+            files.push(file);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
@@ -1,2 +0,0 @@
-/// Backends for compiling Lamina code to various targets
-pub mod huff;
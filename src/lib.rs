@@ -1,48 +1,8 @@
-// Export the main modules
-pub mod backends;
-pub mod embed;
-pub mod error;
-pub mod evaluator;
-pub mod ffi;
-pub mod lexer;
-pub mod parser;
-pub mod value;
-
-use std::cell::RefCell;
-use std::rc::Rc;
-
-// Global environment setup
-thread_local! {
-    // Initialize with an environment directly
-    pub static GLOBAL_ENV: RefCell<Rc<RefCell<crate::value::Environment>>> = {
-        let env = crate::evaluator::environment::setup_initial_env();
-        RefCell::new(env)
-    };
-}
-
-pub fn execute(code: &str) -> Result<String, String> {
-    // Get the global environment
-    let env = GLOBAL_ENV.with(|global_env| global_env.borrow().clone());
-
-    let tokens = match crate::lexer::lex(code) {
-        Ok(tokens) => tokens,
-        Err(err) => return Err(err.to_string()),
-    };
-
-    let parsed = match crate::parser::parse(&tokens) {
-        Ok(expr) => expr,
-        Err(err) => return Err(err.to_string()),
-    };
-
-    match crate::evaluator::eval_with_env(parsed, env) {
-        Ok(result) => {
-            // Special case: Nil (empty list) should display as an empty string
-            if let crate::value::Value::Nil = result {
-                Ok("".to_string())
-            } else {
-                Ok(result.to_string())
-            }
-        }
-        Err(err) => Err(err.to_string()),
-    }
-}
+// The workspace root
+//
+// The language has one implementation, the `lamina` crate under
+// `crates/lamina`, which the root re-exports whole, so code that depends on
+// the repository gets the same interpreter as lx and the backends do.
+// The backends, the IR and lx are crates of their own under `crates/`.
+
+pub use lamina::*;
@@ -0,0 +1,16 @@
+#[test]
+fn test_root_execute_is_lamina_execute() {
+    for code in [
+        "(+ 1 2)",
+        "(string-append \"a\" \"b\")",
+        "(map (lambda (x) (* x x)) '(1 2 3))",
+        "(type-of 'a)",
+        "(car '())",
+    ] {
+        assert_eq!(lamina_lang::execute(code), lamina::execute(code), "{}", code);
+    }
+
+    // Both run in the same global environment
+    lamina_lang::execute("(define shared 42)").unwrap();
+    assert_eq!(lamina::execute("shared").unwrap(), "42");
+}